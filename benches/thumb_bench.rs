@@ -0,0 +1,89 @@
+//! Thumbnail throughput: one worker against several, on synthetic camera-sized JPEGs.
+//! Run with `cargo bench --bench thumb_bench`; FLASH_VIPS_CONCURRENCY and the worker counts
+//! below are the knobs to compare.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use seen_backend::pipeline::thumb::{render_now, ThumbJob};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Images rendered per iteration
+const BATCH: usize = 16;
+
+/// A 12 MP JPEG with enough detail that decoding and encoding do real work
+fn write_photo(path: &Path, seed: u32) {
+    let img = image::RgbImage::from_fn(4032, 3024, |x, y| {
+        let v = x.wrapping_mul(31).wrapping_add(y.wrapping_mul(17)).wrapping_add(seed) ^ (x * y) >> 7;
+        image::Rgb([v as u8, (v >> 3) as u8, (x ^ y) as u8])
+    });
+    img.save(path).unwrap();
+}
+
+fn jobs(dir: &Path) -> Vec<ThumbJob> {
+    (0..BATCH)
+        .map(|i| {
+            let path = dir.join(format!("{}.jpg", i));
+            write_photo(&path, i as u32);
+            ThumbJob {
+                id: i as i64,
+                path: path.to_string_lossy().into_owned(),
+                sha256_hex: format!("{:02x}{:062}", i, 0),
+                mime: "image/jpeg".into(),
+                force: false,
+                queued_at: std::time::Instant::now(),
+            }
+        })
+        .collect()
+}
+
+/// Render the batch with `workers` threads pulling from a shared list, like the thumbnail workers
+fn render_batch(jobs: &[ThumbJob], derived: &Path, workers: usize) {
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let Some(job) = jobs.get(i) else { break };
+                render_now(job, derived, 256, 1600).unwrap();
+            });
+        }
+    });
+}
+
+fn bench_thumbs(c: &mut Criterion) {
+    #[cfg(not(target_env = "msvc"))]
+    let _app = {
+        let app = libvips::VipsApp::new("thumb_bench", false).unwrap();
+        if let Some(n) = std::env::var("FLASH_VIPS_CONCURRENCY").ok().and_then(|v| v.parse().ok()) {
+            app.concurrency_set(n);
+        }
+        app
+    };
+    let photos = TempDir::new().unwrap();
+    let jobs = jobs(photos.path());
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    let mut group = c.benchmark_group("thumbnails");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(BATCH as u64));
+    let mut workers = vec![1, 2, 4, cores / 2, cores];
+    workers.retain(|n| *n >= 1 && *n <= cores);
+    workers.dedup();
+    for n in workers {
+        group.bench_function(format!("{}_workers", n), |b| {
+            b.iter_batched(
+                || TempDir::new().unwrap(),
+                |derived: TempDir| {
+                    let derived_path: PathBuf = derived.path().to_path_buf();
+                    render_batch(&jobs, &derived_path, n);
+                    derived
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_thumbs);
+criterion_main!(benches);
@@ -82,6 +82,24 @@ if not exist "%FRONTEND_DIR%\src-tauri\binaries\seen-backend-x86_64-pc-windows-m
 echo Backend binary copied successfully to:
 echo   %FRONTEND_DIR%\src-tauri\binaries\seen-backend-x86_64-pc-windows-msvc.exe
 
+REM Step 2b: Bundle static ffmpeg/ffprobe next to the backend sidecar.
+REM The backend looks for ffmpeg.exe/ffprobe.exe beside its own executable before falling back to PATH.
+REM Set FFMPEG_DIR to a folder containing static builds, otherwise the copies on PATH are used.
+echo.
+echo ========================================
+echo Step 2b: Copying ffmpeg/ffprobe to Tauri binaries directory...
+echo ========================================
+call :bundle_tool ffmpeg
+if errorlevel 1 (
+    pause
+    exit /b 1
+)
+call :bundle_tool ffprobe
+if errorlevel 1 (
+    pause
+    exit /b 1
+)
+
 REM Step 3: Build the frontend bundle
 echo.
 echo ========================================
@@ -136,4 +154,26 @@ echo.
 echo Note: The backend is bundled as a sidecar and will start automatically with the Tauri app.
 echo.
 pause
+exit /b 0
+
+REM Copy a tool (ffmpeg or ffprobe) into src-tauri\binaries with the target-triple suffix Tauri expects.
+:bundle_tool
+set "TOOL_SRC="
+if defined FFMPEG_DIR (
+    if exist "%FFMPEG_DIR%\%1.exe" set "TOOL_SRC=%FFMPEG_DIR%\%1.exe"
+)
+if not defined TOOL_SRC (
+    for /f "delims=" %%i in ('where %1 2^>NUL') do if not defined TOOL_SRC set "TOOL_SRC=%%i"
+)
+if not defined TOOL_SRC (
+    echo Error: %1.exe not found. Set FFMPEG_DIR to a folder with static ffmpeg builds or add it to PATH.
+    exit /b 1
+)
+copy /Y "%TOOL_SRC%" "%FRONTEND_DIR%\src-tauri\binaries\%1-x86_64-pc-windows-msvc.exe" >NUL
+if errorlevel 1 (
+    echo Error: Failed to copy %1 from %TOOL_SRC%
+    exit /b 1
+)
+echo   %1: %TOOL_SRC%
+exit /b 0
 
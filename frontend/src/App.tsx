@@ -9,6 +9,8 @@ import AlbumsPage from './pages/Albums';
 import PeoplePage from './pages/People';
 import AssetDetail from './pages/AssetDetail';
 import NotFound from './pages/NotFound';
+import SharedPage from './pages/Shared';
+import LoginDialog from './components/LoginDialog';
 import { useUIStore } from './lib/store';
 
 export default function App() {
@@ -44,11 +46,13 @@ export default function App() {
             <Route path="albums" element={<AlbumsPage />} />
             <Route path="people" element={<PeoplePage />} />
             <Route path="asset/:id" element={<AssetDetail />} />
+            <Route path="share/:token" element={<SharedPage />} />
             <Route path="*" element={<NotFound />} />
           </Route>
         </Routes>
       </main>
       <Footer />
+      <LoginDialog />
     </div>
   );
 }
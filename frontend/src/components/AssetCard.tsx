@@ -9,6 +9,7 @@ import ContextMenu from './ContextMenu';
 import ConfirmDialog from './ConfirmDialog';
 import { saveGalleryScroll } from '../lib/scroll';
 import { useUIStore } from '../lib/store';
+import { useIsReadOnlyPath } from '../lib/hooks';
 import { useQuery, useMutation, useQueryClient } from '@tanstack/react-query';
 import type { InfiniteData } from '@tanstack/react-query';
 import { addAssetsToAlbum, getAlbums, createAlbum, getAlbumsForAsset, removeAssetsFromAlbum, type Album } from '../lib/albums';
@@ -38,6 +39,7 @@ export default function AssetCard({ asset, index, sort, order, filteredAssetIds,
   const image = isImage(asset.mime);
   const isUnsupported = !image && !video;
   const [imageError, setImageError] = useState(false);
+  const [isHovering, setIsHovering] = useState(false);
   const [contextMenu, setContextMenu] = useState<{ x: number; y: number } | null>(null);
   const [albumMenuPosition, setAlbumMenuPosition] = useState<{ x: number; y: number } | null>(null);
   const [showDeleteConfirm, setShowDeleteConfirm] = useState(false);
@@ -56,8 +58,8 @@ export default function AssetCard({ asset, index, sort, order, filteredAssetIds,
 
   // Fetch all persons for the dropdown
   const { data: persons } = useQuery({
-    queryKey: ['persons'],
-    queryFn: () => api.listPersons(),
+    queryKey: ['persons', 'all-by-name'],
+    queryFn: () => api.listPersons({ limit: 1000, sort: 'name' }).then((page) => page.items),
     enabled: showAssignDialog,
   });
 
@@ -240,7 +242,10 @@ export default function AssetCard({ asset, index, sort, order, filteredAssetIds,
   };
 
   const showDeleteConfirmation = useUIStore((s) => s.showDeleteConfirmation);
-  const deleteOriginalFiles = useUIStore((s) => s.deleteOriginalFiles);
+  const deleteOriginalSetting = useUIStore((s) => s.deleteOriginalFiles);
+  const isReadOnly = useIsReadOnlyPath(asset.path);
+  // Files on read-only volumes can only be removed from the index
+  const deleteOriginalFiles = deleteOriginalSetting && !isReadOnly;
 
   const handleDeleteClick = () => {
     setContextMenu(null); // Close context menu
@@ -489,6 +494,8 @@ export default function AssetCard({ asset, index, sort, order, filteredAssetIds,
       } ${selectionMode ? 'cursor-pointer' : ''} ${!selectionMode ? 'hover:scale-[1.02]' : ''}`}
       onClick={handleClick}
       onContextMenu={handleContextMenu}
+      onMouseEnter={video ? () => setIsHovering(true) : undefined}
+      onMouseLeave={video ? () => setIsHovering(false) : undefined}
     >
       <div className="relative aspect-[4/3] bg-zinc-200 dark:bg-zinc-700">
         {/* Selection indicator - only show when CTRL is pressed */}
@@ -562,6 +569,14 @@ export default function AssetCard({ asset, index, sort, order, filteredAssetIds,
               onLoad={(e) => (e.currentTarget.style.opacity = '1')}
               onError={handleImageError}
             />
+            {/* Looping hover preview for videos (falls back to the poster frame) */}
+            {video && isHovering && (
+              <img
+                src={media.animatedThumbUrl(asset.id, asset.sha256)}
+                alt=""
+                className="absolute inset-0 w-full h-full object-cover"
+              />
+            )}
           </>
         )}
         {video && !imageError && (
@@ -3,6 +3,7 @@ import { XMarkIcon, ChevronLeftIcon, ChevronRightIcon, MagnifyingGlassPlusIcon,
 import type { Asset } from '../types';
 import { media, assetApi } from '../lib/api';
 import { isVideo } from '../lib/utils';
+import { useIsReadOnlyPath, useVideoTranscode } from '../lib/hooks';
 
 interface LightboxProps {
   asset: Asset;
@@ -33,6 +34,7 @@ export default function Lightbox({ asset, currentIndex, total, onNavigate, onClo
   
   const [zoom, setZoom] = useState(1);
   const [position, setPosition] = useState({ x: 0, y: 0 });
+  const isReadOnly = useIsReadOnlyPath(asset.path);
   const [rotation, setRotation] = useState(0);
   const [isDragging, setIsDragging] = useState(false);
   const [dragStart, setDragStart] = useState({ x: 0, y: 0 });
@@ -45,6 +47,7 @@ export default function Lightbox({ asset, currentIndex, total, onNavigate, onClo
   const saveButtonTimerRef = useRef<NodeJS.Timeout | null>(null);
   const saveButtonFadeTimerRef = useRef<NodeJS.Timeout | null>(null);
   const isVideoFile = isVideo(asset.mime);
+  const transcode = useVideoTranscode(asset);
 
   // Reset zoom, position, and rotation when asset changes
   useEffect(() => {
@@ -420,7 +423,7 @@ export default function Lightbox({ asset, currentIndex, total, onNavigate, onClo
               </button>
             )}
             {/* Save to disk button - appears for 3 seconds after rotation, then fades out */}
-            {showSaveButton && (
+            {showSaveButton && !isReadOnly && (
               <div className="ml-2">
                 <button
                   onClick={handleSaveOrientation}
@@ -453,7 +456,12 @@ export default function Lightbox({ asset, currentIndex, total, onNavigate, onClo
         onTouchEnd={handleTouchEnd}
         style={{ cursor: zoom > 1 ? (isDragging ? 'grabbing' : 'grab') : 'default' }}
       >
-        {isVideoFile ? (
+        {isVideoFile && transcode.preparing ? (
+          <div className="text-white text-center">
+            <div className="w-12 h-12 mx-auto mb-4 border-4 border-white/20 border-t-white rounded-full animate-spin" />
+            <p className="text-sm opacity-75">Preparing video…</p>
+          </div>
+        ) : isVideoFile ? (
           <video
             ref={videoRef}
             src={videoUrl}
@@ -571,7 +579,7 @@ export default function Lightbox({ asset, currentIndex, total, onNavigate, onClo
           />
         )}
 
-        {isLoading && (
+        {isLoading && !transcode.preparing && (
           <div className="absolute inset-0 flex items-center justify-center">
             <div className="w-12 h-12 border-4 border-white/20 border-t-white rounded-full animate-spin" />
           </div>
@@ -0,0 +1,124 @@
+import { Dialog, Transition } from '@headlessui/react';
+import { LockClosedIcon } from '@heroicons/react/24/outline';
+import { Fragment, useEffect, useState } from 'react';
+import { AUTH_REQUIRED_EVENT, authApi } from '../lib/api';
+
+// Opens whenever the backend refuses a protected action; the user repeats the action once signed in
+export default function LoginDialog() {
+  const [isOpen, setIsOpen] = useState(false);
+  const [username, setUsername] = useState('');
+  const [password, setPassword] = useState('');
+  const [error, setError] = useState<string | null>(null);
+  const [submitting, setSubmitting] = useState(false);
+
+  useEffect(() => {
+    const open = () => {
+      setError(null);
+      setIsOpen(true);
+    };
+    window.addEventListener(AUTH_REQUIRED_EVENT, open);
+    return () => window.removeEventListener(AUTH_REQUIRED_EVENT, open);
+  }, []);
+
+  const close = () => {
+    setIsOpen(false);
+    setUsername('');
+    setPassword('');
+  };
+
+  const submit = async (e: React.FormEvent) => {
+    e.preventDefault();
+    setSubmitting(true);
+    setError(null);
+    try {
+      await authApi.login(password, username.trim() || undefined);
+      close();
+    } catch (err) {
+      setError(err instanceof Error && err.message.includes('HTTP 401') ? 'Incorrect username or password' : 'Sign in failed');
+    } finally {
+      setSubmitting(false);
+    }
+  };
+
+  return (
+    <Transition show={isOpen} as={Fragment}>
+      <Dialog as="div" className="relative z-50" onClose={close}>
+        <Transition.Child
+          as={Fragment}
+          enter="ease-out duration-300"
+          enterFrom="opacity-0"
+          enterTo="opacity-100"
+          leave="ease-in duration-200"
+          leaveFrom="opacity-100"
+          leaveTo="opacity-0"
+        >
+          <div className="fixed inset-0 bg-black/30 dark:bg-black/50" />
+        </Transition.Child>
+
+        <div className="fixed inset-0 overflow-y-auto">
+          <div className="flex min-h-full items-center justify-center p-4">
+            <Transition.Child
+              as={Fragment}
+              enter="ease-out duration-300"
+              enterFrom="opacity-0 scale-95"
+              enterTo="opacity-100 scale-100"
+              leave="ease-in duration-200"
+              leaveFrom="opacity-100 scale-100"
+              leaveTo="opacity-0 scale-95"
+            >
+              <Dialog.Panel className="w-full max-w-sm transform overflow-hidden rounded-lg bg-white dark:bg-zinc-900 border border-zinc-200 dark:border-zinc-800 shadow-xl transition-all">
+                <form className="p-6" onSubmit={submit}>
+                  <div className="flex items-start gap-4">
+                    <LockClosedIcon className="h-6 w-6 flex-shrink-0 text-zinc-500" />
+                    <div className="flex-1">
+                      <Dialog.Title className="text-lg font-semibold text-zinc-900 dark:text-zinc-100 mb-2">
+                        Sign in required
+                      </Dialog.Title>
+                      <p className="text-sm text-zinc-600 dark:text-zinc-400 mb-4">
+                        Sign in with your account, or leave the username empty to use the admin password. Then try again.
+                      </p>
+                      <input
+                        type="text"
+                        autoFocus
+                        autoComplete="username"
+                        value={username}
+                        onChange={(e) => setUsername(e.target.value)}
+                        placeholder="Username (optional)"
+                        className="w-full mb-2 px-3 py-2 text-sm rounded-md border border-zinc-300 dark:border-zinc-700 bg-white dark:bg-zinc-800 text-zinc-900 dark:text-zinc-100"
+                      />
+                      <input
+                        type="password"
+                        autoComplete="current-password"
+                        value={password}
+                        onChange={(e) => setPassword(e.target.value)}
+                        placeholder="Password"
+                        className="w-full px-3 py-2 text-sm rounded-md border border-zinc-300 dark:border-zinc-700 bg-white dark:bg-zinc-800 text-zinc-900 dark:text-zinc-100"
+                      />
+                      {error && <p className="mt-2 text-sm text-red-600 dark:text-red-400">{error}</p>}
+                    </div>
+                  </div>
+                  <div className="mt-6 flex justify-end gap-3">
+                    <button
+                      type="button"
+                      onClick={close}
+                      className="px-4 py-2 text-sm font-medium text-zinc-700 dark:text-zinc-300 bg-zinc-100 dark:bg-zinc-800 hover:bg-zinc-200 dark:hover:bg-zinc-700 rounded-md transition-colors"
+                    >
+                      Cancel
+                    </button>
+                    <button
+                      type="submit"
+                      disabled={submitting || password.length === 0}
+                      className="px-4 py-2 text-sm font-medium text-white rounded-md transition-colors bg-blue-600 hover:bg-blue-700 dark:bg-blue-500 dark:hover:bg-blue-600 disabled:opacity-50"
+                    >
+                      {submitting ? 'Signing in…' : 'Sign in'}
+                    </button>
+                  </div>
+                </form>
+              </Dialog.Panel>
+            </Transition.Child>
+          </div>
+        </div>
+      </Dialog>
+    </Transition>
+  );
+}
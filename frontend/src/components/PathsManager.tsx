@@ -30,6 +30,14 @@ export default function PathsManager() {
     ? (fileBrowserEnv === '1' || String(fileBrowserEnv || '').toLowerCase() === 'true')
     : false;
 
+  // Startup self-check (data dirs, libvips, ffmpeg, ONNX, disk, inotify); only problems are shown
+  const { data: selfCheck } = useQuery({
+    queryKey: ['selfCheck'],
+    queryFn: () => api.selfCheck(),
+    staleTime: 5 * 60 * 1000,
+  });
+  const selfCheckIssues = (selfCheck?.checks ?? []).filter((c) => c.status === 'fail' || c.status === 'warn');
+
   const { data: pathsData, isLoading } = useQuery({
     queryKey: ['scanPaths'],
     queryFn: () => api.getScanPaths(),
@@ -245,6 +253,24 @@ export default function PathsManager() {
   return (
     <section>
       <div className="rounded-lg border border-zinc-200 dark:border-zinc-800 p-3 sm:p-4 bg-white dark:bg-zinc-900">
+        {/* Self-check problems */}
+        {selfCheckIssues.length > 0 && (
+          <div className="mb-3 sm:mb-4 space-y-1">
+            {selfCheckIssues.map((c) => (
+              <div
+                key={c.name}
+                className={`text-xs sm:text-sm rounded-md px-2.5 py-1.5 border ${
+                  c.status === 'fail'
+                    ? 'bg-red-50 dark:bg-red-900/20 border-red-200 dark:border-red-800 text-red-700 dark:text-red-300'
+                    : 'bg-yellow-50 dark:bg-yellow-900/20 border-yellow-200 dark:border-yellow-800 text-yellow-700 dark:text-yellow-300'
+                }`}
+              >
+                <span className="font-medium">{c.name}:</span> {c.message}
+              </div>
+            ))}
+          </div>
+        )}
+
         {/* Add new path */}
         <div className="flex gap-2 mb-3 sm:mb-4 flex-nowrap">
           <input
@@ -310,6 +336,14 @@ export default function PathsManager() {
                         Default
                       </span>
                     )}
+                    {item.read_only && (
+                      <span
+                        className="px-1.5 py-0.5 text-[10px] sm:text-xs font-medium rounded bg-amber-100 dark:bg-amber-900/30 text-amber-700 dark:text-amber-300 flex-shrink-0"
+                        title="This volume is not writable; rotate and delete-from-disk are disabled for its files"
+                      >
+                        Read-only
+                      </span>
+                    )}
                   </div>
                   {item.host_path && (
                     <span className="text-[10px] sm:text-xs text-zinc-500 dark:text-zinc-400 font-mono break-all">
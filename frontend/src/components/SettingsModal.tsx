@@ -77,6 +77,8 @@ export default function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
   const setPrioritizeFolderStructure = useUIStore((s) => s.setPrioritizeFolderStructure);
   const prioritizeFilenameDate = useUIStore((s) => s.prioritizeFilenameDate);
   const setPrioritizeFilenameDate = useUIStore((s) => s.setPrioritizeFilenameDate);
+  const collapseStacks = useUIStore((s) => s.collapseStacks);
+  const setCollapseStacks = useUIStore((s) => s.setCollapseStacks);
   const deleteOriginalFiles = useUIStore((s) => s.deleteOriginalFiles);
   const setDeleteOriginalFiles = useUIStore((s) => s.setDeleteOriginalFiles);
   const smartMergeLevel = useUIStore((s) => s.smartMergeLevel);
@@ -730,6 +732,39 @@ export default function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
                               </p>
                             </div>
                           )}
+
+                          {/* Collapse burst stacks */}
+                          <div className="mt-4">
+                            <label className="text-sm font-medium text-zinc-700 dark:text-zinc-300 mb-3 block">
+                              Burst Stacks
+                            </label>
+                            <div className="flex items-center justify-between p-3 rounded-md border border-zinc-200 dark:border-zinc-700">
+                              <span className="text-sm text-zinc-900 dark:text-zinc-100">
+                                Collapse bursts to one thumbnail
+                              </span>
+                              <label className="relative inline-flex items-center cursor-pointer">
+                                <input
+                                  type="checkbox"
+                                  checked={collapseStacks}
+                                  onChange={(e) => {
+                                    const enabled = e.target.checked;
+                                    setCollapseStacks(enabled);
+                                    if (enabled) {
+                                      // Regroup with the latest assets before collapsing
+                                      api.rebuildStacks()
+                                        .catch((err) => console.error('Failed to rebuild stacks:', err))
+                                        .finally(() => queryClient.invalidateQueries({ queryKey: ['assets'] }));
+                                    }
+                                  }}
+                                  className="sr-only peer"
+                                />
+                                <div className="w-11 h-6 bg-zinc-200 peer-focus:outline-none peer-focus:ring-4 peer-focus:ring-blue-300 dark:peer-focus:ring-blue-800 rounded-full peer dark:bg-zinc-700 peer-checked:after:translate-x-full peer-checked:after:border-white after:content-[''] after:absolute after:top-[2px] after:left-[2px] after:bg-white after:border-zinc-300 after:border after:rounded-full after:h-5 after:w-5 after:transition-all dark:border-zinc-600 peer-checked:bg-blue-600"></div>
+                              </label>
+                            </div>
+                            <p className="mt-2 text-xs text-zinc-500 dark:text-zinc-400">
+                              Shots taken within a couple of seconds by the same camera (or sharing a burst ID) are grouped, and the gallery shows only the cover shot of each group.
+                            </p>
+                          </div>
                         </>
                       )}
 
@@ -3,6 +3,7 @@ import { PlayIcon, PauseIcon, SpeakerWaveIcon, SpeakerXMarkIcon, ArrowsPointingO
 import type { Asset } from '../types';
 import { media } from '../lib/api';
 import { useUIStore } from '../lib/store';
+import { useVideoTranscode } from '../lib/hooks';
 
 interface VideoPlayerProps {
   asset: Asset;
@@ -25,6 +26,7 @@ export default function VideoPlayer({ asset, className = '', onFullscreen, onSta
   const [isMuted, setIsMuted] = useState(false);
   const [showControls, setShowControls] = useState(true);
   const [isLoading, setIsLoading] = useState(true);
+  const transcode = useVideoTranscode(asset);
   const [videoDimensions, setVideoDimensions] = useState<{ width: number; height: number } | null>(null);
   const [containerDimensions, setContainerDimensions] = useState<{ width: number; height: number } | null>(null);
   const controlsTimeoutRef = useRef<ReturnType<typeof setTimeout> | undefined>(undefined);
@@ -315,14 +317,14 @@ export default function VideoPlayer({ asset, className = '', onFullscreen, onSta
         height: 'auto',
       }}
     >
-      {isLoading && (
+      {(isLoading || transcode.preparing) && (
         <div className="absolute inset-0 flex items-center justify-center text-white text-lg z-20">
-          Loading...
+          {transcode.preparing ? 'Preparing video...' : 'Loading...'}
         </div>
       )}
       <video
         ref={videoRef}
-        src={media.videoUrl(asset.id)}
+        src={transcode.preparing ? undefined : media.videoUrl(asset.id)}
         type={(() => {
           // For browser-incompatible formats (like AVI), the backend will transcode to MP4
           // So we should use 'video/mp4' as the type to match what's actually being served
@@ -10,7 +10,14 @@ import type {
   SearchResult,
   DeleteResponse,
   PermanentDeleteResponse,
+  SelfCheckReport,
   BulkPermanentDeleteResponse,
+  TranscodeStatus,
+  PersonSummary,
+  User,
+  AlbumShare,
+  ShareLink,
+  SharedView,
 } from '../types';
 
 function withBase(path: string) {
@@ -18,6 +25,15 @@ function withBase(path: string) {
   return `${API_BASE_URL}${path.startsWith('/') ? '' : '/'}${path}`;
 }
 
+export const AUTH_REQUIRED_EVENT = 'seen:auth-required';
+
+// Let the login dialog know a protected route was refused
+function notifyIfUnauthorized(res: Response) {
+  if (res.status === 401 && typeof window !== 'undefined') {
+    window.dispatchEvent(new CustomEvent(AUTH_REQUIRED_EVENT));
+  }
+}
+
 async function request<T>(path: string, init?: RequestInit): Promise<T> {
   const controller = init?.signal ? null : new AbortController();
   const timeoutId = controller ? setTimeout(() => controller.abort(), 30000) : null;
@@ -33,6 +49,7 @@ async function request<T>(path: string, init?: RequestInit): Promise<T> {
     });
     if (timeoutId) clearTimeout(timeoutId);
     if (!res.ok) {
+      notifyIfUnauthorized(res);
       const text = await res.text().catch(() => '');
       throw new Error(`HTTP ${res.status}: ${res.statusText}${text ? `\n${text}` : ''}`);
     }
@@ -62,6 +79,14 @@ export const api = {
     request<{ faces: Array<{ id: number; asset_id: number; bbox: any; confidence: number }> }>(
       `/faces/unassigned?offset=${offset}&limit=${limit}`
     ),
+  unassignedFaceGroups: (offset: number = 0, limit: number = 20, maxDistance?: number) =>
+    request<{
+      groups: Array<{ size: number; faces: Array<{ id: number; asset_id: number; bbox: any; confidence: number }> }>;
+      total_groups: number;
+      ungrouped_faces: number;
+      window: number;
+      unassigned_total: number;
+    }>(`/faces/unassigned?group=true&offset=${offset}&limit=${limit}${maxDistance !== undefined ? `&max_distance=${maxDistance}` : ''}`),
   assignFaceToPerson: (faceId: number, personId: number | null) =>
     request<{ success: boolean }>(`/faces/${faceId}/assign`, {
       method: 'POST',
@@ -69,9 +94,10 @@ export const api = {
     }),
 
   // Health and stats
-  health: () => request<{ status: string; version: string; database: string; backend_libraries: string[] }>('/health'),
+  health: () => request<{ status: string; version: string; database: string; backend_libraries: string[]; warnings?: string[] }>('/health'),
   stats: async () => normalizeStats(await request<any>('/stats')),
   fileTypes: () => request<FileTypesResponse>('/file-types'),
+  selfCheck: () => request<SelfCheckReport>('/diag/selfcheck'),
   performance: async (): Promise<PerformanceResponse> =>
     normalizePerformance(await request<any>('/performance')),
 
@@ -82,9 +108,10 @@ export const api = {
     sort?: SortField;
     order?: SortOrder;
     person_id?: number;
+    collapse_stacks?: boolean;
   } = {}) => {
     const u = new URL(withBase('/assets'));
-    const { offset = 0, limit = DEFAULT_PAGE_SIZE, sort = 'none', order = 'desc', person_id } = params;
+    const { offset = 0, limit = DEFAULT_PAGE_SIZE, sort = 'none', order = 'desc', person_id, collapse_stacks } = params;
     u.searchParams.set('offset', String(offset));
     u.searchParams.set('limit', String(limit));
     u.searchParams.set('sort', sort);
@@ -92,9 +119,19 @@ export const api = {
     if (person_id !== undefined) {
       u.searchParams.set('person_id', String(person_id));
     }
+    if (collapse_stacks) {
+      u.searchParams.set('collapse_stacks', 'true');
+    }
     return request<Paginated<Asset>>(u.toString());
   },
 
+  // Burst stacks
+  rebuildStacks: (windowSecs?: number) =>
+    request<{ success: boolean; stacks: number; window_secs: number }>('/stacks/rebuild', {
+      method: 'POST',
+      body: JSON.stringify(windowSecs !== undefined ? { window_secs: windowSecs } : {}),
+    }),
+
   search: (params: {
     q: string;
     from?: string;
@@ -122,9 +159,9 @@ export const api = {
     // - some older builds returned { paths: [...] }
     // - some reverse proxies might wrap payloads
     const res = await request<any>('/paths');
-    if (Array.isArray(res)) return res as Array<{ path: string; is_default: boolean; host_path?: string | null }>;
+    if (Array.isArray(res)) return res as Array<{ path: string; is_default: boolean; host_path?: string | null; read_only?: boolean }>;
     if (res && typeof res === 'object' && Array.isArray((res as any).paths)) {
-      return (res as any).paths as Array<{ path: string; is_default: boolean; host_path?: string | null }>;
+      return (res as any).paths as Array<{ path: string; is_default: boolean; host_path?: string | null; read_only?: boolean }>;
     }
     return [];
   },
@@ -206,10 +243,23 @@ export const api = {
     ),
 
   // Persons
-  listPersons: () => request<Array<{ id: number; name: string | null; created_at: number }>>('/persons'),
+  listPersons: (params: { offset?: number; limit?: number; sort?: 'faces' | 'assets' | 'name' | 'created' } = {}) => {
+    const u = new URL(withBase('/persons'));
+    u.searchParams.set('offset', String(params.offset ?? 0));
+    if (params.limit) u.searchParams.set('limit', String(params.limit));
+    if (params.sort) u.searchParams.set('sort', params.sort);
+    return request<Paginated<PersonSummary>>(u.toString());
+  },
   getPerson: (id: number) => request<{ id: number; name: string | null; created_at: number }>(`/persons/${id}`),
   getPersonAssets: (id: number) =>
     request<{ asset_ids: number[] }>(`/persons/${id}/assets`).then((res) => res.asset_ids),
+  getPersonTimeline: (id: number, granularity: 'month' | 'year' = 'month') =>
+    request<{
+      person_id: number;
+      granularity: 'month' | 'year';
+      total: number;
+      buckets: Array<{ year: number; month?: number; count: number; sample_asset_ids: number[] }>;
+    }>(`/persons/${id}/timeline?granularity=${granularity}`),
   getPersonFace: (id: number) =>
     request<{ face_id: number }>(`/persons/${id}/face`).then((res) => res.face_id).catch(() => null),
   updatePerson: (id: number, name: string | null) =>
@@ -268,21 +318,48 @@ export const api = {
     ),
 };
 
+export const authApi = {
+  status: () =>
+    request<{ auth_enabled: boolean; authenticated: boolean; is_admin: boolean; user: User | null }>('/auth/status'),
+  // Omit the username to sign in with the admin password
+  login: (password: string, username?: string) =>
+    request<{ success: boolean }>('/auth/login', { method: 'POST', body: JSON.stringify({ username, password }) }),
+  logout: () => request<{ success: boolean }>('/auth/logout', { method: 'POST' }),
+  listTokens: () =>
+    request<Array<{ id: number; name: string; created_at: number; last_used_at: number | null }>>('/auth/tokens'),
+  createToken: (name: string) =>
+    request<{ id: number; name: string; token: string }>('/auth/tokens', { method: 'POST', body: JSON.stringify({ name }) }),
+  deleteToken: (id: number) => request<{ success: boolean }>(`/auth/tokens/${id}`, { method: 'DELETE' }),
+  listUsers: () => request<User[]>('/users'),
+  createUser: (username: string, password: string, isAdmin = false) =>
+    request<User>('/users', { method: 'POST', body: JSON.stringify({ username, password, is_admin: isAdmin }) }),
+  deleteUser: (id: number) => request<{ success: boolean }>(`/users/${id}`, { method: 'DELETE' }),
+};
+
 export const media = {
   faceThumbUrl: (faceId: number, size: number = 160) =>
     withBase(`/faces/${faceId}/thumb?size=${size}`),
   thumbUrl: (id: number, version?: string) =>
     withBase(version ? `/thumb/${id}?v=${version}` : `/thumb/${id}`),
+  animatedThumbUrl: (id: number, version?: string) =>
+    withBase(version ? `/thumb/${id}?animated=true&v=${version}` : `/thumb/${id}?animated=true`),
   previewUrl: (id: number, version?: string) =>
     withBase(version ? `/preview/${id}?v=${version}` : `/preview/${id}`),
   videoUrl: (id: number) => withBase(`/asset/${id}/video`),
   downloadUrl: (id: number) => withBase(`/asset/${id}/download`),
   audioMp3Url: (id: number) => withBase(`/asset/${id}/audio.mp3`),
+  sharedMediaUrl: (token: string, kind: 'thumb' | 'preview' | 'download', assetId: number, key?: string | null) =>
+    withBase(`/share/${encodeURIComponent(token)}/${kind}/${assetId}${key ? `?key=${encodeURIComponent(key)}` : ''}`),
 };
 
 export const assetApi = {
   get: (id: number) => request<Asset>(`/asset/${id}`),
 
+  transcodeStatus: (id: number) => request<TranscodeStatus>(`/assets/${id}/transcode`),
+
+  requestTranscode: (id: number) =>
+    request<TranscodeStatus>(`/assets/${id}/transcode`, { method: 'POST' }),
+
   download: async (id: number) => {
     const url = media.downloadUrl(id);
     const response = await fetch(url);
@@ -307,6 +384,7 @@ export const assetApi = {
     const permanent = options?.permanent === true;
     const url = withBase(permanent ? `/asset/${id}/permanent` : `/asset/${id}`);
     const response = await fetch(url, { method: 'DELETE' });
+    notifyIfUnauthorized(response);
     const text = await response.text().catch(() => '');
     let data: DeleteResponse | PermanentDeleteResponse = { success: response.ok };
     if (text) {
@@ -335,6 +413,7 @@ export const assetApi = {
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify({ ids }),
     });
+    notifyIfUnauthorized(response);
       const text = await response.text().catch(() => '');
     let parsed: any = null;
     if (text) {
@@ -406,6 +485,7 @@ export const assetApi = {
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify({ rotation }),
     });
+    notifyIfUnauthorized(response);
     const text = await response.text().catch(() => '');
     let data: { success: boolean; error?: string } = { success: response.ok };
     if (text) {
@@ -440,6 +520,47 @@ export const assetApi = {
     updated_at: number;
   }>(`/albums/${id}`),
 
+  getAlbumStats: (id: number, top?: number) => request<{
+    asset_count: number;
+    total_bytes: number;
+    date_from: number | null;
+    date_to: number | null;
+    top_people: Array<{ person_id: number; name: string | null; asset_count: number; face_id: number }>;
+    places: Array<{ name: string; dirname: string; asset_count: number }>;
+  }>(`/albums/${id}/stats${top ? `?top=${top}` : ''}`),
+
+  getAlbumPeople: (id: number) =>
+    request<Array<{ person_id: number; name: string | null; asset_count: number; face_id: number }>>(`/albums/${id}/people`),
+
+  getAlbumShares: (id: number) => request<AlbumShare[]>(`/albums/${id}/shares`),
+
+  shareAlbum: (id: number, userId: number) =>
+    request<AlbumShare[]>(`/albums/${id}/shares`, { method: 'POST', body: JSON.stringify({ user_id: userId }) }),
+
+  unshareAlbum: (id: number, userId: number) =>
+    request<AlbumShare[]>(`/albums/${id}/shares/${userId}`, { method: 'DELETE' }),
+
+  // Public share links
+  createShareLink: (params: { album_id?: number; asset_id?: number; password?: string; expires_in_secs?: number }) =>
+    request<{ id: number; token: string; url: string; expires_at: number | null }>('/shares', {
+      method: 'POST',
+      body: JSON.stringify(params),
+    }),
+
+  listShareLinks: () => request<ShareLink[]>('/shares'),
+
+  deleteShareLink: (id: number) => request<{ success: boolean }>(`/shares/${id}`, { method: 'DELETE' }),
+
+  getSharedView: (token: string, access: { password?: string; key?: string } = {}) => {
+    const u = new URL(withBase(`/share/${encodeURIComponent(token)}`));
+    // Passwords go in the body so they never show up in URLs
+    if (access.password) {
+      return request<SharedView>(u.toString(), { method: 'POST', body: JSON.stringify({ password: access.password }) });
+    }
+    if (access.key) u.searchParams.set('key', access.key);
+    return request<SharedView>(u.toString());
+  },
+
   createAlbum: (name: string, description?: string) =>
     request<{
       id: number;
@@ -497,6 +618,12 @@ export const assetApi = {
       body: JSON.stringify({ asset_ids: assetIds }),
     }),
 
+  batchUpdateAlbumAssets: (albumIds: number[], changes: { add?: number[]; remove?: number[] }) =>
+    request<{ success: boolean; album_ids: number[]; added: number; removed: number }>('/albums/batch', {
+      method: 'POST',
+      body: JSON.stringify({ album_ids: albumIds, add: changes.add ?? [], remove: changes.remove ?? [] }),
+    }),
+
   getAlbumsForAsset: (assetId: number) =>
     request<number[]>(`/albums/for-asset/${assetId}`),
 };
@@ -5,9 +5,9 @@ import {
   useQuery,
 } from '@tanstack/react-query';
 import { useEffect, useState } from 'react';
-import { api } from './api';
+import { api, assetApi } from './api';
 import { DEFAULT_PAGE_SIZE, STATS_POLL_MS } from './config';
-import type { Asset, Paginated, Stats, SearchResult } from '../types';
+import type { Asset, Paginated, Stats, SearchResult, TranscodeStatus } from '../types';
 
 // Configure QueryClient with retry logic and better error handling
 export const queryClient = new QueryClient({
@@ -79,6 +79,7 @@ export function useAssetsInfinite(params: {
   order?: 'asc' | 'desc';
   pageSize?: number;
   person_id?: number;
+  collapse_stacks?: boolean;
   enabled?: boolean;
 }) {
   const isVisible = usePageVisibility();
@@ -102,6 +103,7 @@ export function useAssetsInfinite(params: {
         sort: queryParams.sort,
         order: queryParams.order,
         person_id: queryParams.person_id,
+        collapse_stacks: queryParams.collapse_stacks,
       }),
     getNextPageParam: (lastPage, allPages) => {
       const loaded = allPages.reduce((acc, p) => acc + p.items.length, 0);
@@ -170,3 +172,38 @@ export function useSearchInfinite(params: {
   });
 }
 
+// True when the asset lives under a scan path whose volume was detected as read-only
+export function useIsReadOnlyPath(assetPath?: string | null) {
+  const { data } = useQuery({
+    queryKey: ['scanPaths'],
+    queryFn: () => api.getScanPaths(),
+    staleTime: 60_000,
+  });
+  if (!assetPath || !Array.isArray(data)) return false;
+  const normalized = assetPath.replace(/\\/g, '/');
+  return data.some((p) => {
+    if (!p.read_only) return false;
+    const root = p.path.replace(/\\/g, '/').replace(/\/+$/, '');
+    return normalized === root || normalized.startsWith(`${root}/`);
+  });
+}
+
+// Queue a background transcode for videos the browser can't play and poll until it finishes.
+// Anything other than queued/running lets the player load the stream as usual.
+export function useVideoTranscode(asset?: Pick<Asset, 'id' | 'mime'> | null) {
+  const enabled = !!asset && asset.mime.startsWith('video/');
+  const { data } = useQuery<TranscodeStatus>({
+    queryKey: ['transcode', asset?.id],
+    queryFn: () => assetApi.requestTranscode(asset!.id),
+    enabled,
+    retry: false,
+    refetchOnWindowFocus: false,
+    refetchInterval: (query) => {
+      const status = query.state.data?.status;
+      return status === 'queued' || status === 'running' ? 2000 : false;
+    },
+  });
+  const current = data && asset && data.asset_id === asset.id ? data : undefined;
+  const preparing = current?.status === 'queued' || current?.status === 'running';
+  return { preparing, status: current?.status, error: current?.error };
+}
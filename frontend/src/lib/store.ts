@@ -32,6 +32,8 @@ interface UIState {
   setPrioritizeFolderStructure: (value: boolean) => void;
   prioritizeFilenameDate: boolean;
   setPrioritizeFilenameDate: (value: boolean) => void;
+  collapseStacks: boolean;
+  setCollapseStacks: (value: boolean) => void;
   deleteOriginalFiles: boolean;
   setDeleteOriginalFiles: (value: boolean) => void;
   smartMergeLevel: number; // 1-5, where 3 is default (middle)
@@ -59,6 +61,7 @@ const ALBUM_HEADING_FONT_SIZE_KEY = 'seen.albumHeadingFontSize';
 const PLAYBACK_SPEED_KEY = 'seen.playbackSpeed';
 const PRIORITIZE_FOLDER_STRUCTURE_KEY = 'seen.prioritizeFolderStructure';
 const PRIORITIZE_FILENAME_DATE_KEY = 'seen.prioritizeFilenameDate';
+const COLLAPSE_STACKS_KEY = 'seen.collapseStacks';
 const DELETE_ORIGINALS_KEY = 'seen.deleteOriginalFiles';
 const SMART_MERGE_MODE_KEY = 'seen.smartMergeMode';
 const SHOW_ALBUM_TAGS_KEY = 'seen.showAlbumTags';
@@ -224,6 +227,11 @@ export const useUIStore = create<UIState>((set, get) => {
       localStorage.setItem(PRIORITIZE_FILENAME_DATE_KEY, String(value));
       set({ prioritizeFilenameDate: value });
     },
+    collapseStacks: localStorage.getItem(COLLAPSE_STACKS_KEY) === 'true',
+    setCollapseStacks: (value) => {
+      localStorage.setItem(COLLAPSE_STACKS_KEY, String(value));
+      set({ collapseStacks: value });
+    },
     deleteOriginalFiles: localStorage.getItem(DELETE_ORIGINALS_KEY) === 'true',
     setDeleteOriginalFiles: (value) => {
       localStorage.setItem(DELETE_ORIGINALS_KEY, String(value));
@@ -1,6 +1,6 @@
 import { useState, useMemo, useEffect, Fragment } from 'react';
 import { Link } from 'react-router-dom';
-import { PlusIcon, PencilIcon, TrashIcon, ChevronRightIcon, ChevronDownIcon, FolderIcon } from '@heroicons/react/24/outline';
+import { PlusIcon, PencilIcon, TrashIcon, ChevronRightIcon, ChevronDownIcon, FolderIcon, LinkIcon } from '@heroicons/react/24/outline';
 import {
   getAlbums,
   createAlbum,
@@ -12,6 +12,7 @@ import { useAssetsInfinite } from '../lib/hooks';
 import GalleryGrid from '../components/GalleryGrid';
 import { useAdaptivePageSize } from '../lib/adaptiveLoading';
 import { useUIStore, type FontFamily } from '../lib/store';
+import { api } from '../lib/api';
 
 const ALBUMS_EXPANDED_KEY = 'nazr.albums.expanded';
 
@@ -215,6 +216,24 @@ export default function AlbumsPage() {
     }
   };
 
+  const handleShareLink = async (album: Album) => {
+    const password = prompt(`Create a public link to "${album.name}".\nOptional password (leave empty for none):`);
+    if (password === null) return;
+    try {
+      const share = await api.createShareLink({ album_id: parseInt(album.id, 10), password: password || undefined });
+      const url = `${window.location.origin}${share.url}`;
+      try {
+        await navigator.clipboard.writeText(url);
+        alert(`Link copied to clipboard:\n${url}\n\nAnyone with this link can view and download the album.`);
+      } catch {
+        prompt('Copy this link. Anyone with it can view and download the album:', url);
+      }
+    } catch (error) {
+      console.error('Failed to create share link:', error);
+      alert('Failed to create share link. Please try again.');
+    }
+  };
+
   const toggleAlbumExpanded = (albumId: string, event?: React.MouseEvent) => {
     if (event) {
       event.preventDefault();
@@ -348,6 +367,13 @@ export default function AlbumsPage() {
                               {album.assetIds.length} {album.assetIds.length === 1 ? 'item' : 'items'}
                             </span>
                             <div className="flex gap-1" onClick={(e) => e.stopPropagation()}>
+                              <button
+                                onClick={() => handleShareLink(album)}
+                                className="p-1 rounded hover:bg-zinc-200 dark:hover:bg-zinc-700 transition-colors"
+                                title="Create share link"
+                              >
+                                <LinkIcon className="w-4 h-4" />
+                              </button>
                               <button
                                 onClick={() => handleEdit(album)}
                                 className="p-1 rounded hover:bg-zinc-200 dark:hover:bg-zinc-700 transition-colors"
@@ -3,7 +3,7 @@ import { useLocation, useNavigate, useParams } from 'react-router-dom';
 import { useQuery } from '@tanstack/react-query';
 import { ChevronLeftIcon, ChevronRightIcon, ArrowsPointingOutIcon } from '@heroicons/react/24/outline';
 import { media, assetApi } from '../lib/api';
-import { useAssetsInfinite, useSearchInfinite } from '../lib/hooks';
+import { useAssetsInfinite, useSearchInfinite, useIsReadOnlyPath } from '../lib/hooks';
 import { isVideo } from '../lib/utils';
 import type { Asset } from '../types';
 import Lightbox from '../components/Lightbox';
@@ -462,7 +462,10 @@ export default function AssetDetail() {
   }, [navigationAssets, deletedAssetIds, nav, sort, order, fromLocation, state?.filteredAssetIds]);
 
   const showDeleteConfirmation = useUIStore((s) => s.showDeleteConfirmation);
-  const deleteOriginalFiles = useUIStore((s) => s.deleteOriginalFiles);
+  const deleteOriginalSetting = useUIStore((s) => s.deleteOriginalFiles);
+  const isDeleteTargetReadOnly = useIsReadOnlyPath((assetToDelete ?? currentAsset)?.path);
+  // Files on read-only volumes can only be removed from the index
+  const deleteOriginalFiles = deleteOriginalSetting && !isDeleteTargetReadOnly;
 
   const handleDeleteConfirm = useCallback(async (assetId?: number) => {
     const idToDelete = assetId || assetToDelete?.id || (currentAsset?.id);
@@ -233,6 +233,7 @@ export default function Gallery() {
   }, [expandedYears]);
   const prioritizeFolderStructure = useUIStore((s) => s.prioritizeFolderStructure);
   const prioritizeFilenameDate = useUIStore((s) => s.prioritizeFilenameDate);
+  const collapseStacks = useUIStore((s) => s.collapseStacks);
   
   // When organization settings are enabled and sort is 'none', use 'mtime' for backend query
   // This ensures backend returns data roughly sorted by date, which frontend will then refine
@@ -322,6 +323,7 @@ export default function Gallery() {
     sort: effectiveSort, 
     order,
     person_id: personId || undefined,
+    collapse_stacks: collapseStacks || undefined,
     pageSize: adaptivePageSize,
   });
   
@@ -1,5 +1,5 @@
 import { useState, useEffect, useMemo } from 'react';
-import { useQuery, useMutation, useQueryClient, useQueries } from '@tanstack/react-query';
+import { useQuery, useInfiniteQuery, useMutation, useQueryClient } from '@tanstack/react-query';
 import { api, media } from '../lib/api';
 import { Link } from 'react-router-dom';
 import { PencilIcon, TrashIcon, PlayIcon, XMarkIcon, Cog6ToothIcon, StopIcon, SparklesIcon, BarsArrowDownIcon } from '@heroicons/react/24/outline';
@@ -8,15 +8,13 @@ import { Loading } from '../components/Loading';
 import ErrorView from '../components/ErrorView';
 import { useUIStore } from '../lib/store';
 import { usePageVisibility } from '../lib/hooks';
+import type { PersonSummary } from '../types';
 
-interface Person {
-  id: number;
-  name: string | null;
-  created_at: number;
-}
+type Person = PersonSummary;
 
 const PERSON_PLACEHOLDER_REGEX = /^person\s+\d+$/i;
 const PEOPLE_SORT_KEY = 'nazr.people.sortBy';
+const PEOPLE_PAGE_SIZE = 200;
 
 const isPlaceholderName = (person: Person) => {
   const rawName = person.name?.trim();
@@ -122,6 +120,9 @@ export default function PeoplePage() {
   });
   const [faceContextMenu, setFaceContextMenu] = useState<{ faceId: number; x: number; y: number } | null>(null);
   const [assignFaceId, setAssignFaceId] = useState<number | null>(null);
+  // Faces assigned together when a whole review group is confirmed; empty for a single face
+  const [assignGroupIds, setAssignGroupIds] = useState<number[]>([]);
+  const [groupUnassigned, setGroupUnassigned] = useState(true);
   const [selectedAssignPersonId, setSelectedAssignPersonId] = useState<number | null>(null);
   const [mergeStatus, setMergeStatus] = useState<string | null>(null);
   const smartMergeLevel = useUIStore((s) => s.smartMergeLevel);
@@ -165,56 +166,42 @@ export default function PeoplePage() {
     return () => clearTimeout(timer);
   }, [mergeStatus]);
 
-  const { data: persons, isLoading, error } = useQuery({
-    queryKey: ['persons'],
-    queryFn: () => api.listPersons(),
+  // The server sorts and pages; counts and cover faces come with each person
+  const {
+    data: personPages,
+    isLoading,
+    error,
+    hasNextPage,
+    fetchNextPage,
+    isFetchingNextPage,
+  } = useInfiniteQuery({
+    queryKey: ['persons', sortBy],
+    initialPageParam: 0,
+    queryFn: ({ pageParam }) =>
+      api.listPersons({ offset: pageParam as number, limit: PEOPLE_PAGE_SIZE, sort: sortBy === 'photos' ? 'assets' : 'name' }),
+    getNextPageParam: (lastPage, allPages) => {
+      const loaded = allPages.reduce((acc, p) => acc + p.items.length, 0);
+      return loaded < lastPage.total ? loaded : undefined;
+    },
     enabled: isPageVisible,
     refetchInterval: (query) => (!isPageVisible || query.state.error) ? false : 30000,
     retry: false,
   });
 
-  // Fetch asset counts for all persons when sorting by photos
-  const assetCountQueries = useQueries({
-    queries: persons && sortBy === 'photos' ? persons.map((person) => ({
-      queryKey: ['personAssets', person.id],
-      queryFn: () => api.getPersonAssets(person.id),
-      staleTime: 30000, // Cache for 30 seconds
-    })) : [],
-  });
+  const persons = useMemo(() => personPages?.pages.flatMap((page) => page.items), [personPages]);
+  const totalPersons = personPages?.pages[0]?.total ?? 0;
 
-  // Create a map of person ID to asset count
-  const assetCountMap = useMemo(() => {
-    if (!persons || sortBy !== 'photos') return new Map<number, number>();
-    const map = new Map<number, number>();
-    persons.forEach((person, index) => {
-      const assets = assetCountQueries[index]?.data;
-      map.set(person.id, assets?.length || 0);
-    });
-    return map;
-  }, [persons, assetCountQueries, sortBy]);
-
-  // Sort persons based on selected sort option
-  const sortedPersons = useMemo(() => {
-    if (!persons) return [];
-    const sorted = [...persons];
-
-    if (sortBy === 'name') {
-      sorted.sort(comparePersonsByName);
-    } else if (sortBy === 'photos') {
-      sorted.sort((a, b) => {
-        const countA = assetCountMap.get(a.id) || 0;
-        const countB = assetCountMap.get(b.id) || 0;
-        // Sort descending (most photos first)
-        return countB - countA;
-      });
-    }
-    return sorted;
-  }, [persons, sortBy, assetCountMap]);
+  // The assign dialog needs every person, not just the loaded pages
+  const { data: allPersonsByName } = useQuery({
+    queryKey: ['persons', 'all-by-name'],
+    queryFn: () => api.listPersons({ limit: 1000, sort: 'name' }).then((page) => page.items),
+    enabled: assignFaceId !== null,
+  });
 
   const dropdownPersons = useMemo(() => {
-    if (!persons) return [];
-    return [...persons].sort(comparePersonsByName);
-  }, [persons]);
+    if (!allPersonsByName) return [];
+    return [...allPersonsByName].sort(comparePersonsByName);
+  }, [allPersonsByName]);
 
   const { data: faceStatus, error: faceStatusError } = useQuery({
     queryKey: ['faceDetectionStatus'],
@@ -236,7 +223,16 @@ export default function PeoplePage() {
     queryKey: ['unassignedFaces'],
     queryFn: () => api.unassignedFaces(0, 60),
     refetchInterval: (query) => (!isPageVisible || query.state.error) ? false : 15000,
-    enabled: isPageVisible,
+    enabled: isPageVisible && !groupUnassigned,
+    retry: false,
+  });
+
+  // Review queue: batches of similar unassigned faces
+  const { data: unassignedGroups } = useQuery({
+    queryKey: ['unassignedFaces', 'groups'],
+    queryFn: () => api.unassignedFaceGroups(0, 20),
+    refetchInterval: (query) => (!isPageVisible || query.state.error) ? false : 30000,
+    enabled: isPageVisible && groupUnassigned,
     retry: false,
   });
 
@@ -266,8 +262,7 @@ export default function PeoplePage() {
   const clearFacialDataMutation = useMutation({
     mutationFn: () => api.clearFacialData(),
     onSuccess: () => {
-      queryClient.setQueryData(['persons'], []);
-      queryClient.invalidateQueries({ queryKey: ['persons'] });
+      queryClient.resetQueries({ queryKey: ['persons'] });
       queryClient.invalidateQueries({ queryKey: ['faceProgress'] });
       queryClient.invalidateQueries({ queryKey: ['unassignedFaces'] });
       queryClient.removeQueries({ queryKey: ['personAssets'] });
@@ -300,7 +295,7 @@ export default function PeoplePage() {
       const sourceLabel =
         mergeSource?.name || (mergeSource ? `Person ${mergeSource.id}` : `Person ${variables.sourceId}`);
       const target =
-        persons?.find((p) => p.id === variables.targetId) || mergeTarget || { id: variables.targetId, name: null, created_at: 0 };
+        persons?.find((p) => p.id === variables.targetId) || mergeTarget || { id: variables.targetId, name: null, created_at: 0, face_count: 0, asset_count: 0, cover_face_id: null };
       const targetLabel = target.name || `Person ${target.id}`;
       const mergedCount = data?.faces_merged ?? 0;
       const profileCount = data?.profile_refreshed?.face_count;
@@ -324,12 +319,14 @@ export default function PeoplePage() {
   });
 
   const assignFaceMutation = useMutation({
-    mutationFn: ({ faceId, personId }: { faceId: number; personId: number }) => api.assignFaceToPerson(faceId, personId),
+    mutationFn: ({ faceIds, personId }: { faceIds: number[]; personId: number }) =>
+      Promise.all(faceIds.map((faceId) => api.assignFaceToPerson(faceId, personId))),
     onSuccess: () => {
       queryClient.invalidateQueries({ queryKey: ['unassignedFaces'] });
       queryClient.invalidateQueries({ queryKey: ['persons'] });
       queryClient.invalidateQueries({ queryKey: ['faceProgress'] });
       setAssignFaceId(null);
+      setAssignGroupIds([]);
       setSelectedAssignPersonId(null);
     },
     onError: (error) => {
@@ -642,24 +639,13 @@ export default function PeoplePage() {
       return;
     }
 
-    // Fetch asset counts and face IDs for both persons
-    try {
-      const [sourceAssets, targetAssets, sourceFaceId, targetFaceId] = await Promise.all([
-        api.getPersonAssets(sourcePerson.id),
-        api.getPersonAssets(targetPerson.id),
-        api.getPersonFace(sourcePerson.id),
-        api.getPersonFace(targetPerson.id),
-      ]);
-      setMergeSourceCount(sourceAssets.length);
-      setMergeTargetCount(targetAssets.length);
-      setMergeSourceFaceId(sourceFaceId);
-      setMergeTargetFaceId(targetFaceId);
-      setMergeSource(sourcePerson);
-      setMergeTarget(targetPerson);
-      setMergeDialogOpen(true);
-    } catch (error) {
-      console.error('Error fetching person data:', error);
-    }
+    setMergeSourceCount(sourcePerson.asset_count);
+    setMergeTargetCount(targetPerson.asset_count);
+    setMergeSourceFaceId(sourcePerson.cover_face_id);
+    setMergeTargetFaceId(targetPerson.cover_face_id);
+    setMergeSource(sourcePerson);
+    setMergeTarget(targetPerson);
+    setMergeDialogOpen(true);
 
     setDraggedPersonId(null);
     setDropTargetId(null);
@@ -673,12 +659,14 @@ export default function PeoplePage() {
 
   const closeAssignDialog = () => {
     setAssignFaceId(null);
+    setAssignGroupIds([]);
     setSelectedAssignPersonId(null);
   };
 
   const handleAssignFaceConfirm = () => {
     if (assignFaceId !== null && selectedAssignPersonId !== null) {
-      assignFaceMutation.mutate({ faceId: assignFaceId, personId: selectedAssignPersonId });
+      const faceIds = assignGroupIds.length > 0 ? assignGroupIds : [assignFaceId];
+      assignFaceMutation.mutate({ faceIds, personId: selectedAssignPersonId });
     }
   };
 
@@ -870,9 +858,10 @@ export default function PeoplePage() {
         </div>
       )}
 
-      {sortedPersons && sortedPersons.length > 0 ? (
+      {persons && persons.length > 0 ? (
+        <>
         <div className="grid grid-cols-1 sm:grid-cols-2 lg:grid-cols-3 xl:grid-cols-4 gap-3 sm:gap-4">
-          {sortedPersons.map((person) => (
+          {persons.map((person) => (
             <PersonCard
               key={person.id}
               person={person}
@@ -892,6 +881,21 @@ export default function PeoplePage() {
             />
           ))}
         </div>
+        {hasNextPage && (
+          <div className="flex flex-col items-center gap-2 mt-6">
+            <p className="text-xs text-zinc-500 dark:text-zinc-400">
+              Showing {persons.length} of {totalPersons} people
+            </p>
+            <button
+              onClick={() => fetchNextPage()}
+              disabled={isFetchingNextPage}
+              className="px-4 py-2 text-sm font-medium text-zinc-700 dark:text-zinc-300 bg-zinc-100 dark:bg-zinc-800 hover:bg-zinc-200 dark:hover:bg-zinc-700 rounded-md transition-colors disabled:opacity-50"
+            >
+              {isFetchingNextPage ? 'Loading…' : 'Load more'}
+            </button>
+          </div>
+        )}
+        </>
       ) : (
         <div className="text-center py-12 text-zinc-500 dark:text-zinc-400">
           <p className="text-lg mb-2">No people detected yet</p>
@@ -899,9 +903,74 @@ export default function PeoplePage() {
         </div>
       )}
 
-      {unassigned?.faces && unassigned.faces.length > 0 && (
+      {groupUnassigned && unassignedGroups && (unassignedGroups.groups.length > 0 || unassignedGroups.unassigned_total > 0) && (
         <div className="space-y-3">
-          <h2 className="text-lg font-semibold">Unassigned Faces</h2>
+          <div className="flex items-center justify-between gap-3">
+            <h2 className="text-lg font-semibold">Unassigned Faces</h2>
+            <button
+              onClick={() => setGroupUnassigned(false)}
+              className="text-sm text-blue-600 dark:text-blue-400 hover:underline"
+            >
+              Show all faces
+            </button>
+          </div>
+          <p className="text-sm text-zinc-600 dark:text-zinc-400">
+            {unassignedGroups.total_groups} {unassignedGroups.total_groups === 1 ? 'group' : 'groups'} of similar faces
+            among the {unassignedGroups.window} newest of {unassignedGroups.unassigned_total} unassigned.
+            Are these the same person?
+          </p>
+          {unassignedGroups.groups.map((group) => (
+            <div
+              key={group.faces[0].id}
+              className="flex items-center gap-3 p-2 rounded-lg border border-zinc-200 dark:border-zinc-800"
+            >
+              <div className="flex-1 min-w-0 flex gap-1.5 overflow-x-auto">
+                {group.faces.slice(0, 12).map((f) => (
+                  <img
+                    key={f.id}
+                    src={media.faceThumbUrl(f.id, 96)}
+                    alt={`Face ${f.id}`}
+                    className="w-14 h-14 flex-shrink-0 rounded border border-zinc-200 dark:border-zinc-800 bg-zinc-100 dark:bg-zinc-900 cursor-context-menu"
+                    onContextMenu={(e) => {
+                      e.preventDefault();
+                      e.stopPropagation();
+                      setFaceContextMenu({ faceId: f.id, x: e.clientX, y: e.clientY });
+                    }}
+                    loading="lazy"
+                  />
+                ))}
+                {group.size > 12 && (
+                  <div className="w-14 h-14 flex-shrink-0 flex items-center justify-center rounded bg-zinc-100 dark:bg-zinc-800 text-xs text-zinc-600 dark:text-zinc-400">
+                    +{group.size - 12}
+                  </div>
+                )}
+              </div>
+              <button
+                onClick={() => {
+                  setAssignGroupIds(group.faces.map((f) => f.id));
+                  setAssignFaceId(group.faces[0].id);
+                  setSelectedAssignPersonId(null);
+                }}
+                className="flex-shrink-0 px-3 py-1.5 text-sm font-medium text-white bg-blue-600 hover:bg-blue-700 rounded-md"
+              >
+                Assign {group.size}
+              </button>
+            </div>
+          ))}
+        </div>
+      )}
+
+      {!groupUnassigned && unassigned?.faces && unassigned.faces.length > 0 && (
+        <div className="space-y-3">
+          <div className="flex items-center justify-between gap-3">
+            <h2 className="text-lg font-semibold">Unassigned Faces</h2>
+            <button
+              onClick={() => setGroupUnassigned(true)}
+              className="text-sm text-blue-600 dark:text-blue-400 hover:underline"
+            >
+              Group similar faces
+            </button>
+          </div>
           <div className="grid grid-cols-4 sm:grid-cols-5 md:grid-cols-6 lg:grid-cols-8 xl:grid-cols-10 gap-1.5 sm:gap-2">
             {unassigned.faces.map((f: any) => (
               <img
@@ -931,6 +1000,7 @@ export default function PeoplePage() {
             onClick={(e) => {
               e.stopPropagation();
               setAssignFaceId(faceContextMenu.faceId);
+              setAssignGroupIds([]);
               setSelectedAssignPersonId(null);
               setFaceContextMenu(null);
             }}
@@ -954,7 +1024,9 @@ export default function PeoplePage() {
               />
             </div>
             <p className="text-sm text-zinc-600 dark:text-zinc-400">
-              Choose an existing person to link this face. The face will no longer appear in the Unassigned list.
+              {assignGroupIds.length > 1
+                ? `Choose an existing person to link these ${assignGroupIds.length} faces. They will no longer appear in the Unassigned list.`
+                : 'Choose an existing person to link this face. The face will no longer appear in the Unassigned list.'}
             </p>
             <div>
               <label className="block text-sm font-medium text-zinc-700 dark:text-zinc-200 mb-2">Select Person</label>
@@ -1491,18 +1563,8 @@ function PersonCard({
   onDragLeave: () => void;
   onDrop: (e: React.DragEvent) => void;
 }) {
-  const { data: assets } = useQuery({
-    queryKey: ['personAssets', person.id],
-    queryFn: () => api.getPersonAssets(person.id),
-  });
-
-  const { data: faceId } = useQuery({
-    queryKey: ['personFace', person.id],
-    queryFn: () => api.getPersonFace(person.id),
-    enabled: !isEditing,
-  });
-
-  const assetCount = assets?.length || 0;
+  const faceId = person.cover_face_id;
+  const assetCount = person.asset_count;
   const displayName = person.name || `Person ${person.id}`;
 
   return (
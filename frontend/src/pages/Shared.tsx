@@ -0,0 +1,104 @@
+import { useState } from 'react';
+import { useParams } from 'react-router-dom';
+import { useQuery } from '@tanstack/react-query';
+import { ArrowDownTrayIcon, LockClosedIcon } from '@heroicons/react/24/outline';
+import { api, media } from '../lib/api';
+import { Loading } from '../components/Loading';
+
+// Read-only view of a public share link; works without signing in
+export default function SharedPage() {
+  const { token = '' } = useParams();
+  const [password, setPassword] = useState('');
+  const [submittedPassword, setSubmittedPassword] = useState<string | undefined>(undefined);
+
+  const { data, isLoading, error } = useQuery({
+    queryKey: ['sharedView', token, submittedPassword],
+    queryFn: () => api.getSharedView(token, { password: submittedPassword }),
+    retry: false,
+  });
+
+  if (isLoading) return <Loading />;
+
+  const message = error instanceof Error ? error.message : '';
+  if (message.includes('HTTP 403')) {
+    return (
+      <div className="container-responsive py-16 flex justify-center">
+        <form
+          className="w-full max-w-sm space-y-3"
+          onSubmit={(e) => {
+            e.preventDefault();
+            setSubmittedPassword(password);
+          }}
+        >
+          <div className="flex items-center gap-2 text-lg font-semibold text-zinc-900 dark:text-zinc-100">
+            <LockClosedIcon className="h-5 w-5 text-zinc-500" />
+            This link is password protected
+          </div>
+          <input
+            type="password"
+            autoFocus
+            value={password}
+            onChange={(e) => setPassword(e.target.value)}
+            placeholder="Password"
+            className="w-full px-3 py-2 text-sm rounded-md border border-zinc-300 dark:border-zinc-700 bg-white dark:bg-zinc-800 text-zinc-900 dark:text-zinc-100"
+          />
+          {submittedPassword !== undefined && <p className="text-sm text-red-600 dark:text-red-400">Incorrect password</p>}
+          <button
+            type="submit"
+            disabled={password.length === 0}
+            className="px-4 py-2 text-sm font-medium text-white rounded-md bg-blue-600 hover:bg-blue-700 dark:bg-blue-500 dark:hover:bg-blue-600 disabled:opacity-50"
+          >
+            Open
+          </button>
+        </form>
+      </div>
+    );
+  }
+
+  if (error || !data) {
+    return (
+      <div className="container-responsive py-16 text-center">
+        <div className="text-2xl font-semibold mb-2">
+          {message.includes('HTTP 410') ? 'This link has expired' : 'Link not found'}
+        </div>
+        <div className="opacity-70">Ask the person who shared it for a new link.</div>
+      </div>
+    );
+  }
+
+  return (
+    <div className="container-responsive py-6">
+      <div className="mb-4">
+        <h1 className="text-xl sm:text-2xl font-semibold text-zinc-900 dark:text-zinc-100">
+          {data.title || data.assets[0]?.filename || 'Shared'}
+        </h1>
+        {data.description && <p className="text-sm text-zinc-500 dark:text-zinc-400 mt-1">{data.description}</p>}
+        <p className="text-xs text-zinc-500 dark:text-zinc-400 mt-1">
+          {data.assets.length} {data.assets.length === 1 ? 'item' : 'items'}
+          {data.expires_at && ` · available until ${new Date(data.expires_at * 1000).toLocaleString()}`}
+        </p>
+      </div>
+      <div className="grid grid-cols-2 sm:grid-cols-3 lg:grid-cols-4 xl:grid-cols-6 gap-2">
+        {data.assets.map((asset) => (
+          <div key={asset.id} className="group relative aspect-square overflow-hidden rounded-md bg-zinc-100 dark:bg-zinc-800">
+            <a href={media.sharedMediaUrl(token, 'preview', asset.id, data.access_key)} target="_blank" rel="noreferrer">
+              <img
+                src={media.sharedMediaUrl(token, 'thumb', asset.id, data.access_key)}
+                alt={asset.filename}
+                loading="lazy"
+                className="w-full h-full object-cover"
+              />
+            </a>
+            <a
+              href={media.sharedMediaUrl(token, 'download', asset.id, data.access_key)}
+              className="absolute bottom-1 right-1 p-1.5 rounded-md bg-black/50 text-white opacity-0 group-hover:opacity-100 transition-opacity"
+              title={`Download ${asset.filename}`}
+            >
+              <ArrowDownTrayIcon className="w-4 h-4" />
+            </a>
+          </div>
+        ))}
+      </div>
+    </div>
+  );
+}
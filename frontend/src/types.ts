@@ -22,6 +22,9 @@ export interface Asset {
   video_codec?: string;
   mime: string;
   flags: number;
+  rating?: number;
+  favorite?: boolean;
+  stack_id?: number | null;
 }
 
 export interface Paginated<T> {
@@ -135,3 +138,75 @@ export interface BulkPermanentDeleteResponse {
   read_only_failures: Array<{ id: number; path?: string | null; error?: string | null }>;
 }
 
+export type TranscodeStatusKind = 'not_needed' | 'ready' | 'queued' | 'running' | 'failed' | 'none';
+
+export interface TranscodeStatus {
+  asset_id: number;
+  status: TranscodeStatusKind;
+  error?: string;
+}
+
+export interface PersonSummary {
+  id: number;
+  name: string | null;
+  created_at: number;
+  face_count: number;
+  asset_count: number;
+  cover_face_id: number | null;
+}
+
+export interface User {
+  id: number;
+  username: string;
+  is_admin: boolean;
+  created_at: number;
+}
+
+export interface AlbumShare {
+  user_id: number;
+  username: string;
+}
+
+export interface ShareLink {
+  id: number;
+  album_id: number | null;
+  asset_id: number | null;
+  has_password: boolean;
+  expires_at: number | null;
+  created_by: number | null;
+  created_at: number;
+}
+
+export interface SharedAsset {
+  id: number;
+  filename: string;
+  mime: string;
+  size_bytes: number;
+  width: number | null;
+  height: number | null;
+  duration_ms: number | null;
+  taken_at: number | null;
+}
+
+export interface SharedView {
+  kind: 'album' | 'asset';
+  title: string | null;
+  description: string | null;
+  expires_at: number | null;
+  access_key: string | null;
+  assets: SharedAsset[];
+}
+
+export type SelfCheckStatus = 'pass' | 'warn' | 'fail' | 'skip';
+
+export interface SelfCheckResult {
+  name: string;
+  status: SelfCheckStatus;
+  message: string;
+  details?: Record<string, unknown>;
+}
+
+export interface SelfCheckReport {
+  ok: boolean;
+  checks: SelfCheckResult[];
+}
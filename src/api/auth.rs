@@ -0,0 +1,890 @@
+use std::sync::Arc;
+use axum::{extract::{Path, Request, State}, http::{header, HeaderMap, Method, StatusCode}, middleware::Next, response::{IntoResponse, Response}, Extension, Json};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::db::query::AccessScope;
+
+pub const SESSION_COOKIE: &str = "seen_session";
+const SESSION_TTL_SECS: i64 = 30 * 24 * 3600;
+const PASSWORD_HASH_ITERATIONS: u32 = 100_000;
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// Authentication is off unless an admin password is configured (FLASH_ADMIN_PASSWORD)
+#[derive(Default)]
+pub struct AuthConfig {
+    admin_password: Option<String>,
+}
+
+impl AuthConfig {
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("FLASH_ADMIN_PASSWORD").ok())
+    }
+
+    pub fn new(admin_password: Option<String>) -> Self {
+        Self { admin_password: admin_password.filter(|p| !p.is_empty()) }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.admin_password.is_some()
+    }
+
+    fn password_matches(&self, candidate: &str) -> bool {
+        match &self.admin_password {
+            // Compare digests so the comparison time doesn't depend on where the strings differ
+            Some(p) => constant_time_eq(&Sha256::digest(p.as_bytes()), &Sha256::digest(candidate.as_bytes())),
+            None => false,
+        }
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Who is making a request; `require_auth` stores it in the request extensions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Principal {
+    /// No credentials while auth is enabled
+    Anonymous,
+    /// Signed in with the admin password, or auth is disabled
+    Admin,
+    User { id: i64, is_admin: bool },
+}
+
+impl Principal {
+    pub fn is_authenticated(&self) -> bool {
+        *self != Principal::Anonymous
+    }
+
+    pub fn is_admin(&self) -> bool {
+        matches!(self, Principal::Admin | Principal::User { is_admin: true, .. })
+    }
+
+    /// The user account behind the request; None for the admin password and anonymous requests
+    pub fn user_id(&self) -> Option<i64> {
+        match self {
+            Principal::User { id, .. } => Some(*id),
+            _ => None,
+        }
+    }
+
+    pub fn scope(&self) -> AccessScope {
+        match self {
+            _ if self.is_admin() => AccessScope::All,
+            Principal::User { id, .. } => AccessScope::User(*id),
+            _ => AccessScope::Public,
+        }
+    }
+}
+
+/// 32 random bytes, hex encoded
+pub fn generate_token() -> Result<String> {
+    let mut buf = [0u8; 32];
+    getrandom::fill(&mut buf).map_err(|e| anyhow::anyhow!("Failed to generate token: {}", e))?;
+    Ok(hex::encode(buf))
+}
+
+pub fn hash_token(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+fn stretch_password(password: &str, salt: &[u8], iterations: u32) -> Vec<u8> {
+    let mut digest = Sha256::new().chain_update(salt).chain_update(password.as_bytes()).finalize();
+    for _ in 1..iterations {
+        digest = Sha256::new().chain_update(digest).chain_update(password.as_bytes()).finalize();
+    }
+    digest.to_vec()
+}
+
+/// Salted, iterated SHA-256, stored as `sha256$<iterations>$<salt>$<digest>`
+pub fn hash_password(password: &str) -> Result<String> {
+    let mut salt = [0u8; 16];
+    getrandom::fill(&mut salt).map_err(|e| anyhow::anyhow!("Failed to generate salt: {}", e))?;
+    let digest = stretch_password(password, &salt, PASSWORD_HASH_ITERATIONS);
+    Ok(format!("sha256${}${}${}", PASSWORD_HASH_ITERATIONS, hex::encode(salt), hex::encode(digest)))
+}
+
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    let mut parts = stored.split('$');
+    let (Some("sha256"), Some(iterations), Some(salt), Some(digest), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    let (Ok(iterations), Ok(salt), Ok(digest)) = (iterations.parse::<u32>(), hex::decode(salt), hex::decode(digest)) else {
+        return false;
+    };
+    iterations > 0 && constant_time_eq(&stretch_password(password, &salt, iterations), &digest)
+}
+
+/// Routes that delete data, touch files on disk, or expose the filesystem
+pub fn is_protected(method: &Method, path: &str) -> bool {
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    if *method == Method::DELETE
+        || path.starts_with("/auth/tokens")
+        || path.starts_with("/browse")
+        || path.starts_with("/deletes/pending")
+        || path.starts_with("/users")
+        || path.starts_with("/shares")
+        || path.starts_with("/kiosks")
+        || path.starts_with("/manifest")
+        || path.starts_with("/maintenance")
+        || path.starts_with("/admin")
+        || path.starts_with("/storage")
+        || path.starts_with("/rules")
+        || path.starts_with("/reports")
+        || path == "/stats/slowest"
+    {
+        return true;
+    }
+    if *method != Method::POST && *method != Method::PUT {
+        return false;
+    }
+    matches!(
+        path,
+        "/assets/permanent"
+            | "/import"
+            | "/import/archive"
+            | "/export"
+            | "/albums"
+            | "/paths"
+            | "/paths/scan"
+            | "/paths/cancel"
+            | "/paths/pause"
+            | "/paths/resume"
+            | "/paths/schedule"
+            | "/paths/filters"
+            | "/paths/quota"
+            | "/paths/dates"
+            | "/paths/archive"
+            | "/paths/relocate"
+            | "/stats/reset"
+            | "/pipeline/pause"
+            | "/pipeline/resume"
+            | "/thumbnails/warm"
+            | "/thumbnails/regenerate"
+            | "/faces/detect"
+            | "/faces/stop"
+            | "/faces/cluster"
+            | "/faces/recluster"
+            | "/faces/smart-merge"
+            | "/faces/settings"
+            | "/persons/merge"
+            | "/persons/export-metadata"
+            | "/stacks/rebuild"
+            | "/search/reference"
+            | "/cast/sessions"
+    ) || path.starts_with("/settings/")
+        || (path.starts_with("/asset/") && path.ends_with("/orientation"))
+        || (path.starts_with("/assets/") && path.ends_with("/lock"))
+        || (path.starts_with("/albums/") && (path.ends_with("/export-site") || path.ends_with("/highlight")))
+        || (path.starts_with("/persons/") && path.ends_with("/cover"))
+        || path.starts_with("/faces/recluster/person/")
+        || (path.starts_with("/faces/") && (path.ends_with("/assign") || path.ends_with("/feedback")))
+        || path.strip_prefix("/persons/").is_some_and(|id| id.parse::<i64>().is_ok())
+}
+
+/// Routes that affect every user's data, so only admins may call them
+pub fn is_admin_only(method: &Method, path: &str) -> bool {
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    path.starts_with("/users")
+        || path.starts_with("/manifest")
+        || path.starts_with("/maintenance")
+        || path.starts_with("/admin")
+        || path.starts_with("/storage")
+        || path.starts_with("/rules")
+        || path.starts_with("/reports")
+        || path == "/stats/slowest"
+        || ((*method == Method::PUT || *method == Method::POST) && path.starts_with("/settings/"))
+        || (*method == Method::DELETE && matches!(path, "/clear" | "/faces/clear"))
+        || (*method == Method::POST && matches!(path, "/stats/reset" | "/export" | "/import/archive" | "/pipeline/pause" | "/pipeline/resume" | "/persons/export-metadata" | "/faces/settings" | "/faces/detect" | "/faces/stop" | "/faces/cluster"))
+}
+
+/// An asset or album addressed by the request path
+#[derive(Debug, PartialEq, Eq)]
+enum Resource {
+    Asset(i64),
+    Album(i64),
+}
+
+fn resource_of(path: &str) -> Option<Resource> {
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    let mut parts = path.trim_start_matches('/').split('/');
+    let kind = parts.next()?;
+    let id = parts.next()?;
+    match kind {
+        "asset" | "assets" | "thumb" | "preview" => id.parse().ok().map(Resource::Asset),
+        "albums" if id == "for-asset" => parts.next()?.parse().ok().map(Resource::Asset),
+        "albums" => id.parse().ok().map(Resource::Album),
+        _ => None,
+    }
+}
+
+fn session_cookie(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value)
+}
+
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// The admin for credentials without a user, otherwise that user (Anonymous if the account is gone)
+fn principal_of(conn: &rusqlite::Connection, user_id: Option<i64>) -> Result<Principal> {
+    Ok(match user_id {
+        None => Principal::Admin,
+        Some(user_id) => match db::query::get_user(conn, user_id)? {
+            Some((id, _, is_admin, _)) => Principal::User { id, is_admin },
+            None => Principal::Anonymous,
+        },
+    })
+}
+
+/// Who an API token belongs to; None for unknown tokens
+pub fn token_principal(conn: &rusqlite::Connection, token: &str, now: i64) -> Result<Option<Principal>> {
+    let Some((id, user_id)) = db::query::find_api_token(conn, &hash_token(token))? else {
+        return Ok(None);
+    };
+    db::writer::touch_api_token(conn, id, now)?;
+    principal_of(conn, user_id).map(Some)
+}
+
+/// Who a subscription URL (calendar, feed reader) acts as: its `?token=` when auth is on and one is given,
+/// otherwise the request's own principal; None for an unknown token
+pub fn subscription_principal(conn: &rusqlite::Connection, token: Option<&str>, principal: Principal, auth_enabled: bool, now: i64) -> Result<Option<Principal>> {
+    match token {
+        Some(token) if auth_enabled => token_principal(conn, token, now),
+        _ => Ok(Some(principal)),
+    }
+}
+
+/// Work out who sent the request from its API token or session cookie
+pub fn resolve_principal(conn: &rusqlite::Connection, headers: &HeaderMap, now: i64) -> Result<Principal> {
+    if let Some(token) = bearer_token(headers) {
+        if let Some(principal) = token_principal(conn, token, now)? {
+            return Ok(principal);
+        }
+    }
+    if let Some(session) = session_cookie(headers) {
+        if let Some(user_id) = db::query::find_session(conn, &hash_token(session), now)? {
+            return principal_of(conn, user_id);
+        }
+    }
+    Ok(Principal::Anonymous)
+}
+
+enum Verdict {
+    Allow(Principal),
+    Unauthorized,
+    Forbidden,
+    NotFound(&'static str),
+}
+
+fn authorize(conn: &rusqlite::Connection, headers: &HeaderMap, method: &Method, path: &str, now: i64) -> Result<Verdict> {
+    let principal = resolve_principal(conn, headers, now)?;
+    if is_protected(method, path) && !principal.is_authenticated() {
+        return Ok(Verdict::Unauthorized);
+    }
+    if is_admin_only(method, path) && !principal.is_admin() {
+        return Ok(if principal.is_authenticated() { Verdict::Forbidden } else { Verdict::Unauthorized });
+    }
+
+    let scope = principal.scope();
+    if scope == AccessScope::All {
+        return Ok(Verdict::Allow(principal));
+    }
+    // Requesting a transcode only prepares a playable copy, so it counts as viewing
+    let read_only = *method == Method::GET || *method == Method::HEAD || path.ends_with("/transcode");
+    match resource_of(path) {
+        Some(Resource::Asset(id)) => {
+            if !db::query::can_view_asset(conn, id, scope)? {
+                return Ok(Verdict::NotFound("Asset not found"));
+            }
+            if !read_only && !db::query::can_edit_asset(conn, id, scope)? {
+                return Ok(Verdict::Forbidden);
+            }
+        }
+        Some(Resource::Album(id)) => {
+            if !db::query::can_view_album(conn, id, scope)? {
+                return Ok(Verdict::NotFound("Album not found"));
+            }
+            if !read_only && !db::query::can_edit_album(conn, id, scope)? {
+                return Ok(Verdict::Forbidden);
+            }
+        }
+        None => {}
+    }
+    Ok(Verdict::Allow(principal))
+}
+
+fn unauthorized() -> Response {
+    (StatusCode::UNAUTHORIZED, Json(serde_json::json!({
+        "error": "Authentication required"
+    }))).into_response()
+}
+
+fn forbidden() -> Response {
+    (StatusCode::FORBIDDEN, Json(serde_json::json!({
+        "error": "Not allowed"
+    }))).into_response()
+}
+
+/// Middleware: identify the caller, reject protected routes without credentials, and hide
+/// assets and albums outside the caller's libraries. Everything is allowed when auth is disabled.
+pub async fn require_auth(State(state): State<Arc<AppState>>, mut req: Request, next: Next) -> Response {
+    if !state.auth.enabled() {
+        req.extensions_mut().insert(Principal::Admin);
+        return next.run(req).await;
+    }
+    let pool = state.pool.clone();
+    let headers = req.headers().clone();
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let verdict = tokio::task::spawn_blocking(move || -> Result<Verdict> {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+        authorize(&conn, &headers, &method, &path, chrono::Utc::now().timestamp())
+    }).await;
+
+    match verdict {
+        Ok(Ok(Verdict::Allow(principal))) => {
+            req.extensions_mut().insert(principal);
+            next.run(req).await
+        }
+        Ok(Ok(Verdict::Unauthorized)) => unauthorized(),
+        Ok(Ok(Verdict::Forbidden)) => forbidden(),
+        Ok(Ok(Verdict::NotFound(message))) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": message
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error checking authentication: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error checking authentication: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    /// Sign in as a user account; omit to sign in with the admin password
+    pub username: Option<String>,
+    pub password: String,
+}
+
+pub async fn login(State(state): State<Arc<AppState>>, Json(req): Json<LoginRequest>) -> impl IntoResponse {
+    if !state.auth.enabled() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Authentication is not enabled"
+        }))).into_response();
+    }
+    let username = req.username.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    let rejection = if username.is_some() { "Invalid username or password" } else { "Invalid password" };
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let state = state.clone();
+        move || -> Result<Option<String>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            // Some(user_id) on success; the admin password signs in without a user
+            let owner = match &username {
+                Some(name) => db::query::get_user_credentials(&conn, name)?
+                    .filter(|(_, hash, _)| verify_password(&req.password, hash))
+                    .map(|(id, _, _)| Some(id)),
+                None => state.auth.password_matches(&req.password).then_some(None),
+            };
+            let Some(user_id) = owner else {
+                return Ok(None);
+            };
+            let token = generate_token()?;
+            let now = chrono::Utc::now().timestamp();
+            db::writer::insert_session(&conn, &hash_token(&token), user_id, now, now + SESSION_TTL_SECS)?;
+            Ok(Some(token))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(token))) => {
+            let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}", SESSION_COOKIE, token, SESSION_TTL_SECS);
+            (StatusCode::OK, [(header::SET_COOKIE, cookie)], Json(serde_json::json!({ "success": true }))).into_response()
+        }
+        Ok(Ok(None)) => {
+            // Slow down password guessing
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            (StatusCode::UNAUTHORIZED, Json(serde_json::json!({
+                "error": rejection
+            }))).into_response()
+        }
+        Ok(Err(e)) => {
+            tracing::error!("Error creating session: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error creating session: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn logout(State(state): State<Arc<AppState>>, headers: HeaderMap) -> impl IntoResponse {
+    if let Some(session) = session_cookie(&headers).map(hash_token) {
+        let pool = state.pool.clone();
+        let result = tokio::task::spawn_blocking(move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::delete_session(&conn, &session)
+        }).await;
+        if let Ok(Err(e)) = result {
+            tracing::warn!("Error deleting session: {}", e);
+        }
+    }
+    let cookie = format!("{}=; Path=/; HttpOnly; SameSite=Strict; Max-Age=0", SESSION_COOKIE);
+    (StatusCode::OK, [(header::SET_COOKIE, cookie)], Json(serde_json::json!({ "success": true }))).into_response()
+}
+
+#[derive(Serialize)]
+pub struct UserResponse {
+    pub id: i64,
+    pub username: String,
+    pub is_admin: bool,
+    pub created_at: i64,
+}
+
+impl From<db::query::UserInfo> for UserResponse {
+    fn from((id, username, is_admin, created_at): db::query::UserInfo) -> Self {
+        Self { id, username, is_admin, created_at }
+    }
+}
+
+pub async fn auth_status(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>) -> impl IntoResponse {
+    if !state.auth.enabled() {
+        return (StatusCode::OK, Json(serde_json::json!({
+            "auth_enabled": false,
+            "authenticated": true,
+            "is_admin": true,
+            "user": null
+        }))).into_response();
+    }
+    let Some(user_id) = principal.user_id() else {
+        return (StatusCode::OK, Json(serde_json::json!({
+            "auth_enabled": true,
+            "authenticated": principal.is_authenticated(),
+            "is_admin": principal.is_admin(),
+            "user": null
+        }))).into_response();
+    };
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<UserResponse>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            Ok(db::query::get_user(&conn, user_id)?.map(UserResponse::from))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(user)) => (StatusCode::OK, Json(serde_json::json!({
+            "auth_enabled": true,
+            "authenticated": true,
+            "is_admin": principal.is_admin(),
+            "user": user
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error loading user: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error loading user: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ApiTokenResponse {
+    pub id: i64,
+    pub name: String,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+}
+
+/// Admins manage every token and share link; users only see and revoke their own
+pub(crate) fn owner_filter(principal: &Principal) -> Option<i64> {
+    if principal.is_admin() {
+        None
+    } else {
+        principal.user_id()
+    }
+}
+
+pub async fn list_tokens(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<ApiTokenResponse>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            Ok(db::query::list_api_tokens(&conn, owner_filter(&principal))?
+                .into_iter()
+                .map(|(id, name, created_at, last_used_at)| ApiTokenResponse { id, name, created_at, last_used_at })
+                .collect())
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(tokens)) => (StatusCode::OK, Json(tokens)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error listing API tokens: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error listing API tokens: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+}
+
+pub async fn create_token(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<CreateTokenRequest>,
+) -> impl IntoResponse {
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Token name is required"
+        }))).into_response();
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<(i64, String)> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let token = generate_token()?;
+            let id = db::writer::insert_api_token(&conn, &name, &hash_token(&token), principal.user_id())?;
+            Ok((id, token))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok((id, token))) => (StatusCode::CREATED, Json(serde_json::json!({
+            "id": id,
+            "name": req.name.trim(),
+            "token": token
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error creating API token: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error creating API token: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn delete_token(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::delete_api_token(&conn, id, owner_filter(&principal))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({ "success": true }))).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Token not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error deleting API token: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error deleting API token: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn list_users(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<UserResponse>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            Ok(db::query::list_users(&conn)?.into_iter().map(UserResponse::from).collect())
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(users)) => (StatusCode::OK, Json(users)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error listing users: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error listing users: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub password: String,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+pub async fn create_user(State(state): State<Arc<AppState>>, Json(req): Json<CreateUserRequest>) -> impl IntoResponse {
+    if !state.auth.enabled() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Authentication is not enabled"
+        }))).into_response();
+    }
+    let username = req.username.trim().to_string();
+    if username.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Username is required"
+        }))).into_response();
+    }
+    if req.password.chars().count() < MIN_PASSWORD_LEN {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("Password must be at least {} characters", MIN_PASSWORD_LEN)
+        }))).into_response();
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<UserResponse>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let password_hash = hash_password(&req.password)?;
+            match db::writer::create_user(&conn, &username, &password_hash, req.is_admin)? {
+                Some(id) => Ok(db::query::get_user(&conn, id)?.map(UserResponse::from)),
+                None => Ok(None),
+            }
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(user))) => (StatusCode::CREATED, Json(user)).into_response(),
+        Ok(Ok(None)) => (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "Username already exists"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error creating user: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error creating user: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn delete_user(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::delete_user(&conn, id)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({ "success": true }))).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "User not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error deleting user: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error deleting user: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_protected_routes() {
+        assert!(is_protected(&Method::DELETE, "/asset/1"));
+        assert!(is_protected(&Method::GET, "/browse"));
+        assert!(is_protected(&Method::POST, "/api/assets/permanent"));
+        assert!(is_protected(&Method::POST, "/asset/7/orientation"));
+        assert!(is_protected(&Method::PUT, "/api/assets/7/lock") && !is_protected(&Method::PUT, "/api/assets/7/favorite"));
+        assert!(is_protected(&Method::PUT, "/api/paths/filters"));
+        assert!(is_protected(&Method::PUT, "/api/paths/quota"));
+        assert!(is_protected(&Method::POST, "/api/paths/cancel"));
+        assert!(is_protected(&Method::PUT, "/api/paths/dates"));
+        assert!(is_protected(&Method::PUT, "/api/paths/archive"));
+        assert!(is_protected(&Method::PUT, "/api/paths/relocate"));
+        assert!(is_protected(&Method::PUT, "/api/settings/ingest"));
+        assert!(is_protected(&Method::POST, "/api/thumbnails/warm") && !is_protected(&Method::GET, "/api/thumbnails/warm"));
+        assert!(is_protected(&Method::POST, "/api/thumbnails/regenerate"));
+        assert!(is_protected(&Method::POST, "/api/stacks/rebuild") && !is_protected(&Method::GET, "/api/stacks"));
+        assert!(is_protected(&Method::POST, "/api/search/reference") && !is_protected(&Method::GET, "/api/search/reference/1/image"));
+        assert!(is_protected(&Method::POST, "/api/cast/sessions"));
+        assert!(is_protected(&Method::PUT, "/api/persons/3/cover") && !is_protected(&Method::GET, "/api/persons/3/face"));
+        assert!(is_protected(&Method::POST, "/api/albums/2/highlight") && !is_protected(&Method::GET, "/api/albums/2/highlight"));
+        assert!(is_protected(&Method::POST, "/api/albums") && !is_protected(&Method::GET, "/api/albums"));
+        assert!(is_protected(&Method::POST, "/api/paths/pause") && is_protected(&Method::POST, "/api/paths/resume"));
+        assert!(is_protected(&Method::GET, "/api/deletes/pending") && is_protected(&Method::POST, "/api/deletes/pending/4/undo"));
+        assert!(is_protected(&Method::POST, "/api/faces/assign") && is_protected(&Method::POST, "/api/faces/7/assign"));
+        assert!(is_protected(&Method::POST, "/api/faces/7/feedback") && !is_protected(&Method::GET, "/api/faces/7/similar"));
+        assert!(is_protected(&Method::POST, "/api/persons/3") && !is_protected(&Method::GET, "/api/persons/3"));
+        assert!(is_protected(&Method::POST, "/api/faces/recluster/person/3") && !is_admin_only(&Method::POST, "/api/faces/recluster/person/3"));
+        assert!(!is_protected(&Method::GET, "/api/settings"));
+        assert!(is_protected(&Method::PUT, "/api/paths/schedule"));
+        assert!(is_protected(&Method::GET, "/auth/tokens"));
+        assert!(is_protected(&Method::GET, "/users"));
+        assert!(is_protected(&Method::GET, "/shares"));
+        assert!(!is_protected(&Method::GET, "/api/share/abc/thumb/1"));
+        assert!(is_protected(&Method::GET, "/api/kiosks") && !is_protected(&Method::GET, "/api/kiosk/abc/preview/1"));
+        assert!(!is_protected(&Method::GET, "/assets"));
+        assert!(!is_protected(&Method::PUT, "/assets/1/rating"));
+        assert!(!is_protected(&Method::POST, "/auth/login"));
+
+        assert!(is_admin_only(&Method::POST, "/api/users"));
+        assert!(is_admin_only(&Method::DELETE, "/clear"));
+        assert!(is_admin_only(&Method::POST, "/api/export"));
+        assert!(is_admin_only(&Method::POST, "/import/archive"));
+        assert!(is_admin_only(&Method::POST, "/api/pipeline/pause") && is_protected(&Method::POST, "/api/pipeline/resume"));
+        assert!(is_admin_only(&Method::GET, "/api/manifest") && is_protected(&Method::GET, "/api/manifest/verify"));
+        assert!(is_admin_only(&Method::POST, "/api/maintenance/gc") && is_protected(&Method::GET, "/api/maintenance/gc"));
+        assert!(is_admin_only(&Method::GET, "/api/storage") && is_protected(&Method::GET, "/api/storage"));
+        assert!(is_admin_only(&Method::DELETE, "/api/rules/3") && is_protected(&Method::GET, "/rules"));
+        assert!(is_admin_only(&Method::POST, "/api/reports/library") && is_protected(&Method::POST, "/reports/library"));
+        assert!(is_admin_only(&Method::POST, "/api/settings/digest/send") && is_protected(&Method::POST, "/settings/digest/send"));
+        assert!(is_admin_only(&Method::GET, "/api/stats/slowest") && is_protected(&Method::GET, "/stats/slowest"));
+        assert!(is_admin_only(&Method::POST, "/api/persons/export-metadata") && is_protected(&Method::POST, "/persons/export-metadata"));
+        assert!(is_admin_only(&Method::POST, "/api/faces/settings") && is_protected(&Method::POST, "/faces/settings"));
+        assert!(!is_protected(&Method::GET, "/api/faces/settings"));
+        for path in ["/api/faces/detect", "/api/faces/stop", "/api/faces/cluster"] {
+            assert!(is_admin_only(&Method::POST, path) && is_protected(&Method::POST, path));
+        }
+        assert!(is_admin_only(&Method::POST, "/api/admin/queues/flush") && is_protected(&Method::POST, "/admin/queues/flush"));
+        assert!(!is_protected(&Method::GET, "/api/stats"));
+        assert!(!is_admin_only(&Method::DELETE, "/asset/1"));
+
+        assert_eq!(resource_of("/api/asset/12/video"), Some(Resource::Asset(12)));
+        assert_eq!(resource_of("/thumb/3"), Some(Resource::Asset(3)));
+        assert_eq!(resource_of("/albums/for-asset/9"), Some(Resource::Asset(9)));
+        assert_eq!(resource_of("/albums/4/people"), Some(Resource::Album(4)));
+        assert_eq!(resource_of("/assets/search"), None);
+        assert_eq!(resource_of("/albums/batch"), None);
+    }
+
+    #[test]
+    fn test_session_and_token_auth() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::schema::apply_schema(&conn).unwrap();
+        let token = generate_token().unwrap();
+        db::writer::insert_api_token(&conn, "ci", &hash_token(&token), None).unwrap();
+        db::writer::insert_session(&conn, &hash_token("sess"), None, 100, 200).unwrap();
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(resolve_principal(&conn, &headers, 150).unwrap(), Principal::Anonymous);
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token)).unwrap());
+        assert_eq!(resolve_principal(&conn, &headers, 150).unwrap(), Principal::Admin);
+        assert_eq!(db::query::list_api_tokens(&conn, None).unwrap()[0].3, Some(150));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("theme=dark; seen_session=sess"));
+        assert_eq!(resolve_principal(&conn, &headers, 150).unwrap(), Principal::Admin);
+        assert_eq!(resolve_principal(&conn, &headers, 200).unwrap(), Principal::Anonymous);
+
+        let cfg = AuthConfig::new(Some("hunter2".into()));
+        assert!(cfg.password_matches("hunter2") && !cfg.password_matches("hunter3"));
+        assert!(!AuthConfig::new(Some(String::new())).enabled());
+    }
+
+    #[test]
+    fn test_user_sessions_and_scope() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::schema::apply_schema(&conn).unwrap();
+        let hash = hash_password("correct horse").unwrap();
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("wrong horse", &hash));
+        assert!(!verify_password("correct horse", "garbage"));
+
+        let alice = db::writer::create_user(&conn, "alice", &hash, false).unwrap().unwrap();
+        assert!(db::writer::create_user(&conn, "ALICE", &hash, false).unwrap().is_none());
+        db::writer::insert_session(&conn, &hash_token("alice-sess"), Some(alice), 100, 200).unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("seen_session=alice-sess"));
+        let principal = resolve_principal(&conn, &headers, 150).unwrap();
+        assert_eq!(principal, Principal::User { id: alice, is_admin: false });
+        assert_eq!(principal.scope(), AccessScope::User(alice));
+        assert_eq!(Principal::Anonymous.scope(), AccessScope::Public);
+        assert_eq!(Principal::User { id: alice, is_admin: true }.scope(), AccessScope::All);
+
+        // Deleting the user ends their sessions
+        assert!(db::writer::delete_user(&conn, alice).unwrap());
+        assert_eq!(resolve_principal(&conn, &headers, 150).unwrap(), Principal::Anonymous);
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::Arc;
+use axum::{extract::{Path, Query, State}, http::{HeaderMap, StatusCode}, response::{IntoResponse, Response}, Extension, Json};
+use serde::Deserialize;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::api::auth::Principal;
+use crate::api::handlers;
+use crate::api::memories::origin;
+use crate::models::asset::Asset;
+use crate::utils::cast::{self, CastSession};
+
+#[derive(Deserialize)]
+pub struct CreateCastRequest {
+    /// Assets to queue, in order
+    pub asset_ids: Option<Vec<i64>>,
+    /// Or queue a whole album
+    pub album_id: Option<i64>,
+    /// Seconds each photo stays up before the sender advances (default 10)
+    pub slide_secs: Option<u32>,
+}
+
+fn not_found(message: &str) -> Response {
+    (StatusCode::NOT_FOUND, Json(serde_json::json!({
+        "error": message
+    }))).into_response()
+}
+
+/// Assets of the session in queue order; deleted ones are skipped
+async fn session_assets(state: &AppState, session: &CastSession) -> Result<Vec<Asset>> {
+    let pool = state.pool.clone();
+    let ids = session.asset_ids.clone();
+    tokio::task::spawn_blocking(move || -> Result<Vec<Asset>> {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+        let mut assets = Vec::with_capacity(ids.len());
+        for id in ids {
+            assets.extend(db::query::get_asset_by_id(&conn, id)?);
+        }
+        Ok(assets)
+    })
+    .await?
+}
+
+/// The session as a Cast queue: one `media` entry per asset, ready to load with the sender SDK
+async fn cast_queue(state: &AppState, session: &CastSession, origin: &str) -> Result<serde_json::Value> {
+    let base = format!("{}/api/cast/{}", origin, session.token);
+    let mut items = Vec::new();
+    for asset in session_assets(state, session).await? {
+        let is_video = asset.mime.starts_with("video/");
+        // Photos go out as the 1600px WebP preview, which every receiver can show
+        let content_type = if is_video {
+            handlers::playable_video_mime(state, asset.id).await
+        } else {
+            Some("image/webp".to_string())
+        };
+        let url = format!("{}/media/{}", base, asset.id);
+        let mut metadata = serde_json::json!({
+            "metadataType": cast::metadata_type(&asset.mime),
+            "title": asset.filename,
+            "images": [{ "url": format!("{}/thumb/{}", base, asset.id) }],
+        });
+        if let Some(taken) = asset.taken_at.and_then(|t| chrono::DateTime::from_timestamp(t, 0)) {
+            metadata["creationDateTime"] = serde_json::json!(taken.to_rfc3339());
+        }
+        if !is_video {
+            metadata["width"] = serde_json::json!(asset.width);
+            metadata["height"] = serde_json::json!(asset.height);
+        }
+        items.push(serde_json::json!({
+            "asset_id": asset.id,
+            // Videos that still need transcoding are queued for it and become ready later
+            "ready": content_type.is_some(),
+            "media": {
+                "contentId": url,
+                "contentUrl": url,
+                "contentType": content_type.unwrap_or_else(|| "video/mp4".to_string()),
+                "streamType": "BUFFERED",
+                "duration": asset.duration_ms.map(|ms| ms as f64 / 1000.0),
+                "metadata": metadata
+            }
+        }));
+    }
+    Ok(serde_json::json!({
+        "token": session.token,
+        "expires_at": session.expires_at,
+        "slide_secs": session.slide_secs,
+        "queue_url": format!("{}/queue", base),
+        "items": items
+    }))
+}
+
+fn queue_response(status: StatusCode, queue: Result<serde_json::Value>) -> Response {
+    match queue {
+        Ok(body) => (status, Json(body)).into_response(),
+        Err(e) => {
+            tracing::error!("Error building cast queue: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+    }
+}
+
+/// Open a cast session for some assets or an album
+pub async fn create_cast_session(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+    Json(req): Json<CreateCastRequest>,
+) -> Response {
+    if req.asset_ids.is_some() == req.album_id.is_some() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Give either asset_ids or album_id"
+        }))).into_response();
+    }
+    let slide_secs = req.slide_secs.unwrap_or(10).clamp(3, 600);
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<Vec<i64>>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let scope = principal.scope();
+            let ids = match (req.asset_ids, req.album_id) {
+                (Some(ids), _) => db::query::filter_visible_assets(&conn, ids, scope)?,
+                (_, Some(album_id)) => match handlers::visible_album(&conn, album_id, scope)? {
+                    Some(album) => album.5,
+                    None => return Ok(None),
+                },
+                _ => Vec::new(),
+            };
+            Ok(Some(ids))
+        }
+    }).await;
+
+    let mut ids = match result {
+        Ok(Ok(Some(ids))) => ids,
+        Ok(Ok(None)) => return not_found("Album not found"),
+        Ok(Err(e)) => {
+            tracing::error!("Error opening cast session: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response();
+        }
+        Err(e) => {
+            tracing::error!("Task error opening cast session: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response();
+        }
+    };
+    if ids.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Nothing to cast"
+        }))).into_response();
+    }
+    ids.truncate(cast::MAX_QUEUE);
+    let session = match state.cast_sessions.insert(ids, slide_secs, chrono::Utc::now().timestamp()) {
+        Ok(session) => session,
+        Err(e) => {
+            tracing::error!("Error opening cast session: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response();
+        }
+    };
+    let queue = cast_queue(&state, &session, &origin(&headers)).await;
+    queue_response(StatusCode::CREATED, queue)
+}
+
+/// The session's queue, with video readiness refreshed
+pub async fn cast_session_queue(State(state): State<Arc<AppState>>, Path(token): Path<String>, headers: HeaderMap) -> Response {
+    let Some(session) = state.cast_sessions.get(&token, chrono::Utc::now().timestamp()) else {
+        return not_found("Cast session not found");
+    };
+    let queue = cast_queue(&state, &session, &origin(&headers)).await;
+    queue_response(StatusCode::OK, queue)
+}
+
+/// The 404 to send unless the session is open and queues the asset
+fn check_session_asset(state: &AppState, token: &str, asset_id: i64) -> Option<Response> {
+    match state.cast_sessions.get(token, chrono::Utc::now().timestamp()) {
+        Some(session) if session.covers_asset(asset_id) => None,
+        Some(_) => Some(not_found("Asset not found")),
+        None => Some(not_found("Cast session not found")),
+    }
+}
+
+/// What the receiver plays: the photo's WebP preview or the (transcoded) video, with Range support
+pub async fn cast_media(State(state): State<Arc<AppState>>, Path((token, asset_id)): Path<(String, i64)>, headers: HeaderMap) -> Response {
+    if let Some(response) = check_session_asset(&state, &token, asset_id) {
+        return response;
+    }
+    let mime = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Option<String> {
+            let conn = pool.get().ok()?;
+            db::query::get_asset_by_id(&conn, asset_id).ok()?.map(|a| a.mime)
+        }
+    }).await.ok().flatten();
+    match mime {
+        Some(mime) if mime.starts_with("video/") => handlers::stream_video(State(state), Path(asset_id), headers).await.into_response(),
+        Some(_) => handlers::preview_1600(State(state), Path(asset_id)).await.into_response(),
+        None => not_found("Asset not found"),
+    }
+}
+
+pub async fn cast_thumb(State(state): State<Arc<AppState>>, Path((token, asset_id)): Path<(String, i64)>) -> Response {
+    if let Some(response) = check_session_asset(&state, &token, asset_id) {
+        return response;
+    }
+    handlers::thumb_256(State(state), Path(asset_id), Query(handlers::ThumbQuery { animated: None })).await.into_response()
+}
+
+/// End a session early; its URLs stop working
+pub async fn delete_cast_session(State(state): State<Arc<AppState>>, Path(token): Path<String>) -> Response {
+    if state.cast_sessions.remove(&token) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        not_found("Cast session not found")
+    }
+}
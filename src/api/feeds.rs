@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use axum::{extract::{Query, State}, http::{header, HeaderMap, StatusCode}, response::{IntoResponse, Response}, Extension, Json};
+use serde::Deserialize;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::api::auth::{self, Principal};
+use crate::api::memories::origin;
+use crate::utils::feed::{self, RecentAsset};
+
+const FEED_TITLE: &str = "Recently added";
+
+#[derive(Deserialize, Default)]
+pub struct RecentFeedQuery {
+    /// API token, for feed readers that can't send headers
+    pub token: Option<String>,
+    /// Items to list (default 50, max 200)
+    pub limit: Option<usize>,
+}
+
+enum Format {
+    Rss,
+    Json,
+}
+
+/// Newest assets the feed's principal can see; None for an unknown token
+async fn load_recent(state: &AppState, principal: Principal, q: RecentFeedQuery) -> Result<Option<Vec<RecentAsset>>> {
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    let auth_enabled = state.auth.enabled();
+    let pool = state.pool.clone();
+    tokio::task::spawn_blocking(move || -> Result<Option<Vec<RecentAsset>>> {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+        let Some(principal) = auth::subscription_principal(&conn, q.token.as_deref(), principal, auth_enabled, chrono::Utc::now().timestamp())? else {
+            return Ok(None);
+        };
+        Ok(Some(db::query::recent_assets(&conn, principal.scope(), limit)?))
+    })
+    .await?
+}
+
+async fn recent_feed(state: Arc<AppState>, principal: Principal, headers: HeaderMap, q: RecentFeedQuery, format: Format) -> Response {
+    let origin = origin(&headers);
+    match load_recent(&state, principal, q).await {
+        Ok(Some(assets)) => match format {
+            Format::Rss => {
+                let body = feed::rss(FEED_TITLE, &origin, &format!("{}/api/feeds/recent.xml", origin), &assets, chrono::Utc::now());
+                ([(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")], body).into_response()
+            }
+            Format::Json => {
+                let body = feed::json_feed(FEED_TITLE, &origin, &format!("{}/api/feeds/recent.json", origin), &assets);
+                ([(header::CONTENT_TYPE, "application/feed+json")], body.to_string()).into_response()
+            }
+        },
+        Ok(None) => (StatusCode::UNAUTHORIZED, Json(serde_json::json!({
+            "error": "Invalid token"
+        }))).into_response(),
+        Err(e) => {
+            tracing::error!("Error building recent feed: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+    }
+}
+
+/// RSS feed of newly indexed assets
+pub async fn recent_rss(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+    Query(q): Query<RecentFeedQuery>,
+) -> Response {
+    recent_feed(state, principal, headers, q, Format::Rss).await
+}
+
+/// JSON Feed of newly indexed assets
+pub async fn recent_json(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+    Query(q): Query<RecentFeedQuery>,
+) -> Response {
+    recent_feed(state, principal, headers, q, Format::Json).await
+}
@@ -1,13 +1,23 @@
 use std::sync::Arc;
-use axum::{extract::{State, Path, Query}, http::{StatusCode, header, HeaderMap}, Json, response::IntoResponse};
+use axum::{extract::{State, Path, Query}, http::{StatusCode, header, HeaderMap}, Extension, Json, response::IntoResponse};
 use serde::{Deserialize, Serialize};
 use crate::{AppPaths, AppState, db};
+use crate::api::auth::Principal;
+use crate::db::query::AccessScope;
+use crate::models::album::SmartAlbumRules;
 use tracing::info;
 use rusqlite::{Connection, params, OptionalExtension};
 use anyhow::Result;
-use hex;
 use std::path::Path as StdPath;
 use crate::utils::ffmpeg;
+use crate::utils::delete_hold::{self, remove_derived_files};
+use crate::utils::backup;
+use crate::utils::scheduler;
+use crate::pipeline::dates;
+use crate::pipeline::path_filter::{PathFilter, SharedPathFilter};
+use crate::pipeline::discover::ScanOptions;
+use crate::pipeline::quota::ScanQuota;
+use crate::pipeline::transcode::{self, TranscodeJob, TranscodeState};
 use std::io;
 use axum::response::Html;
 #[cfg(not(target_env = "msvc"))]
@@ -23,7 +33,11 @@ pub async fn serve_index() -> impl IntoResponse {
     }
 }
 
-pub async fn health() -> impl IntoResponse {
+/// Liveness plus dependency checks: `unhealthy` (503) when the library database or data directories are unusable,
+/// `degraded` when an optional dependency such as ffmpeg, libvips, the face models or a watcher is broken
+pub async fn health(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    use crate::utils::selfcheck;
+
     let v = env!("CARGO_PKG_VERSION");
     #[cfg(feature = "postgres")]
     let db_type = "Postgres";
@@ -71,41 +85,110 @@ pub async fn health() -> impl IntoResponse {
         backend_libraries.push("ndarray - N-dimensional arrays (optional, facial recognition)".to_string());
     }
 
+    // Surface missing external tools (ffmpeg/ffprobe) without failing the health check
+    let binaries = ffmpeg::binary_status();
+    let warnings: Vec<String> = binaries.iter().filter_map(|b| b.warning.clone()).collect();
+
+    // Face models only matter once face detection has been turned on
+    #[cfg(feature = "facial-recognition")]
+    let face_models = state.face_detection_enabled.load(std::sync::atomic::Ordering::Relaxed).then(|| {
+        let proc = state.face_processor.lock();
+        (proc.models_dir.clone(), proc.scrfd_loaded(), proc.arcface_loaded())
+    });
+    let watchers: Vec<(String, bool)> = state.path_watchers.lock()
+        .iter()
+        .map(|(path, handle)| (path.clone(), !handle.is_finished()))
+        .collect();
+    let pool = state.pool.clone();
+    let paths = state.paths.clone();
+    let db_dir = state.db_path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| paths.data.join("db"));
+
+    let report = tokio::task::spawn_blocking(move || {
+        let critical = vec![
+            selfcheck::check_database(&pool),
+            selfcheck::check_writable_dirs(&[("db", db_dir.as_path()), ("derived", paths.derived.as_path())]),
+        ];
+        let mut optional = vec![selfcheck::check_disk_space(&paths.derived), selfcheck::check_libvips()];
+        optional.extend(selfcheck::check_ffmpeg().into_iter().filter(|c| c.name != "ffmpeg_features"));
+        #[cfg(feature = "facial-recognition")]
+        if let Some((models_dir, scrfd_loaded, arcface_loaded)) = face_models {
+            optional.push(selfcheck::check_onnx(models_dir, scrfd_loaded, arcface_loaded));
+        }
+        optional.push(selfcheck::check_watchers(&watchers));
+        selfcheck::HealthReport::new(critical, optional)
+    }).await;
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            tracing::error!("Task error running health checks: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            })));
+        }
+    };
+
+    let code = if report.status == selfcheck::Health::Unhealthy { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::OK };
     let body = serde_json::json!({
-        "status": "ok",
+        "status": report.status,
         "version": v,
         "database": db_type,
-        "backend_libraries": backend_libraries
+        "checks": report.checks,
+        "backend_libraries": backend_libraries,
+        "binaries": binaries,
+        "warnings": warnings
     });
-    (StatusCode::OK, Json(body))
+    (code, Json(body))
+}
+
+fn stage_json(stats: &crate::stats::Stats, stage: crate::stats::IoStage) -> serde_json::Value {
+    let io = stats.stage(stage);
+    let (files_per_sec, bytes_per_sec) = io.rates();
+    serde_json::json!({
+        "files_total": io.files_total(),
+        "bytes_total": io.bytes_total(),
+        "files_per_sec": files_per_sec,
+        "mb_per_sec": bytes_per_sec / 1_000_000.0
+    })
 }
 
-pub async fn stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+pub async fn stats(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>) -> impl IntoResponse {
     let depths = state.gauges.depths();
 
     // Use cached counts with 5-second TTL to reduce database load
     const CACHE_TTL_SECS: u64 = 5;
-    let (db_count, total_photos, total_videos) = if state.stats_cache.is_stale(CACHE_TTL_SECS) {
+    let scope = principal.scope();
+    let ((db_count, total_photos, total_videos), (trash_count, trash_bytes)) = if scope != AccessScope::All {
+        // The cache holds library-wide counts; everyone else only counts what they can see
+        tokio::task::spawn_blocking({
+            let pool = state.pool.clone();
+            move || {
+                let conn = pool.get().ok()?;
+                let counts = db::query::asset_type_counts(&conn, scope).ok()?;
+                let trash = db::query::pending_delete_totals(&conn, scope).ok()?;
+                Some((counts, trash))
+            }
+        }).await.ok().flatten().unwrap_or(((0, 0, 0), (0, 0)))
+    } else if state.stats_cache.is_stale(CACHE_TTL_SECS) {
         // Cache is stale, refresh from database
         let counts = tokio::task::spawn_blocking({
             let pool = state.pool.clone();
             move || {
                 let conn = pool.get().ok()?;
-                let total: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).ok()?;
-                let photos: i64 = conn.query_row("SELECT COUNT(*) FROM assets WHERE mime LIKE 'image/%'", [], |r| r.get(0)).ok()?;
-                let videos: i64 = conn.query_row("SELECT COUNT(*) FROM assets WHERE mime LIKE 'video/%'", [], |r| r.get(0)).ok()?;
-                Some((total, photos, videos))
+                let counts = db::query::asset_type_counts(&conn, AccessScope::All).ok()?;
+                // Held deletes are no longer in assets, so they only show up here
+                let trash = db::query::pending_delete_totals(&conn, AccessScope::All).ok()?;
+                Some((counts, trash))
             }
-        }).await.ok().flatten().unwrap_or((0, 0, 0));
+        }).await.ok().flatten().unwrap_or(((0, 0, 0), (0, 0)));
 
         // Update cache
-        state.stats_cache.update(counts.0, counts.1, counts.2);
+        state.stats_cache.update_trash(counts.1.0, counts.1.1);
+        state.stats_cache.update(counts.0.0, counts.0.1, counts.0.2);
         counts
     } else {
         // Use cached values
-        state.stats_cache.get()
+        (state.stats_cache.get(), state.stats_cache.trash())
     };
-
     let scan_stats = state.stats.scan_stats();
     let last_completed_scan_files = state.stats.last_completed_scan_files();
     let last_completed_scan_elapsed = state.stats.last_completed_scan_elapsed();
@@ -164,7 +247,7 @@ pub async fn stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
 
     let body = serde_json::json!({
         "uptime_seconds": state.stats.uptime_secs(),
-        "queues": {"discover": depths.discover, "hash": depths.hash, "metadata": depths.metadata, "db_write": depths.db_write, "thumb": depths.thumb},
+        "queues": {"discover": depths.discover, "hash": depths.hash, "metadata": depths.metadata, "db_write": depths.db_write, "thumb": depths.thumb, "transcode": depths.transcode},
         // Discovery stats (files discovered in the last/active scan)
         "discovery": {
             "files_discovered": scan_stats.map(|(files, _, _)| files).unwrap_or(last_completed_scan_files),
@@ -175,18 +258,40 @@ pub async fn stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
             "files_total": state.stats.files_total(),
             "bytes_total": state.stats.bytes_total(),
             "files_per_sec": files_per_sec,
-            "bytes_per_sec": files_per_sec * 1_000_000.0, // Approximate, but consistent with mb_per_sec
+            "bytes_per_sec": mb_per_sec * 1_000_000.0,
             "mb_per_sec": mb_per_sec
         },
         "processing": {
             "files_committed": state.stats.files_committed(),
+            "files_unchanged": state.stats.files_unchanged(),
+            "files_filtered": state.stats.files_filtered(),
             "bytes_total": state.stats.bytes_total(),
             "rate_files_per_sec": processing_rate,
             "throughput_mb_per_sec": processing_mb_per_sec,
             "last_completed_elapsed_seconds": state.stats.last_completed_processing_elapsed()
         },
+        // Bytes each stage read from originals, with throughput over the last few seconds
+        "stages": {
+            "hash": stage_json(&state.stats, crate::stats::IoStage::Hash),
+            "metadata": stage_json(&state.stats, crate::stats::IoStage::Metadata),
+            "thumb": stage_json(&state.stats, crate::stats::IoStage::Thumb)
+        },
         "scan_running": is_scanning,
         "processing_active": has_queued_items,
+        // Read pool vs the single writer connection, to tell pool starvation from a backed-up writer
+        "database": {
+            "pool": state.pool.status(),
+            "writer": {
+                "queue": depths.db_write,
+                "batches": state.stats.stage_latency(crate::stats::PipelineStage::DbWrite).count(),
+                "avg_batch_rows": state.stats.db_batch_rows().mean(),
+                "avg_batch_ms": state.stats.stage_latency(crate::stats::PipelineStage::DbWrite).mean() * 1000.0,
+                "errors": state.stats.stage_errors(crate::stats::PipelineStage::DbWrite)
+            }
+        },
+        "pipeline": {"paused": state.gauges.is_paused(), "paused_since": state.gauges.paused_since(), "low_impact": state.gauges.throttle.settings().enabled},
+        // Latest bulk warm-up or regeneration, same report as GET /thumbnails/warm
+        "thumbnails": state.thumb_warm.lock().as_ref().map(|job| job.report()),
         "current_scan": scan_stats.map(|(files, rate, elapsed)| {
             // If scan is not running, use the completed rate to prevent decay
             let discovery_rate = if !is_scanning {
@@ -215,7 +320,8 @@ pub async fn stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
                 "elapsed_seconds": elapsed
             })
         }),
-        "db": {"assets": db_count}
+        "db": {"assets": db_count},
+        "trash": {"count": trash_count, "size_bytes": trash_bytes}
     });
     // Add Cache-Control header to allow short-term caching
     let mut response = Json(body).into_response();
@@ -452,6 +558,8 @@ pub async fn clear_all_data(State(state): State<Arc<AppState>>) -> impl IntoResp
         Ok(Ok((assets_deleted, faces_deleted, persons_deleted))) => {
             // Also reset performance statistics when clearing all data
             state.stats.reset_stats();
+            #[cfg(feature = "facial-recognition")]
+            state.face_index.lock().clear();
             state.stats_cache.was_processing_active.store(false, std::sync::atomic::Ordering::Relaxed);
             (StatusCode::OK, Json(serde_json::json!({
                 "success": true,
@@ -478,6 +586,91 @@ pub async fn clear_all_data(State(state): State<Arc<AppState>>) -> impl IntoResp
     }
 }
 
+#[derive(Deserialize)]
+pub struct CalendarQuery {
+    /// Defaults to the current year
+    pub year: Option<i32>,
+}
+
+pub async fn stats_calendar(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Query(q): Query<CalendarQuery>) -> impl IntoResponse {
+    let year = q.year.unwrap_or_else(|| chrono::Datelike::year(&chrono::Utc::now()));
+    if !(1800..=9999).contains(&year) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "year must be between 1800 and 9999"
+        }))).into_response();
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<crate::models::asset::CalendarStats> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::calendar_counts(&conn, year, principal.scope())
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(calendar)) => (StatusCode::OK, Json(calendar)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error getting calendar stats: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error getting calendar stats: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SlowestQuery {
+    /// Default 20, max 200
+    pub limit: Option<usize>,
+}
+
+/// The files that took longest to process since startup or the last stats reset, with time per stage
+pub async fn stats_slowest(State(state): State<Arc<AppState>>, Query(q): Query<SlowestQuery>) -> impl IntoResponse {
+    let slowest = state.stats.slowest_assets(q.limit.unwrap_or(20).clamp(1, 200));
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<serde_json::Value>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let mut stmt = conn.prepare("SELECT id FROM assets WHERE path = ?1")?;
+            slowest
+                .into_iter()
+                .map(|cost| {
+                    let asset_id: Option<i64> = stmt.query_row([&cost.path], |r| r.get(0)).optional()?;
+                    Ok(serde_json::json!({
+                        "asset_id": asset_id,
+                        "path": cost.path,
+                        "total_ms": cost.total_ms,
+                        "stages": cost.stages,
+                    }))
+                })
+                .collect()
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(items)) => (StatusCode::OK, Json(serde_json::json!({ "items": items }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error listing slowest assets: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error listing slowest assets: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
 pub async fn reset_stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     // Check if any path is currently scanning
     let any_scanning = state.path_scan_running.lock()
@@ -505,42 +698,140 @@ pub struct ListQuery {
     limit: Option<i64>,
     sort: Option<String>,
     order: Option<String>,
+    favorite: Option<bool>,
+    collapse_stacks: Option<bool>,
+    /// Include media received through messaging apps (default: the `received_media` setting)
+    include_received: Option<bool>,
     #[cfg(feature = "facial-recognition")]
     person_id: Option<i64>,
 }
 
-pub async fn assets(State(state): State<Arc<AppState>>, Query(q): Query<ListQuery>) -> impl IntoResponse {
+pub async fn assets(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Query(q): Query<ListQuery>) -> impl IntoResponse {
     let offset = q.offset.unwrap_or(0);
     let limit = q.limit.unwrap_or(200);
     let sort = q.sort.unwrap_or_else(|| "none".to_string());
     let order = q.order.unwrap_or_else(|| "desc".to_string());
+    let user_id = principal.user_id();
+    let filters = crate::db::query::ListFilters {
+        favorite: q.favorite,
+        user_id,
+        collapse_stacks: q.collapse_stacks.unwrap_or(false),
+        hide_received: !q.include_received.unwrap_or(!state.received_media.read().hide_in_timeline),
+        ingested_since: None,
+        scope: principal.scope(),
+    };
     #[cfg(feature = "facial-recognition")]
     let person_id = q.person_id;
     let pool = state.pool.clone();
     let res = tokio::task::spawn_blocking(move || {
         let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
         #[cfg(feature = "facial-recognition")]
-        {
-            if let Some(pid) = person_id {
-                crate::db::query::list_assets_by_person(&conn, pid, offset, limit, &sort, &order).map_err(|e| anyhow::anyhow!(e.to_string()))
-            } else {
-                crate::db::query::list_assets(&conn, offset, limit, &sort, &order).map_err(|e| anyhow::anyhow!(e.to_string()))
-            }
-        }
+        let page = match person_id {
+            Some(pid) => crate::db::query::list_assets_by_person(&conn, pid, offset, limit, &sort, &order, filters),
+            None => crate::db::query::list_assets(&conn, offset, limit, &sort, &order, filters),
+        };
         #[cfg(not(feature = "facial-recognition"))]
-        {
-            crate::db::query::list_assets(&conn, offset, limit, &sort, &order).map_err(|e| anyhow::anyhow!(e.to_string()))
-        }
+        let page = crate::db::query::list_assets(&conn, offset, limit, &sort, &order, filters);
+        let mut page = page.map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        crate::db::query::mark_user_favorites(&conn, user_id, &mut page.items)?;
+        Ok::<_, anyhow::Error>(page)
     }).await;
     match res { Ok(Ok(p)) => (StatusCode::OK, Json(p)).into_response(), _ => StatusCode::INTERNAL_SERVER_ERROR.into_response() }
 }
 
 #[derive(Deserialize)]
-pub struct SearchQuery { q: String, from: Option<i64>, to: Option<i64>, camera_make: Option<String>, camera_model: Option<String>, platform_type: Option<String>, offset: Option<i64>, limit: Option<i64> }
+pub struct NewAssetsQuery {
+    /// Unix seconds, typically the `until` of the previous call
+    since: Option<i64>,
+    limit: Option<i64>,
+}
+
+/// How many assets were indexed after `since`, with the newest of them, so a client can badge
+/// what arrived since it was last opened
+pub async fn new_assets(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Query(q): Query<NewAssetsQuery>) -> impl IntoResponse {
+    let Some(since) = q.since else {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "since is required"
+        }))).into_response();
+    };
+    let limit = q.limit.unwrap_or(50).clamp(0, 500);
+    let user_id = principal.user_id();
+    let filters = crate::db::query::ListFilters {
+        user_id,
+        hide_received: state.received_media.read().hide_in_timeline,
+        ingested_since: Some(since),
+        scope: principal.scope(),
+        ..Default::default()
+    };
+    // Timestamps are whole seconds: going a second back counts assets indexed later in this one
+    // again on the next call rather than missing them
+    let until = chrono::Utc::now().timestamp() - 1;
+    let pool = state.pool.clone();
+    let res = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+        let conn = pool.get()?;
+        let mut page = crate::db::query::list_assets(&conn, 0, limit, "ingested_at", "desc", filters)?;
+        crate::db::query::mark_user_favorites(&conn, user_id, &mut page.items)?;
+        Ok(page)
+    })
+    .await;
+    match res {
+        Ok(Ok(page)) => Json(serde_json::json!({
+            "since": since,
+            "until": until,
+            "count": page.total,
+            "items": page.items
+        })).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Database error listing new assets: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error listing new assets: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    q: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    camera_make: Option<String>,
+    camera_model: Option<String>,
+    platform_type: Option<String>,
+    /// Comma-separated tag names
+    tag: Option<String>,
+    tag_match: Option<crate::db::query::MatchMode>,
+    /// Comma-separated person ids
+    person_id: Option<String>,
+    person_match: Option<crate::db::query::MatchMode>,
+    place: Option<String>,
+    sort: Option<String>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+}
 
-pub async fn assets_search(State(state): State<Arc<AppState>>, Query(qs): Query<SearchQuery>) -> impl IntoResponse {
+pub async fn assets_search(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Query(qs): Query<SearchQuery>) -> impl IntoResponse {
     let offset = qs.offset.unwrap_or(0);
     let limit = qs.limit.unwrap_or(200);
+    let mut person_ids = Vec::new();
+    for part in qs.person_id.as_deref().unwrap_or_default().split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.parse::<i64>() {
+            Ok(id) => person_ids.push(id),
+            Err(_) => {
+                return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                    "error": format!("Invalid person id '{}'", part)
+                }))).into_response();
+            }
+        }
+    }
+    let tags: Vec<String> = qs.tag.as_deref().unwrap_or_default().split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
     let pool = state.pool.clone();
     let res = tokio::task::spawn_blocking(move || {
         let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
@@ -551,31 +842,48 @@ pub async fn assets_search(State(state): State<Arc<AppState>>, Query(qs): Query<
             camera_make: qs.camera_make.as_deref(),
             camera_model: qs.camera_model.as_deref(),
             platform_type: qs.platform_type.as_deref(),
+            tags: &tags,
+            tag_match: qs.tag_match.unwrap_or_default(),
+            person_ids: &person_ids,
+            person_match: qs.person_match.unwrap_or_default(),
+            place: qs.place.as_deref(),
+            scope: principal.scope(),
+            relevance: qs.sort.as_deref() == Some("relevance"),
             offset,
             limit,
         };
-        crate::db::query::search_assets(&conn, &search_params).map_err(|e| anyhow::anyhow!(e.to_string()))
+        let mut result = crate::db::query::search_assets(&conn, &search_params).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        crate::db::query::mark_user_favorites(&conn, principal.user_id(), &mut result.items)?;
+        Ok::<_, anyhow::Error>(result)
     }).await;
     match res { Ok(Ok(p)) => (StatusCode::OK, Json(p)).into_response(), _ => StatusCode::INTERNAL_SERVER_ERROR.into_response() }
 }
 
-pub async fn thumb_256(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+#[derive(Deserialize)]
+pub struct ThumbQuery {
+    /// Serve the animated hover preview for videos when one was generated
+    pub animated: Option<bool>,
+}
+
+pub async fn thumb_256(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Query(q): Query<ThumbQuery>) -> impl IntoResponse {
     let derived_dir = state.paths.data.join("derived");
     // No longer need scan_running check for thumbnails - per-path scans don't block thumbnails
-    serve_derived(state.clone(), id, derived_dir, None, 256).await
+    serve_derived(state.clone(), id, derived_dir, q.animated.unwrap_or(false), 256).await
 }
 
 pub async fn preview_1600(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
     let derived_dir = state.paths.data.join("derived");
     // No longer need scan_running check for previews - per-path scans don't block previews
-    serve_derived(state.clone(), id, derived_dir, None, 1600).await
+    serve_derived(state.clone(), id, derived_dir, false, 1600).await
 }
 
-pub async fn get_asset(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+pub async fn get_asset(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Path(id): Path<i64>) -> impl IntoResponse {
     let pool = state.pool.clone();
     let res = tokio::task::spawn_blocking(move || {
         let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-        crate::db::query::get_asset_by_id(&conn, id).map_err(|e| anyhow::anyhow!(e.to_string()))
+        let mut asset = crate::db::query::get_asset_by_id(&conn, id).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        crate::db::query::mark_user_favorites(&conn, principal.user_id(), asset.as_mut_slice())?;
+        Ok::<_, anyhow::Error>(asset)
     }).await;
     match res {
         Ok(Ok(Some(asset))) => (StatusCode::OK, Json(asset)).into_response(),
@@ -584,7 +892,80 @@ pub async fn get_asset(State(state): State<Arc<AppState>>, Path(id): Path<i64>)
     }
 }
 
-async fn serve_derived(state: Arc<AppState>, id: i64, derived_dir: std::path::PathBuf, _flag: Option<std::sync::Arc<std::sync::atomic::AtomicBool>>, size: i32) -> impl IntoResponse {
+#[derive(Deserialize)]
+pub struct CompareQuery {
+    /// Comma-separated asset ids, e.g. `12,40`
+    pub ids: String,
+}
+
+/// Most assets one comparison accepts
+const MAX_COMPARE: usize = 10;
+
+pub async fn compare_assets(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(q): Query<CompareQuery>,
+) -> impl IntoResponse {
+    let mut ids: Vec<i64> = Vec::new();
+    for part in q.ids.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.parse::<i64>() {
+            Ok(id) if !ids.contains(&id) => ids.push(id),
+            Ok(_) => {}
+            Err(_) => {
+                return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                    "error": format!("Invalid asset id '{}'", part)
+                }))).into_response();
+            }
+        }
+    }
+    if ids.len() < 2 || ids.len() > MAX_COMPARE {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("Provide between 2 and {} distinct asset ids", MAX_COMPARE)
+        }))).into_response();
+    }
+
+    let scope = principal.scope();
+    let user_id = principal.user_id();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<std::result::Result<Vec<crate::models::asset::Asset>, Vec<i64>>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let mut assets = Vec::with_capacity(ids.len());
+            let mut missing = Vec::new();
+            for id in ids {
+                let asset = if db::query::can_view_asset(&conn, id, scope)? { db::query::get_asset_by_id(&conn, id)? } else { None };
+                match asset {
+                    Some(asset) => assets.push(asset),
+                    None => missing.push(id),
+                }
+            }
+            db::query::mark_user_favorites(&conn, user_id, &mut assets)?;
+            Ok(if missing.is_empty() { Ok(assets) } else { Err(missing) })
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Ok(assets))) => Json(crate::models::asset::AssetComparison::new(assets)).into_response(),
+        Ok(Ok(Err(missing))) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset not found",
+            "missing": missing
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error comparing assets: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error comparing assets: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+async fn serve_derived(state: Arc<AppState>, id: i64, derived_dir: std::path::PathBuf, animated: bool, size: i32) -> impl IntoResponse {
     let info = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         move || { let conn = pool.get().ok(); conn.and_then(|c| crate::db::query::get_thumb_info(&c, id).ok()) }
@@ -592,14 +973,26 @@ async fn serve_derived(state: Arc<AppState>, id: i64, derived_dir: std::path::Pa
     if let Some((Some(sha_hex), _mime)) = info {
         if sha_hex.len() >= 2 {
             let sub = &sha_hex[0..2];
-            let path = derived_dir.join(sub).join(format!("{}-{}.webp", sha_hex, size));
+            let mut path = derived_dir.join(sub).join(format!("{}-{}.webp", sha_hex, size));
+            // Fall back to the static thumb when no animated preview exists (yet), without caching it
+            let mut cache_control = "public, max-age=31536000, immutable";
+            if animated {
+                let anim = crate::pipeline::thumb::anim_path(&derived_dir, &sha_hex);
+                if tokio::fs::try_exists(&anim).await.unwrap_or(false) {
+                    path = anim;
+                } else {
+                    cache_control = "no-cache";
+                }
+            }
             if let Ok(bytes) = tokio::fs::read(&path).await {
                 let mut resp = axum::http::Response::builder().status(StatusCode::OK);
                 let headers = resp.headers_mut().unwrap();
                 headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("image/webp"));
-                headers.insert(header::CACHE_CONTROL, header::HeaderValue::from_static("public, max-age=31536000, immutable"));
+                headers.insert(header::CACHE_CONTROL, header::HeaderValue::from_static(cache_control));
+                state.stats.inc_thumb_cache(true);
                 return resp.body(axum::body::Body::from(bytes)).unwrap();
             }
+            state.stats.inc_thumb_cache(false);
         }
     }
     StatusCode::NOT_FOUND.into_response()
@@ -608,11 +1001,24 @@ async fn serve_derived(state: Arc<AppState>, id: i64, derived_dir: std::path::Pa
 pub async fn metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     let mut text = state.stats.metrics_text();
     let d = state.gauges.depths();
-    text.push_str(&format!("seen_queue_discover {}\n", d.discover));
-    text.push_str(&format!("seen_queue_hash {}\n", d.hash));
-    text.push_str(&format!("seen_queue_metadata {}\n", d.metadata));
-    text.push_str(&format!("seen_queue_db_write {}\n", d.db_write));
-    text.push_str(&format!("seen_queue_thumb {}\n", d.thumb));
+    for (name, depth) in [
+        ("seen_queue_discover", d.discover),
+        ("seen_queue_hash", d.hash),
+        ("seen_queue_metadata", d.metadata),
+        ("seen_queue_db_write", d.db_write),
+        ("seen_queue_thumb", d.thumb),
+        ("seen_queue_transcode", d.transcode),
+    ] {
+        text.push_str(&format!("# TYPE {} gauge\n{} {}\n", name, name, depth));
+    }
+    let gpu = crate::utils::ffmpeg::get_gpu_stats();
+    text.push_str("# HELP seen_ffmpeg_jobs_total ffmpeg jobs run with hardware acceleration (gpu) or in software (cpu)\n# TYPE seen_ffmpeg_jobs_total counter\n");
+    text.push_str(&format!("seen_ffmpeg_jobs_total{{device=\"gpu\"}} {}\n", gpu.jobs_gpu));
+    text.push_str(&format!("seen_ffmpeg_jobs_total{{device=\"cpu\"}} {}\n", gpu.jobs_cpu));
+    text.push_str("# HELP seen_gpu_consecutive_failures GPU jobs failed in a row\n# TYPE seen_gpu_consecutive_failures gauge\n");
+    text.push_str(&format!("seen_gpu_consecutive_failures {}\n", gpu.consecutive_failures));
+    text.push_str("# HELP seen_gpu_auto_disabled 1 while hardware acceleration is off after repeated failures\n# TYPE seen_gpu_auto_disabled gauge\n");
+    text.push_str(&format!("seen_gpu_auto_disabled {}\n", gpu.auto_disabled as u8));
     axum::http::Response::builder().status(StatusCode::OK).header(header::CONTENT_TYPE, "text/plain; version=0.0.4").body(axum::body::Body::from(text)).unwrap()
 }
 
@@ -740,23 +1146,47 @@ pub async fn performance(State(state): State<Arc<AppState>>) -> impl IntoRespons
 #[derive(Deserialize)]
 pub struct AddPathReq {
     path: String,
+    /// Soft limits applied from the first scan, see `PUT /paths/quota`
+    #[serde(default)]
+    max_assets: Option<u64>,
+    #[serde(default)]
+    max_bytes: Option<u64>,
 }
 
-pub async fn get_scan_paths(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+pub async fn get_scan_paths(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>) -> impl IntoResponse {
     let default_root = state.paths.root.to_string_lossy().to_string();
     let default_root_host = state.paths.root_host.clone();
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         move || {
             let conn = pool.get().ok()?;
-            db::query::get_scan_paths(&conn).ok()
+            let paths = db::query::get_scan_paths_with_flags(&conn, principal.scope()).ok()?;
+            let schedules = db::query::get_scan_path_schedules(&conn).ok()?;
+            let filters = db::query::get_scan_path_filters(&conn).ok()?;
+            let archived = db::query::get_archived_scan_paths(&conn).ok()?;
+            let quotas = db::query::get_scan_path_quotas(&conn).ok()?;
+            Some((paths, schedules, filters, archived, quotas))
         }
     }).await.ok().flatten();
+    let date_priorities = state.date_priorities.read().clone();
 
     match result {
-        Some(paths) => {
+        Some((paths, schedules, filters, archived, quotas)) => {
+            let now = chrono::Local::now();
             // Return only the configured paths, flagging the default root when present
-            let response: Vec<serde_json::Value> = paths.iter().map(|path| {
+            let response: Vec<serde_json::Value> = paths.iter().map(|(path, read_only)| {
+                let schedule = schedules.iter().find(|(p, _, _)| p == path).map(|(_, expr, _)| expr.clone());
+                let next_scan_at = schedule.as_deref()
+                    .and_then(|expr| scheduler::Schedule::parse(expr).ok())
+                    .and_then(|parsed| scheduler::next_run_at(&parsed, now));
+                let (include, exclude) = filters.iter().find(|(p, _, _)| p == path)
+                    .map(|(_, include, exclude)| (include.clone(), exclude.clone()))
+                    .unwrap_or_default();
+                let archive = archived.iter().find(|(p, _)| p == path);
+                let date_priority = date_priorities.paths.iter().find(|(p, _)| p == path).map(|(_, priority)| priority);
+                let (quota, quota_hit) = quotas.iter().find(|(p, _, _)| p == path)
+                    .map(|(_, quota, hit)| (*quota, hit.clone()))
+                    .unwrap_or_default();
                 let is_default = path == &default_root;
                 let host_path = if is_default {
                     default_root_host.clone()
@@ -766,7 +1196,19 @@ pub async fn get_scan_paths(State(state): State<Arc<AppState>>) -> impl IntoResp
                 serde_json::json!({
                     "path": path,
                     "is_default": is_default,
-                    "host_path": host_path
+                    "host_path": host_path,
+                    "read_only": read_only,
+                    "schedule": schedule,
+                    "next_scan_at": next_scan_at,
+                    "include": include,
+                    "exclude": exclude,
+                    "archived": archive.is_some(),
+                    "archive_label": archive.and_then(|(_, label)| label.clone()),
+                    "date_priority": date_priority.unwrap_or(&date_priorities.default),
+                    "date_priority_default": date_priority.is_none(),
+                    "max_assets": quota.max_assets,
+                    "max_bytes": quota.max_bytes,
+                    "quota_hit": quota_hit
                 })
             }).collect();
             (StatusCode::OK, Json(serde_json::json!(response)))
@@ -775,21 +1217,48 @@ pub async fn get_scan_paths(State(state): State<Arc<AppState>>) -> impl IntoResp
     }
 }
 
-pub async fn add_scan_path(State(state): State<Arc<AppState>>, Json(req): Json<AddPathReq>) -> impl IntoResponse {
+pub async fn add_scan_path(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Json(req): Json<AddPathReq>) -> impl IntoResponse {
     use std::sync::atomic::Ordering;
 
-    let decoded_path = req.path.clone();
+    // `\\?\C:\...` and `C:\...` are the same scan path
+    let decoded_path = crate::utils::path::strip_verbatim(&req.path).into_owned();
+    let quota = match ScanQuota::new(req.max_assets, req.max_bytes) {
+        Ok(quota) => quota,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))),
+    };
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         let path = decoded_path.clone();
         move || {
+            let read_only = !crate::utils::path::is_dir_writable(StdPath::new(&path));
+            if read_only {
+                tracing::warn!("Scan path {} is read-only; rotate and delete-from-disk will be unavailable", path);
+            }
             let conn = pool.get().ok()?;
-            db::writer::add_scan_path(&conn, &path).ok()
+            // Re-adding or overlapping someone else's path would claim the assets indexed there
+            match db::query::scan_path_clash(&conn, &path, principal.scope()).ok()? {
+                Some(db::query::ScanPathClash::Exists) => return Some(Err("Path is already a scan path".to_string())),
+                Some(db::query::ScanPathClash::Overlaps(other)) => {
+                    return Some(Err(format!("Path overlaps scan path {}", other)));
+                }
+                None => {}
+            }
+            let id = db::writer::add_scan_path(&conn, &path).ok()?;
+            db::writer::set_scan_path_read_only(&conn, &path, read_only).ok()?;
+            // Paths added by a user account become part of that user's library
+            if let Some(user_id) = principal.user_id() {
+                db::writer::set_scan_path_owner(&conn, &path, user_id).ok()?;
+            }
+            if !quota.is_unlimited() {
+                db::writer::set_scan_path_quota(&conn, &path, &quota).ok()?;
+            }
+            Some(Ok(id))
         }
     }).await.ok().flatten();
 
     match result {
-        Some(_) => {
+        Some(Err(error)) => (StatusCode::CONFLICT, Json(serde_json::json!({ "error": error }))),
+        Some(Ok(_)) => {
             // Get or create per-path watcher_paused flag
             let path_watcher_paused = {
                 let mut map = state.path_watcher_paused.lock();
@@ -810,9 +1279,10 @@ pub async fn add_scan_path(State(state): State<Arc<AppState>>, Json(req): Json<A
                     let db_path = state.db_path.clone();
                     let stats = state.stats.clone();
                     let paused = path_watcher_paused.clone();
+                    let filter = path_filter(&state, &decoded_path);
 
                     let handle = tokio::spawn(async move {
-                        let _ = crate::pipeline::discover::watch(root, dtx, Some(db_path), g, Some(stats), Some(paused)).await;
+                        let _ = crate::pipeline::discover::watch(root, dtx, Some(db_path), g, Some(stats), Some(paused), Some(filter)).await;
                     });
                     watchers.insert(decoded_path.clone(), handle);
                 }
@@ -855,11 +1325,16 @@ pub async fn add_scan_path(State(state): State<Arc<AppState>>, Json(req): Json<A
             let path_scan_map = state.path_scan_running.clone();
             let global_scan_flag = state.scan_running.clone();
             let path_for_scan = decoded_path.clone();
+            let options = ScanOptions {
+                filter: path_filter(&state, &decoded_path).read().clone(),
+                cancel: Some(new_scan_cancel(&state, &decoded_path)),
+                ..Default::default()
+            };
+            let pool = state.pool.clone();
 
             tokio::spawn(async move {
                 info!("scan_start for path: {:?}", path_for_scan);
-                let root = std::path::PathBuf::from(&path_for_scan);
-                let _ = crate::pipeline::discover::scan_bfs(root, tx, gauges, scan_running.clone(), Some(stats.clone())).await;
+                run_path_scan(pool, path_for_scan.clone(), tx, gauges, scan_running.clone(), stats.clone(), options).await;
                 info!("scan_finish for path: {:?}", path_for_scan);
                 scan_running.store(false, Ordering::SeqCst);
 
@@ -890,11 +1365,41 @@ pub struct RemovePathQuery {
     path: String,
 }
 
-pub async fn remove_scan_path(State(state): State<Arc<AppState>>, Query(params): Query<RemovePathQuery>) -> impl IntoResponse {
+pub async fn remove_scan_path(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(params): Query<RemovePathQuery>,
+) -> impl IntoResponse {
     let path_to_remove = params.path.clone();
 
     tracing::info!(path = %path_to_remove, "remove_scan_path: received request to remove path");
 
+    // Users may only remove their own (or shared) scan paths
+    if principal.scope() != AccessScope::All {
+        let allowed = tokio::task::spawn_blocking({
+            let pool = state.pool.clone();
+            let path = path_to_remove.clone();
+            move || -> Result<bool> {
+                let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+                db::query::can_edit_scan_path(&conn, &path, principal.scope())
+            }
+        }).await;
+        match allowed {
+            Ok(Ok(true)) => {}
+            Ok(Ok(false)) => {
+                return (StatusCode::FORBIDDEN, Json(serde_json::json!({"error": "Not allowed"})));
+            }
+            Ok(Err(e)) => {
+                tracing::error!("Error checking scan path owner: {}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": format!("Database error: {}", e)})));
+            }
+            Err(e) => {
+                tracing::error!("Task error: {}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "Task error"})));
+            }
+        }
+    }
+
     // Stop scanning and pause watcher for this path
     {
         if let Some(scan_running) = state.path_scan_running.lock().get(&path_to_remove) {
@@ -903,6 +1408,10 @@ pub async fn remove_scan_path(State(state): State<Arc<AppState>>, Query(params):
         if let Some(watcher_paused) = state.path_watcher_paused.lock().get(&path_to_remove) {
             watcher_paused.store(true, std::sync::atomic::Ordering::SeqCst);
         }
+        // Files the scan queued for a path that is going away aren't indexed
+        if let Some(cancel) = state.path_scan_cancel.lock().remove(&path_to_remove) {
+            cancel.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
     }
 
     // Abort and remove watcher task
@@ -917,6 +1426,8 @@ pub async fn remove_scan_path(State(state): State<Arc<AppState>>, Query(params):
     {
         state.path_scan_running.lock().remove(&path_to_remove);
         state.path_watcher_paused.lock().remove(&path_to_remove);
+        state.path_filters.lock().remove(&path_to_remove);
+        state.date_priorities.write().paths.retain(|(p, _)| p != &path_to_remove);
     }
 
     // Recompute global scan flag
@@ -972,10 +1483,16 @@ pub struct PathActionReq {
     path: String,
 }
 
-pub async fn scan_path(State(state): State<Arc<AppState>>, Json(req): Json<PathActionReq>) -> impl IntoResponse {
-    use std::sync::atomic::Ordering;
+#[derive(Deserialize)]
+pub struct ScanPathReq {
+    path: String,
+    /// Re-hash and re-extract metadata for every file, not just new or changed ones
+    #[serde(default)]
+    force: bool,
+}
 
-    let decoded_path = req.path;
+pub async fn scan_path(State(state): State<Arc<AppState>>, Json(req): Json<ScanPathReq>) -> impl IntoResponse {
+    let decoded_path = crate::utils::path::strip_verbatim(&req.path).into_owned();
     let default_root = state.paths.root.to_string_lossy().to_string();
     let is_default_path = decoded_path == default_root;
 
@@ -990,6 +1507,11 @@ pub async fn scan_path(State(state): State<Arc<AppState>>, Json(req): Json<PathA
                 let conn = pool.get().ok()?;
                 let mut stmt = conn.prepare("SELECT 1 FROM scan_paths WHERE path = ?1").ok()?;
                 let exists = stmt.exists(params![path_check]).ok()?;
+                if exists {
+                    // Re-probe on rescan in case the volume was remounted
+                    let read_only = !crate::utils::path::is_dir_writable(StdPath::new(&path_check));
+                    let _ = db::writer::set_scan_path_read_only(&conn, &path_check, read_only);
+                }
                 Some(exists)
             }
         }).await.ok().flatten().unwrap_or(false)
@@ -1001,1639 +1523,3896 @@ pub async fn scan_path(State(state): State<Arc<AppState>>, Json(req): Json<PathA
         })));
     }
 
-    // Determine if this is the first active scan (used to start scan stats)
-    let was_scanning = {
-        let map = state.path_scan_running.lock();
-        map.values().any(|flag| flag.load(Ordering::Relaxed))
-    };
-
-    // Get or create per-path scan_running flag
-    let path_scan_running = {
-        let mut map = state.path_scan_running.lock();
-        map.entry(decoded_path.clone())
-            .or_insert_with(|| Arc::new(std::sync::atomic::AtomicBool::new(false)))
-            .clone()
-    };
+    let archived = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let path = decoded_path.clone();
+        move || {
+            let conn = pool.get().ok()?;
+            let archived = db::query::get_archived_scan_paths(&conn).ok()?;
+            Some(archived.iter().any(|(p, _)| *p == path))
+        }
+    }).await.ok().flatten().unwrap_or(false);
+    if archived {
+        // A scan of an unplugged drive finds nothing; bring the path back online first
+        return (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "Path is archived; mark it online before scanning"
+        })));
+    }
 
-    // Check if already scanning
-    if path_scan_running.swap(true, Ordering::SeqCst) {
+    if !start_path_scan(&state, decoded_path, req.force) {
         return (StatusCode::CONFLICT, Json(serde_json::json!({
             "error": "Path is already being scanned"
         })));
     }
 
-    // Get or create per-path watcher_paused flag
-    let path_watcher_paused = {
-        let mut map = state.path_watcher_paused.lock();
-        map.entry(decoded_path.clone())
-            .or_insert_with(|| Arc::new(std::sync::atomic::AtomicBool::new(false)))
-            .clone()
-    };
-    // Ensure watcher is marked as active when starting/resuming scan
-    path_watcher_paused.store(false, Ordering::SeqCst);
+    (StatusCode::ACCEPTED, Json(serde_json::json!({
+        "success": true,
+        "message": "Scan started for path"
+    })))
+}
 
-    // Start watcher if not already running
-    {
-        let mut watchers = state.path_watchers.lock();
-        if !watchers.contains_key(&decoded_path) {
-            let root = std::path::PathBuf::from(&decoded_path);
-            let dtx = state.queues.discover_tx.clone();
-            let g = state.gauges.clone();
-            let db_path = state.db_path.clone();
-            let stats = state.stats.clone();
-            let paused = path_watcher_paused.clone();
+#[derive(Deserialize)]
+pub struct ScheduleReq {
+    path: String,
+    /// Cron expression such as `0 2 * * *`; null removes the schedule
+    schedule: Option<String>,
+}
 
-            let handle = tokio::spawn(async move {
-                let _ = crate::pipeline::discover::watch(root, dtx, Some(db_path), g, Some(stats), Some(paused)).await;
-            });
-            watchers.insert(decoded_path.clone(), handle);
+pub async fn set_path_schedule(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<ScheduleReq>,
+) -> impl IntoResponse {
+    let schedule = req.schedule.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let next_scan_at = match schedule.as_deref().map(scheduler::Schedule::parse) {
+        Some(Ok(parsed)) => scheduler::next_run_at(&parsed, chrono::Local::now()),
+        Some(Err(e)) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("Invalid schedule: {}", e)
+            }))).into_response();
         }
-    }
+        None => None,
+    };
 
-    // If no other scans were running, start global scan stats
-    if !was_scanning {
-        state.stats.start_scan();
-    }
-    state.scan_running.store(true, Ordering::SeqCst);
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let path = req.path.clone();
+        let schedule = schedule.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_edit_scan_path(&conn, &path, principal.scope())? {
+                return Ok(false);
+            }
+            db::writer::set_scan_path_schedule(&conn, &path, schedule.as_deref())
+        }
+    }).await;
 
-    // Start scan for this path
-    let tx = state.queues.discover_tx.clone();
-    let gauges = state.gauges.clone();
-    let scan_running = path_scan_running.clone();
-    let stats = state.stats.clone();
-    let path_scan_map = state.path_scan_running.clone();
-    let global_scan_flag = state.scan_running.clone();
-    let path_for_scan = decoded_path.clone();
+    match result {
+        Ok(Ok(true)) => Json(serde_json::json!({
+            "success": true,
+            "path": req.path,
+            "schedule": schedule,
+            "next_scan_at": next_scan_at
+        })).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Path not found in scan paths"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error setting schedule for {}: {}", req.path, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error setting scan schedule: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
 
-    tokio::spawn(async move {
-        info!("scan_start for path: {:?}", path_for_scan);
-        let root = std::path::PathBuf::from(&path_for_scan);
-        let _ = crate::pipeline::discover::scan_bfs(root, tx, gauges, scan_running.clone(), Some(stats.clone())).await;
-        info!("scan_finish for path: {:?}", path_for_scan);
-        scan_running.store(false, Ordering::SeqCst);
+#[derive(Deserialize)]
+pub struct ArchivePathReq {
+    path: String,
+    /// True when the originals have moved to cold storage, false once they are back online
+    archived: bool,
+    /// Where the originals are, e.g. "Backup drive B"
+    label: Option<String>,
+}
 
-        // If no scans remain active, finalize statistics
-        let any_active = path_scan_map.lock()
-            .values()
-            .any(|flag| flag.load(Ordering::Relaxed));
-        if !any_active {
-            stats.finish_processing();
-            stats.finish_scan();
-            global_scan_flag.store(false, Ordering::SeqCst);
-        } else {
-            global_scan_flag.store(true, Ordering::SeqCst);
+/// Mark a scan path's originals as archived to cold storage: metadata, thumbnails and search keep
+/// working, scanning and watching stop, and original downloads report the drive as offline
+pub async fn set_path_archived(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<ArchivePathReq>,
+) -> impl IntoResponse {
+    let label = req.label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty() && req.archived);
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let path = req.path.clone();
+        let label = label.clone();
+        move || -> Result<Option<usize>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_edit_scan_path(&conn, &path, principal.scope())? {
+                return Ok(None);
+            }
+            db::writer::set_scan_path_archived(&conn, &path, req.archived, label.as_deref())
         }
-    });
+    }).await;
 
-    (StatusCode::ACCEPTED, Json(serde_json::json!({
-        "success": true,
-        "message": "Scan started for path"
-    })))
+    match result {
+        Ok(Ok(Some(assets))) => {
+            // An unplugged drive must not look like a mass deletion to the watcher
+            if req.archived {
+                pause_scan_path(&state, &req.path);
+            } else {
+                resume_scan_path(&state, &req.path);
+            }
+            Json(serde_json::json!({
+                "success": true,
+                "path": req.path,
+                "archived": req.archived,
+                "archive_label": label,
+                "assets": assets
+            })).into_response()
+        }
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Path not found in scan paths"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error archiving {}: {}", req.path, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error archiving scan path: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
 }
 
-pub async fn pause_path(State(state): State<Arc<AppState>>, Json(req): Json<PathActionReq>) -> impl IntoResponse {
-    use std::sync::atomic::Ordering;
-
-    let decoded_path = req.path;
+#[derive(Deserialize)]
+pub struct RelocatePathReq {
+    path: String,
+    /// Where the library is mounted now
+    new_path: String,
+}
 
-    // Stop scanning for this path
-    if let Some(scan_running) = state.path_scan_running.lock().get(&decoded_path) {
-        scan_running.store(false, Ordering::SeqCst);
+/// Point a scan path at the library's new mount point. Assets keep their ids, albums,
+/// tags and faces; only the scan path row changes and the stored paths follow it.
+pub async fn relocate_path(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<RelocatePathReq>,
+) -> impl IntoResponse {
+    if !StdPath::new(&req.new_path).is_dir() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("{} is not a directory", req.new_path)
+        }))).into_response();
+    }
+    let scanning = state.path_scan_running.lock().get(&req.path)
+        .is_some_and(|flag| flag.load(std::sync::atomic::Ordering::Relaxed));
+    if scanning {
+        return (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "Path is being scanned; pause it first"
+        }))).into_response();
     }
 
-    // Pause watcher for this path
-    let watcher_paused = {
-        let mut map = state.path_watcher_paused.lock();
-        map.entry(decoded_path.clone())
-            .or_insert_with(|| Arc::new(std::sync::atomic::AtomicBool::new(false)))
-            .clone()
-    };
-    watcher_paused.store(true, Ordering::SeqCst);
-
-    // Recompute global scan flag
-    let any_active = state.path_scan_running.lock()
-        .values()
-        .any(|flag| flag.load(Ordering::Relaxed));
-    state.scan_running.store(any_active, Ordering::SeqCst);
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let (path, new_path) = (req.path.clone(), req.new_path.clone());
+        move || -> Result<Option<i64>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_edit_scan_path(&conn, &path, principal.scope())? {
+                return Ok(None);
+            }
+            db::writer::relocate_scan_path(&conn, &path, &new_path)
+        }
+    }).await;
 
-    (StatusCode::OK, Json(serde_json::json!({
-        "success": true,
-        "message": "Path paused"
-    })))
+    match result {
+        Ok(Ok(Some(assets))) => {
+            // The old watcher is looking at a directory that is gone
+            if let Some(handle) = state.path_watchers.lock().remove(&req.path) {
+                handle.abort();
+            }
+            state.path_scan_running.lock().remove(&req.path);
+            state.path_watcher_paused.lock().remove(&req.path);
+            state.path_filters.lock().remove(&req.path);
+            for (p, _) in state.date_priorities.write().paths.iter_mut().filter(|(p, _)| p == &req.path) {
+                *p = req.new_path.clone();
+            }
+            // Unchanged files are skipped, so this only re-attaches the watcher and picks up changes
+            start_path_scan(&state, req.new_path.clone(), false);
+            tracing::info!("scan path relocated: {} -> {} ({} assets)", req.path, req.new_path, assets);
+            Json(serde_json::json!({
+                "success": true,
+                "path": req.new_path,
+                "previous_path": req.path,
+                "assets": assets
+            })).into_response()
+        }
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Path not found in scan paths"
+        }))).into_response(),
+        Ok(Err(e)) if e.to_string().contains("UNIQUE constraint") => (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "Assets or a scan path already exist at the new location"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error relocating {}: {}", req.path, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error relocating scan path: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
 }
 
-pub async fn resume_path(State(state): State<Arc<AppState>>, Json(req): Json<PathActionReq>) -> impl IntoResponse {
-    use std::sync::atomic::Ordering;
-
-    let decoded_path = req.path;
+#[derive(Deserialize)]
+pub struct FiltersReq {
+    path: String,
+    /// Globs a file must match one of to be indexed; empty indexes everything
+    #[serde(default)]
+    include: Vec<String>,
+    /// Globs for files and directories to skip, e.g. `@eaDir` or `*.tmp`
+    #[serde(default)]
+    exclude: Vec<String>,
+}
 
-    // Resume watcher for this path
-    let watcher_paused = {
-        let mut map = state.path_watcher_paused.lock();
-        map.entry(decoded_path.clone())
-            .or_insert_with(|| Arc::new(std::sync::atomic::AtomicBool::new(false)))
-            .clone()
+pub async fn set_path_filters(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<FiltersReq>,
+) -> impl IntoResponse {
+    let clean = |patterns: Vec<String>| -> Vec<String> {
+        patterns.into_iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect()
+    };
+    let include = clean(req.include);
+    let exclude = clean(req.exclude);
+    let filter = match PathFilter::new(StdPath::new(&req.path), &include, &exclude) {
+        Ok(filter) => filter,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("Invalid pattern: {}", e)
+            }))).into_response();
+        }
     };
-    watcher_paused.store(false, Ordering::SeqCst);
-
-    (StatusCode::OK, Json(serde_json::json!({
-        "success": true,
-        "message": "Path resumed"
-    })))
-}
 
-pub async fn get_path_status(State(state): State<Arc<AppState>>, Query(params): Query<PathActionReq>) -> impl IntoResponse {
-    use std::sync::atomic::Ordering;
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let path = req.path.clone();
+        let (include, exclude) = (include.clone(), exclude.clone());
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_edit_scan_path(&conn, &path, principal.scope())? {
+                return Ok(false);
+            }
+            db::writer::set_scan_path_filters(&conn, &path, &include, &exclude)
+        }
+    }).await;
 
-    let decoded_path = params.path;
+    match result {
+        Ok(Ok(true)) => {
+            // A running watcher picks the new globs up immediately; scans use them from their next run
+            if let Some(shared) = state.path_filters.lock().get(&req.path) {
+                *shared.write() = filter;
+            }
+            Json(serde_json::json!({
+                "success": true,
+                "path": req.path,
+                "include": include,
+                "exclude": exclude
+            })).into_response()
+        }
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Path not found in scan paths"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error setting filters for {}: {}", req.path, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error setting scan path filters: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
 
-    let scanning = state.path_scan_running.lock()
-        .get(&decoded_path)
-        .map(|flag| flag.load(Ordering::Relaxed))
-        .unwrap_or(false);
+#[derive(Deserialize)]
+pub struct DatePriorityReq {
+    path: String,
+    /// Sources in order of preference; null or absent returns the path to the default order
+    #[serde(default)]
+    priority: Option<Vec<String>>,
+}
 
-    let watcher_paused = state.path_watcher_paused.lock()
-        .get(&decoded_path)
-        .map(|flag| flag.load(Ordering::Relaxed))
-        .unwrap_or(false);
+pub async fn set_path_date_priority(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<DatePriorityReq>,
+) -> impl IntoResponse {
+    let priority = match req.priority.as_deref().map(dates::parse_priority).transpose() {
+        Ok(priority) => priority,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response();
+        }
+    };
 
-    let watching = state.path_watchers.lock().contains_key(&decoded_path);
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let path = req.path.clone();
+        let saved = priority.as_deref().map(dates::format_priority);
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_edit_scan_path(&conn, &path, principal.scope())? {
+                return Ok(false);
+            }
+            db::writer::set_scan_path_date_priority(&conn, &path, saved.as_deref())
+        }
+    }).await;
 
-    (StatusCode::OK, Json(serde_json::json!({
-        "scanning": scanning,
-        "watcher_paused": watcher_paused,
-        "watching": watching
-    })))
+    match result {
+        Ok(Ok(true)) => {
+            // Files indexed from now on use the new order; existing assets keep their dates
+            let mut shared = state.date_priorities.write();
+            shared.paths.retain(|(p, _)| p != &req.path);
+            if let Some(priority) = &priority {
+                shared.paths.push((req.path.clone(), priority.clone()));
+            }
+            Json(serde_json::json!({
+                "success": true,
+                "path": req.path,
+                "date_priority": priority.as_ref().unwrap_or(&shared.default),
+                "date_priority_default": priority.is_none()
+            })).into_response()
+        }
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Path not found in scan paths"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error setting date priority for {}: {}", req.path, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error setting scan path date priority: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
 }
 
-pub async fn diag_ffmpeg() -> impl IntoResponse {
-    use std::process::Command;
+#[derive(Deserialize)]
+pub struct QuotaReq {
+    path: String,
+    /// Null or absent for no limit
+    #[serde(default)]
+    max_assets: Option<u64>,
+    #[serde(default)]
+    max_bytes: Option<u64>,
+}
 
-    let mut info = serde_json::json!({
-        "ffmpeg_version": "unknown",
-        "hwaccels": [],
-        "filters": [],
-        "gpu_config": {}
-    });
+pub async fn set_path_quota(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<QuotaReq>,
+) -> impl IntoResponse {
+    let quota = match ScanQuota::new(req.max_assets, req.max_bytes) {
+        Ok(quota) => quota,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response(),
+    };
 
-    // Get ffmpeg version
-    if let Ok(output) = Command::new("ffmpeg").args(["-version"]).output() {
-        if output.status.success() {
-            let version_str = String::from_utf8_lossy(&output.stdout);
-            let first_line = version_str.lines().next().unwrap_or("unknown");
-            info["ffmpeg_version"] = serde_json::Value::String(first_line.to_string());
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let path = req.path.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_edit_scan_path(&conn, &path, principal.scope())? {
+                return Ok(false);
+            }
+            db::writer::set_scan_path_quota(&conn, &path, &quota)
         }
-    }
+    }).await;
 
-    // Get available hardware accelerators
-    if let Ok(output) = Command::new("ffmpeg").args(["-hide_banner", "-hwaccels"]).output() {
-        if output.status.success() {
-            let hwaccels_str = String::from_utf8_lossy(&output.stdout);
-            let accels: Vec<&str> = hwaccels_str
-                .lines()
-                .skip(1) // Skip header line
-                .filter(|l| !l.trim().is_empty())
-                .map(|l| l.trim())
-                .collect();
-            info["hwaccels"] = serde_json::Value::Array(
-                accels.iter().map(|a| serde_json::Value::String(a.to_string())).collect()
-            );
+    match result {
+        // Applies from the next scan; what earlier scans indexed stays
+        Ok(Ok(true)) => Json(serde_json::json!({
+            "success": true,
+            "path": req.path,
+            "max_assets": quota.max_assets,
+            "max_bytes": quota.max_bytes
+        })).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Path not found in scan paths"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error setting quota for {}: {}", req.path, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
         }
-    }
-
-    // Get available filters (check for GPU scaling filters)
-    if let Ok(output) = Command::new("ffmpeg").args(["-hide_banner", "-filters"]).output() {
-        if output.status.success() {
-            let filters_str = String::from_utf8_lossy(&output.stdout);
-            let gpu_filters = ["scale_cuda", "scale_npp", "scale_qsv"];
-            let found: Vec<String> = gpu_filters
-                .iter()
-                .filter(|f| filters_str.contains(*f))
-                .map(|f| f.to_string())
-                .collect();
-            info["filters"] = serde_json::Value::Array(
-                found.iter().map(|f| serde_json::Value::String(f.clone())).collect()
-            );
+        Err(e) => {
+            tracing::error!("Task error setting scan path quota: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
         }
     }
+}
 
-    // Get current GPU config
-    let gpu_config = crate::utils::ffmpeg::get_gpu_config();
-    let accel_str = match gpu_config.accel {
-        crate::utils::ffmpeg::GpuAccel::Cuda => "CUDA",
-        crate::utils::ffmpeg::GpuAccel::Qsv => "QSV",
-        crate::utils::ffmpeg::GpuAccel::D3d11va => "D3D11VA",
-        crate::utils::ffmpeg::GpuAccel::VideoToolbox => "VideoToolbox",
-        crate::utils::ffmpeg::GpuAccel::Cpu => "CPU",
-    };
-    let gpu_stats = crate::utils::ffmpeg::get_gpu_stats();
-
-    // Get device counts for diagnostics
-    let cuda_devices = crate::utils::ffmpeg::check_cuda_devices();
-    let intel_gpu = crate::utils::ffmpeg::check_intel_gpu_devices();
-    let opencl_devices = crate::utils::ffmpeg::check_opencl_devices();
-
-    info["gpu_config"] = serde_json::json!({
-        "accel": accel_str,
-        "enabled": gpu_config.enabled && !gpu_stats.auto_disabled,
-        "consecutive_failures": gpu_stats.consecutive_failures,
-        "auto_disabled": gpu_stats.auto_disabled,
-        "device_counts": {
-            "cuda": cuda_devices,
-            "intel_gpu": intel_gpu,
-            "opencl": opencl_devices,
-        },
-    });
-
-    (StatusCode::OK, Json(info))
+/// A fresh cancel flag for a path's next scan
+fn new_scan_cancel(state: &AppState, path: &str) -> Arc<std::sync::atomic::AtomicBool> {
+    let cancel = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    state.path_scan_cancel.lock().insert(path.to_string(), cancel.clone());
+    cancel
 }
 
-pub async fn stream_video(State(state): State<Arc<AppState>>, Path(id): Path<i64>, headers: HeaderMap) -> impl IntoResponse {
-    // Get asset path, MIME type, and codec from database
-    let (file_path, mime_str, video_codec) = match tokio::task::spawn_blocking({
-        let pool = state.pool.clone();
+/// One scan of a path under its quota, saving afterwards whether the quota stopped it and adding
+/// it to the scan history. A scan paused or canceled midway leaves the last quota report as it was.
+async fn run_path_scan(
+    pool: db::DbPool,
+    path: String,
+    tx: tokio::sync::mpsc::Sender<crate::pipeline::discover::DiscoverItem>,
+    gauges: Arc<crate::pipeline::QueueGauges>,
+    scan_running: Arc<std::sync::atomic::AtomicBool>,
+    stats: Arc<crate::stats::Stats>,
+    mut options: ScanOptions,
+) {
+    options.quota = tokio::task::spawn_blocking({
+        let pool = pool.clone();
+        let path = path.clone();
         move || {
             let conn = pool.get().ok()?;
-            let asset = crate::db::query::get_asset_by_id(&conn, id).ok()??;
-            // Use MIME type from database (more accurate than guessing from path)
-            let mime_str = if !asset.mime.is_empty() {
-                asset.mime.clone()
-            } else {
-                // Fallback to guessing from path if database MIME is empty
-                mime_guess::from_path(&asset.path).first_or_octet_stream().to_string()
-            };
-            Some((std::path::PathBuf::from(asset.path), mime_str, asset.video_codec.clone()))
+            db::query::get_scan_path_quota(&conn, &path).ok()
         }
-    }).await.ok().flatten() {
-        Some((path, mime_str, codec)) => (path, mime_str, codec),
-        None => return StatusCode::NOT_FOUND.into_response(),
+    }).await.ok().flatten().unwrap_or_default();
+
+    let root = std::path::PathBuf::from(&path);
+    let cancel = options.cancel.clone();
+    let started_at = chrono::Utc::now().timestamp();
+    let result = crate::pipeline::discover::scan_bfs(root, tx, gauges, scan_running.clone(), Some(stats), options).await;
+    let stopped = !scan_running.load(std::sync::atomic::Ordering::SeqCst);
+    let status = match &result {
+        Err(_) => "failed",
+        Ok(_) if cancel.is_some_and(|c| c.load(std::sync::atomic::Ordering::SeqCst)) => "canceled",
+        Ok(_) if stopped => "paused",
+        Ok(summary) if summary.quota_hit.is_some() => "quota",
+        Ok(_) => "completed",
     };
+    let summary = result.as_ref().cloned().unwrap_or_default();
+    let run = crate::pipeline::discover::ScanRun {
+        path: path.clone(),
+        started_at,
+        finished_at: chrono::Utc::now().timestamp(),
+        status: status.to_string(),
+        files: summary.files,
+        bytes: summary.bytes,
+    };
+    let recorded = tokio::task::spawn_blocking({
+        let pool = pool.clone();
+        move || {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::record_scan_run(&conn, &run)
+        }
+    }).await;
+    if let Ok(Err(e)) = recorded {
+        tracing::error!("Could not save scan history: {}", e);
+    }
+    if result.is_err() || stopped {
+        return;
+    }
+    let hit = summary.quota_hit;
+    if let Some(hit) = &hit {
+        tracing::warn!(
+            "Scan of {} stopped at its {} limit after {} files ({} bytes); raise it with PUT /paths/quota",
+            path, hit.limit, hit.indexed_assets, hit.indexed_bytes
+        );
+    }
+    let saved = tokio::task::spawn_blocking(move || {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+        db::writer::set_scan_path_quota_hit(&conn, &path, hit.as_ref())
+    }).await;
+    if let Ok(Err(e)) = saved {
+        tracing::error!("Could not save scan quota report: {}", e);
+    }
+}
 
-    let derived_dir = state.paths.data.join("derived");
+/// Include/exclude filter for a scan path, loaded from the database the first time it is needed
+fn path_filter(state: &AppState, path: &str) -> SharedPathFilter {
+    state.path_filters.lock()
+        .entry(path.to_string())
+        .or_insert_with(|| {
+            let root = StdPath::new(path);
+            let (include, exclude) = state.pool.get().ok()
+                .and_then(|conn| db::query::get_scan_path_filter(&conn, path).ok())
+                .unwrap_or_default();
+            let filter = PathFilter::new(root, &include, &exclude).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring invalid filters for {}: {}", path, e);
+                PathFilter::new(root, &[], &[]).unwrap_or_default()
+            });
+            Arc::new(parking_lot::RwLock::new(filter))
+        })
+        .clone()
+}
+
+/// Start the watcher and a background scan for a path; false if it is already being scanned
+pub(crate) fn start_path_scan(state: &AppState, decoded_path: String, force: bool) -> bool {
+    use std::sync::atomic::Ordering;
 
-    // Determine which file to serve (original or transcoded)
-    // MIME-based compatibility first, then refine for MP4 based on stored codec.
-    let mut browser_compatible = is_browser_compatible_video(&mime_str);
+    // Determine if this is the first active scan (used to start scan stats)
+    let was_scanning = {
+        let map = state.path_scan_running.lock();
+        map.values().any(|flag| flag.load(Ordering::Relaxed))
+    };
 
-    if mime_str == "video/mp4" {
-        // HEVC transcode behavior can be controlled via env:
-        // SEEN_HEVC_TRANSCODE = "auto" (default) | "never" | "always"
-        let hevc_mode = std::env::var("SEEN_HEVC_TRANSCODE")
-            .unwrap_or_else(|_| "auto".to_string())
-            .to_lowercase();
+    // Get or create per-path scan_running flag
+    let path_scan_running = {
+        let mut map = state.path_scan_running.lock();
+        map.entry(decoded_path.clone())
+            .or_insert_with(|| Arc::new(std::sync::atomic::AtomicBool::new(false)))
+            .clone()
+    };
 
-        match hevc_mode.as_str() {
-            "never" | "off" | "false" => {
-                // Always treat MP4 as browser-compatible, even if HEVC.
-                browser_compatible = true;
-            }
-            "always" | "force" => {
-                // Always transcode MP4, regardless of codec.
-                browser_compatible = false;
-            }
-            _ => {
-                // auto mode: only force transcode for HEVC/H.265 when codec is known.
-                if let Some(codec) = video_codec.as_deref() {
-                    let codec_lower = codec.to_lowercase();
-                    if codec_lower.contains("hevc") || codec_lower.contains("h265") {
-                        browser_compatible = false;
-                    }
-                }
-            }
-        }
+    // Check if already scanning
+    if path_scan_running.swap(true, Ordering::SeqCst) {
+        return false;
     }
 
-    let (video_path, content_mime) = if browser_compatible {
-        // Browser-compatible format - serve original
-        (file_path, mime_str)
-    } else {
-        // Need transcoding - get SHA256 and check for cached transcoded version
-        let sha256 = match tokio::task::spawn_blocking({
-            let pool = state.pool.clone();
-            move || {
-                let conn = pool.get().ok()?;
-                crate::db::query::get_asset_sha256(&conn, id).ok()?
-            }
-        }).await.ok().flatten() {
-            Some(sha) if !sha.is_empty() => sha,
-            _ => {
-                tracing::warn!("Cannot transcode video {}: SHA256 not available", id);
-                // Fallback to original (will likely fail in browser, but file is available for download)
-                return serve_video_file(&file_path, &mime_str, &headers).await.into_response();
-            }
-        };
-
-        let transcoded_path = get_transcoded_video_path(&derived_dir, &sha256);
+    // Get or create per-path watcher_paused flag
+    let path_watcher_paused = {
+        let mut map = state.path_watcher_paused.lock();
+        map.entry(decoded_path.clone())
+            .or_insert_with(|| Arc::new(std::sync::atomic::AtomicBool::new(false)))
+            .clone()
+    };
+    // Ensure watcher is marked as active when starting/resuming scan
+    path_watcher_paused.store(false, Ordering::SeqCst);
 
-        // Check if transcoded version exists (could be MP4 or WebM)
-        let transcoded_mp4 = transcoded_path.clone();
-        let transcoded_webm = transcoded_path.with_extension("webm");
+    // Start watcher if not already running
+    {
+        let mut watchers = state.path_watchers.lock();
+        if !watchers.contains_key(&decoded_path) {
+            let root = std::path::PathBuf::from(&decoded_path);
+            let dtx = state.queues.discover_tx.clone();
+            let g = state.gauges.clone();
+            let db_path = state.db_path.clone();
+            let stats = state.stats.clone();
+            let paused = path_watcher_paused.clone();
+            let filter = path_filter(state, &decoded_path);
 
-        // Check for cached versions first
-        if tokio::fs::metadata(&transcoded_mp4).await.is_ok() {
-            // Use cached MP4 version
-            (transcoded_mp4, "video/mp4".to_string())
-        } else if tokio::fs::metadata(&transcoded_webm).await.is_ok() {
-            // Use cached WebM version
-            (transcoded_webm, "video/webm".to_string())
-        } else {
-            // Need to transcode
-            tracing::info!("Transcoding video {} ({} -> MP4)", id, mime_str);
-            match transcode_video_to_mp4(&file_path, &transcoded_path).await {
-                Ok(_) => {
-                    // Verify the transcoded file exists, is readable, and has content
-                    match tokio::fs::metadata(&transcoded_path).await {
-                        Ok(meta) if meta.is_file() && meta.len() > 0 => {
-                            tracing::info!("Transcoded file verified: {} ({} bytes)", transcoded_path.display(), meta.len());
-                            (transcoded_path, "video/mp4".to_string())
-                        }
-                        Ok(meta) => {
-                            tracing::error!("Transcoded file exists but is invalid: {} (is_file: {}, size: {})",
-                                transcoded_path.display(), meta.is_file(), meta.len());
-                            // Fallback to original (will likely fail in browser, but file is available for download)
-                            return serve_video_file(&file_path, &mime_str, &headers).await.into_response();
-                        }
-                        Err(e) => {
-                            tracing::error!("Transcoding completed but file not found: {} - {}", transcoded_path.display(), e);
-                            // Fallback to original (will likely fail in browser, but file is available for download)
-                            return serve_video_file(&file_path, &mime_str, &headers).await.into_response();
-                        }
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Failed to transcode video {}: {}", id, e);
-                    // Try CPU encoding as fallback if GPU encoding failed
-                    tracing::info!("Attempting CPU fallback transcoding for video {}", id);
-                    match transcode_video_to_mp4_cpu(&file_path, &transcoded_path).await {
-                        Ok(_) => {
-                            // Check which format was created (MP4 or WebM)
-                            let transcoded_mp4_check = transcoded_path.clone();
-                            let transcoded_webm_check = transcoded_path.with_extension("webm");
-                            if let Ok(meta) = tokio::fs::metadata(&transcoded_mp4_check).await {
-                                if meta.is_file() && meta.len() > 0 {
-                                    tracing::info!("CPU transcoding succeeded: {} ({} bytes)", transcoded_mp4_check.display(), meta.len());
-                                    (transcoded_mp4_check, "video/mp4".to_string())
-                                } else {
-                                    tracing::error!("CPU transcoding completed but file is invalid");
-                                    return (StatusCode::INTERNAL_SERVER_ERROR, "Video transcoding failed").into_response();
-                                }
-                            } else if let Ok(meta) = tokio::fs::metadata(&transcoded_webm_check).await {
-                                if meta.is_file() && meta.len() > 0 {
-                                    tracing::info!("CPU transcoding succeeded (WebM): {} ({} bytes)", transcoded_webm_check.display(), meta.len());
-                                    (transcoded_webm_check, "video/webm".to_string())
-                                } else {
-                                    tracing::error!("CPU transcoding completed but file is invalid");
-                                    return (StatusCode::INTERNAL_SERVER_ERROR, "Video transcoding failed").into_response();
-                                }
-                            } else {
-                                tracing::error!("CPU transcoding completed but no output file found");
-                                return (StatusCode::INTERNAL_SERVER_ERROR, "Video transcoding failed - file not found").into_response();
-                            }
-                        }
-                        Err(e2) => {
-                            tracing::error!("CPU fallback transcoding also failed: {}", e2);
-                            return (StatusCode::INTERNAL_SERVER_ERROR, "Video transcoding failed").into_response();
-                        }
-                    }
-                }
-            }
+            let handle = tokio::spawn(async move {
+                let _ = crate::pipeline::discover::watch(root, dtx, Some(db_path), g, Some(stats), Some(paused), Some(filter)).await;
+            });
+            watchers.insert(decoded_path.clone(), handle);
         }
-    };
+    }
 
-    // Serve the video file (original or transcoded)
-    serve_video_file(&video_path, &content_mime, &headers).await.into_response()
-}
+    // If no other scans were running, start global scan stats
+    if !was_scanning {
+        state.stats.start_scan();
+    }
+    state.scan_running.store(true, Ordering::SeqCst);
 
-async fn serve_video_file(file_path: &std::path::Path, mime_str: &str, headers: &HeaderMap) -> impl IntoResponse {
-    // Verify file exists before attempting to serve
-    let metadata = match tokio::fs::metadata(file_path).await {
-        Ok(m) => m,
-        Err(e) => {
-            tracing::error!("Video file not found or not accessible: {} - {}", file_path.display(), e);
-            return StatusCode::NOT_FOUND.into_response();
-        }
+    // Start scan for this path
+    let tx = state.queues.discover_tx.clone();
+    let gauges = state.gauges.clone();
+    let scan_running = path_scan_running.clone();
+    let stats = state.stats.clone();
+    let path_scan_map = state.path_scan_running.clone();
+    let global_scan_flag = state.scan_running.clone();
+    let path_for_scan = decoded_path.clone();
+    let options = ScanOptions {
+        force,
+        filter: path_filter(state, &decoded_path).read().clone(),
+        cancel: Some(new_scan_cancel(state, &decoded_path)),
+        ..Default::default()
     };
+    let pool = state.pool.clone();
 
-    if metadata.is_file() {
-        let file_size = metadata.len();
-
-        // Check for Range header
-        if let Some(range_header) = headers.get(header::RANGE) {
-            if let Ok(range_str) = range_header.to_str() {
-                // Parse Range header (e.g., "bytes=0-1023" or "bytes=1024-")
-                if let Some(range) = parse_range(range_str, file_size) {
-                    let (start, end) = range;
-                    let content_length = end - start + 1;
-
-                    // Read the requested byte range
-                    if let Ok(mut file) = tokio::fs::File::open(file_path).await {
-                        use tokio::io::{AsyncSeekExt, AsyncReadExt};
-                        if file.seek(std::io::SeekFrom::Start(start)).await.is_ok() {
-                            let mut buffer = vec![0u8; content_length as usize];
-                            if file.read_exact(&mut buffer).await.is_ok() {
-                                let mut resp = axum::http::Response::builder()
-                                    .status(StatusCode::PARTIAL_CONTENT);
-                                let resp_headers = resp.headers_mut().unwrap();
-                                resp_headers.insert(
-                                    header::CONTENT_TYPE,
-                                    header::HeaderValue::from_str(mime_str)
-                                        .unwrap_or_else(|_| header::HeaderValue::from_static("video/mp4"))
-                                );
-                                resp_headers.insert(
-                                    header::CONTENT_LENGTH,
-                                    header::HeaderValue::from(content_length)
-                                );
-                                resp_headers.insert(
-                                    header::CONTENT_RANGE,
-                                    header::HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, file_size))
-                                        .unwrap_or_else(|_| header::HeaderValue::from_static("bytes */*"))
-                                );
-                                resp_headers.insert(
-                                    header::ACCEPT_RANGES,
-                                    header::HeaderValue::from_static("bytes")
-                                );
-                                // Add CORS headers for video streaming
-                                resp_headers.insert(
-                                    header::ACCESS_CONTROL_ALLOW_ORIGIN,
-                                    header::HeaderValue::from_static("*")
-                                );
-                                return resp.body(axum::body::Body::from(buffer)).unwrap();
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    tokio::spawn(async move {
+        info!("scan_start for path: {:?} (force: {})", path_for_scan, force);
+        run_path_scan(pool, path_for_scan.clone(), tx, gauges, scan_running.clone(), stats.clone(), options).await;
+        info!("scan_finish for path: {:?}", path_for_scan);
+        scan_running.store(false, Ordering::SeqCst);
 
-        // No range request or invalid range - serve entire file
-        if let Ok(bytes) = tokio::fs::read(file_path).await {
-            let mut resp = axum::http::Response::builder().status(StatusCode::OK);
-            let resp_headers = resp.headers_mut().unwrap();
-            resp_headers.insert(
-                header::CONTENT_TYPE,
-                header::HeaderValue::from_str(mime_str)
-                    .unwrap_or_else(|_| header::HeaderValue::from_static("video/mp4"))
-            );
-            resp_headers.insert(
-                header::CONTENT_LENGTH,
-                header::HeaderValue::from(file_size)
-            );
-            resp_headers.insert(
-                header::ACCEPT_RANGES,
-                header::HeaderValue::from_static("bytes")
-            );
-            // Add CORS headers for video streaming
-            resp_headers.insert(
-                header::ACCESS_CONTROL_ALLOW_ORIGIN,
-                header::HeaderValue::from_static("*")
-            );
-            return resp.body(axum::body::Body::from(bytes)).unwrap();
+        // If no scans remain active, finalize statistics
+        let any_active = path_scan_map.lock()
+            .values()
+            .any(|flag| flag.load(Ordering::Relaxed));
+        if !any_active {
+            stats.finish_processing();
+            stats.finish_scan();
+            global_scan_flag.store(false, Ordering::SeqCst);
+        } else {
+            global_scan_flag.store(true, Ordering::SeqCst);
         }
-    }
-    StatusCode::NOT_FOUND.into_response()
-}
+    });
 
-fn is_browser_compatible_video(mime: &str) -> bool {
-    // Browser-compatible formats that don't need transcoding
-    matches!(
-        mime,
-        "video/mp4" | "video/webm" | "video/ogg" | "video/ogv"
-    )
-}
 
-fn get_transcoded_video_path(derived_dir: &std::path::Path, sha256: &str) -> std::path::PathBuf {
-    if sha256.len() >= 2 {
-        let sub = &sha256[0..2];
-        derived_dir.join(sub).join(format!("{}-transcoded.mp4", sha256))
-    } else {
-        derived_dir.join(format!("{}-transcoded.mp4", sha256))
-    }
+    true
 }
 
-async fn transcode_video_to_mp4(src_path: &std::path::Path, dst_path: &std::path::Path) -> Result<(), anyhow::Error> {
-    use std::time::Duration;
+/// Stop scanning a path and ignore watcher events for it until resumed
+pub(crate) fn pause_scan_path(state: &AppState, path: &str) {
+    use std::sync::atomic::Ordering;
 
-    // Ensure parent directory exists
-    if let Some(parent) = dst_path.parent() {
-        tokio::fs::create_dir_all(parent).await?;
+    // Stop scanning for this path
+    if let Some(scan_running) = state.path_scan_running.lock().get(path) {
+        scan_running.store(false, Ordering::SeqCst);
     }
 
-    // Get GPU config for potential acceleration
-    let gpu_config = crate::utils::ffmpeg::get_gpu_config();
-    // Respect auto-disabled GPU flag: if disabled, fall back to CPU.
-    let accel = if gpu_config.enabled {
-        gpu_config.accel.clone()
-    } else {
-        crate::utils::ffmpeg::GpuAccel::Cpu
+    // Pause watcher for this path
+    let watcher_paused = {
+        let mut map = state.path_watcher_paused.lock();
+        map.entry(path.to_string())
+            .or_insert_with(|| Arc::new(std::sync::atomic::AtomicBool::new(false)))
+            .clone()
     };
+    watcher_paused.store(true, Ordering::SeqCst);
 
-    // Build FFmpeg args based on GPU availability
-    let mut args = Vec::new();
-
-    // Overwrite output file if it exists
-    args.push("-y".to_string());
-
-    // Add GPU acceleration settings (must come before input file)
-    match accel {
-        crate::utils::ffmpeg::GpuAccel::Cuda => {
-            // Use CUDA for hardware-accelerated decoding
-            args.push("-hwaccel".to_string());
-            args.push("cuda".to_string());
-        }
-        crate::utils::ffmpeg::GpuAccel::Qsv => {
-            args.push("-hwaccel".to_string());
-            args.push("qsv".to_string());
-        }
-        crate::utils::ffmpeg::GpuAccel::D3d11va => {
-            args.push("-hwaccel".to_string());
-            args.push("d3d11va".to_string());
-        }
-        crate::utils::ffmpeg::GpuAccel::VideoToolbox => {
-            args.push("-hwaccel".to_string());
-            args.push("videotoolbox".to_string());
-        }
-        crate::utils::ffmpeg::GpuAccel::Cpu => {
-            // No hardware acceleration
-        }
-    }
-
-    // Add input file
-    args.push("-i".to_string());
-    args.push(src_path.to_string_lossy().to_string());
-
-    // Add encoding settings (after input file)
-    match accel {
-        crate::utils::ffmpeg::GpuAccel::Cuda => {
-            args.push("-c:v".to_string());
-            args.push("h264_nvenc".to_string());
-            args.push("-preset".to_string());
-            args.push("p4".to_string()); // NVENC preset (p4 = medium quality, good speed)
-            args.push("-cq".to_string());
-            args.push("23".to_string()); // Constant quality (similar to CRF)
-        }
-        crate::utils::ffmpeg::GpuAccel::Qsv => {
-            args.push("-c:v".to_string());
-            args.push("h264_qsv".to_string());
-            args.push("-preset".to_string());
-            args.push("medium".to_string());
-            args.push("-global_quality".to_string());
-            args.push("23".to_string());
-        }
-        crate::utils::ffmpeg::GpuAccel::D3d11va => {
-            // D3D11VA for decode, try NVENC for encode (fallback to CPU handled by FFmpeg)
-            args.push("-c:v".to_string());
-            args.push("h264_nvenc".to_string());
-            args.push("-preset".to_string());
-            args.push("p4".to_string());
-            args.push("-cq".to_string());
-            args.push("23".to_string());
-        }
-        crate::utils::ffmpeg::GpuAccel::VideoToolbox => {
-            args.push("-c:v".to_string());
-            args.push("h264_videotoolbox".to_string());
-            args.push("-b:v".to_string());
-            args.push("5M".to_string()); // Bitrate for VideoToolbox
-        }
-        crate::utils::ffmpeg::GpuAccel::Cpu => {
-            // Pure CPU encoding using libx264 (widely supported in browsers)
-            args.push("-c:v".to_string());
-            args.push("libx264".to_string());
-            args.push("-preset".to_string());
-            args.push("medium".to_string());
-            args.push("-crf".to_string());
-            args.push("23".to_string());
-        }
-    }
-
-    // Audio encoding
-    args.push("-c:a".to_string());
-    args.push("aac".to_string());
-    args.push("-b:a".to_string());
-    args.push("192k".to_string());
-
-    // Output format settings
-    args.push("-movflags".to_string());
-    args.push("+faststart".to_string()); // Enable streaming
-    args.push("-f".to_string());
-    args.push("mp4".to_string());
-
-    // Output file
-    args.push(dst_path.to_string_lossy().to_string());
-
-    tracing::info!("Transcoding video: {} -> {}", src_path.display(), dst_path.display());
-
-    // Run FFmpeg with timeout (10 minutes for long videos) in a blocking task
-    let src_path_str = src_path.to_path_buf();
-    let dst_path_str = dst_path.to_path_buf();
-    let output = tokio::task::spawn_blocking(move || {
-        crate::utils::ffmpeg::run_ffmpeg_with_timeout(args, Duration::from_secs(600))
-    }).await??;
-
-    let used_gpu = !matches!(accel, crate::utils::ffmpeg::GpuAccel::Cpu);
-
-    if !output.status.success() {
-        if used_gpu {
-            crate::utils::ffmpeg::record_gpu_failure();
-        } else {
-            crate::utils::ffmpeg::increment_cpu_job();
-        }
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        // Clean up partial file if it exists
-        let _ = std::fs::remove_file(&dst_path_str);
-        return Err(anyhow::anyhow!("FFmpeg transcoding failed: {}", stderr));
-    }
+    // Recompute global scan flag
+    let any_active = state.path_scan_running.lock()
+        .values()
+        .any(|flag| flag.load(Ordering::Relaxed));
+    state.scan_running.store(any_active, Ordering::SeqCst);
+}
 
-    // Successful transcode; update GPU/CPU stats
-    if used_gpu {
-        crate::utils::ffmpeg::increment_gpu_job();
-    } else {
-        crate::utils::ffmpeg::increment_cpu_job();
-    }
+pub(crate) fn resume_scan_path(state: &AppState, path: &str) {
+    // Resume watcher for this path
+    let watcher_paused = {
+        let mut map = state.path_watcher_paused.lock();
+        map.entry(path.to_string())
+            .or_insert_with(|| Arc::new(std::sync::atomic::AtomicBool::new(false)))
+            .clone()
+    };
+    watcher_paused.store(false, std::sync::atomic::Ordering::SeqCst);
+}
 
-    // Verify the output file exists and has content
-    // Small delay to ensure file is fully written to disk
-    std::thread::sleep(std::time::Duration::from_millis(100));
+/// True if the caller may pause, resume or cancel `path`
+pub(crate) async fn can_edit_path(state: &AppState, principal: Principal, path: &str) -> Result<bool> {
+    let pool = state.pool.clone();
+    let path = path.to_string();
+    tokio::task::spawn_blocking(move || -> Result<bool> {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+        db::query::can_edit_scan_path(&conn, &path, principal.scope())
+    }).await?
+}
 
-    match std::fs::metadata(&dst_path_str) {
-        Ok(meta) if meta.is_file() && meta.len() > 0 => {
-            tracing::info!("Video transcoding completed successfully: {} -> {} ({} bytes)",
-                src_path_str.display(), dst_path_str.display(), meta.len());
-            Ok(())
-        }
-        Ok(meta) => {
-            let _ = std::fs::remove_file(&dst_path_str);
-            Err(anyhow::anyhow!("Transcoded file is invalid: is_file={}, size={}", meta.is_file(), meta.len()))
-        }
+/// The response for a path action the caller may not take, or None if it may
+async fn path_action_rejection(state: &AppState, principal: Principal, path: &str) -> Option<(StatusCode, Json<serde_json::Value>)> {
+    match can_edit_path(state, principal, path).await {
+        Ok(true) => None,
+        Ok(false) => Some((StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Path not found in scan paths"
+        })))),
         Err(e) => {
-            Err(anyhow::anyhow!("Transcoded file not found after transcoding: {}", e))
+            tracing::error!("Error checking scan path {}: {}", path, e);
+            Some((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))))
         }
     }
 }
 
-async fn transcode_video_to_mp4_cpu(src_path: &std::path::Path, dst_path: &std::path::Path) -> Result<(), anyhow::Error> {
-    use std::time::Duration;
-
-    // Ensure parent directory exists
-    if let Some(parent) = dst_path.parent() {
-        tokio::fs::create_dir_all(parent).await?;
+pub async fn pause_path(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Json(req): Json<PathActionReq>) -> impl IntoResponse {
+    if let Some(rejection) = path_action_rejection(&state, principal, &req.path).await {
+        return rejection;
     }
+    pause_scan_path(&state, &req.path);
 
-    // Try encoders in order of preference
-    // Note: mpeg4 (MPEG-4 Part 2) is not well-supported by browsers in MP4 containers.
-    // Browsers expect H.264 (MPEG-4 Part 10/AVC), so we prioritize libx264 and WebM codecs.
-    let encoder_configs = vec![
-        ("libx264", ("mp4", vec!["-preset", "medium", "-crf", "23"])),
-        ("h264_v4l2m2m", ("mp4", vec!["-qmin", "18", "-qmax", "28", "-b:v", "2M"])), // V4L2 mem2mem H.264 (hardware if available)
-        ("libx265", ("mp4", vec!["-preset", "medium", "-x265-params", "crf=23"])), // Use x265-params for libx265
-        ("h264_qsv", ("mp4", vec!["-preset", "medium", "-global_quality", "23"])),
-        ("libvpx-vp9", ("webm", vec!["-quality", "good", "-speed", "1", "-b:v", "2M"])), // WebM with VP9 - use quality/speed instead of crf
-        ("libvpx", ("webm", vec!["-quality", "good", "-speed", "1", "-b:v", "2M"])), // WebM with VP8 - use quality/speed instead of crf
-        ("mpeg4", ("mp4", vec!["-qscale:v", "3", "-pix_fmt", "yuv420p"])), // Last resort
-    ];
-
-    let mut last_error = None;
-
-    for (encoder, (container_format, encoder_args)) in encoder_configs {
-        // Build FFmpeg args for CPU-only encoding
-        let mut args = vec!["-y".to_string()];
-
-        // Add input file
-        args.push("-i".to_string());
-        args.push(src_path.to_string_lossy().to_string());
+    (StatusCode::OK, Json(serde_json::json!({
+        "success": true,
+        "message": "Path paused"
+    })))
+}
 
-        // Add color space conversion for MJPEG inputs (yuvj422p -> yuv420p)
-        // This fixes the "deprecated pixel format" warning and ensures proper color range
-        // MJPEG uses full-range JPEG colorspace (yuvj422p), need to convert to standard yuv420p
-        args.push("-vf".to_string());
-        args.push("format=yuv420p".to_string()); // Convert to standard yuv420p with proper color range
+/// Abort a path's scan: the walk stops, files it queued that haven't been hashed yet are dropped,
+/// and the scan goes into the history as canceled. Unlike pausing, the watcher keeps running.
+pub async fn cancel_path(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<PathActionReq>,
+) -> impl IntoResponse {
+    use std::sync::atomic::Ordering;
 
-        // Try this encoder
-        args.push("-c:v".to_string());
-        args.push(encoder.to_string());
-        for arg in encoder_args {
-            args.push(arg.to_string());
+    let allowed = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let path = req.path.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::can_edit_scan_path(&conn, &path, principal.scope())
         }
-
-        // Audio encoding - use opus for WebM, aac for MP4
-        args.push("-c:a".to_string());
-        if container_format == "webm" {
-            args.push("libopus".to_string());
-            args.push("-b:a".to_string());
-            args.push("128k".to_string());
-        } else {
-            args.push("aac".to_string());
-            args.push("-b:a".to_string());
-            args.push("192k".to_string());
+    }).await;
+    match allowed {
+        Ok(Ok(true)) => {}
+        Ok(Ok(false)) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+                "error": "Path not found in scan paths"
+            })));
         }
-
-        // Output format settings
-        if container_format == "mp4" {
-            args.push("-movflags".to_string());
-            args.push("+faststart".to_string());
+        Ok(Err(e)) => {
+            tracing::error!("Error checking scan path {}: {}", req.path, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            })));
         }
-        args.push("-f".to_string());
-        args.push(container_format.to_string());
-
-        // Output file - adjust extension based on container format
-        let output_path = if container_format == "webm" {
-            dst_path.with_extension("webm")
-        } else {
-            dst_path.to_path_buf()
-        };
-        args.push(output_path.to_string_lossy().to_string());
-
-        tracing::info!("Trying CPU transcoding with encoder '{}' ({}): {} -> {}", encoder, container_format, src_path.display(), output_path.display());
-
-        // Run FFmpeg with timeout
-        let src_path_str = src_path.to_path_buf();
-        let output_path_str = output_path.clone();
-        let output = tokio::task::spawn_blocking(move || {
-            crate::utils::ffmpeg::run_ffmpeg_with_timeout(args, Duration::from_secs(600))
-        }).await??;
-
-        if output.status.success() {
-            // Verify the output file exists and has content
-            std::thread::sleep(std::time::Duration::from_millis(100));
-
-            match std::fs::metadata(&output_path_str) {
-                Ok(meta) if meta.is_file() && meta.len() > 0 => {
-                    tracing::info!("CPU video transcoding succeeded with encoder '{}' ({}): {} -> {} ({} bytes)",
-                        encoder, container_format, src_path_str.display(), output_path_str.display(), meta.len());
-                    // Increment CPU job counter for periodic GPU retry mechanism
-                    crate::utils::ffmpeg::increment_cpu_job();
-                    // Keep the file in its native format (WebM or MP4)
-                    // The serving logic will handle the MIME type based on file extension
-                    return Ok(());
-                }
-                Ok(meta) => {
-                    let _ = std::fs::remove_file(&output_path_str);
-                    last_error = Some(format!("Transcoded file is invalid: is_file={}, size={}", meta.is_file(), meta.len()));
-                    continue; // Try next encoder
-                }
-                Err(e) => {
-                    last_error = Some(format!("Transcoded file not found: {}", e));
-                    continue; // Try next encoder
-                }
-            }
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            // Clean up partial file if it exists
-            let _ = std::fs::remove_file(&output_path_str);
-            last_error = Some(format!("Encoder '{}' failed: {}", encoder, stderr));
-            tracing::warn!("Encoder '{}' failed, trying next: {}", encoder, stderr);
-            continue; // Try next encoder
+        Err(e) => {
+            tracing::error!("Task error checking scan path {}: {}", req.path, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            })));
         }
     }
 
-    // All encoders failed
-    Err(anyhow::anyhow!("All video encoders failed. Last error: {}",
-        last_error.unwrap_or_else(|| "Unknown error".to_string())))
-}
-
-fn parse_range(range_str: &str, file_size: u64) -> Option<(u64, u64)> {
-    // Parse "bytes=start-end" format
-    if let Some(bytes_part) = range_str.strip_prefix("bytes=") {
-        if let Some((start_str, end_str)) = bytes_part.split_once('-') {
-            let start = if start_str.is_empty() {
-                0
-            } else {
-                start_str.parse::<u64>().ok()?
-            };
-
-            let end = if end_str.is_empty() {
-                file_size - 1
-            } else {
-                end_str.parse::<u64>().ok()?
-            };
-
-            if start <= end && end < file_size {
-                return Some((start, end));
-            }
-        }
+    let scan_running = state.path_scan_running.lock().get(&req.path)
+        .filter(|flag| flag.load(Ordering::SeqCst))
+        .cloned();
+    let Some(scan_running) = scan_running else {
+        return (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "Path is not being scanned"
+        })));
+    };
+    if let Some(cancel) = state.path_scan_cancel.lock().get(&req.path) {
+        cancel.store(true, Ordering::SeqCst);
     }
-    None
-}
-
-pub async fn download_asset(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
-    let path = tokio::task::spawn_blocking({
-        let pool = state.pool.clone();
-        move || {
-            let conn = pool.get().ok()?;
-            crate::db::query::get_asset_path(&conn, id).ok()?
-        }
-    }).await.ok().flatten();
-
-    if let Some(file_path) = path {
-        if let Ok(bytes) = tokio::fs::read(&file_path).await {
-            let filename = std::path::Path::new(&file_path)
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("file");
-
-            let mime = mime_guess::from_path(&file_path)
-                .first_or_octet_stream();
+    scan_running.store(false, Ordering::SeqCst);
+    let any_active = state.path_scan_running.lock()
+        .values()
+        .any(|flag| flag.load(Ordering::Relaxed));
+    state.scan_running.store(any_active, Ordering::SeqCst);
+    tracing::info!("scan of {} canceled", req.path);
 
-            let mut resp = axum::http::Response::builder().status(StatusCode::OK);
-            let headers = resp.headers_mut().unwrap();
-            headers.insert(
-                header::CONTENT_TYPE,
-                header::HeaderValue::from_str(mime.as_ref()).unwrap_or_else(|_| header::HeaderValue::from_static("application/octet-stream"))
-            );
-            headers.insert(
-                header::CONTENT_DISPOSITION,
-                header::HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename))
-                    .unwrap_or_else(|_| header::HeaderValue::from_static("attachment"))
-            );
-            return resp.body(axum::body::Body::from(bytes)).unwrap();
-        }
-    }
-    StatusCode::NOT_FOUND.into_response()
+    (StatusCode::OK, Json(serde_json::json!({
+        "success": true,
+        "message": "Scan canceled"
+    })))
 }
 
-#[derive(Debug, Clone)]
-struct AssetFileInfo {
+#[derive(Deserialize)]
+pub struct ScanHistoryQuery {
     path: Option<String>,
-    sha256: Option<Vec<u8>>,
+    limit: Option<i64>,
 }
 
-fn fetch_asset_file_info(conn: &Connection, id: i64) -> rusqlite::Result<Option<AssetFileInfo>> {
-    conn
-        .prepare("SELECT path, sha256 FROM assets WHERE id = ?1")?
-        .query_row(params![id], |row| {
-            Ok(AssetFileInfo {
-                path: row.get(0)?,
-                sha256: row.get(1)?,
-            })
-        })
-        .optional()
-}
+/// Recent scans, newest first, with how each ended and how much it queued
+pub async fn get_scan_history(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(q): Query<ScanHistoryQuery>,
+) -> impl IntoResponse {
+    let limit = q.limit.unwrap_or(20).clamp(1, 200);
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<crate::pipeline::discover::ScanRun>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::get_scan_runs(&conn, q.path.as_deref(), limit, principal.scope())
+        }
+    }).await;
 
-fn remove_derived_files(sha256: Option<&[u8]>, derived_dir: &StdPath) {
-    if let Some(sha) = sha256 {
-        if sha.is_empty() {
-            return;
+    match result {
+        Ok(Ok(runs)) => Json(serde_json::json!({ "runs": runs })).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error loading scan history: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
         }
-        let sha_hex = hex::encode(sha);
-        if sha_hex.len() < 2 {
-            return;
+        Err(e) => {
+            tracing::error!("Task error loading scan history: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
         }
-        let sub = &sha_hex[0..2];
-        let thumb_path = derived_dir.join(sub).join(format!("{}-256.webp", sha_hex));
-        let preview_path = derived_dir.join(sub).join(format!("{}-1600.webp", sha_hex));
-        let _ = std::fs::remove_file(thumb_path);
-        let _ = std::fs::remove_file(preview_path);
     }
 }
 
-enum DeleteDiskError {
-    ReadOnly(io::Error),
-    Other(io::Error),
+pub async fn resume_path(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Json(req): Json<PathActionReq>) -> impl IntoResponse {
+    if let Some(rejection) = path_action_rejection(&state, principal, &req.path).await {
+        return rejection;
+    }
+    resume_scan_path(&state, &req.path);
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "success": true,
+        "message": "Path resumed"
+    })))
 }
 
-fn is_read_only_error(err: &io::Error) -> bool {
-    err.kind() == io::ErrorKind::PermissionDenied || err.to_string().to_lowercase().contains("read-only")
+/// Hold the discover, hash, metadata, thumbnail, transcode and face workers; scans and the watcher keep queueing
+pub async fn pause_pipeline(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    if state.gauges.pause() {
+        tracing::info!("processing pipeline paused");
+    }
+    Json(serde_json::json!({
+        "success": true,
+        "paused": true,
+        "paused_since": state.gauges.paused_since()
+    }))
 }
 
-fn remove_original_file(path: &str, paths: &AppPaths) -> Result<(), DeleteDiskError> {
-    let resolved_path = crate::utils::path::resolve_asset_path(path, paths);
-    match std::fs::remove_file(&resolved_path) {
-        Ok(_) => Ok(()),
-        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
-        Err(e) if is_read_only_error(&e) => Err(DeleteDiskError::ReadOnly(e)),
-        Err(e) => Err(DeleteDiskError::Other(e)),
+pub async fn resume_pipeline(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    if state.gauges.resume() {
+        tracing::info!("processing pipeline resumed");
     }
+    Json(serde_json::json!({
+        "success": true,
+        "paused": false
+    }))
 }
 
-#[derive(Serialize)]
-struct PermanentDeleteResponse {
-    success: bool,
-    deleted_from_disk: bool,
-    read_only: bool,
-    path: Option<String>,
-    error: Option<String>,
+#[derive(Deserialize)]
+pub struct FlushQueuesReq {
+    queues: Vec<String>,
 }
 
-impl PermanentDeleteResponse {
-    fn success(path: Option<String>) -> Self {
-        Self {
-            success: true,
-            deleted_from_disk: true,
-            read_only: false,
-            path,
-            error: None,
+/// Throw away the jobs waiting in the thumbnail and/or face queues, e.g. after a mounted backup of
+/// another library was scanned by mistake. Jobs already being worked on finish.
+pub async fn flush_queues(State(state): State<Arc<AppState>>, Json(req): Json<FlushQueuesReq>) -> impl IntoResponse {
+    let mut queues = Vec::new();
+    for name in &req.queues {
+        match crate::pipeline::FlushQueue::parse(name) {
+            Some(queue) if !queues.contains(&queue) => queues.push(queue),
+            Some(_) => {}
+            None => {
+                return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                    "error": format!("Unknown queue '{}'", name)
+                }))).into_response();
+            }
         }
     }
+    if queues.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Name at least one queue to flush"
+        }))).into_response();
+    }
 
-    #[allow(dead_code)]
-    fn not_found() -> Self {
-        Self {
-            success: false,
-            deleted_from_disk: false,
-            read_only: false,
-            path: None,
-            error: Some("Asset not found".to_string()),
+    let mut flushed = serde_json::Map::new();
+    for queue in queues {
+        let discarded = state.gauges.flush(queue);
+        tracing::warn!("Flushed the {} queue, discarding {} pending jobs", queue.name(), discarded);
+        flushed.insert(queue.name().to_string(), discarded.into());
+        // The face journal would queue the discarded jobs again on the next start
+        #[cfg(feature = "facial-recognition")]
+        if queue == crate::pipeline::FlushQueue::Face {
+            let pool = state.pool.clone();
+            let cleared = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+                let conn = pool.get()?;
+                db::writer::clear_face_jobs(&conn)
+            })
+            .await;
+            match cleared {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    tracing::error!("Database error clearing face jobs: {}", e);
+                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                        "error": format!("Database error: {}", e)
+                    }))).into_response();
+                }
+                Err(e) => {
+                    tracing::error!("Task error clearing face jobs: {}", e);
+                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                        "error": "Internal server error"
+                    }))).into_response();
+                }
+            }
         }
     }
+    Json(serde_json::json!({
+        "success": true,
+        "flushed": flushed
+    })).into_response()
+}
 
-    fn read_only(path: Option<String>, error: String) -> Self {
-        Self {
-            success: false,
-            deleted_from_disk: false,
-            read_only: true,
-            path,
-            error: Some(error),
+pub async fn get_path_status(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(params): Query<PathActionReq>,
+) -> impl IntoResponse {
+    use std::sync::atomic::Ordering;
+
+    let decoded_path = params.path;
+    // Other users' paths look like unknown ones
+    if principal.scope() != AccessScope::All {
+        if let Some(rejection) = path_action_rejection(&state, principal, &decoded_path).await {
+            return rejection;
         }
     }
-}
 
-#[derive(Deserialize)]
-pub struct BulkPermanentDeleteRequest {
-    ids: Vec<i64>,
-}
+    let scanning = state.path_scan_running.lock()
+        .get(&decoded_path)
+        .map(|flag| flag.load(Ordering::Relaxed))
+        .unwrap_or(false);
 
-#[derive(Serialize)]
-struct BulkPermanentDeleteResult {
-    id: i64,
-    deleted: bool,
-    read_only: bool,
-    path: Option<String>,
-    error: Option<String>,
+    let watcher_paused = state.path_watcher_paused.lock()
+        .get(&decoded_path)
+        .map(|flag| flag.load(Ordering::Relaxed))
+        .unwrap_or(false);
+
+    let watching = state.path_watchers.lock().contains_key(&decoded_path);
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "scanning": scanning,
+        "watcher_paused": watcher_paused,
+        "watching": watching
+    })))
 }
 
-impl From<BulkPermanentDeleteResult> for PermanentDeleteResponse {
-    fn from(result: BulkPermanentDeleteResult) -> Self {
-        let BulkPermanentDeleteResult { deleted, read_only, path, error, .. } = result;
-        if deleted {
-            return PermanentDeleteResponse::success(path);
-        }
-        if read_only {
-            return PermanentDeleteResponse::read_only(
-                path,
-                error.unwrap_or_else(|| "File is read-only".to_string()),
-            );
-        }
-        PermanentDeleteResponse {
-            success: false,
-            deleted_from_disk: false,
-            read_only: false,
-            path,
-            error: Some(error.unwrap_or_else(|| "Asset not found".to_string())),
+pub async fn diag_ffmpeg() -> impl IntoResponse {
+    use std::process::Command;
+
+    let mut info = serde_json::json!({
+        "ffmpeg_version": "unknown",
+        "ffmpeg_path": ffmpeg::ffmpeg_bin(),
+        "ffprobe_path": ffmpeg::ffprobe_bin(),
+        "hwaccels": [],
+        "filters": [],
+        "gpu_config": {}
+    });
+
+    // Get ffmpeg version
+    if let Ok(output) = Command::new(ffmpeg::ffmpeg_bin()).args(["-version"]).output() {
+        if output.status.success() {
+            let version_str = String::from_utf8_lossy(&output.stdout);
+            let first_line = version_str.lines().next().unwrap_or("unknown");
+            info["ffmpeg_version"] = serde_json::Value::String(first_line.to_string());
         }
     }
-}
 
-fn perform_permanent_delete(conn: &Connection, derived_dir: &StdPath, paths: &AppPaths, id: i64) -> Result<BulkPermanentDeleteResult> {
-    let asset_info = fetch_asset_file_info(conn, id)?;
-    if let Some(info) = asset_info {
-        let path = info.path.clone();
-        let sha = info.sha256.clone();
-        if let Some(ref file_path) = path {
-            match remove_original_file(file_path, paths) {
-                Ok(_) => {
-                    let deleted = crate::db::query::delete_asset_by_id(conn, id)?;
-                    if deleted {
-                        remove_derived_files(sha.as_deref(), derived_dir);
-                        Ok(BulkPermanentDeleteResult { id, deleted: true, read_only: false, path, error: None })
-                    } else {
-                        Ok(BulkPermanentDeleteResult { id, deleted: false, read_only: false, path, error: Some("Asset not found".to_string()) })
-                    }
-                }
-                Err(DeleteDiskError::ReadOnly(err)) => Ok(BulkPermanentDeleteResult {
-                    id,
-                    deleted: false,
-                    read_only: true,
-                    path,
-                    error: Some(err.to_string()),
-                }),
-                Err(DeleteDiskError::Other(err)) => Err(anyhow::Error::new(err)),
-            }
-        } else {
-            let deleted = crate::db::query::delete_asset_by_id(conn, id)?;
-            if deleted {
-                remove_derived_files(sha.as_deref(), derived_dir);
-                Ok(BulkPermanentDeleteResult { id, deleted: true, read_only: false, path: None, error: None })
-            } else {
-                Ok(BulkPermanentDeleteResult { id, deleted: false, read_only: false, path: None, error: Some("Asset not found".to_string()) })
-            }
+    // Get available hardware accelerators
+    if let Ok(output) = Command::new(ffmpeg::ffmpeg_bin()).args(["-hide_banner", "-hwaccels"]).output() {
+        if output.status.success() {
+            let hwaccels_str = String::from_utf8_lossy(&output.stdout);
+            let accels: Vec<&str> = hwaccels_str
+                .lines()
+                .skip(1) // Skip header line
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| l.trim())
+                .collect();
+            info["hwaccels"] = serde_json::Value::Array(
+                accels.iter().map(|a| serde_json::Value::String(a.to_string())).collect()
+            );
         }
-    } else {
-        Ok(BulkPermanentDeleteResult { id, deleted: false, read_only: false, path: None, error: Some("Asset not found".to_string()) })
     }
-}
-
-pub async fn delete_asset(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
-    let derived_dir = state.paths.data.join("derived");
-    let result = tokio::task::spawn_blocking({
-        let pool = state.pool.clone();
-        let derived_dir = derived_dir.clone();
-        move || -> Result<bool> {
-            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            let asset_info = fetch_asset_file_info(&conn, id)?;
-            let deleted = crate::db::query::delete_asset_by_id(&conn, id)?;
-            if deleted {
-                if let Some(info) = asset_info {
-                    remove_derived_files(info.sha256.as_deref(), derived_dir.as_path());
-                }
-            }
-            Ok(deleted)
-        }
-    }).await;
 
-    match result {
-        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({"success": true}))).into_response(),
-        Ok(Ok(false)) => (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({"success": false, "error": "Asset not found"}))
-        ).into_response(),
-        Ok(Err(e)) => {
-            tracing::error!("Error deleting asset {}: {}", id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": format!("Internal error: {}", e)
-                }))
-            ).into_response()
-        }
-        Err(e) => {
-            tracing::error!("Task error deleting asset {}: {}", id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"success": false, "error": "Internal error"}))
-            ).into_response()
+    // Get available filters (check for GPU scaling filters)
+    if let Ok(output) = Command::new(ffmpeg::ffmpeg_bin()).args(["-hide_banner", "-filters"]).output() {
+        if output.status.success() {
+            let filters_str = String::from_utf8_lossy(&output.stdout);
+            let gpu_filters = ["scale_cuda", "scale_npp", "scale_qsv"];
+            let found: Vec<String> = gpu_filters
+                .iter()
+                .filter(|f| filters_str.contains(*f))
+                .map(|f| f.to_string())
+                .collect();
+            info["filters"] = serde_json::Value::Array(
+                found.iter().map(|f| serde_json::Value::String(f.clone())).collect()
+            );
         }
     }
+
+    // Get current GPU config
+    let gpu_config = crate::utils::ffmpeg::get_gpu_config();
+    let accel_str = match gpu_config.accel {
+        crate::utils::ffmpeg::GpuAccel::Cuda => "CUDA",
+        crate::utils::ffmpeg::GpuAccel::Qsv => "QSV",
+        crate::utils::ffmpeg::GpuAccel::D3d11va => "D3D11VA",
+        crate::utils::ffmpeg::GpuAccel::VideoToolbox => "VideoToolbox",
+        crate::utils::ffmpeg::GpuAccel::Cpu => "CPU",
+    };
+    let gpu_stats = crate::utils::ffmpeg::get_gpu_stats();
+
+    // Get device counts for diagnostics
+    let cuda_devices = crate::utils::ffmpeg::check_cuda_devices();
+    let intel_gpu = crate::utils::ffmpeg::check_intel_gpu_devices();
+    let opencl_devices = crate::utils::ffmpeg::check_opencl_devices();
+
+    info["gpu_config"] = serde_json::json!({
+        "accel": accel_str,
+        "enabled": gpu_config.enabled && !gpu_stats.auto_disabled,
+        "consecutive_failures": gpu_stats.consecutive_failures,
+        "auto_disabled": gpu_stats.auto_disabled,
+        "device_counts": {
+            "cuda": cuda_devices,
+            "intel_gpu": intel_gpu,
+            "opencl": opencl_devices,
+        },
+    });
+
+    (StatusCode::OK, Json(info))
 }
 
-pub async fn delete_asset_permanent(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
-    let derived_dir = state.paths.data.join("derived");
+pub async fn diag_selfcheck(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    use crate::utils::selfcheck;
+
+    #[cfg(feature = "facial-recognition")]
+    let (models_dir, scrfd_loaded, arcface_loaded) = {
+        let proc = state.face_processor.lock();
+        (proc.models_dir.clone(), proc.scrfd_loaded(), proc.arcface_loaded())
+    };
+
     let paths = state.paths.clone();
-    let result = tokio::task::spawn_blocking({
-        let pool = state.pool.clone();
-        let derived_dir = derived_dir.clone();
-        let paths = paths.clone();
-        move || -> Result<BulkPermanentDeleteResult> {
-            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            perform_permanent_delete(&conn, &derived_dir, &paths, id)
-        }
+    let pool = state.pool.clone();
+    let sqlite_tuning = state.sqlite_tuning;
+    let recovery = state.recovery.lock().clone();
+    let db_dir = state.db_path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| paths.data.join("db"));
+
+    // All checks touch the filesystem or spawn processes, keep them off the async runtime
+    let result = tokio::task::spawn_blocking(move || {
+        let mut checks = vec![selfcheck::check_writable_dirs(&[
+            ("data", paths.data.as_path()),
+            ("db", db_dir.as_path()),
+            ("derived", paths.derived.as_path()),
+        ])];
+        checks.push(selfcheck::check_libvips());
+        checks.extend(selfcheck::check_ffmpeg());
+        #[cfg(feature = "facial-recognition")]
+        checks.push(selfcheck::check_onnx(models_dir, scrfd_loaded, arcface_loaded));
+        #[cfg(not(feature = "facial-recognition"))]
+        checks.push(selfcheck::check_onnx(paths.data.join("models")));
+        checks.push(selfcheck::check_disk_space(&paths.data));
+        checks.push(selfcheck::check_inotify());
+        checks.push(selfcheck::check_sqlite_tuning(&pool, &sqlite_tuning));
+        checks.push(selfcheck::check_recovery(&recovery));
+        selfcheck::SelfCheckReport::new(checks)
     }).await;
 
     match result {
-        Ok(Ok(outcome)) => {
-            let status = if outcome.deleted {
-                StatusCode::OK
-            } else if outcome.read_only {
-                StatusCode::CONFLICT
-            } else if outcome.error.as_deref() == Some("Asset not found") {
-                StatusCode::NOT_FOUND
-            } else {
-                StatusCode::BAD_REQUEST
-            };
-            let response: PermanentDeleteResponse = outcome.into();
-            (status, Json(response)).into_response()
-        }
-        Ok(Err(e)) => {
-            tracing::error!("Error permanently deleting asset {}: {}", id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"success": false, "error": format!("Internal error: {}", e)}))
-            ).into_response()
-        }
+        Ok(report) => (StatusCode::OK, Json(serde_json::to_value(report).unwrap_or_default())).into_response(),
         Err(e) => {
-            tracing::error!("Task error permanently deleting asset {}: {}", id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"success": false, "error": "Internal error"}))
-            ).into_response()
+            tracing::error!("Task error running self-check: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
         }
     }
 }
 
-pub async fn delete_assets_permanent(
-    State(state): State<Arc<AppState>>,
-    Json(payload): Json<BulkPermanentDeleteRequest>
-) -> impl IntoResponse {
-    if payload.ids.is_empty() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"success": false, "error": "No asset IDs provided"}))
-        ).into_response();
-    }
+pub async fn stream_video(State(state): State<Arc<AppState>>, Path(id): Path<i64>, headers: HeaderMap) -> impl IntoResponse {
+    let target = match video_transcode_target(&state, id).await {
+        Some(t) => t,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
 
-    let derived_dir = state.paths.data.join("derived");
-    let paths = state.paths.clone();
-    let ids = payload.ids;
-    let result = tokio::task::spawn_blocking({
-        let pool = state.pool.clone();
-        let derived_dir = derived_dir.clone();
-        let paths = paths.clone();
-        move || -> Result<Vec<BulkPermanentDeleteResult>> {
-            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            let mut outcomes = Vec::with_capacity(ids.len());
-            for asset_id in ids {
-                let outcome = perform_permanent_delete(&conn, &derived_dir, &paths, asset_id)?;
-                outcomes.push(outcome);
-            }
-            Ok(outcomes)
+    // With the cold-storage drive unplugged only an already transcoded copy can play
+    if target.archived && !target.path.exists() {
+        if let Some((video_path, content_mime)) = target.transcoded_path.as_deref().and_then(transcode::cached_transcode) {
+            return serve_video_file(&video_path, content_mime, &headers).await.into_response();
         }
-    }).await;
+        return original_unavailable(&state, id).await;
+    }
 
-    match result {
-        Ok(Ok(results)) => {
-            let any_failure = results.iter().any(|r| !r.deleted);
-            let read_only_failures: Vec<_> = results
-                .iter()
-                .filter(|r| r.read_only)
-                .map(|r| serde_json::json!({
-                    "id": r.id,
-                    "path": r.path.clone(),
-                    "error": r.error.clone()
-                }))
-                .collect();
-            let status = if !read_only_failures.is_empty() {
-                StatusCode::CONFLICT
-            } else {
-                StatusCode::OK
-            };
-            (
-                status,
-                Json(serde_json::json!({
-                    "success": !any_failure,
-                    "results": results,
-                    "read_only_failures": read_only_failures
-                }))
-            ).into_response()
-        }
-        Ok(Err(e)) => {
-            tracing::error!("Error permanently deleting assets: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"success": false, "error": format!("Internal error: {}", e)}))
-            ).into_response()
-        }
-        Err(e) => {
-            tracing::error!("Task error permanently deleting assets: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"success": false, "error": "Internal error"}))
-            ).into_response()
+    if !target.needs_transcode {
+        // Browser-compatible format - serve original
+        return serve_video_file(&target.path, &target.mime, &headers).await.into_response();
+    }
+
+    let transcoded_path = match target.transcoded_path {
+        Some(p) => p,
+        None => {
+            tracing::warn!("Cannot transcode video {}: SHA256 not available", id);
+            // Fallback to original (will likely fail in browser, but file is available for download)
+            return serve_video_file(&target.path, &target.mime, &headers).await.into_response();
         }
+    };
+
+    if let Some((video_path, content_mime)) = transcode::cached_transcode(&transcoded_path) {
+        return serve_video_file(&video_path, content_mime, &headers).await.into_response();
+    }
+
+    // Not transcoded yet: hand it to the background worker instead of holding the request open
+    if let Some(TranscodeState::Failed { error }) = state.transcodes.get(id) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(TranscodeStatusResponse::new(id, "failed", Some(error)))).into_response();
+    }
+    match queue_transcode(&state, id, target.path, transcoded_path) {
+        Some(s) => (
+            StatusCode::ACCEPTED,
+            [(header::RETRY_AFTER, "5")],
+            Json(TranscodeStatusResponse::from_state(id, s)),
+        ).into_response(),
+        None => (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
+            "error": "Transcode queue is full"
+        }))).into_response(),
     }
 }
 
-pub async fn extract_audio_mp3(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
-    // Look up the asset path
-    let path = tokio::task::spawn_blocking({
+struct VideoTranscodeTarget {
+    path: std::path::PathBuf,
+    mime: String,
+    needs_transcode: bool,
+    /// None when the asset hasn't been hashed yet
+    transcoded_path: Option<std::path::PathBuf>,
+    archived: bool,
+}
+
+async fn video_transcode_target(state: &AppState, id: i64) -> Option<VideoTranscodeTarget> {
+    let asset = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         move || {
             let conn = pool.get().ok()?;
-            crate::db::query::get_asset_path(&conn, id).ok()?
+            crate::db::query::get_asset_by_id(&conn, id).ok()?
         }
-    }).await.ok().flatten();
+    }).await.ok().flatten()?;
+    // Use MIME type from database (more accurate than guessing from path)
+    let mime = if !asset.mime.is_empty() {
+        asset.mime.clone()
+    } else {
+        // Fallback to guessing from path if database MIME is empty
+        mime_guess::from_path(&asset.path).first_or_octet_stream().to_string()
+    };
+    let derived_dir = state.paths.data.join("derived");
+    Some(VideoTranscodeTarget {
+        needs_transcode: transcode::needs_transcode(&mime, asset.video_codec.as_deref()),
+        transcoded_path: asset.sha256.as_deref().filter(|s| !s.is_empty()).map(|sha| transcode::get_transcoded_video_path(&derived_dir, sha)),
+        path: std::path::PathBuf::from(asset.path),
+        mime,
+        archived: asset.archived,
+    })
+}
+
+/// Queue a transcode unless one is already pending. Returns the job's state, or None if the queue is full.
+/// Content type /asset/:id/video will serve: the original's, or its transcoded copy's.
+/// None while there's nothing playable yet, in which case the transcode is queued.
+pub(crate) async fn playable_video_mime(state: &AppState, id: i64) -> Option<String> {
+    let target = video_transcode_target(state, id).await?;
+    if !target.needs_transcode {
+        return Some(target.mime);
+    }
+    let transcoded_path = target.transcoded_path?;
+    if let Some((_, mime)) = transcode::cached_transcode(&transcoded_path) {
+        return Some(mime.to_string());
+    }
+    if target.path.exists() {
+        queue_transcode(state, id, target.path, transcoded_path);
+    }
+    None
+}
+
+fn queue_transcode(state: &AppState, asset_id: i64, src: std::path::PathBuf, dst: std::path::PathBuf) -> Option<TranscodeState> {
+    if !state.transcodes.try_queue(asset_id) {
+        return state.transcodes.get(asset_id);
+    }
+    match state.queues.transcode_tx.try_send(TranscodeJob { asset_id, src, dst }) {
+        Ok(()) => {
+            state.gauges.transcode.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Some(TranscodeState::Queued)
+        }
+        Err(_) => {
+            state.transcodes.clear(asset_id);
+            None
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TranscodeStatusResponse {
+    pub asset_id: i64,
+    /// not_needed, ready, queued, running, failed or none
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TranscodeStatusResponse {
+    fn new(asset_id: i64, status: &'static str, error: Option<String>) -> Self {
+        Self { asset_id, status, error }
+    }
+
+    fn from_state(asset_id: i64, state: TranscodeState) -> Self {
+        match state {
+            TranscodeState::Queued => Self::new(asset_id, "queued", None),
+            TranscodeState::Running => Self::new(asset_id, "running", None),
+            TranscodeState::Failed { error } => Self::new(asset_id, "failed", Some(error)),
+        }
+    }
+}
+
+fn transcode_status(state: &AppState, id: i64, target: &VideoTranscodeTarget) -> TranscodeStatusResponse {
+    if !target.needs_transcode {
+        return TranscodeStatusResponse::new(id, "not_needed", None);
+    }
+    if target.transcoded_path.as_deref().and_then(transcode::cached_transcode).is_some() {
+        return TranscodeStatusResponse::new(id, "ready", None);
+    }
+    match state.transcodes.get(id) {
+        Some(s) => TranscodeStatusResponse::from_state(id, s),
+        None => TranscodeStatusResponse::new(id, "none", None),
+    }
+}
+
+/// The motion half of a live photo, streamed like `GET /asset/:id/video`
+pub async fn stream_live_photo(State(state): State<Arc<AppState>>, Path(id): Path<i64>, headers: HeaderMap) -> impl IntoResponse {
+    let pool = state.pool.clone();
+    let video = tokio::task::spawn_blocking(move || -> anyhow::Result<Option<i64>> {
+        let conn = pool.get()?;
+        db::query::get_live_photo_video(&conn, id)
+    })
+    .await;
+    match video {
+        Ok(Ok(Some(video_id))) => stream_video(State(state), Path(video_id), headers).await.into_response(),
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset is not a live photo"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Database error looking up live photo {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error looking up live photo {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn get_transcode_status(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+    match video_transcode_target(&state, id).await {
+        Some(target) => (StatusCode::OK, Json(transcode_status(&state, id, &target))).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset not found"
+        }))).into_response(),
+    }
+}
+
+pub async fn request_transcode(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+    let target = match video_transcode_target(&state, id).await {
+        Some(t) => t,
+        None => return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset not found"
+        }))).into_response(),
+    };
+    let status = transcode_status(&state, id, &target);
+    if matches!(status.status, "not_needed" | "ready" | "queued" | "running") {
+        return (StatusCode::OK, Json(status)).into_response();
+    }
+    let Some(dst) = target.transcoded_path else {
+        return (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "Asset has not been hashed yet"
+        }))).into_response();
+    };
+    match queue_transcode(&state, id, target.path, dst) {
+        Some(s) => (StatusCode::ACCEPTED, Json(TranscodeStatusResponse::from_state(id, s))).into_response(),
+        None => (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
+            "error": "Transcode queue is full"
+        }))).into_response(),
+    }
+}
+
+async fn serve_video_file(file_path: &std::path::Path, mime_str: &str, headers: &HeaderMap) -> impl IntoResponse {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    // Verify file exists before attempting to serve
+    let metadata = match tokio::fs::metadata(file_path).await {
+        Ok(m) if m.is_file() => m,
+        Ok(_) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            tracing::error!("Video file not found or not accessible: {} - {}", file_path.display(), e);
+            return StatusCode::NOT_FOUND.into_response();
+        }
+    };
+    let file_size = metadata.len();
+
+    let mut file = match tokio::fs::File::open(file_path).await {
+        Ok(f) => f,
+        Err(e) => {
+            tracing::error!("Failed to open video file {}: {}", file_path.display(), e);
+            return StatusCode::NOT_FOUND.into_response();
+        }
+    };
+
+    let mut resp = axum::http::Response::builder();
+    let resp_headers = resp.headers_mut().unwrap();
+    resp_headers.insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_str(mime_str)
+            .unwrap_or_else(|_| header::HeaderValue::from_static("video/mp4"))
+    );
+    resp_headers.insert(
+        header::ACCEPT_RANGES,
+        header::HeaderValue::from_static("bytes")
+    );
+    // Add CORS headers for video streaming
+    resp_headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        header::HeaderValue::from_static("*")
+    );
+
+    // Parse Range header (e.g., "bytes=0-1023", "bytes=1024-" or "bytes=-500")
+    let range_str = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+    let (status, start, content_length) = match range_str.map(|r| parse_range(r, file_size)) {
+        Some(ByteRange::Partial(start, end)) => {
+            if let Ok(v) = header::HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, file_size)) {
+                resp_headers.insert(header::CONTENT_RANGE, v);
+            }
+            (StatusCode::PARTIAL_CONTENT, start, end - start + 1)
+        }
+        Some(ByteRange::Unsatisfiable) => {
+            let content_range = header::HeaderValue::from_str(&format!("bytes */{}", file_size))
+                .unwrap_or_else(|_| header::HeaderValue::from_static("bytes */*"));
+            return (StatusCode::RANGE_NOT_SATISFIABLE, [(header::CONTENT_RANGE, content_range)]).into_response();
+        }
+        Some(ByteRange::Full) | None => (StatusCode::OK, 0, file_size),
+    };
+    resp_headers.insert(
+        header::CONTENT_LENGTH,
+        header::HeaderValue::from(content_length)
+    );
+
+    if start > 0 {
+        if let Err(e) = file.seek(std::io::SeekFrom::Start(start)).await {
+            tracing::error!("Failed to seek video file {}: {}", file_path.display(), e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    }
+
+    // Stream the requested bytes in chunks; the body is only polled as fast as the client reads
+    let stream = tokio_util::io::ReaderStream::with_capacity(file.take(content_length), VIDEO_STREAM_CHUNK);
+    resp.status(status).body(axum::body::Body::from_stream(stream)).unwrap().into_response()
+}
+
+/// Read size for streamed video responses
+const VIDEO_STREAM_CHUNK: usize = 256 * 1024;
+
+/// What a Range header asks of a file
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    /// Inclusive (start, end), with the end clamped to the file
+    Partial(u64, u64),
+    /// A well-formed range that doesn't overlap the file (416)
+    Unsatisfiable,
+    /// A malformed or non-bytes range, which RFC 9110 says to ignore and serve the whole file
+    Full,
+}
+
+/// Parse a single-range "bytes=" header
+fn parse_range(range_str: &str, file_size: u64) -> ByteRange {
+    let Some((unit, ranges)) = range_str.trim().split_once('=') else {
+        return ByteRange::Full;
+    };
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return ByteRange::Full;
+    }
+    // Only the first range of a multi-range request is honoured
+    let first = ranges.split(',').next().unwrap_or("").trim();
+    let Some((start_str, end_str)) = first.split_once('-') else {
+        return ByteRange::Full;
+    };
+    let parse = |s: &str| if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) { s.parse::<u64>().ok() } else { None };
+
+    if start_str.is_empty() {
+        // Suffix range: the last N bytes
+        return match parse(end_str) {
+            None => ByteRange::Full,
+            Some(0) => ByteRange::Unsatisfiable,
+            Some(_) if file_size == 0 => ByteRange::Unsatisfiable,
+            Some(suffix) => ByteRange::Partial(file_size.saturating_sub(suffix), file_size - 1),
+        };
+    }
+
+    let Some(start) = parse(start_str) else {
+        return ByteRange::Full;
+    };
+    let end = if end_str.is_empty() {
+        None
+    } else {
+        match parse(end_str) {
+            Some(end) if end >= start => Some(end),
+            // Not digits, or ending before it starts: not a valid range
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= file_size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end.unwrap_or(u64::MAX).min(file_size - 1))
+}
+
+pub async fn download_asset(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+    let path = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || {
+            let conn = pool.get().ok()?;
+            crate::db::query::get_asset_path(&conn, id).ok()?
+        }
+    }).await.ok().flatten();
 
     if let Some(file_path) = path {
-        // Quick filter: only allow on video/audio assets
-        let mime = mime_guess::from_path(&file_path).first_or_octet_stream();
-        let mime_str = mime.essence_str();
-        let allow = mime_str.starts_with("video/") || mime_str.starts_with("audio/");
-        if !allow {
-            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
-                "success": false,
-                "error": "Audio extraction is only supported for video/audio files",
-            }))).into_response();
+        if let Ok(bytes) = tokio::fs::read(&file_path).await {
+            let filename = std::path::Path::new(&file_path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("file");
+
+            let mime = mime_guess::from_path(&file_path)
+                .first_or_octet_stream();
+
+            let mut resp = axum::http::Response::builder().status(StatusCode::OK);
+            let headers = resp.headers_mut().unwrap();
+            headers.insert(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_str(mime.as_ref()).unwrap_or_else(|_| header::HeaderValue::from_static("application/octet-stream"))
+            );
+            headers.insert(
+                header::CONTENT_DISPOSITION,
+                header::HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename))
+                    .unwrap_or_else(|_| header::HeaderValue::from_static("attachment"))
+            );
+            return resp.body(axum::body::Body::from(bytes)).unwrap();
+        }
+        return original_unavailable(&state, id).await;
+    }
+    StatusCode::NOT_FOUND.into_response()
+}
+
+/// Reply for an asset whose original can't be read: 503 naming the archive when it is on
+/// cold storage, so clients can tell an unplugged drive from a deleted file; otherwise 404
+pub(crate) async fn original_unavailable(state: &AppState, id: i64) -> axum::response::Response {
+    let archive = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || {
+            let conn = pool.get().ok()?;
+            db::query::get_asset_archive(&conn, id).ok()?
+        }
+    }).await.ok().flatten();
+
+    match archive {
+        Some(label) => (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
+            "error": "Original is offline",
+            "offline": true,
+            "archive_label": label
+        }))).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AssetFileInfo {
+    path: Option<String>,
+    sha256: Option<Vec<u8>>,
+    locked: bool,
+}
+
+fn fetch_asset_file_info(conn: &Connection, id: i64) -> rusqlite::Result<Option<AssetFileInfo>> {
+    conn
+        .prepare("SELECT path, sha256, locked FROM assets WHERE id = ?1")?
+        .query_row(params![id], |row| {
+            Ok(AssetFileInfo {
+                path: row.get(0)?,
+                sha256: row.get(1)?,
+                locked: row.get::<_, i64>(2)? != 0,
+            })
+        })
+        .optional()
+}
+
+enum DeleteDiskError {
+    ReadOnly(io::Error),
+    Other(io::Error),
+}
+
+fn is_read_only_error(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::PermissionDenied || err.to_string().to_lowercase().contains("read-only")
+}
+
+fn remove_original_file(path: &str, paths: &AppPaths) -> Result<(), DeleteDiskError> {
+    let resolved_path = crate::utils::path::resolve_asset_path(path, paths);
+    match std::fs::remove_file(&resolved_path) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) if is_read_only_error(&e) => Err(DeleteDiskError::ReadOnly(e)),
+        Err(e) => Err(DeleteDiskError::Other(e)),
+    }
+}
+
+#[derive(Serialize)]
+struct PermanentDeleteResponse {
+    success: bool,
+    deleted_from_disk: bool,
+    read_only: bool,
+    path: Option<String>,
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pending_delete_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delete_after: Option<i64>,
+}
+
+impl PermanentDeleteResponse {
+    fn success(path: Option<String>) -> Self {
+        Self {
+            success: true,
+            deleted_from_disk: true,
+            read_only: false,
+            path,
+            error: None,
+            pending_delete_id: None,
+            delete_after: None,
+        }
+    }
+
+    fn held(path: Option<String>, pending_delete_id: i64, delete_after: i64) -> Self {
+        Self {
+            success: true,
+            deleted_from_disk: false,
+            read_only: false,
+            path,
+            error: None,
+            pending_delete_id: Some(pending_delete_id),
+            delete_after: Some(delete_after),
+        }
+    }
+
+    #[allow(dead_code)]
+    fn not_found() -> Self {
+        Self {
+            success: false,
+            deleted_from_disk: false,
+            read_only: false,
+            path: None,
+            error: Some("Asset not found".to_string()),
+            pending_delete_id: None,
+            delete_after: None,
+        }
+    }
+
+    fn read_only(path: Option<String>, error: String) -> Self {
+        Self {
+            success: false,
+            deleted_from_disk: false,
+            read_only: true,
+            path,
+            error: Some(error),
+            pending_delete_id: None,
+            delete_after: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BulkPermanentDeleteRequest {
+    ids: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteHoldQuery {
+    /// Keep originals in the holding area this long before removing them (defaults to FLASH_DELETE_HOLD_SECS)
+    pub hold_secs: Option<i64>,
+}
+
+#[derive(Serialize, Default)]
+struct BulkPermanentDeleteResult {
+    id: i64,
+    deleted: bool,
+    read_only: bool,
+    locked: bool,
+    path: Option<String>,
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pending_delete_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delete_after: Option<i64>,
+}
+
+impl From<BulkPermanentDeleteResult> for PermanentDeleteResponse {
+    fn from(result: BulkPermanentDeleteResult) -> Self {
+        let BulkPermanentDeleteResult { deleted, read_only, path, error, pending_delete_id, delete_after, .. } = result;
+        if let (true, Some(pending_id), Some(after)) = (deleted, pending_delete_id, delete_after) {
+            return PermanentDeleteResponse::held(path, pending_id, after);
+        }
+        if deleted {
+            return PermanentDeleteResponse::success(path);
+        }
+        if read_only {
+            return PermanentDeleteResponse::read_only(
+                path,
+                error.unwrap_or_else(|| "File is read-only".to_string()),
+            );
+        }
+        PermanentDeleteResponse {
+            success: false,
+            deleted_from_disk: false,
+            read_only: false,
+            path,
+            error: Some(error.unwrap_or_else(|| "Asset not found".to_string())),
+            pending_delete_id: None,
+            delete_after: None,
+        }
+    }
+}
+
+/// Move the original into the holding area instead of removing it.
+/// Returns None when there is nothing on disk to hold, so the caller falls back to a plain delete.
+fn hold_original(conn: &Connection, paths: &AppPaths, id: i64, file_path: &str, sha: Option<&[u8]>, hold_secs: i64) -> Result<Option<BulkPermanentDeleteResult>> {
+    let resolved_path = crate::utils::path::resolve_asset_path(file_path, paths);
+    if !resolved_path.is_file() {
+        return Ok(None);
+    }
+    let Some(snapshot) = crate::db::writer::snapshot_asset(conn, id)? else {
+        return Ok(None);
+    };
+    let held = match delete_hold::move_into_hold(&resolved_path, &delete_hold::hold_dir(&paths.data), id) {
+        Ok(held) => held,
+        Err(e) if is_read_only_error(&e) => {
+            return Ok(Some(BulkPermanentDeleteResult {
+                id,
+                read_only: true,
+                path: Some(file_path.to_string()),
+                error: Some(e.to_string()),
+                ..Default::default()
+            }));
+        }
+        Err(e) => return Err(anyhow::Error::new(e)),
+    };
+    crate::db::query::delete_asset_by_id(conn, id)?;
+    let delete_after = chrono::Utc::now().timestamp() + hold_secs;
+    let pending_id = crate::db::writer::insert_pending_delete(
+        conn,
+        id,
+        &resolved_path.to_string_lossy(),
+        &held.to_string_lossy(),
+        sha,
+        &snapshot,
+        delete_after,
+    )?;
+    info!("delete_held: asset {} held as {:?} until {}", id, held, delete_after);
+    Ok(Some(BulkPermanentDeleteResult {
+        id,
+        deleted: true,
+        path: Some(file_path.to_string()),
+        pending_delete_id: Some(pending_id),
+        delete_after: Some(delete_after),
+        ..Default::default()
+    }))
+}
+
+fn perform_permanent_delete(conn: &Connection, derived_dir: &StdPath, paths: &AppPaths, id: i64, hold_secs: i64) -> Result<BulkPermanentDeleteResult> {
+    let not_found = |path: Option<String>| BulkPermanentDeleteResult { id, path, error: Some("Asset not found".to_string()), ..Default::default() };
+    let asset_info = fetch_asset_file_info(conn, id)?;
+    if let Some(info) = asset_info {
+        let path = info.path.clone();
+        let sha = info.sha256.clone();
+        if info.locked {
+            return Ok(BulkPermanentDeleteResult { id, locked: true, path, error: Some(LOCKED_ERROR.to_string()), ..Default::default() });
+        }
+        if let Some(ref file_path) = path {
+            if hold_secs > 0 {
+                if let Some(held) = hold_original(conn, paths, id, file_path, sha.as_deref(), hold_secs)? {
+                    return Ok(held);
+                }
+            }
+            match remove_original_file(file_path, paths) {
+                Ok(_) => {
+                    let deleted = crate::db::query::delete_asset_by_id(conn, id)?;
+                    if deleted {
+                        remove_derived_files(sha.as_deref(), derived_dir);
+                        Ok(BulkPermanentDeleteResult { id, deleted: true, path, ..Default::default() })
+                    } else {
+                        Ok(not_found(path))
+                    }
+                }
+                Err(DeleteDiskError::ReadOnly(err)) => Ok(BulkPermanentDeleteResult {
+                    id,
+                    read_only: true,
+                    path,
+                    error: Some(err.to_string()),
+                    ..Default::default()
+                }),
+                Err(DeleteDiskError::Other(err)) => Err(anyhow::Error::new(err)),
+            }
+        } else {
+            let deleted = crate::db::query::delete_asset_by_id(conn, id)?;
+            if deleted {
+                remove_derived_files(sha.as_deref(), derived_dir);
+                Ok(BulkPermanentDeleteResult { id, deleted: true, ..Default::default() })
+            } else {
+                Ok(not_found(None))
+            }
+        }
+    } else {
+        Ok(not_found(None))
+    }
+}
+
+pub async fn delete_asset(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+    if let Some(response) = reject_locked(&state, id).await {
+        return response;
+    }
+    let derived_dir = state.paths.data.join("derived");
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let derived_dir = derived_dir.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let asset_info = fetch_asset_file_info(&conn, id)?;
+            let deleted = crate::db::query::delete_asset_by_id(&conn, id)?;
+            if deleted {
+                if let Some(info) = asset_info {
+                    remove_derived_files(info.sha256.as_deref(), derived_dir.as_path());
+                }
+            }
+            Ok(deleted)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({"success": true}))).into_response(),
+        Ok(Ok(false)) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"success": false, "error": "Asset not found"}))
+        ).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error deleting asset {}: {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "success": false,
+                    "error": format!("Internal error: {}", e)
+                }))
+            ).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error deleting asset {}: {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"success": false, "error": "Internal error"}))
+            ).into_response()
+        }
+    }
+}
+
+pub async fn delete_asset_permanent(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Query(q): Query<DeleteHoldQuery>) -> impl IntoResponse {
+    let hold_secs = q.hold_secs.unwrap_or_else(delete_hold::default_hold_secs);
+    if hold_secs < 0 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": "hold_secs must not be negative"}))
+        ).into_response();
+    }
+    let derived_dir = state.paths.data.join("derived");
+    let paths = state.paths.clone();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let derived_dir = derived_dir.clone();
+        let paths = paths.clone();
+        move || -> Result<BulkPermanentDeleteResult> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            perform_permanent_delete(&conn, &derived_dir, &paths, id, hold_secs)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(outcome)) => {
+            let status = if outcome.deleted {
+                StatusCode::OK
+            } else if outcome.read_only {
+                StatusCode::CONFLICT
+            } else if outcome.locked {
+                StatusCode::LOCKED
+            } else if outcome.error.as_deref() == Some("Asset not found") {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::BAD_REQUEST
+            };
+            let response: PermanentDeleteResponse = outcome.into();
+            (status, Json(response)).into_response()
+        }
+        Ok(Err(e)) => {
+            tracing::error!("Error permanently deleting asset {}: {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"success": false, "error": format!("Internal error: {}", e)}))
+            ).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error permanently deleting asset {}: {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"success": false, "error": "Internal error"}))
+            ).into_response()
+        }
+    }
+}
+
+pub async fn delete_assets_permanent(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(q): Query<DeleteHoldQuery>,
+    Json(payload): Json<BulkPermanentDeleteRequest>
+) -> impl IntoResponse {
+    if payload.ids.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": "No asset IDs provided"}))
+        ).into_response();
+    }
+    let hold_secs = q.hold_secs.unwrap_or_else(delete_hold::default_hold_secs);
+    if hold_secs < 0 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": "hold_secs must not be negative"}))
+        ).into_response();
+    }
+
+    let derived_dir = state.paths.data.join("derived");
+    let paths = state.paths.clone();
+    let ids = payload.ids;
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let derived_dir = derived_dir.clone();
+        let paths = paths.clone();
+        move || -> Result<Vec<BulkPermanentDeleteResult>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let editable: std::collections::HashSet<i64> =
+                db::query::filter_editable_assets(&conn, ids.clone(), principal.scope())?.into_iter().collect();
+            let mut outcomes = Vec::with_capacity(ids.len());
+            for asset_id in ids {
+                if !editable.contains(&asset_id) {
+                    outcomes.push(BulkPermanentDeleteResult {
+                        id: asset_id,
+                        error: Some("Asset not found".to_string()),
+                        ..Default::default()
+                    });
+                    continue;
+                }
+                let outcome = perform_permanent_delete(&conn, &derived_dir, &paths, asset_id, hold_secs)?;
+                outcomes.push(outcome);
+            }
+            Ok(outcomes)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(results)) => {
+            let any_failure = results.iter().any(|r| !r.deleted);
+            let read_only_failures: Vec<_> = results
+                .iter()
+                .filter(|r| r.read_only)
+                .map(|r| serde_json::json!({
+                    "id": r.id,
+                    "path": r.path.clone(),
+                    "error": r.error.clone()
+                }))
+                .collect();
+            let status = if !read_only_failures.is_empty() {
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::OK
+            };
+            (
+                status,
+                Json(serde_json::json!({
+                    "success": !any_failure,
+                    "results": results,
+                    "read_only_failures": read_only_failures
+                }))
+            ).into_response()
+        }
+        Ok(Err(e)) => {
+            tracing::error!("Error permanently deleting assets: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"success": false, "error": format!("Internal error: {}", e)}))
+            ).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error permanently deleting assets: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"success": false, "error": "Internal error"}))
+            ).into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PendingDeleteResponse {
+    pub id: i64,
+    pub asset_id: i64,
+    pub original_path: String,
+    pub held_path: String,
+    pub delete_after: i64,
+    pub created_at: i64,
+}
+
+pub async fn list_pending_deletes(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>) -> impl IntoResponse {
+    let scope = principal.scope();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<PendingDeleteResponse>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let rows = db::query::list_pending_deletes(&conn, scope)?;
+            Ok(rows
+                .into_iter()
+                .map(|(id, asset_id, original_path, held_path, delete_after, created_at)| PendingDeleteResponse {
+                    id,
+                    asset_id,
+                    original_path,
+                    held_path,
+                    delete_after,
+                    created_at,
+                })
+                .collect())
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(items)) => (StatusCode::OK, Json(items)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error listing pending deletes: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error listing pending deletes: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+enum UndoDeleteOutcome {
+    Restored(i64, String),
+    NotFound,
+    Conflict(String),
+}
+
+pub async fn undo_pending_delete(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Path(id): Path<i64>) -> impl IntoResponse {
+    let scope = principal.scope();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<UndoDeleteOutcome> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let Some((original_path, held_path, snapshot_json)) = db::query::get_pending_delete(&conn, id, scope)? else {
+                return Ok(UndoDeleteOutcome::NotFound);
+            };
+            let snapshot: serde_json::Value = serde_json::from_str(&snapshot_json)?;
+            let db_path = snapshot
+                .pointer("/asset/path")
+                .and_then(|p| p.as_str())
+                .unwrap_or(&original_path)
+                .to_string();
+            if conn.query_row("SELECT 1 FROM assets WHERE path = ?1", params![db_path], |_| Ok(())).is_ok() {
+                return Ok(UndoDeleteOutcome::Conflict(format!("An asset already exists at {}", db_path)));
+            }
+            match delete_hold::move_out_of_hold(StdPath::new(&held_path), StdPath::new(&original_path)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    return Ok(UndoDeleteOutcome::Conflict(e.to_string()));
+                }
+                Err(e) => return Err(anyhow::Error::new(e)),
+            }
+            let asset_id = db::writer::restore_asset_snapshot(&conn, &snapshot)?;
+            db::writer::remove_pending_delete(&conn, id)?;
+            Ok(UndoDeleteOutcome::Restored(asset_id, db_path))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(UndoDeleteOutcome::Restored(asset_id, path))) => {
+            info!("delete_undone: pending {} restored as asset {}", id, asset_id);
+            (StatusCode::OK, Json(serde_json::json!({
+                "success": true,
+                "asset_id": asset_id,
+                "path": path
+            }))).into_response()
+        }
+        Ok(Ok(UndoDeleteOutcome::NotFound)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Pending delete not found"
+        }))).into_response(),
+        Ok(Ok(UndoDeleteOutcome::Conflict(msg))) => (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": msg
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error undoing pending delete {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Undo failed: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error undoing pending delete {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn extract_audio_mp3(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+    // Look up the asset path
+    let path = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || {
+            let conn = pool.get().ok()?;
+            crate::db::query::get_asset_path(&conn, id).ok()?
+        }
+    }).await.ok().flatten();
+
+    if let Some(file_path) = path {
+        // Quick filter: only allow on video/audio assets
+        let mime = mime_guess::from_path(&file_path).first_or_octet_stream();
+        let mime_str = mime.essence_str();
+        let allow = mime_str.starts_with("video/") || mime_str.starts_with("audio/");
+        if !allow {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "success": false,
+                "error": "Audio extraction is only supported for video/audio files",
+            }))).into_response();
+        }
+        if !StdPath::new(&file_path).exists() {
+            return original_unavailable(&state, id).await;
+        }
+
+        // If already mp3 audio, stream original file (no transcode)
+        if mime_str == "audio/mpeg" {
+            if let Ok(bytes) = tokio::fs::read(&file_path).await {
+                let base = StdPath::new(&file_path)
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("audio");
+                let mut resp = axum::http::Response::builder().status(StatusCode::OK);
+                let headers = resp.headers_mut().unwrap();
+                headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("audio/mpeg"));
+                headers.insert(
+                    header::CONTENT_DISPOSITION,
+                    header::HeaderValue::from_str(&format!("attachment; filename=\"{}.mp3\"", base))
+                        .unwrap_or_else(|_| header::HeaderValue::from_static("attachment"))
+                );
+                return resp.body(axum::body::Body::from(bytes)).unwrap();
+            }
+        }
+
+        // Otherwise, transcode/extract to mp3 using ffmpeg to stdout
+        let file_path_for_closure = file_path.clone();
+        let file_path_for_log = file_path.clone();
+        tracing::info!("Starting audio extraction for asset {}: {}", id, file_path_for_log);
+        let res = tokio::task::spawn_blocking(move || {
+            // Try libmp3lame first (best quality), then libshine (if built), then generic mp3
+            let encoders = vec!["libmp3lame", "libshine", "mp3"];
+            let mut last_error = None;
+
+            for encoder in encoders {
+                tracing::info!("Attempting audio extraction with encoder '{}' for {}", encoder, file_path_for_closure);
+                let args = vec![
+                    "-i".to_string(), file_path_for_closure.clone(),
+                    "-vn".to_string(),
+                    "-acodec".to_string(), encoder.to_string(),
+                    "-ab".to_string(), "192k".to_string(),
+                    "-f".to_string(), "mp3".to_string(),
+                    "-map".to_string(), "a:0".to_string(),
+                    "-hide_banner".to_string(),
+                    "-loglevel".to_string(), "warning".to_string(), // Changed to warning to see progress
+                    "-".to_string(),
+                ];
+
+                // Log the full command for debugging
+                let cmd_str = args.join(" ");
+                tracing::info!("FFmpeg command: {}", cmd_str);
+
+                let start_time = std::time::Instant::now();
+                match ffmpeg::run_ffmpeg_with_timeout(args.clone(), std::time::Duration::from_secs(600)) {
+                    Ok(output) if output.status.success() => {
+                        let elapsed = start_time.elapsed();
+                        tracing::info!("Audio extraction succeeded with encoder '{}' in {:?}, output size: {} bytes",
+                            encoder, elapsed, output.stdout.len());
+                        return Ok(output);
+                    }
+                    Ok(output) => {
+                        let elapsed = start_time.elapsed();
+                        let err_msg = String::from_utf8_lossy(&output.stderr).to_string();
+                        tracing::warn!("Encoder '{}' failed after {:?} (exit code: {}): {}",
+                            encoder, elapsed, output.status.code().unwrap_or(-1), err_msg);
+                        last_error = Some(format!("Encoder '{}' failed: {}", encoder, err_msg));
+                        // Continue to next encoder
+                    }
+                    Err(e) => {
+                        let elapsed = start_time.elapsed();
+                        let error_msg = format!("{}", e);
+                        tracing::error!("Encoder '{}' error after {:?}: {}", encoder, elapsed, error_msg);
+                        last_error = Some(format!("Encoder '{}' error: {}", encoder, error_msg));
+                        // Continue to next encoder
+                    }
+                }
+            }
+
+            // All encoders failed
+            tracing::error!("All MP3 encoders failed for {}", file_path_for_closure);
+            Err(anyhow::anyhow!("All MP3 encoders failed. Last error: {}", last_error.unwrap_or_else(|| "Unknown error".to_string())))
+        }).await;
+
+        match res {
+            Ok(Ok(output)) => {
+                // Success - output is already verified in the loop
+                let base = StdPath::new(&file_path)
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("audio");
+                let mut resp = axum::http::Response::builder().status(StatusCode::OK);
+                let headers = resp.headers_mut().unwrap();
+                headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("audio/mpeg"));
+                headers.insert(
+                    header::CONTENT_DISPOSITION,
+                    header::HeaderValue::from_str(&format!("attachment; filename=\"{}.mp3\"", base))
+                        .unwrap_or_else(|_| header::HeaderValue::from_static("attachment"))
+                );
+                return resp.body(axum::body::Body::from(output.stdout)).unwrap();
+            }
+            _ => {
+                // Fallback: try AAC in M4A container when MP3 encoders are not available
+                tracing::warn!("MP3 extraction failed; attempting AAC/m4a fallback");
+                let fallback = tokio::task::spawn_blocking({
+                    let fp = file_path.clone();
+                    move || {
+                        let args = vec![
+                            "-i".to_string(), fp.clone(),
+                            "-vn".to_string(),
+                            "-c:a".to_string(), "aac".to_string(),
+                            "-b:a".to_string(), "192k".to_string(),
+                            "-movflags".to_string(), "+faststart".to_string(),
+                            "-f".to_string(), "mp4".to_string(),
+                            "-map".to_string(), "a:0".to_string(),
+                            "-hide_banner".to_string(),
+                            "-loglevel".to_string(), "error".to_string(),
+                            "-".to_string(),
+                        ];
+                        ffmpeg::run_ffmpeg_with_timeout(args, std::time::Duration::from_secs(600))
+                    }
+                }).await;
+
+                match fallback {
+                    Ok(Ok(out2)) if out2.status.success() => {
+                        let base = StdPath::new(&file_path)
+                            .file_stem()
+                            .and_then(|s| s.to_str())
+                            .unwrap_or("audio");
+                        let mut resp = axum::http::Response::builder().status(StatusCode::OK);
+                        let headers = resp.headers_mut().unwrap();
+                        headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("audio/mp4"));
+                        headers.insert(
+                            header::CONTENT_DISPOSITION,
+                            header::HeaderValue::from_str(&format!("attachment; filename=\"{}.m4a\"", base))
+                                .unwrap_or_else(|_| header::HeaderValue::from_static("attachment"))
+                        );
+                        return resp.body(axum::body::Body::from(out2.stdout)).unwrap();
+                    }
+                    Ok(Ok(out2)) => {
+                        let err2 = String::from_utf8_lossy(&out2.stderr).to_string();
+                        tracing::error!("ffmpeg aac fallback failed: {}", err2);
+                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                            "success": false,
+                            "error": "FFmpeg failed to extract audio",
+                            "details": err2,
+                        }))).into_response();
+                    }
+                    Ok(Err(e)) => {
+                        tracing::error!("FFmpeg error (fallback): {}", e);
+                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                            "success": false,
+                            "error": format!("FFmpeg error: {}", e),
+                        }))).into_response();
+                    }
+                    Err(e) => {
+                        tracing::error!("Task join error running ffmpeg (fallback): {}", e);
+                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                            "success": false,
+                            "error": "Internal error",
+                        }))).into_response();
+                    }
+                }
+            }
+        }
+    }
+
+    StatusCode::NOT_FOUND.into_response()
+}
+
+#[derive(Deserialize)]
+pub struct BrowseQuery {
+    path: Option<String>,
+}
+
+pub async fn browse_directory(State(_state): State<Arc<AppState>>, Query(params): Query<BrowseQuery>) -> impl IntoResponse {
+    let requested_path = params.path.as_deref().unwrap_or("/");
+
+    // Allow browsing from container root (/)
+    // Security: Only allow absolute paths, prevent directory traversal attacks
+    let target_path = if requested_path == "/" || requested_path.is_empty() {
+        std::path::PathBuf::from("/")
+    } else {
+        let requested = std::path::PathBuf::from(requested_path);
+
+        // Only allow absolute paths
+        if !requested.is_absolute() {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "Path must be absolute"
+            })));
+        }
+
+        // Normalize the path to prevent directory traversal
+        let resolved = requested.canonicalize().unwrap_or(requested);
+
+        // Ensure it's still absolute after canonicalization
+        if !resolved.is_absolute() {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "Invalid path"
+            })));
+        }
+
+        resolved
+    };
+
+    let result = tokio::task::spawn_blocking({
+        let path = target_path.clone();
+        move || -> Result<Vec<serde_json::Value>, anyhow::Error> {
+            let mut entries = Vec::new();
+
+            if !path.exists() {
+                return Ok(entries);
+            }
+
+            if !path.is_dir() {
+                return Err(anyhow::anyhow!("Path is not a directory"));
+            }
+
+            let dir_entries = std::fs::read_dir(&path)?;
+            let mut dirs = Vec::new();
+            let mut files = Vec::new();
+
+            for entry_result in dir_entries {
+                let entry = entry_result?;
+                let entry_path = entry.path();
+                let metadata = entry.metadata().ok();
+
+                // Skip hidden files/directories
+                if entry_path.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|s| s.starts_with('.'))
+                    .unwrap_or(false) {
+                    continue;
+                }
+
+                let is_dir = metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false);
+                let name = entry_path.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+
+                let entry_json = serde_json::json!({
+                    "name": name,
+                    "path": entry_path.to_string_lossy().to_string(),
+                    "is_dir": is_dir,
+                });
+
+                if is_dir {
+                    dirs.push(entry_json);
+                } else {
+                    files.push(entry_json);
+                }
+            }
+
+            // Sort: directories first, then files, both alphabetically
+            dirs.sort_by(|a, b| {
+                a["name"].as_str().unwrap_or("").cmp(b["name"].as_str().unwrap_or(""))
+            });
+            files.sort_by(|a, b| {
+                a["name"].as_str().unwrap_or("").cmp(b["name"].as_str().unwrap_or(""))
+            });
+
+            entries.extend(dirs);
+            entries.extend(files);
+
+            Ok(entries)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(entries)) => {
+            (StatusCode::OK, Json(serde_json::json!({
+                "path": target_path.to_string_lossy().to_string(),
+                "entries": entries
+            })))
+        },
+        Ok(Err(e)) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": e.to_string()
+            })))
+        },
+        Err(e) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Task error: {}", e)
+            })))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SaveOrientationRequest {
+    rotation: i32,
+}
+
+pub async fn save_orientation(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<SaveOrientationRequest>) -> impl IntoResponse {
+    let pool = state.pool.clone();
+    let paths = state.paths.clone();
+    let rotation = req.rotation;
+
+    // Normalize rotation to 0-360 range
+    let normalized_rotation = ((rotation % 360) + 360) % 360;
+
+    // Only allow 90-degree increments
+    if normalized_rotation != 0 && normalized_rotation != 90 && normalized_rotation != 180 && normalized_rotation != 270 {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "success": false,
+            "error": "Rotation must be a multiple of 90 degrees"
+        }))).into_response();
+    }
+    if let Some(response) = reject_locked(&state, id).await {
+        return response;
+    }
+
+    let result = tokio::task::spawn_blocking(move || -> Result<()> {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+
+        // Get asset path
+        let path: String = conn.query_row(
+            "SELECT path FROM assets WHERE id = ?1",
+            params![id],
+            |row| row.get(0)
+        )?;
+
+        let resolved_path = crate::utils::path::resolve_asset_path(&path, &paths);
+        if !resolved_path.exists() {
+            anyhow::bail!(
+                "File not found: stored path '{}', resolved path '{}'",
+                path,
+                resolved_path.display()
+            );
+        }
+
+        #[cfg(not(target_env = "msvc"))]
+        {
+            let resolved_str = resolved_path.to_string_lossy();
+            // Load image with libvips
+            let img = libvips::VipsImage::new_from_file(resolved_str.as_ref())?;
+
+            // Determine rotation angle based on normalized rotation
+            let rotated = match normalized_rotation {
+                90 => libvips::ops::rot(&img, Angle::D90)?,
+                180 => libvips::ops::rot(&img, Angle::D180)?,
+                270 => libvips::ops::rot(&img, Angle::D270)?,
+                _ => img, // 0 degrees, no rotation needed
+            };
+
+            // Save rotated image back to disk
+            // Use the same format as the original file
+            rotated.image_write_to_file(resolved_str.as_ref())?;
+            Ok(())
+        }
+        #[cfg(target_env = "msvc")]
+        {
+            anyhow::bail!("Image rotation not available on Windows MSVC (libvips not supported)")
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(())) => {
+            (StatusCode::OK, Json(serde_json::json!({
+                "success": true
+            }))).into_response()
+        },
+        Ok(Err(e)) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            }))).into_response()
+        },
+        Err(e) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "success": false,
+                "error": format!("Task error: {}", e)
+            }))).into_response()
+        }
+    }
+}
+
+// Album handlers
+
+#[derive(Deserialize)]
+pub struct CreateAlbumRequest {
+    pub name: String,
+    pub description: Option<String>,
+    /// Filter rules; when present the album is a smart album
+    pub rules: Option<SmartAlbumRules>,
+}
+
+#[derive(Serialize)]
+pub struct AlbumResponse {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub asset_ids: Vec<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub smart: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rules: Option<SmartAlbumRules>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateAlbumRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub rules: Option<SmartAlbumRules>,
+}
+
+/// Result of adding/removing assets on an album
+enum AlbumAssetsOutcome {
+    Updated(Box<AlbumResponse>),
+    NotFound,
+    SmartAlbum,
+}
+
+#[derive(Deserialize)]
+pub struct AddAssetsToAlbumRequest {
+    pub asset_ids: Vec<i64>,
+}
+
+/// An album with its asset ids narrowed to what `scope` may see
+pub(crate) fn visible_album(conn: &Connection, id: i64, scope: AccessScope) -> Result<Option<db::query::AlbumDetail>> {
+    match db::query::get_album(conn, id)? {
+        Some(mut album) => {
+            album.5 = db::query::filter_visible_assets(conn, std::mem::take(&mut album.5), scope)?;
+            Ok(Some(album))
+        }
+        None => Ok(None),
+    }
+}
+
+pub async fn list_albums(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>) -> impl IntoResponse {
+    let scope = principal.scope();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<AlbumResponse>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            // Use optimized single-query function (no N+1)
+            let albums = db::query::list_albums_with_assets(&conn, scope)?;
+            let mut responses = Vec::with_capacity(albums.len());
+            for (id, name, description, created_at, updated_at, asset_ids, rules) in albums {
+                responses.push(AlbumResponse {
+                    id,
+                    name,
+                    description,
+                    asset_ids: db::query::filter_visible_assets(&conn, asset_ids, scope)?,
+                    created_at,
+                    updated_at,
+                    smart: rules.is_some(),
+                    rules,
+                });
+            }
+            Ok(responses)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(albums)) => (StatusCode::OK, Json(albums)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error listing albums: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error listing albums: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn get_album(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Path(id): Path<i64>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<AlbumResponse>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if let Some((id, name, description, created_at, updated_at, asset_ids, rules)) = visible_album(&conn, id, principal.scope())? {
+                Ok(Some(AlbumResponse {
+                    id,
+                    name,
+                    description,
+                    asset_ids,
+                    created_at,
+                    updated_at,
+                    smart: rules.is_some(),
+                    rules,
+                }))
+            } else {
+                Ok(None)
+            }
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(album))) => (StatusCode::OK, Json(album)).into_response(),
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error getting album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error getting album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AlbumStatsQuery {
+    /// How many people and places to return (default 10)
+    pub top: Option<usize>,
+}
+
+pub async fn get_album_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+    Query(q): Query<AlbumStatsQuery>,
+) -> impl IntoResponse {
+    let top = q.top.unwrap_or(10).clamp(1, 100);
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<crate::models::album::AlbumStats>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            match visible_album(&conn, id, principal.scope())? {
+                Some((_, _, _, _, _, asset_ids, _)) => Ok(Some(db::query::album_stats(&conn, &asset_ids, top)?)),
+                None => Ok(None),
+            }
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(stats))) => (StatusCode::OK, Json(stats)).into_response(),
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error getting album stats: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error getting album stats: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HighlightQuery {
+    /// Number of slides (default: 20, 2-60)
+    pub max_slides: Option<usize>,
+    /// Seconds each slide is shown (default: 3, 1.5-10)
+    pub seconds: Option<f32>,
+}
+
+/// Render a crossfaded slideshow of the album's best shots into derived/highlights
+pub async fn create_album_highlight(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+    Query(q): Query<HighlightQuery>,
+) -> impl IntoResponse {
+    use crate::pipeline::highlight::{self, HighlightCandidate};
+    use crate::pipeline::transcode::TranscodeState;
+
+    if let Some(current @ (TranscodeState::Queued | TranscodeState::Running)) = state.highlights.get(id) {
+        return (StatusCode::ACCEPTED, Json(current)).into_response();
+    }
+    let max_slides = q.max_slides.unwrap_or(highlight::DEFAULT_MAX_SLIDES).clamp(2, 60);
+    let slide_secs = q.seconds.unwrap_or(highlight::DEFAULT_SLIDE_SECS).clamp(1.5, 10.0);
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let derived = state.paths.derived.clone();
+        move || -> Result<Option<Vec<std::path::PathBuf>>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let Some((_, _, _, _, _, asset_ids, _)) = visible_album(&conn, id, principal.scope())? else {
+                return Ok(None);
+            };
+            // Slides are the 1600px previews, so assets still waiting for thumbnails are skipped
+            let mut previews = std::collections::HashMap::new();
+            let mut candidates = Vec::new();
+            for (asset_id, sha, taken_at, rating, favorite, face_count) in db::query::highlight_candidates(&conn, &asset_ids)? {
+                let Some(sha) = sha.filter(|s| s.len() >= 2) else { continue };
+                let preview = crate::pipeline::thumb::thumb_path(&derived, &sha, 1600);
+                if preview.exists() {
+                    previews.insert(asset_id, preview);
+                    candidates.push(HighlightCandidate { id: asset_id, taken_at, rating, favorite, face_count });
+                }
+            }
+            Ok(Some(highlight::pick_highlights(&candidates, max_slides).into_iter().filter_map(|id| previews.remove(&id)).collect()))
+        }
+    }).await;
+
+    let slides = match result {
+        Ok(Ok(Some(slides))) if !slides.is_empty() => slides,
+        Ok(Ok(Some(_))) => return (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "Album has no assets with previews yet"
+        }))).into_response(),
+        Ok(Ok(None)) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error picking highlight assets: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response();
+        }
+        Err(e) => {
+            tracing::error!("Task error picking highlight assets: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response();
+        }
+    };
+
+    if !state.highlights.try_queue(id) {
+        let current = state.highlights.get(id).unwrap_or(TranscodeState::Queued);
+        return (StatusCode::ACCEPTED, Json(current)).into_response();
+    }
+    let slide_count = slides.len();
+    let tracker = state.highlights.clone();
+    let dst = highlight::highlight_path(&state.paths.derived, id);
+    tokio::spawn(async move {
+        tracker.set(id, TranscodeState::Running);
+        let rendered = tokio::task::spawn_blocking(move || highlight::render_slideshow(&slides, &dst, slide_secs)).await;
+        match rendered {
+            Ok(Ok(())) => {
+                info!("highlight_rendered: album {} ({} slides)", id, slide_count);
+                tracker.clear(id);
+            }
+            Ok(Err(e)) => {
+                tracing::error!("Highlight video for album {} failed: {}", id, e);
+                tracker.set(id, TranscodeState::Failed { error: e.to_string() });
+            }
+            Err(e) => {
+                tracing::error!("Task error rendering highlight for album {}: {}", id, e);
+                tracker.set(id, TranscodeState::Failed { error: "Internal server error".to_string() });
+            }
+        }
+    });
+
+    (StatusCode::ACCEPTED, Json(serde_json::json!({
+        "status": "queued",
+        "slides": slide_count
+    }))).into_response()
+}
+
+/// Stream the album's highlight video, or report the render status while it isn't ready
+pub async fn get_album_highlight(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let visible = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            Ok(visible_album(&conn, id, principal.scope())?.is_some())
+        }
+    }).await;
+    match visible {
+        Ok(Ok(true)) => {}
+        Ok(Ok(false)) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error getting album for highlight: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response();
+        }
+        Err(e) => {
+            tracing::error!("Task error getting album for highlight: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response();
+        }
+    }
+
+    // An existing video keeps playing while a newer one renders
+    let path = crate::pipeline::highlight::highlight_path(&state.paths.derived, id);
+    if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+        return serve_video_file(&path, "video/mp4", &headers).await.into_response();
+    }
+    match state.highlights.get(id) {
+        Some(failed @ crate::pipeline::transcode::TranscodeState::Failed { .. }) => (StatusCode::INTERNAL_SERVER_ERROR, Json(failed)).into_response(),
+        Some(pending) => (StatusCode::ACCEPTED, Json(pending)).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "No highlight video yet; POST to this URL to create one"
+        }))).into_response(),
+    }
+}
+
+pub async fn get_album_people(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Path(id): Path<i64>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<Vec<crate::models::album::AlbumPersonCount>>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            match visible_album(&conn, id, principal.scope())? {
+                Some((_, _, _, _, _, asset_ids, _)) => Ok(Some(db::query::album_people(&conn, &asset_ids, None)?)),
+                None => Ok(None),
+            }
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(people))) => (StatusCode::OK, Json(people)).into_response(),
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error getting album people: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error getting album people: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn create_album(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Json(req): Json<CreateAlbumRequest>) -> impl IntoResponse {
+    if req.rules.as_ref().is_some_and(|r| r.is_empty()) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Smart album rules must set at least one filter"
+        }))).into_response();
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let name = req.name.clone();
+        let description = req.description.clone();
+        let rules = req.rules.clone();
+        move || -> Result<AlbumResponse> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let id = db::writer::create_album(&conn, &name, description.as_deref(), rules.as_ref())?;
+            if let Some(user_id) = principal.user_id() {
+                db::writer::set_album_owner(&conn, id, Some(user_id))?;
+            }
+            // Get the created album
+            if let Some((id, name, description, created_at, updated_at, asset_ids, rules)) = db::query::get_album(&conn, id)? {
+                Ok(AlbumResponse {
+                    id,
+                    name,
+                    description,
+                    asset_ids,
+                    created_at,
+                    updated_at,
+                    smart: rules.is_some(),
+                    rules,
+                })
+            } else {
+                Err(anyhow::anyhow!("Failed to retrieve created album"))
+            }
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(album)) => (StatusCode::CREATED, Json(album)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error creating album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error creating album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn update_album(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<UpdateAlbumRequest>) -> impl IntoResponse {
+    if req.rules.as_ref().is_some_and(|r| r.is_empty()) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Smart album rules must set at least one filter"
+        }))).into_response();
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let name = req.name.clone();
+        let description = req.description.clone();
+        let rules = req.rules.clone();
+        move || -> Result<std::result::Result<Option<AlbumResponse>, &'static str>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            // A shared album turned smart would show its sharees nothing, see is_smart_album
+            if rules.is_some() && !db::query::list_album_shares(&conn, id)?.is_empty() {
+                return Ok(Err(SMART_ALBUM_SHARE_ERROR));
+            }
+            let mut updated = db::writer::update_album(&conn, id, name.as_deref(), description.as_deref())?;
+            if let Some(rules) = &rules {
+                updated = db::writer::set_album_rules(&conn, id, rules)?;
+            }
+            if updated {
+                // Get the updated album
+                if let Some((id, name, description, created_at, updated_at, asset_ids, rules)) = db::query::get_album(&conn, id)? {
+                    Ok(Ok(Some(AlbumResponse {
+                        id,
+                        name,
+                        description,
+                        asset_ids,
+                        created_at,
+                        updated_at,
+                        smart: rules.is_some(),
+                        rules,
+                    })))
+                } else {
+                    Ok(Ok(None))
+                }
+            } else {
+                Ok(Ok(None))
+            }
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Ok(Some(album)))) => (StatusCode::OK, Json(album)).into_response(),
+        Ok(Ok(Ok(None))) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Ok(Err(msg))) => (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": msg
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error updating album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error updating album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn delete_album(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::delete_album(&conn, id)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(true)) => {
+            state.highlights.clear(id);
+            let _ = tokio::fs::remove_file(crate::pipeline::highlight::highlight_path(&state.paths.derived, id)).await;
+            (StatusCode::OK, Json(serde_json::json!({
+                "success": true
+            }))).into_response()
+        }
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error deleting album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error deleting album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn add_assets_to_album(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+    Json(req): Json<AddAssetsToAlbumRequest>,
+) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let asset_ids = req.asset_ids.clone();
+        move || -> Result<AlbumAssetsOutcome> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            // Check if album exists; smart album contents come from their rules
+            match db::query::get_album(&conn, id)? {
+                None => return Ok(AlbumAssetsOutcome::NotFound),
+                Some(album) if album.6.is_some() => return Ok(AlbumAssetsOutcome::SmartAlbum),
+                Some(_) => {}
+            }
+            // Only assets the caller can see may be added
+            let asset_ids = db::query::filter_visible_assets(&conn, asset_ids, principal.scope())?;
+            db::writer::add_assets_to_album(&conn, id, &asset_ids)?;
+            // Get the updated album
+            if let Some((id, name, description, created_at, updated_at, asset_ids, rules)) = visible_album(&conn, id, principal.scope())? {
+                Ok(AlbumAssetsOutcome::Updated(Box::new(AlbumResponse {
+                    id,
+                    name,
+                    description,
+                    asset_ids,
+                    created_at,
+                    updated_at,
+                    smart: rules.is_some(),
+                    rules,
+                })))
+            } else {
+                Ok(AlbumAssetsOutcome::NotFound)
+            }
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(AlbumAssetsOutcome::Updated(album))) => (StatusCode::OK, Json(album)).into_response(),
+        Ok(Ok(AlbumAssetsOutcome::NotFound)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Ok(AlbumAssetsOutcome::SmartAlbum)) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Smart album contents are defined by its rules"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error adding assets to album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error adding assets to album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn remove_assets_from_album(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+    Json(req): Json<AddAssetsToAlbumRequest>,
+) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let asset_ids = req.asset_ids.clone();
+        move || -> Result<AlbumAssetsOutcome> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            // Check if album exists; smart album contents come from their rules
+            match db::query::get_album(&conn, id)? {
+                None => return Ok(AlbumAssetsOutcome::NotFound),
+                Some(album) if album.6.is_some() => return Ok(AlbumAssetsOutcome::SmartAlbum),
+                Some(_) => {}
+            }
+            db::writer::remove_assets_from_album(&conn, id, &asset_ids)?;
+            // Get the updated album
+            if let Some((id, name, description, created_at, updated_at, asset_ids, rules)) = visible_album(&conn, id, principal.scope())? {
+                Ok(AlbumAssetsOutcome::Updated(Box::new(AlbumResponse {
+                    id,
+                    name,
+                    description,
+                    asset_ids,
+                    created_at,
+                    updated_at,
+                    smart: rules.is_some(),
+                    rules,
+                })))
+            } else {
+                Ok(AlbumAssetsOutcome::NotFound)
+            }
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(AlbumAssetsOutcome::Updated(album))) => (StatusCode::OK, Json(album)).into_response(),
+        Ok(Ok(AlbumAssetsOutcome::NotFound)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Ok(AlbumAssetsOutcome::SmartAlbum)) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Smart album contents are defined by its rules"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error removing assets from album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error removing assets from album: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BatchAlbumAssetsRequest {
+    pub album_ids: Vec<i64>,
+    #[serde(default)]
+    pub add: Vec<i64>,
+    #[serde(default)]
+    pub remove: Vec<i64>,
+}
+
+enum BatchAlbumOutcome {
+    Applied(usize, usize),
+    AlbumNotFound(i64),
+    SmartAlbum(i64),
+    Forbidden(i64),
+}
+
+pub async fn batch_album_assets(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(mut req): Json<BatchAlbumAssetsRequest>,
+) -> impl IntoResponse {
+    req.album_ids.sort_unstable();
+    req.album_ids.dedup();
+    if req.album_ids.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "album_ids must not be empty"
+        }))).into_response();
+    }
+    if req.add.is_empty() && req.remove.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Nothing to add or remove"
+        }))).into_response();
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let album_ids = req.album_ids.clone();
+        move || -> Result<BatchAlbumOutcome> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let scope = principal.scope();
+            // Validate every album up front so the batch applies all-or-nothing
+            for &album_id in &album_ids {
+                if !db::query::can_view_album(&conn, album_id, scope)? {
+                    return Ok(BatchAlbumOutcome::AlbumNotFound(album_id));
+                }
+                if !db::query::can_edit_album(&conn, album_id, scope)? {
+                    return Ok(BatchAlbumOutcome::Forbidden(album_id));
+                }
+                match db::query::album_is_smart(&conn, album_id)? {
+                    None => return Ok(BatchAlbumOutcome::AlbumNotFound(album_id)),
+                    Some(true) => return Ok(BatchAlbumOutcome::SmartAlbum(album_id)),
+                    Some(false) => {}
+                }
+            }
+            let add = db::query::filter_visible_assets(&conn, req.add, scope)?;
+            let (added, removed) = db::writer::batch_update_album_assets(&conn, &album_ids, &add, &req.remove)?;
+            Ok(BatchAlbumOutcome::Applied(added, removed))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(BatchAlbumOutcome::Applied(added, removed))) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "album_ids": req.album_ids,
+            "added": added,
+            "removed": removed
+        }))).into_response(),
+        Ok(Ok(BatchAlbumOutcome::AlbumNotFound(id))) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": format!("Album {} not found", id)
+        }))).into_response(),
+        Ok(Ok(BatchAlbumOutcome::SmartAlbum(id))) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("Album {} is a smart album; its contents are defined by its rules", id)
+        }))).into_response(),
+        Ok(Ok(BatchAlbumOutcome::Forbidden(id))) => (StatusCode::FORBIDDEN, Json(serde_json::json!({
+            "error": format!("Album {} is shared with you read-only", id)
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error applying batch album update: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error applying batch album update: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AlbumShareResponse {
+    pub user_id: i64,
+    pub username: String,
+}
+
+#[derive(Deserialize)]
+pub struct ShareAlbumRequest {
+    pub user_id: i64,
+}
+
+enum AlbumShareOutcome {
+    Shares(Vec<AlbumShareResponse>),
+    AlbumNotFound,
+    UserNotFound,
+    SmartAlbum,
+}
+
+const SMART_ALBUM_SHARE_ERROR: &str = "Smart albums can't be shared; share a regular album with the photos instead";
+
+fn album_shares(conn: &Connection, album_id: i64) -> Result<Vec<AlbumShareResponse>> {
+    Ok(db::query::list_album_shares(conn, album_id)?
+        .into_iter()
+        .map(|(user_id, username)| AlbumShareResponse { user_id, username })
+        .collect())
+}
+
+fn album_share_response(result: Result<Result<AlbumShareOutcome>, tokio::task::JoinError>, action: &str) -> axum::response::Response {
+    match result {
+        Ok(Ok(AlbumShareOutcome::Shares(shares))) => (StatusCode::OK, Json(shares)).into_response(),
+        Ok(Ok(AlbumShareOutcome::AlbumNotFound)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Ok(AlbumShareOutcome::UserNotFound)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "User not found"
+        }))).into_response(),
+        Ok(Ok(AlbumShareOutcome::SmartAlbum)) => (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": SMART_ALBUM_SHARE_ERROR
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error {}: {}", action, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error {}: {}", action, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn list_album_shares(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<AlbumShareOutcome> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_album(&conn, id, AccessScope::All)? {
+                return Ok(AlbumShareOutcome::AlbumNotFound);
+            }
+            Ok(AlbumShareOutcome::Shares(album_shares(&conn, id)?))
+        }
+    }).await;
+    album_share_response(result, "listing album shares")
+}
+
+pub async fn share_album(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<ShareAlbumRequest>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<AlbumShareOutcome> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_album(&conn, id, AccessScope::All)? {
+                return Ok(AlbumShareOutcome::AlbumNotFound);
+            }
+            if db::query::get_user(&conn, req.user_id)?.is_none() {
+                return Ok(AlbumShareOutcome::UserNotFound);
+            }
+            if db::query::is_smart_album(&conn, id)? {
+                return Ok(AlbumShareOutcome::SmartAlbum);
+            }
+            db::writer::share_album(&conn, id, req.user_id)?;
+            Ok(AlbumShareOutcome::Shares(album_shares(&conn, id)?))
         }
+    }).await;
+    album_share_response(result, "sharing album")
+}
 
-        // If already mp3 audio, stream original file (no transcode)
-        if mime_str == "audio/mpeg" {
-            if let Ok(bytes) = tokio::fs::read(&file_path).await {
-                let base = StdPath::new(&file_path)
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("audio");
-                let mut resp = axum::http::Response::builder().status(StatusCode::OK);
-                let headers = resp.headers_mut().unwrap();
-                headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("audio/mpeg"));
-                headers.insert(
-                    header::CONTENT_DISPOSITION,
-                    header::HeaderValue::from_str(&format!("attachment; filename=\"{}.mp3\"", base))
-                        .unwrap_or_else(|_| header::HeaderValue::from_static("attachment"))
-                );
-                return resp.body(axum::body::Body::from(bytes)).unwrap();
+pub async fn unshare_album(State(state): State<Arc<AppState>>, Path((id, user_id)): Path<(i64, i64)>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<AlbumShareOutcome> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_album(&conn, id, AccessScope::All)? {
+                return Ok(AlbumShareOutcome::AlbumNotFound);
             }
+            if !db::writer::unshare_album(&conn, id, user_id)? {
+                return Ok(AlbumShareOutcome::UserNotFound);
+            }
+            Ok(AlbumShareOutcome::Shares(album_shares(&conn, id)?))
         }
+    }).await;
+    album_share_response(result, "unsharing album")
+}
 
-        // Otherwise, transcode/extract to mp3 using ffmpeg to stdout
-        let file_path_for_closure = file_path.clone();
-        let file_path_for_log = file_path.clone();
-        tracing::info!("Starting audio extraction for asset {}: {}", id, file_path_for_log);
-        let res = tokio::task::spawn_blocking(move || {
-            // Try libmp3lame first (best quality), then libshine (if built), then generic mp3
-            let encoders = vec!["libmp3lame", "libshine", "mp3"];
-            let mut last_error = None;
+pub async fn get_albums_for_asset(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(asset_id): Path<i64>,
+) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<i64>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::get_albums_for_asset(&conn, asset_id, principal.scope())
+        }
+    }).await;
 
-            for encoder in encoders {
-                tracing::info!("Attempting audio extraction with encoder '{}' for {}", encoder, file_path_for_closure);
-                let args = vec![
-                    "-i".to_string(), file_path_for_closure.clone(),
-                    "-vn".to_string(),
-                    "-acodec".to_string(), encoder.to_string(),
-                    "-ab".to_string(), "192k".to_string(),
-                    "-f".to_string(), "mp3".to_string(),
-                    "-map".to_string(), "a:0".to_string(),
-                    "-hide_banner".to_string(),
-                    "-loglevel".to_string(), "warning".to_string(), // Changed to warning to see progress
-                    "-".to_string(),
-                ];
+    match result {
+        Ok(Ok(album_ids)) => (StatusCode::OK, Json(album_ids)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error getting albums for asset: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error getting albums for asset: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
 
-                // Log the full command for debugging
-                let cmd_str = args.join(" ");
-                tracing::info!("FFmpeg command: {}", cmd_str);
+// Rating / favorite handlers
 
-                let start_time = std::time::Instant::now();
-                match ffmpeg::run_ffmpeg_with_timeout(args.clone(), std::time::Duration::from_secs(600)) {
-                    Ok(output) if output.status.success() => {
-                        let elapsed = start_time.elapsed();
-                        tracing::info!("Audio extraction succeeded with encoder '{}' in {:?}, output size: {} bytes",
-                            encoder, elapsed, output.stdout.len());
-                        return Ok(output);
-                    }
-                    Ok(output) => {
-                        let elapsed = start_time.elapsed();
-                        let err_msg = String::from_utf8_lossy(&output.stderr).to_string();
-                        tracing::warn!("Encoder '{}' failed after {:?} (exit code: {}): {}",
-                            encoder, elapsed, output.status.code().unwrap_or(-1), err_msg);
-                        last_error = Some(format!("Encoder '{}' failed: {}", encoder, err_msg));
-                        // Continue to next encoder
-                    }
-                    Err(e) => {
-                        let elapsed = start_time.elapsed();
-                        let error_msg = format!("{}", e);
-                        tracing::error!("Encoder '{}' error after {:?}: {}", encoder, elapsed, error_msg);
-                        last_error = Some(format!("Encoder '{}' error: {}", encoder, error_msg));
-                        // Continue to next encoder
-                    }
-                }
-            }
+#[derive(Deserialize)]
+pub struct SetRatingRequest {
+    pub rating: i64,
+}
 
-            // All encoders failed
-            tracing::error!("All MP3 encoders failed for {}", file_path_for_closure);
-            Err(anyhow::anyhow!("All MP3 encoders failed. Last error: {}", last_error.unwrap_or_else(|| "Unknown error".to_string())))
-        }).await;
+#[derive(Deserialize)]
+pub struct SetFavoriteRequest {
+    pub favorite: bool,
+}
 
-        match res {
-            Ok(Ok(output)) => {
-                // Success - output is already verified in the loop
-                let base = StdPath::new(&file_path)
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("audio");
-                let mut resp = axum::http::Response::builder().status(StatusCode::OK);
-                let headers = resp.headers_mut().unwrap();
-                headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("audio/mpeg"));
-                headers.insert(
-                    header::CONTENT_DISPOSITION,
-                    header::HeaderValue::from_str(&format!("attachment; filename=\"{}.mp3\"", base))
-                        .unwrap_or_else(|_| header::HeaderValue::from_static("attachment"))
-                );
-                return resp.body(axum::body::Body::from(output.stdout)).unwrap();
-            }
-            _ => {
-                // Fallback: try AAC in M4A container when MP3 encoders are not available
-                tracing::warn!("MP3 extraction failed; attempting AAC/m4a fallback");
-                let fallback = tokio::task::spawn_blocking({
-                    let fp = file_path.clone();
-                    move || {
-                        let args = vec![
-                            "-i".to_string(), fp.clone(),
-                            "-vn".to_string(),
-                            "-c:a".to_string(), "aac".to_string(),
-                            "-b:a".to_string(), "192k".to_string(),
-                            "-movflags".to_string(), "+faststart".to_string(),
-                            "-f".to_string(), "mp4".to_string(),
-                            "-map".to_string(), "a:0".to_string(),
-                            "-hide_banner".to_string(),
-                            "-loglevel".to_string(), "error".to_string(),
-                            "-".to_string(),
-                        ];
-                        ffmpeg::run_ffmpeg_with_timeout(args, std::time::Duration::from_secs(600))
-                    }
-                }).await;
+pub async fn set_asset_rating(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<SetRatingRequest>) -> impl IntoResponse {
+    if !(0..=5).contains(&req.rating) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Rating must be between 0 and 5"
+        }))).into_response();
+    }
+    if let Some(response) = reject_locked(&state, id).await {
+        return response;
+    }
 
-                match fallback {
-                    Ok(Ok(out2)) if out2.status.success() => {
-                        let base = StdPath::new(&file_path)
-                            .file_stem()
-                            .and_then(|s| s.to_str())
-                            .unwrap_or("audio");
-                        let mut resp = axum::http::Response::builder().status(StatusCode::OK);
-                        let headers = resp.headers_mut().unwrap();
-                        headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("audio/mp4"));
-                        headers.insert(
-                            header::CONTENT_DISPOSITION,
-                            header::HeaderValue::from_str(&format!("attachment; filename=\"{}.m4a\"", base))
-                                .unwrap_or_else(|_| header::HeaderValue::from_static("attachment"))
-                        );
-                        return resp.body(axum::body::Body::from(out2.stdout)).unwrap();
-                    }
-                    Ok(Ok(out2)) => {
-                        let err2 = String::from_utf8_lossy(&out2.stderr).to_string();
-                        tracing::error!("ffmpeg aac fallback failed: {}", err2);
-                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
-                            "success": false,
-                            "error": "FFmpeg failed to extract audio",
-                            "details": err2,
-                        }))).into_response();
-                    }
-                    Ok(Err(e)) => {
-                        tracing::error!("FFmpeg error (fallback): {}", e);
-                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
-                            "success": false,
-                            "error": format!("FFmpeg error: {}", e),
-                        }))).into_response();
-                    }
-                    Err(e) => {
-                        tracing::error!("Task join error running ffmpeg (fallback): {}", e);
-                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
-                            "success": false,
-                            "error": "Internal error",
-                        }))).into_response();
-                    }
-                }
+    let rating = req.rating;
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::set_asset_rating(&conn, id, rating)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "asset_id": id,
+            "rating": rating
+        }))).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error setting rating for asset {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error setting rating for asset {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+/// Favorites are per user for user accounts; the admin password and auth-disabled instances share one set
+pub async fn set_asset_favorite(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Path(id): Path<i64>, Json(req): Json<SetFavoriteRequest>) -> impl IntoResponse {
+    let favorite = req.favorite;
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_asset(&conn, id, principal.scope())? {
+                return Ok(false);
+            }
+            match principal.user_id() {
+                Some(user_id) => db::writer::set_user_favorite(&conn, user_id, id, favorite),
+                None => db::writer::set_asset_favorite(&conn, id, favorite),
             }
         }
+    }).await;
+
+    match result {
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "asset_id": id,
+            "favorite": favorite
+        }))).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error setting favorite for asset {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error setting favorite for asset {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetArchivedRequest {
+    pub archived: bool,
+}
+
+pub async fn set_asset_archived(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<SetArchivedRequest>) -> impl IntoResponse {
+    let archived = req.archived;
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::set_asset_archived(&conn, id, archived)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "asset_id": id,
+            "archived": archived
+        }))).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error setting archived for asset {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error setting archived for asset {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
     }
-
-    StatusCode::NOT_FOUND.into_response()
 }
 
 #[derive(Deserialize)]
-pub struct BrowseQuery {
-    path: Option<String>,
+pub struct SetLockedRequest {
+    pub locked: bool,
 }
 
-pub async fn browse_directory(State(_state): State<Arc<AppState>>, Query(params): Query<BrowseQuery>) -> impl IntoResponse {
-    let requested_path = params.path.as_deref().unwrap_or("/");
-
-    // Allow browsing from container root (/)
-    // Security: Only allow absolute paths, prevent directory traversal attacks
-    let target_path = if requested_path == "/" || requested_path.is_empty() {
-        std::path::PathBuf::from("/")
-    } else {
-        let requested = std::path::PathBuf::from(requested_path);
+const LOCKED_ERROR: &str = "Asset is locked";
 
-        // Only allow absolute paths
-        if !requested.is_absolute() {
-            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
-                "error": "Path must be absolute"
-            })));
+/// 423 when the asset is locked, so the caller stops before changing it. Missing assets pass;
+/// the handler reports those itself.
+async fn reject_locked(state: &AppState, id: i64) -> Option<axum::response::Response> {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::is_asset_locked(&conn, id)
         }
+    }).await;
 
-        // Normalize the path to prevent directory traversal
-        let resolved = requested.canonicalize().unwrap_or(requested);
-
-        // Ensure it's still absolute after canonicalization
-        if !resolved.is_absolute() {
-            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
-                "error": "Invalid path"
-            })));
+    match result {
+        Ok(Ok(false)) => None,
+        Ok(Ok(true)) => Some((StatusCode::LOCKED, Json(serde_json::json!({
+            "error": LOCKED_ERROR,
+            "asset_id": id
+        }))).into_response()),
+        Ok(Err(e)) => {
+            tracing::error!("Error checking lock on asset {}: {}", id, e);
+            Some((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response())
         }
+        Err(e) => {
+            tracing::error!("Task error checking lock on asset {}: {}", id, e);
+            Some((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response())
+        }
+    }
+}
 
-        resolved
-    };
-
+/// Lock an asset against rotation, tag, rating and property changes and deletion, or unlock it
+pub async fn set_asset_locked(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<SetLockedRequest>) -> impl IntoResponse {
+    let locked = req.locked;
     let result = tokio::task::spawn_blocking({
-        let path = target_path.clone();
-        move || -> Result<Vec<serde_json::Value>, anyhow::Error> {
-            let mut entries = Vec::new();
-
-            if !path.exists() {
-                return Ok(entries);
-            }
-
-            if !path.is_dir() {
-                return Err(anyhow::anyhow!("Path is not a directory"));
-            }
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::set_asset_locked(&conn, id, locked)
+        }
+    }).await;
 
-            let dir_entries = std::fs::read_dir(&path)?;
-            let mut dirs = Vec::new();
-            let mut files = Vec::new();
+    match result {
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "asset_id": id,
+            "locked": locked
+        }))).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error setting lock for asset {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error setting lock for asset {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
 
-            for entry_result in dir_entries {
-                let entry = entry_result?;
-                let entry_path = entry.path();
-                let metadata = entry.metadata().ok();
+// Stack handlers
 
-                // Skip hidden files/directories
-                if entry_path.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|s| s.starts_with('.'))
-                    .unwrap_or(false) {
-                    continue;
-                }
+#[derive(Deserialize)]
+pub struct StacksQuery {
+    offset: Option<i64>,
+    limit: Option<i64>,
+}
 
-                let is_dir = metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false);
-                let name = entry_path.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| "unknown".to_string());
+#[derive(Deserialize, Default)]
+pub struct RebuildStacksRequest {
+    /// Max seconds between consecutive shots from the same camera (default FLASH_STACK_WINDOW_SECS or 2);
+    /// stacks span at most 10s, or `window_secs` if longer
+    pub window_secs: Option<i64>,
+}
 
-                let entry_json = serde_json::json!({
-                    "name": name,
-                    "path": entry_path.to_string_lossy().to_string(),
-                    "is_dir": is_dir,
-                });
+#[derive(Serialize)]
+pub struct StackResponse {
+    pub id: i64,
+    pub cover_asset_id: i64,
+    pub asset_count: i64,
+    pub burst_key: Option<String>,
+    pub asset_ids: Vec<i64>,
+}
 
-                if is_dir {
-                    dirs.push(entry_json);
-                } else {
-                    files.push(entry_json);
-                }
-            }
+pub async fn list_stacks(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Query(q): Query<StacksQuery>) -> impl IntoResponse {
+    let offset = q.offset.unwrap_or(0);
+    let limit = q.limit.unwrap_or(200);
+    let scope = principal.scope();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<crate::models::asset::Paged<StackResponse>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let page = db::query::list_stacks(&conn, offset, limit, scope)?;
+            let items = page
+                .items
+                .into_iter()
+                .map(|(id, cover_asset_id, asset_count, burst_key, asset_ids)| StackResponse {
+                    id,
+                    cover_asset_id,
+                    asset_count,
+                    burst_key,
+                    asset_ids,
+                })
+                .collect();
+            Ok(crate::models::asset::Paged { total: page.total, items })
+        }
+    }).await;
 
-            // Sort: directories first, then files, both alphabetically
-            dirs.sort_by(|a, b| {
-                a["name"].as_str().unwrap_or("").cmp(b["name"].as_str().unwrap_or(""))
-            });
-            files.sort_by(|a, b| {
-                a["name"].as_str().unwrap_or("").cmp(b["name"].as_str().unwrap_or(""))
-            });
+    match result {
+        Ok(Ok(stacks)) => (StatusCode::OK, Json(stacks)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error listing stacks: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error listing stacks: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
 
-            entries.extend(dirs);
-            entries.extend(files);
+pub async fn rebuild_stacks(State(state): State<Arc<AppState>>, req: Option<Json<RebuildStacksRequest>>) -> impl IntoResponse {
+    let req = req.map(|Json(r)| r).unwrap_or_default();
+    let window_secs = req.window_secs.unwrap_or_else(db::writer::stack_window_secs);
+    if window_secs < 0 {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "window_secs must not be negative"
+        }))).into_response();
+    }
 
-            Ok(entries)
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<usize> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::rebuild_stacks(&conn, window_secs)
         }
     }).await;
 
     match result {
-        Ok(Ok(entries)) => {
-            (StatusCode::OK, Json(serde_json::json!({
-                "path": target_path.to_string_lossy().to_string(),
-                "entries": entries
-            })))
-        },
+        Ok(Ok(stacks)) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "stacks": stacks,
+            "window_secs": window_secs
+        }))).into_response(),
         Ok(Err(e)) => {
+            tracing::error!("Error rebuilding stacks: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
-                "error": e.to_string()
-            })))
-        },
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
         Err(e) => {
+            tracing::error!("Task error rebuilding stacks: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
-                "error": format!("Task error: {}", e)
-            })))
+                "error": "Internal server error"
+            }))).into_response()
         }
     }
 }
 
+// Import handlers
+
 #[derive(Deserialize)]
-pub struct SaveOrientationRequest {
-    rotation: i32,
+pub struct ImportQuery {
+    pub filename: String,
+    /// Target directory; must be the library root or a configured scan path (or below one)
+    pub dir: Option<String>,
 }
 
-pub async fn save_orientation(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<SaveOrientationRequest>) -> impl IntoResponse {
-    let pool = state.pool.clone();
-    let paths = state.paths.clone();
-    let rotation = req.rotation;
+enum ImportOutcome {
+    Imported(std::path::PathBuf),
+    AlreadyExists(i64, String),
+    BadRequest(String),
+}
 
-    // Normalize rotation to 0-360 range
-    let normalized_rotation = ((rotation % 360) + 360) % 360;
+/// Pick a filename in `dir` that doesn't exist yet ("name.jpg", "name (1).jpg", ...)
+fn unique_import_path(dir: &StdPath, filename: &str) -> std::path::PathBuf {
+    let candidate = dir.join(filename);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let p = StdPath::new(filename);
+    let stem = p.file_stem().and_then(|s| s.to_str()).unwrap_or(filename);
+    let ext = p.extension().and_then(|s| s.to_str());
+    let mut n = 1;
+    loop {
+        let name = match ext {
+            Some(ext) => format!("{} ({}).{}", stem, n, ext),
+            None => format!("{} ({})", stem, n),
+        };
+        let candidate = dir.join(name);
+        if !candidate.exists() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
 
-    // Only allow 90-degree increments
-    if normalized_rotation != 0 && normalized_rotation != 90 && normalized_rotation != 180 && normalized_rotation != 270 {
+pub async fn import_file(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(q): Query<ImportQuery>,
+    body: axum::body::Bytes,
+) -> impl IntoResponse {
+    let filename = q.filename.trim().to_string();
+    if filename.is_empty() || filename == "." || filename == ".." || filename.contains(['/', '\\']) {
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
-            "success": false,
-            "error": "Rotation must be a multiple of 90 degrees"
+            "error": "filename must be a plain file name"
+        }))).into_response();
+    }
+    if !crate::pipeline::discover::has_image_video_extension(StdPath::new(&filename)) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Only image and video files can be imported"
+        }))).into_response();
+    }
+    if body.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Request body is empty"
         }))).into_response();
     }
 
-    let result = tokio::task::spawn_blocking(move || -> Result<()> {
-        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-
-        // Get asset path
-        let path: String = conn.query_row(
-            "SELECT path FROM assets WHERE id = ?1",
-            params![id],
-            |row| row.get(0)
-        )?;
-
-        let resolved_path = crate::utils::path::resolve_asset_path(&path, &paths);
-        if !resolved_path.exists() {
-            anyhow::bail!(
-                "File not found: stored path '{}', resolved path '{}'",
-                path,
-                resolved_path.display()
-            );
-        }
-
-        #[cfg(not(target_env = "msvc"))]
-        {
-            let resolved_str = resolved_path.to_string_lossy();
-            // Load image with libvips
-            let img = libvips::VipsImage::new_from_file(resolved_str.as_ref())?;
-
-            // Determine rotation angle based on normalized rotation
-            let rotated = match normalized_rotation {
-                90 => libvips::ops::rot(&img, Angle::D90)?,
-                180 => libvips::ops::rot(&img, Angle::D180)?,
-                270 => libvips::ops::rot(&img, Angle::D270)?,
-                _ => img, // 0 degrees, no rotation needed
-            };
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let root = state.paths.root.clone();
+        let filename = filename.clone();
+        move || -> Result<ImportOutcome> {
+            // Hash before touching the disk so duplicates never get written
+            let (xxh64, sha256) = crate::pipeline::hash::hash_bytes(&body);
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if let Some((id, path)) = db::query::find_asset_by_hash(&conn, &sha256, xxh64, body.len() as i64, principal.scope())? {
+                return Ok(ImportOutcome::AlreadyExists(id, path));
+            }
 
-            // Save rotated image back to disk
-            // Use the same format as the original file
-            rotated.image_write_to_file(resolved_str.as_ref())?;
-            Ok(())
-        }
-        #[cfg(target_env = "msvc")]
-        {
-            anyhow::bail!("Image rotation not available on Windows MSVC (libvips not supported)")
+            let target_dir = match q.dir.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+                Some(dir) => {
+                    let dir = std::path::PathBuf::from(dir);
+                    if !dir.is_absolute() || dir.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+                        return Ok(ImportOutcome::BadRequest("dir must be an absolute path".to_string()));
+                    }
+                    // The root belongs to no one, so only admins may write anywhere in it
+                    let mut allowed: Vec<std::path::PathBuf> = if principal.is_admin() { vec![root.clone()] } else { Vec::new() };
+                    allowed.extend(
+                        db::query::get_scan_paths_with_flags(&conn, principal.scope())?
+                            .into_iter()
+                            .map(|(path, _)| std::path::PathBuf::from(path)),
+                    );
+                    if !allowed.iter().any(|a| dir.starts_with(a)) {
+                        return Ok(ImportOutcome::BadRequest("dir must be inside the library root or one of your scan paths".to_string()));
+                    }
+                    dir
+                }
+                None => root.join("Imports"),
+            };
+            drop(conn);
+
+            std::fs::create_dir_all(&target_dir)?;
+            let target = unique_import_path(&target_dir, &filename);
+            let mut f = std::fs::OpenOptions::new().write(true).create_new(true).open(&target)?;
+            std::io::Write::write_all(&mut f, &body)?;
+            f.sync_all()?;
+            Ok(ImportOutcome::Imported(target))
         }
     }).await;
 
     match result {
-        Ok(Ok(())) => {
-            (StatusCode::OK, Json(serde_json::json!({
-                "success": true
+        Ok(Ok(ImportOutcome::Imported(path))) => {
+            info!("import_written: {:?}", path);
+            if let Some(item) = crate::pipeline::discover::to_discover_item(&path) {
+                state.stats.inc_files(1);
+                let _ = state.queues.discover_tx.send(item).await;
+                state.gauges.discover.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+            (StatusCode::CREATED, Json(serde_json::json!({
+                "status": "imported",
+                "path": path.to_string_lossy()
             }))).into_response()
-        },
+        }
+        Ok(Ok(ImportOutcome::AlreadyExists(asset_id, path))) => (StatusCode::CONFLICT, Json(serde_json::json!({
+            "status": "already_exists",
+            "asset_id": asset_id,
+            "path": path
+        }))).into_response(),
+        Ok(Ok(ImportOutcome::BadRequest(msg))) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": msg
+        }))).into_response(),
         Ok(Err(e)) => {
+            tracing::error!("Error importing {}: {}", filename, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
-                "success": false,
-                "error": e.to_string()
+                "error": format!("Import failed: {}", e)
             }))).into_response()
-        },
+        }
         Err(e) => {
+            tracing::error!("Task error importing file: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
-                "success": false,
-                "error": format!("Task error: {}", e)
+                "error": "Internal server error"
             }))).into_response()
         }
     }
 }
 
-// Album handlers
+// Backup handlers
 
-#[derive(Deserialize)]
-pub struct CreateAlbumRequest {
-    pub name: String,
-    pub description: Option<String>,
-}
+pub async fn export_library(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let now = chrono::Utc::now();
+    let snapshot = state.paths.db_path.with_file_name(format!("export-{}.db", now.timestamp_millis()));
 
-#[derive(Serialize)]
-pub struct AlbumResponse {
-    pub id: i64,
-    pub name: String,
-    pub description: Option<String>,
-    pub asset_ids: Vec<i64>,
-    pub created_at: i64,
-    pub updated_at: i64,
-}
+    // Snapshot first so database errors still produce a proper status instead of a truncated download
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let snapshot = snapshot.clone();
+        move || -> Result<backup::BackupManifest> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            backup::snapshot_database(&conn, &snapshot)
+        }
+    }).await;
+    let manifest = match result {
+        Ok(Ok(manifest)) => manifest,
+        Ok(Err(e)) => {
+            let _ = std::fs::remove_file(&snapshot);
+            tracing::error!("Error snapshotting database for export: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Export failed: {}", e)
+            }))).into_response();
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&snapshot);
+            tracing::error!("Task error exporting library: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response();
+        }
+    };
 
-#[derive(Deserialize)]
-pub struct UpdateAlbumRequest {
-    pub name: Option<String>,
-    pub description: Option<String>,
+    let (tx, rx) = tokio::sync::mpsc::channel::<io::Result<bytes::Bytes>>(16);
+    let derived = state.paths.derived.clone();
+    tokio::task::spawn_blocking(move || {
+        let writer = io::BufWriter::with_capacity(256 * 1024, backup::ChannelWriter::new(tx.clone()));
+        let written = backup::write_archive(writer, &manifest, &snapshot, &derived)
+            .and_then(|mut w| Ok(io::Write::flush(&mut w)?));
+        if let Err(e) = written {
+            tracing::error!("Error writing backup archive: {}", e);
+            // Abort the response so the client doesn't keep a truncated archive
+            let _ = tx.blocking_send(Err(io::Error::other(e.to_string())));
+        }
+        let _ = std::fs::remove_file(&snapshot);
+    });
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) });
+    axum::http::Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/x-tar")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"seen-backup-{}.tar\"", now.format("%Y%m%d-%H%M%S")))
+        .body(axum::body::Body::from_stream(stream))
+        .unwrap()
+        .into_response()
 }
 
 #[derive(Deserialize)]
-pub struct AddAssetsToAlbumRequest {
-    pub asset_ids: Vec<i64>,
+pub struct RestoreQuery {
+    /// Replace a library that already has assets
+    #[serde(default)]
+    pub force: bool,
 }
 
-pub async fn list_albums(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+pub async fn restore_library(State(state): State<Arc<AppState>>, Query(q): Query<RestoreQuery>, body: axum::body::Body) -> impl IntoResponse {
+    if !q.force {
+        let existing = tokio::task::spawn_blocking({
+            let pool = state.pool.clone();
+            move || -> Result<i64> {
+                let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+                db::query::count_assets(&conn)
+            }
+        }).await;
+        match existing {
+            Ok(Ok(0)) => {}
+            Ok(Ok(count)) => return (StatusCode::CONFLICT, Json(serde_json::json!({
+                "error": format!("Library already contains {} assets; pass force=true to replace it", count)
+            }))).into_response(),
+            Ok(Err(e)) => {
+                tracing::error!("Error counting assets before restore: {}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                    "error": format!("Database error: {}", e)
+                }))).into_response();
+            }
+            Err(e) => {
+                tracing::error!("Task error counting assets: {}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                    "error": "Internal server error"
+                }))).into_response();
+            }
+        }
+    }
+
+    // Archives can be far larger than memory, so spool the upload to disk before unpacking
+    let (upload, file) = match backup::create_upload_spool(&state.paths.data) {
+        Ok(spool) => spool,
+        Err(e) => {
+            tracing::error!("Error creating upload file: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Restore failed: {}", e)
+            }))).into_response();
+        }
+    };
+    let spooled: Result<u64> = async {
+        use futures_util::StreamExt;
+        use tokio::io::AsyncWriteExt;
+        let mut file = tokio::fs::File::from_std(file);
+        let mut stream = body.into_data_stream();
+        let mut written = 0u64;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| anyhow::anyhow!("Upload interrupted: {}", e))?;
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        file.flush().await?;
+        Ok(written)
+    }.await;
+    match spooled {
+        Ok(0) => {
+            let _ = std::fs::remove_file(&upload);
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "Request body is empty"
+            }))).into_response();
+        }
+        Ok(_) => {}
+        Err(e) => {
+            let _ = std::fs::remove_file(&upload);
+            tracing::error!("Error receiving backup archive: {}", e);
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("Upload failed: {}", e)
+            }))).into_response();
+        }
+    }
+
     let result = tokio::task::spawn_blocking({
-        let pool = state.pool.clone();
-        move || -> Result<Vec<AlbumResponse>> {
-            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            // Use optimized single-query function (no N+1)
-            let albums = db::query::list_albums_with_assets(&conn)?;
-            let responses: Vec<AlbumResponse> = albums
-                .into_iter()
-                .map(|(id, name, description, created_at, updated_at, asset_ids)| {
-                    AlbumResponse {
-                        id,
-                        name,
-                        description,
-                        asset_ids,
-                        created_at,
-                        updated_at,
-                    }
-                })
-                .collect();
-            Ok(responses)
+        let paths = state.paths.clone();
+        let upload = upload.clone();
+        move || -> Result<std::result::Result<backup::BackupManifest, String>> {
+            let file = std::fs::File::open(&upload)?;
+            let restored = backup::restore_archive(io::BufReader::new(file), &paths.db_path, &paths.derived);
+            let _ = std::fs::remove_file(&upload);
+            restored
         }
     }).await;
 
     match result {
-        Ok(Ok(albums)) => (StatusCode::OK, Json(albums)).into_response(),
+        Ok(Ok(Ok(manifest))) => {
+            info!("library_restore_staged: {} assets, {} albums", manifest.assets, manifest.albums);
+            Json(serde_json::json!({
+                "success": true,
+                "restart_required": true,
+                "manifest": manifest
+            })).into_response()
+        }
+        Ok(Ok(Err(msg))) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": msg
+        }))).into_response(),
         Ok(Err(e)) => {
-            tracing::error!("Error listing albums: {}", e);
+            tracing::error!("Error restoring backup archive: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
-                "error": format!("Database error: {}", e)
+                "error": format!("Restore failed: {}", e)
             }))).into_response()
         }
         Err(e) => {
-            tracing::error!("Task error listing albums: {}", e);
+            tracing::error!("Task error restoring backup archive: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": "Internal server error"
             }))).into_response()
@@ -2641,39 +5420,44 @@ pub async fn list_albums(State(state): State<Arc<AppState>>) -> impl IntoRespons
     }
 }
 
-pub async fn get_album(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+// Tag handlers
+
+#[derive(Deserialize)]
+pub struct AddTagsRequest {
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct TagResponse {
+    pub id: i64,
+    pub name: String,
+    pub asset_count: i64,
+}
+
+pub async fn list_tags(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>) -> impl IntoResponse {
+    let scope = principal.scope();
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
-        move || -> Result<Option<AlbumResponse>> {
+        move || -> Result<Vec<TagResponse>> {
             let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            if let Some((id, name, description, created_at, updated_at, asset_ids)) = db::query::get_album(&conn, id)? {
-                Ok(Some(AlbumResponse {
-                    id,
-                    name,
-                    description,
-                    asset_ids,
-                    created_at,
-                    updated_at,
-                }))
-            } else {
-                Ok(None)
-            }
+            let tags = db::query::list_tags(&conn, scope)?;
+            Ok(tags
+                .into_iter()
+                .map(|(id, name, asset_count)| TagResponse { id, name, asset_count })
+                .collect())
         }
     }).await;
 
     match result {
-        Ok(Ok(Some(album))) => (StatusCode::OK, Json(album)).into_response(),
-        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
-            "error": "Album not found"
-        }))).into_response(),
+        Ok(Ok(tags)) => (StatusCode::OK, Json(tags)).into_response(),
         Ok(Err(e)) => {
-            tracing::error!("Error getting album: {}", e);
+            tracing::error!("Error listing tags: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": format!("Database error: {}", e)
             }))).into_response()
         }
         Err(e) => {
-            tracing::error!("Task error getting album: {}", e);
+            tracing::error!("Task error listing tags: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": "Internal server error"
             }))).into_response()
@@ -2681,40 +5465,41 @@ pub async fn get_album(State(state): State<Arc<AppState>>, Path(id): Path<i64>)
     }
 }
 
-pub async fn create_album(State(state): State<Arc<AppState>>, Json(req): Json<CreateAlbumRequest>) -> impl IntoResponse {
+/// Every tag name on an asset, and the predicted ones with their confidence
+type AssetTags = (Vec<String>, Vec<(String, f64)>);
+
+pub async fn get_asset_tags(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
-        let name = req.name.clone();
-        let description = req.description.clone();
-        move || -> Result<AlbumResponse> {
+        move || -> Result<Option<AssetTags>> {
             let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            let id = db::writer::create_album(&conn, &name, description.as_deref())?;
-            // Get the created album
-            if let Some((id, name, description, created_at, updated_at, asset_ids)) = db::query::get_album(&conn, id)? {
-                Ok(AlbumResponse {
-                    id,
-                    name,
-                    description,
-                    asset_ids,
-                    created_at,
-                    updated_at,
-                })
-            } else {
-                Err(anyhow::anyhow!("Failed to retrieve created album"))
+            if db::query::get_asset_path(&conn, id)?.is_none() {
+                return Ok(None);
             }
+            Ok(Some((db::query::get_asset_tags(&conn, id)?, db::query::get_asset_auto_tags(&conn, id)?)))
         }
     }).await;
 
     match result {
-        Ok(Ok(album)) => (StatusCode::CREATED, Json(album)).into_response(),
+        Ok(Ok(Some((tags, auto_tags)))) => (StatusCode::OK, Json(serde_json::json!({
+            "asset_id": id,
+            "tags": tags,
+            "auto_tags": auto_tags.into_iter().map(|(name, confidence)| serde_json::json!({
+                "name": name,
+                "confidence": confidence
+            })).collect::<Vec<_>>()
+        }))).into_response(),
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset not found"
+        }))).into_response(),
         Ok(Err(e)) => {
-            tracing::error!("Error creating album: {}", e);
+            tracing::error!("Error getting tags for asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": format!("Database error: {}", e)
             }))).into_response()
         }
         Err(e) => {
-            tracing::error!("Task error creating album: {}", e);
+            tracing::error!("Task error getting tags for asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": "Internal server error"
             }))).into_response()
@@ -2722,47 +5507,46 @@ pub async fn create_album(State(state): State<Arc<AppState>>, Json(req): Json<Cr
     }
 }
 
-pub async fn update_album(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<UpdateAlbumRequest>) -> impl IntoResponse {
+pub async fn add_asset_tags(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<AddTagsRequest>) -> impl IntoResponse {
+    if req.tags.iter().all(|t| t.trim().is_empty()) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "No tags provided"
+        }))).into_response();
+    }
+    if let Some(response) = reject_locked(&state, id).await {
+        return response;
+    }
+
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
-        let name = req.name.clone();
-        let description = req.description.clone();
-        move || -> Result<Option<AlbumResponse>> {
+        let tags = req.tags;
+        move || -> Result<Option<Vec<String>>> {
             let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            let updated = db::writer::update_album(&conn, id, name.as_deref(), description.as_deref())?;
-            if updated {
-                // Get the updated album
-                if let Some((id, name, description, created_at, updated_at, asset_ids)) = db::query::get_album(&conn, id)? {
-                    Ok(Some(AlbumResponse {
-                        id,
-                        name,
-                        description,
-                        asset_ids,
-                        created_at,
-                        updated_at,
-                    }))
-                } else {
-                    Ok(None)
-                }
-            } else {
-                Ok(None)
+            // Check if asset exists
+            if db::query::get_asset_path(&conn, id)?.is_none() {
+                return Ok(None);
             }
+            db::writer::add_tags_to_asset(&conn, id, &tags)?;
+            Ok(Some(db::query::get_asset_tags(&conn, id)?))
         }
     }).await;
 
     match result {
-        Ok(Ok(Some(album))) => (StatusCode::OK, Json(album)).into_response(),
+        Ok(Ok(Some(tags))) => (StatusCode::OK, Json(serde_json::json!({
+            "asset_id": id,
+            "tags": tags
+        }))).into_response(),
         Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
-            "error": "Album not found"
+            "error": "Asset not found"
         }))).into_response(),
         Ok(Err(e)) => {
-            tracing::error!("Error updating album: {}", e);
+            tracing::error!("Error adding tags to asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": format!("Database error: {}", e)
             }))).into_response()
         }
         Err(e) => {
-            tracing::error!("Task error updating album: {}", e);
+            tracing::error!("Task error adding tags to asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": "Internal server error"
             }))).into_response()
@@ -2770,12 +5554,15 @@ pub async fn update_album(State(state): State<Arc<AppState>>, Path(id): Path<i64
     }
 }
 
-pub async fn delete_album(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+pub async fn remove_asset_tag(State(state): State<Arc<AppState>>, Path((id, tag)): Path<(i64, String)>) -> impl IntoResponse {
+    if let Some(response) = reject_locked(&state, id).await {
+        return response;
+    }
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         move || -> Result<bool> {
             let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            db::writer::delete_album(&conn, id)
+            db::writer::remove_tag_from_asset(&conn, id, &tag)
         }
     }).await;
 
@@ -2784,16 +5571,16 @@ pub async fn delete_album(State(state): State<Arc<AppState>>, Path(id): Path<i64
             "success": true
         }))).into_response(),
         Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
-            "error": "Album not found"
+            "error": "Tag not found on asset"
         }))).into_response(),
         Ok(Err(e)) => {
-            tracing::error!("Error deleting album: {}", e);
+            tracing::error!("Error removing tag from asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": format!("Database error: {}", e)
             }))).into_response()
         }
         Err(e) => {
-            tracing::error!("Task error deleting album: {}", e);
+            tracing::error!("Task error removing tag from asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": "Internal server error"
             }))).into_response()
@@ -2801,46 +5588,49 @@ pub async fn delete_album(State(state): State<Arc<AppState>>, Path(id): Path<i64
     }
 }
 
-pub async fn add_assets_to_album(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<AddAssetsToAlbumRequest>) -> impl IntoResponse {
+/// Longest accepted custom property key
+const MAX_PROPERTY_KEY_LEN: usize = 64;
+/// Longest accepted custom property value
+const MAX_PROPERTY_VALUE_LEN: usize = 4096;
+
+#[derive(Deserialize)]
+pub struct SetPropertiesRequest {
+    /// Merged into the asset's properties; `null` removes a key
+    pub properties: serde_json::Map<String, serde_json::Value>,
+}
+
+fn properties_json(properties: Vec<(String, String)>) -> serde_json::Value {
+    serde_json::Value::Object(properties.into_iter().map(|(k, v)| (k, serde_json::Value::String(v))).collect())
+}
+
+pub async fn get_asset_properties(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
-        let asset_ids = req.asset_ids.clone();
-        move || -> Result<Option<AlbumResponse>> {
+        move || -> Result<Option<Vec<(String, String)>>> {
             let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            // Check if album exists
-            if db::query::get_album(&conn, id)?.is_none() {
+            if db::query::get_asset_path(&conn, id)?.is_none() {
                 return Ok(None);
             }
-            db::writer::add_assets_to_album(&conn, id, &asset_ids)?;
-            // Get the updated album
-            if let Some((id, name, description, created_at, updated_at, asset_ids)) = db::query::get_album(&conn, id)? {
-                Ok(Some(AlbumResponse {
-                    id,
-                    name,
-                    description,
-                    asset_ids,
-                    created_at,
-                    updated_at,
-                }))
-            } else {
-                Ok(None)
-            }
+            Ok(Some(db::query::get_asset_properties(&conn, id)?))
         }
     }).await;
 
     match result {
-        Ok(Ok(Some(album))) => (StatusCode::OK, Json(album)).into_response(),
+        Ok(Ok(Some(properties))) => (StatusCode::OK, Json(serde_json::json!({
+            "asset_id": id,
+            "properties": properties_json(properties)
+        }))).into_response(),
         Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
-            "error": "Album not found"
+            "error": "Asset not found"
         }))).into_response(),
         Ok(Err(e)) => {
-            tracing::error!("Error adding assets to album: {}", e);
+            tracing::error!("Error getting properties for asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": format!("Database error: {}", e)
             }))).into_response()
         }
         Err(e) => {
-            tracing::error!("Task error adding assets to album: {}", e);
+            tracing::error!("Task error getting properties for asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": "Internal server error"
             }))).into_response()
@@ -2848,46 +5638,60 @@ pub async fn add_assets_to_album(State(state): State<Arc<AppState>>, Path(id): P
     }
 }
 
-pub async fn remove_assets_from_album(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<AddAssetsToAlbumRequest>) -> impl IntoResponse {
+/// Merge custom properties into an asset. Strings are stored as-is and other JSON values as their JSON text.
+pub async fn set_asset_properties(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<SetPropertiesRequest>) -> impl IntoResponse {
+    let mut properties = Vec::with_capacity(req.properties.len());
+    for (key, value) in req.properties {
+        let key = key.trim().to_string();
+        if key.is_empty() || key.len() > MAX_PROPERTY_KEY_LEN {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("Property keys must be 1-{} bytes", MAX_PROPERTY_KEY_LEN)
+            }))).into_response();
+        }
+        let value = match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some(s),
+            other => Some(other.to_string()),
+        };
+        if value.as_ref().is_some_and(|v| v.len() > MAX_PROPERTY_VALUE_LEN) {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("Value of '{}' is longer than {} bytes", key, MAX_PROPERTY_VALUE_LEN)
+            }))).into_response();
+        }
+        properties.push((key, value));
+    }
+    if let Some(response) = reject_locked(&state, id).await {
+        return response;
+    }
+
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
-        let asset_ids = req.asset_ids.clone();
-        move || -> Result<Option<AlbumResponse>> {
+        move || -> Result<Option<Vec<(String, String)>>> {
             let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            // Check if album exists
-            if db::query::get_album(&conn, id)?.is_none() {
+            if db::query::get_asset_path(&conn, id)?.is_none() {
                 return Ok(None);
             }
-            db::writer::remove_assets_from_album(&conn, id, &asset_ids)?;
-            // Get the updated album
-            if let Some((id, name, description, created_at, updated_at, asset_ids)) = db::query::get_album(&conn, id)? {
-                Ok(Some(AlbumResponse {
-                    id,
-                    name,
-                    description,
-                    asset_ids,
-                    created_at,
-                    updated_at,
-                }))
-            } else {
-                Ok(None)
-            }
+            db::writer::set_asset_properties(&conn, id, &properties)?;
+            Ok(Some(db::query::get_asset_properties(&conn, id)?))
         }
     }).await;
 
     match result {
-        Ok(Ok(Some(album))) => (StatusCode::OK, Json(album)).into_response(),
+        Ok(Ok(Some(properties))) => (StatusCode::OK, Json(serde_json::json!({
+            "asset_id": id,
+            "properties": properties_json(properties)
+        }))).into_response(),
         Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
-            "error": "Album not found"
+            "error": "Asset not found"
         }))).into_response(),
         Ok(Err(e)) => {
-            tracing::error!("Error removing assets from album: {}", e);
+            tracing::error!("Error setting properties on asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": format!("Database error: {}", e)
             }))).into_response()
         }
         Err(e) => {
-            tracing::error!("Task error removing assets from album: {}", e);
+            tracing::error!("Task error setting properties on asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": "Internal server error"
             }))).into_response()
@@ -2895,28 +5699,66 @@ pub async fn remove_assets_from_album(State(state): State<Arc<AppState>>, Path(i
     }
 }
 
-pub async fn get_albums_for_asset(State(state): State<Arc<AppState>>, Path(asset_id): Path<i64>) -> impl IntoResponse {
+pub async fn remove_asset_property(State(state): State<Arc<AppState>>, Path((id, key)): Path<(i64, String)>) -> impl IntoResponse {
+    if let Some(response) = reject_locked(&state, id).await {
+        return response;
+    }
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
-        move || -> Result<Vec<i64>> {
+        move || -> Result<bool> {
             let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
-            db::query::get_albums_for_asset(&conn, asset_id)
+            let key = key.trim().to_string();
+            if !db::query::get_asset_properties(&conn, id)?.iter().any(|(k, _)| k.eq_ignore_ascii_case(&key)) {
+                return Ok(false);
+            }
+            db::writer::set_asset_properties(&conn, id, &[(key, None)])?;
+            Ok(true)
         }
     }).await;
 
     match result {
-        Ok(Ok(album_ids)) => (StatusCode::OK, Json(album_ids)).into_response(),
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true
+        }))).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Property not found on asset"
+        }))).into_response(),
         Ok(Err(e)) => {
-            tracing::error!("Error getting albums for asset: {}", e);
+            tracing::error!("Error removing property from asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": format!("Database error: {}", e)
             }))).into_response()
         }
         Err(e) => {
-            tracing::error!("Task error getting albums for asset: {}", e);
+            tracing::error!("Task error removing property from asset {}: {}", id, e);
             (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
                 "error": "Internal server error"
             }))).into_response()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), ByteRange::Partial(0, 99));
+        assert_eq!(parse_range("bytes=900-", 1000), ByteRange::Partial(900, 999));
+        assert_eq!(parse_range("bytes=900-5000", 1000), ByteRange::Partial(900, 999));
+        assert_eq!(parse_range("bytes=-100", 1000), ByteRange::Partial(900, 999));
+        assert_eq!(parse_range("bytes=-5000", 1000), ByteRange::Partial(0, 999));
+        assert_eq!(parse_range("bytes=0-9, 20-29", 1000), ByteRange::Partial(0, 9));
+
+        // Well-formed but past the end of the file
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), ByteRange::Unsatisfiable);
+
+        // Malformed or another unit: ignored
+        for header in ["bytes=abc", "bytes=5-3", "bytes=-", "bytes=1-2x", "items=0-9", "bytes 0-9", ""] {
+            assert_eq!(parse_range(header, 1000), ByteRange::Full, "{:?}", header);
+        }
+    }
+}
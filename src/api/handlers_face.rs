@@ -1,12 +1,76 @@
 use std::sync::Arc;
-use axum::{extract::{State, Path}, http::StatusCode, Json};
+use axum::{extract::{State, Path}, http::StatusCode, Extension, Json};
 use axum::response::IntoResponse;
 use serde::Deserialize;
 use crate::AppState;
+use crate::api::auth::Principal;
 use crate::db;
 use axum::extract::Query;
 use serde::Serialize;
 use rusqlite::OptionalExtension;
+use crate::utils::xmp;
+
+/// SQL condition on `assets a` for the files face detection handles, following the excluded
+/// extensions setting; None when every type is excluded
+fn face_asset_condition(conn: &rusqlite::Connection) -> anyhow::Result<Option<String>> {
+    // All image extensions that can be processed
+    let all_image_exts = vec![
+        "jpg", "jpeg", "png", "gif", "bmp", "webp", "tiff", "tif", "heic", "heif",
+        "raw", "cr2", "nef", "orf", "sr2", "arw", "dng", "rw2", "raf", "pef",
+        "srw", "3fr", "x3f", "mrw", "mef", "mos", "erf", "dcr", "kdc", "fff",
+        "iiq", "rwl", "r3d", "ari", "bay", "cap", "data", "dcs", "drf", "eip",
+        "k25", "mdc", "nrw", "obm", "ptx", "pxn", "rwz", "srf", "crw"
+    ];
+
+    // Default allowed extensions (if no exclusions set in database)
+    let default_allowed = vec!["jpg", "jpeg", "png", "webp", "heic", "heif", "tiff", "tif"];
+
+    // Read excluded extensions from database
+    let excluded = db::writer::get_face_setting(conn, "excluded_extensions")?;
+    let excluded_list: Vec<String> = excluded
+        .map(|s| s.split(',').map(|x| x.trim().to_lowercase()).collect())
+        .unwrap_or_default();
+
+    // Build allowed extensions list
+    let allowed_exts: Vec<&str> = if excluded_list.is_empty() {
+        // No exclusions set - use default allowed list
+        default_allowed
+    } else {
+        // Exclusions are set - start with all image extensions and remove excluded ones
+        all_image_exts.into_iter()
+            .filter(|ext| !excluded_list.contains(&ext.to_lowercase()))
+            .collect()
+    };
+
+    if allowed_exts.is_empty() {
+        return Ok(None);
+    }
+
+    // Build SQL query with allowed extensions
+    // Handle both ".ext" and "ext" formats, and case-insensitive matching
+    let ext_conditions: Vec<String> = allowed_exts.iter()
+        .flat_map(|ext| vec![
+            format!("LOWER(a.ext) = '.{}'", ext),
+            format!("LOWER(a.ext) = '{}'", ext),
+            format!("LOWER(REPLACE(a.ext, '.', '')) = '{}'", ext)
+        ])
+        .collect();
+    // Videos too, unless their extension is excluded or frame sampling is off
+    let video_exts: Vec<String> = if crate::pipeline::face::video_frame_count() == 0 {
+        Vec::new()
+    } else {
+        crate::pipeline::face::VIDEO_EXTENSIONS.iter()
+            .filter(|ext| !excluded_list.iter().any(|x| x == *ext))
+            .map(|ext| format!("'{}'", ext))
+            .collect()
+    };
+    let video_condition = if video_exts.is_empty() {
+        String::new()
+    } else {
+        format!(" OR (a.mime LIKE 'video/%' AND LOWER(REPLACE(a.ext, '.', '')) IN ({}))", video_exts.join(","))
+    };
+    Ok(Some(format!("({}{})", ext_conditions.join(" OR "), video_condition)))
+}
 
 // Face detection handlers
 pub async fn detect_faces(State(state): State<Arc<AppState>>) -> impl axum::response::IntoResponse {
@@ -34,54 +98,15 @@ pub async fn detect_faces(State(state): State<Arc<AppState>>) -> impl axum::resp
             move || {
                 let conn = pool.get().ok()?;
 
-                // All image extensions that can be processed
-                let all_image_exts = vec![
-                    "jpg", "jpeg", "png", "gif", "bmp", "webp", "tiff", "tif", "heic", "heif",
-                    "raw", "cr2", "nef", "orf", "sr2", "arw", "dng", "rw2", "raf", "pef",
-                    "srw", "3fr", "x3f", "mrw", "mef", "mos", "erf", "dcr", "kdc", "fff",
-                    "iiq", "rwl", "r3d", "ari", "bay", "cap", "data", "dcs", "drf", "eip",
-                    "k25", "mdc", "nrw", "obm", "ptx", "pxn", "rwz", "srf", "crw"
-                ];
-
-                // Default allowed extensions (if no exclusions set in database)
-                let default_allowed = vec!["jpg", "jpeg", "png", "webp", "heic", "heif", "tiff", "tif"];
-
-                // Read excluded extensions from database
-                let excluded = db::writer::get_face_setting(&conn, "excluded_extensions").ok()?;
-                let excluded_list: Vec<String> = excluded
-                    .map(|s| s.split(',').map(|x| x.trim().to_lowercase()).collect())
-                    .unwrap_or_default();
-
-                // Build allowed extensions list
-                let allowed_exts: Vec<&str> = if excluded_list.is_empty() {
-                    // No exclusions set - use default allowed list
-                    default_allowed
-                } else {
-                    // Exclusions are set - start with all image extensions and remove excluded ones
-                    all_image_exts.into_iter()
-                        .filter(|ext| !excluded_list.contains(&ext.to_lowercase()))
-                        .collect()
-                };
-
-                if allowed_exts.is_empty() {
+                let Some(condition) = face_asset_condition(&conn).ok()? else {
                     return Some(Vec::new());
-                }
-
-                // Build SQL query with allowed extensions
-                // Handle both ".ext" and "ext" formats, and case-insensitive matching
-                let ext_conditions: Vec<String> = allowed_exts.iter()
-                    .flat_map(|ext| vec![
-                        format!("LOWER(a.ext) = '.{}'", ext),
-                        format!("LOWER(a.ext) = '{}'", ext),
-                        format!("LOWER(REPLACE(a.ext, '.', '')) = '{}'", ext)
-                    ])
-                    .collect();
+                };
                 let sql = format!(
                     "SELECT a.id, a.path
                      FROM assets a
-                     WHERE ({})
+                     WHERE {}
                      AND a.id NOT IN (SELECT DISTINCT asset_id FROM face_embeddings)",
-                    ext_conditions.join(" OR ")
+                    condition
                 );
 
                 let mut stmt = conn.prepare(&sql).ok()?;
@@ -92,13 +117,17 @@ pub async fn detect_faces(State(state): State<Arc<AppState>>) -> impl axum::resp
                 for (id, path) in rows.flatten() {
                     assets.push((id, std::path::PathBuf::from(path)));
                 }
+                // Journaled before queueing, so a restart picks up where this run stopped
+                let ids: Vec<i64> = assets.iter().map(|(id, _)| *id).collect();
+                db::writer::start_face_run(&conn).ok()?;
+                db::writer::journal_face_jobs(&conn, &ids).ok()?;
                 Some(assets)
             }
         }).await.ok().flatten().unwrap_or_default();
 
         // Queue all JPEG images for face detection
         for (asset_id, path) in image_assets {
-            if face_tx.send(crate::pipeline::face::FaceJob { asset_id, image_path: path }).await.is_err() {
+            if face_tx.send(crate::pipeline::face::FaceJob { asset_id, image_path: path, queued_at: std::time::Instant::now() }).await.is_err() {
                 break;
             }
             gauges.face.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -111,6 +140,111 @@ pub async fn detect_faces(State(state): State<Arc<AppState>>) -> impl axum::resp
     })))
 }
 
+/// Queue the assets `resolve` returns for face detection now, without starting a library-wide run.
+/// Assets that already have faces, or whose type face detection skips, are left out. `resolve`
+/// returns None when the asset or album isn't there for the caller.
+async fn detect_faces_in<F>(state: Arc<AppState>, what: &'static str, resolve: F) -> axum::response::Response
+where
+    F: FnOnce(&rusqlite::Connection) -> anyhow::Result<Option<Vec<i64>>> + Send + 'static,
+{
+    {
+        let processor = state.face_processor.lock();
+        if !processor.scrfd_loaded() || !processor.arcface_loaded() {
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
+                "error": "Face models are not loaded"
+            }))).into_response();
+        }
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> anyhow::Result<Option<(usize, Vec<crate::pipeline::face::FaceJob>)>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let Some(ids) = resolve(&conn)? else {
+                return Ok(None);
+            };
+            let Some(condition) = face_asset_condition(&conn)? else {
+                return Ok(Some((ids.len(), Vec::new())));
+            };
+            let mut stmt = conn.prepare(&format!(
+                "SELECT a.id, a.path FROM assets a
+                 WHERE a.id = ?1 AND {}
+                 AND NOT EXISTS (SELECT 1 FROM face_embeddings f WHERE f.asset_id = a.id)",
+                condition
+            ))?;
+            let mut jobs = Vec::new();
+            for id in &ids {
+                let path = stmt.query_row([id], |row| row.get::<_, String>(1)).optional()?;
+                if let Some(path) = path {
+                    jobs.push(crate::pipeline::face::FaceJob { asset_id: *id, image_path: path.into(), queued_at: std::time::Instant::now() });
+                }
+            }
+            // Journaled like a full run, so a restart still finishes them
+            let queued: Vec<i64> = jobs.iter().map(|job| job.asset_id).collect();
+            db::writer::journal_face_jobs(&conn, &queued)?;
+            Ok(Some((ids.len(), jobs)))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some((requested, jobs)))) => {
+            let queued = jobs.len();
+            let face_tx = state.queues.face_tx.clone();
+            let gauges = state.gauges.clone();
+            tokio::spawn(async move {
+                for job in jobs {
+                    if face_tx.send(job).await.is_err() {
+                        break;
+                    }
+                    gauges.face.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
+            });
+            (StatusCode::ACCEPTED, Json(serde_json::json!({
+                "status": "queued",
+                "queued": queued,
+                "skipped": requested - queued
+            }))).into_response()
+        }
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": format!("{} not found", what)
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error queueing face detection: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error queueing face detection: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+/// `POST /assets/:id/detect-faces`
+pub async fn detect_asset_faces(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    detect_faces_in(state, "Asset", move |conn| {
+        Ok(db::query::can_view_asset(conn, id, principal.scope())?.then(|| vec![id]))
+    }).await
+}
+
+/// `POST /albums/:id/detect-faces`
+pub async fn detect_album_faces(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    detect_faces_in(state, "Album", move |conn| {
+        Ok(crate::api::handlers::visible_album(conn, id, principal.scope())?.map(|album| album.5))
+    }).await
+}
+
 pub async fn face_detection_status(State(state): State<Arc<AppState>>) -> impl axum::response::IntoResponse {
     let enabled = state.face_detection_enabled.load(std::sync::atomic::Ordering::Relaxed);
     let queue_depth = state.gauges.face.load(std::sync::atomic::Ordering::Relaxed);
@@ -128,6 +262,7 @@ pub async fn stop_face_detection(State(state): State<Arc<AppState>>) -> impl axu
         let pool = pool.clone();
         move || {
             let conn = pool.get().ok()?;
+            db::writer::clear_face_jobs(&conn).ok()?;
             db::writer::set_face_detection_enabled(&conn, false).ok()
         }
     }).await.ok().flatten();
@@ -165,11 +300,13 @@ pub async fn face_progress(State(state): State<Arc<AppState>>) -> impl axum::res
     let enabled = state.face_detection_enabled.load(std::sync::atomic::Ordering::Relaxed);
 
     // Models loaded status
-    let (scrfd_loaded, arcface_loaded) = {
+    let (scrfd_loaded, arcface_loaded, pets_loaded, accelerator) = {
         let guard = state.face_processor.lock();
         (
             guard.scrfd_loaded(),
             guard.arcface_loaded(),
+            guard.pets_loaded(),
+            guard.accelerator(),
         )
     };
 
@@ -184,15 +321,22 @@ pub async fn face_progress(State(state): State<Arc<AppState>>) -> impl axum::res
         "Models not loaded"
     };
 
+    let (indexed, warmup) = {
+        let index = state.face_index.lock();
+        (index.len(), index.warmup())
+    };
+
     // DB counts
     let pool = state.pool.clone();
-    let (faces_total, persons_total, assets_with_faces) = tokio::task::spawn_blocking(move || {
+    let (faces_total, persons_total, pets_total, assets_with_faces, (jobs_completed, jobs_total)) = tokio::task::spawn_blocking(move || {
         let conn = pool.get().ok()?;
         let faces_total: i64 = conn.query_row("SELECT COUNT(*) FROM face_embeddings", [], |r| r.get(0)).ok()?;
-        let persons_total: i64 = conn.query_row("SELECT COUNT(*) FROM persons", [], |r| r.get(0)).ok()?;
+        let persons_total: i64 = conn.query_row("SELECT COUNT(*) FROM persons WHERE subject_type = 'person'", [], |r| r.get(0)).ok()?;
+        let pets_total: i64 = conn.query_row("SELECT COUNT(*) FROM persons WHERE subject_type = 'pet'", [], |r| r.get(0)).ok()?;
         let assets_with_faces: i64 = conn.query_row("SELECT COUNT(DISTINCT asset_id) FROM face_embeddings", [], |r| r.get(0)).ok()?;
-        Some((faces_total, persons_total, assets_with_faces))
-    }).await.ok().flatten().unwrap_or((0, 0, 0));
+        let jobs = db::query::face_job_counts(&conn).ok()?;
+        Some((faces_total, persons_total, pets_total, assets_with_faces, jobs))
+    }).await.ok().flatten().unwrap_or((0, 0, 0, 0, (0, 0)));
 
     let batch = std::env::var("SEEN_FACE_CLUSTER_BATCH").ok().and_then(|v| v.parse().ok()).unwrap_or(crate::pipeline::face::FACE_CLUSTER_BATCH_SIZE as i64);
     let remaining_to_next_cluster = if faces_total == 0 { batch } else { (batch - (faces_total % batch)) % batch };
@@ -212,17 +356,30 @@ pub async fn face_progress(State(state): State<Arc<AppState>>) -> impl axum::res
     (StatusCode::OK, Json(serde_json::json!({
         "enabled": enabled,
         "queue_depth": queue_depth,
-        "models_loaded": { "scrfd": scrfd_loaded, "arcface": arcface_loaded },
+        "models_loaded": { "scrfd": scrfd_loaded, "arcface": arcface_loaded, "pets": pets_loaded },
+        "execution_provider": scrfd_loaded.then_some(accelerator),
         "models_status": models_status,
         "counts": {
             "faces_total": faces_total,
             "persons_total": persons_total,
+            "pets_total": pets_total,
             "assets_with_faces": assets_with_faces
         },
+        "progress": {
+            "completed": jobs_completed,
+            "total": jobs_total
+        },
         "thresholds": {
             "cluster_batch_size": batch,
             "remaining_to_next_cluster": remaining_to_next_cluster
         },
+        "index": {
+            "faces": indexed,
+            "warming_up": !warmup.done,
+            "to_add": warmup.to_add,
+            "added": warmup.added,
+            "removed": warmup.removed
+        },
         "status": status_msg
     })))
 }
@@ -234,6 +391,18 @@ pub struct PageQ {
     pub offset: Option<i64>,
     #[serde(default)]
     pub limit: Option<i64>,
+    /// Return batches of similar faces instead of a flat list; offset/limit then page over groups
+    #[serde(default)]
+    pub group: Option<bool>,
+    /// Max cosine distance between a face and the seed of its group (default 0.5)
+    #[serde(default)]
+    pub max_distance: Option<f32>,
+    /// How many of the newest unassigned faces to group (default 2000, max 5000)
+    #[serde(default)]
+    pub window: Option<i64>,
+    /// Smallest group to return (default 2)
+    #[serde(default)]
+    pub min_size: Option<usize>,
 }
 
 #[cfg(feature = "facial-recognition")]
@@ -246,13 +415,21 @@ pub struct FaceListItem {
 }
 
 #[cfg(feature = "facial-recognition")]
-pub async fn list_unassigned_faces(State(state): State<Arc<AppState>>, Query(q): Query<PageQ>) -> impl axum::response::IntoResponse {
+pub async fn list_unassigned_faces(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(q): Query<PageQ>,
+) -> axum::response::Response {
+    let scope = principal.scope();
+    if q.group.unwrap_or(false) {
+        return list_unassigned_face_groups(state, q, scope).await;
+    }
     let offset = q.offset.unwrap_or(0);
     let limit = q.limit.unwrap_or(60).clamp(1, 500);
     let pool = state.pool.clone();
     let rows = tokio::task::spawn_blocking(move || {
         let conn = pool.get().ok()?;
-        let rows = db::query::get_unassigned_faces(&conn, offset, limit).ok()?;
+        let rows = db::query::get_unassigned_faces(&conn, offset, limit, scope).ok()?;
         Some(rows)
     }).await.ok().flatten().unwrap_or_default();
 
@@ -260,7 +437,291 @@ pub async fn list_unassigned_faces(State(state): State<Arc<AppState>>, Query(q):
         id, asset_id, bbox: serde_json::from_str(&bbox_json).unwrap_or_else(|_| serde_json::json!({})), confidence
     }).collect();
 
-    (StatusCode::OK, Json(serde_json::json!({ "faces": out })))
+    (StatusCode::OK, Json(serde_json::json!({ "faces": out }))).into_response()
+}
+
+#[cfg(feature = "facial-recognition")]
+#[derive(Serialize)]
+pub struct FaceGroup {
+    pub size: usize,
+    /// Seed face first, then the rest in order of confidence
+    pub faces: Vec<FaceListItem>,
+}
+
+/// Review queue: mini-clusters of similar unassigned faces, largest first
+#[cfg(feature = "facial-recognition")]
+async fn list_unassigned_face_groups(state: Arc<AppState>, q: PageQ, scope: db::query::AccessScope) -> axum::response::Response {
+    let offset = q.offset.unwrap_or(0).max(0) as usize;
+    let limit = q.limit.unwrap_or(20).clamp(1, 100) as usize;
+    let max_distance = q.max_distance.unwrap_or(0.5).clamp(0.0, 2.0);
+    let window = q.window.unwrap_or(2000).clamp(1, 5000);
+    let min_size = q.min_size.unwrap_or(2).max(1);
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> anyhow::Result<serde_json::Value> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let unassigned_total = db::query::count_unassigned_faces(&conn, scope)?;
+            let mut faces = db::query::get_recent_unassigned_faces_with_embeddings(&conn, window, scope)?;
+            let grouped_faces = faces.len();
+            // Clearest faces first, so they become the seeds
+            faces.sort_by(|a, b| b.3.total_cmp(&a.3));
+            let embeddings = faces
+                .iter()
+                .map(|(_, _, blob, _, _)| db::query::decode_embedding_blob(blob).unwrap_or_default())
+                .collect::<Vec<_>>();
+
+            let groups: Vec<Vec<usize>> = crate::pipeline::face::group_similar_faces(&embeddings, max_distance)
+                .into_iter()
+                .filter(|members| members.len() >= min_size)
+                .collect();
+            let total_groups = groups.len();
+            let faces_in_groups: usize = groups.iter().map(Vec::len).sum();
+            let page: Vec<FaceGroup> = groups
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .map(|members| FaceGroup {
+                    size: members.len(),
+                    faces: members
+                        .into_iter()
+                        .map(|i| {
+                            let (id, asset_id, _, confidence, bbox_json) = &faces[i];
+                            FaceListItem {
+                                id: *id,
+                                asset_id: *asset_id,
+                                bbox: serde_json::from_str(bbox_json).unwrap_or_else(|_| serde_json::json!({})),
+                                confidence: *confidence,
+                            }
+                        })
+                        .collect(),
+                })
+                .collect();
+
+            Ok(serde_json::json!({
+                "groups": page,
+                "total_groups": total_groups,
+                "ungrouped_faces": grouped_faces - faces_in_groups,
+                "window": grouped_faces,
+                "unassigned_total": unassigned_total,
+            }))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(body)) => (StatusCode::OK, Json(body)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error grouping unassigned faces: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error grouping unassigned faces: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[cfg(feature = "facial-recognition")]
+#[derive(Deserialize)]
+pub struct SimilarFacesQ {
+    /// Faces to return (default 20, at most 200)
+    pub k: Option<usize>,
+    /// Leave out faces further than this cosine distance
+    pub max_distance: Option<f32>,
+}
+
+#[cfg(feature = "facial-recognition")]
+#[derive(Serialize)]
+pub struct SimilarFace {
+    #[serde(flatten)]
+    pub face: FaceListItem,
+    pub distance: f32,
+}
+
+/// Nearest unassigned faces to a face, for "same person?" suggestions
+#[cfg(feature = "facial-recognition")]
+pub async fn similar_faces(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(face_id): Path<i64>,
+    Query(q): Query<SimilarFacesQ>,
+) -> axum::response::Response {
+    let k = q.k.unwrap_or(20).clamp(1, 200);
+    let max_distance = q.max_distance.unwrap_or(f32::INFINITY);
+    let scope = principal.scope();
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let index = state.face_index.clone();
+        move || -> anyhow::Result<Option<Vec<SimilarFace>>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_face(&conn, face_id, scope)? {
+                return Ok(None);
+            }
+            let Some((_, embedding)) = db::query::get_face_embeddings_by_id(&conn, &[face_id])?.pop() else {
+                return Ok(None);
+            };
+            // Assigned faces take up search results, so widen the search until enough unassigned ones turn up
+            let mut search = (k * 4).max(64);
+            loop {
+                let hits = index.lock().find_similar(&embedding, search, max_distance);
+                let ids: Vec<i64> = hits.iter().map(|(id, _)| *id).filter(|id| *id != face_id).collect();
+                let rows = db::query::get_unassigned_faces_by_id(&conn, &ids, scope)?;
+                if rows.len() >= k || hits.len() < search {
+                    let distances: std::collections::HashMap<i64, f32> = hits.into_iter().collect();
+                    return Ok(Some(rows.into_iter().take(k).map(|(id, asset_id, bbox_json, confidence)| SimilarFace {
+                        distance: distances[&id],
+                        face: FaceListItem {
+                            id, asset_id, bbox: serde_json::from_str(&bbox_json).unwrap_or_else(|_| serde_json::json!({})), confidence
+                        },
+                    }).collect()));
+                }
+                search *= 4;
+            }
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(faces))) => (StatusCode::OK, Json(serde_json::json!({ "face_id": face_id, "faces": faces }))).into_response(),
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Face not found" }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error finding faces similar to {}: {}", face_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error finding similar faces: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[cfg(feature = "facial-recognition")]
+#[derive(Deserialize)]
+pub struct AssignFacesReq {
+    pub person_id: i64,
+    pub face_ids: Vec<i64>,
+}
+
+/// Assign several faces to one person at once, e.g. the accepted suggestions from `similar_faces`
+#[cfg(feature = "facial-recognition")]
+pub async fn assign_faces(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<AssignFacesReq>,
+) -> axum::response::Response {
+    if req.face_ids.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": "face_ids is empty" }))).into_response();
+    }
+    let scope = principal.scope();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> anyhow::Result<Result<Vec<i64>, &'static str>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_person(&conn, req.person_id, scope)? {
+                return Ok(Err("Person not found"));
+            }
+            for &face_id in &req.face_ids {
+                if !db::query::can_view_face(&conn, face_id, scope)? {
+                    return Ok(Err("Face not found"));
+                }
+            }
+            db::writer::assign_faces_to_person(&conn, req.person_id, &req.face_ids).map(Ok)
+        }
+    }).await;
+    state.face_index.lock().invalidate_persons();
+
+    match result {
+        Ok(Ok(Ok(assigned))) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "person_id": req.person_id,
+            "assigned": assigned.len(),
+            "face_ids": assigned
+        }))).into_response(),
+        Ok(Ok(Err(message))) => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": message }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error assigning faces to person {}: {}", req.person_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error assigning faces: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[cfg(feature = "facial-recognition")]
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FaceVerdict {
+    Confirm,
+    Reject,
+}
+
+#[cfg(feature = "facial-recognition")]
+#[derive(Deserialize)]
+pub struct FaceFeedbackReq {
+    pub person_id: i64,
+    pub verdict: FaceVerdict,
+}
+
+/// Confirm or reject a suggested face for a person; matching and smart merge learn from it
+#[cfg(feature = "facial-recognition")]
+pub async fn face_feedback(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(face_id): Path<i64>,
+    Json(req): Json<FaceFeedbackReq>,
+) -> axum::response::Response {
+    let confirmed = req.verdict == FaceVerdict::Confirm;
+    let scope = principal.scope();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> anyhow::Result<Option<Option<Option<i64>>>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_person(&conn, req.person_id, scope)? {
+                return Ok(None);
+            }
+            if !db::query::can_view_face(&conn, face_id, scope)? {
+                return Ok(Some(None));
+            }
+            db::writer::set_face_feedback(&conn, face_id, req.person_id, confirmed).map(Some)
+        }
+    }).await;
+    state.face_index.lock().invalidate_persons();
+
+    match result {
+        Ok(Ok(Some(Some(person_id)))) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "face_id": face_id,
+            "person_id": person_id
+        }))).into_response(),
+        Ok(Ok(Some(None))) => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Face not found" }))).into_response(),
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Person not found" }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error recording feedback for face {}: {}", face_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error recording face feedback: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
 }
 
 #[cfg(feature = "facial-recognition")]
@@ -272,6 +733,7 @@ pub struct AssignFaceReq {
 #[cfg(feature = "facial-recognition")]
 pub async fn assign_face_to_person(
     State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
     Path(face_id): Path<i64>,
     Json(req): Json<AssignFaceReq>,
 ) -> impl axum::response::IntoResponse {
@@ -285,8 +747,12 @@ pub async fn assign_face_to_person(
 
     let pool = state.pool.clone();
     let target_person = req.person_id;
+    let scope = principal.scope();
     let result = tokio::task::spawn_blocking(move || {
         let conn = pool.get().ok()?;
+        if !db::query::can_view_face(&conn, face_id, scope).ok()? {
+            return Some(AssignOutcome::FaceNotFound);
+        }
         let previous_person_id: Option<i64> = match conn
             .query_row(
                 "SELECT person_id FROM face_embeddings WHERE id = ?1",
@@ -301,11 +767,16 @@ pub async fn assign_face_to_person(
         };
 
         if let Some(pid) = target_person {
-            if db::query::get_person(&conn, pid).ok()?.is_none() {
+            if !db::query::can_view_person(&conn, pid, scope).ok()? {
                 return Some(AssignOutcome::PersonNotFound);
             }
             match db::writer::update_face_person(&conn, face_id, Some(pid)) {
                 Ok(true) => {
+                    // A manual assignment confirms the new person and rejects the old one
+                    db::writer::record_face_feedback(&conn, face_id, pid, true).ok()?;
+                    if let Some(prev) = previous_person_id.filter(|prev| *prev != pid) {
+                        db::writer::record_face_feedback(&conn, face_id, prev, false).ok()?;
+                    }
                     if db::writer::rebuild_person_profile(&conn, pid).is_err() {
                         return None;
                     }
@@ -323,6 +794,7 @@ pub async fn assign_face_to_person(
             match db::writer::update_face_person(&conn, face_id, None) {
                 Ok(true) => {
                     if let Some(prev) = previous_person_id {
+                        db::writer::record_face_feedback(&conn, face_id, prev, false).ok()?;
                         if db::writer::rebuild_person_profile(&conn, prev).is_err() {
                             return None;
                         }
@@ -363,58 +835,75 @@ pub async fn assign_face_to_person(
 }
 
 #[cfg(feature = "facial-recognition")]
-pub async fn face_thumb(State(state): State<Arc<AppState>>, Path(face_id): Path<i64>, Query(q): Query<std::collections::HashMap<String, String>>) -> impl axum::response::IntoResponse {
-    let size: u32 = q.get("size").and_then(|s| s.parse().ok()).unwrap_or(160).clamp(32, 1024);
-    let pool = state.pool.clone();
-    let res: Option<(Vec<u8>,)> = tokio::task::spawn_blocking(move || {
-        let conn = pool.get().ok()?;
-        let row = db::query::get_face_row(&conn, face_id).ok().flatten()?;
-        let (_id, asset_id, bbox_json, _conf) = row;
-        let (path, _w_opt, _h_opt) = db::query::get_asset_path_size(&conn, asset_id).ok().flatten()?;
-        let bbox: crate::pipeline::face::FaceBbox = serde_json::from_str(&bbox_json).ok()?;
-        let img = image::open(&path).ok()?;
-
-        // Use actual image dimensions
-        let img_w = img.width() as f32;
-        let img_h = img.height() as f32;
-
-        // Clamp bounding box to image bounds
-        let x1 = bbox.x1.max(0.0).min(img_w);
-        let y1 = bbox.y1.max(0.0).min(img_h);
-        let x2 = bbox.x2.max(0.0).min(img_w);
-        let y2 = bbox.y2.max(0.0).min(img_h);
-
-        // Ensure valid bounding box
-        if x2 <= x1 || y2 <= y1 {
-            tracing::warn!("Invalid bounding box for face {}: x1={}, y1={}, x2={}, y2={}", face_id, x1, y1, x2, y2);
-            return None;
-        }
+/// The face's box from its photo or video frame, padded by a fifth on each side
+fn face_crop(conn: &rusqlite::Connection, face_id: i64) -> Option<image::DynamicImage> {
+    let row = db::query::get_face_row(conn, face_id).ok().flatten()?;
+    let (_id, asset_id, bbox_json, _conf) = row;
+    let (path, _w_opt, _h_opt) = db::query::get_asset_path_size(conn, asset_id).ok().flatten()?;
+    let bbox: crate::pipeline::face::FaceBbox = serde_json::from_str(&bbox_json).ok()?;
+    // Faces found in videos are cropped from the frame they were detected in
+    let img = match bbox.frame_ms {
+        Some(frame_ms) => crate::pipeline::face::video_frame(std::path::Path::new(&path), frame_ms).ok()?,
+        None => image::open(&path).ok()?,
+    };
 
-        // Add padding (20% on each side)
-        let width = x2 - x1;
-        let height = y2 - y1;
-        let padding_x = width * 0.2;
-        let padding_y = height * 0.2;
+    // Use actual image dimensions
+    let img_w = img.width() as f32;
+    let img_h = img.height() as f32;
 
-        let crop_x1 = (x1 - padding_x).max(0.0) as u32;
-        let crop_y1 = (y1 - padding_y).max(0.0) as u32;
-        let crop_x2 = ((x2 + padding_x).min(img_w) as u32).min(img.width());
-        let crop_y2 = ((y2 + padding_y).min(img_h) as u32).min(img.height());
+    // Clamp bounding box to image bounds
+    let x1 = bbox.x1.max(0.0).min(img_w);
+    let y1 = bbox.y1.max(0.0).min(img_h);
+    let x2 = bbox.x2.max(0.0).min(img_w);
+    let y2 = bbox.y2.max(0.0).min(img_h);
 
-        if crop_x2 <= crop_x1 || crop_y2 <= crop_y1 {
-            tracing::warn!("Invalid crop coordinates for face {}: x1={}, y1={}, x2={}, y2={}", face_id, crop_x1, crop_y1, crop_x2, crop_y2);
-            return None;
-        }
+    // Ensure valid bounding box
+    if x2 <= x1 || y2 <= y1 {
+        tracing::warn!("Invalid bounding box for face {}: x1={}, y1={}, x2={}, y2={}", face_id, x1, y1, x2, y2);
+        return None;
+    }
+
+    // Add padding (20% on each side)
+    let width = x2 - x1;
+    let height = y2 - y1;
+    let padding_x = width * 0.2;
+    let padding_y = height * 0.2;
+
+    let crop_x1 = (x1 - padding_x).max(0.0) as u32;
+    let crop_y1 = (y1 - padding_y).max(0.0) as u32;
+    let crop_x2 = ((x2 + padding_x).min(img_w) as u32).min(img.width());
+    let crop_y2 = ((y2 + padding_y).min(img_h) as u32).min(img.height());
+
+    if crop_x2 <= crop_x1 || crop_y2 <= crop_y1 {
+        tracing::warn!("Invalid crop coordinates for face {}: x1={}, y1={}, x2={}, y2={}", face_id, crop_x1, crop_y1, crop_x2, crop_y2);
+        return None;
+    }
+
+    let crop_width = crop_x2 - crop_x1;
+    let crop_height = crop_y2 - crop_y1;
 
-        let crop_width = crop_x2 - crop_x1;
-        let crop_height = crop_y2 - crop_y1;
+    if crop_width == 0 || crop_height == 0 {
+        tracing::warn!("Zero-size crop for face {}", face_id);
+        return None;
+    }
+
+    Some(img.crop_imm(crop_x1, crop_y1, crop_width, crop_height))
+}
 
-        if crop_width == 0 || crop_height == 0 {
-            tracing::warn!("Zero-size crop for face {}", face_id);
+pub async fn face_thumb(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(face_id): Path<i64>,
+    Query(q): Query<std::collections::HashMap<String, String>>,
+) -> impl axum::response::IntoResponse {
+    let size: u32 = q.get("size").and_then(|s| s.parse().ok()).unwrap_or(160).clamp(32, 1024);
+    let pool = state.pool.clone();
+    let res: Option<(Vec<u8>,)> = tokio::task::spawn_blocking(move || {
+        let conn = pool.get().ok()?;
+        if !db::query::can_view_face(&conn, face_id, principal.scope()).ok()? {
             return None;
         }
-
-        let crop = img.crop_imm(crop_x1, crop_y1, crop_width, crop_height);
+        let crop = face_crop(&conn, face_id)?;
         let resized = crop.resize_exact(size, size, image::imageops::FilterType::Triangle);
         let mut buf = Vec::new();
         if resized.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageOutputFormat::Png).is_ok() {
@@ -438,33 +927,89 @@ pub async fn face_thumb(State(state): State<Arc<AppState>>, Path(face_id): Path<
 }
 
 // Person handlers
-pub async fn list_persons(State(state): State<Arc<AppState>>) -> impl axum::response::IntoResponse {
+#[derive(Deserialize)]
+pub struct PersonListQuery {
+    offset: Option<i64>,
+    /// Page size (default 100, max 1000)
+    limit: Option<i64>,
+    /// "faces" (default), "assets", "name" or "created"
+    sort: Option<String>,
+}
+
+pub async fn list_persons(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(q): Query<PersonListQuery>,
+) -> axum::response::Response {
+    list_subjects(state, q, crate::pipeline::face::Subject::Person, principal.scope()).await
+}
+
+/// Recognized pets, paged and sorted like persons
+pub async fn list_pets(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(q): Query<PersonListQuery>,
+) -> axum::response::Response {
+    list_subjects(state, q, crate::pipeline::face::Subject::Pet, principal.scope()).await
+}
+
+async fn list_subjects(
+    state: Arc<AppState>,
+    q: PersonListQuery,
+    subject: crate::pipeline::face::Subject,
+    scope: db::query::AccessScope,
+) -> axum::response::Response {
+    let sort = match q.sort.as_deref() {
+        None | Some("faces") | Some("count") => db::query::PersonSort::Faces,
+        Some("assets") => db::query::PersonSort::Assets,
+        Some("name") => db::query::PersonSort::Name,
+        Some("created") => db::query::PersonSort::Created,
+        Some(other) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("Unknown sort '{}', expected 'faces', 'assets', 'name' or 'created'", other)
+            }))).into_response();
+        }
+    };
+    let offset = q.offset.unwrap_or(0).max(0);
+    let limit = q.limit.unwrap_or(100).clamp(1, 1000);
+
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
-        move || {
-            let conn = pool.get().ok()?;
-            let persons = db::query::list_persons(&conn).ok()?;
-            Some(persons.into_iter().map(|(id, name, created_at)| {
-                serde_json::json!({
-                    "id": id,
-                    "name": name,
-                    "created_at": created_at
-                })
-            }).collect::<Vec<_>>())
+        move || -> anyhow::Result<_> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::list_persons_paged(&conn, offset, limit, sort, subject, scope)
         }
-    }).await.ok().flatten();
+    }).await;
 
     match result {
-        Some(persons) => (StatusCode::OK, Json(serde_json::json!(persons))),
-        None => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "Database error"}))),
+        Ok(Ok(page)) => (StatusCode::OK, Json(serde_json::json!(page))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error listing {}s: {}", subject.as_str(), e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error listing {}s: {}", subject.as_str(), e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
     }
 }
 
-pub async fn get_person(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl axum::response::IntoResponse {
+pub async fn get_person(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+) -> impl axum::response::IntoResponse {
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         move || {
             let conn = pool.get().ok()?;
+            if !db::query::can_view_person(&conn, id, principal.scope()).ok()? {
+                return None;
+            }
             db::query::get_person(&conn, id).ok()?
         }
     }).await.ok().flatten();
@@ -481,28 +1026,105 @@ pub async fn get_person(State(state): State<Arc<AppState>>, Path(id): Path<i64>)
     }
 }
 
-pub async fn get_person_assets(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl axum::response::IntoResponse {
+pub async fn get_person_assets(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+) -> impl axum::response::IntoResponse {
+    let scope = principal.scope();
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         move || {
             let conn = pool.get().ok()?;
-            db::query::get_person_assets(&conn, id).ok()
+            if !db::query::can_view_person(&conn, id, scope).ok()? {
+                return Some(None);
+            }
+            db::query::get_person_assets(&conn, id, scope).ok().map(Some)
         }
     }).await.ok().flatten();
 
     match result {
-        Some(assets) => (StatusCode::OK, Json(serde_json::json!({"asset_ids": assets}))),
+        Some(Some(assets)) => (StatusCode::OK, Json(serde_json::json!({"asset_ids": assets}))),
+        Some(None) => (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Person not found"}))),
         None => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "Database error"}))),
     }
 }
 
+#[derive(Deserialize)]
+pub struct PersonTimelineQuery {
+    /// "month" (default) or "year"
+    granularity: Option<String>,
+    /// Sample assets per bucket (default 4, max 20)
+    samples: Option<usize>,
+}
+
+#[cfg(feature = "facial-recognition")]
+pub async fn get_person_timeline(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+    Query(q): Query<PersonTimelineQuery>,
+) -> impl axum::response::IntoResponse {
+    let scope = principal.scope();
+    let by_month = match q.granularity.as_deref() {
+        None | Some("month") => true,
+        Some("year") => false,
+        Some(other) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("Unknown granularity '{}', expected 'month' or 'year'", other)
+            }))).into_response();
+        }
+    };
+    let samples = q.samples.unwrap_or(4).min(20);
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> anyhow::Result<Option<Vec<crate::models::person::PersonTimelineBucket>>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_person(&conn, id, scope)? {
+                return Ok(None);
+            }
+            Ok(Some(db::query::get_person_timeline(&conn, id, by_month, samples, scope)?))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(buckets))) => {
+            let total: i64 = buckets.iter().map(|b| b.count).sum();
+            (StatusCode::OK, Json(serde_json::json!({
+                "person_id": id,
+                "granularity": if by_month { "month" } else { "year" },
+                "total": total,
+                "buckets": buckets,
+            }))).into_response()
+        }
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Person not found"}))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error getting person timeline: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error getting person timeline: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
 #[cfg(feature = "facial-recognition")]
-pub async fn get_person_face(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl axum::response::IntoResponse {
+pub async fn get_person_face(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+) -> impl axum::response::IntoResponse {
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         move || {
             let conn = pool.get().ok()?;
-            db::query::get_person_representative_face(&conn, id).ok()
+            db::query::get_person_representative_face(&conn, id, principal.scope()).ok()
         }
     }).await.ok().flatten();
 
@@ -512,6 +1134,219 @@ pub async fn get_person_face(State(state): State<Arc<AppState>>, Path(id): Path<
     }
 }
 
+/// Faces in a person collage, as a grid of up to 3×3
+const COLLAGE_FACES: usize = 9;
+
+/// `GET /persons/:id/collage?size=`: the person's best faces as one square WebP grid, `size`
+/// pixels across (default 240)
+pub async fn person_collage(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+    Query(q): Query<std::collections::HashMap<String, String>>,
+) -> axum::response::Response {
+    let size: u32 = q.get("size").and_then(|s| s.parse().ok()).unwrap_or(240).clamp(64, 1024);
+    let scope = principal.scope();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> anyhow::Result<Option<Vec<u8>>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_person(&conn, id, scope)? {
+                return Ok(None);
+            }
+            // Faces whose file has gone missing are left out
+            let crops: Vec<_> = db::query::get_person_collage_faces(&conn, id, COLLAGE_FACES, scope)?
+                .into_iter()
+                .filter_map(|face_id| face_crop(&conn, face_id))
+                .collect();
+            if crops.is_empty() {
+                return Ok(None);
+            }
+            // 1, 2×2 or 3×3 cells; cells without a face stay dark
+            let cols = (crops.len() as f64).sqrt().ceil() as u32;
+            let cell = size / cols;
+            let mut grid = image::RgbImage::from_pixel(cell * cols, cell * cols, image::Rgb([32, 32, 32]));
+            for (i, crop) in crops.iter().enumerate() {
+                let tile = crop.resize_to_fill(cell, cell, image::imageops::FilterType::Triangle).to_rgb8();
+                let (x, y) = ((i as u32 % cols) * cell, (i as u32 / cols) * cell);
+                image::imageops::replace(&mut grid, &tile, x as i64, y as i64);
+            }
+            Ok(Some(crate::pipeline::thumb::encode_webp(&image::DynamicImage::ImageRgb8(grid))?))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(bytes))) => axum::http::Response::builder()
+            .status(StatusCode::OK)
+            .header(axum::http::header::CONTENT_TYPE, "image/webp")
+            .header(axum::http::header::CACHE_CONTROL, "no-cache")
+            .body(axum::body::Body::from(bytes))
+            .unwrap(),
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "No faces found for this person"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error building collage for person {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Collage error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error building person collage: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct ExportMetadataReq {
+    #[serde(default)]
+    pub target: xmp::Target,
+    /// Only photos showing one of these people
+    #[serde(default)]
+    pub person_ids: Option<Vec<i64>>,
+    /// Replace XMP that another tool wrote
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Write one photo's named faces as XMP face regions
+fn export_face_regions(photo: &db::query::NamedFaces, paths: &crate::AppPaths, target: xmp::Target, overwrite: bool) -> anyhow::Result<xmp::Outcome> {
+    if !photo.mime.starts_with("image/") {
+        return Ok(xmp::Outcome::Skipped("not a photo"));
+    }
+    let (Some(width), Some(height)) = (photo.width, photo.height) else {
+        return Ok(xmp::Outcome::Skipped("no dimensions"));
+    };
+    let regions: Vec<xmp::FaceRegion> = photo.faces.iter().filter_map(|(name, subject_type, bbox_json)| {
+        let bbox: crate::pipeline::face::FaceBbox = serde_json::from_str(bbox_json).ok()?;
+        Some(xmp::FaceRegion {
+            name: name.trim().to_string(),
+            kind: if subject_type == "pet" { "Pet" } else { "Face" },
+            x1: bbox.x1,
+            y1: bbox.y1,
+            x2: bbox.x2,
+            y2: bbox.y2,
+        })
+    }).collect();
+    if regions.is_empty() {
+        return Ok(xmp::Outcome::Skipped("no regions"));
+    }
+    let path = crate::utils::path::resolve_asset_path(&photo.path, paths);
+    if !path.exists() {
+        return Ok(xmp::Outcome::Skipped("file missing"));
+    }
+    // Locked photos are never modified, so they get a sidecar
+    let target = if photo.locked { xmp::Target::Sidecar } else { target };
+    let packet = xmp::regions_packet(width as u32, height as u32, &regions);
+    xmp::write_regions(&path, &packet, target, overwrite)
+}
+
+/// `POST /persons/export-metadata`: write the rectangles of named people and pets into XMP
+/// (MWG regions) for other photo tools to read
+pub async fn export_face_metadata(State(state): State<Arc<AppState>>, req: Option<Json<ExportMetadataReq>>) -> axum::response::Response {
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let paths = state.paths.clone();
+        move || -> anyhow::Result<xmp::ExportSummary> {
+            let photos = {
+                let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+                db::query::get_named_faces(&conn, req.person_ids.as_deref())?
+            };
+            let mut summary = xmp::ExportSummary::default();
+            for photo in &photos {
+                match export_face_regions(photo, &paths, req.target, req.overwrite) {
+                    Ok(outcome) => summary.record(outcome),
+                    Err(e) => {
+                        tracing::warn!("Failed to write face regions for asset {}: {}", photo.asset_id, e);
+                        summary.assets += 1;
+                        summary.failed += 1;
+                    }
+                }
+            }
+            Ok(summary)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(summary)) => {
+            tracing::info!("Face regions written for {} assets ({} sidecars, {} embedded)", summary.sidecars + summary.embedded, summary.sidecars, summary.embedded);
+            Json(serde_json::json!(summary)).into_response()
+        }
+        Ok(Err(e)) => {
+            tracing::error!("Error exporting face metadata: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error exporting face metadata: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetPersonCoverReq {
+    /// One of the person's faces, or null to pick one automatically again
+    face_id: Option<i64>,
+}
+
+#[cfg(feature = "facial-recognition")]
+pub async fn set_person_cover(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+    Json(req): Json<SetPersonCoverReq>,
+) -> impl axum::response::IntoResponse {
+    let scope = principal.scope();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> anyhow::Result<Result<Option<i64>, (StatusCode, &'static str)>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_person(&conn, id, scope)? {
+                return Ok(Err((StatusCode::NOT_FOUND, "Person not found")));
+            }
+            if let Some(face_id) = req.face_id {
+                if !db::query::can_view_face(&conn, face_id, scope)? {
+                    return Ok(Err((StatusCode::NOT_FOUND, "Face not found")));
+                }
+                if !db::query::face_belongs_to(&conn, face_id, id)? {
+                    return Ok(Err((StatusCode::BAD_REQUEST, "Face does not belong to this person")));
+                }
+            }
+            db::writer::set_person_cover(&conn, id, req.face_id)?;
+            Ok(Ok(db::query::get_person_representative_face(&conn, id, scope)?))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Ok(face_id))) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "cover_face_id": face_id,
+            "automatic": req.face_id.is_none(),
+        }))).into_response(),
+        Ok(Ok(Err((status, msg)))) => (status, Json(serde_json::json!({"error": msg}))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error setting person cover: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error setting person cover: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct UpdatePersonReq {
     name: Option<String>,
@@ -536,6 +1371,7 @@ pub async fn recluster_faces(State(state): State<Arc<AppState>>) -> impl axum::r
     if unassigned.is_empty() {
       return Some((0usize, 0usize));
     }
+    let pet_faces = crate::db::query::get_pet_face_ids(&conn).ok()?;
 
     // 3) Re-run clustering in-process using the same helper as the async workers
     // (We reuse the HDBSCAN-style wrapper from pipeline::face)
@@ -547,6 +1383,7 @@ pub async fn recluster_faces(State(state): State<Arc<AppState>>) -> impl axum::r
         embedding: emb,
         bbox,
         asset_id: 0, // asset_id not needed for clustering
+        subject: if pet_faces.contains(&face_id) { crate::pipeline::face::Subject::Pet } else { crate::pipeline::face::Subject::Person },
       }));
     }
 
@@ -560,17 +1397,17 @@ pub async fn recluster_faces(State(state): State<Arc<AppState>>) -> impl axum::r
       .and_then(|v| v.parse().ok())
       .unwrap_or(2);
 
-    let clusters = crate::pipeline::face::cluster_faces_hdbscan(&embeds_only, min_cluster_size, min_samples);
+    let clusters = crate::pipeline::face::cluster_by_subject(&embeds_only, min_cluster_size, min_samples);
 
-    let mut persons_created = 0usize;
+    let mut person_ids = Vec::new();
     let mut faces_assigned = 0usize;
-    for cluster in clusters {
+    for (subject, cluster) in clusters {
       if cluster.is_empty() {
         continue;
       }
-      let person_id = match crate::db::writer::insert_person(&conn, None) {
+      let person_id = match crate::db::writer::insert_subject(&conn, None, subject) {
         Ok(pid) => {
-          persons_created += 1;
+          person_ids.push(pid);
           pid
         }
         Err(_) => continue,
@@ -584,11 +1421,14 @@ pub async fn recluster_faces(State(state): State<Arc<AppState>>) -> impl axum::r
       }
     }
 
+    let persons_created = person_ids.len();
+    crate::pipeline::plugins::faces_clustered("recluster", person_ids, faces_assigned);
     Some((persons_created, faces_assigned))
   })
   .await
   .ok()
   .flatten();
+  state.face_index.lock().invalidate_persons();
 
   match result {
     Some((persons, faces)) => (
@@ -610,13 +1450,17 @@ pub async fn recluster_faces(State(state): State<Arc<AppState>>) -> impl axum::r
 }
 
 #[cfg(feature = "facial-recognition")]
-pub async fn refresh_person_profile(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl axum::response::IntoResponse {
+pub async fn refresh_person_profile(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+) -> impl axum::response::IntoResponse {
+    let scope = principal.scope();
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         move || {
             let conn = pool.get().ok()?;
-            let exists = db::query::get_person(&conn, id).ok()?.is_some();
-            if !exists {
+            if !db::query::can_view_person(&conn, id, scope).ok()? {
                 return Some(Err("Person not found".to_string()));
             }
     match db::writer::rebuild_person_profile(&conn, id) {
@@ -656,12 +1500,22 @@ pub async fn refresh_person_profile(State(state): State<Arc<AppState>>, Path(id)
     }
 }
 
-pub async fn update_person(State(state): State<Arc<AppState>>, Path(id): Path<i64>, Json(req): Json<UpdatePersonReq>) -> impl axum::response::IntoResponse {
+#[cfg(feature = "facial-recognition")]
+pub async fn update_person(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+    Json(req): Json<UpdatePersonReq>,
+) -> impl axum::response::IntoResponse {
+    let scope = principal.scope();
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         let name = req.name;
         move || {
             let conn = pool.get().ok()?;
+            if !db::query::can_view_person(&conn, id, scope).ok()? {
+                return Some(false);
+            }
             db::writer::update_person_name(&conn, id, name).ok()
         }
     }).await.ok().flatten();
@@ -673,14 +1527,20 @@ pub async fn update_person(State(state): State<Arc<AppState>>, Path(id): Path<i6
     }
 }
 
-pub async fn delete_person(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl axum::response::IntoResponse {
+#[cfg(feature = "facial-recognition")]
+pub async fn delete_person(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Path(id): Path<i64>) -> impl axum::response::IntoResponse {
+    let scope = principal.scope();
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         move || {
             let conn = pool.get().ok()?;
+            if !db::query::can_view_person(&conn, id, scope).ok()? {
+                return Some(false);
+            }
             db::writer::delete_person(&conn, id).ok()
         }
     }).await.ok().flatten();
+    state.face_index.lock().invalidate_persons();
 
     match result {
         Some(true) => (StatusCode::OK, Json(serde_json::json!({"success": true}))),
@@ -695,7 +1555,12 @@ pub struct MergePersonsReq {
     pub target_person_id: i64,
 }
 
-pub async fn merge_persons(State(state): State<Arc<AppState>>, Json(req): Json<MergePersonsReq>) -> impl axum::response::IntoResponse {
+#[cfg(feature = "facial-recognition")]
+pub async fn merge_persons(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<MergePersonsReq>,
+) -> impl axum::response::IntoResponse {
     // Prevent merging a person into itself
     if req.source_person_id == req.target_person_id {
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
@@ -703,6 +1568,7 @@ pub async fn merge_persons(State(state): State<Arc<AppState>>, Json(req): Json<M
         })));
     }
 
+    let scope = principal.scope();
     let result = tokio::task::spawn_blocking({
         let pool = state.pool.clone();
         let source_id = req.source_person_id;
@@ -710,9 +1576,9 @@ pub async fn merge_persons(State(state): State<Arc<AppState>>, Json(req): Json<M
         move || -> Option<Result<(db::writer::MergePersonsResult, Option<db::writer::PersonProfileSummary>), String>> {
             let conn = pool.get().ok()?;
 
-            // Validate that both persons exist
-            let source_exists = db::query::get_person(&conn, source_id).ok()?.is_some();
-            let target_exists = db::query::get_person(&conn, target_id).ok()?.is_some();
+            // Validate that both persons exist and the caller can see them
+            let source_exists = db::query::can_view_person(&conn, source_id, scope).ok()?;
+            let target_exists = db::query::can_view_person(&conn, target_id, scope).ok()?;
 
             if !source_exists {
                 return Some(Err("Source person not found".to_string()));
@@ -734,6 +1600,7 @@ pub async fn merge_persons(State(state): State<Arc<AppState>>, Json(req): Json<M
             }
         }
     }).await.ok().flatten();
+    state.face_index.lock().invalidate_persons();
 
     match result {
         Some(Ok((merge_info, profile))) => (StatusCode::OK, Json(serde_json::json!({
@@ -804,6 +1671,7 @@ pub async fn smart_merge_persons(
             }
         }
     }).await.ok().flatten();
+    state.face_index.lock().invalidate_persons();
 
     match result {
         Some(Ok((persons_merged, faces_merged))) => {
@@ -911,6 +1779,7 @@ pub async fn trigger_clustering(
             if unassigned.is_empty() {
                 return Some((0, 0, "No unassigned faces to cluster".to_string()));
             }
+            let pet_faces = db::query::get_pet_face_ids(&conn).ok()?;
 
             // Convert database embeddings to FaceEmbedding structs
             let mut face_embeddings = Vec::new();
@@ -936,6 +1805,7 @@ pub async fn trigger_clustering(
                     embedding,
                     bbox,
                     asset_id: *asset_id,
+                    subject: if pet_faces.contains(face_id) { crate::pipeline::face::Subject::Pet } else { crate::pipeline::face::Subject::Person },
                 });
                 face_id_map.push(*face_id);
             }
@@ -986,19 +1856,19 @@ pub async fn trigger_clustering(
                 }
             }
 
-            let clusters = crate::pipeline::face::cluster_faces_hdbscan(&face_embeddings, min_cluster_size, min_samples);
+            let clusters = crate::pipeline::face::cluster_by_subject(&face_embeddings, min_cluster_size, min_samples);
             tracing::info!("Clustering {} unassigned faces produced {} clusters", face_embeddings.len(), clusters.len());
 
             // Create persons and assign faces
-            let mut persons_created = 0;
+            let mut person_ids = Vec::new();
             let mut faces_assigned = 0;
 
-            for cluster in clusters {
+            for (subject, cluster) in clusters {
                 if cluster.is_empty() { continue; }
 
-                let person_id = match db::writer::insert_person(&conn, None) {
+                let person_id = match db::writer::insert_subject(&conn, None, subject) {
                     Ok(pid) => {
-                        persons_created += 1;
+                        person_ids.push(pid);
                         pid
                     },
                     Err(e) => {
@@ -1018,9 +1888,12 @@ pub async fn trigger_clustering(
                 }
             }
 
+            let persons_created = person_ids.len();
+            crate::pipeline::plugins::faces_clustered("unassigned", person_ids, faces_assigned);
             Some((persons_created, faces_assigned, format!("Clustered {} faces into {} persons", face_embeddings.len(), persons_created)))
         }
     }).await.ok().flatten();
+    state.face_index.lock().invalidate_persons();
 
     match result {
         Some((persons_created, faces_assigned, message)) => {
@@ -1077,6 +1950,10 @@ pub struct FaceSettings {
     pub cluster_epsilon: Option<f32>,
     pub min_cluster_size: Option<usize>,
     pub min_samples: Option<usize>,
+    /// "hdbscan" or "dbscan"
+    pub cluster_algorithm: Option<String>,
+    /// Largest distance from a person's centroid for a new face to join them directly; 0 turns it off
+    pub assign_threshold: Option<f32>,
     pub excluded_extensions: Option<Vec<String>>,
 }
 
@@ -1113,25 +1990,42 @@ pub async fn get_face_settings(State(state): State<Arc<AppState>>) -> impl IntoR
         cluster_epsilon: Some(cluster_epsilon),
         min_cluster_size: Some(min_cluster_size),
         min_samples: Some(min_samples),
+        cluster_algorithm: Some(crate::pipeline::face::cluster_algorithm().to_string()),
+        assign_threshold: Some(crate::pipeline::face::assign_threshold()),
         excluded_extensions,
     }))
 }
 
 pub async fn update_face_settings(State(state): State<Arc<AppState>>, Json(payload): Json<FaceSettings>) -> impl IntoResponse {
-    if let Some(v) = payload.confidence_threshold {
-        std::env::set_var("SEEN_FACE_CONFIDENCE_THRESHOLD", v.to_string());
-    }
-    if let Some(v) = payload.nms_iou_threshold {
-        std::env::set_var("SEEN_FACE_NMS_IOU_THRESHOLD", v.to_string());
-    }
-    if let Some(v) = payload.cluster_epsilon {
-        std::env::set_var("SEEN_FACE_CLUSTER_EPSILON", v.to_string());
-    }
-    if let Some(v) = payload.min_cluster_size {
-        std::env::set_var("SEEN_FACE_HDBSCAN_MIN_CLUSTER_SIZE", v.to_string());
+    if let Some(algorithm) = payload.cluster_algorithm.as_deref() {
+        if !crate::pipeline::face::CLUSTER_ALGORITHMS.contains(&algorithm) {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": format!("cluster_algorithm must be one of {}", crate::pipeline::face::CLUSTER_ALGORITHMS.join(", "))})));
+        }
     }
-    if let Some(v) = payload.min_samples {
-        std::env::set_var("SEEN_FACE_HDBSCAN_MIN_SAMPLES", v.to_string());
+    let changes: Vec<(String, Option<String>)> = [
+        ("SEEN_FACE_CLUSTER_ALGORITHM", payload.cluster_algorithm.clone()),
+        ("SEEN_FACE_CONFIDENCE_THRESHOLD", payload.confidence_threshold.map(|v| v.to_string())),
+        ("SEEN_FACE_NMS_IOU_THRESHOLD", payload.nms_iou_threshold.map(|v| v.to_string())),
+        ("SEEN_FACE_CLUSTER_EPSILON", payload.cluster_epsilon.map(|v| v.to_string())),
+        ("SEEN_FACE_HDBSCAN_MIN_CLUSTER_SIZE", payload.min_cluster_size.map(|v| v.to_string())),
+        ("SEEN_FACE_HDBSCAN_MIN_SAMPLES", payload.min_samples.map(|v| v.to_string())),
+        ("SEEN_FACE_ASSIGN_THRESHOLD", payload.assign_threshold.map(|v| v.to_string())),
+    ]
+    .into_iter()
+    .filter_map(|(var, value)| value.map(|value| (var.to_string(), Some(value))))
+    .collect();
+    crate::utils::env_settings::apply(changes.iter().map(|(var, value)| (var.as_str(), value.as_deref())));
+
+    // Keep them across restarts
+    if !changes.is_empty() {
+        let pool = state.pool.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            if let Ok(conn) = pool.get() {
+                if let Err(e) = crate::utils::env_settings::persist(&conn, &changes) {
+                    tracing::warn!("Failed to save face settings: {}", e);
+                }
+            }
+        }).await;
     }
 
     // Save excluded extensions to database
@@ -0,0 +1,485 @@
+use std::sync::Arc;
+use axum::{extract::{Path, Query, State}, http::{HeaderMap, StatusCode}, response::{IntoResponse, Response}, Extension, Json};
+use serde::Deserialize;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::api::auth::{self, Principal};
+use crate::api::{handlers, shares};
+
+/// Longest kiosk name accepted
+const MAX_NAME_LEN: usize = 100;
+
+#[derive(Deserialize)]
+pub struct CreateKioskRequest {
+    pub name: String,
+    /// Albums the kiosk may show, in display order
+    pub album_ids: Vec<i64>,
+}
+
+pub async fn create_kiosk(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<CreateKioskRequest>,
+) -> impl IntoResponse {
+    let name = req.name.trim().to_string();
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("name must be 1-{} characters", MAX_NAME_LEN)
+        }))).into_response();
+    }
+    if req.album_ids.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "album_ids must list at least one album"
+        }))).into_response();
+    }
+    let album_ids = req.album_ids;
+    let scope = principal.scope();
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<std::result::Result<(i64, String), i64>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            for &album_id in &album_ids {
+                if !db::query::can_view_album(&conn, album_id, scope)? {
+                    return Ok(Err(album_id));
+                }
+            }
+            let token = auth::generate_token()?;
+            let id = db::writer::insert_kiosk(&conn, &auth::hash_token(&token), &name, &album_ids, principal.user_id())?;
+            Ok(Ok((id, token)))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Ok((id, token)))) => (StatusCode::CREATED, Json(serde_json::json!({
+            "id": id,
+            "token": token,
+            "url": format!("/kiosk/{}", token)
+        }))).into_response(),
+        Ok(Ok(Err(album_id))) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": format!("Album {} not found", album_id)
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error creating kiosk token: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error creating kiosk token: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn list_kiosks(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<crate::models::share::Kiosk>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::list_kiosks(&conn, auth::owner_filter(&principal))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(kiosks)) => (StatusCode::OK, Json(kiosks)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error listing kiosk tokens: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error listing kiosk tokens: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn delete_kiosk(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::delete_kiosk(&conn, id, auth::owner_filter(&principal))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({ "success": true }))).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Kiosk not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error deleting kiosk token: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error deleting kiosk token: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+/// An album a kiosk shows, with the assets its creator can see
+#[derive(Debug)]
+pub(crate) struct KioskAlbum {
+    pub id: i64,
+    pub name: String,
+    pub asset_ids: Vec<i64>,
+}
+
+/// A kiosk token that has been presented
+#[derive(Debug)]
+pub(crate) struct OpenKiosk {
+    pub id: i64,
+    pub name: String,
+    pub albums: Vec<KioskAlbum>,
+}
+
+impl OpenKiosk {
+    pub fn asset_ids(&self) -> impl Iterator<Item = i64> + '_ {
+        self.albums.iter().flat_map(|a| a.asset_ids.iter().copied())
+    }
+
+    fn covers_asset(&self, asset_id: i64) -> bool {
+        self.asset_ids().any(|id| id == asset_id)
+    }
+}
+
+/// None for unknown or revoked tokens. Albums the creator can no longer see are left out.
+pub(crate) fn open_kiosk(conn: &rusqlite::Connection, token: &str, now: i64) -> Result<Option<OpenKiosk>> {
+    let Some(kiosk) = db::query::find_kiosk(conn, &auth::hash_token(token))? else {
+        return Ok(None);
+    };
+    db::writer::touch_kiosk(conn, kiosk.id, now)?;
+    let scope = shares::creator_scope(conn, kiosk.created_by)?;
+    let mut albums = Vec::with_capacity(kiosk.album_ids.len());
+    for album_id in kiosk.album_ids {
+        if !db::query::can_view_album(conn, album_id, scope)? {
+            continue;
+        }
+        if let Some((id, name, _, _, _, asset_ids, _)) = db::query::get_album(conn, album_id)? {
+            albums.push(KioskAlbum { id, name, asset_ids: db::query::filter_visible_assets(conn, asset_ids, scope)? });
+        }
+    }
+    Ok(Some(OpenKiosk { id: kiosk.id, name: kiosk.name, albums }))
+}
+
+pub(crate) async fn resolve_kiosk(state: &Arc<AppState>, token: String) -> std::result::Result<OpenKiosk, Response> {
+    let pool = state.pool.clone();
+    let result = tokio::task::spawn_blocking(move || -> Result<_> {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+        open_kiosk(&conn, &token, chrono::Utc::now().timestamp())
+    }).await;
+
+    match result {
+        Ok(Ok(Some(kiosk))) => Ok(kiosk),
+        Ok(Ok(None)) => Err((StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Kiosk not found"
+        }))).into_response()),
+        Ok(Err(e)) => {
+            tracing::error!("Error opening kiosk: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response())
+        }
+        Err(e) => {
+            tracing::error!("Task error opening kiosk: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response())
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct KioskQuery {
+    /// Only this album's assets
+    pub album_id: Option<i64>,
+}
+
+/// The kiosk's albums and the slideshow playlist; no authentication needed
+pub async fn view_kiosk(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Query(q): Query<KioskQuery>,
+) -> impl IntoResponse {
+    let kiosk = match resolve_kiosk(&state, token).await {
+        Ok(kiosk) => kiosk,
+        Err(response) => return response,
+    };
+    let asset_ids: Vec<i64> = match q.album_id {
+        Some(album_id) => match kiosk.albums.iter().find(|a| a.id == album_id) {
+            Some(album) => album.asset_ids.clone(),
+            None => {
+                return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+                    "error": "Album not found"
+                }))).into_response();
+            }
+        },
+        None => {
+            let mut seen = std::collections::HashSet::new();
+            kiosk.asset_ids().filter(|id| seen.insert(*id)).collect()
+        }
+    };
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<crate::models::share::SharedAsset>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::get_shared_assets(&conn, &asset_ids)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(assets)) => (StatusCode::OK, Json(serde_json::json!({
+            "name": kiosk.name,
+            "albums": kiosk.albums.iter().map(|a| serde_json::json!({
+                "id": a.id,
+                "name": a.name,
+                "asset_count": a.asset_ids.len()
+            })).collect::<Vec<_>>(),
+            "assets": assets
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error loading kiosk assets: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error loading kiosk assets: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+/// Let the request through only if the kiosk covers the asset
+async fn check_kiosk_asset(state: &Arc<AppState>, token: String, asset_id: i64) -> std::result::Result<(), Response> {
+    let kiosk = resolve_kiosk(state, token).await?;
+    if kiosk.covers_asset(asset_id) {
+        Ok(())
+    } else {
+        Err((StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset not found"
+        }))).into_response())
+    }
+}
+
+pub async fn kiosk_thumb(State(state): State<Arc<AppState>>, Path((token, asset_id)): Path<(String, i64)>) -> Response {
+    if let Err(response) = check_kiosk_asset(&state, token, asset_id).await {
+        return response;
+    }
+    handlers::thumb_256(State(state), Path(asset_id), Query(handlers::ThumbQuery { animated: None })).await.into_response()
+}
+
+pub async fn kiosk_preview(State(state): State<Arc<AppState>>, Path((token, asset_id)): Path<(String, i64)>) -> Response {
+    if let Err(response) = check_kiosk_asset(&state, token, asset_id).await {
+        return response;
+    }
+    handlers::preview_1600(State(state), Path(asset_id)).await.into_response()
+}
+
+/// The album's highlight slideshow video, if one has been rendered
+pub async fn kiosk_highlight(
+    State(state): State<Arc<AppState>>,
+    Path((token, album_id)): Path<(String, i64)>,
+    headers: HeaderMap,
+) -> Response {
+    let kiosk = match resolve_kiosk(&state, token).await {
+        Ok(kiosk) => kiosk,
+        Err(response) => return response,
+    };
+    if !kiosk.albums.iter().any(|a| a.id == album_id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response();
+    }
+    // Visibility was checked against the kiosk's creator above
+    handlers::get_album_highlight(State(state), Extension(Principal::Admin), Path(album_id), headers).await.into_response()
+}
+
+#[derive(Deserialize, Default)]
+pub struct FrameQuery {
+    /// Kiosk token; may also be sent as `Authorization: Bearer <token>`
+    pub token: Option<String>,
+    pub album_id: Option<i64>,
+    /// `landscape` or `portrait`
+    pub orientation: Option<String>,
+    pub min_rating: Option<i64>,
+    /// Only favorites
+    pub favorites: Option<bool>,
+}
+
+/// The next photo for an always-on frame: the best-scored image in the kiosk's albums that the frame
+/// hasn't shown yet this rotation. Once every match has been shown a new rotation starts, without
+/// repeating the last photo.
+pub async fn frame_next(State(state): State<Arc<AppState>>, headers: HeaderMap, Query(q): Query<FrameQuery>) -> Response {
+    let Some(token) = q.token.clone().or_else(|| auth::bearer_token(&headers).map(str::to_string)) else {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({
+            "error": "Kiosk token required"
+        }))).into_response();
+    };
+    let landscape = match q.orientation.as_deref() {
+        None => None,
+        Some("landscape") => Some(true),
+        Some("portrait") => Some(false),
+        Some(other) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("Invalid orientation '{}'", other)
+            }))).into_response();
+        }
+    };
+    let kiosk = match resolve_kiosk(&state, token.clone()).await {
+        Ok(kiosk) => kiosk,
+        Err(response) => return response,
+    };
+    let asset_ids: Vec<i64> = match q.album_id {
+        Some(album_id) => match kiosk.albums.iter().find(|a| a.id == album_id) {
+            Some(album) => album.asset_ids.clone(),
+            None => {
+                return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+                    "error": "Album not found"
+                }))).into_response();
+            }
+        },
+        None => {
+            let mut seen = std::collections::HashSet::new();
+            kiosk.asset_ids().filter(|id| seen.insert(*id)).collect()
+        }
+    };
+    let mut seed = [0u8; 8];
+    if let Err(e) = getrandom::fill(&mut seed) {
+        tracing::warn!("frame: no random seed, ties go to the first asset: {}", e);
+    }
+    let seed = u64::from_le_bytes(seed);
+    let kiosk_id = kiosk.id;
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<serde_json::Value>> {
+            use crate::pipeline::highlight::{self, HighlightCandidate};
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let mut assets: std::collections::HashMap<i64, _> = db::query::get_shared_assets(&conn, &asset_ids)?
+                .into_iter()
+                .filter(|a| a.mime.starts_with("image/"))
+                .filter(|a| match (landscape, a.width, a.height) {
+                    (None, _, _) => true,
+                    (Some(wide), Some(w), Some(h)) => (w >= h) == wide,
+                    _ => false,
+                })
+                .map(|a| (a.id, a))
+                .collect();
+            let ids: Vec<i64> = asset_ids.iter().copied().filter(|id| assets.contains_key(id)).collect();
+            // Only assets with thumbnails to show
+            let candidates: Vec<HighlightCandidate> = db::query::highlight_candidates(&conn, &ids)?
+                .into_iter()
+                .filter(|(_, sha, _, rating, favorite, _)| {
+                    sha.is_some() && *rating >= q.min_rating.unwrap_or(0) && (*favorite || !q.favorites.unwrap_or(false))
+                })
+                .map(|(id, _, taken_at, rating, favorite, face_count)| HighlightCandidate { id, taken_at, rating, favorite, face_count })
+                .collect();
+
+            let shown_ids = db::query::kiosk_shown(&conn, kiosk_id)?;
+            let mut shown: std::collections::HashSet<i64> = shown_ids.iter().copied().collect();
+            let next = match highlight::pick_next(&candidates, &shown, seed) {
+                Some(next) => Some(next),
+                None if candidates.is_empty() => None,
+                None => {
+                    db::writer::reset_kiosk_shown(&conn, kiosk_id)?;
+                    shown.clear();
+                    if let (Some(&last), true) = (shown_ids.first(), candidates.len() > 1) {
+                        shown.insert(last);
+                    }
+                    highlight::pick_next(&candidates, &shown, seed)
+                }
+            };
+            let Some(next) = next else {
+                return Ok(None);
+            };
+            db::writer::record_kiosk_shown(&conn, kiosk_id, next, chrono::Utc::now().timestamp())?;
+            shown.insert(next);
+            let score = candidates.iter().find(|c| c.id == next).map_or(0, |c| c.score());
+            Ok(Some(serde_json::json!({
+                "asset": assets.remove(&next),
+                "preview_url": format!("/kiosk/{}/preview/{}", token, next),
+                "score": score,
+                "remaining": candidates.iter().filter(|c| !shown.contains(&c.id)).count(),
+                "cycle_size": candidates.len()
+            })))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(body))) => (StatusCode::OK, Json(body)).into_response(),
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "No photos match"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error picking next frame photo: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error picking next frame photo: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_open_kiosk() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::schema::apply_schema(&conn).unwrap();
+        for path in ["/p/a.jpg", "/p/b.jpg", "/p/c.jpg"] {
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags)
+                 VALUES (?1, '/p', ?1, 'jpg', 1, 0, 0, 'image/jpeg', 0)",
+                [path],
+            ).unwrap();
+        }
+        let trip = db::writer::create_album(&conn, "Trip", None, None).unwrap();
+        let home = db::writer::create_album(&conn, "Home", None, None).unwrap();
+        let other = db::writer::create_album(&conn, "Other", None, None).unwrap();
+        db::writer::add_assets_to_album(&conn, trip, &[1, 2]).unwrap();
+        db::writer::add_assets_to_album(&conn, home, &[1]).unwrap();
+        db::writer::add_assets_to_album(&conn, other, &[3]).unwrap();
+
+        let id = db::writer::insert_kiosk(&conn, &auth::hash_token("frame"), "Hallway", &[home, trip], None).unwrap();
+        assert!(open_kiosk(&conn, "nope", 0).unwrap().is_none());
+        let kiosk = open_kiosk(&conn, "frame", 100).unwrap().unwrap();
+        assert_eq!(kiosk.albums.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), vec!["Home", "Trip"]);
+        assert!(kiosk.covers_asset(2) && !kiosk.covers_asset(3));
+        assert_eq!(db::query::list_kiosks(&conn, None).unwrap()[0].last_used_at, Some(100));
+
+        // Deleting an album drops it from the kiosk; revoking closes it
+        db::writer::delete_album(&conn, home).unwrap();
+        let kiosk = open_kiosk(&conn, "frame", 100).unwrap().unwrap();
+        assert_eq!(kiosk.asset_ids().collect::<Vec<_>>(), vec![1, 2]);
+        assert!(db::writer::delete_kiosk(&conn, id, None).unwrap());
+        assert!(open_kiosk(&conn, "frame", 100).unwrap().is_none());
+    }
+}
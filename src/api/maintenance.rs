@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use axum::{extract::{Query, State}, http::{header, StatusCode}, response::{IntoResponse, Response}, Json};
+use crate::AppState;
+use serde::Deserialize;
+use crate::utils::{derived_gc, report, storage};
+
+/// Remove derived files (thumbnails, previews, transcodes, highlight videos) of assets and albums that are gone
+pub async fn run_gc(State(state): State<Arc<AppState>>) -> Response {
+    let result = tokio::task::spawn_blocking({
+        let state = state.clone();
+        move || derived_gc::run(&state)
+    }).await;
+
+    match result {
+        Ok(Ok(Some(report))) => Json(report).into_response(),
+        Ok(Ok(None)) => (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "A derived cache collection is already running"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error collecting derived files: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error collecting derived files: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+/// The latest collection, periodic or requested
+pub async fn gc_status(State(state): State<Arc<AppState>>) -> Response {
+    match state.derived_gc.lock().clone() {
+        Some(report) => Json(report).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "No derived cache collection has run yet"
+        }))).into_response(),
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct StorageQuery {
+    /// Recompute in the background
+    pub refresh: Option<bool>,
+}
+
+/// Disk usage of the database, derived cache and originals, from the background sizer's latest report
+pub async fn get_storage(State(state): State<Arc<AppState>>, Query(q): Query<StorageQuery>) -> Response {
+    let report = state.storage.lock().clone();
+    if report.is_none() || q.refresh.unwrap_or(false) {
+        storage::spawn_refresh(state.clone());
+    }
+    match report {
+        Some(report) => Json(report).into_response(),
+        None => (StatusCode::ACCEPTED, Json(serde_json::json!({
+            "status": "computing"
+        }))).into_response(),
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct ReportRequest {
+    /// "html" (default) or "pdf"
+    pub format: Option<String>,
+}
+
+/// Library statistics as a downloadable HTML page or PDF, for keeping with periodic reviews
+pub async fn library_report(State(state): State<Arc<AppState>>, body: Option<Json<ReportRequest>>) -> Response {
+    let format = body.and_then(|Json(b)| b.format).unwrap_or_else(|| "html".to_string()).to_lowercase();
+    if format != "html" && format != "pdf" {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "format must be \"html\" or \"pdf\""
+        }))).into_response();
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let storage = state.storage.lock().clone();
+        move || -> anyhow::Result<report::LibraryReport> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            report::build(&conn, storage)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(library)) => {
+            let filename = format!("library-report-{}.{}", chrono::Utc::now().format("%Y-%m-%d"), format);
+            let (content_type, body) = if format == "pdf" {
+                ("application/pdf", report::render_pdf(&library))
+            } else {
+                ("text/html; charset=utf-8", report::render_html(&library).into_bytes())
+            };
+            (
+                [
+                    (header::CONTENT_TYPE, content_type.to_string()),
+                    (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+                ],
+                body,
+            ).into_response()
+        }
+        Ok(Err(e)) => {
+            tracing::error!("Error building library report: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error building library report: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+use axum::{extract::{Query, State}, http::{header, StatusCode}, response::{IntoResponse, Response}, Json};
+use serde::Deserialize;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::utils::manifest::{self, VerifyJob};
+
+/// A manifest line is ~100 bytes, so this covers a few million files
+pub const MANIFEST_MAX_BYTES: usize = 512 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct ExportManifestQuery {
+    /// Only include assets under this directory, with paths relative to it
+    pub path: Option<String>,
+}
+
+struct ManifestText {
+    text: String,
+    entries: usize,
+    /// Assets whose original could not be read to fill in a missing hash
+    skipped: usize,
+}
+
+/// SHA-256 manifest of the originals, in `sha256sum` format
+pub async fn export_manifest(State(state): State<Arc<AppState>>, Query(q): Query<ExportManifestQuery>) -> Response {
+    let dir = q.path.map(|p| p.trim_end_matches(['/', '\\']).to_string()).filter(|p| !p.is_empty());
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let dir = dir.clone();
+        move || -> Result<ManifestText> {
+            let rows = {
+                let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+                db::query::manifest_rows(&conn, dir.as_deref())?
+            };
+            let mut out = ManifestText { text: String::with_capacity(rows.len() * 100), entries: 0, skipped: 0 };
+            for (path, sha256) in rows {
+                // Very large images are indexed without SHA-256, so hash those now
+                let digest = match sha256.filter(|s| !s.is_empty()) {
+                    Some(digest) => digest,
+                    None => match crate::pipeline::hash::sha256_file(std::path::Path::new(&path)) {
+                        Ok(digest) => digest,
+                        Err(e) => {
+                            tracing::warn!("manifest export: cannot hash {:?}: {}", path, e);
+                            out.skipped += 1;
+                            continue;
+                        }
+                    },
+                };
+                let listed = match &dir {
+                    Some(dir) => path[dir.len()..].trim_start_matches(['/', '\\']).to_string(),
+                    None => path,
+                };
+                out.text.push_str(&manifest::format_line(&hex::encode(digest), &listed));
+                out.entries += 1;
+            }
+            Ok(out)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(ManifestText { text, entries, skipped })) => (
+            [
+                (header::CONTENT_TYPE, "text/plain; charset=utf-8".to_string()),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"seen-manifest.sha256\"".to_string()),
+                (header::HeaderName::from_static("x-manifest-entries"), entries.to_string()),
+                (header::HeaderName::from_static("x-manifest-skipped"), skipped.to_string()),
+            ],
+            text,
+        ).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error exporting manifest: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error exporting manifest: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct VerifyManifestQuery {
+    /// Directory the manifest's relative paths are resolved against, e.g. the backup copy
+    pub root: Option<String>,
+}
+
+/// Start checking a copy against an uploaded manifest; progress via GET /manifest/verify
+pub async fn verify_manifest(State(state): State<Arc<AppState>>, Query(q): Query<VerifyManifestQuery>, body: String) -> Response {
+    let entries = match manifest::parse(&body) {
+        Ok(entries) if !entries.is_empty() => entries,
+        Ok(_) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "Manifest is empty"
+            }))).into_response();
+        }
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response();
+        }
+    };
+    let root = q.root.filter(|r| !r.is_empty());
+    match &root {
+        Some(root) if !std::path::Path::new(root).is_dir() => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("{} is not a directory", root)
+            }))).into_response();
+        }
+        None if entries.iter().any(|e| !std::path::Path::new(&e.path).is_absolute()) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "Manifest has relative paths; pass root to say where the copy lives"
+            }))).into_response();
+        }
+        _ => {}
+    }
+
+    let job = {
+        let mut current = state.manifest_verify.lock();
+        if current.as_ref().is_some_and(|job| job.is_running()) {
+            return (StatusCode::CONFLICT, Json(serde_json::json!({
+                "error": "A verification is already running"
+            }))).into_response();
+        }
+        let job = Arc::new(VerifyJob::new(root.clone(), entries.len() as u64, chrono::Utc::now().timestamp()));
+        *current = Some(job.clone());
+        job
+    };
+
+    let total = entries.len();
+    tokio::task::spawn_blocking({
+        let job = job.clone();
+        move || {
+            job.run(&entries, root.as_deref().map(std::path::Path::new));
+            let report = job.report();
+            tracing::info!("manifest verify finished: {} ok, {} missing, {} modified", report.ok, report.missing_count, report.modified_count);
+        }
+    });
+
+    (StatusCode::ACCEPTED, Json(serde_json::json!({
+        "success": true,
+        "entries": total
+    }))).into_response()
+}
+
+pub async fn verify_manifest_status(State(state): State<Arc<AppState>>) -> Response {
+    match state.manifest_verify.lock().as_ref() {
+        Some(job) => Json(job.report()).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "No verification has been run"
+        }))).into_response(),
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use axum::{extract::{Query, State}, http::{header, HeaderMap, StatusCode}, response::{IntoResponse, Response}, Extension, Json};
+use serde::Deserialize;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::api::auth::{self, Principal};
+use crate::pipeline::memories;
+use crate::utils::ics::{self, IcsEvent};
+
+/// Days before today the feed still lists, so calendars keep recent entries
+const FEED_PAST_DAYS: u64 = 30;
+
+#[derive(Deserialize, Default)]
+pub struct MemoriesFeedQuery {
+    /// API token, for calendar apps that can't send headers
+    pub token: Option<String>,
+    /// Days ahead to list (default 365, max 730)
+    pub days: Option<u64>,
+}
+
+/// Scheme and host the request came in on, for links back into the app
+pub(crate) fn origin(headers: &HeaderMap) -> String {
+    let get = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).and_then(|v| v.split(',').next()).map(str::trim);
+    let host = get("x-forwarded-host").or_else(|| get(header::HOST.as_str())).unwrap_or("localhost");
+    let scheme = get("x-forwarded-proto").unwrap_or("http");
+    format!("{}://{}", scheme, host)
+}
+
+/// Anniversaries of detected events and "on this day" memories as an iCalendar feed
+pub async fn memories_feed(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+    Query(q): Query<MemoriesFeedQuery>,
+) -> Response {
+    let days = q.days.unwrap_or(365).min(730);
+    let origin = origin(&headers);
+    let auth_enabled = state.auth.enabled();
+    let hide_received = state.received_media.read().hide_in_memories;
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<String>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let now = chrono::Utc::now();
+            let Some(principal) = auth::subscription_principal(&conn, q.token.as_deref(), principal, auth_enabled, now.timestamp())? else {
+                return Ok(None);
+            };
+            let assets = db::query::dated_assets(&conn, principal.scope(), hide_received)?;
+            let today = now.date_naive();
+            let from = today - chrono::Days::new(FEED_PAST_DAYS);
+            let to = today + chrono::Days::new(days);
+            let mut entries = memories::anniversaries(&memories::detect_events(&assets), from, to);
+            entries.extend(memories::on_this_day(&assets, from, to));
+            let events: Vec<IcsEvent> = entries
+                .into_iter()
+                .map(|m| IcsEvent {
+                    uid: format!("{}@seen", m.key),
+                    date: m.date,
+                    summary: m.summary,
+                    description: m.description,
+                    url: Some(format!("{}/asset/{}", origin, m.asset_id)),
+                })
+                .collect();
+            Ok(Some(ics::calendar("Photo memories", &events, now)))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(body))) => ([(header::CONTENT_TYPE, "text/calendar; charset=utf-8")], body).into_response(),
+        Ok(Ok(None)) => (StatusCode::UNAUTHORIZED, Json(serde_json::json!({
+            "error": "Invalid token"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error building memories feed: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error building memories feed: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
@@ -1,4 +1,20 @@
+pub mod auth;
 pub mod routes;
+pub mod cast;
+pub mod feeds;
 pub mod handlers;
+pub mod kiosk;
+pub mod maintenance;
+pub mod manifest;
+pub mod memories;
+pub mod reference;
+pub mod rules;
+pub mod settings;
+pub mod shares;
+pub mod site_export;
+pub mod thumbnails;
+pub mod ws;
 #[cfg(feature = "facial-recognition")]
 pub mod handlers_face;
+#[cfg(feature = "semantic-search")]
+pub mod semantic;
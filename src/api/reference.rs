@@ -0,0 +1,217 @@
+use std::sync::Arc;
+use axum::{extract::{Path, State}, http::{header, StatusCode}, response::{IntoResponse, Response}, Extension, Json};
+#[cfg(feature = "facial-recognition")]
+use axum::extract::Query;
+#[cfg(feature = "facial-recognition")]
+use serde::Deserialize;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::api::auth::Principal;
+use crate::utils::reference::ReferenceImage;
+
+/// Reference images are single photos, so they get a much smaller limit than imports
+pub const REFERENCE_MAX_BYTES: usize = 50 * 1024 * 1024;
+
+struct Uploaded {
+    reference: ReferenceImage,
+    width: u32,
+    height: u32,
+    /// Library asset with identical content, if any
+    exact_match: Option<(i64, String)>,
+}
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, Json(serde_json::json!({
+        "error": "Reference image not found or expired"
+    }))).into_response()
+}
+
+pub async fn upload_reference(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    body: axum::body::Bytes,
+) -> Response {
+    if body.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Request body is empty"
+        }))).into_response();
+    }
+
+    let scope = principal.scope();
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let store = state.reference_images.clone();
+        move || -> Result<Option<Uploaded>> {
+            // Only formats we can decode are useful as a query
+            let Some(ext) = image::guess_format(&body).ok().and_then(|f| f.extensions_str().first().copied()) else {
+                return Ok(None);
+            };
+            let Ok(decoded) = image::load_from_memory(&body) else {
+                return Ok(None);
+            };
+            let reference = store.insert(scope, ext, &body, chrono::Utc::now().timestamp())?;
+
+            // The same file may already be in the library
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let exact_match = db::query::find_asset_by_hash(&conn, &reference.sha256, reference.xxh64, reference.size_bytes, scope)?;
+            Ok(Some(Uploaded { reference, width: decoded.width(), height: decoded.height(), exact_match }))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(Uploaded { reference, width, height, exact_match }))) => (StatusCode::CREATED, Json(serde_json::json!({
+            "id": reference.id,
+            "width": width,
+            "height": height,
+            "size_bytes": reference.size_bytes,
+            "expires_at": reference.expires_at,
+            "exact_match": exact_match.map(|(asset_id, path)| serde_json::json!({ "asset_id": asset_id, "path": path }))
+        }))).into_response(),
+        Ok(Ok(None)) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Body is not a supported image (JPEG, PNG, WebP, GIF, TIFF or BMP)"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error storing reference image: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Upload failed: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error storing reference image: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn get_reference_image(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+) -> Response {
+    let Some(reference) = state.reference_images.get(&id, principal.scope(), chrono::Utc::now().timestamp()) else {
+        return not_found();
+    };
+    match tokio::fs::read(&reference.path).await {
+        Ok(bytes) => {
+            let mime = mime_guess::from_path(&reference.path).first_or_octet_stream();
+            ([(header::CONTENT_TYPE, mime.to_string()), (header::CACHE_CONTROL, "private, no-store".to_string())], bytes).into_response()
+        }
+        Err(_) => not_found(),
+    }
+}
+
+pub async fn delete_reference(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+) -> Response {
+    if state.reference_images.remove(&id, principal.scope()) {
+        Json(serde_json::json!({ "success": true })).into_response()
+    } else {
+        not_found()
+    }
+}
+
+#[cfg(feature = "facial-recognition")]
+#[derive(Deserialize)]
+pub struct ReferenceFaceQuery {
+    /// Matching assets returned per detected face (default: 50, max: 500)
+    pub limit: Option<usize>,
+    /// Maximum cosine distance for a match (default: 0.5)
+    pub max_distance: Option<f32>,
+}
+
+/// Detect faces in a reference image and rank library assets containing similar faces
+#[cfg(feature = "facial-recognition")]
+pub async fn reference_faces(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+    Query(q): Query<ReferenceFaceQuery>,
+) -> Response {
+    let scope = principal.scope();
+    let Some(reference) = state.reference_images.get(&id, scope, chrono::Utc::now().timestamp()) else {
+        return not_found();
+    };
+    {
+        let processor = state.face_processor.lock();
+        if !processor.scrfd_loaded() || !processor.arcface_loaded() {
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
+                "error": "Face models are not loaded"
+            }))).into_response();
+        }
+    }
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+    let max_distance = q.max_distance.unwrap_or(0.5).clamp(0.0, 2.0);
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let processor = state.face_processor.clone();
+        move || -> Result<Vec<serde_json::Value>> {
+            let detected = processor.lock().process_image(0, &reference.path)?;
+            if detected.is_empty() {
+                return Ok(Vec::new());
+            }
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let library: Vec<(i64, i64, Vec<f32>, Option<i64>)> = db::query::get_all_face_embeddings(&conn)?
+                .into_iter()
+                .filter_map(|(face_id, asset_id, blob, person_id)| {
+                    db::query::decode_embedding_blob(&blob).ok().map(|e| (face_id, asset_id, e, person_id))
+                })
+                .collect();
+
+            let mut faces = Vec::with_capacity(detected.len());
+            for (index, face) in detected.iter().enumerate() {
+                let mut candidates: Vec<(f32, i64, i64, Option<i64>)> = library.iter()
+                    .map(|(face_id, asset_id, embedding, person_id)| {
+                        (crate::pipeline::face::cosine_distance(&face.embedding, embedding), *face_id, *asset_id, *person_id)
+                    })
+                    .filter(|(distance, ..)| *distance <= max_distance)
+                    .collect();
+                candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+                // Best face per asset, skipping assets the caller can't see
+                let mut seen_assets = std::collections::HashSet::new();
+                let mut matches = Vec::new();
+                for (distance, face_id, asset_id, person_id) in candidates {
+                    if matches.len() >= limit {
+                        break;
+                    }
+                    if !seen_assets.insert(asset_id) || !db::query::can_view_asset(&conn, asset_id, scope)? {
+                        continue;
+                    }
+                    matches.push(serde_json::json!({
+                        "asset_id": asset_id,
+                        "face_id": face_id,
+                        "person_id": person_id,
+                        "distance": distance
+                    }));
+                }
+                faces.push(serde_json::json!({
+                    "index": index,
+                    "bbox": face.bbox,
+                    "matches": matches
+                }));
+            }
+            Ok(faces)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(faces)) => Json(serde_json::json!({ "faces": faces })).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error searching faces for reference image {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Face search failed: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error searching reference faces: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
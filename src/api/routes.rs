@@ -3,60 +3,159 @@ use std::sync::Arc;
 use tower_http::cors::{CorsLayer, AllowOrigin};
 use tower_http::services::ServeDir;
 use axum::http::Method;
+use axum::extract::DefaultBodyLimit;
 use crate::AppState;
-use crate::api::handlers;
+use crate::api::{auth, cast, feeds, handlers, kiosk, maintenance, manifest, memories, reference, rules, settings, shares, site_export, thumbnails, ws};
 #[cfg(feature = "facial-recognition")]
 use crate::api::handlers_face;
+#[cfg(feature = "semantic-search")]
+use crate::api::semantic;
 
 pub fn router(state: Arc<AppState>) -> Router {
     let cors = CorsLayer::new()
         .allow_origin(AllowOrigin::any())
-        .allow_methods(vec![Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
-        .allow_headers(vec![axum::http::header::CONTENT_TYPE, axum::http::header::ACCEPT]);
+        .allow_methods(vec![Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
+        .allow_headers(vec![axum::http::header::CONTENT_TYPE, axum::http::header::ACCEPT, axum::http::header::AUTHORIZATION]);
 
     let api_router = {
         let r = Router::new()
             .route("/health", get(handlers::health))
+            .route("/auth/login", post(auth::login))
+            .route("/auth/logout", post(auth::logout))
+            .route("/auth/status", get(auth::auth_status))
+            .route("/auth/tokens", get(auth::list_tokens).post(auth::create_token))
+            .route("/auth/tokens/:id", delete(auth::delete_token))
+            .route("/users", get(auth::list_users).post(auth::create_user))
+            .route("/users/:id", delete(auth::delete_user))
             .route("/stats", get(handlers::stats))
             .route("/stats/reset", post(handlers::reset_stats))
+            .route("/stats/calendar", get(handlers::stats_calendar))
+            .route("/stats/slowest", get(handlers::stats_slowest))
+            .route("/ws", get(ws::ws_handler))
             .route("/clear", delete(handlers::clear_all_data))
             .route("/assets", get(handlers::assets))
             .route("/assets/search", get(handlers::assets_search))
+            .route("/assets/new", get(handlers::new_assets))
+            .route("/assets/compare", get(handlers::compare_assets))
+            .route("/search/reference", post(reference::upload_reference).layer(DefaultBodyLimit::max(reference::REFERENCE_MAX_BYTES)))
+            .route("/search/reference/:id", delete(reference::delete_reference))
+            .route("/search/reference/:id/image", get(reference::get_reference_image))
             .route("/thumb/:id", get(handlers::thumb_256))
             .route("/preview/:id", get(handlers::preview_1600))
             .route("/asset/:id", get(handlers::get_asset))
             .route("/asset/:id/video", get(handlers::stream_video))
+            .route("/assets/:id/live", get(handlers::stream_live_photo))
+            .route("/assets/:id/transcode", get(handlers::get_transcode_status).post(handlers::request_transcode))
             .route("/asset/:id/audio.mp3", get(handlers::extract_audio_mp3))
             .route("/asset/:id/download", get(handlers::download_asset))
             .route("/asset/:id", delete(handlers::delete_asset))
             .route("/asset/:id/permanent", delete(handlers::delete_asset_permanent))
             .route("/assets/permanent", post(handlers::delete_assets_permanent))
+            .route("/deletes/pending", get(handlers::list_pending_deletes))
+            .route("/deletes/pending/:id/undo", post(handlers::undo_pending_delete))
             .route("/asset/:id/orientation", post(handlers::save_orientation))
             .route("/file-types", get(handlers::file_types))
             .route("/metrics", get(handlers::metrics))
             .route("/performance", get(handlers::performance))
             .route("/diag/ffmpeg", get(handlers::diag_ffmpeg))
+            .route("/diag/selfcheck", get(handlers::diag_selfcheck))
+            .route("/settings", get(settings::get_settings))
+            .route("/settings/ingest", put(settings::update_ingest_settings))
+            .route("/settings/extractors", put(settings::update_extractor_settings))
+            .route("/settings/received-media", put(settings::update_received_media_settings))
+            .route("/settings/env", put(settings::update_env_settings))
+            .route("/settings/low-impact", put(settings::update_low_impact_settings))
+            .route("/settings/sqlite", put(settings::update_sqlite_settings))
+            .route("/settings/db-pool", put(settings::update_pool_settings))
+            .route("/settings/digest", put(settings::update_digest_settings))
+            .route("/settings/digest/send", post(settings::send_digest_now))
+            .route("/pipeline/pause", post(handlers::pause_pipeline))
+            .route("/pipeline/resume", post(handlers::resume_pipeline))
+            .route("/admin/queues/flush", post(handlers::flush_queues))
             // More specific routes must come before less specific ones
             .route("/paths/scan", post(handlers::scan_path))
             .route("/paths/pause", post(handlers::pause_path))
+            .route("/paths/cancel", post(handlers::cancel_path))
+            .route("/paths/history", get(handlers::get_scan_history))
             .route("/paths/resume", post(handlers::resume_path))
             .route("/paths/status", get(handlers::get_path_status))
+            .route("/paths/schedule", put(handlers::set_path_schedule))
+            .route("/paths/filters", put(handlers::set_path_filters))
+            .route("/paths/dates", put(handlers::set_path_date_priority))
+            .route("/paths/quota", put(handlers::set_path_quota))
+            .route("/paths/archive", put(handlers::set_path_archived))
+            .route("/paths/relocate", put(handlers::relocate_path))
             .route("/paths", get(handlers::get_scan_paths))
             .route("/paths", post(handlers::add_scan_path))
             .route("/paths", delete(handlers::remove_scan_path))
             .route("/browse", get(handlers::browse_directory))
             .route("/albums", get(handlers::list_albums))
             .route("/albums", post(handlers::create_album))
+            .route("/albums/batch", post(handlers::batch_album_assets))
             .route("/albums/:id", get(handlers::get_album))
             .route("/albums/:id", put(handlers::update_album))
             .route("/albums/:id", delete(handlers::delete_album))
+            .route("/albums/:id/stats", get(handlers::get_album_stats))
+            .route("/albums/:id/people", get(handlers::get_album_people))
+            .route("/albums/:id/highlight", get(handlers::get_album_highlight).post(handlers::create_album_highlight))
+            .route("/albums/:id/export-site", post(site_export::export_album_site))
+            .route("/albums/:id/shares", get(handlers::list_album_shares).post(handlers::share_album))
+            .route("/albums/:id/shares/:user_id", delete(handlers::unshare_album))
             .route("/albums/:id/assets", post(handlers::add_assets_to_album))
             .route("/albums/:id/assets", delete(handlers::remove_assets_from_album))
-            .route("/albums/for-asset/:asset_id", get(handlers::get_albums_for_asset));
+            .route("/albums/for-asset/:asset_id", get(handlers::get_albums_for_asset))
+            .route("/shares", get(shares::list_shares).post(shares::create_share))
+            .route("/shares/:id", delete(shares::delete_share))
+            .route("/share/:token", get(shares::view_share).post(shares::unlock_share))
+            .route("/share/:token/thumb/:asset_id", get(shares::share_thumb))
+            .route("/share/:token/preview/:asset_id", get(shares::share_preview))
+            .route("/share/:token/download/:asset_id", get(shares::share_download))
+            .route("/rules", get(rules::list_rules).post(rules::create_rule))
+            .route("/rules/:id", put(rules::update_rule).delete(rules::delete_rule))
+            .route("/kiosks", get(kiosk::list_kiosks).post(kiosk::create_kiosk))
+            .route("/kiosks/:id", delete(kiosk::delete_kiosk))
+            .route("/kiosk/:token", get(kiosk::view_kiosk))
+            .route("/kiosk/:token/thumb/:asset_id", get(kiosk::kiosk_thumb))
+            .route("/kiosk/:token/preview/:asset_id", get(kiosk::kiosk_preview))
+            .route("/kiosk/:token/albums/:album_id/highlight", get(kiosk::kiosk_highlight))
+            .route("/frame/next", get(kiosk::frame_next))
+            .route("/memories.ics", get(memories::memories_feed))
+            .route("/feeds/recent.xml", get(feeds::recent_rss))
+            .route("/feeds/recent.json", get(feeds::recent_json))
+            .route("/cast/sessions", post(cast::create_cast_session))
+            .route("/cast/:token", delete(cast::delete_cast_session))
+            .route("/cast/:token/queue", get(cast::cast_session_queue))
+            .route("/cast/:token/media/:asset_id", get(cast::cast_media))
+            .route("/cast/:token/thumb/:asset_id", get(cast::cast_thumb))
+            .route("/assets/:id/rating", put(handlers::set_asset_rating))
+            .route("/assets/:id/favorite", put(handlers::set_asset_favorite))
+            .route("/assets/:id/archive", put(handlers::set_asset_archived))
+            .route("/assets/:id/lock", put(handlers::set_asset_locked))
+            .route("/stacks", get(handlers::list_stacks))
+            .route("/stacks/rebuild", post(handlers::rebuild_stacks))
+            .route("/import", post(handlers::import_file).layer(DefaultBodyLimit::max(import_max_bytes())))
+            .route("/export", post(handlers::export_library))
+            .route("/import/archive", post(handlers::restore_library).layer(DefaultBodyLimit::disable()))
+            .route("/manifest", get(manifest::export_manifest))
+            .route("/manifest/verify", get(manifest::verify_manifest_status).post(manifest::verify_manifest).layer(DefaultBodyLimit::max(manifest::MANIFEST_MAX_BYTES)))
+            .route("/thumbnails/warm", get(thumbnails::warm_thumbnails_status).post(thumbnails::warm_thumbnails))
+            .route("/thumbnails/regenerate", post(thumbnails::regenerate_thumbnails))
+            .route("/maintenance/gc", get(maintenance::gc_status).post(maintenance::run_gc))
+            .route("/storage", get(maintenance::get_storage))
+            .route("/reports/library", post(maintenance::library_report))
+            .route("/tags", get(handlers::list_tags))
+            .route("/assets/:id/tags", get(handlers::get_asset_tags))
+            .route("/assets/:id/tags", post(handlers::add_asset_tags))
+            .route("/assets/:id/tags/:tag", delete(handlers::remove_asset_tag))
+            .route("/assets/:id/properties", get(handlers::get_asset_properties))
+            .route("/assets/:id/properties", put(handlers::set_asset_properties))
+            .route("/assets/:id/properties/:key", delete(handlers::remove_asset_property));
 
         #[cfg(feature = "facial-recognition")]
         let r = {
             r.route("/faces/detect", post(handlers_face::detect_faces))
+                .route("/assets/:id/detect-faces", post(handlers_face::detect_asset_faces))
+                .route("/albums/:id/detect-faces", post(handlers_face::detect_album_faces))
                 .route("/faces/stop", post(handlers_face::stop_face_detection))
                 .route("/faces/settings", get(handlers_face::get_face_settings).post(handlers_face::update_face_settings))
                 .route("/faces/status", get(handlers_face::face_detection_status))
@@ -65,6 +164,9 @@ pub fn router(state: Arc<AppState>) -> Router {
                 .route("/faces/:id/thumb", get(handlers_face::face_thumb))
                 .route("/faces/recluster/person/:id", post(handlers_face::refresh_person_profile))
                 .route("/faces/:id/assign", post(handlers_face::assign_face_to_person))
+                .route("/faces/:id/similar", get(handlers_face::similar_faces))
+                .route("/faces/:id/feedback", post(handlers_face::face_feedback))
+                .route("/faces/assign", post(handlers_face::assign_faces))
                 .route("/faces/cluster", post(handlers_face::trigger_clustering))
                 .route("/faces/recluster", post(handlers_face::recluster_faces))
                 .route("/faces/smart-merge", post(handlers_face::smart_merge_persons))
@@ -72,15 +174,25 @@ pub fn router(state: Arc<AppState>) -> Router {
                 .route("/persons", get(handlers_face::list_persons))
                 .route("/persons/:id", get(handlers_face::get_person))
                 .route("/persons/:id/assets", get(handlers_face::get_person_assets))
+                .route("/persons/:id/timeline", get(handlers_face::get_person_timeline))
                 .route("/persons/:id/face", get(handlers_face::get_person_face))
+                .route("/persons/:id/collage", get(handlers_face::person_collage))
+                .route("/persons/:id/cover", put(handlers_face::set_person_cover))
                 .route("/persons/:id", post(handlers_face::update_person))
                 .route("/persons/:id", delete(handlers_face::delete_person))
                 .route("/persons/merge", post(handlers_face::merge_persons))
+                .route("/persons/export-metadata", post(handlers_face::export_face_metadata))
+                .route("/pets", get(handlers_face::list_pets))
+                .route("/search/reference/:id/faces", get(reference::reference_faces))
                 .route("/assets/:id/faces", get(handlers_face::get_asset_faces))
         };
         #[cfg(not(feature = "facial-recognition"))]
         let r = r;
-        r
+        #[cfg(feature = "semantic-search")]
+        let r = r.route("/search/semantic", get(semantic::semantic_search));
+        #[cfg(feature = "auto-tagging")]
+        let r = r.route("/settings/auto-tags", put(settings::update_auto_tag_settings));
+        r.layer(axum::middleware::from_fn_with_state(state.clone(), auth::require_auth))
     };
 
     Router::new()
@@ -93,3 +205,11 @@ pub fn router(state: Arc<AppState>) -> Router {
         .layer(cors)
         .with_state(state)
 }
+
+/// Upload size limit for /import (FLASH_IMPORT_MAX_BYTES, default 2 GiB)
+fn import_max_bytes() -> usize {
+    std::env::var("FLASH_IMPORT_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2 * 1024 * 1024 * 1024)
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+use axum::{extract::{Path, State}, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::models::rule::{ProcessingRule, RuleActions, RuleConditions};
+
+/// Longest rule name accepted
+const MAX_NAME_LEN: usize = 100;
+
+#[derive(Deserialize)]
+pub struct CreateRuleRequest {
+    pub name: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    pub conditions: RuleConditions,
+    pub actions: RuleActions,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Fields left out keep their current value
+#[derive(Deserialize)]
+pub struct UpdateRuleRequest {
+    pub name: Option<String>,
+    pub enabled: Option<bool>,
+    pub conditions: Option<RuleConditions>,
+    pub actions: Option<RuleActions>,
+}
+
+fn validate(name: &str, conditions: &RuleConditions, actions: &RuleActions) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!("name must be 1-{} characters", MAX_NAME_LEN));
+    }
+    // A rule without conditions would apply to the whole library
+    if conditions.is_empty() {
+        return Err("conditions must set at least one field".to_string());
+    }
+    if conditions.path_contains.as_deref().is_some_and(|p| p.trim().is_empty()) {
+        return Err("conditions.path_contains must not be empty".to_string());
+    }
+    if actions.is_empty() {
+        return Err("actions must add a tag or skip faces".to_string());
+    }
+    Ok(())
+}
+
+fn error(status: StatusCode, message: String) -> axum::response::Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+fn database_error(action: &str, e: anyhow::Error) -> axum::response::Response {
+    tracing::error!("Error {} processing rule: {}", action, e);
+    error(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))
+}
+
+fn task_error(action: &str, e: tokio::task::JoinError) -> axum::response::Response {
+    tracing::error!("Task error {} processing rule: {}", action, e);
+    error(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string())
+}
+
+pub async fn list_rules(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<ProcessingRule>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::list_rules(&conn)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(rules)) => (StatusCode::OK, Json(rules)).into_response(),
+        Ok(Err(e)) => database_error("listing", e),
+        Err(e) => task_error("listing", e),
+    }
+}
+
+pub async fn create_rule(State(state): State<Arc<AppState>>, Json(req): Json<CreateRuleRequest>) -> impl IntoResponse {
+    let name = req.name.trim().to_string();
+    if let Err(message) = validate(&name, &req.conditions, &req.actions) {
+        return error(StatusCode::BAD_REQUEST, message);
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<ProcessingRule>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let id = db::writer::insert_rule(&conn, &name, req.enabled, &req.conditions, &req.actions)?;
+            db::query::get_rule(&conn, id)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(rule))) => (StatusCode::CREATED, Json(rule)).into_response(),
+        Ok(Ok(None)) => error(StatusCode::INTERNAL_SERVER_ERROR, "Rule vanished after creation".to_string()),
+        Ok(Err(e)) => database_error("creating", e),
+        Err(e) => task_error("creating", e),
+    }
+}
+
+pub async fn update_rule(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(req): Json<UpdateRuleRequest>,
+) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<std::result::Result<ProcessingRule, String>>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let Some(current) = db::query::get_rule(&conn, id)? else {
+                return Ok(None);
+            };
+            let name = req.name.map(|n| n.trim().to_string()).unwrap_or(current.name);
+            let enabled = req.enabled.unwrap_or(current.enabled);
+            let conditions = req.conditions.unwrap_or(current.conditions);
+            let actions = req.actions.unwrap_or(current.actions);
+            if let Err(message) = validate(&name, &conditions, &actions) {
+                return Ok(Some(Err(message)));
+            }
+            db::writer::update_rule(&conn, id, &name, enabled, &conditions, &actions)?;
+            Ok(db::query::get_rule(&conn, id)?.map(Ok))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some(Ok(rule)))) => (StatusCode::OK, Json(rule)).into_response(),
+        Ok(Ok(Some(Err(message)))) => error(StatusCode::BAD_REQUEST, message),
+        Ok(Ok(None)) => error(StatusCode::NOT_FOUND, "Rule not found".to_string()),
+        Ok(Err(e)) => database_error("updating", e),
+        Err(e) => task_error("updating", e),
+    }
+}
+
+pub async fn delete_rule(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::delete_rule(&conn, id)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({ "success": true }))).into_response(),
+        Ok(Ok(false)) => error(StatusCode::NOT_FOUND, "Rule not found".to_string()),
+        Ok(Err(e)) => database_error("deleting", e),
+        Err(e) => task_error("deleting", e),
+    }
+}
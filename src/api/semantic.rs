@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use axum::{extract::{Query, State}, http::StatusCode, response::{IntoResponse, Response}, Extension, Json};
+use serde::Deserialize;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::api::auth::Principal;
+
+#[derive(Deserialize)]
+pub struct SemanticQuery {
+    /// What to look for, in plain words ("dog on a beach")
+    pub q: String,
+    /// Maximum assets returned (default: 50, max: 500)
+    pub limit: Option<usize>,
+}
+
+/// Assets whose CLIP image vectors are nearest the encoded query text
+pub async fn semantic_search(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(q): Query<SemanticQuery>,
+) -> Response {
+    let text = q.q.trim().to_string();
+    if text.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Query is empty"
+        }))).into_response();
+    }
+    let Some(processor) = state.semantic.processor() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
+            "error": "Semantic search models are not loaded"
+        }))).into_response();
+    };
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+
+    let result = tokio::task::spawn_blocking({
+        let state = state.clone();
+        move || -> Result<serde_json::Value> {
+            let query = processor.embed_text(&text)?;
+            let ranked = state.semantic.index.read().search(&query);
+            let indexed = ranked.len();
+            let conn = state.pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let mut items = Vec::new();
+            let mut scores = Vec::new();
+            // Walk the ranking until enough visible assets are found
+            for chunk in ranked.chunks(500) {
+                let ids = chunk.iter().map(|(id, _)| *id).collect();
+                let visible: std::collections::HashSet<i64> = db::query::filter_visible_assets(&conn, ids, principal.scope())?.into_iter().collect();
+                for (id, score) in chunk.iter().filter(|(id, _)| visible.contains(id)) {
+                    if let Some(asset) = db::query::get_asset_by_id(&conn, *id)? {
+                        items.push(asset);
+                        scores.push(*score);
+                    }
+                    if items.len() >= limit {
+                        break;
+                    }
+                }
+                if items.len() >= limit {
+                    break;
+                }
+            }
+            Ok(serde_json::json!({
+                "total": items.len(),
+                "items": items,
+                "scores": scores,
+                "indexed": indexed
+            }))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(body)) => Json(body).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error in semantic search: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error in semantic search: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
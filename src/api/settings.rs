@@ -0,0 +1,431 @@
+use std::sync::Arc;
+use axum::{extract::State, http::StatusCode, response::{IntoResponse, Response}, Json};
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use crate::{AppState, db};
+use crate::pipeline::extract::{Extractor, Extractors};
+use crate::pipeline::ingest::IngestFilter;
+use crate::pipeline::messaging::ReceivedMedia;
+use crate::pipeline::throttle::LowImpact;
+use crate::db::pool::{PoolSettings, MAX_POOL_SIZE};
+use crate::db::tuning::SqliteOverrides;
+use crate::utils::digest::{self, DigestSettings};
+use crate::utils::env_settings;
+#[cfg(feature = "auto-tagging")]
+use crate::pipeline::autotag::AutoTagSettings;
+
+/// Settings key holding the ingest filters as JSON
+pub const INGEST_KEY: &str = "ingest";
+/// Settings key holding the low-impact mode as JSON
+pub const LOW_IMPACT_KEY: &str = "low_impact";
+/// Settings key holding the SQLite cache/mmap overrides as JSON
+pub const SQLITE_KEY: &str = "sqlite";
+/// Settings key holding the read pool size as JSON
+pub const DB_POOL_KEY: &str = "db_pool";
+/// Settings key holding the auto-tagging labels and thresholds as JSON
+pub const AUTO_TAGS_KEY: &str = "auto_tags";
+/// Settings key holding the memories digest and its mail server as JSON
+pub const DIGEST_KEY: &str = "digest";
+/// Settings key holding the filename metadata extractors as JSON
+pub const EXTRACTORS_KEY: &str = "filename_extractors";
+/// Settings key holding where messaging-app media is shown as JSON
+pub const RECEIVED_MEDIA_KEY: &str = "received_media";
+
+/// Saved value under `key`, or `default` (from the environment) if none was saved or it no longer parses
+fn load_json<T: DeserializeOwned + Clone>(conn: &rusqlite::Connection, key: &str, default: &T) -> Result<T> {
+    Ok(match db::writer::get_setting(conn, key)? {
+        Some(json) => serde_json::from_str::<T>(&json).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring saved {} settings: {}", key, e);
+            default.clone()
+        }),
+        None => default.clone(),
+    })
+}
+
+/// Saved ingest filters, or `default` (from the environment) if none were saved
+pub fn load_ingest_filter(conn: &rusqlite::Connection, default: &IngestFilter) -> Result<IngestFilter> {
+    Ok(load_json(conn, INGEST_KEY, default)?.normalized())
+}
+
+/// Saved low-impact mode, or `default` (from the environment) if none was saved
+pub fn load_low_impact(conn: &rusqlite::Connection, default: &LowImpact) -> Result<LowImpact> {
+    load_json(conn, LOW_IMPACT_KEY, default)
+}
+
+/// Saved SQLite overrides, read before the connection pool is created
+pub fn load_sqlite_overrides(conn: &rusqlite::Connection) -> Result<SqliteOverrides> {
+    load_json(conn, SQLITE_KEY, &SqliteOverrides::default())
+}
+
+/// Saved read pool size, or `default` (from the SEEN_PROFILE profile) if none was saved
+pub fn load_pool_settings(conn: &rusqlite::Connection, default: &PoolSettings) -> Result<PoolSettings> {
+    load_json(conn, DB_POOL_KEY, default)
+}
+
+/// Saved auto-tagging labels, or the built-in set
+#[cfg(feature = "auto-tagging")]
+pub fn load_auto_tags(conn: &rusqlite::Connection) -> Result<AutoTagSettings> {
+    load_json(conn, AUTO_TAGS_KEY, &AutoTagSettings::default())
+}
+
+/// Saved memories digest settings, or the digest turned off
+pub fn load_digest(conn: &rusqlite::Connection) -> Result<DigestSettings> {
+    load_json(conn, DIGEST_KEY, &DigestSettings::default())
+}
+
+/// Saved filename extractors, or none
+pub fn load_extractors(conn: &rusqlite::Connection) -> Result<Extractors> {
+    Ok(Extractors::load(load_json(conn, EXTRACTORS_KEY, &Vec::new())?))
+}
+
+/// Saved received-media settings, or such media hidden from the timeline and memories
+pub fn load_received_media(conn: &rusqlite::Connection) -> Result<ReceivedMedia> {
+    load_json(conn, RECEIVED_MEDIA_KEY, &ReceivedMedia::default())
+}
+
+/// Digest settings as the API shows them: the SMTP password is never sent back
+fn redacted_digest(settings: &DigestSettings) -> serde_json::Value {
+    let mut value = serde_json::json!(settings);
+    value["smtp"]["password"] = serde_json::Value::Null;
+    value["smtp"]["password_set"] = serde_json::json!(settings.smtp.password.as_deref().is_some_and(|p| !p.is_empty()));
+    value
+}
+
+/// Persist `value` under `key`; the error is the response to send
+async fn save_json<T: Serialize>(state: &AppState, key: &'static str, value: &T) -> Result<(), Response> {
+    let json = serde_json::to_string(value).map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+            "error": format!("Cannot encode settings: {}", e)
+        }))).into_response()
+    })?;
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<()> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::set_setting(&conn, key, &json)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => {
+            tracing::error!("Error saving {} settings: {}", key, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response())
+        }
+        Err(e) => {
+            tracing::error!("Task error saving {} settings: {}", key, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response())
+        }
+    }
+}
+
+/// Runtime settings, grouped by section
+pub async fn get_settings(State(state): State<Arc<AppState>>) -> Response {
+    let ingest = state.ingest_filter.read().clone();
+    let settings = serde_json::json!({
+        "ingest": ingest,
+        "low_impact": state.gauges.throttle.settings(),
+        "sqlite": *state.sqlite_overrides.read(),
+        "db_pool": PoolSettings { size: state.pool.size() },
+        "digest": redacted_digest(&state.digest.read()),
+        "filename_extractors": state.filename_extractors.read().extractors(),
+        "received_media": *state.received_media.read(),
+        "env": env_section()
+    });
+    #[cfg(feature = "auto-tagging")]
+    let settings = {
+        let mut settings = settings;
+        settings["auto_tags"] = serde_json::json!(state.semantic.auto_tags.settings());
+        settings
+    };
+    Json(settings).into_response()
+}
+
+/// The environment-style settings kept in the database, and what startup did with the real environment
+fn env_section() -> serde_json::Value {
+    let values: serde_json::Map<String, serde_json::Value> = env_settings::PERSISTED_VARS
+        .iter()
+        .map(|var| (var.to_string(), serde_json::json!(std::env::var(var).ok())))
+        .collect();
+    let startup = env_settings::last_import().cloned().unwrap_or_default();
+    serde_json::json!({
+        "values": values,
+        "imported": startup.imported,
+        "shadowed": startup.shadowed
+    })
+}
+
+/// Change settings that used to come from environment variables; null goes back to the built-in
+/// default. Thread counts apply on the next restart, the rest immediately.
+pub async fn update_env_settings(State(state): State<Arc<AppState>>, Json(req): Json<std::collections::BTreeMap<String, Option<String>>>) -> Response {
+    let mut changes = Vec::with_capacity(req.len());
+    for (var, value) in req {
+        let value = value.map(|v| v.trim().to_string());
+        let checked = match value.as_deref() {
+            Some(value) => env_settings::validate(&var, value),
+            None if env_settings::PERSISTED_VARS.contains(&var.as_str()) => Ok(()),
+            None => Err(format!("{} is not kept in the database", var)),
+        };
+        if let Err(e) = checked {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": e
+            }))).into_response();
+        }
+        changes.push((var, value));
+    }
+    let restart_required = changes.iter().any(|(var, _)| env_settings::RESTART_VARS.contains(&var.as_str()));
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let changes = changes.clone();
+        move || -> Result<()> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            env_settings::persist(&conn, &changes).map(|_| ())
+        }
+    }).await;
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            tracing::error!("Error saving env settings: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response();
+        }
+        Err(e) => {
+            tracing::error!("Task error saving env settings: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response();
+        }
+    }
+    env_settings::apply(changes.iter().map(|(var, value)| (var.as_str(), value.as_deref())));
+    tracing::info!("env settings updated: {:?}", changes);
+    Json(serde_json::json!({
+        "success": true,
+        "env": env_section(),
+        "restart_required": restart_required
+    })).into_response()
+}
+
+/// Replace the ingest filters. They apply to files discovered from now on; indexed assets are kept.
+pub async fn update_ingest_settings(State(state): State<Arc<AppState>>, Json(req): Json<IngestFilter>) -> Response {
+    let filter = req.normalized();
+    if let Some(bad) = filter.extensions.iter().find(|e| !e.chars().all(|c| c.is_ascii_alphanumeric())) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("Invalid extension '{}'", bad)
+        }))).into_response();
+    }
+
+    if let Err(response) = save_json(&state, INGEST_KEY, &filter).await {
+        return response;
+    }
+    *state.ingest_filter.write() = filter.clone();
+    tracing::info!("ingest filters updated: {:?}", filter);
+    Json(serde_json::json!({
+        "success": true,
+        "ingest": filter
+    })).into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct ExtractorsReq {
+    pub extractors: Vec<Extractor>,
+}
+
+/// Replace the filename extractors. They apply to files indexed from now on.
+pub async fn update_extractor_settings(State(state): State<Arc<AppState>>, Json(req): Json<ExtractorsReq>) -> Response {
+    let extractors = match Extractors::new(req.extractors) {
+        Ok(extractors) => extractors,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": e
+            }))).into_response();
+        }
+    };
+
+    let saved = extractors.extractors();
+    if let Err(response) = save_json(&state, EXTRACTORS_KEY, &saved).await {
+        return response;
+    }
+    *state.filename_extractors.write() = extractors;
+    tracing::info!("filename extractors updated: {} patterns", saved.len());
+    Json(serde_json::json!({
+        "success": true,
+        "filename_extractors": saved
+    })).into_response()
+}
+
+/// Choose whether media received through messaging apps shows in the timeline and memories
+pub async fn update_received_media_settings(State(state): State<Arc<AppState>>, Json(req): Json<ReceivedMedia>) -> Response {
+    if let Err(response) = save_json(&state, RECEIVED_MEDIA_KEY, &req).await {
+        return response;
+    }
+    *state.received_media.write() = req;
+    tracing::info!("received media settings updated: {:?}", req);
+    Json(serde_json::json!({
+        "success": true,
+        "received_media": req
+    })).into_response()
+}
+
+/// Turn low-impact mode on or off, or change its limits. Takes effect on the next file each worker picks up.
+pub async fn update_low_impact_settings(State(state): State<Arc<AppState>>, Json(req): Json<LowImpact>) -> Response {
+    if req.max_workers == 0 {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "max_workers must be at least 1"
+        }))).into_response();
+    }
+
+    if let Err(response) = save_json(&state, LOW_IMPACT_KEY, &req).await {
+        return response;
+    }
+    state.gauges.throttle.set(req.clone());
+    tracing::info!("low-impact mode updated: {:?}", req);
+    Json(serde_json::json!({
+        "success": true,
+        "low_impact": req
+    })).into_response()
+}
+
+/// Override the auto-tuned SQLite page cache and mmap sizes; null goes back to auto. Applies on the next restart.
+pub async fn update_sqlite_settings(State(state): State<Arc<AppState>>, Json(req): Json<SqliteOverrides>) -> Response {
+    if req.cache_mb == Some(0) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "cache_mb must be at least 1"
+        }))).into_response();
+    }
+
+    if let Err(response) = save_json(&state, SQLITE_KEY, &req).await {
+        return response;
+    }
+    *state.sqlite_overrides.write() = req;
+    tracing::info!("SQLite overrides updated: {:?}", req);
+    Json(serde_json::json!({
+        "success": true,
+        "sqlite": req,
+        "restart_required": true
+    })).into_response()
+}
+
+/// Resize the read connection pool. Applies immediately; checked-out connections finish their work first.
+pub async fn update_pool_settings(State(state): State<Arc<AppState>>, Json(req): Json<PoolSettings>) -> Response {
+    if req.size == 0 || req.size > MAX_POOL_SIZE {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("size must be between 1 and {}", MAX_POOL_SIZE)
+        }))).into_response();
+    }
+
+    let resized = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || pool.resize(req.size)
+    }).await;
+    match resized {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            tracing::error!("Error resizing connection pool: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response();
+        }
+        Err(e) => {
+            tracing::error!("Task error resizing connection pool: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response();
+        }
+    }
+    if let Err(response) = save_json(&state, DB_POOL_KEY, &req).await {
+        return response;
+    }
+    tracing::info!("connection pool resized to {}", req.size);
+    Json(serde_json::json!({
+        "success": true,
+        "db_pool": req
+    })).into_response()
+}
+
+/// Replace the memories digest settings. An omitted SMTP password keeps the saved one; "" clears it.
+pub async fn update_digest_settings(State(state): State<Arc<AppState>>, Json(mut req): Json<DigestSettings>) -> Response {
+    req.recipients = req.recipients.iter().map(|r| r.trim().to_string()).filter(|r| !r.is_empty()).collect();
+    req.smtp.from = req.smtp.from.trim().to_string();
+    req.base_url = req.base_url.trim().trim_end_matches('/').to_string();
+    if let Err(e) = req.validate() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": e
+        }))).into_response();
+    }
+    match req.smtp.password.as_deref() {
+        None => req.smtp.password = state.digest.read().smtp.password.clone(),
+        Some("") => req.smtp.password = None,
+        Some(_) => {}
+    }
+
+    if let Err(response) = save_json(&state, DIGEST_KEY, &req).await {
+        return response;
+    }
+    *state.digest.write() = req.clone();
+    tracing::info!("memories digest updated: enabled={}, {} recipients, schedule '{}'", req.enabled, req.recipients.len(), req.schedule);
+    Json(serde_json::json!({
+        "success": true,
+        "digest": redacted_digest(&req)
+    })).into_response()
+}
+
+/// Send the memories digest now with the saved settings, whether or not it is scheduled
+pub async fn send_digest_now(State(state): State<Arc<AppState>>) -> Response {
+    let settings = state.digest.read().clone();
+    if settings.smtp.host.trim().is_empty() || settings.smtp.from.is_empty() || settings.recipients.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Set up the mail server, sender and recipients through PUT /settings/digest first"
+        }))).into_response();
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let state = state.clone();
+        move || digest::send_digest(&state, &settings)
+    }).await;
+    match result {
+        Ok(Ok(memories)) => Json(serde_json::json!({
+            "success": true,
+            "sent": memories > 0,
+            "memories": memories
+        })).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error sending memories digest: {:#}", e);
+            (StatusCode::BAD_GATEWAY, Json(serde_json::json!({
+                "error": format!("{:#}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error sending memories digest: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+/// Replace the auto-tagging labels and thresholds; library-wide predictions are refreshed in the background
+#[cfg(feature = "auto-tagging")]
+pub async fn update_auto_tag_settings(State(state): State<Arc<AppState>>, Json(req): Json<AutoTagSettings>) -> Response {
+    if let Err(e) = req.validate() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": e
+        }))).into_response();
+    }
+
+    if let Err(response) = save_json(&state, AUTO_TAGS_KEY, &req).await {
+        return response;
+    }
+    state.semantic.auto_tags.set_settings(req.clone());
+    crate::pipeline::autotag::start_retag(state.clone());
+    tracing::info!("auto-tagging updated: {} labels, enabled={}", req.labels.len(), req.enabled);
+    Json(serde_json::json!({
+        "success": true,
+        "auto_tags": req
+    })).into_response()
+}
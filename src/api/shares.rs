@@ -0,0 +1,506 @@
+use std::sync::Arc;
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::{IntoResponse, Response}, Extension, Json};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::api::auth::{self, Principal};
+use crate::api::handlers;
+use crate::db::query::AccessScope;
+
+#[derive(Deserialize)]
+pub struct CreateShareRequest {
+    pub album_id: Option<i64>,
+    pub asset_id: Option<i64>,
+    /// Visitors must enter this password before seeing anything
+    pub password: Option<String>,
+    /// Lifetime of the link in seconds; omit for a link that never expires
+    pub expires_in_secs: Option<i64>,
+}
+
+pub async fn create_share(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<CreateShareRequest>,
+) -> impl IntoResponse {
+    let (album_id, asset_id) = match (req.album_id, req.asset_id) {
+        (Some(album_id), None) => (Some(album_id), None),
+        (None, Some(asset_id)) => (None, Some(asset_id)),
+        _ => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "Specify exactly one of album_id or asset_id"
+            }))).into_response();
+        }
+    };
+    if req.expires_in_secs.is_some_and(|secs| secs <= 0) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "expires_in_secs must be positive"
+        }))).into_response();
+    }
+    let password = req.password.filter(|p| !p.is_empty());
+    let expires_in_secs = req.expires_in_secs;
+    let scope = principal.scope();
+    let missing = if album_id.is_some() { "Album not found" } else { "Asset not found" };
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Option<(i64, String, Option<i64>)>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let visible = match (album_id, asset_id) {
+                (Some(id), _) => db::query::can_view_album(&conn, id, scope)?,
+                (_, Some(id)) => db::query::can_view_asset(&conn, id, scope)?,
+                _ => false,
+            };
+            if !visible {
+                return Ok(None);
+            }
+            let password_hash = password.as_deref().map(auth::hash_password).transpose()?;
+            let token = auth::generate_token()?;
+            let expires_at = expires_in_secs.map(|secs| chrono::Utc::now().timestamp().saturating_add(secs));
+            let id = db::writer::insert_share(
+                &conn,
+                &auth::hash_token(&token),
+                album_id,
+                asset_id,
+                password_hash.as_deref(),
+                expires_at,
+                principal.user_id(),
+            )?;
+            Ok(Some((id, token, expires_at)))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some((id, token, expires_at)))) => (StatusCode::CREATED, Json(serde_json::json!({
+            "id": id,
+            "token": token,
+            "url": format!("/share/{}", token),
+            "expires_at": expires_at
+        }))).into_response(),
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": missing
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error creating share link: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error creating share link: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn list_shares(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<Vec<crate::models::share::ShareLink>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::list_shares(&conn, auth::owner_filter(&principal))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(shares)) => (StatusCode::OK, Json(shares)).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error listing share links: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error listing share links: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn delete_share(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        move || -> Result<bool> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::writer::delete_share(&conn, id, auth::owner_filter(&principal))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(true)) => (StatusCode::OK, Json(serde_json::json!({ "success": true }))).into_response(),
+        Ok(Ok(false)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Share not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error deleting share link: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error deleting share link: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+/// How long an access key issued for a password stays valid
+const ACCESS_KEY_TTL_SECS: i64 = 6 * 60 * 60;
+
+/// Query parameters of share requests; the password itself is never accepted here,
+/// since query strings end up in access logs, browser history and Referer headers
+#[derive(Deserialize, Default)]
+pub struct ShareKeyQuery {
+    /// Access key returned by `POST /share/:token`
+    pub key: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UnlockShareRequest {
+    pub password: String,
+}
+
+/// Credentials for password-protected links
+#[derive(Default)]
+struct ShareAccess {
+    /// The link's password; accepted once in exchange for an access key
+    password: Option<String>,
+    /// Short-lived access key, so later requests skip the password check
+    key: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ShareDenied {
+    NotFound,
+    Expired,
+    PasswordRequired,
+    WrongPassword,
+}
+
+/// A share link a visitor has been let into
+#[derive(Debug)]
+struct OpenShare {
+    is_album: bool,
+    title: Option<String>,
+    description: Option<String>,
+    expires_at: Option<i64>,
+    access_key: Option<String>,
+    asset_ids: Vec<i64>,
+}
+
+/// Cheap to check on every request, and only derivable by someone who knows the password.
+/// The key carries its own expiry, so a leaked key stops working after `ACCESS_KEY_TTL_SECS`.
+fn access_key(token_hash: &[u8], password_hash: &str, expires_at: i64) -> String {
+    let digest = Sha256::new()
+        .chain_update(token_hash)
+        .chain_update(password_hash.as_bytes())
+        .chain_update(expires_at.to_be_bytes())
+        .finalize();
+    format!("{}.{}", expires_at, hex::encode(digest))
+}
+
+fn verify_access_key(key: &str, token_hash: &[u8], password_hash: &str, now: i64) -> bool {
+    let Some(expires_at) = key.split_once('.').and_then(|(at, _)| at.parse::<i64>().ok()) else {
+        return false;
+    };
+    expires_at > now && auth::constant_time_eq(key.as_bytes(), access_key(token_hash, password_hash, expires_at).as_bytes())
+}
+
+/// Visitors see what the link's creator could see when they open it; kiosks work the same way
+pub(crate) fn creator_scope(conn: &rusqlite::Connection, created_by: Option<i64>) -> Result<AccessScope> {
+    let Some(user_id) = created_by else {
+        return Ok(AccessScope::All);
+    };
+    Ok(match db::query::get_user(conn, user_id)? {
+        Some((_, _, true, _)) => AccessScope::All,
+        Some(_) => AccessScope::User(user_id),
+        None => AccessScope::Public,
+    })
+}
+
+fn open_share(
+    conn: &rusqlite::Connection,
+    token: &str,
+    access: &ShareAccess,
+    now: i64,
+) -> Result<std::result::Result<OpenShare, ShareDenied>> {
+    let token_hash = auth::hash_token(token);
+    let Some((link, password_hash)) = db::query::find_share(conn, &token_hash)? else {
+        return Ok(Err(ShareDenied::NotFound));
+    };
+    if link.expires_at.is_some_and(|at| at <= now) {
+        return Ok(Err(ShareDenied::Expired));
+    }
+    let access_key = match password_hash {
+        None => None,
+        Some(hash) => match (&access.key, &access.password) {
+            (Some(key), _) if verify_access_key(key, &token_hash, &hash, now) => Some(key.clone()),
+            (None, Some(password)) if auth::verify_password(password, &hash) => {
+                Some(access_key(&token_hash, &hash, now.saturating_add(ACCESS_KEY_TTL_SECS)))
+            }
+            (None, None) => return Ok(Err(ShareDenied::PasswordRequired)),
+            _ => return Ok(Err(ShareDenied::WrongPassword)),
+        },
+    };
+
+    let scope = creator_scope(conn, link.created_by)?;
+    let (title, description, asset_ids) = match link.album_id {
+        Some(album_id) => match db::query::get_album(conn, album_id)? {
+            Some((_, name, description, _, _, asset_ids, _)) => {
+                (Some(name), description, db::query::filter_visible_assets(conn, asset_ids, scope)?)
+            }
+            None => return Ok(Err(ShareDenied::NotFound)),
+        },
+        None => (None, None, db::query::filter_visible_assets(conn, link.asset_id.into_iter().collect(), scope)?),
+    };
+    Ok(Ok(OpenShare {
+        is_album: link.album_id.is_some(),
+        title,
+        description,
+        expires_at: link.expires_at,
+        access_key,
+        asset_ids,
+    }))
+}
+
+fn denied_response(denied: ShareDenied) -> Response {
+    match denied {
+        ShareDenied::NotFound => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Share not found"
+        }))).into_response(),
+        ShareDenied::Expired => (StatusCode::GONE, Json(serde_json::json!({
+            "error": "Share link has expired"
+        }))).into_response(),
+        // 403 rather than 401: the visitor needs the link's password, not an account
+        ShareDenied::PasswordRequired => (StatusCode::FORBIDDEN, Json(serde_json::json!({
+            "error": "Password required",
+            "password_required": true
+        }))).into_response(),
+        ShareDenied::WrongPassword => (StatusCode::FORBIDDEN, Json(serde_json::json!({
+            "error": "Invalid password",
+            "password_required": true
+        }))).into_response(),
+    }
+}
+
+async fn resolve_share(state: &Arc<AppState>, token: String, access: ShareAccess) -> std::result::Result<OpenShare, Response> {
+    let pool = state.pool.clone();
+    let result = tokio::task::spawn_blocking(move || -> Result<_> {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+        open_share(&conn, &token, &access, chrono::Utc::now().timestamp())
+    }).await;
+
+    match result {
+        Ok(Ok(Ok(share))) => Ok(share),
+        Ok(Ok(Err(ShareDenied::WrongPassword))) => {
+            // Slow down password guessing
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            Err(denied_response(ShareDenied::WrongPassword))
+        }
+        Ok(Ok(Err(denied))) => Err(denied_response(denied)),
+        Ok(Err(e)) => {
+            tracing::error!("Error opening share link: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response())
+        }
+        Err(e) => {
+            tracing::error!("Task error opening share link: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response())
+        }
+    }
+}
+
+/// Read-only view of a shared album or asset; no authentication needed
+pub async fn view_share(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Query(query): Query<ShareKeyQuery>,
+) -> Response {
+    share_view_response(&state, token, ShareAccess { password: None, key: query.key }).await
+}
+
+/// Same as `view_share` for password-protected links, with the password in the body
+/// so it stays out of URLs; the response carries a short-lived `access_key`
+pub async fn unlock_share(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Json(req): Json<UnlockShareRequest>,
+) -> Response {
+    share_view_response(&state, token, ShareAccess { password: Some(req.password), key: None }).await
+}
+
+async fn share_view_response(state: &Arc<AppState>, token: String, access: ShareAccess) -> Response {
+    let share = match resolve_share(state, token, access).await {
+        Ok(share) => share,
+        Err(response) => return response,
+    };
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let asset_ids = share.asset_ids.clone();
+        move || -> Result<Vec<crate::models::share::SharedAsset>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            db::query::get_shared_assets(&conn, &asset_ids)
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(assets)) => (StatusCode::OK, Json(serde_json::json!({
+            "kind": if share.is_album { "album" } else { "asset" },
+            "title": share.title,
+            "description": share.description,
+            "expires_at": share.expires_at,
+            "access_key": share.access_key,
+            "assets": assets
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error loading shared assets: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error loading shared assets: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+/// Let the request through only if the link is open and covers the asset
+async fn check_shared_asset(state: &Arc<AppState>, token: String, access: ShareAccess, asset_id: i64) -> std::result::Result<(), Response> {
+    let share = resolve_share(state, token, access).await?;
+    if share.asset_ids.contains(&asset_id) {
+        Ok(())
+    } else {
+        Err((StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Asset not found"
+        }))).into_response())
+    }
+}
+
+pub async fn share_thumb(
+    State(state): State<Arc<AppState>>,
+    Path((token, asset_id)): Path<(String, i64)>,
+    Query(query): Query<ShareKeyQuery>,
+) -> Response {
+    let access = ShareAccess { password: None, key: query.key };
+    if let Err(response) = check_shared_asset(&state, token, access, asset_id).await {
+        return response;
+    }
+    handlers::thumb_256(State(state), Path(asset_id), Query(handlers::ThumbQuery { animated: None })).await.into_response()
+}
+
+pub async fn share_preview(
+    State(state): State<Arc<AppState>>,
+    Path((token, asset_id)): Path<(String, i64)>,
+    Query(query): Query<ShareKeyQuery>,
+) -> Response {
+    let access = ShareAccess { password: None, key: query.key };
+    if let Err(response) = check_shared_asset(&state, token, access, asset_id).await {
+        return response;
+    }
+    handlers::preview_1600(State(state), Path(asset_id)).await.into_response()
+}
+
+pub async fn share_download(
+    State(state): State<Arc<AppState>>,
+    Path((token, asset_id)): Path<(String, i64)>,
+    Query(query): Query<ShareKeyQuery>,
+) -> Response {
+    let access = ShareAccess { password: None, key: query.key };
+    if let Err(response) = check_shared_asset(&state, token, access, asset_id).await {
+        return response;
+    }
+    handlers::download_asset(State(state), Path(asset_id)).await.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn insert_asset(conn: &Connection, path: &str) -> i64 {
+        let (dirname, filename) = path.rsplit_once('/').unwrap();
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags)
+             VALUES (?1, ?2, ?3, 'jpg', 1, 0, 0, 'image/jpeg', 0)",
+            rusqlite::params![path, dirname, filename],
+        ).unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn test_open_share() {
+        let conn = Connection::open_in_memory().unwrap();
+        db::schema::apply_schema(&conn).unwrap();
+        let a = insert_asset(&conn, "/p/a.jpg");
+        let b = insert_asset(&conn, "/p/b.jpg");
+        let album = db::writer::create_album(&conn, "Trip", Some("Summer"), None).unwrap();
+        db::writer::add_assets_to_album(&conn, album, &[b, a]).unwrap();
+        let no_access = ShareAccess::default();
+
+        db::writer::insert_share(&conn, &auth::hash_token("album"), Some(album), None, None, None, None).unwrap();
+        let share = open_share(&conn, "album", &no_access, 0).unwrap().unwrap();
+        assert!(share.is_album);
+        assert_eq!(share.title.as_deref(), Some("Trip"));
+        assert_eq!(share.asset_ids.len(), 2);
+        assert!(share.access_key.is_none());
+        assert_eq!(open_share(&conn, "nope", &no_access, 0).unwrap().unwrap_err(), ShareDenied::NotFound);
+
+        // Expiry and password
+        let hash = auth::hash_password("letmein").unwrap();
+        db::writer::insert_share(&conn, &auth::hash_token("asset"), None, Some(a), Some(&hash), Some(100), None).unwrap();
+        assert_eq!(open_share(&conn, "asset", &no_access, 100).unwrap().unwrap_err(), ShareDenied::Expired);
+        assert_eq!(open_share(&conn, "asset", &no_access, 50).unwrap().unwrap_err(), ShareDenied::PasswordRequired);
+        let wrong = ShareAccess { password: Some("guess".into()), key: None };
+        assert_eq!(open_share(&conn, "asset", &wrong, 50).unwrap().unwrap_err(), ShareDenied::WrongPassword);
+        let right = ShareAccess { password: Some("letmein".into()), key: None };
+        let share = open_share(&conn, "asset", &right, 50).unwrap().unwrap();
+        assert_eq!(share.asset_ids, vec![a]);
+        let with_key = ShareAccess { password: None, key: share.access_key.clone() };
+        assert!(open_share(&conn, "asset", &with_key, 50).unwrap().is_ok());
+        let bad_key = ShareAccess { password: None, key: Some("00".into()) };
+        assert_eq!(open_share(&conn, "asset", &bad_key, 50).unwrap().unwrap_err(), ShareDenied::WrongPassword);
+
+        // Access keys are short-lived and can't be extended by editing the expiry
+        let hash = auth::hash_password("letmein").unwrap();
+        db::writer::insert_share(&conn, &auth::hash_token("long"), None, Some(a), Some(&hash), None, None).unwrap();
+        let right = ShareAccess { password: Some("letmein".into()), key: None };
+        let key = open_share(&conn, "long", &right, 0).unwrap().unwrap().access_key.unwrap();
+        let with_key = ShareAccess { password: None, key: Some(key.clone()) };
+        assert!(open_share(&conn, "long", &with_key, ACCESS_KEY_TTL_SECS - 1).unwrap().is_ok());
+        assert_eq!(open_share(&conn, "long", &with_key, ACCESS_KEY_TTL_SECS).unwrap().unwrap_err(), ShareDenied::WrongPassword);
+        let (_, digest) = key.split_once('.').unwrap();
+        let forged = ShareAccess { password: None, key: Some(format!("{}.{}", i64::MAX, digest)) };
+        assert_eq!(open_share(&conn, "long", &forged, ACCESS_KEY_TTL_SECS).unwrap().unwrap_err(), ShareDenied::WrongPassword);
+
+        // A user's link only shows what that user can see
+        let alice = db::writer::create_user(&conn, "alice", "x", false).unwrap().unwrap();
+        let bob = db::writer::create_user(&conn, "bob", "x", false).unwrap().unwrap();
+        conn.execute("UPDATE assets SET owner_id = ?1 WHERE id = ?2", rusqlite::params![bob, a]).unwrap();
+        db::writer::insert_share(&conn, &auth::hash_token("alice"), Some(album), None, None, None, Some(alice)).unwrap();
+        assert_eq!(open_share(&conn, "alice", &no_access, 0).unwrap().unwrap().asset_ids, vec![b]);
+
+        // Revoking and deleting the album both close the link
+        assert!(!db::writer::delete_share(&conn, 1, Some(alice)).unwrap());
+        assert!(db::writer::delete_share(&conn, 1, None).unwrap());
+        assert_eq!(open_share(&conn, "album", &no_access, 0).unwrap().unwrap_err(), ShareDenied::NotFound);
+        db::writer::delete_album(&conn, album).unwrap();
+        assert_eq!(open_share(&conn, "alice", &no_access, 0).unwrap().unwrap_err(), ShareDenied::NotFound);
+    }
+}
@@ -0,0 +1,203 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use axum::{extract::{Path, State}, http::{header, StatusCode}, response::{IntoResponse, Response}, Extension, Json};
+use serde::Deserialize;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::api::auth::Principal;
+use crate::api::handlers;
+use crate::utils::backup::ChannelWriter;
+use crate::utils::site_export::{self, DirSink, SiteItem, ZipSink};
+
+#[derive(Deserialize, Default)]
+pub struct ExportSiteRequest {
+    /// Server directory to write the gallery into (admins only); without it the gallery downloads as a ZIP
+    pub dir: Option<String>,
+    /// Copy the originals too, so videos play and full-size files can be downloaded
+    #[serde(default)]
+    pub include_originals: bool,
+    /// Page heading (default: the album name)
+    pub title: Option<String>,
+}
+
+struct SiteAlbum {
+    name: String,
+    description: Option<String>,
+    items: Vec<SiteItem>,
+}
+
+/// The album and its assets as the principal sees them; None if it doesn't exist for them
+async fn load_album(state: &AppState, id: i64, principal: Principal) -> Result<Option<SiteAlbum>> {
+    let pool = state.pool.clone();
+    tokio::task::spawn_blocking(move || -> Result<Option<SiteAlbum>> {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+        let Some(album) = handlers::visible_album(&conn, id, principal.scope())? else {
+            return Ok(None);
+        };
+        let mut items = Vec::with_capacity(album.5.len());
+        for asset_id in album.5 {
+            if let Some(asset) = db::query::get_asset_by_id(&conn, asset_id)? {
+                items.push(SiteItem {
+                    id: asset.id,
+                    filename: asset.filename,
+                    path: asset.path,
+                    mime: asset.mime,
+                    sha256: asset.sha256,
+                    width: asset.width,
+                    height: asset.height,
+                    taken_at: asset.taken_at,
+                });
+            }
+        }
+        Ok(Some(SiteAlbum { name: album.1, description: album.2, items }))
+    })
+    .await?
+}
+
+fn bad_request(message: &str) -> Response {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+        "error": message
+    }))).into_response()
+}
+
+/// A directory the gallery may be written to: absolute, and new or empty
+fn export_dir(dir: &str) -> std::result::Result<PathBuf, &'static str> {
+    let path = PathBuf::from(dir.trim());
+    if !path.is_absolute() {
+        return Err("dir must be an absolute path");
+    }
+    if path.exists() {
+        let empty = std::fs::read_dir(&path).map(|mut entries| entries.next().is_none()).unwrap_or(false);
+        if !path.is_dir() || !empty {
+            return Err("dir must not exist yet or be an empty directory");
+        }
+    }
+    Ok(path)
+}
+
+/// Name for the downloaded ZIP
+fn zip_name(album: &str) -> String {
+    let slug: String = album
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() { "album.zip".to_string() } else { format!("{}.zip", slug) }
+}
+
+/// Export an album as a static HTML gallery, into a server directory or as a ZIP download
+pub async fn export_album_site(
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<i64>,
+    req: Option<Json<ExportSiteRequest>>,
+) -> Response {
+    let req = req.map(|Json(r)| r).unwrap_or_default();
+    let dir = match req.dir.as_deref().filter(|d| !d.trim().is_empty()) {
+        Some(_) if !principal.is_admin() => {
+            return (StatusCode::FORBIDDEN, Json(serde_json::json!({
+                "error": "Only admins can export to a server directory"
+            }))).into_response();
+        }
+        Some(dir) => match export_dir(dir) {
+            Ok(path) => Some(path),
+            Err(message) => return bad_request(message),
+        },
+        None => None,
+    };
+
+    let album = match load_album(&state, id, principal).await {
+        Ok(Some(album)) => album,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+                "error": "Album not found"
+            }))).into_response();
+        }
+        Err(e) => {
+            tracing::error!("Error loading album {} for export: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response();
+        }
+    };
+    let title = req.title.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| album.name.clone());
+    let derived = state.paths.derived.clone();
+
+    if let Some(dir) = dir {
+        let result = tokio::task::spawn_blocking({
+            let dir = dir.clone();
+            move || {
+                let mut sink = DirSink::new(dir);
+                site_export::write_site(&mut sink, &title, album.description.as_deref(), &album.items, &derived, req.include_originals)
+            }
+        }).await;
+        return match result {
+            Ok(Ok(summary)) => {
+                tracing::info!("Exported album {} to {:?}: {} assets", id, dir, summary.items);
+                (StatusCode::OK, Json(serde_json::json!({
+                    "success": true,
+                    "dir": dir,
+                    "summary": summary
+                }))).into_response()
+            }
+            Ok(Err(e)) => {
+                tracing::error!("Error exporting album {} to {:?}: {}", id, dir, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                    "error": format!("Export failed: {}", e)
+                }))).into_response()
+            }
+            Err(e) => {
+                tracing::error!("Task error exporting album {}: {}", id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                    "error": "Internal server error"
+                }))).into_response()
+            }
+        };
+    }
+
+    let filename = zip_name(&album.name);
+    let (tx, rx) = tokio::sync::mpsc::channel::<io::Result<bytes::Bytes>>(16);
+    tokio::task::spawn_blocking(move || {
+        let writer = io::BufWriter::with_capacity(256 * 1024, ChannelWriter::new(tx.clone()));
+        let mut sink = ZipSink::new(writer, chrono::Local::now().naive_local());
+        let written = site_export::write_site(&mut sink, &title, album.description.as_deref(), &album.items, &derived, req.include_originals)
+            .and_then(|_| sink.finish())
+            .and_then(|mut w| Ok(io::Write::flush(&mut w)?));
+        if let Err(e) = written {
+            tracing::error!("Error writing site export for album {}: {}", id, e);
+            // Abort the response so the client doesn't keep a truncated archive
+            let _ = tx.blocking_send(Err(io::Error::other(e.to_string())));
+        }
+    });
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) });
+    axum::http::Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))
+        .body(axum::body::Body::from_stream(stream))
+        .unwrap()
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_target() {
+        assert_eq!(zip_name("Iceland 2019 / Day #1"), "iceland-2019-day-1.zip");
+        assert_eq!(zip_name("日本"), "album.zip");
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        assert!(export_dir("relative/out").is_err());
+        assert!(export_dir(&tmp.path().join("new").to_string_lossy()).is_ok());
+        assert!(export_dir(&tmp.path().to_string_lossy()).is_ok());
+        std::fs::write(tmp.path().join("index.html"), "x").unwrap();
+        assert!(export_dir(&tmp.path().to_string_lossy()).is_err());
+    }
+}
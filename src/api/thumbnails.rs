@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use axum::{extract::State, http::StatusCode, response::{IntoResponse, Response}, Extension, Json};
+use serde::Deserialize;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::api::auth::Principal;
+use crate::pipeline::thumb::{self, ThumbJob, WarmJob};
+
+/// The sizes GET /thumb and GET /preview serve
+const THUMB_SIZE: i32 = 256;
+const PREVIEW_SIZE: i32 = 1600;
+
+/// Which assets a warm-up or regeneration covers
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct WarmThumbnailsReq {
+    pub album_id: Option<i64>,
+    /// Taken at or after this Unix time
+    pub from: Option<i64>,
+    /// Taken at or before this Unix time
+    pub to: Option<i64>,
+    /// Only assets under this directory
+    pub path: Option<String>,
+    /// A full MIME type (`image/heic`) or just its top-level type (`video`)
+    pub mime: Option<String>,
+}
+
+/// (assets matching the filter, jobs for those to render)
+type WarmPlan = (u64, Vec<ThumbJob>);
+
+fn mime_matches(mime: &str, filter: &str) -> bool {
+    let filter = filter.trim_end_matches('/');
+    mime.eq_ignore_ascii_case(filter)
+        || mime.split_once('/').is_some_and(|(kind, _)| kind.eq_ignore_ascii_case(filter))
+}
+
+/// Pre-render missing thumbnails and previews for the matching assets; progress via GET /thumbnails/warm
+pub async fn warm_thumbnails(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Json(req): Json<WarmThumbnailsReq>) -> Response {
+    start_bulk(state, principal, req, false).await
+}
+
+/// Re-render thumbnails and previews for the matching assets even where they exist; progress via GET /thumbnails/warm and GET /stats
+pub async fn regenerate_thumbnails(State(state): State<Arc<AppState>>, Extension(principal): Extension<Principal>, Json(req): Json<WarmThumbnailsReq>) -> Response {
+    start_bulk(state, principal, req, true).await
+}
+
+async fn start_bulk(state: Arc<AppState>, principal: Principal, req: WarmThumbnailsReq, regenerate: bool) -> Response {
+    if state.thumb_warm.lock().as_ref().is_some_and(|job| job.is_running()) {
+        return (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "A thumbnail warm-up or regeneration is already running"
+        }))).into_response();
+    }
+
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let derived = state.paths.derived.clone();
+        move || -> Result<Option<WarmPlan>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            let album = match req.album_id {
+                Some(album_id) => {
+                    if !db::query::can_view_album(&conn, album_id, principal.scope())? {
+                        return Ok(None);
+                    }
+                    match db::query::get_album(&conn, album_id)? {
+                        Some((_, _, _, _, _, asset_ids, _)) => Some(asset_ids.into_iter().collect::<HashSet<i64>>()),
+                        None => return Ok(None),
+                    }
+                }
+                None => None,
+            };
+            let dir = req.path.as_deref().filter(|p| !p.is_empty());
+            let mime = req.mime.as_deref().filter(|m| !m.is_empty());
+            let candidates: Vec<_> = db::query::thumb_candidates(&conn, req.from, req.to, dir, principal.scope())?
+                .into_iter()
+                .filter(|(id, _, _, _)| album.as_ref().is_none_or(|ids| ids.contains(id)))
+                .filter(|(_, _, _, m)| mime.is_none_or(|f| mime_matches(m, f)))
+                .collect();
+            drop(conn);
+            let matched = candidates.len() as u64;
+            let missing = candidates
+                .into_iter()
+                .filter(|(_, _, sha, _)| {
+                    sha.len() >= 2
+                        && (regenerate || [THUMB_SIZE, PREVIEW_SIZE].iter().any(|size| !thumb::thumb_path(&derived, sha, *size).exists()))
+                })
+                .map(|(id, path, sha256_hex, mime)| ThumbJob { id, path, sha256_hex, mime, force: regenerate, queued_at: std::time::Instant::now() })
+                .collect();
+            Ok(Some((matched, missing)))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(Some((matched, jobs)))) => {
+            let job = {
+                let mut current = state.thumb_warm.lock();
+                if current.as_ref().is_some_and(|job| job.is_running()) {
+                    return (StatusCode::CONFLICT, Json(serde_json::json!({
+                        "error": "A thumbnail warm-up or regeneration is already running"
+                    }))).into_response();
+                }
+                let now = chrono::Utc::now().timestamp();
+                let job = Arc::new(if regenerate {
+                    WarmJob::regenerating(matched, jobs.len() as u64, now)
+                } else {
+                    WarmJob::new(matched, jobs.len() as u64, now)
+                });
+                *current = Some(job.clone());
+                job
+            };
+            if regenerate {
+                tracing::info!("thumbnail regeneration: re-rendering {} of {} matching assets", jobs.len(), matched);
+            } else {
+                tracing::info!("thumbnail warm-up: {} of {} matching assets need renders", jobs.len(), matched);
+            }
+            tokio::spawn({
+                let job = job.clone();
+                let tx = state.queues.thumb_tx.clone();
+                let gauges = state.gauges.clone();
+                async move { job.run(jobs, tx, gauges).await }
+            });
+            (StatusCode::ACCEPTED, Json(job.report())).into_response()
+        }
+        Ok(Ok(None)) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Album not found"
+        }))).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Error planning thumbnail renders: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Task error planning thumbnail renders: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Internal server error"
+            }))).into_response()
+        }
+    }
+}
+
+pub async fn warm_thumbnails_status(State(state): State<Arc<AppState>>) -> Response {
+    match state.thumb_warm.lock().as_ref() {
+        Some(job) => Json(job.report()).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "No thumbnail warm-up or regeneration has been run"
+        }))).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mime_filter() {
+        assert!(mime_matches("image/heic", "image/heic"));
+        assert!(mime_matches("image/heic", "image"));
+        assert!(mime_matches("video/mp4", "video/"));
+        assert!(!mime_matches("image/heic", "image/jpeg"));
+        assert!(!mime_matches("video/mp4", "vid"));
+    }
+}
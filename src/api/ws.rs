@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use axum::{extract::{ws::{Message, WebSocket, WebSocketUpgrade}, Query, State}, response::Response, Extension};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use anyhow::Result;
+use crate::{AppState, db};
+use crate::api::auth::Principal;
+use crate::api::handlers;
+use crate::db::query::AccessScope;
+use crate::pipeline::thumb;
+
+#[derive(Deserialize)]
+pub struct WsQuery {
+    /// Milliseconds between progress checks (250-10000, default 1000)
+    pub interval_ms: Option<u64>,
+}
+
+/// Commands a client may send as JSON text frames. `id` is echoed back in the reply.
+#[derive(Deserialize)]
+struct Envelope {
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    command: Command,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Command {
+    Ping,
+    PausePath { path: String },
+    ResumePath { path: String },
+    PrioritizeThumbnail { asset_id: i64 },
+}
+
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Extension(principal): Extension<Principal>,
+    Query(q): Query<WsQuery>,
+) -> Response {
+    let interval = Duration::from_millis(q.interval_ms.unwrap_or(1000).clamp(250, 10_000));
+    ws.on_upgrade(move |socket| session(socket, state, principal, interval))
+}
+
+async fn session(socket: WebSocket, state: Arc<AppState>, principal: Principal, interval: Duration) {
+    let (mut sink, mut stream) = socket.split();
+    // Results of background work (thumbnail renders) come back through here so one task owns the sink
+    let (events_tx, mut events_rx) = mpsc::channel::<serde_json::Value>(64);
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut last_progress: Option<serde_json::Value> = None;
+
+    let hello = serde_json::json!({
+        "type": "hello",
+        "version": env!("CARGO_PKG_VERSION"),
+        "interval_ms": interval.as_millis() as u64
+    });
+    if sink.send(Message::Text(hello.to_string())).await.is_err() {
+        return;
+    }
+
+    loop {
+        let outgoing = tokio::select! {
+            _ = ticker.tick() => {
+                let visible = match visible_paths(&state, principal).await {
+                    Ok(visible) => visible,
+                    Err(e) => {
+                        tracing::warn!("Could not load scan paths for progress: {}", e);
+                        continue;
+                    }
+                };
+                // Only push when something changed, so an idle server stays quiet
+                let progress = progress_snapshot(&state, visible.as_ref());
+                if last_progress.as_ref() == Some(&progress) {
+                    continue;
+                }
+                last_progress = Some(progress.clone());
+                serde_json::json!({ "type": "progress", "data": progress })
+            }
+            Some(event) = events_rx.recv() => event,
+            msg = stream.next() => match msg {
+                Some(Ok(Message::Text(text))) => handle_command(&state, &principal, &text, &events_tx).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum; binary frames carry no commands
+                Some(Ok(_)) => continue,
+            },
+        };
+        if sink.send(Message::Text(outgoing.to_string())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// The scan paths the principal can see; None when it sees all of them
+async fn visible_paths(state: &AppState, principal: Principal) -> Result<Option<HashSet<String>>> {
+    let scope = principal.scope();
+    if scope == AccessScope::All {
+        return Ok(None);
+    }
+    let pool = state.pool.clone();
+    tokio::task::spawn_blocking(move || -> Result<Option<HashSet<String>>> {
+        let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+        let paths = db::query::get_scan_paths_with_flags(&conn, scope)?;
+        Ok(Some(paths.into_iter().map(|(path, _)| path).collect()))
+    }).await?
+}
+
+/// Same counters as GET /stats, plus the scan state of the `visible` paths (all when None)
+fn progress_snapshot(state: &AppState, visible: Option<&HashSet<String>>) -> serde_json::Value {
+    let depths = state.gauges.depths();
+    #[allow(unused_mut)]
+    let mut queues = serde_json::json!({
+        "discover": depths.discover,
+        "hash": depths.hash,
+        "metadata": depths.metadata,
+        "db_write": depths.db_write,
+        "thumb": depths.thumb,
+        "transcode": depths.transcode
+    });
+    #[cfg(feature = "facial-recognition")]
+    {
+        queues["face"] = serde_json::json!(depths.face);
+    }
+
+    let scanning: Vec<(String, bool)> = state.path_scan_running.lock()
+        .iter()
+        .map(|(path, flag)| (path.clone(), flag.load(Ordering::Relaxed)))
+        .collect();
+    let shown = |path: &String| visible.is_none_or(|v| v.contains(path));
+    let paused = state.path_watcher_paused.lock();
+    let mut paths: Vec<serde_json::Value> = scanning.iter()
+        .filter(|(path, _)| shown(path))
+        .map(|(path, running)| serde_json::json!({
+            "path": path,
+            "scanning": running,
+            "paused": paused.get(path).is_some_and(|p| p.load(Ordering::Relaxed))
+        }))
+        .collect();
+    for (path, flag) in paused.iter() {
+        if flag.load(Ordering::Relaxed) && shown(path) && !scanning.iter().any(|(p, _)| p == path) {
+            paths.push(serde_json::json!({ "path": path, "scanning": false, "paused": true }));
+        }
+    }
+    drop(paused);
+    paths.sort_by(|a, b| a["path"].as_str().cmp(&b["path"].as_str()));
+
+    let scan_running = scanning.iter().any(|(_, running)| *running);
+    let processing_active = depths.discover > 0 || depths.hash > 0 || depths.metadata > 0 || depths.db_write > 0 || depths.thumb > 0;
+    serde_json::json!({
+        "queues": queues,
+        "scan_running": scan_running,
+        "processing_active": processing_active,
+        "files_total": state.stats.files_total(),
+        "files_committed": state.stats.files_committed(),
+        "bytes_total": state.stats.bytes_total(),
+        "files_per_sec": if scan_running || processing_active { state.stats.files_per_sec() } else { 0.0 },
+        "paths": paths
+    })
+}
+
+/// None if the principal may pause or resume `path`, otherwise the error to send back
+async fn path_command_error(state: &AppState, principal: Principal, path: &str) -> Option<&'static str> {
+    if !principal.is_authenticated() {
+        return Some("Authentication required");
+    }
+    match handlers::can_edit_path(state, principal, path).await {
+        Ok(true) => None,
+        Ok(false) => Some("Path not found in scan paths"),
+        Err(e) => {
+            tracing::error!("Error checking scan path {}: {}", path, e);
+            Some("Internal server error")
+        }
+    }
+}
+
+async fn handle_command(state: &Arc<AppState>, principal: &Principal, text: &str, events_tx: &mpsc::Sender<serde_json::Value>) -> serde_json::Value {
+    let Envelope { id, command } = match serde_json::from_str::<Envelope>(text) {
+        Ok(envelope) => envelope,
+        Err(e) => {
+            return serde_json::json!({ "type": "error", "message": format!("Invalid command: {}", e) });
+        }
+    };
+    match command {
+        Command::Ping => serde_json::json!({ "type": "pong", "id": id }),
+        Command::PausePath { path } => {
+            if let Some(message) = path_command_error(state, *principal, &path).await {
+                return serde_json::json!({ "type": "error", "id": id, "path": path, "message": message });
+            }
+            handlers::pause_scan_path(state, &path);
+            serde_json::json!({ "type": "ack", "id": id, "command": "pause_path", "path": path })
+        }
+        Command::ResumePath { path } => {
+            if let Some(message) = path_command_error(state, *principal, &path).await {
+                return serde_json::json!({ "type": "error", "id": id, "path": path, "message": message });
+            }
+            handlers::resume_scan_path(state, &path);
+            serde_json::json!({ "type": "ack", "id": id, "command": "resume_path", "path": path })
+        }
+        Command::PrioritizeThumbnail { asset_id } => {
+            tokio::spawn(prioritize_thumbnail(state.clone(), *principal, asset_id, id.clone(), events_tx.clone()));
+            serde_json::json!({ "type": "ack", "id": id, "command": "prioritize_thumbnail", "asset_id": asset_id })
+        }
+    }
+}
+
+/// Render an asset's thumbnails ahead of the queue, then report `thumbnail_ready`
+async fn prioritize_thumbnail(state: Arc<AppState>, principal: Principal, asset_id: i64, id: Option<serde_json::Value>, events_tx: mpsc::Sender<serde_json::Value>) {
+    let result = tokio::task::spawn_blocking({
+        let pool = state.pool.clone();
+        let derived = state.paths.derived.clone();
+        move || -> Result<Option<bool>> {
+            let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            if !db::query::can_view_asset(&conn, asset_id, principal.scope())? {
+                return Ok(None);
+            }
+            let Some(asset) = db::query::get_asset_by_id(&conn, asset_id)? else {
+                return Ok(None);
+            };
+            drop(conn);
+            let job = thumb::ThumbJob {
+                id: asset.id,
+                path: asset.path,
+                sha256_hex: asset.sha256.unwrap_or_default(),
+                mime: asset.mime,
+                force: false,
+                queued_at: std::time::Instant::now(),
+            };
+            thumb::render_now(&job, &derived, 256, 1600).map(Some)
+        }
+    }).await;
+
+    let event = match result {
+        Ok(Ok(Some(generated))) => serde_json::json!({ "type": "thumbnail_ready", "id": id, "asset_id": asset_id, "generated": generated }),
+        Ok(Ok(None)) => serde_json::json!({ "type": "error", "id": id, "asset_id": asset_id, "message": "Asset not found" }),
+        Ok(Err(e)) => {
+            tracing::warn!("Priority thumbnail for asset {} failed: {}", asset_id, e);
+            serde_json::json!({ "type": "error", "id": id, "asset_id": asset_id, "message": format!("Thumbnail failed: {}", e) })
+        }
+        Err(e) => {
+            tracing::error!("Task error rendering thumbnail: {}", e);
+            serde_json::json!({ "type": "error", "id": id, "asset_id": asset_id, "message": "Internal server error" })
+        }
+    };
+    let _ = events_tx.send(event).await;
+}
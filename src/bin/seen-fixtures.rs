@@ -0,0 +1,38 @@
+//! Writes a deterministic synthetic library for benchmarking discover/hash/writer.
+//!
+//! `seen-fixtures <out_dir> [--images N] [--duplicate-every N] [--faces N] [--videos N]
+//! [--max-dimension PX] [--seed S]`; prints a JSON summary of what was written.
+
+use seen_backend::utils::fixtures::{generate, FixtureSpec};
+
+const USAGE: &str = "usage: seen-fixtures <out_dir> [--images N] [--duplicate-every N] [--faces N] \
+[--videos N] [--max-dimension PX] [--seed S]";
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let mut out = None;
+    let mut spec = FixtureSpec::default();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            out = Some(std::path::PathBuf::from(arg));
+            continue;
+        }
+        let value = args.next().ok_or_else(|| anyhow::anyhow!("{} needs a value\n{}", arg, USAGE))?;
+        let number: u64 = value.parse().map_err(|_| anyhow::anyhow!("{}: not a number: {}", arg, value))?;
+        match arg.as_str() {
+            "--images" => spec.images = number as usize,
+            "--duplicate-every" => spec.duplicate_every = number as usize,
+            "--faces" => spec.faces = number as usize,
+            "--videos" => spec.videos = number as usize,
+            "--max-dimension" => spec.max_dimension = number as u32,
+            "--seed" => spec.seed = number,
+            _ => anyhow::bail!("unknown option {}\n{}", arg, USAGE),
+        }
+    }
+    let out = out.ok_or_else(|| anyhow::anyhow!(USAGE))?;
+    let started = std::time::Instant::now();
+    let report = generate(&out, &spec)?;
+    eprintln!("wrote {} in {:.1}s", out.display(), started.elapsed().as_secs_f64());
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
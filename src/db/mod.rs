@@ -1,12 +1,13 @@
 pub mod schema;
 pub mod writer;
 pub mod query;
+pub mod pool;
+pub mod tuning;
 
 use anyhow::Result;
 use rusqlite::Connection;
 use std::path::Path;
-use r2d2::Pool;
-use r2d2_sqlite::SqliteConnectionManager;
+pub use pool::DbPool;
 
 pub fn open_or_create<P: AsRef<Path>>(db_path: P) -> Result<Connection> {
     let conn = Connection::open(db_path)?;
@@ -19,21 +20,9 @@ pub fn open_or_create<P: AsRef<Path>>(db_path: P) -> Result<Connection> {
 ///
 /// The pool size is set to 10 by default, which works well with SQLite's WAL mode
 /// allowing concurrent readers while still having a single writer.
-pub fn create_pool<P: AsRef<Path>>(db_path: P, pool_size: u32) -> Result<Pool<SqliteConnectionManager>> {
-    let manager = SqliteConnectionManager::file(db_path.as_ref())
-        .with_init(|conn| {
-            // Apply pragmas to each connection in the pool
-            conn.pragma_update(None, "journal_mode", "WAL")?;
-            conn.pragma_update(None, "synchronous", "NORMAL")?;
-            conn.pragma_update(None, "temp_store", "MEMORY")?;
-            conn.pragma_update(None, "mmap_size", 268435456i64)?;
-            conn.pragma_update(None, "page_size", 4096i64)?;
-            Ok(())
-        });
-
-    let pool = Pool::builder()
-        .max_size(pool_size)
-        .build(manager)?;
+/// Each connection gets the page cache and mmap size from `tuning`.
+pub fn create_pool<P: AsRef<Path>>(db_path: P, pool_size: u32, tuning: tuning::SqliteTuning) -> Result<DbPool> {
+    let pool = DbPool::new(db_path.as_ref(), pool_size, tuning)?;
 
     // Apply schema using a connection from the pool
     {
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use parking_lot::RwLock;
+use r2d2::{event::{CheckoutEvent, HandleEvent, TimeoutEvent}, Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use super::tuning::SqliteTuning;
+
+/// Largest pool PUT /settings/db-pool accepts
+pub const MAX_POOL_SIZE: u32 = 64;
+
+/// Read pool size, saved through PUT /settings/db-pool
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PoolSettings {
+    pub size: u32,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self { size: 10 }
+    }
+}
+
+/// Checkout counters, fed by r2d2's event hooks
+#[derive(Debug, Default)]
+struct PoolCounters {
+    checkouts: AtomicU64,
+    wait_us_total: AtomicU64,
+    wait_us_max: AtomicU64,
+    timeouts: AtomicU64,
+}
+
+#[derive(Debug)]
+struct PoolEvents(Arc<PoolCounters>);
+
+impl HandleEvent for PoolEvents {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        let waited = event.duration().as_micros() as u64;
+        self.0.checkouts.fetch_add(1, Ordering::Relaxed);
+        self.0.wait_us_total.fetch_add(waited, Ordering::Relaxed);
+        self.0.wait_us_max.fetch_max(waited, Ordering::Relaxed);
+    }
+
+    fn handle_timeout(&self, _event: TimeoutEvent) {
+        self.0.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Pool utilization for GET /stats
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStatus {
+    pub size: u32,
+    pub connections: u32,
+    pub idle: u32,
+    /// Connections checked out right now
+    pub active: u32,
+    pub checkouts: u64,
+    /// Checkouts that gave up waiting for a free connection
+    pub timeouts: u64,
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: f64,
+}
+
+/// The read connection pool. Clones share one pool, which `resize` can swap out at runtime;
+/// connections already handed out stay valid and close when returned.
+#[derive(Clone)]
+pub struct DbPool {
+    inner: Arc<RwLock<Pool<SqliteConnectionManager>>>,
+    counters: Arc<PoolCounters>,
+    db_path: PathBuf,
+    tuning: SqliteTuning,
+}
+
+fn build(db_path: &Path, size: u32, tuning: SqliteTuning, counters: &Arc<PoolCounters>) -> Result<Pool<SqliteConnectionManager>> {
+    let manager = SqliteConnectionManager::file(db_path)
+        .with_init(move |conn| {
+            // Apply pragmas to each connection in the pool
+            conn.pragma_update(None, "journal_mode", "WAL")?;
+            conn.pragma_update(None, "synchronous", "NORMAL")?;
+            conn.pragma_update(None, "temp_store", "MEMORY")?;
+            conn.pragma_update(None, "page_size", 4096i64)?;
+            tuning.apply(conn)
+        });
+
+    Ok(Pool::builder()
+        .max_size(size)
+        .event_handler(Box::new(PoolEvents(counters.clone())))
+        .build(manager)?)
+}
+
+impl DbPool {
+    pub fn new(db_path: &Path, size: u32, tuning: SqliteTuning) -> Result<Self> {
+        let counters = Arc::new(PoolCounters::default());
+        let pool = build(db_path, size, tuning, &counters)?;
+        Ok(Self { inner: Arc::new(RwLock::new(pool)), counters, db_path: db_path.to_path_buf(), tuning })
+    }
+
+    pub fn get(&self) -> Result<PooledConnection<SqliteConnectionManager>, r2d2::Error> {
+        let pool = self.inner.read().clone();
+        pool.get()
+    }
+
+    pub fn size(&self) -> u32 {
+        self.inner.read().max_size()
+    }
+
+    /// Replace the pool with one of `size` connections
+    pub fn resize(&self, size: u32) -> Result<()> {
+        let pool = build(&self.db_path, size, self.tuning, &self.counters)?;
+        *self.inner.write() = pool;
+        Ok(())
+    }
+
+    pub fn status(&self) -> PoolStatus {
+        let pool = self.inner.read().clone();
+        let state = pool.state();
+        let checkouts = self.counters.checkouts.load(Ordering::Relaxed);
+        let wait_us = self.counters.wait_us_total.load(Ordering::Relaxed);
+        PoolStatus {
+            size: pool.max_size(),
+            connections: state.connections,
+            idle: state.idle_connections,
+            active: state.connections.saturating_sub(state.idle_connections),
+            checkouts,
+            timeouts: self.counters.timeouts.load(Ordering::Relaxed),
+            avg_wait_ms: if checkouts > 0 { wait_us as f64 / checkouts as f64 / 1000.0 } else { 0.0 },
+            max_wait_ms: self.counters.wait_us_max.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_resize_and_status() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let pool = DbPool::new(&tmp.path().join("seen.db"), 2, SqliteTuning::default()).unwrap();
+        let held = pool.get().unwrap();
+        held.execute_batch("CREATE TABLE t (x INTEGER); INSERT INTO t VALUES (1);").unwrap();
+        let status = pool.status();
+        assert_eq!((status.size, status.active, status.checkouts), (2, 1, 1));
+
+        pool.resize(4).unwrap();
+        assert_eq!(pool.size(), 4);
+        // The connection from the old pool still works and a clone sees the new pool
+        let n: i64 = held.query_row("SELECT COUNT(*) FROM t", [], |r| r.get(0)).unwrap();
+        assert_eq!(n, 1);
+        let other = pool.clone();
+        let conns: Vec<_> = (0..4).map(|_| other.get().unwrap()).collect();
+        let status = pool.status();
+        assert_eq!((status.size, status.connections, status.idle, status.checkouts), (4, 4, 0, 5));
+        drop(conns);
+    }
+}
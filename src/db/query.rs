@@ -1,11 +1,18 @@
 use anyhow::Result;
 use rusqlite::{Connection, params, Row, OptionalExtension};
-use crate::models::asset::{Asset, Paged, SearchResult, SearchMatchCounts};
+use crate::models::asset::{Asset, CalendarDay, CalendarStats, Paged, SearchHit, SearchResult, SearchMatchCounts};
+use crate::models::album::{AlbumPersonCount, AlbumPlaceCount, AlbumStats, SmartAlbumRules};
+use crate::models::share::{Kiosk, ShareLink, SharedAsset};
+use crate::models::rule::ProcessingRule;
+use crate::pipeline::quota::{QuotaHit, ScanQuota};
+#[cfg(feature = "facial-recognition")]
+use crate::models::person::{PersonSummary, PersonTimelineBucket};
 
 // Type aliases for complex query result types
 #[cfg(feature = "facial-recognition")]
 pub type AssetPathSize = (String, Option<i64>, Option<i64>);
-pub type FileUnchangedInfo = (i64, Option<i64>, Option<Vec<u8>>);
+/// (id, xxh64, sha256, flags)
+pub type FileUnchangedInfo = (i64, Option<i64>, Option<Vec<u8>>, i64);
 #[cfg(feature = "facial-recognition")]
 pub type FaceInfo = (i64, Option<i64>, String, f64);
 #[cfg(feature = "facial-recognition")]
@@ -13,9 +20,98 @@ pub type FaceEmbeddingRow = (i64, i64, Vec<u8>, Option<i64>);
 #[cfg(feature = "facial-recognition")]
 pub type UnassignedFace = (i64, i64, Vec<u8>, f64, String);
 pub type AlbumInfo = (i64, String, Option<String>, i64, i64);
-pub type AlbumDetail = (i64, String, Option<String>, i64, i64, Vec<i64>);
+pub type AlbumDetail = (i64, String, Option<String>, i64, i64, Vec<i64>, Option<SmartAlbumRules>);
+pub type TagInfo = (i64, String, i64);
+pub type StackInfo = (i64, i64, i64, Option<String>, Vec<i64>);
+pub type PendingDeleteInfo = (i64, i64, String, String, i64, i64);
+pub type DuePendingDelete = (i64, String, Option<Vec<u8>>);
+pub type ApiTokenInfo = (i64, String, i64, Option<i64>);
+pub type UserInfo = (i64, String, bool, i64);
+pub type UserCredentials = (i64, String, bool);
+/// (id, sha256 hex, taken_at, rating, favorite, face count)
+pub type HighlightCandidateRow = (i64, Option<String>, Option<i64>, i64, bool, i64);
+/// (path, include globs, exclude globs)
+pub type ScanPathFilters = (String, Vec<String>, Vec<String>);
+/// (id, path, sha256 hex, mime)
+pub type ThumbCandidate = (i64, String, String, String);
+
+/// Which assets, albums and scan paths a request may see
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessScope {
+    /// Everything: auth is disabled, or the caller is an admin
+    #[default]
+    All,
+    /// Shared (unowned) items, the user's own, and albums shared with them
+    User(i64),
+    /// Only shared (unowned) items; signed-out visitors when auth is enabled
+    Public,
+}
+
+impl AccessScope {
+    /// Condition on an owner column for items the scope owns outright (no sharing)
+    fn owner_condition(&self, column: &str) -> Option<String> {
+        match self {
+            AccessScope::All => None,
+            AccessScope::User(id) => Some(format!("({c} IS NULL OR {c} = {id})", c = column, id = id)),
+            AccessScope::Public => Some(format!("{} IS NULL", column)),
+        }
+    }
+
+    /// Condition on the assets table, with columns qualified by `prefix`
+    fn asset_condition(&self, prefix: &str) -> Option<String> {
+        match self {
+            AccessScope::User(id) => Some(format!(
+                "({p}owner_id IS NULL OR {p}owner_id = {id} OR {p}id IN (
+                    SELECT aa.asset_id FROM album_assets aa
+                    JOIN album_shares s ON s.album_id = aa.album_id
+                    WHERE s.user_id = {id}))",
+                p = prefix,
+                id = id
+            )),
+            _ => self.owner_condition(&format!("{}owner_id", prefix)),
+        }
+    }
+
+    /// Condition on the albums table, with columns qualified by `prefix`
+    fn album_condition(&self, prefix: &str) -> Option<String> {
+        match self {
+            AccessScope::User(id) => Some(format!(
+                "({p}owner_id IS NULL OR {p}owner_id = {id} OR {p}id IN (SELECT album_id FROM album_shares WHERE user_id = {id}))",
+                p = prefix,
+                id = id
+            )),
+            _ => self.owner_condition(&format!("{}owner_id", prefix)),
+        }
+    }
+
+    /// Condition on face_embeddings: faces in assets the scope can see
+    #[cfg(feature = "facial-recognition")]
+    fn face_condition(&self, prefix: &str) -> Option<String> {
+        self.asset_condition("")
+            .map(|c| format!("{}asset_id IN (SELECT id FROM assets WHERE {})", prefix, c))
+    }
+
+    /// Condition on persons: persons have no owner, so those with a face the scope can see
+    #[cfg(feature = "facial-recognition")]
+    fn person_condition(&self, prefix: &str) -> Option<String> {
+        self.face_condition("")
+            .map(|c| format!("{}id IN (SELECT person_id FROM face_embeddings WHERE person_id IS NOT NULL AND {})", prefix, c))
+    }
+}
+
+/// How several people or tags in a search combine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Every one of them in the same photo
+    #[default]
+    All,
+    /// At least one of them
+    Any,
+}
 
 // Search parameters struct
+#[derive(Default)]
 pub struct SearchParams<'a> {
     pub q: &'a str,
     pub from: Option<i64>,
@@ -23,10 +119,23 @@ pub struct SearchParams<'a> {
     pub camera_make: Option<&'a str>,
     pub camera_model: Option<&'a str>,
     pub platform_type: Option<&'a str>,
+    /// Exact tag names (case-insensitive)
+    pub tags: &'a [String],
+    pub tag_match: MatchMode,
+    pub person_ids: &'a [i64],
+    pub person_match: MatchMode,
+    /// A folder: a full directory path (with its subfolders), or a folder name anywhere in the path
+    pub place: Option<&'a str>,
+    pub scope: AccessScope,
+    /// Order text matches by search relevance instead of filename, folder, then date
+    pub relevance: bool,
     pub offset: i64,
     pub limit: i64,
 }
 
+/// bm25 column weights for fts_search: filename, folders, tags, albums, people, caption, ocr
+const SEARCH_WEIGHTS: &str = "10.0, 2.0, 6.0, 4.0, 6.0, 3.0, 2.0";
+
 fn row_to_asset(row: &Row<'_>) -> rusqlite::Result<Asset> {
     let sha: Option<Vec<u8>> = row.get("sha256")?;
     let sha_hex = sha.map(hex::encode);
@@ -54,6 +163,13 @@ fn row_to_asset(row: &Row<'_>) -> rusqlite::Result<Asset> {
         video_codec: row.get("video_codec").ok(),
         mime: row.get("mime")?,
         flags: row.get("flags")?,
+        rating: row.get("rating").unwrap_or(0),
+        favorite: row.get::<_, i64>("favorite").map(|v| v != 0).unwrap_or(false),
+        stack_id: row.get("stack_id").ok().flatten(),
+        archived: row.get::<_, i64>("archived").map(|v| v != 0).unwrap_or(false),
+        locked: row.get::<_, i64>("locked").map(|v| v != 0).unwrap_or(false),
+        live_photo_video_id: row.get("live_photo_video_id").ok().flatten(),
+        ingested_at: row.get("ingested_at").ok().flatten(),
     })
 }
 
@@ -63,9 +179,79 @@ pub fn count_assets(conn: &Connection) -> Result<i64> {
     Ok(n)
 }
 
+/// Assets `scope` can see as (total, photos, videos)
+pub fn asset_type_counts(conn: &Connection, scope: AccessScope) -> Result<(i64, i64, i64)> {
+    let where_sql = scope.asset_condition("").map(|c| format!("WHERE {}", c)).unwrap_or_default();
+    let counts = conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM(mime LIKE 'image/%'), 0), COALESCE(SUM(mime LIKE 'video/%'), 0) FROM assets {}",
+            where_sql
+        ),
+        [],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+    )?;
+    Ok(counts)
+}
+
+/// Face detection jobs of the current run: (completed, total)
+pub fn face_job_counts(conn: &Connection) -> Result<(i64, i64)> {
+    let counts = conn.query_row("SELECT COALESCE(SUM(done), 0), COUNT(*) FROM face_jobs", [], |r| Ok((r.get(0)?, r.get(1)?)))?;
+    Ok(counts)
+}
+
+/// Per-day asset counts for one calendar year, from `taken_at`. Undated assets are left out.
+pub fn calendar_counts(conn: &Connection, year: i32, scope: AccessScope) -> Result<CalendarStats> {
+    let scope_sql = scope.asset_condition("").map(|c| format!(" AND {}", c)).unwrap_or_default();
+    let start = chrono::NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year {}", year))?
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc()
+        .timestamp();
+    let end = chrono::NaiveDate::from_ymd_opt(year + 1, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year {}", year))?
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc()
+        .timestamp();
+
+    // Range on taken_at first so the index does the work, then bucket by day
+    let mut stmt = conn.prepare(&format!(
+        "SELECT date(taken_at, 'unixepoch') AS day, COUNT(*) FROM assets
+         WHERE taken_at >= ?1 AND taken_at < ?2{}
+         GROUP BY day ORDER BY day",
+        scope_sql
+    ))?;
+    let days = stmt
+        .query_map(params![start, end], |row| Ok(CalendarDay { date: row.get(0)?, count: row.get(1)? }))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT DISTINCT CAST(strftime('%Y', taken_at, 'unixepoch') AS INTEGER) AS y FROM assets
+         WHERE taken_at IS NOT NULL{}
+         ORDER BY y DESC",
+        scope_sql
+    ))?;
+    let years = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<i32>, _>>()?;
+
+    Ok(CalendarStats {
+        year,
+        total: days.iter().map(|d| d.count).sum(),
+        max_count: days.iter().map(|d| d.count).max().unwrap_or(0),
+        days,
+        years,
+    })
+}
+
 #[cfg(feature = "facial-recognition")]
-pub fn get_unassigned_faces(conn: &Connection, offset: i64, limit: i64) -> Result<Vec<(i64, i64, String, f64)>> {
-    let mut stmt = conn.prepare("SELECT id, asset_id, bbox_json, confidence FROM face_embeddings WHERE person_id IS NULL ORDER BY id DESC LIMIT ? OFFSET ?")?;
+pub fn get_unassigned_faces(conn: &Connection, offset: i64, limit: i64, scope: AccessScope) -> Result<Vec<(i64, i64, String, f64)>> {
+    let filter_sql = scope.face_condition("").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, asset_id, bbox_json, confidence FROM face_embeddings WHERE person_id IS NULL {} ORDER BY id DESC LIMIT ? OFFSET ?",
+        filter_sql
+    ))?;
     let rows = stmt.query_map(params![limit, offset], |row| {
         Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
     })?;
@@ -83,6 +269,23 @@ pub fn get_face_row(conn: &Connection, face_id: i64) -> Result<Option<(i64, i64,
     Ok(row)
 }
 
+/// The given faces that are still unassigned and visible to `scope`, in the order asked for
+#[cfg(feature = "facial-recognition")]
+pub fn get_unassigned_faces_by_id(conn: &Connection, ids: &[i64], scope: AccessScope) -> Result<Vec<(i64, i64, String, f64)>> {
+    let filter_sql = scope.face_condition("").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, asset_id, bbox_json, confidence FROM face_embeddings WHERE id = ? AND person_id IS NULL {}",
+        filter_sql
+    ))?;
+    let mut out = Vec::new();
+    for &id in ids {
+        if let Some(row) = stmt.query_row(params![id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))).optional()? {
+            out.push(row);
+        }
+    }
+    Ok(out)
+}
+
 #[cfg(feature = "facial-recognition")]
 pub fn get_asset_path_size(conn: &Connection, asset_id: i64) -> Result<Option<AssetPathSize>> {
     let mut stmt = conn.prepare("SELECT path, width, height FROM assets WHERE id = ?")?;
@@ -92,13 +295,73 @@ pub fn get_asset_path_size(conn: &Connection, asset_id: i64) -> Result<Option<As
     Ok(row)
 }
 
-pub fn list_assets(conn: &Connection, offset: i64, limit: i64, sort: &str, order: &str) -> Result<Paged<Asset>> {
-    let total = count_assets(conn)?;
+/// Optional filters shared by the asset list queries
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ListFilters {
+    pub favorite: Option<bool>,
+    /// Whose favorites `favorite` refers to; None for the instance-wide ones
+    pub user_id: Option<i64>,
+    /// Only return the cover asset of each stack (plus unstacked assets)
+    pub collapse_stacks: bool,
+    /// Leave out media received through messaging apps
+    pub hide_received: bool,
+    /// Only assets first indexed after this Unix time
+    pub ingested_since: Option<i64>,
+    pub scope: AccessScope,
+}
+
+impl ListFilters {
+    /// SQL conditions on the assets table, with columns qualified by `prefix` (e.g. "a.")
+    fn conditions(&self, prefix: &str) -> Vec<String> {
+        let mut conds = Vec::new();
+        match (self.favorite, self.user_id) {
+            (Some(true), None) => conds.push(format!("{}favorite = 1", prefix)),
+            (Some(false), None) => conds.push(format!("{}favorite = 0", prefix)),
+            (Some(favorite), Some(user_id)) => conds.push(format!(
+                "{p}id {not}IN (SELECT asset_id FROM user_favorites WHERE user_id = {u})",
+                p = prefix,
+                not = if favorite { "" } else { "NOT " },
+                u = user_id
+            )),
+            (None, _) => {}
+        }
+        if self.collapse_stacks {
+            // Members of a stack whose cover the scope can't see stay listed on their own
+            let hidden_cover_sql = self
+                .scope
+                .asset_condition("c.")
+                .map(|c| format!(" OR {}stack_id NOT IN (SELECT s.id FROM stacks s JOIN assets c ON c.id = s.cover_asset_id WHERE {})", prefix, c))
+                .unwrap_or_default();
+            conds.push(format!("({p}stack_id IS NULL OR {p}id IN (SELECT cover_asset_id FROM stacks){h})", p = prefix, h = hidden_cover_sql));
+        }
+        if self.hide_received {
+            conds.push(format!("{}source_app IS NULL", prefix));
+        }
+        if let Some(since) = self.ingested_since {
+            conds.push(format!("{}ingested_at > {}", prefix, since));
+        }
+        conds.extend(self.scope.asset_condition(prefix));
+        conds
+    }
+}
+
+pub fn list_assets(conn: &Connection, offset: i64, limit: i64, sort: &str, order: &str, filters: ListFilters) -> Result<Paged<Asset>> {
+    let conds = filters.conditions("");
+    let where_sql = if conds.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conds.join(" AND "))
+    };
+    let total = if where_sql.is_empty() {
+        count_assets(conn)?
+    } else {
+        conn.query_row(&format!("SELECT COUNT(*) FROM assets {}", where_sql), [], |r| r.get(0))?
+    };
     
     // Handle "none" sort - return assets in natural order (by ID)
     if sort == "none" {
         let order_dir = match order { "asc" => "ASC", _ => "DESC" };
-        let sql = format!("SELECT * FROM assets ORDER BY id {} LIMIT ? OFFSET ?", order_dir);
+        let sql = format!("SELECT * FROM assets {} ORDER BY id {} LIMIT ? OFFSET ?", where_sql, order_dir);
         let mut stmt = conn.prepare(&sql)?;
         let items = stmt.query_map(params![limit, offset], row_to_asset)?.collect::<std::result::Result<Vec<_>, _>>()?;
         return Ok(Paged { total, items });
@@ -108,18 +371,20 @@ pub fn list_assets(conn: &Connection, offset: i64, limit: i64, sort: &str, order
     // Handle NULL values properly for nullable columns
     let (sort_col, nulls_clause) = match sort {
         "taken_at" => ("taken_at", "NULLS LAST"), // NULLS LAST for taken_at (photos without EXIF)
+        "ingested_at" => ("ingested_at", "NULLS LAST"),
         "filename" => ("filename", ""),
         "size_bytes" => ("size_bytes", ""),
         "mtime" | "mtime_ns" => ("mtime_ns", ""),
+        "rating" => ("rating", ""),
         _ => ("mtime_ns", ""), // Default to mtime_ns for unrecognized values
     };
     let order_dir = match order { "asc" => "ASC", _ => "DESC" };
     
     // Build SQL with proper NULL handling
     let sql = if nulls_clause.is_empty() {
-        format!("SELECT * FROM assets ORDER BY {} {} LIMIT ? OFFSET ?", sort_col, order_dir)
+        format!("SELECT * FROM assets {} ORDER BY {} {} LIMIT ? OFFSET ?", where_sql, sort_col, order_dir)
     } else {
-        format!("SELECT * FROM assets ORDER BY {} {} {} LIMIT ? OFFSET ?", sort_col, order_dir, nulls_clause)
+        format!("SELECT * FROM assets {} ORDER BY {} {} {} LIMIT ? OFFSET ?", where_sql, sort_col, order_dir, nulls_clause)
     };
     
     let mut stmt = conn.prepare(&sql)?;
@@ -128,10 +393,17 @@ pub fn list_assets(conn: &Connection, offset: i64, limit: i64, sort: &str, order
 }
 
 #[cfg(feature = "facial-recognition")]
-pub fn list_assets_by_person(conn: &Connection, person_id: i64, offset: i64, limit: i64, sort: &str, order: &str) -> Result<Paged<Asset>> {
+pub fn list_assets_by_person(conn: &Connection, person_id: i64, offset: i64, limit: i64, sort: &str, order: &str, filters: ListFilters) -> Result<Paged<Asset>> {
+    let filter_sql: String = filters.conditions("a.").iter().map(|c| format!("AND {} ", c)).collect();
+
     // Count total assets for this person
     let total: i64 = conn.query_row(
-        "SELECT COUNT(DISTINCT asset_id) FROM face_embeddings WHERE person_id = ?",
+        &format!(
+            "SELECT COUNT(DISTINCT fe.asset_id) FROM face_embeddings fe
+             INNER JOIN assets a ON a.id = fe.asset_id
+             WHERE fe.person_id = ? {}",
+            filter_sql
+        ),
         params![person_id],
         |r| r.get(0)
     )?;
@@ -142,11 +414,11 @@ pub fn list_assets_by_person(conn: &Connection, person_id: i64, offset: i64, lim
         let sql = format!(
             "SELECT a.* FROM assets a 
              INNER JOIN face_embeddings fe ON a.id = fe.asset_id 
-             WHERE fe.person_id = ? 
+             WHERE fe.person_id = ? {} 
              GROUP BY a.id 
              ORDER BY a.id {} 
              LIMIT ? OFFSET ?",
-            order_dir
+            filter_sql, order_dir
         );
         let mut stmt = conn.prepare(&sql)?;
         let items = stmt.query_map(params![person_id, limit, offset], row_to_asset)?.collect::<std::result::Result<Vec<_>, _>>()?;
@@ -156,9 +428,11 @@ pub fn list_assets_by_person(conn: &Connection, person_id: i64, offset: i64, lim
     // Map frontend sort field names to database column names
     let (sort_col, nulls_clause) = match sort {
         "taken_at" => ("taken_at", "NULLS LAST"),
+        "ingested_at" => ("ingested_at", "NULLS LAST"),
         "filename" => ("filename", ""),
         "size_bytes" => ("size_bytes", ""),
         "mtime" | "mtime_ns" => ("mtime_ns", ""),
+        "rating" => ("rating", ""),
         _ => ("mtime_ns", ""),
     };
     let order_dir = match order { "asc" => "ASC", _ => "DESC" };
@@ -168,21 +442,21 @@ pub fn list_assets_by_person(conn: &Connection, person_id: i64, offset: i64, lim
         format!(
             "SELECT a.* FROM assets a 
              INNER JOIN face_embeddings fe ON a.id = fe.asset_id 
-             WHERE fe.person_id = ? 
+             WHERE fe.person_id = ? {} 
              GROUP BY a.id 
              ORDER BY a.{} {} 
              LIMIT ? OFFSET ?",
-            sort_col, order_dir
+            filter_sql, sort_col, order_dir
         )
     } else {
         format!(
             "SELECT a.* FROM assets a 
              INNER JOIN face_embeddings fe ON a.id = fe.asset_id 
-             WHERE fe.person_id = ? 
+             WHERE fe.person_id = ? {} 
              GROUP BY a.id 
              ORDER BY a.{} {} {} 
              LIMIT ? OFFSET ?",
-            sort_col, order_dir, nulls_clause
+            filter_sql, sort_col, order_dir, nulls_clause
         )
     };
     
@@ -240,9 +514,20 @@ pub fn search_assets(conn: &Connection, params: &SearchParams<'_>) -> Result<Sea
     let mut params_vec: Vec<rusqlite::types::Value> = Vec::new();
     
     // Add FTS5 search only if we have text terms
+    // Tag names and property values are indexed separately in fts_tags and fts_asset_properties, and
+    // fts_search adds album names, person names, captions and recognized text; column filters (e.g. "filename:x") only
+    // exist on fts_assets, so skip those branches for such queries
+    let column_filter = fts_query.contains(':');
     if use_fts5 {
-        where_clauses.push("id IN (SELECT rowid FROM fts_assets WHERE fts_assets MATCH ?)".to_string());
-        params_vec.push(rusqlite::types::Value::from(fts_query));
+        if column_filter {
+            where_clauses.push("id IN (SELECT rowid FROM fts_assets WHERE fts_assets MATCH ?)".to_string());
+            params_vec.push(rusqlite::types::Value::from(fts_query.clone()));
+        } else {
+            where_clauses.push("(id IN (SELECT rowid FROM fts_assets WHERE fts_assets MATCH ?) OR id IN (SELECT rowid FROM fts_search WHERE fts_search MATCH ?) OR id IN (SELECT at.asset_id FROM asset_tags at JOIN fts_tags ON fts_tags.rowid = at.tag_id WHERE fts_tags MATCH ?) OR id IN (SELECT ap.asset_id FROM asset_properties ap JOIN fts_asset_properties ON fts_asset_properties.rowid = ap.id WHERE fts_asset_properties MATCH ?))".to_string());
+            for _ in 0..4 {
+                params_vec.push(rusqlite::types::Value::from(fts_query.clone()));
+            }
+        }
     }
     
     // Apply GLOB filename filters for wildcard patterns
@@ -271,6 +556,56 @@ pub fn search_assets(conn: &Connection, params: &SearchParams<'_>) -> Result<Sea
     if let Some(t) = params.to { where_clauses.push("taken_at <= ?".to_string()); params_vec.push(t.into()); }
     if let Some(m) = params.camera_make { where_clauses.push("camera_make = ?".to_string()); params_vec.push(rusqlite::types::Value::from(m.to_string())); }
     if let Some(m) = params.camera_model { where_clauses.push("camera_model = ?".to_string()); params_vec.push(rusqlite::types::Value::from(m.to_string())); }
+    // People and tags are resolved through their (person_id, asset_id) / tag name indexes into one
+    // asset id set each; "all" keeps the assets where every requested one was found
+    let mut person_ids = params.person_ids.to_vec();
+    person_ids.sort_unstable();
+    person_ids.dedup();
+    if !person_ids.is_empty() {
+        let placeholders = vec!["?"; person_ids.len()].join(",");
+        where_clauses.push(match params.person_match {
+            MatchMode::All => format!(
+                "id IN (SELECT asset_id FROM face_embeddings WHERE person_id IN ({}) GROUP BY asset_id HAVING COUNT(DISTINCT person_id) = {})",
+                placeholders,
+                person_ids.len()
+            ),
+            MatchMode::Any => format!("id IN (SELECT asset_id FROM face_embeddings WHERE person_id IN ({}))", placeholders),
+        });
+        params_vec.extend(person_ids.iter().map(|&id| rusqlite::types::Value::from(id)));
+    }
+    let mut tags: Vec<String> = Vec::new();
+    for tag in params.tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    if !tags.is_empty() {
+        let placeholders = vec!["?"; tags.len()].join(",");
+        where_clauses.push(match params.tag_match {
+            MatchMode::All => format!(
+                "id IN (SELECT at.asset_id FROM asset_tags at JOIN tags t ON t.id = at.tag_id WHERE t.name IN ({}) GROUP BY at.asset_id HAVING COUNT(DISTINCT at.tag_id) = {})",
+                placeholders,
+                tags.len()
+            ),
+            MatchMode::Any => format!("id IN (SELECT at.asset_id FROM asset_tags at JOIN tags t ON t.id = at.tag_id WHERE t.name IN ({}))", placeholders),
+        });
+        params_vec.extend(tags.into_iter().map(rusqlite::types::Value::from));
+    }
+    if let Some(place) = params.place.map(|p| p.trim().trim_end_matches(['/', '\\'])).filter(|p| !p.is_empty()) {
+        if place.contains(['/', '\\']) {
+            // A directory and everything below it, as an index range on dirname: the separator's
+            // next character bounds the subfolders
+            let (sep, next) = if place.contains('\\') { ('\\', ']') } else { ('/', '0') };
+            where_clauses.push("(dirname = ? OR (dirname >= ? AND dirname < ?))".to_string());
+            params_vec.push(rusqlite::types::Value::from(place.to_string()));
+            params_vec.push(rusqlite::types::Value::from(format!("{}{}", place, sep)));
+            params_vec.push(rusqlite::types::Value::from(format!("{}{}", place, next)));
+        } else {
+            let escaped = place.to_lowercase().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            where_clauses.push("('/' || REPLACE(LOWER(dirname), '\\', '/') || '/') LIKE ? ESCAPE '\\'".to_string());
+            params_vec.push(rusqlite::types::Value::from(format!("%/{}/%", escaped)));
+        }
+    }
     if let Some(pt) = params.platform_type {
         if pt == "whatsapp" {
             // WhatsApp filename pattern: [A-Z]{3}-\d{8}-WA\d{4}\.\w+
@@ -282,6 +617,7 @@ pub fn search_assets(conn: &Connection, params: &SearchParams<'_>) -> Result<Sea
             where_clauses.push("filename GLOB 'PXL_[0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9]_[0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9].*'".to_string());
         }
     }
+    where_clauses.extend(params.scope.asset_condition(""));
     let where_sql = if where_clauses.is_empty() { String::new() } else { format!("WHERE {}", where_clauses.join(" AND ")) };
     let count_sql = format!("SELECT COUNT(*) FROM assets {}", where_sql);
     let total: i64 = conn.query_row(&count_sql, rusqlite::params_from_iter(params_vec.clone()), |r| r.get(0))?;
@@ -387,16 +723,64 @@ pub fn search_assets(conn: &Connection, params: &SearchParams<'_>) -> Result<Sea
         "taken_at DESC NULLS LAST, mtime_ns DESC".to_string()
     };
     
-    let list_sql = format!(
-        "SELECT * FROM assets {} ORDER BY {} LIMIT ? OFFSET ?",
-        where_sql, order_by_clause
-    );
-    let mut all_params = params_vec.clone();
+    let ranked = params.relevance && use_fts5 && !column_filter && !has_wildcard_patterns;
+    let (list_sql, mut all_params) = if ranked {
+        // Matches found only through fts_assets paths or other properties have no rank and come last
+        let sql = format!(
+            "SELECT assets.* FROM assets
+             LEFT JOIN (SELECT rowid AS search_id, bm25(fts_search, {}) AS search_rank FROM fts_search WHERE fts_search MATCH ?) ON search_id = assets.id
+             {} ORDER BY search_rank IS NULL, search_rank, taken_at DESC NULLS LAST, mtime_ns DESC LIMIT ? OFFSET ?",
+            SEARCH_WEIGHTS, where_sql
+        );
+        let mut all = vec![rusqlite::types::Value::from(fts_query.clone())];
+        all.extend(params_vec);
+        (sql, all)
+    } else {
+        (format!("SELECT * FROM assets {} ORDER BY {} LIMIT ? OFFSET ?", where_sql, order_by_clause), params_vec)
+    };
     all_params.push(params.limit.into());
     all_params.push(params.offset.into());
     let mut stmt = conn.prepare(&list_sql)?;
-    let items = stmt.query_map(rusqlite::params_from_iter(all_params.into_iter()), row_to_asset)?.collect::<std::result::Result<Vec<_>, _>>()?;
-    Ok(SearchResult { total, items, match_counts })
+    let items = stmt.query_map(rusqlite::params_from_iter(all_params), row_to_asset)?.collect::<std::result::Result<Vec<_>, _>>()?;
+    let hits = if use_fts5 && !column_filter {
+        let ids: Vec<i64> = items.iter().map(|a| a.id).collect();
+        Some(search_hits(conn, &fts_query, &ids)?)
+    } else {
+        None
+    };
+    Ok(SearchResult { total, items, match_counts, hits })
+}
+
+/// Relevance and snippet for each of `ids` that fts_search matches, in `ids` order
+fn search_hits(conn: &Connection, fts_query: &str, ids: &[i64]) -> Result<Vec<SearchHit>> {
+    let mut found = std::collections::HashMap::new();
+    for chunk in ids.chunks(500) {
+        // \x01 and \x02 mark matches until the text is escaped
+        let sql = format!(
+            "SELECT rowid, bm25(fts_search, {}), snippet(fts_search, -1, char(1), char(2), '…', 12)
+             FROM fts_search WHERE fts_search MATCH ? AND rowid IN ({})",
+            SEARCH_WEIGHTS,
+            vec!["?"; chunk.len()].join(",")
+        );
+        let mut values = vec![rusqlite::types::Value::from(fts_query.to_string())];
+        values.extend(chunk.iter().map(|&id| rusqlite::types::Value::from(id)));
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?, row.get::<_, String>(2)?))
+        })?;
+        for row in rows {
+            let (id, rank, snippet) = row?;
+            let snippet = snippet
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('\u{1}', "<mark>")
+                .replace('\u{2}', "</mark>");
+            // bm25 is lower for better matches
+            found.insert(id, SearchHit { asset_id: id, score: -rank, snippet });
+        }
+    }
+    Ok(ids.iter().filter_map(|id| found.remove(id)).collect())
 }
 
 pub fn get_asset_sha256(conn: &Connection, id: i64) -> Result<Option<String>> {
@@ -418,15 +802,16 @@ pub fn get_thumb_info(conn: &Connection, id: i64) -> Result<(Option<String>, Str
 }
 
 /// Check if a file is unchanged (path and mtime match)
-/// Returns Some(id, xxh64, sha256) if unchanged, None if changed or not found
+/// Returns Some(id, xxh64, sha256, flags) if unchanged, None if changed or not found
 pub fn check_file_unchanged(conn: &Connection, path: &str, mtime_ns: i64, size_bytes: i64) -> Result<Option<FileUnchangedInfo>> {
-    let mut stmt = conn.prepare("SELECT id, xxh64, sha256 FROM assets WHERE path = ? AND mtime_ns = ? AND size_bytes = ?")?;
+    let mut stmt = conn.prepare("SELECT id, xxh64, sha256, flags FROM assets WHERE path = ? AND mtime_ns = ? AND size_bytes = ?")?;
     let mut rows = stmt.query(params![path, mtime_ns, size_bytes])?;
     if let Some(row) = rows.next()? {
         let id: i64 = row.get(0)?;
         let xxh64: Option<i64> = row.get(1).ok();
         let sha256: Option<Vec<u8>> = row.get(2).ok();
-        Ok(Some((id, xxh64, sha256)))
+        let flags: i64 = row.get(3)?;
+        Ok(Some((id, xxh64, sha256, flags)))
     } else {
         Ok(None)
     }
@@ -458,11 +843,41 @@ pub fn check_metadata_complete(conn: &Connection, id: i64, mime: &str) -> Result
 }
 
 /// Get asset path by ID
+/// Whether the asset is locked; false when it doesn't exist
+pub fn is_asset_locked(conn: &Connection, id: i64) -> Result<bool> {
+    let locked: Option<i64> = conn.query_row("SELECT locked FROM assets WHERE id = ?", params![id], |r| r.get(0)).optional()?;
+    Ok(locked.unwrap_or(0) != 0)
+}
+
+/// The video paired with a live photo still, if both still exist
+pub fn get_live_photo_video(conn: &Connection, id: i64) -> Result<Option<i64>> {
+    let video: Option<i64> = conn
+        .query_row(
+            "SELECT v.id FROM assets a JOIN assets v ON v.id = a.live_photo_video_id WHERE a.id = ?",
+            params![id],
+            |r| r.get(0),
+        )
+        .optional()?;
+    Ok(video)
+}
+
 pub fn get_asset_path(conn: &Connection, id: i64) -> Result<Option<String>> {
     let path: Option<String> = conn.query_row("SELECT path FROM assets WHERE id = ?", params![id], |r| r.get(0)).ok();
     Ok(path)
 }
 
+/// Replace the instance-wide favorite flag of `assets` with the user's own; no-op for None
+pub fn mark_user_favorites(conn: &Connection, user_id: Option<i64>, assets: &mut [Asset]) -> Result<()> {
+    let Some(user_id) = user_id else {
+        return Ok(());
+    };
+    let mut stmt = conn.prepare_cached("SELECT 1 FROM user_favorites WHERE user_id = ?1 AND asset_id = ?2")?;
+    for asset in assets.iter_mut() {
+        asset.favorite = stmt.exists(params![user_id, asset.id])?;
+    }
+    Ok(())
+}
+
 /// Get a single asset by ID
 pub fn get_asset_by_id(conn: &Connection, id: i64) -> Result<Option<Asset>> {
     let mut stmt = conn.prepare("SELECT * FROM assets WHERE id = ?")?;
@@ -476,11 +891,21 @@ pub fn get_asset_by_id(conn: &Connection, id: i64) -> Result<Option<Asset>> {
 
 /// Delete an asset by ID from both assets and fts_assets tables
 pub fn delete_asset_by_id(conn: &Connection, id: i64) -> Result<bool> {
+    // Properties first: their rows cascade away with the asset, which would orphan their search entries
+    crate::db::writer::delete_asset_properties(conn, id)?;
+
     // Delete from assets table
     let deleted = conn.execute("DELETE FROM assets WHERE id = ?", params![id])?;
     
     // Delete from FTS table
     let _ = conn.execute("DELETE FROM fts_assets WHERE rowid = ?", params![id]);
+
+    // Drop tag links and share links, dissolve any stack this asset was the cover of, and
+    // unpair the still it was the live photo clip of
+    conn.execute("DELETE FROM asset_tags WHERE asset_id = ?", params![id])?;
+    conn.execute("DELETE FROM shares WHERE asset_id = ?", params![id])?;
+    crate::db::writer::dissolve_stacks_with_cover(conn, id)?;
+    crate::db::writer::unlink_live_photo(conn, id)?;
     
     Ok(deleted > 0)
 }
@@ -494,17 +919,196 @@ pub fn get_scan_paths(conn: &Connection) -> Result<Vec<String>> {
     Ok(paths)
 }
 
+/// Get the scan paths visible to `scope` with their read-only flag
+pub fn get_scan_paths_with_flags(conn: &Connection, scope: AccessScope) -> Result<Vec<(String, bool)>> {
+    let where_sql = scope.owner_condition("owner_id").map(|c| format!("WHERE {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!("SELECT path, read_only FROM scan_paths {} ORDER BY created_at", where_sql))?;
+    let paths = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? != 0))
+    })?.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(paths)
+}
+
+/// (path, sha256) of every asset, optionally only those under `dir`, in path order. Sampled
+/// hashes aren't checksums of the whole file, so those come back as None.
+pub fn manifest_rows(conn: &Connection, dir: Option<&str>) -> Result<Vec<(String, Option<Vec<u8>>)>> {
+    let prefix = dir.map(|d| format!("{}{}", d.trim_end_matches(['/', '\\']), std::path::MAIN_SEPARATOR));
+    let mut stmt = conn.prepare(
+        "SELECT path, CASE WHEN flags & ?2 THEN NULL ELSE sha256 END FROM assets
+         WHERE ?1 IS NULL OR substr(path, 1, length(?1)) = ?1 ORDER BY path",
+    )?;
+    let rows = stmt.query_map(params![prefix, crate::models::asset::FLAG_SAMPLED_HASH], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Hashed, online images and videos visible to `scope`, optionally limited by taken date and directory
+pub fn thumb_candidates(conn: &Connection, from: Option<i64>, to: Option<i64>, dir: Option<&str>, scope: AccessScope) -> Result<Vec<ThumbCandidate>> {
+    let prefix = dir.map(|d| format!("{}{}", d.trim_end_matches(['/', '\\']), std::path::MAIN_SEPARATOR));
+    let mut sql = String::from(
+        "SELECT id, path, sha256, mime FROM assets
+         WHERE sha256 IS NOT NULL AND archived = 0 AND (mime LIKE 'image/%' OR mime LIKE 'video/%')
+           AND (?1 IS NULL OR taken_at >= ?1) AND (?2 IS NULL OR taken_at <= ?2)
+           AND (?3 IS NULL OR substr(path, 1, length(?3)) = ?3)"
+    );
+    if let Some(cond) = scope.asset_condition("") {
+        sql.push_str(" AND ");
+        sql.push_str(&cond);
+    }
+    sql.push_str(" ORDER BY taken_at DESC, id DESC");
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![from, to, prefix], |row| {
+        let sha: Vec<u8> = row.get(2)?;
+        Ok((row.get(0)?, row.get(1)?, hex::encode(sha), row.get(3)?))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Scan paths with a recurring scan: (path, cron expression, last time it fired)
+pub fn get_scan_path_schedules(conn: &Connection) -> Result<Vec<(String, String, Option<i64>)>> {
+    let mut stmt = conn.prepare("SELECT path, schedule, last_scheduled_at FROM scan_paths WHERE schedule IS NOT NULL AND archived = 0 ORDER BY created_at")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Archived scan paths and their labels
+pub fn get_archived_scan_paths(conn: &Connection) -> Result<Vec<(String, Option<String>)>> {
+    let mut stmt = conn.prepare("SELECT path, archive_label FROM scan_paths WHERE archived = 1 ORDER BY created_at")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// None unless the asset is archived; otherwise the label of the archived scan path holding it, if any
+pub fn get_asset_archive(conn: &Connection, id: i64) -> Result<Option<Option<String>>> {
+    let row: Option<(String, bool)> = conn.query_row(
+        "SELECT path, archived FROM assets WHERE id = ?1",
+        params![id],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    ).optional()?;
+    let Some((path, true)) = row else {
+        return Ok(None);
+    };
+    let label = get_archived_scan_paths(conn)?
+        .into_iter()
+        .filter(|(root, _)| std::path::Path::new(&path).starts_with(root))
+        .max_by_key(|(root, _)| root.len())
+        .and_then(|(_, label)| label);
+    Ok(Some(label))
+}
+
+fn split_patterns(text: Option<String>) -> Vec<String> {
+    text.map(|t| t.lines().map(str::to_string).collect()).unwrap_or_default()
+}
+
+/// Include and exclude globs for every scan path that has any
+pub fn get_scan_path_filters(conn: &Connection) -> Result<Vec<ScanPathFilters>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, include_patterns, exclude_patterns FROM scan_paths
+         WHERE include_patterns IS NOT NULL OR exclude_patterns IS NOT NULL ORDER BY created_at",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, split_patterns(row.get(1)?), split_patterns(row.get(2)?))))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Include and exclude globs for one scan path; both empty when none are set
+pub fn get_scan_path_filter(conn: &Connection, path: &str) -> Result<(Vec<String>, Vec<String>)> {
+    let row: Option<(Option<String>, Option<String>)> = conn.query_row(
+        "SELECT include_patterns, exclude_patterns FROM scan_paths WHERE path = ?1",
+        params![path],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?;
+    let (include, exclude) = row.unwrap_or_default();
+    Ok((split_patterns(include), split_patterns(exclude)))
+}
+
+/// Asset and byte limits for one scan path; unlimited when none are set or the path is unknown
+pub fn get_scan_path_quota(conn: &Connection, path: &str) -> Result<ScanQuota> {
+    let row: Option<(Option<i64>, Option<i64>)> = conn.query_row(
+        "SELECT max_assets, max_bytes FROM scan_paths WHERE path = ?1",
+        params![path],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?;
+    let (max_assets, max_bytes) = row.unwrap_or_default();
+    Ok(ScanQuota { max_assets: max_assets.map(|n| n as u64), max_bytes: max_bytes.map(|n| n as u64) })
+}
+
+/// Scan paths with limits set or whose last scan stopped at them
+pub fn get_scan_path_quotas(conn: &Connection) -> Result<Vec<(String, ScanQuota, Option<QuotaHit>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, max_assets, max_bytes, quota_hit FROM scan_paths
+         WHERE max_assets IS NOT NULL OR max_bytes IS NOT NULL OR quota_hit IS NOT NULL ORDER BY created_at",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let quota = ScanQuota {
+            max_assets: row.get::<_, Option<i64>>(1)?.map(|n| n as u64),
+            max_bytes: row.get::<_, Option<i64>>(2)?.map(|n| n as u64),
+        };
+        let hit = row.get::<_, Option<String>>(3)?.and_then(|json| serde_json::from_str(&json).ok());
+        Ok((row.get(0)?, quota, hit))
+    })?
+    .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Recent scans, newest first, of one path or of all of them
+pub fn get_scan_runs(conn: &Connection, path: Option<&str>, limit: i64, scope: AccessScope) -> Result<Vec<crate::pipeline::discover::ScanRun>> {
+    // Runs of removed paths have no owner left, so only unrestricted scopes see them
+    let filter_sql = scope
+        .owner_condition("owner_id")
+        .map(|c| format!("AND path IN (SELECT path FROM scan_paths WHERE {})", c))
+        .unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT path, started_at, finished_at, status, files, bytes FROM scan_runs
+         WHERE (?1 IS NULL OR path = ?1) {} ORDER BY id DESC LIMIT ?2",
+        filter_sql
+    ))?;
+    let rows = stmt.query_map(params![path, limit], |row| {
+        Ok(crate::pipeline::discover::ScanRun {
+            path: row.get(0)?,
+            started_at: row.get(1)?,
+            finished_at: row.get(2)?,
+            status: row.get(3)?,
+            files: row.get::<_, i64>(4)? as u64,
+            bytes: row.get::<_, i64>(5)? as u64,
+        })
+    })?
+    .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Scan paths with their own date source order, as saved (comma-separated)
+pub fn get_scan_path_date_priorities(conn: &Connection) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT path, date_priority FROM scan_paths WHERE date_priority IS NOT NULL ORDER BY created_at")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
 /// Delete an asset by path from both assets and fts_assets tables
 pub fn delete_asset_by_path(conn: &Connection, path: &str) -> Result<bool> {
     // First get the id to delete from FTS
-    let id: Option<i64> = conn.query_row("SELECT id FROM assets WHERE path = ?", params![path], |r| r.get(0)).ok();
+    let row: Option<(i64, bool)> = conn.query_row("SELECT id, archived FROM assets WHERE path = ?", params![path], |r| Ok((r.get(0)?, r.get(1)?))).ok();
+    // Archived originals are expected to disappear when their drive is unplugged
+    if matches!(row, Some((_, true))) {
+        return Ok(false);
+    }
+    let id = row.map(|(id, _)| id);
     
+    if let Some(asset_id) = id {
+        crate::db::writer::delete_asset_properties(conn, asset_id)?;
+    }
+
     // Delete from assets table
     let deleted = conn.execute("DELETE FROM assets WHERE path = ?", params![path])?;
     
     // Delete from FTS table if we found an id
     if let Some(asset_id) = id {
         let _ = conn.execute("DELETE FROM fts_assets WHERE rowid = ?", params![asset_id]);
+        conn.execute("DELETE FROM shares WHERE asset_id = ?", params![asset_id])?;
+        crate::db::writer::unlink_live_photo(conn, asset_id)?;
     }
     
     Ok(deleted > 0)
@@ -515,7 +1119,7 @@ pub fn delete_asset_by_path(conn: &Connection, path: &str) -> Result<bool> {
 pub fn find_moved_asset(conn: &Connection, filename: &str, size_bytes: i64) -> Result<Option<String>> {
     use std::path::Path;
     
-    let mut stmt = conn.prepare("SELECT path FROM assets WHERE filename = ? AND size_bytes = ?")?;
+    let mut stmt = conn.prepare("SELECT path FROM assets WHERE filename = ? AND size_bytes = ? AND archived = 0")?;
     let rows = stmt.query_map(params![filename, size_bytes], |row| {
         row.get::<_, String>(0)
     })?;
@@ -532,6 +1136,28 @@ pub fn find_moved_asset(conn: &Connection, filename: &str, size_bytes: i64) -> R
     Ok(None)
 }
 
+/// Find an existing asset with the same content among those `scope` can see
+/// Matches on SHA256 first; large images are stored without SHA256 so fall back to xxh64 + size
+pub fn find_asset_by_hash(conn: &Connection, sha256: &[u8], xxh64: i64, size_bytes: i64, scope: AccessScope) -> Result<Option<(i64, String)>> {
+    let filter_sql = scope.asset_condition("").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let by_sha = conn
+        .query_row(&format!("SELECT id, path FROM assets WHERE sha256 = ? {} LIMIT 1", filter_sql), params![sha256], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })
+        .optional()?;
+    if by_sha.is_some() {
+        return Ok(by_sha);
+    }
+    let by_xxh = conn
+        .query_row(
+            &format!("SELECT id, path FROM assets WHERE xxh64 = ? AND size_bytes = ? {} LIMIT 1", filter_sql),
+            params![xxh64, size_bytes],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()?;
+    Ok(by_xxh)
+}
+
 /// Update an asset's path (for file moves/renames) in both assets and fts_assets tables
 pub fn update_asset_path(conn: &Connection, old_path: &str, new_path: &str) -> Result<bool> {
     use std::path::Path;
@@ -577,6 +1203,7 @@ pub fn update_asset_path(conn: &Connection, old_path: &str, new_path: &str) -> R
             "UPDATE fts_assets SET path = ?1, dirname = ?2, filename = ?3 WHERE rowid = ?4",
             params![new_path, dirname, filename, asset_id],
         );
+        crate::db::writer::assign_asset_roots(&tx, Some(&[asset_id]))?;
     }
     
     tx.commit()?;
@@ -593,6 +1220,77 @@ pub fn list_persons(conn: &Connection) -> Result<Vec<(i64, Option<String>, i64)>
     Ok(persons)
 }
 
+/// Ordering of the paged person listing
+#[cfg(feature = "facial-recognition")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersonSort {
+    /// Most faces first
+    Faces,
+    /// Most distinct assets first
+    Assets,
+    /// Named persons alphabetically, then unnamed ones by id
+    Name,
+    /// Newest first
+    Created,
+}
+
+/// One page of persons with face/asset counts from a single pass over face_embeddings, and each one's cover face.
+/// Persons, counts and covers only take in the faces `scope` can see.
+#[cfg(feature = "facial-recognition")]
+pub fn list_persons_paged(
+    conn: &Connection,
+    offset: i64,
+    limit: i64,
+    sort: PersonSort,
+    subject: crate::pipeline::face::Subject,
+    scope: AccessScope,
+) -> Result<Paged<PersonSummary>> {
+    let person_sql = scope.person_condition("p.").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let face_sql = scope.face_condition("").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM persons p WHERE p.subject_type = ?1 {}", person_sql),
+        params![subject.as_str()],
+        |row| row.get(0),
+    )?;
+    let order = match sort {
+        PersonSort::Faces => "face_count DESC, p.id ASC",
+        PersonSort::Assets => "asset_count DESC, face_count DESC, p.id ASC",
+        PersonSort::Name => "p.name IS NULL, p.name COLLATE NOCASE ASC, p.id ASC",
+        PersonSort::Created => "p.created_at DESC, p.id DESC",
+    };
+    let sql = format!(
+        "SELECT p.id, p.name, p.created_at,
+                COALESCE(f.face_count, 0) AS face_count,
+                COALESCE(f.asset_count, 0) AS asset_count
+         FROM persons p
+         LEFT JOIN (
+             SELECT person_id, COUNT(*) AS face_count, COUNT(DISTINCT asset_id) AS asset_count
+             FROM face_embeddings
+             WHERE person_id IS NOT NULL {}
+             GROUP BY person_id
+         ) f ON f.person_id = p.id
+         WHERE p.subject_type = ? {}
+         ORDER BY {}
+         LIMIT ? OFFSET ?",
+        face_sql, person_sql, order
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let mut items = stmt.query_map(params![subject.as_str(), limit, offset], |row| {
+        Ok(PersonSummary {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            created_at: row.get(2)?,
+            face_count: row.get(3)?,
+            asset_count: row.get(4)?,
+            cover_face_id: None,
+        })
+    })?.collect::<std::result::Result<Vec<_>, _>>()?;
+    for item in items.iter_mut().filter(|item| item.face_count > 0) {
+        item.cover_face_id = get_person_representative_face(conn, item.id, scope)?;
+    }
+    Ok(Paged { total, items })
+}
+
 #[cfg(feature = "facial-recognition")]
 pub fn get_person(conn: &Connection, person_id: i64) -> Result<Option<(i64, Option<String>, i64)>> {
     let mut stmt = conn.prepare("SELECT id, name, created_at FROM persons WHERE id = ?")?;
@@ -602,23 +1300,224 @@ pub fn get_person(conn: &Connection, person_id: i64) -> Result<Option<(i64, Opti
     Ok(person)
 }
 
+/// True if the person exists and has a face `scope` can see
+#[cfg(feature = "facial-recognition")]
+pub fn can_view_person(conn: &Connection, person_id: i64, scope: AccessScope) -> Result<bool> {
+    row_matches(conn, "persons", person_id, scope.person_condition(""))
+}
+
+/// True if the face exists and is in an asset `scope` can see
+#[cfg(feature = "facial-recognition")]
+pub fn can_view_face(conn: &Connection, face_id: i64, scope: AccessScope) -> Result<bool> {
+    row_matches(conn, "face_embeddings", face_id, scope.face_condition(""))
+}
+
+/// The person's assets that `scope` can see
 #[cfg(feature = "facial-recognition")]
-pub fn get_person_assets(conn: &Connection, person_id: i64) -> Result<Vec<i64>> {
-    let mut stmt = conn.prepare("SELECT DISTINCT asset_id FROM face_embeddings WHERE person_id = ?")?;
+pub fn get_person_assets(conn: &Connection, person_id: i64, scope: AccessScope) -> Result<Vec<i64>> {
+    let filter_sql = scope.face_condition("").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!("SELECT DISTINCT asset_id FROM face_embeddings WHERE person_id = ? {}", filter_sql))?;
     let assets = stmt.query_map(params![person_id], |row| {
         row.get(0)
     })?.collect::<std::result::Result<Vec<_>, _>>()?;
     Ok(assets)
 }
 
+/// Bucket a person's assets that `scope` can see by year (or year and month) of taken_at, falling
+/// back to mtime, newest first. Each bucket carries up to `samples` asset ids for its thumbnails.
 #[cfg(feature = "facial-recognition")]
-pub fn get_person_representative_face(conn: &Connection, person_id: i64) -> Result<Option<i64>> {
-    // Get the face with highest confidence for this person
-    let mut stmt = conn.prepare("SELECT id FROM face_embeddings WHERE person_id = ? ORDER BY confidence DESC LIMIT 1")?;
-    let face_id: Option<i64> = stmt.query_row(params![person_id], |row| {
-        row.get(0)
-    }).optional()?;
-    Ok(face_id)
+pub fn get_person_timeline(conn: &Connection, person_id: i64, by_month: bool, samples: usize, scope: AccessScope) -> Result<Vec<PersonTimelineBucket>> {
+    use chrono::Datelike;
+
+    let filter_sql = scope.asset_condition("a.").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT a.id, COALESCE(a.taken_at, a.mtime_ns / 1000000000) AS ts, MAX(f.confidence) AS conf
+         FROM face_embeddings f JOIN assets a ON a.id = f.asset_id
+         WHERE f.person_id = ?1 {}
+         GROUP BY a.id
+         ORDER BY ts DESC, a.id DESC",
+        filter_sql
+    ))?;
+    let rows = stmt
+        .query_map(params![person_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, f64>(2)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut buckets: Vec<(PersonTimelineBucket, Vec<(f64, i64)>)> = Vec::new();
+    for (asset_id, ts, conf) in rows {
+        let dt = chrono::DateTime::from_timestamp(ts, 0).unwrap_or_default();
+        let (year, month) = (dt.year(), if by_month { Some(dt.month()) } else { None });
+        match buckets.last_mut() {
+            Some((b, candidates)) if b.year == year && b.month == month => {
+                b.count += 1;
+                candidates.push((conf, asset_id));
+            }
+            _ => buckets.push((
+                PersonTimelineBucket { year, month, count: 1, sample_asset_ids: Vec::new() },
+                vec![(conf, asset_id)],
+            )),
+        }
+    }
+
+    Ok(buckets
+        .into_iter()
+        .map(|(mut b, mut candidates)| {
+            candidates.sort_by(|a, c| c.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+            b.sample_asset_ids = candidates.into_iter().take(samples).map(|(_, id)| id).collect();
+            b
+        })
+        .collect())
+}
+
+/// Most confident faces weighed when picking a cover automatically
+#[cfg(feature = "facial-recognition")]
+const COVER_CANDIDATES: i64 = 200;
+
+/// Side in pixels past which a bigger face makes no better avatar; the size faces are embedded at
+#[cfg(feature = "facial-recognition")]
+const COVER_FULL_SIZE: f64 = 112.0;
+
+/// Width over height of a detection box around a face looking at the camera
+#[cfg(feature = "facial-recognition")]
+const FRONTAL_ASPECT: f64 = 0.8;
+
+/// How good an avatar a face makes: confident, large and frontal. Landmarks aren't stored, so
+/// frontal is judged from the box, which gets narrower as the head turns. Faces with an
+/// unreadable box are scored on confidence alone.
+#[cfg(feature = "facial-recognition")]
+fn cover_score(bbox_json: &str, confidence: f64) -> f64 {
+    let Ok(bbox) = serde_json::from_str::<crate::pipeline::face::FaceBbox>(bbox_json) else {
+        return confidence;
+    };
+    let (w, h) = ((bbox.x2 - bbox.x1) as f64, (bbox.y2 - bbox.y1) as f64);
+    if w <= 0.0 || h <= 0.0 {
+        return confidence;
+    }
+    let size = ((w * h).sqrt() / COVER_FULL_SIZE).min(1.0);
+    let aspect = w / h;
+    let frontal = aspect.min(FRONTAL_ASPECT) / aspect.max(FRONTAL_ASPECT);
+    confidence * size * frontal
+}
+
+/// The person's chosen cover face while it is still theirs, otherwise the best scoring of their most
+/// confident faces; either way only among the faces `scope` can see
+#[cfg(feature = "facial-recognition")]
+pub fn get_person_representative_face(conn: &Connection, person_id: i64, scope: AccessScope) -> Result<Option<i64>> {
+    let filter_sql = scope.face_condition("f.").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let chosen: Option<i64> = conn.query_row(
+        &format!("SELECT f.id FROM persons p JOIN face_embeddings f ON f.id = p.cover_face_id AND f.person_id = p.id WHERE p.id = ?1 {}", filter_sql),
+        params![person_id],
+        |row| row.get(0),
+    ).optional()?;
+    if chosen.is_some() {
+        return Ok(chosen);
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.id, f.bbox_json, f.confidence FROM face_embeddings f WHERE f.person_id = ?1 {} ORDER BY f.confidence DESC, f.id ASC LIMIT ?2",
+        filter_sql
+    ))?;
+    let faces = stmt
+        .query_map(params![person_id, COVER_CANDIDATES], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    // Ties go to the more confident face
+    let best = faces.into_iter().fold(None::<(i64, f64)>, |best, (id, bbox_json, confidence)| {
+        let score = cover_score(&bbox_json, confidence);
+        match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((id, score)),
+        }
+    });
+    Ok(best.map(|(id, _)| id))
+}
+
+/// Up to `limit` of the person's best faces for a collage: the cover first, then the best scoring
+/// of their most confident faces, at most one per asset so a burst doesn't fill the grid. Only
+/// faces in assets `scope` can see are used.
+#[cfg(feature = "facial-recognition")]
+pub fn get_person_collage_faces(conn: &Connection, person_id: i64, limit: usize, scope: AccessScope) -> Result<Vec<i64>> {
+    let filter_sql = scope.face_condition("").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, asset_id, bbox_json, confidence FROM face_embeddings WHERE person_id = ?1 {} ORDER BY confidence DESC, id ASC LIMIT ?2",
+        filter_sql
+    ))?;
+    let mut faces = stmt
+        .query_map(params![person_id, COVER_CANDIDATES], |row| {
+            let bbox_json: String = row.get(2)?;
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, cover_score(&bbox_json, row.get(3)?)))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    // Stable, so ties keep the more confident face first
+    faces.sort_by(|a, b| b.2.total_cmp(&a.2));
+    if let Some(cover) = get_person_representative_face(conn, person_id, scope)? {
+        if let Some(pos) = faces.iter().position(|f| f.0 == cover) {
+            let face = faces.remove(pos);
+            faces.insert(0, face);
+        }
+    }
+    let mut assets = std::collections::HashSet::new();
+    Ok(faces.into_iter().filter(|f| assets.insert(f.1)).map(|f| f.0).take(limit).collect())
+}
+
+/// An asset's faces that belong to a named person or pet
+#[cfg(feature = "facial-recognition")]
+#[derive(Debug)]
+pub struct NamedFaces {
+    pub asset_id: i64,
+    pub path: String,
+    pub mime: String,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub locked: bool,
+    /// (name, subject type, bbox JSON)
+    pub faces: Vec<(String, String, String)>,
+}
+
+/// Named faces per asset, for writing into the files' metadata. With `person_ids`, only assets
+/// showing one of them, but still with all their named faces so the written regions are complete.
+#[cfg(feature = "facial-recognition")]
+pub fn get_named_faces(conn: &Connection, person_ids: Option<&[i64]>) -> Result<Vec<NamedFaces>> {
+    let mut sql = String::from(
+        "SELECT a.id, a.path, a.mime, a.width, a.height, a.locked, p.name, p.subject_type, f.bbox_json
+         FROM face_embeddings f JOIN persons p ON p.id = f.person_id JOIN assets a ON a.id = f.asset_id
+         WHERE p.name IS NOT NULL AND TRIM(p.name) != ''",
+    );
+    let ids = person_ids.unwrap_or_default();
+    if person_ids.is_some() {
+        sql.push_str(&format!(
+            " AND f.asset_id IN (SELECT asset_id FROM face_embeddings WHERE person_id IN ({}))",
+            vec!["?"; ids.len()].join(",")
+        ));
+    }
+    sql.push_str(" ORDER BY a.id, f.id");
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(ids))?;
+    let mut out: Vec<NamedFaces> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let asset_id: i64 = row.get(0)?;
+        if out.last().map(|a| a.asset_id) != Some(asset_id) {
+            out.push(NamedFaces {
+                asset_id,
+                path: row.get(1)?,
+                mime: row.get(2)?,
+                width: row.get(3)?,
+                height: row.get(4)?,
+                locked: row.get::<_, i64>(5)? != 0,
+                faces: Vec::new(),
+            });
+        }
+        if let Some(asset) = out.last_mut() {
+            asset.faces.push((row.get(6)?, row.get(7)?, row.get(8)?));
+        }
+    }
+    Ok(out)
+}
+
+/// Whether a face is currently assigned to the person
+#[cfg(feature = "facial-recognition")]
+pub fn face_belongs_to(conn: &Connection, face_id: i64, person_id: i64) -> Result<bool> {
+    let found: Option<i64> = conn
+        .query_row("SELECT 1 FROM face_embeddings WHERE id = ?1 AND person_id = ?2", params![face_id, person_id], |row| row.get(0))
+        .optional()?;
+    Ok(found.is_some())
 }
 
 #[cfg(feature = "facial-recognition")]
@@ -639,6 +1538,28 @@ pub fn get_all_face_embeddings(conn: &Connection) -> Result<Vec<FaceEmbeddingRow
     Ok(embeddings)
 }
 
+/// Ids of every stored person face; pet faces aren't in the face index
+#[cfg(feature = "facial-recognition")]
+pub fn face_embedding_ids(conn: &Connection) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare("SELECT id FROM face_embeddings WHERE subject_type = 'person' ORDER BY id")?;
+    let ids = stmt.query_map([], |row| row.get(0))?.collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(ids)
+}
+
+/// Decoded embeddings of the given faces; missing faces and unreadable blobs are left out
+#[cfg(feature = "facial-recognition")]
+pub fn get_face_embeddings_by_id(conn: &Connection, ids: &[i64]) -> Result<Vec<(i64, Vec<f32>)>> {
+    let mut stmt = conn.prepare("SELECT embedding_blob FROM face_embeddings WHERE id = ?")?;
+    let mut out = Vec::with_capacity(ids.len());
+    for &id in ids {
+        let blob: Option<Vec<u8>> = stmt.query_row(params![id], |row| row.get(0)).optional()?;
+        if let Some(embedding) = blob.and_then(|b| decode_embedding_blob(&b).ok()) {
+            out.push((id, embedding));
+        }
+    }
+    Ok(out)
+}
+
 #[cfg(feature = "facial-recognition")]
 pub fn get_unassigned_faces_with_embeddings(conn: &Connection) -> Result<Vec<UnassignedFace>> {
     let mut stmt = conn.prepare("SELECT id, asset_id, embedding_blob, confidence, bbox_json FROM face_embeddings WHERE person_id IS NULL ORDER BY id")?;
@@ -650,7 +1571,29 @@ pub fn get_unassigned_faces_with_embeddings(conn: &Connection) -> Result<Vec<Una
     Ok(out)
 }
 
+/// The `limit` most recently detected unassigned faces `scope` can see, with their embeddings
+#[cfg(feature = "facial-recognition")]
+pub fn get_recent_unassigned_faces_with_embeddings(conn: &Connection, limit: i64, scope: AccessScope) -> Result<Vec<UnassignedFace>> {
+    let filter_sql = scope.face_condition("").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, asset_id, embedding_blob, confidence, bbox_json FROM face_embeddings WHERE person_id IS NULL {} ORDER BY id DESC LIMIT ?",
+        filter_sql
+    ))?;
+    let rows = stmt.query_map(params![limit], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+    })?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
 #[cfg(feature = "facial-recognition")]
+pub fn count_unassigned_faces(conn: &Connection, scope: AccessScope) -> Result<i64> {
+    let filter_sql = scope.face_condition("").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let count = conn.query_row(&format!("SELECT COUNT(*) FROM face_embeddings WHERE person_id IS NULL {}", filter_sql), [], |row| row.get(0))?;
+    Ok(count)
+}
+
 pub fn decode_embedding_blob(blob: &[u8]) -> Result<Vec<f32>> {
     // Convert bytes back to f32 (little-endian)
     if !blob.len().is_multiple_of(4) {
@@ -664,18 +1607,22 @@ pub fn decode_embedding_blob(blob: &[u8]) -> Result<Vec<f32>> {
     Ok(embeddings)
 }
 
+/// A person's face embeddings, each with whether a user confirmed it
 #[cfg(feature = "facial-recognition")]
-pub fn get_person_face_embeddings(conn: &Connection, person_id: i64) -> Result<Vec<Vec<f32>>> {
-    let mut stmt = conn.prepare("SELECT embedding_blob FROM face_embeddings WHERE person_id = ?")?;
+pub fn get_person_face_embeddings(conn: &Connection, person_id: i64) -> Result<Vec<(Vec<f32>, bool)>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.embedding_blob,
+                EXISTS(SELECT 1 FROM face_feedback ff WHERE ff.face_id = f.id AND ff.person_id = f.person_id AND ff.verdict > 0)
+         FROM face_embeddings f WHERE f.person_id = ?",
+    )?;
     let rows = stmt.query_map(params![person_id], |row| {
-        let blob: Vec<u8> = row.get(0)?;
-        Ok(blob)
+        Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, bool>(1)?))
     })?;
     let mut embeddings = Vec::new();
     for row in rows {
-        let blob = row?;
+        let (blob, confirmed) = row?;
         match decode_embedding_blob(&blob) {
-            Ok(embedding) => embeddings.push(embedding),
+            Ok(embedding) => embeddings.push((embedding, confirmed)),
             Err(e) => {
                 tracing::warn!("Failed to decode embedding for person {}: {}", person_id, e);
                 continue;
@@ -685,6 +1632,56 @@ pub fn get_person_face_embeddings(conn: &Connection, person_id: i64) -> Result<V
     Ok(embeddings)
 }
 
+/// Persons that are pets
+#[cfg(feature = "facial-recognition")]
+pub fn get_pet_person_ids(conn: &Connection) -> Result<std::collections::HashSet<i64>> {
+    let mut stmt = conn.prepare("SELECT id FROM persons WHERE subject_type = 'pet'")?;
+    let ids = stmt.query_map([], |row| row.get(0))?.collect::<std::result::Result<_, _>>()?;
+    Ok(ids)
+}
+
+/// Faces that belong to pets
+#[cfg(feature = "facial-recognition")]
+pub fn get_pet_face_ids(conn: &Connection) -> Result<std::collections::HashSet<i64>> {
+    let mut stmt = conn.prepare("SELECT id FROM face_embeddings WHERE subject_type = 'pet'")?;
+    let ids = stmt.query_map([], |row| row.get(0))?.collect::<std::result::Result<_, _>>()?;
+    Ok(ids)
+}
+
+/// Embeddings of faces users said are not the given person, keyed by that person
+#[cfg(feature = "facial-recognition")]
+pub fn get_person_rejections(conn: &Connection) -> Result<Vec<(i64, Vec<f32>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT ff.person_id, f.embedding_blob FROM face_feedback ff JOIN face_embeddings f ON f.id = ff.face_id WHERE ff.verdict < 0",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)))?;
+    let mut out = Vec::new();
+    for row in rows {
+        let (person_id, blob) = row?;
+        if let Ok(embedding) = decode_embedding_blob(&blob) {
+            out.push((person_id, embedding));
+        }
+    }
+    Ok(out)
+}
+
+/// Pairs of persons (smaller id first) that a rejection says are different people: a face was
+/// rejected for one of them and now belongs to the other
+#[cfg(feature = "facial-recognition")]
+pub fn get_distinct_person_pairs(conn: &Connection) -> Result<std::collections::HashSet<(i64, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT ff.person_id, f.person_id FROM face_feedback ff JOIN face_embeddings f ON f.id = ff.face_id
+         WHERE ff.verdict < 0 AND f.person_id IS NOT NULL AND f.person_id != ff.person_id",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+    let mut pairs = std::collections::HashSet::new();
+    for row in rows {
+        let (a, b) = row?;
+        pairs.insert((a.min(b), a.max(b)));
+    }
+    Ok(pairs)
+}
+
 #[cfg(feature = "facial-recognition")]
 pub fn get_person_centroid(conn: &Connection, person_id: i64) -> Result<Option<Vec<f32>>> {
     let mut stmt = conn.prepare("SELECT centroid_blob FROM person_profiles WHERE person_id = ?")?;
@@ -704,6 +1701,22 @@ pub fn get_person_centroid(conn: &Connection, person_id: i64) -> Result<Option<V
     }
 }
 
+/// Stored centroids of every person profile
+#[cfg(feature = "facial-recognition")]
+pub fn get_person_centroids(conn: &Connection) -> Result<Vec<(i64, Vec<f32>)>> {
+    let mut stmt = conn.prepare("SELECT pp.person_id, pp.centroid_blob FROM person_profiles pp JOIN persons p ON p.id = pp.person_id")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)))?;
+    let mut centroids = Vec::new();
+    for row in rows {
+        let (person_id, blob) = row?;
+        match decode_embedding_blob(&blob) {
+            Ok(centroid) => centroids.push((person_id, centroid)),
+            Err(e) => tracing::warn!("Failed to decode centroid for person {}: {}", person_id, e),
+        }
+    }
+    Ok(centroids)
+}
+
 /// List all albums (basic info only, without asset IDs)
 pub fn list_albums(conn: &Connection) -> Result<Vec<AlbumInfo>> {
     let mut stmt = conn.prepare("SELECT id, name, description, created_at, updated_at FROM albums ORDER BY updated_at DESC")?;
@@ -724,21 +1737,26 @@ pub fn list_albums(conn: &Connection) -> Result<Vec<AlbumInfo>> {
 }
 
 /// List all albums with their asset IDs in a single query (no N+1)
-/// Uses GROUP_CONCAT to fetch all asset IDs for each album in one pass
-pub fn list_albums_with_assets(conn: &Connection) -> Result<Vec<AlbumDetail>> {
-    let mut stmt = conn.prepare(
+/// Uses GROUP_CONCAT to fetch all asset IDs for each album in one pass;
+/// smart albums are evaluated from their rules instead
+pub fn list_albums_with_assets(conn: &Connection, scope: AccessScope) -> Result<Vec<AlbumDetail>> {
+    let where_sql = scope.album_condition("a.").map(|c| format!("WHERE {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
         "SELECT a.id, a.name, a.description, a.created_at, a.updated_at,
-                GROUP_CONCAT(aa.asset_id) as asset_ids
+                GROUP_CONCAT(aa.asset_id) as asset_ids, a.rules_json
          FROM albums a
          LEFT JOIN album_assets aa ON a.id = aa.album_id
+         {}
          GROUP BY a.id
-         ORDER BY a.updated_at DESC"
-    )?;
+         ORDER BY a.updated_at DESC",
+        where_sql
+    ))?;
     let rows = stmt.query_map([], |row| {
         let asset_ids_str: Option<String> = row.get(5).ok();
         let asset_ids: Vec<i64> = asset_ids_str
             .map(|s| s.split(',').filter_map(|id| id.parse().ok()).collect())
             .unwrap_or_default();
+        let rules_json: Option<String> = row.get(6).ok().flatten();
         Ok((
             row.get(0)?,
             row.get(1)?,
@@ -746,19 +1764,26 @@ pub fn list_albums_with_assets(conn: &Connection) -> Result<Vec<AlbumDetail>> {
             row.get(3)?,
             row.get(4)?,
             asset_ids,
+            rules_json,
         ))
     })?;
     let mut albums = Vec::new();
     for row in rows {
-        albums.push(row?);
+        let (id, name, description, created_at, updated_at, asset_ids, rules_json) = row?;
+        let rules = parse_album_rules(rules_json.as_deref());
+        let asset_ids = match &rules {
+            Some(r) => smart_album_asset_ids(conn, r)?,
+            None => asset_ids,
+        };
+        albums.push((id, name, description, created_at, updated_at, asset_ids, rules));
     }
     Ok(albums)
 }
 
-/// Get a single album with its asset IDs
+/// Get a single album with its asset IDs (evaluating rules for smart albums)
 pub fn get_album(conn: &Connection, album_id: i64) -> Result<Option<AlbumDetail>> {
     // Get album info
-    let mut stmt = conn.prepare("SELECT id, name, description, created_at, updated_at FROM albums WHERE id = ?1")?;
+    let mut stmt = conn.prepare("SELECT id, name, description, created_at, updated_at, rules_json FROM albums WHERE id = ?1")?;
     let album_info = stmt.query_row(params![album_id], |row| {
         Ok((
             row.get(0)?,
@@ -766,10 +1791,16 @@ pub fn get_album(conn: &Connection, album_id: i64) -> Result<Option<AlbumDetail>
             row.get(2).ok(),
             row.get(3)?,
             row.get(4)?,
+            row.get::<_, Option<String>>(5).ok().flatten(),
         ))
     }).optional()?;
     
-    if let Some((id, name, description, created_at, updated_at)) = album_info {
+    if let Some((id, name, description, created_at, updated_at, rules_json)) = album_info {
+        let rules = parse_album_rules(rules_json.as_deref());
+        if let Some(r) = rules {
+            let asset_ids = smart_album_asset_ids(conn, &r)?;
+            return Ok(Some((id, name, description, created_at, updated_at, asset_ids, Some(r))));
+        }
         // Get asset IDs for this album
         let mut asset_stmt = conn.prepare("SELECT asset_id FROM album_assets WHERE album_id = ?1 ORDER BY asset_id")?;
         let asset_rows = asset_stmt.query_map(params![album_id], |row| {
@@ -779,15 +1810,182 @@ pub fn get_album(conn: &Connection, album_id: i64) -> Result<Option<AlbumDetail>
         for row in asset_rows {
             asset_ids.push(row?);
         }
-        Ok(Some((id, name, description, created_at, updated_at, asset_ids)))
+        Ok(Some((id, name, description, created_at, updated_at, asset_ids, None)))
     } else {
         Ok(None)
     }
 }
 
-/// Get all albums that contain a specific asset
-pub fn get_albums_for_asset(conn: &Connection, asset_id: i64) -> Result<Vec<i64>> {
-    let mut stmt = conn.prepare("SELECT album_id FROM album_assets WHERE asset_id = ?1")?;
+/// Count, size, date range, top people and top folders across an album's assets
+pub fn album_stats(conn: &Connection, asset_ids: &[i64], top: usize) -> Result<AlbumStats> {
+    let mut stats = AlbumStats::default();
+    let mut places: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+
+    for chunk in asset_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(",");
+        let sql = format!("SELECT size_bytes, taken_at, dirname FROM assets WHERE id IN ({})", placeholders);
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(chunk.iter()))?;
+        while let Some(row) = rows.next()? {
+            stats.asset_count += 1;
+            stats.total_bytes += row.get::<_, i64>(0)?;
+            if let Some(t) = row.get::<_, Option<i64>>(1)? {
+                stats.date_from = Some(stats.date_from.map_or(t, |f| f.min(t)));
+                stats.date_to = Some(stats.date_to.map_or(t, |f| f.max(t)));
+            }
+            *places.entry(row.get::<_, String>(2)?).or_insert(0) += 1;
+        }
+    }
+    stats.top_people = album_people(conn, asset_ids, Some(top))?;
+
+    let mut places: Vec<(String, i64)> = places.into_iter().collect();
+    places.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    stats.places = places
+        .into_iter()
+        .take(top)
+        .map(|(dirname, asset_count)| {
+            let name = std::path::Path::new(&dirname)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| dirname.clone());
+            AlbumPlaceCount { name, dirname, asset_count }
+        })
+        .collect();
+    Ok(stats)
+}
+
+/// Scoring inputs for picking the best shots among `asset_ids`
+pub fn highlight_candidates(conn: &Connection, asset_ids: &[i64]) -> Result<Vec<HighlightCandidateRow>> {
+    let mut out = Vec::with_capacity(asset_ids.len());
+    for chunk in asset_ids.chunks(500) {
+        let sql = format!(
+            "SELECT a.id, a.sha256, a.taken_at, a.rating, a.favorite,
+                    (SELECT COUNT(*) FROM face_embeddings f WHERE f.asset_id = a.id)
+             FROM assets a WHERE a.id IN ({})",
+            vec!["?"; chunk.len()].join(",")
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(chunk.iter()), |row| {
+            let sha: Option<Vec<u8>> = row.get(1)?;
+            Ok((row.get(0)?, sha.map(hex::encode), row.get(2)?, row.get(3)?, row.get::<_, i64>(4)? != 0, row.get(5)?))
+        })?;
+        for row in rows {
+            out.push(row?);
+        }
+    }
+    Ok(out)
+}
+
+/// Persons detected in the given assets, most frequent first, each with the number of assets
+/// they appear in and their most confident face among those assets
+pub fn album_people(conn: &Connection, asset_ids: &[i64], limit: Option<usize>) -> Result<Vec<AlbumPersonCount>> {
+    // person_id -> (asset_count, best confidence, best face id)
+    let mut people: std::collections::HashMap<i64, (i64, f64, i64)> = std::collections::HashMap::new();
+    for chunk in asset_ids.chunks(500) {
+        // Chunks hold disjoint assets, so per-chunk distinct counts add up.
+        // SQLite fills the bare `id` column from the row that produced MAX(confidence).
+        let sql = format!(
+            "SELECT person_id, COUNT(DISTINCT asset_id), MAX(confidence), id FROM face_embeddings
+             WHERE person_id IS NOT NULL AND asset_id IN ({}) GROUP BY person_id",
+            vec!["?"; chunk.len()].join(",")
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(chunk.iter()))?;
+        while let Some(row) = rows.next()? {
+            let (count, conf, face_id): (i64, f64, i64) = (row.get(1)?, row.get(2)?, row.get(3)?);
+            let entry = people.entry(row.get(0)?).or_insert((0, f64::MIN, face_id));
+            entry.0 += count;
+            if conf > entry.1 {
+                entry.1 = conf;
+                entry.2 = face_id;
+            }
+        }
+    }
+
+    let mut people: Vec<(i64, (i64, f64, i64))> = people.into_iter().collect();
+    people.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.0.cmp(&b.0)));
+    let mut name_stmt = conn.prepare("SELECT name FROM persons WHERE id = ?1")?;
+    let mut result = Vec::new();
+    for (person_id, (asset_count, _, face_id)) in people.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        let name: Option<String> = name_stmt.query_row(params![person_id], |r| r.get(0)).optional()?.flatten();
+        result.push(AlbumPersonCount { person_id, name, asset_count, face_id });
+    }
+    Ok(result)
+}
+
+fn parse_album_rules(rules_json: Option<&str>) -> Option<SmartAlbumRules> {
+    let raw = rules_json?;
+    match serde_json::from_str::<SmartAlbumRules>(raw) {
+        Ok(r) => Some(r),
+        Err(e) => {
+            tracing::warn!("Ignoring invalid smart album rules {:?}: {}", raw, e);
+            None
+        }
+    }
+}
+
+/// Evaluate smart album rules into the matching asset IDs (newest first)
+pub fn smart_album_asset_ids(conn: &Connection, rules: &SmartAlbumRules) -> Result<Vec<i64>> {
+    let mut where_clauses: Vec<&str> = Vec::new();
+    let mut params_vec: Vec<rusqlite::types::Value> = Vec::new();
+
+    if let Some(f) = rules.from { where_clauses.push("taken_at >= ?"); params_vec.push(f.into()); }
+    if let Some(t) = rules.to { where_clauses.push("taken_at <= ?"); params_vec.push(t.into()); }
+    if let Some(m) = &rules.camera_make { where_clauses.push("camera_make = ?"); params_vec.push(m.clone().into()); }
+    if let Some(m) = &rules.camera_model { where_clauses.push("camera_model = ?"); params_vec.push(m.clone().into()); }
+    if let Some(tag) = &rules.tag {
+        where_clauses.push("id IN (SELECT at.asset_id FROM asset_tags at JOIN tags t ON t.id = at.tag_id WHERE t.name = ?)");
+        params_vec.push(tag.trim().to_string().into());
+    }
+    if let Some(pid) = rules.person_id {
+        where_clauses.push("id IN (SELECT asset_id FROM face_embeddings WHERE person_id = ?)");
+        params_vec.push(pid.into());
+    }
+    if let Some(mime) = &rules.mime {
+        let mime = mime.trim().to_lowercase();
+        if mime.contains('/') {
+            where_clauses.push("mime = ?");
+            params_vec.push(mime.into());
+        } else {
+            where_clauses.push("mime LIKE ?");
+            params_vec.push(format!("{}/%", mime).into());
+        }
+    }
+
+    let where_sql = if where_clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", where_clauses.join(" AND "))
+    };
+    let sql = format!("SELECT id FROM assets {} ORDER BY taken_at DESC NULLS LAST, id DESC", where_sql);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params_vec), |row| row.get::<_, i64>(0))?;
+    let mut ids = Vec::new();
+    for row in rows {
+        ids.push(row?);
+    }
+    Ok(ids)
+}
+
+/// Whether an album is a smart album, or None if it doesn't exist
+pub fn album_is_smart(conn: &Connection, album_id: i64) -> Result<Option<bool>> {
+    let smart = conn
+        .query_row(
+            "SELECT rules_json IS NOT NULL FROM albums WHERE id = ?1",
+            params![album_id],
+            |row| row.get::<_, bool>(0),
+        )
+        .optional()?;
+    Ok(smart)
+}
+
+/// Get the albums visible to `scope` that contain a specific asset
+pub fn get_albums_for_asset(conn: &Connection, asset_id: i64, scope: AccessScope) -> Result<Vec<i64>> {
+    let filter_sql = scope.album_condition("a.").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT aa.album_id FROM album_assets aa JOIN albums a ON a.id = aa.album_id WHERE aa.asset_id = ?1 {}",
+        filter_sql
+    ))?;
     let rows = stmt.query_map(params![asset_id], |row| {
         row.get::<_, i64>(0)
     })?;
@@ -798,6 +1996,590 @@ pub fn get_albums_for_asset(conn: &Connection, asset_id: i64) -> Result<Vec<i64>
     Ok(album_ids)
 }
 
+/// List the stacks with a cover `scope` can see as (id, cover_asset_id, asset_count, burst_key, asset_ids),
+/// largest first
+pub fn list_stacks(conn: &Connection, offset: i64, limit: i64, scope: AccessScope) -> Result<Paged<StackInfo>> {
+    let where_sql = scope
+        .asset_condition("")
+        .map(|c| format!("WHERE s.cover_asset_id IN (SELECT id FROM assets WHERE {})", c))
+        .unwrap_or_default();
+    let member_sql = scope.asset_condition("a.").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let total: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM stacks s {}", where_sql), [], |r| r.get(0))?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT s.id, s.cover_asset_id, s.asset_count, s.burst_key, GROUP_CONCAT(a.id)
+         FROM stacks s
+         LEFT JOIN assets a ON a.stack_id = s.id {}
+         {}
+         GROUP BY s.id
+         ORDER BY s.asset_count DESC, s.id
+         LIMIT ?1 OFFSET ?2",
+        member_sql, where_sql
+    ))?;
+    let rows = stmt.query_map(params![limit, offset], |row| {
+        let ids: Option<String> = row.get(4)?;
+        let mut asset_ids: Vec<i64> = ids
+            .map(|s| s.split(',').filter_map(|id| id.parse().ok()).collect())
+            .unwrap_or_default();
+        asset_ids.sort_unstable();
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, asset_ids))
+    })?;
+    let mut items = Vec::new();
+    for row in rows {
+        items.push(row?);
+    }
+    Ok(Paged { total, items })
+}
+
+/// Condition on pending_deletes: holds of assets `scope` could edit before they were deleted
+fn pending_delete_condition(scope: AccessScope) -> Option<String> {
+    scope.owner_condition("json_extract(snapshot_json, '$.asset.owner_id')")
+}
+
+/// List the held deletes `scope` may undo as (id, asset_id, original_path, held_path, delete_after, created_at),
+/// soonest first
+pub fn list_pending_deletes(conn: &Connection, scope: AccessScope) -> Result<Vec<PendingDeleteInfo>> {
+    let where_sql = pending_delete_condition(scope).map(|c| format!("WHERE {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, asset_id, original_path, held_path, delete_after, created_at FROM pending_deletes {} ORDER BY delete_after, id",
+        where_sql
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+    })?;
+    let mut items = Vec::new();
+    for row in rows {
+        items.push(row?);
+    }
+    Ok(items)
+}
+
+/// Number of held deletes `scope` may undo and the total size of their originals, i.e. what purging
+/// them frees
+pub fn pending_delete_totals(conn: &Connection, scope: AccessScope) -> Result<(i64, i64)> {
+    let where_sql = pending_delete_condition(scope).map(|c| format!("WHERE {}", c)).unwrap_or_default();
+    let totals = conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM(json_extract(snapshot_json, '$.asset.size_bytes')), 0) FROM pending_deletes {}",
+            where_sql
+        ),
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(totals)
+}
+
+/// Get a held delete `scope` may undo as (original_path, held_path, snapshot_json)
+pub fn get_pending_delete(conn: &Connection, id: i64, scope: AccessScope) -> Result<Option<(String, String, String)>> {
+    let filter_sql = pending_delete_condition(scope).map(|c| format!("AND {}", c)).unwrap_or_default();
+    let row = conn
+        .query_row(
+            &format!("SELECT original_path, held_path, snapshot_json FROM pending_deletes WHERE id = ?1 {}", filter_sql),
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    Ok(row)
+}
+
+/// Held deletes whose undo window has passed, as (id, held_path, sha256)
+pub fn due_pending_deletes(conn: &Connection, now: i64) -> Result<Vec<DuePendingDelete>> {
+    let mut stmt = conn.prepare("SELECT id, held_path, sha256 FROM pending_deletes WHERE delete_after <= ?1")?;
+    let rows = stmt.query_map(params![now], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    let mut items = Vec::new();
+    for row in rows {
+        items.push(row?);
+    }
+    Ok(items)
+}
+
+/// List API tokens as (id, name, created_at, last_used_at); `user_id` limits the list to one user's tokens
+pub fn list_api_tokens(conn: &Connection, user_id: Option<i64>) -> Result<Vec<ApiTokenInfo>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, created_at, last_used_at FROM tokens WHERE ?1 IS NULL OR user_id = ?1 ORDER BY id",
+    )?;
+    let rows = stmt.query_map(params![user_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+    let mut items = Vec::new();
+    for row in rows {
+        items.push(row?);
+    }
+    Ok(items)
+}
+
+/// Assets with a capture date the scope can see, oldest first; `hide_received` leaves out media
+/// received through messaging apps
+pub fn dated_assets(conn: &Connection, scope: AccessScope, hide_received: bool) -> Result<Vec<crate::pipeline::memories::DatedAsset>> {
+    let mut conds = vec!["taken_at IS NOT NULL".to_string()];
+    if hide_received {
+        conds.push("source_app IS NULL".to_string());
+    }
+    conds.extend(scope.asset_condition(""));
+    let mut stmt = conn.prepare(&format!("SELECT id, taken_at, dirname FROM assets WHERE {} ORDER BY taken_at, id", conds.join(" AND ")))?;
+    let rows = stmt.query_map([], |row| {
+        Ok(crate::pipeline::memories::DatedAsset { id: row.get(0)?, taken_at: row.get(1)?, dirname: row.get(2)? })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// The most recently indexed assets the scope can see, newest first
+pub fn recent_assets(conn: &Connection, scope: AccessScope, limit: usize) -> Result<Vec<crate::utils::feed::RecentAsset>> {
+    let filter_sql = scope.asset_condition("").map(|c| format!("WHERE {}", c)).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, filename, dirname, mime, width, height, taken_at, mtime_ns FROM assets {} ORDER BY id DESC LIMIT ?1",
+        filter_sql
+    ))?;
+    let rows = stmt.query_map(params![limit as i64], |row| {
+        Ok(crate::utils::feed::RecentAsset {
+            id: row.get(0)?,
+            filename: row.get(1)?,
+            dirname: row.get(2)?,
+            mime: row.get(3)?,
+            width: row.get(4)?,
+            height: row.get(5)?,
+            taken_at: row.get(6)?,
+            modified_at: row.get::<_, i64>(7)? / 1_000_000_000,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// (id, user_id) of the API token with this hash, if any
+pub fn find_api_token(conn: &Connection, token_hash: &[u8]) -> Result<Option<(i64, Option<i64>)>> {
+    let token = conn
+        .query_row("SELECT id, user_id FROM tokens WHERE token_hash = ?1", params![token_hash], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .optional()?;
+    Ok(token)
+}
+
+/// Some(user_id) for a live session; the user id is None for admin-password sessions
+pub fn find_session(conn: &Connection, token_hash: &[u8], now: i64) -> Result<Option<Option<i64>>> {
+    let found = conn
+        .query_row(
+            "SELECT user_id FROM sessions WHERE token_hash = ?1 AND expires_at > ?2",
+            params![token_hash, now],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(found)
+}
+
+/// List users as (id, username, is_admin, created_at)
+pub fn list_users(conn: &Connection) -> Result<Vec<UserInfo>> {
+    let mut stmt = conn.prepare("SELECT id, username, is_admin, created_at FROM users ORDER BY username")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? != 0, row.get(3)?)))?;
+    let mut users = Vec::new();
+    for row in rows {
+        users.push(row?);
+    }
+    Ok(users)
+}
+
+pub fn get_user(conn: &Connection, user_id: i64) -> Result<Option<UserInfo>> {
+    let user = conn
+        .query_row(
+            "SELECT id, username, is_admin, created_at FROM users WHERE id = ?1",
+            params![user_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? != 0, row.get(3)?)),
+        )
+        .optional()?;
+    Ok(user)
+}
+
+/// (id, password_hash, is_admin) for a username (case-insensitive)
+pub fn get_user_credentials(conn: &Connection, username: &str) -> Result<Option<UserCredentials>> {
+    let user = conn
+        .query_row(
+            "SELECT id, password_hash, is_admin FROM users WHERE username = ?1",
+            params![username],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? != 0)),
+        )
+        .optional()?;
+    Ok(user)
+}
+
+/// Whether an album is a smart album. Those can't be shared: a user only sees another user's
+/// assets through album_assets rows, which smart albums don't have.
+pub fn is_smart_album(conn: &Connection, album_id: i64) -> Result<bool> {
+    let rules: Option<Option<String>> = conn
+        .query_row("SELECT rules_json FROM albums WHERE id = ?1", params![album_id], |r| r.get(0))
+        .optional()?;
+    Ok(parse_album_rules(rules.flatten().as_deref()).is_some())
+}
+
+/// Users an album is shared with, as (user_id, username)
+pub fn list_album_shares(conn: &Connection, album_id: i64) -> Result<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT u.id, u.username FROM album_shares s JOIN users u ON u.id = s.user_id
+         WHERE s.album_id = ?1 ORDER BY u.username",
+    )?;
+    let rows = stmt.query_map(params![album_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    let mut shares = Vec::new();
+    for row in rows {
+        shares.push(row?);
+    }
+    Ok(shares)
+}
+
+fn row_matches(conn: &Connection, table: &str, id: i64, condition: Option<String>) -> Result<bool> {
+    let sql = match condition {
+        Some(c) => format!("SELECT 1 FROM {} WHERE id = ?1 AND {}", table, c),
+        None => format!("SELECT 1 FROM {} WHERE id = ?1", table),
+    };
+    let found: Option<i64> = conn.query_row(&sql, params![id], |row| row.get(0)).optional()?;
+    Ok(found.is_some())
+}
+
+/// True if the asset exists and `scope` may see it
+pub fn can_view_asset(conn: &Connection, asset_id: i64, scope: AccessScope) -> Result<bool> {
+    row_matches(conn, "assets", asset_id, scope.asset_condition(""))
+}
+
+/// True if the asset exists and `scope` owns it (or it is shared with everyone)
+pub fn can_edit_asset(conn: &Connection, asset_id: i64, scope: AccessScope) -> Result<bool> {
+    row_matches(conn, "assets", asset_id, scope.owner_condition("owner_id"))
+}
+
+pub fn can_view_album(conn: &Connection, album_id: i64, scope: AccessScope) -> Result<bool> {
+    row_matches(conn, "albums", album_id, scope.album_condition(""))
+}
+
+/// Albums shared with a user are read-only for them
+pub fn can_edit_album(conn: &Connection, album_id: i64, scope: AccessScope) -> Result<bool> {
+    row_matches(conn, "albums", album_id, scope.owner_condition("owner_id"))
+}
+
+/// True if the scan path exists and `scope` owns it (or it is shared with everyone)
+pub fn can_edit_scan_path(conn: &Connection, path: &str, scope: AccessScope) -> Result<bool> {
+    let filter_sql = scope.owner_condition("owner_id").map(|c| format!("AND {}", c)).unwrap_or_default();
+    let found: Option<i64> = conn
+        .query_row(&format!("SELECT 1 FROM scan_paths WHERE path = ?1 {}", filter_sql), params![path], |row| row.get(0))
+        .optional()?;
+    Ok(found.is_some())
+}
+
+/// Why `path` can't be added as a scan path
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScanPathClash {
+    /// The path is already a scan path
+    Exists,
+    /// The path contains, or lies inside, a scan path `scope` doesn't own
+    Overlaps(String),
+}
+
+/// True if `path` is `root` or lies below it
+fn path_within(path: &str, root: &str) -> bool {
+    let path = path.trim_end_matches(['/', '\\']);
+    let root = root.trim_end_matches(['/', '\\']);
+    path == root
+        || path.strip_prefix(root).is_some_and(|rest| rest.starts_with(['/', '\\']))
+}
+
+/// Check `path` before adding it for `scope`. Restricted scopes may not overlap a path
+/// they don't own, since adding it would hand them the assets already indexed there.
+pub fn scan_path_clash(conn: &Connection, path: &str, scope: AccessScope) -> Result<Option<ScanPathClash>> {
+    let mut stmt = conn.prepare("SELECT path, owner_id FROM scan_paths ORDER BY created_at")?;
+    let existing = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    if existing.iter().any(|(p, _)| p.trim_end_matches(['/', '\\']) == trimmed) {
+        return Ok(Some(ScanPathClash::Exists));
+    }
+    let owns = |owner: Option<i64>| match scope {
+        AccessScope::All => true,
+        AccessScope::User(id) => owner == Some(id),
+        AccessScope::Public => false,
+    };
+    Ok(existing
+        .into_iter()
+        .find(|(p, owner)| !owns(*owner) && (path_within(path, p) || path_within(p, path)))
+        .map(|(p, _)| ScanPathClash::Overlaps(p)))
+}
+
+fn filter_assets(conn: &Connection, asset_ids: Vec<i64>, condition: Option<String>) -> Result<Vec<i64>> {
+    let Some(condition) = condition else {
+        return Ok(asset_ids);
+    };
+    let mut allowed = std::collections::HashSet::new();
+    for chunk in asset_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(",");
+        let mut stmt = conn.prepare(&format!("SELECT id FROM assets WHERE id IN ({}) AND {}", placeholders, condition))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(chunk), |row| row.get::<_, i64>(0))?;
+        for row in rows {
+            allowed.insert(row?);
+        }
+    }
+    Ok(asset_ids.into_iter().filter(|id| allowed.contains(id)).collect())
+}
+
+/// Keep only the assets `scope` may see, preserving order
+pub fn filter_visible_assets(conn: &Connection, asset_ids: Vec<i64>, scope: AccessScope) -> Result<Vec<i64>> {
+    filter_assets(conn, asset_ids, scope.asset_condition(""))
+}
+
+/// Keep only the assets `scope` may modify, preserving order
+pub fn filter_editable_assets(conn: &Connection, asset_ids: Vec<i64>, scope: AccessScope) -> Result<Vec<i64>> {
+    filter_assets(conn, asset_ids, scope.owner_condition("owner_id"))
+}
+
+const SHARE_COLUMNS: &str = "id, album_id, asset_id, password_hash, expires_at, created_by, created_at";
+
+fn row_to_share(row: &Row<'_>) -> rusqlite::Result<(ShareLink, Option<String>)> {
+    let password_hash: Option<String> = row.get(3)?;
+    let link = ShareLink {
+        id: row.get(0)?,
+        album_id: row.get(1)?,
+        asset_id: row.get(2)?,
+        has_password: password_hash.is_some(),
+        expires_at: row.get(4)?,
+        created_by: row.get(5)?,
+        created_at: row.get(6)?,
+    };
+    Ok((link, password_hash))
+}
+
+/// Share links, newest first; `created_by` restricts the list to one user's links
+pub fn list_shares(conn: &Connection, created_by: Option<i64>) -> Result<Vec<ShareLink>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM shares WHERE ?1 IS NULL OR created_by = ?1 ORDER BY id DESC",
+        SHARE_COLUMNS
+    ))?;
+    let rows = stmt.query_map(params![created_by], row_to_share)?;
+    let mut shares = Vec::new();
+    for row in rows {
+        shares.push(row?.0);
+    }
+    Ok(shares)
+}
+
+/// The share link with this token hash, along with its password hash
+pub fn find_share(conn: &Connection, token_hash: &[u8]) -> Result<Option<(ShareLink, Option<String>)>> {
+    let share = conn
+        .query_row(
+            &format!("SELECT {} FROM shares WHERE token_hash = ?1", SHARE_COLUMNS),
+            params![token_hash],
+            row_to_share,
+        )
+        .optional()?;
+    Ok(share)
+}
+
+fn row_to_kiosk(conn: &Connection, row: &Row<'_>) -> rusqlite::Result<Kiosk> {
+    let id: i64 = row.get(0)?;
+    let mut stmt = conn.prepare_cached("SELECT album_id FROM kiosk_albums WHERE kiosk_id = ?1 ORDER BY position")?;
+    let album_ids = stmt.query_map(params![id], |r| r.get(0))?.collect::<rusqlite::Result<Vec<i64>>>()?;
+    Ok(Kiosk {
+        id,
+        name: row.get(1)?,
+        album_ids,
+        created_by: row.get(2)?,
+        created_at: row.get(3)?,
+        last_used_at: row.get(4)?,
+    })
+}
+
+/// Kiosk tokens, newest first; `created_by` restricts the list to one user's tokens
+pub fn list_kiosks(conn: &Connection, created_by: Option<i64>) -> Result<Vec<Kiosk>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, created_by, created_at, last_used_at FROM kiosks WHERE ?1 IS NULL OR created_by = ?1 ORDER BY id DESC",
+    )?;
+    let rows = stmt.query_map(params![created_by], |row| row_to_kiosk(conn, row))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+fn row_to_rule(row: &Row<'_>) -> rusqlite::Result<ProcessingRule> {
+    let conditions = serde_json::from_str(&row.get::<_, String>(3)?);
+    let actions = serde_json::from_str(&row.get::<_, String>(4)?);
+    // A rule whose JSON no longer parses is listed but treated as disabled
+    let parsed = conditions.is_ok() && actions.is_ok();
+    Ok(ProcessingRule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        enabled: row.get::<_, i64>(2)? != 0 && parsed,
+        conditions: conditions.unwrap_or_default(),
+        actions: actions.unwrap_or_default(),
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+/// Processing rules in the order they were created
+pub fn list_rules(conn: &Connection) -> Result<Vec<ProcessingRule>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, name, enabled, conditions_json, actions_json, created_at, updated_at FROM processing_rules ORDER BY id",
+    )?;
+    let rows = stmt.query_map([], row_to_rule)?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+pub fn get_rule(conn: &Connection, id: i64) -> Result<Option<ProcessingRule>> {
+    let rule = conn
+        .query_row(
+            "SELECT id, name, enabled, conditions_json, actions_json, created_at, updated_at FROM processing_rules WHERE id = ?1",
+            params![id],
+            row_to_rule,
+        )
+        .optional()?;
+    Ok(rule)
+}
+
+/// The kiosk with this token hash
+pub fn find_kiosk(conn: &Connection, token_hash: &[u8]) -> Result<Option<Kiosk>> {
+    let kiosk = conn
+        .query_row(
+            "SELECT id, name, created_by, created_at, last_used_at FROM kiosks WHERE token_hash = ?1",
+            params![token_hash],
+            |row| row_to_kiosk(conn, row),
+        )
+        .optional()?;
+    Ok(kiosk)
+}
+
+/// Assets the kiosk's frame has displayed this rotation, most recent first
+pub fn kiosk_shown(conn: &Connection, kiosk_id: i64) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare("SELECT asset_id FROM kiosk_shown WHERE kiosk_id = ?1 ORDER BY shown_at DESC, rowid DESC")?;
+    let rows = stmt.query_map(params![kiosk_id], |r| r.get(0))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<i64>>>()?)
+}
+
+/// Stored CLIP vectors made with `model`
+pub fn clip_embeddings(conn: &Connection, model: &str) -> Result<Vec<(i64, Vec<f32>)>> {
+    let mut stmt = conn.prepare("SELECT asset_id, embedding FROM clip_embeddings WHERE model = ?1")?;
+    let rows = stmt.query_map(params![model], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, Vec<u8>>(1)?)))?;
+    let mut out = Vec::new();
+    for row in rows {
+        let (asset_id, blob) = row?;
+        out.push((asset_id, decode_embedding_blob(&blob)?));
+    }
+    Ok(out)
+}
+
+/// Images after `after` (by id) with no CLIP vector from `model`, with their hex sha256
+pub fn assets_missing_clip_embedding(conn: &Connection, model: &str, after: i64, limit: usize) -> Result<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.sha256 FROM assets a
+         WHERE a.id > ?1 AND a.mime LIKE 'image/%' AND a.sha256 IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM clip_embeddings c WHERE c.asset_id = a.id AND c.model = ?2)
+         ORDER BY a.id LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(params![after, model, limit as i64], |r| Ok((r.get(0)?, hex::encode(r.get::<_, Vec<u8>>(1)?))))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Images after `after` that OCR hasn't read yet: (id, path, mime, sha256 hex)
+pub fn assets_missing_text(conn: &Connection, after: i64, limit: usize) -> Result<Vec<(i64, String, String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.path, a.mime, a.sha256 FROM assets a
+         WHERE a.id > ?1 AND a.mime LIKE 'image/%' AND a.sha256 IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM asset_text t WHERE t.asset_id = a.id)
+         ORDER BY a.id LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![after, limit as i64], |r| {
+        Ok((r.get(0)?, r.get(1)?, r.get(2)?, hex::encode(r.get::<_, Vec<u8>>(3)?)))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Public details of the given assets, preserving order; unknown ids are skipped
+pub fn get_shared_assets(conn: &Connection, asset_ids: &[i64]) -> Result<Vec<SharedAsset>> {
+    let mut found = std::collections::HashMap::new();
+    for chunk in asset_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(",");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, filename, mime, size_bytes, width, height, duration_ms, taken_at FROM assets WHERE id IN ({})",
+            placeholders
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(chunk), |row| {
+            Ok(SharedAsset {
+                id: row.get(0)?,
+                filename: row.get(1)?,
+                mime: row.get(2)?,
+                size_bytes: row.get(3)?,
+                width: row.get(4)?,
+                height: row.get(5)?,
+                duration_ms: row.get(6)?,
+                taken_at: row.get(7)?,
+            })
+        })?;
+        for row in rows {
+            let asset = row?;
+            found.insert(asset.id, asset);
+        }
+    }
+    Ok(asset_ids.iter().filter_map(|id| found.remove(id)).collect())
+}
+
+/// List all tags with the number of assets carrying each tag
+/// Tags with the number of assets `scope` can see under each. Tags are shared, so a restricted
+/// scope only gets the tags on at least one of its assets.
+pub fn list_tags(conn: &Connection, scope: AccessScope) -> Result<Vec<TagInfo>> {
+    let sql = match scope.asset_condition("a.") {
+        Some(c) => format!(
+            "SELECT t.id, t.name, COUNT(a.id) as asset_count
+             FROM tags t
+             JOIN asset_tags at ON t.id = at.tag_id
+             JOIN assets a ON a.id = at.asset_id AND {}
+             GROUP BY t.id
+             ORDER BY t.name COLLATE NOCASE",
+            c
+        ),
+        None => "SELECT t.id, t.name, COUNT(at.asset_id) as asset_count
+             FROM tags t
+             LEFT JOIN asset_tags at ON t.id = at.tag_id
+             GROUP BY t.id
+             ORDER BY t.name COLLATE NOCASE"
+            .to_string(),
+    };
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })?;
+    let mut tags = Vec::new();
+    for row in rows {
+        tags.push(row?);
+    }
+    Ok(tags)
+}
+
+/// Custom properties of an asset as (key, value) pairs, ordered by key
+pub fn get_asset_properties(conn: &Connection, asset_id: i64) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT key, value FROM asset_properties WHERE asset_id = ?1 ORDER BY key COLLATE NOCASE"
+    )?;
+    let rows = stmt.query_map(params![asset_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Get the tag names attached to a specific asset
+pub fn get_asset_tags(conn: &Connection, asset_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.name FROM asset_tags at
+         JOIN tags t ON t.id = at.tag_id
+         WHERE at.asset_id = ?1
+         ORDER BY t.name COLLATE NOCASE"
+    )?;
+    let rows = stmt.query_map(params![asset_id], |row| {
+        row.get::<_, String>(0)
+    })?;
+    let mut tags = Vec::new();
+    for row in rows {
+        tags.push(row?);
+    }
+    Ok(tags)
+}
+
+/// Predicted tags on an asset with the classifier's confidence, most confident first
+pub fn get_asset_auto_tags(conn: &Connection, asset_id: i64) -> Result<Vec<(String, f64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.name, at.confidence FROM asset_tags at
+         JOIN tags t ON t.id = at.tag_id
+         WHERE at.asset_id = ?1 AND at.confidence IS NOT NULL
+         ORDER BY at.confidence DESC"
+    )?;
+    let rows = stmt.query_map(params![asset_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -821,7 +2603,7 @@ mod tests {
     #[test]
     fn test_list_assets_empty() {
         let (_tmp, conn) = setup_test_db();
-        let result = list_assets(&conn, 0, 10, "none", "desc").unwrap();
+        let result = list_assets(&conn, 0, 10, "none", "desc", ListFilters::default()).unwrap();
         assert_eq!(result.total, 0);
         assert_eq!(result.items.len(), 0);
     }
@@ -842,11 +2624,11 @@ mod tests {
             params!["/test/2.jpg", "/test", "2.jpg", "jpg", 2000, 2000000, 2000000, "image/jpeg", 0]
         ).unwrap();
 
-        let result = list_assets(&conn, 0, 1, "none", "desc").unwrap();
+        let result = list_assets(&conn, 0, 1, "none", "desc", ListFilters::default()).unwrap();
         assert_eq!(result.total, 2);
         assert_eq!(result.items.len(), 1);
         
-        let result = list_assets(&conn, 1, 1, "none", "desc").unwrap();
+        let result = list_assets(&conn, 1, 1, "none", "desc", ListFilters::default()).unwrap();
         assert_eq!(result.total, 2);
         assert_eq!(result.items.len(), 1);
     }
@@ -866,13 +2648,203 @@ mod tests {
             params!["/test/b.jpg", "/test", "b.jpg", "jpg", 2000, 2000000, 2000000, "image/jpeg", 0]
         ).unwrap();
 
-        let result = list_assets(&conn, 0, 10, "filename", "asc").unwrap();
+        let result = list_assets(&conn, 0, 10, "filename", "asc", ListFilters::default()).unwrap();
         assert_eq!(result.items[0].filename, "a.jpg");
         
-        let result = list_assets(&conn, 0, 10, "filename", "desc").unwrap();
+        let result = list_assets(&conn, 0, 10, "filename", "desc", ListFilters::default()).unwrap();
         assert_eq!(result.items[0].filename, "b.jpg");
     }
 
+    #[test]
+    fn test_list_assets_rating_and_favorite() {
+        let (_tmp, conn) = setup_test_db();
+        
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags) VALUES 
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params!["/test/a.jpg", "/test", "a.jpg", "jpg", 1000, 1000000, 1000000, "image/jpeg", 0]
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags) VALUES 
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params!["/test/b.jpg", "/test", "b.jpg", "jpg", 2000, 2000000, 2000000, "image/jpeg", 0]
+        ).unwrap();
+
+        assert!(crate::db::writer::set_asset_rating(&conn, 1, 5).unwrap());
+        assert!(crate::db::writer::set_asset_favorite(&conn, 2, true).unwrap());
+        assert!(!crate::db::writer::set_asset_rating(&conn, 99999, 3).unwrap());
+
+        let result = list_assets(&conn, 0, 10, "rating", "desc", ListFilters::default()).unwrap();
+        assert_eq!(result.items[0].filename, "a.jpg");
+        assert_eq!(result.items[0].rating, 5);
+
+        let result = list_assets(&conn, 0, 10, "none", "desc", ListFilters { favorite: Some(true), ..Default::default() }).unwrap();
+        assert_eq!(result.total, 1);
+        assert_eq!(result.items[0].filename, "b.jpg");
+        assert!(result.items[0].favorite);
+
+        // A user account keeps its own favorites, separate from the instance-wide ones
+        let alice = crate::db::writer::create_user(&conn, "alice", "x", false).unwrap().unwrap();
+        assert!(crate::db::writer::set_user_favorite(&conn, alice, 1, true).unwrap());
+        assert!(!crate::db::writer::set_user_favorite(&conn, alice, 99999, true).unwrap());
+        let mut result = list_assets(&conn, 0, 10, "none", "desc", ListFilters { favorite: Some(true), user_id: Some(alice), ..Default::default() }).unwrap();
+        assert_eq!((result.total, result.items[0].filename.as_str()), (1, "a.jpg"));
+        mark_user_favorites(&conn, Some(alice), &mut result.items).unwrap();
+        assert!(result.items[0].favorite);
+        let mut all = list_assets(&conn, 0, 10, "filename", "asc", ListFilters::default()).unwrap().items;
+        mark_user_favorites(&conn, Some(alice), &mut all).unwrap();
+        assert_eq!(all.iter().map(|a| a.favorite).collect::<Vec<_>>(), vec![true, false]);
+        let others = list_assets(&conn, 0, 10, "none", "desc", ListFilters { favorite: Some(false), user_id: Some(alice), ..Default::default() }).unwrap();
+        assert_eq!(others.total, 1);
+    }
+
+    #[test]
+    fn test_hide_received_media() {
+        let (_tmp, conn) = setup_test_db();
+        for (path, source_app) in [("/test/a.jpg", None), ("/test/IMG-20230514-WA0001.jpg", Some("whatsapp"))] {
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, taken_at, source_app)
+                 VALUES (?1, '/test', ?1, 'jpg', 1, 0, 0, 'image/jpeg', 0, 1680000000, ?2)",
+                params![path, source_app],
+            ).unwrap();
+        }
+        let total = |hide_received| list_assets(&conn, 0, 10, "none", "desc", ListFilters { hide_received, ..Default::default() }).unwrap().total;
+        assert_eq!((total(false), total(true)), (2, 1));
+        assert_eq!(dated_assets(&conn, AccessScope::All, true).unwrap().len(), 1);
+        assert_eq!(dated_assets(&conn, AccessScope::All, false).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_ingested_since() {
+        let (_tmp, conn) = setup_test_db();
+        for (path, ingested_at) in [("/test/a.jpg", Some(100)), ("/test/b.jpg", Some(200)), ("/test/c.jpg", Some(300)), ("/test/d.jpg", None)] {
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, ingested_at)
+                 VALUES (?1, '/test', ?1, 'jpg', 1, 0, 0, 'image/jpeg', 0, ?2)",
+                params![path, ingested_at],
+            ).unwrap();
+        }
+        let page = list_assets(&conn, 0, 1, "ingested_at", "desc", ListFilters { ingested_since: Some(100), ..Default::default() }).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!((page.items[0].path.as_str(), page.items[0].ingested_at), ("/test/c.jpg", Some(300)));
+        assert_eq!(list_assets(&conn, 0, 10, "none", "desc", ListFilters { ingested_since: Some(300), ..Default::default() }).unwrap().total, 0);
+    }
+
+    #[test]
+    fn test_smart_album_rules() {
+        let (_tmp, conn) = setup_test_db();
+
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, taken_at) VALUES 
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params!["/test/clip.mp4", "/test", "clip.mp4", "mp4", 1000, 1000000, 1000000, "video/mp4", 0, 1_680_000_000i64]
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, taken_at) VALUES 
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params!["/test/photo.jpg", "/test", "photo.jpg", "jpg", 1000, 1000000, 1000000, "image/jpeg", 0, 1_680_000_000i64]
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, taken_at) VALUES 
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params!["/test/old.mp4", "/test", "old.mp4", "mp4", 1000, 1000000, 1000000, "video/mp4", 0, 1_500_000_000i64]
+        ).unwrap();
+
+        // "Videos from 2023"
+        let rules = SmartAlbumRules {
+            from: Some(1_672_531_200),
+            to: Some(1_704_067_199),
+            mime: Some("video".to_string()),
+            ..Default::default()
+        };
+        let album_id = crate::db::writer::create_album(&conn, "Videos 2023", None, Some(&rules)).unwrap();
+        let (_, _, _, _, _, asset_ids, stored) = get_album(&conn, album_id).unwrap().unwrap();
+        assert_eq!(asset_ids, vec![1]);
+        assert_eq!(stored, Some(rules));
+
+        // New matching assets show up without touching the album
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, taken_at) VALUES 
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params!["/test/new.mov", "/test", "new.mov", "mov", 1000, 1000000, 1000000, "video/quicktime", 0, 1_690_000_000i64]
+        ).unwrap();
+        let albums = list_albums_with_assets(&conn, AccessScope::All).unwrap();
+        assert_eq!(albums[0].5, vec![4, 1]);
+    }
+
+    #[test]
+    fn test_calendar_counts() {
+        let (_tmp, conn) = setup_test_db();
+        // 2024-03-05 10:00, 2024-03-05 23:59, 2024-12-31 12:00, 2023-06-01 00:00, undated, someone else's
+        for (path, taken, owner) in [
+            ("/p/1.jpg", Some(1709632800i64), None),
+            ("/p/2.jpg", Some(1709683140), None),
+            ("/p/3.jpg", Some(1735646400), None),
+            ("/p/4.jpg", Some(1685577600), None),
+            ("/p/5.jpg", None, None),
+            ("/p/6.jpg", Some(1709632800), Some(2i64)),
+        ] {
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, taken_at, owner_id)
+                 VALUES (?1, '/p', 'x.jpg', 'jpg', 1, 0, 0, 'image/jpeg', 0, ?2, ?3)",
+                params![path, taken, owner],
+            ).unwrap();
+        }
+
+        let stats = calendar_counts(&conn, 2024, AccessScope::User(1)).unwrap();
+        assert_eq!(stats.days, vec![
+            CalendarDay { date: "2024-03-05".to_string(), count: 2 },
+            CalendarDay { date: "2024-12-31".to_string(), count: 1 },
+        ]);
+        assert_eq!((stats.total, stats.max_count), (3, 2));
+        assert_eq!(stats.years, vec![2024, 2023]);
+
+        assert_eq!(calendar_counts(&conn, 2024, AccessScope::All).unwrap().max_count, 3);
+        assert_eq!(calendar_counts(&conn, 2022, AccessScope::All).unwrap().total, 0);
+    }
+
+    #[test]
+    fn test_album_stats() {
+        let (_tmp, conn) = setup_test_db();
+        for (path, dirname, size, taken) in [
+            ("/p/rome/1.jpg", "/p/rome", 100, Some(2000)),
+            ("/p/rome/2.jpg", "/p/rome", 200, Some(1000)),
+            ("/p/paris/3.jpg", "/p/paris", 300, None),
+        ] {
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, taken_at)
+                 VALUES (?1, ?2, 'x.jpg', 'jpg', ?3, 0, 0, 'image/jpeg', 0, ?4)",
+                params![path, dirname, size, taken],
+            ).unwrap();
+        }
+        conn.execute("INSERT INTO persons (id, name, created_at) VALUES (1, 'Ann', 0), (2, NULL, 0)", []).unwrap();
+        conn.execute(
+            "INSERT INTO face_embeddings (asset_id, person_id, embedding_blob, bbox_json, confidence) VALUES
+             (1, 1, x'00', '{}', 0.9), (1, 1, x'00', '{}', 0.9), (2, 1, x'00', '{}', 0.9), (3, 2, x'00', '{}', 0.9)",
+            [],
+        ).unwrap();
+
+        let stats = album_stats(&conn, &[1, 2, 3], 10).unwrap();
+        assert_eq!(stats.asset_count, 3);
+        assert_eq!(stats.total_bytes, 600);
+        assert_eq!((stats.date_from, stats.date_to), (Some(1000), Some(2000)));
+        assert_eq!(stats.top_people[0].person_id, 1);
+        assert_eq!(stats.top_people[0].name.as_deref(), Some("Ann"));
+        assert_eq!(stats.top_people[0].asset_count, 2);
+        assert_eq!(stats.top_people[0].face_id, 1);
+        assert_eq!(stats.places[0].name, "rome");
+        assert_eq!(stats.places[0].asset_count, 2);
+
+        let stats = album_stats(&conn, &[3], 1).unwrap();
+        assert_eq!(stats.top_people.len(), 1);
+        assert_eq!(stats.date_from, None);
+        assert_eq!(album_stats(&conn, &[], 10).unwrap(), AlbumStats::default());
+
+        let people = album_people(&conn, &[1, 2, 3], None).unwrap();
+        assert_eq!(people.iter().map(|p| (p.person_id, p.asset_count)).collect::<Vec<_>>(), vec![(1, 2), (2, 1)]);
+        assert_eq!(people[1].name, None);
+    }
+
     #[test]
     fn test_get_asset_by_id() {
         let (_tmp, conn) = setup_test_db();
@@ -912,6 +2884,167 @@ mod tests {
         assert_eq!(path, None);
     }
 
+    #[cfg(feature = "facial-recognition")]
+    #[test]
+    fn test_person_cover() {
+        let (_tmp, conn) = setup_test_db();
+        for id in 1..=4 {
+            conn.execute(
+                "INSERT INTO assets (id, path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags)
+                 VALUES (?1, ?2, '/p', 'x.jpg', 'jpg', 1, 0, 0, 'image/jpeg', 0)",
+                params![id, format!("/p/{}.jpg", id)],
+            ).unwrap();
+        }
+        conn.execute("INSERT INTO persons (id, name, created_at) VALUES (1, 'Alice', 0), (2, 'Bob', 0)", []).unwrap();
+        let bbox = |w: f32, h: f32| format!(r#"{{"x1":0,"y1":0,"x2":{},"y2":{},"confidence":0.9}}"#, w, h);
+        conn.execute(
+            "INSERT INTO face_embeddings (id, asset_id, person_id, embedding_blob, bbox_json, confidence) VALUES
+             (1, 1, 1, x'00', ?1, 0.95), (2, 2, 1, x'00', ?2, 0.85), (3, 3, 1, x'00', ?3, 0.9), (4, 4, 2, x'00', ?2, 0.9)",
+            params![bbox(30.0, 38.0), bbox(120.0, 150.0), bbox(70.0, 150.0)],
+        ).unwrap();
+
+        // Tiny and turned faces lose to a large frontal one despite their confidence
+        assert_eq!(get_person_representative_face(&conn, 1, AccessScope::All).unwrap(), Some(2));
+
+        crate::db::writer::set_person_cover(&conn, 1, Some(3)).unwrap();
+        assert_eq!(get_person_representative_face(&conn, 1, AccessScope::All).unwrap(), Some(3));
+        assert!(face_belongs_to(&conn, 3, 1).unwrap() && !face_belongs_to(&conn, 4, 1).unwrap());
+
+        // A chosen face that moves to someone else no longer counts
+        conn.execute("UPDATE face_embeddings SET person_id = 2 WHERE id = 3", []).unwrap();
+        assert_eq!(get_person_representative_face(&conn, 1, AccessScope::All).unwrap(), Some(2));
+        crate::db::writer::set_person_cover(&conn, 1, None).unwrap();
+        assert_eq!(get_person_representative_face(&conn, 1, AccessScope::All).unwrap(), Some(2));
+    }
+
+    #[cfg(feature = "facial-recognition")]
+    #[test]
+    fn test_person_collage_faces() {
+        let (_tmp, conn) = setup_test_db();
+        let owner = db::writer::create_user(&conn, "owner", "x", false).unwrap().unwrap();
+        // Asset 3 is private to its owner
+        for id in 1..=3 {
+            conn.execute(
+                "INSERT INTO assets (id, path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, owner_id)
+                 VALUES (?1, ?2, '/p', 'x.jpg', 'jpg', 1, 0, 0, 'image/jpeg', 0, ?3)",
+                params![id, format!("/p/{}.jpg", id), if id == 3 { Some(owner) } else { None }],
+            ).unwrap();
+        }
+        conn.execute("INSERT INTO persons (id, name, created_at) VALUES (1, 'Alice', 0)", []).unwrap();
+        let bbox = |w: f32, h: f32| format!(r#"{{"x1":0,"y1":0,"x2":{},"y2":{},"confidence":0.9}}"#, w, h);
+        // Faces 2 and 5 share an asset; 5 is the better of the two
+        conn.execute(
+            "INSERT INTO face_embeddings (id, asset_id, person_id, embedding_blob, bbox_json, confidence) VALUES
+             (1, 1, 1, x'00', ?1, 0.95), (2, 2, 1, x'00', ?2, 0.85), (3, 3, 1, x'00', ?3, 0.9), (5, 2, 1, x'00', ?2, 0.9)",
+            params![bbox(30.0, 38.0), bbox(100.0, 125.0), bbox(70.0, 150.0)],
+        ).unwrap();
+        assert_eq!(get_person_collage_faces(&conn, 1, 9, AccessScope::All).unwrap(), vec![5, 3, 1]);
+        assert_eq!(get_person_collage_faces(&conn, 1, 9, AccessScope::Public).unwrap(), vec![5, 1]);
+        crate::db::writer::set_person_cover(&conn, 1, Some(1)).unwrap();
+        assert_eq!(get_person_collage_faces(&conn, 1, 2, AccessScope::All).unwrap(), vec![1, 5]);
+        assert!(get_person_collage_faces(&conn, 2, 9, AccessScope::All).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "facial-recognition")]
+    #[test]
+    fn test_get_named_faces() {
+        let (_tmp, conn) = setup_test_db();
+        for id in 1..=2 {
+            conn.execute(
+                "INSERT INTO assets (id, path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, width, height)
+                 VALUES (?1, ?2, '/p', 'x.jpg', 'jpg', 1, 0, 0, 'image/jpeg', 0, 400, 300)",
+                params![id, format!("/p/{}.jpg", id)],
+            ).unwrap();
+        }
+        conn.execute("INSERT INTO persons (id, name, created_at) VALUES (1, 'Alice', 0), (2, 'Bob', 0), (3, NULL, 0)", []).unwrap();
+        conn.execute(
+            "INSERT INTO face_embeddings (id, asset_id, person_id, embedding_blob, bbox_json, confidence) VALUES
+             (1, 1, 1, x'00', '{}', 0.9), (2, 1, 2, x'00', '{}', 0.9), (3, 1, 3, x'00', '{}', 0.9), (4, 2, 2, x'00', '{}', 0.9)",
+            [],
+        ).unwrap();
+
+        let all = get_named_faces(&conn, None).unwrap();
+        assert_eq!(all.iter().map(|a| (a.asset_id, a.faces.len())).collect::<Vec<_>>(), vec![(1, 2), (2, 1)]);
+        assert_eq!(all[0].faces[1].0, "Bob");
+        // Asset 1 shows Alice, so Bob's face there is written too
+        let alice = get_named_faces(&conn, Some(&[1])).unwrap();
+        assert_eq!(alice.iter().map(|a| (a.asset_id, a.faces.len())).collect::<Vec<_>>(), vec![(1, 2)]);
+    }
+
+    #[cfg(feature = "facial-recognition")]
+    #[test]
+    fn test_get_person_timeline() {
+        let (_tmp, conn) = setup_test_db();
+        // 2021-03-10, 2021-03-20, 2022-07-01
+        for (i, taken) in [1615334400i64, 1616198400, 1656633600].iter().enumerate() {
+            conn.execute(
+                "INSERT INTO assets (id, path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, taken_at)
+                 VALUES (?1, ?2, '/p', 'x.jpg', 'jpg', 1, 0, 0, 'image/jpeg', 0, ?3)",
+                params![i as i64 + 1, format!("/p/{}.jpg", i), taken],
+            ).unwrap();
+        }
+        conn.execute("INSERT INTO persons (id, name, created_at) VALUES (1, 'Alice', 0)", []).unwrap();
+        conn.execute(
+            "INSERT INTO face_embeddings (asset_id, person_id, embedding_blob, bbox_json, confidence) VALUES
+             (1, 1, x'00', '{}', 0.5), (2, 1, x'00', '{}', 0.9), (2, 1, x'00', '{}', 0.7), (3, 1, x'00', '{}', 0.8)",
+            [],
+        ).unwrap();
+
+        let months = get_person_timeline(&conn, 1, true, 4, AccessScope::All).unwrap();
+        assert_eq!(months.len(), 2);
+        assert_eq!((months[0].year, months[0].month, months[0].count), (2022, Some(7), 1));
+        assert_eq!((months[1].year, months[1].month, months[1].count), (2021, Some(3), 2));
+        assert_eq!(months[1].sample_asset_ids, vec![2, 1]);
+
+        let years = get_person_timeline(&conn, 1, false, 1, AccessScope::All).unwrap();
+        assert_eq!(years[1].month, None);
+        assert_eq!(years[1].sample_asset_ids, vec![2]);
+        assert!(get_person_timeline(&conn, 2, true, 4, AccessScope::All).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "facial-recognition")]
+    #[test]
+    fn test_list_persons_paged() {
+        use crate::pipeline::face::Subject;
+        let (_tmp, conn) = setup_test_db();
+        for i in 1..=3i64 {
+            conn.execute(
+                "INSERT INTO assets (id, path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags)
+                 VALUES (?1, ?2, '/p', 'x.jpg', 'jpg', 1, 0, 0, 'image/jpeg', 0)",
+                params![i, format!("/p/{}.jpg", i)],
+            ).unwrap();
+        }
+        conn.execute(
+            "INSERT INTO persons (id, name, created_at) VALUES (1, 'bob', 10), (2, NULL, 30), (3, 'Alice', 20)",
+            [],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO face_embeddings (id, asset_id, person_id, embedding_blob, bbox_json, confidence) VALUES
+             (10, 1, 1, x'00', '{}', 0.5), (11, 1, 1, x'00', '{}', 0.9), (12, 2, 1, x'00', '{}', 0.7),
+             (13, 1, 2, x'00', '{}', 0.6), (14, 2, 2, x'00', '{}', 0.8), (15, 3, 2, x'00', '{}', 0.4),
+             (16, 3, NULL, x'00', '{}', 0.99)",
+            [],
+        ).unwrap();
+
+        let page = list_persons_paged(&conn, 0, 10, PersonSort::Faces, Subject::Person, AccessScope::All).unwrap();
+        assert_eq!(page.total, 3);
+        let ids: Vec<i64> = page.items.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!((page.items[0].face_count, page.items[0].asset_count, page.items[0].cover_face_id), (3, 2, Some(11)));
+        assert_eq!((page.items[1].face_count, page.items[1].asset_count, page.items[1].cover_face_id), (3, 3, Some(14)));
+        assert_eq!((page.items[2].face_count, page.items[2].asset_count, page.items[2].cover_face_id), (0, 0, None));
+
+        let ids = |sort| list_persons_paged(&conn, 0, 10, sort, Subject::Person, AccessScope::All).unwrap().items.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids(PersonSort::Assets), vec![2, 1, 3]);
+        assert_eq!(ids(PersonSort::Name), vec![3, 1, 2]);
+        assert_eq!(ids(PersonSort::Created), vec![2, 3, 1]);
+
+        let page = list_persons_paged(&conn, 1, 1, PersonSort::Name, Subject::Person, AccessScope::All).unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].id, 1);
+    }
+
     #[test]
     fn test_delete_asset_by_id() {
         let (_tmp, conn) = setup_test_db();
@@ -960,8 +3093,11 @@ mod tests {
             camera_make: None,
             camera_model: None,
             platform_type: None,
+            scope: AccessScope::All,
+            relevance: false,
             offset: 0,
             limit: 10,
+            ..Default::default()
         };
         let result = search_assets(&conn, &search_params).unwrap();
         assert_eq!(result.total, 1);
@@ -991,14 +3127,241 @@ mod tests {
             camera_make: None,
             camera_model: None,
             platform_type: None,
+            scope: AccessScope::All,
+            relevance: false,
             offset: 0,
             limit: 10,
+            ..Default::default()
         };
         let result = search_assets(&conn, &search_params).unwrap();
         assert_eq!(result.total, 1);
         assert_eq!(result.items[0].ext, "jpg");
     }
 
+    #[test]
+    fn test_search_assets_by_tag() {
+        let (_tmp, conn) = setup_test_db();
+        
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags) VALUES 
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params!["/test/photo1.jpg", "/test", "photo1.jpg", "jpg", 1000, 1000000, 1000000, "image/jpeg", 0]
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags) VALUES 
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params!["/test/photo2.jpg", "/test", "photo2.jpg", "jpg", 2000, 2000000, 2000000, "image/jpeg", 0]
+        ).unwrap();
+        conn.execute("INSERT INTO fts_assets(rowid, filename, dirname, path) VALUES (1, 'photo1.jpg', '/test', '/test/photo1.jpg')", []).unwrap();
+        conn.execute("INSERT INTO fts_assets(rowid, filename, dirname, path) VALUES (2, 'photo2.jpg', '/test', '/test/photo2.jpg')", []).unwrap();
+
+        crate::db::writer::add_tags_to_asset(&conn, 2, &["Beach".to_string(), "summer".to_string()]).unwrap();
+        assert_eq!(get_asset_tags(&conn, 2).unwrap(), vec!["Beach".to_string(), "summer".to_string()]);
+
+        // Free-text search matches tag names
+        let mut search_params = SearchParams {
+            q: "beach",
+            from: None,
+            to: None,
+            camera_make: None,
+            camera_model: None,
+            platform_type: None,
+            scope: AccessScope::All,
+            relevance: false,
+            offset: 0,
+            limit: 10,
+            ..Default::default()
+        };
+        let result = search_assets(&conn, &search_params).unwrap();
+        assert_eq!(result.total, 1);
+        assert_eq!(result.items[0].filename, "photo2.jpg");
+
+        // Exact tag filter is case-insensitive
+        let summer = vec!["SUMMER".to_string()];
+        search_params.q = "photo";
+        search_params.tags = &summer;
+        let result = search_assets(&conn, &search_params).unwrap();
+        assert_eq!(result.total, 1);
+
+        assert!(crate::db::writer::remove_tag_from_asset(&conn, 2, "beach").unwrap());
+        let tags = list_tags(&conn, AccessScope::All).unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].2, 0);
+
+        // Other users only see tags on assets they can see
+        let alice = db::writer::create_user(&conn, "alice", "x", false).unwrap().unwrap();
+        let bob = db::writer::create_user(&conn, "bob", "x", false).unwrap().unwrap();
+        conn.execute("UPDATE assets SET owner_id = ?1", params![alice]).unwrap();
+        let names = |scope| list_tags(&conn, scope).unwrap().into_iter().map(|t| (t.1, t.2)).collect::<Vec<(String, i64)>>();
+        assert_eq!(names(AccessScope::User(alice)), vec![("summer".to_string(), 1)]);
+        assert!(names(AccessScope::User(bob)).is_empty());
+    }
+
+    #[test]
+    fn test_search_people_tags_places() {
+        let (_tmp, conn) = setup_test_db();
+        let photos = [
+            (1, "/photos/Rome 2019/day1", 1_560_000_000),
+            (2, "/photos/Rome 2019", 1_560_100_000),
+            (3, "/photos/rome", 1_600_000_000),
+            (4, "/photos/Romeo", 1_560_000_000),
+        ];
+        for (id, dir, taken) in photos {
+            conn.execute(
+                "INSERT INTO assets (id, path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, taken_at)
+                 VALUES (?1, ?2, ?3, ?4, 'jpg', 1, 0, 0, 'image/jpeg', 0, ?5)",
+                params![id, format!("{}/{}.jpg", dir, id), dir, format!("{}.jpg", id), taken],
+            ).unwrap();
+        }
+        conn.execute("INSERT INTO persons (id, name, created_at) VALUES (1, 'Ann', 0), (2, 'Ben', 0), (3, 'Cy', 0)", []).unwrap();
+        // Ann and Ben together in 1, Ann twice in 2, Ben alone in 3
+        conn.execute(
+            "INSERT INTO face_embeddings (asset_id, person_id, embedding_blob, bbox_json, confidence) VALUES
+             (1, 1, x'00', '{}', 0.9), (1, 2, x'00', '{}', 0.9), (2, 1, x'00', '{}', 0.9), (2, 1, x'00', '{}', 0.8), (3, 2, x'00', '{}', 0.9)",
+            [],
+        ).unwrap();
+        crate::db::writer::add_tags_to_asset(&conn, 1, &["beach".to_string(), "sunset".to_string()]).unwrap();
+        crate::db::writer::add_tags_to_asset(&conn, 3, &["Beach".to_string()]).unwrap();
+
+        let search = |params: SearchParams| {
+            let mut ids: Vec<i64> = search_assets(&conn, &SearchParams { limit: 10, ..params }).unwrap().items.iter().map(|a| a.id).collect();
+            ids.sort_unstable();
+            ids
+        };
+        let ann_ben = [1, 2, 2];
+        assert_eq!(search(SearchParams { person_ids: &ann_ben, ..Default::default() }), vec![1]);
+        assert_eq!(search(SearchParams { person_ids: &ann_ben, person_match: MatchMode::Any, ..Default::default() }), vec![1, 2, 3]);
+        assert_eq!(search(SearchParams { person_ids: &[1, 3], ..Default::default() }), Vec::<i64>::new());
+
+        let tags = vec!["BEACH".to_string(), "sunset".to_string()];
+        assert_eq!(search(SearchParams { tags: &tags, ..Default::default() }), vec![1]);
+        assert_eq!(search(SearchParams { tags: &tags, tag_match: MatchMode::Any, ..Default::default() }), vec![1, 3]);
+
+        // A path covers its subfolders; a name matches whole folder names anywhere, ignoring case
+        assert_eq!(search(SearchParams { place: Some("/photos/Rome 2019/"), ..Default::default() }), vec![1, 2]);
+        assert_eq!(search(SearchParams { place: Some("rome"), ..Default::default() }), vec![3]);
+        assert_eq!(search(SearchParams { place: Some("ROME 2019"), ..Default::default() }), vec![1, 2]);
+
+        // Everything together: Ann, in Rome 2019, before mid-2019, tagged beach
+        let beach = vec!["beach".to_string()];
+        let combined = SearchParams { person_ids: &[1], place: Some("Rome 2019"), to: Some(1_560_050_000), tags: &beach, ..Default::default() };
+        assert_eq!(search(combined), vec![1]);
+    }
+
+    #[test]
+    fn test_asset_properties_search_and_snapshot() {
+        let (_tmp, conn) = setup_test_db();
+        for (path, name) in [("/test/a.jpg", "a.jpg"), ("/test/b.jpg", "b.jpg")] {
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags) VALUES
+                 (?1, '/test', ?2, 'jpg', 1000, 1000000, 1000000, 'image/jpeg', 0)",
+                params![path, name]
+            ).unwrap();
+        }
+        let set = |id: i64, key: &str, value: Option<&str>| {
+            crate::db::writer::set_asset_properties(&conn, id, &[(key.to_string(), value.map(String::from))]).unwrap();
+        };
+        set(2, "project", Some("Harbor P-104"));
+        set(2, "Client", Some("Acme"));
+        set(2, "CLIENT", Some("Globex"));
+        assert_eq!(get_asset_properties(&conn, 2).unwrap(), vec![
+            ("CLIENT".to_string(), "Globex".to_string()),
+            ("project".to_string(), "Harbor P-104".to_string()),
+        ]);
+
+        let search = |q: &str| {
+            let params = SearchParams { q, from: None, to: None, camera_make: None, camera_model: None, platform_type: None, scope: AccessScope::All, relevance: false, offset: 0, limit: 10, ..Default::default() };
+            search_assets(&conn, &params).unwrap().items.into_iter().map(|a| a.filename).collect::<Vec<_>>()
+        };
+        assert_eq!(search("globex"), vec!["b.jpg"]);
+        assert!(search("acme").is_empty());
+
+        let snapshot = crate::db::writer::snapshot_asset(&conn, 2).unwrap().unwrap();
+        assert!(delete_asset_by_id(&conn, 2).unwrap());
+        assert!(search("harbor").is_empty());
+        let restored = crate::db::writer::restore_asset_snapshot(&conn, &snapshot).unwrap();
+        assert_eq!(get_asset_properties(&conn, restored).unwrap().len(), 2);
+        assert_eq!(search("harbor"), vec!["b.jpg"]);
+
+        set(restored, "project", None);
+        assert!(search("harbor").is_empty());
+    }
+
+    #[test]
+    fn test_search_ranked_with_snippets() {
+        let (_tmp, conn) = setup_test_db();
+        for (path, dir, name) in [
+            ("/trips/beach.jpg", "/trips", "beach.jpg"),
+            ("/beach/IMG_0001.jpg", "/beach", "IMG_0001.jpg"),
+            ("/misc/IMG_0002.jpg", "/misc", "IMG_0002.jpg"),
+            ("/misc/IMG_0003.jpg", "/misc", "IMG_0003.jpg"),
+        ] {
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags) VALUES
+                 (?1, ?2, ?3, 'jpg', 1000, 1000000, 1000000, 'image/jpeg', 0)",
+                params![path, dir, name]
+            ).unwrap();
+        }
+        let album = crate::db::writer::create_album(&conn, "Summer", None, None).unwrap();
+        crate::db::writer::add_assets_to_album(&conn, album, &[3]).unwrap();
+        conn.execute("INSERT INTO persons (name, created_at) VALUES ('Alice', 0)", []).unwrap();
+        let person = conn.last_insert_rowid();
+        conn.execute("INSERT INTO face_embeddings (asset_id, embedding_blob, bbox_json, confidence, person_id) VALUES (4, x'00', '{}', 0.9, ?1)", [person]).unwrap();
+        crate::db::writer::set_asset_properties(&conn, 4, &[("caption".to_string(), Some("Alice at the <b>beach</b> bar".to_string()))]).unwrap();
+
+        let search = |q: &str| {
+            let params = SearchParams { q, from: None, to: None, camera_make: None, camera_model: None, platform_type: None, scope: AccessScope::All, relevance: true, offset: 0, limit: 10, ..Default::default() };
+            search_assets(&conn, &params).unwrap()
+        };
+        // A filename match ranks first; hits follow the page order
+        let result = search("beach");
+        assert_eq!(result.items.iter().map(|a| a.id).collect::<Vec<_>>(), vec![1, 2, 4]);
+        let hits = result.hits.unwrap();
+        assert_eq!(hits.iter().map(|h| h.asset_id).collect::<Vec<_>>(), vec![1, 2, 4]);
+        assert!(hits[0].score > hits[1].score && hits[1].score > hits[2].score);
+        assert_eq!(hits[2].snippet, "Alice at the &lt;b&gt;<mark>beach</mark>&lt;/b&gt; bar");
+
+        assert_eq!(search("summer").items.len(), 1);
+        assert_eq!(search("alice").items.iter().map(|a| a.id).collect::<Vec<_>>(), vec![4]);
+        // Renames are picked up by the index
+        crate::db::writer::update_album(&conn, album, Some("Winter"), None).unwrap();
+        assert!(search("summer").items.is_empty());
+        assert_eq!(search("winter").items[0].id, 3);
+        conn.execute("UPDATE persons SET name = 'Bob' WHERE id = ?1", [person]).unwrap();
+        assert_eq!(search("bob").items[0].id, 4);
+        // Recognized text is searchable and shows in the snippet
+        crate::db::writer::set_asset_text(&conn, 3, "ACME Ltd\nInvoice 2023-118", "tesseract", 0).unwrap();
+        let result = search("invoice 2023");
+        assert_eq!(result.items.iter().map(|a| a.id).collect::<Vec<_>>(), vec![3]);
+        assert!(result.hits.unwrap()[0].snippet.contains("<mark>Invoice</mark>"));
+    }
+
+    #[test]
+    fn test_thumb_candidates() {
+        let (_tmp, conn) = setup_test_db();
+        // (path, sha256 byte, taken_at, mime, archived)
+        let rows = [
+            ("/lib/2024/a.jpg", Some(0xab), 100, "image/jpeg", 0),
+            ("/lib/2024/b.mp4", Some(0xcd), 200, "video/mp4", 0),
+            ("/lib/2024/c.jpg", None, 300, "image/jpeg", 0),
+            ("/lib/2024/d.jpg", Some(0xef), 400, "image/jpeg", 1),
+            ("/lib/2023/e.jpg", Some(0x01), 50, "image/jpeg", 0),
+        ];
+        for (path, sha, taken, mime, archived) in rows {
+            let sha = sha.map(|b: u8| vec![b; 32]);
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, sha256, taken_at, archived)
+                 VALUES (?1, '', '', '', 1, 1, 1, ?2, ?3, ?4, ?5)",
+                params![path, mime, sha, taken, archived]
+            ).unwrap();
+        }
+        let paths = |from, to, dir| thumb_candidates(&conn, from, to, dir, AccessScope::All).unwrap().into_iter().map(|c| c.1).collect::<Vec<String>>();
+        assert_eq!(paths(None, None, None), vec!["/lib/2024/b.mp4", "/lib/2024/a.jpg", "/lib/2023/e.jpg"]);
+        assert_eq!(paths(Some(60), Some(150), None), vec!["/lib/2024/a.jpg"]);
+        assert_eq!(paths(None, None, Some("/lib/2023/")), vec!["/lib/2023/e.jpg"]);
+        assert_eq!(thumb_candidates(&conn, None, None, None, AccessScope::All).unwrap()[0].2, "cd".repeat(32));
+    }
+
     #[test]
     fn test_check_file_unchanged() {
         let (_tmp, conn) = setup_test_db();
@@ -1059,5 +3422,316 @@ mod tests {
         let paths = get_scan_paths(&conn).unwrap();
         assert_eq!(paths.len(), 1);
         assert_eq!(paths[0], "/test/path1");
+
+        let flagged = get_scan_paths_with_flags(&conn, AccessScope::All).unwrap();
+        assert_eq!(flagged, vec![("/test/path1".to_string(), false)]);
+        crate::db::writer::set_scan_path_read_only(&conn, "/test/path1", true).unwrap();
+        assert!(get_scan_paths_with_flags(&conn, AccessScope::All).unwrap()[0].1);
+
+        assert!(get_scan_path_schedules(&conn).unwrap().is_empty());
+        crate::db::writer::set_scan_path_schedule(&conn, "/test/path1", Some("0 2 * * *")).unwrap();
+        crate::db::writer::mark_scan_path_scheduled(&conn, "/test/path1", 1234).unwrap();
+        assert_eq!(get_scan_path_schedules(&conn).unwrap(), vec![("/test/path1".to_string(), "0 2 * * *".to_string(), Some(1234))]);
+        crate::db::writer::set_scan_path_schedule(&conn, "/test/path1", None).unwrap();
+        assert!(get_scan_path_schedules(&conn).unwrap().is_empty());
+
+        assert!(get_scan_path_quota(&conn, "/test/path1").unwrap().is_unlimited());
+        assert!(get_scan_path_quotas(&conn).unwrap().is_empty());
+        let quota = ScanQuota { max_assets: Some(5000), max_bytes: None };
+        assert!(crate::db::writer::set_scan_path_quota(&conn, "/test/path1", &quota).unwrap());
+        assert!(!crate::db::writer::set_scan_path_quota(&conn, "/missing", &quota).unwrap());
+        assert_eq!(get_scan_path_quota(&conn, "/test/path1").unwrap(), quota);
+        let hit = QuotaHit { at: 99, limit: "assets".into(), indexed_assets: 5000, indexed_bytes: 1 << 33 };
+        crate::db::writer::set_scan_path_quota_hit(&conn, "/test/path1", Some(&hit)).unwrap();
+        assert_eq!(get_scan_path_quotas(&conn).unwrap(), vec![("/test/path1".to_string(), quota, Some(hit))]);
+        crate::db::writer::set_scan_path_quota(&conn, "/test/path1", &ScanQuota::default()).unwrap();
+        crate::db::writer::set_scan_path_quota_hit(&conn, "/test/path1", None).unwrap();
+        assert!(get_scan_path_quotas(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scan_path_clash() {
+        let (_tmp, conn) = setup_test_db();
+        let alice = db::writer::create_user(&conn, "alice", "x", false).unwrap().unwrap();
+        let bob = db::writer::create_user(&conn, "bob", "x", false).unwrap().unwrap();
+        db::writer::add_scan_path(&conn, "/shared").unwrap();
+        db::writer::add_scan_path(&conn, "/home/alice").unwrap();
+        db::writer::set_scan_path_owner(&conn, "/home/alice", alice).unwrap();
+        let overlaps = |p: &str| Some(ScanPathClash::Overlaps(p.to_string()));
+
+        // Re-adding an existing path is refused for everyone, so it can't be claimed
+        for scope in [AccessScope::All, AccessScope::User(alice), AccessScope::User(bob)] {
+            assert_eq!(scan_path_clash(&conn, "/shared", scope).unwrap(), Some(ScanPathClash::Exists));
+            assert_eq!(scan_path_clash(&conn, "/shared/", scope).unwrap(), Some(ScanPathClash::Exists));
+        }
+
+        // Users can't add a path containing, or inside, one they don't own
+        assert_eq!(scan_path_clash(&conn, "/", AccessScope::User(bob)).unwrap(), overlaps("/shared"));
+        assert_eq!(scan_path_clash(&conn, "/shared/2023", AccessScope::User(bob)).unwrap(), overlaps("/shared"));
+        assert_eq!(scan_path_clash(&conn, "/home", AccessScope::User(bob)).unwrap(), overlaps("/home/alice"));
+        assert_eq!(scan_path_clash(&conn, "/home/alice/2023", AccessScope::User(bob)).unwrap(), overlaps("/home/alice"));
+        assert_eq!(scan_path_clash(&conn, "/home", AccessScope::User(alice)).unwrap(), None);
+        assert_eq!(scan_path_clash(&conn, "/home/alice/2023", AccessScope::User(alice)).unwrap(), None);
+        assert_eq!(scan_path_clash(&conn, "/home/bob", AccessScope::User(bob)).unwrap(), None);
+        assert_eq!(scan_path_clash(&conn, "/sharedother", AccessScope::User(bob)).unwrap(), None);
+        assert_eq!(scan_path_clash(&conn, "/", AccessScope::All).unwrap(), None);
+    }
+
+    #[test]
+    fn test_scan_runs() {
+        let (_tmp, conn) = setup_test_db();
+        let run = |path: &str, at: i64, status: &str| crate::pipeline::discover::ScanRun {
+            path: path.to_string(), started_at: at, finished_at: at + 5, status: status.to_string(), files: 10, bytes: 1000,
+        };
+        for at in 0..crate::db::writer::SCAN_RUNS_KEPT + 2 {
+            crate::db::writer::record_scan_run(&conn, &run("/a", at, "completed")).unwrap();
+        }
+        crate::db::writer::record_scan_run(&conn, &run("/b", 100, "canceled")).unwrap();
+
+        let all = get_scan_runs(&conn, None, 3, AccessScope::All).unwrap();
+        assert_eq!(all[0], run("/b", 100, "canceled"));
+        let a = get_scan_runs(&conn, Some("/a"), 1000, AccessScope::All).unwrap();
+        // The oldest beyond the limit are gone
+        assert_eq!(a.len() as i64, crate::db::writer::SCAN_RUNS_KEPT);
+        assert_eq!((a[0].started_at, a.last().unwrap().started_at), (crate::db::writer::SCAN_RUNS_KEPT + 1, 2));
+
+        // Users only see runs of their own and shared scan paths
+        let alice = db::writer::create_user(&conn, "alice", "x", false).unwrap().unwrap();
+        let bob = db::writer::create_user(&conn, "bob", "x", false).unwrap().unwrap();
+        db::writer::add_scan_path(&conn, "/a").unwrap();
+        db::writer::add_scan_path(&conn, "/b").unwrap();
+        db::writer::set_scan_path_owner(&conn, "/b", alice).unwrap();
+        assert_eq!(get_scan_runs(&conn, None, 1, AccessScope::User(alice)).unwrap()[0].path, "/b");
+        assert!(get_scan_runs(&conn, None, 1000, AccessScope::User(bob)).unwrap().iter().all(|r| r.path == "/a"));
+    }
+
+    #[test]
+    fn test_live_photo_pairs() {
+        let (_tmp, conn) = setup_test_db();
+        for (filename, dirname, duration_ms) in [
+            ("IMG_0001.HEIC", "/dcim", None),
+            ("IMG_0001.MOV", "/dcim", Some(2900)),
+            ("IMG_0002.JPG", "/dcim", None),
+            ("IMG_0002.mov", "/dcim", Some(60_000)),
+            ("IMG_0003.jpg", "/dcim", None),
+            ("IMG_0003.MOV", "/other", Some(2900)),
+        ] {
+            let ext = filename.rsplit('.').next().unwrap().to_lowercase();
+            let mime = if ext == "mov" { "video/quicktime" } else { "image/jpeg" };
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, duration_ms)
+                 VALUES (?1 || '/' || ?2, ?1, ?2, ?3, 1, 0, 0, ?4, 0, ?5)",
+                params![dirname, filename, ext, mime, duration_ms],
+            ).unwrap();
+        }
+        // Only the short clip beside the still counts, whatever the case of the name
+        assert_eq!(crate::db::writer::link_live_photos(&conn, Some(&[1, 3, 4, 5])).unwrap(), 1);
+        assert_eq!(get_live_photo_video(&conn, 1).unwrap(), Some(2));
+        assert_eq!(get_asset_by_id(&conn, 1).unwrap().unwrap().live_photo_video_id, Some(2));
+        assert_eq!(get_live_photo_video(&conn, 3).unwrap(), None);
+        assert_eq!(get_live_photo_video(&conn, 5).unwrap(), None);
+
+        // Deleting the clip unpairs the still; writing it again (by its own id) pairs it back
+        assert!(delete_asset_by_id(&conn, 2).unwrap());
+        assert_eq!(get_asset_by_id(&conn, 1).unwrap().unwrap().live_photo_video_id, None);
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, duration_ms)
+             VALUES ('/dcim/IMG_0001.MOV', '/dcim', 'IMG_0001.MOV', 'mov', 1, 0, 0, 'video/quicktime', 0, 2900)",
+            [],
+        ).unwrap();
+        let clip = conn.last_insert_rowid();
+        assert_eq!(crate::db::writer::link_live_photos(&conn, Some(&[clip])).unwrap(), 1);
+        assert_eq!(get_live_photo_video(&conn, 1).unwrap(), Some(clip));
+
+        conn.execute("DELETE FROM assets WHERE id = ?1", params![clip]).unwrap();
+        assert_eq!(get_live_photo_video(&conn, 1).unwrap(), None);
+        assert_eq!(crate::db::writer::link_live_photos(&conn, None).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_pending_delete_totals() {
+        let (_tmp, conn) = setup_test_db();
+        assert_eq!(pending_delete_totals(&conn, AccessScope::All).unwrap(), (0, 0));
+        for (id, size) in [(1, 1000), (2, 500)] {
+            let snapshot = serde_json::json!({ "asset": { "id": id, "size_bytes": size }, "tags": [], "albums": [] });
+            crate::db::writer::insert_pending_delete(&conn, id, "/p/a.jpg", "/hold/a.jpg", None, &snapshot, 0).unwrap();
+        }
+        assert_eq!(pending_delete_totals(&conn, AccessScope::All).unwrap(), (2, 1500));
+    }
+
+    #[test]
+    fn test_pending_deletes_scope() {
+        let (_tmp, conn) = setup_test_db();
+        let alice = db::writer::create_user(&conn, "alice", "x", false).unwrap().unwrap();
+        let bob = db::writer::create_user(&conn, "bob", "x", false).unwrap().unwrap();
+        let mut ids = Vec::new();
+        for (asset_id, owner) in [(1, None), (2, Some(alice))] {
+            let snapshot = serde_json::json!({ "asset": { "id": asset_id, "owner_id": owner }, "tags": [], "albums": [] });
+            ids.push(crate::db::writer::insert_pending_delete(&conn, asset_id, "/p/a.jpg", "/hold/a.jpg", None, &snapshot, 0).unwrap());
+        }
+        let listed = |scope| list_pending_deletes(&conn, scope).unwrap().iter().map(|d| d.1).collect::<Vec<i64>>();
+        assert_eq!(listed(AccessScope::All), vec![1, 2]);
+        assert_eq!(listed(AccessScope::User(alice)), vec![1, 2]);
+        assert_eq!(listed(AccessScope::User(bob)), vec![1]);
+        assert!(get_pending_delete(&conn, ids[1], AccessScope::User(alice)).unwrap().is_some());
+        assert!(get_pending_delete(&conn, ids[1], AccessScope::User(bob)).unwrap().is_none());
+        assert_eq!(pending_delete_totals(&conn, AccessScope::User(bob)).unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_access_scope() {
+        let (_tmp, conn) = setup_test_db();
+        let alice = db::writer::create_user(&conn, "alice", "x", false).unwrap().unwrap();
+        let bob = db::writer::create_user(&conn, "bob", "x", false).unwrap().unwrap();
+        db::writer::add_scan_path(&conn, "/shared").unwrap();
+        db::writer::add_scan_path(&conn, "/home/alice").unwrap();
+        for path in ["/shared/a.jpg", "/home/alice/b.jpg", "/home/alice2/c.jpg"] {
+            let (dirname, filename) = path.rsplit_once('/').unwrap();
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags)
+                 VALUES (?1, ?2, ?3, 'jpg', 1, 0, 0, 'image/jpeg', 0)",
+                params![path, dirname, filename],
+            ).unwrap();
+        }
+        // Claiming the path claims the assets already under it, but not /home/alice2
+        db::writer::set_scan_path_owner(&conn, "/home/alice", alice).unwrap();
+        let ids: Vec<i64> = list_assets(&conn, 0, 10, "filename", "asc", ListFilters::default())
+            .unwrap().items.iter().map(|a| a.id).collect();
+
+        let visible = |scope| list_assets(&conn, 0, 10, "filename", "asc", ListFilters { scope, ..Default::default() }).unwrap().total;
+        assert_eq!(visible(AccessScope::All), 3);
+        assert_eq!(visible(AccessScope::User(alice)), 3);
+        assert_eq!(visible(AccessScope::User(bob)), 2);
+        assert_eq!(visible(AccessScope::Public), 2);
+        assert_eq!(get_scan_paths_with_flags(&conn, AccessScope::User(bob)).unwrap().len(), 1);
+        assert!(can_edit_scan_path(&conn, "/home/alice", AccessScope::User(alice)).unwrap());
+        assert!(!can_edit_scan_path(&conn, "/home/alice", AccessScope::User(bob)).unwrap());
+
+        // Sharing an album makes its assets visible, read-only
+        let album = db::writer::create_album(&conn, "Trip", None, None).unwrap();
+        db::writer::set_album_owner(&conn, album, Some(alice)).unwrap();
+        db::writer::add_assets_to_album(&conn, album, &[ids[1]]).unwrap();
+        assert!(!can_view_album(&conn, album, AccessScope::User(bob)).unwrap());
+        assert!(!can_view_asset(&conn, ids[1], AccessScope::User(bob)).unwrap());
+        db::writer::share_album(&conn, album, bob).unwrap();
+        assert!(can_view_album(&conn, album, AccessScope::User(bob)).unwrap());
+        assert!(!can_edit_album(&conn, album, AccessScope::User(bob)).unwrap());
+        assert!(can_view_asset(&conn, ids[1], AccessScope::User(bob)).unwrap());
+        assert!(!can_edit_asset(&conn, ids[1], AccessScope::User(bob)).unwrap());
+        assert_eq!(list_albums_with_assets(&conn, AccessScope::User(bob)).unwrap().len(), 1);
+        assert_eq!(filter_visible_assets(&conn, ids.clone(), AccessScope::User(bob)).unwrap(), ids);
+        assert_eq!(filter_editable_assets(&conn, ids.clone(), AccessScope::User(bob)).unwrap(), vec![ids[0], ids[2]]);
+        assert_eq!(list_album_shares(&conn, album).unwrap(), vec![(bob, "bob".to_string())]);
+        assert!(!is_smart_album(&conn, album).unwrap());
+        let rules = SmartAlbumRules { mime: Some("image".into()), ..Default::default() };
+        let smart = db::writer::create_album(&conn, "Photos", None, Some(&rules)).unwrap();
+        assert!(is_smart_album(&conn, smart).unwrap());
+    }
+
+    #[cfg(feature = "facial-recognition")]
+    #[test]
+    fn test_face_access_scope() {
+        use crate::pipeline::face::Subject;
+        let (_tmp, conn) = setup_test_db();
+        let alice = db::writer::create_user(&conn, "alice", "x", false).unwrap().unwrap();
+        let bob = db::writer::create_user(&conn, "bob", "x", false).unwrap().unwrap();
+        // Asset 1 is shared with everyone, asset 2 is Alice's
+        conn.execute(
+            "INSERT INTO assets (id, path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, owner_id) VALUES
+             (1, '/shared/a.jpg', '/shared', 'a.jpg', 'jpg', 1, 0, 0, 'image/jpeg', 0, NULL),
+             (2, '/home/alice/b.jpg', '/home/alice', 'b.jpg', 'jpg', 1, 0, 0, 'image/jpeg', 0, ?1)",
+            params![alice],
+        ).unwrap();
+        conn.execute("INSERT INTO persons (id, name, created_at) VALUES (1, 'Carol', 0), (2, 'Dave', 0)", []).unwrap();
+        // Carol is in both photos, Dave only in Alice's; faces 5 and 6 are unassigned
+        conn.execute(
+            "INSERT INTO face_embeddings (id, asset_id, person_id, embedding_blob, bbox_json, confidence) VALUES
+             (1, 1, 1, x'00', '{}', 0.8), (2, 2, 1, x'00', '{}', 0.9), (3, 2, 2, x'00', '{}', 0.9),
+             (5, 2, NULL, x'00', '{}', 0.9), (6, 1, NULL, x'00', '{}', 0.9)",
+            [],
+        ).unwrap();
+
+        let listed = |scope| list_persons_paged(&conn, 0, 10, PersonSort::Faces, Subject::Person, scope).unwrap();
+        let all = listed(AccessScope::User(alice));
+        assert_eq!((all.total, all.items[0].face_count, all.items[0].cover_face_id), (2, 2, Some(2)));
+        let page = listed(AccessScope::User(bob));
+        assert_eq!(page.total, 1);
+        assert_eq!((page.items[0].id, page.items[0].face_count, page.items[0].asset_count), (1, 1, 1));
+        assert_eq!(page.items[0].cover_face_id, Some(1));
+        assert_eq!(listed(AccessScope::Public).total, 1);
+
+        assert!(can_view_person(&conn, 2, AccessScope::User(alice)).unwrap());
+        assert!(!can_view_person(&conn, 2, AccessScope::User(bob)).unwrap());
+        assert!(!can_view_person(&conn, 99, AccessScope::All).unwrap());
+        assert_eq!(get_person_assets(&conn, 1, AccessScope::User(bob)).unwrap(), vec![1]);
+        assert_eq!(get_person_timeline(&conn, 1, true, 4, AccessScope::User(bob)).unwrap()[0].sample_asset_ids, vec![1]);
+
+        // A chosen cover in a photo the caller can't see falls back to one they can
+        db::writer::set_person_cover(&conn, 1, Some(2)).unwrap();
+        assert_eq!(get_person_representative_face(&conn, 1, AccessScope::User(bob)).unwrap(), Some(1));
+        assert_eq!(get_person_representative_face(&conn, 2, AccessScope::User(bob)).unwrap(), None);
+
+        assert!(can_view_face(&conn, 2, AccessScope::User(alice)).unwrap());
+        assert!(!can_view_face(&conn, 2, AccessScope::User(bob)).unwrap());
+        let ids = |faces: Vec<(i64, i64, String, f64)>| faces.into_iter().map(|f| f.0).collect::<Vec<_>>();
+        assert_eq!(ids(get_unassigned_faces(&conn, 0, 10, AccessScope::User(alice)).unwrap()), vec![6, 5]);
+        assert_eq!(ids(get_unassigned_faces(&conn, 0, 10, AccessScope::User(bob)).unwrap()), vec![6]);
+        assert_eq!(ids(get_unassigned_faces_by_id(&conn, &[5, 6], AccessScope::User(bob)).unwrap()), vec![6]);
+        assert_eq!(count_unassigned_faces(&conn, AccessScope::User(bob)).unwrap(), 1);
+        assert_eq!(get_recent_unassigned_faces_with_embeddings(&conn, 10, AccessScope::User(bob)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_find_asset_by_hash() {
+        let (_tmp, conn) = setup_test_db();
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, sha256, xxh64) VALUES
+             ('/test/a.jpg', '/test', 'a.jpg', 'jpg', 100, 0, 0, 'image/jpeg', 0, X'AABB', 11)",
+            [],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, xxh64) VALUES
+             ('/test/big.jpg', '/test', 'big.jpg', 'jpg', 900, 0, 0, 'image/jpeg', 0, 22)",
+            [],
+        ).unwrap();
+
+        let hit = find_asset_by_hash(&conn, &[0xAA, 0xBB], 0, 0, AccessScope::All).unwrap();
+        assert_eq!(hit.map(|(_, p)| p), Some("/test/a.jpg".to_string()));
+        // No SHA256 stored: xxh64 + size must both match
+        let hit = find_asset_by_hash(&conn, &[0x01], 22, 900, AccessScope::All).unwrap();
+        assert_eq!(hit.map(|(_, p)| p), Some("/test/big.jpg".to_string()));
+        assert!(find_asset_by_hash(&conn, &[0x01], 22, 901, AccessScope::All).unwrap().is_none());
+
+        // Another user's copy doesn't count
+        let alice = db::writer::create_user(&conn, "alice", "x", false).unwrap().unwrap();
+        let bob = db::writer::create_user(&conn, "bob", "x", false).unwrap().unwrap();
+        conn.execute("UPDATE assets SET owner_id = ?1", params![alice]).unwrap();
+        assert!(find_asset_by_hash(&conn, &[0xAA, 0xBB], 0, 0, AccessScope::User(alice)).unwrap().is_some());
+        assert!(find_asset_by_hash(&conn, &[0xAA, 0xBB], 0, 0, AccessScope::User(bob)).unwrap().is_none());
+        assert!(find_asset_by_hash(&conn, &[0x01], 22, 900, AccessScope::Public).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_clip_embeddings() {
+        let (_tmp, conn) = setup_test_db();
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, sha256) VALUES
+             ('/test/a.jpg', '/test', 'a.jpg', 'jpg', 100, 0, 0, 'image/jpeg', 0, X'AABB'),
+             ('/test/b.mp4', '/test', 'b.mp4', 'mp4', 100, 0, 0, 'video/mp4', 0, X'CCDD'),
+             ('/test/c.jpg', '/test', 'c.jpg', 'jpg', 100, 0, 0, 'image/jpeg', 0, NULL),
+             ('/test/d.png', '/test', 'd.png', 'png', 100, 0, 0, 'image/png', 0, X'EEFF')",
+            [],
+        ).unwrap();
+
+        let missing = assets_missing_clip_embedding(&conn, "clip", 0, 10).unwrap();
+        assert_eq!(missing, vec![(1, "aabb".to_string()), (4, "eeff".to_string())]);
+        assert_eq!(assets_missing_clip_embedding(&conn, "clip", 1, 10).unwrap().len(), 1);
+
+        db::writer::upsert_clip_embedding(&conn, 1, "clip", &[0.5, -1.0], 0).unwrap();
+        db::writer::upsert_clip_embedding(&conn, 1, "clip", &[0.25, 2.0], 1).unwrap();
+        assert_eq!(clip_embeddings(&conn, "clip").unwrap(), vec![(1, vec![0.25, 2.0])]);
+        assert_eq!(assets_missing_clip_embedding(&conn, "clip", 0, 10).unwrap().len(), 1);
+        // Vectors from another model don't count
+        assert_eq!(assets_missing_clip_embedding(&conn, "other", 0, 10).unwrap().len(), 2);
     }
 }
\ No newline at end of file
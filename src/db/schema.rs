@@ -36,7 +36,18 @@ CREATE TABLE IF NOT EXISTS assets (
   exposure REAL,
   video_codec TEXT,
   mime TEXT NOT NULL,
-  flags INTEGER DEFAULT 0
+  flags INTEGER DEFAULT 0,
+  rating INTEGER NOT NULL DEFAULT 0,
+  favorite INTEGER NOT NULL DEFAULT 0,
+  stack_id INTEGER,
+  owner_id INTEGER,
+  archived INTEGER NOT NULL DEFAULT 0,
+  locked INTEGER NOT NULL DEFAULT 0,
+  path_id INTEGER REFERENCES scan_paths(id) ON DELETE SET NULL,
+  rel_path TEXT,
+  source_app TEXT,
+  live_photo_video_id INTEGER,
+  ingested_at INTEGER
 );
 
 CREATE VIRTUAL TABLE IF NOT EXISTS fts_assets USING fts5(filename, dirname, path, content='');
@@ -51,7 +62,11 @@ CREATE INDEX IF NOT EXISTS idx_assets_dirname ON assets(dirname);
 CREATE TABLE IF NOT EXISTS persons (
   id INTEGER PRIMARY KEY,
   name TEXT,
-  created_at INTEGER NOT NULL
+  created_at INTEGER NOT NULL,
+  -- 'person' or 'pet'
+  subject_type TEXT NOT NULL DEFAULT 'person',
+  -- Face chosen as the avatar; ignored once the face is assigned elsewhere
+  cover_face_id INTEGER
 );
 
 CREATE TABLE IF NOT EXISTS face_embeddings (
@@ -61,12 +76,26 @@ CREATE TABLE IF NOT EXISTS face_embeddings (
   embedding_blob BLOB NOT NULL,
   bbox_json TEXT NOT NULL,
   confidence REAL NOT NULL,
+  subject_type TEXT NOT NULL DEFAULT 'person',
   FOREIGN KEY(asset_id) REFERENCES assets(id),
   FOREIGN KEY(person_id) REFERENCES persons(id)
 );
 
 CREATE INDEX IF NOT EXISTS idx_face_embeddings_asset ON face_embeddings(asset_id);
-CREATE INDEX IF NOT EXISTS idx_face_embeddings_person ON face_embeddings(person_id);
+-- (person_id, asset_id) also answers "these people in one photo" searches from the index alone
+DROP INDEX IF EXISTS idx_face_embeddings_person;
+CREATE INDEX IF NOT EXISTS idx_face_embeddings_person_asset ON face_embeddings(person_id, asset_id);
+
+-- What users said about a face: 1 confirmed it shows the person, -1 said it doesn't
+CREATE TABLE IF NOT EXISTS face_feedback (
+  face_id INTEGER NOT NULL,
+  person_id INTEGER NOT NULL,
+  verdict INTEGER NOT NULL,
+  created_at INTEGER NOT NULL,
+  PRIMARY KEY(face_id, person_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_face_feedback_person ON face_feedback(person_id, verdict);
 
 CREATE TABLE IF NOT EXISTS person_profiles (
   person_id INTEGER PRIMARY KEY,
@@ -76,10 +105,46 @@ CREATE TABLE IF NOT EXISTS person_profiles (
   FOREIGN KEY(person_id) REFERENCES persons(id)
 );
 
+-- CLIP image vectors for semantic search, one per asset
+CREATE TABLE IF NOT EXISTS clip_embeddings (
+  asset_id INTEGER PRIMARY KEY,
+  model TEXT NOT NULL,
+  embedding BLOB NOT NULL,
+  created_at INTEGER NOT NULL,
+  FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
+-- Text recognized in an image; empty when the image was read and had none
+CREATE TABLE IF NOT EXISTS asset_text (
+  asset_id INTEGER PRIMARY KEY,
+  text TEXT NOT NULL,
+  engine TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
 CREATE TABLE IF NOT EXISTS scan_paths (
   id INTEGER PRIMARY KEY,
   path TEXT NOT NULL UNIQUE,
-  created_at INTEGER NOT NULL
+  created_at INTEGER NOT NULL,
+  read_only INTEGER NOT NULL DEFAULT 0,
+  owner_id INTEGER,
+  schedule TEXT,
+  last_scheduled_at INTEGER,
+  include_patterns TEXT,
+  exclude_patterns TEXT,
+  archived INTEGER NOT NULL DEFAULT 0,
+  archive_label TEXT,
+  date_priority TEXT,
+  max_assets INTEGER,
+  max_bytes INTEGER,
+  quota_hit TEXT
+);
+
+CREATE TABLE IF NOT EXISTS settings (
+  key TEXT PRIMARY KEY,
+  value TEXT NOT NULL,
+  updated_at INTEGER NOT NULL
 );
 
 CREATE TABLE IF NOT EXISTS face_settings (
@@ -93,7 +158,9 @@ CREATE TABLE IF NOT EXISTS albums (
   name TEXT NOT NULL,
   description TEXT,
   created_at INTEGER NOT NULL,
-  updated_at INTEGER NOT NULL
+  updated_at INTEGER NOT NULL,
+  rules_json TEXT,
+  owner_id INTEGER
 );
 
 CREATE TABLE IF NOT EXISTS album_assets (
@@ -106,24 +173,405 @@ CREATE TABLE IF NOT EXISTS album_assets (
 
 CREATE INDEX IF NOT EXISTS idx_album_assets_album ON album_assets(album_id);
 CREATE INDEX IF NOT EXISTS idx_album_assets_asset ON album_assets(asset_id);
+
+CREATE TABLE IF NOT EXISTS tags (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+  created_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS asset_tags (
+  asset_id INTEGER NOT NULL,
+  tag_id INTEGER NOT NULL,
+  created_at INTEGER NOT NULL,
+  FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE,
+  FOREIGN KEY(tag_id) REFERENCES tags(id) ON DELETE CASCADE,
+  PRIMARY KEY(asset_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_asset_tags_tag ON asset_tags(tag_id);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS fts_tags USING fts5(name);
+
+CREATE TABLE IF NOT EXISTS asset_properties (
+  id INTEGER PRIMARY KEY,
+  asset_id INTEGER NOT NULL,
+  key TEXT NOT NULL COLLATE NOCASE,
+  value TEXT NOT NULL,
+  updated_at INTEGER NOT NULL,
+  FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE,
+  UNIQUE(asset_id, key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_asset_properties_key ON asset_properties(key, value);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS fts_asset_properties USING fts5(value);
+
+CREATE TABLE IF NOT EXISTS stacks (
+  id INTEGER PRIMARY KEY,
+  cover_asset_id INTEGER NOT NULL,
+  asset_count INTEGER NOT NULL,
+  burst_key TEXT,
+  created_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS pending_deletes (
+  id INTEGER PRIMARY KEY,
+  asset_id INTEGER NOT NULL,
+  original_path TEXT NOT NULL,
+  held_path TEXT NOT NULL,
+  sha256 BLOB,
+  snapshot_json TEXT NOT NULL,
+  delete_after INTEGER NOT NULL,
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_deletes_due ON pending_deletes(delete_after);
+
+CREATE TABLE IF NOT EXISTS pending_jobs (
+  path TEXT PRIMARY KEY,
+  queued_at INTEGER NOT NULL
+);
+
+-- Assets queued for face detection; kept until the next run so progress counts survive restarts
+CREATE TABLE IF NOT EXISTS face_jobs (
+  asset_id INTEGER PRIMARY KEY,
+  queued_at INTEGER NOT NULL,
+  done INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS tokens (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL,
+  token_hash BLOB NOT NULL UNIQUE,
+  created_at INTEGER NOT NULL,
+  last_used_at INTEGER,
+  user_id INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+  token_hash BLOB PRIMARY KEY,
+  created_at INTEGER NOT NULL,
+  expires_at INTEGER NOT NULL,
+  user_id INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS users (
+  id INTEGER PRIMARY KEY,
+  username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+  password_hash TEXT NOT NULL,
+  is_admin INTEGER NOT NULL DEFAULT 0,
+  created_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS album_shares (
+  album_id INTEGER NOT NULL,
+  user_id INTEGER NOT NULL,
+  created_at INTEGER NOT NULL,
+  FOREIGN KEY(album_id) REFERENCES albums(id) ON DELETE CASCADE,
+  FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+  PRIMARY KEY(album_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_album_shares_user ON album_shares(user_id);
+
+-- Favorites of user accounts; assets.favorite holds those of the admin password (or everyone, with auth off)
+CREATE TABLE IF NOT EXISTS user_favorites (
+  user_id INTEGER NOT NULL,
+  asset_id INTEGER NOT NULL,
+  created_at INTEGER NOT NULL,
+  FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+  FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE,
+  PRIMARY KEY(user_id, asset_id)
+);
+
+-- Pre-authorized tokens for photo frames and TV browsers: read-only access to a few albums
+CREATE TABLE IF NOT EXISTS kiosks (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL,
+  token_hash BLOB NOT NULL UNIQUE,
+  created_by INTEGER,
+  created_at INTEGER NOT NULL,
+  last_used_at INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS kiosk_albums (
+  kiosk_id INTEGER NOT NULL,
+  album_id INTEGER NOT NULL,
+  position INTEGER NOT NULL,
+  FOREIGN KEY(kiosk_id) REFERENCES kiosks(id) ON DELETE CASCADE,
+  FOREIGN KEY(album_id) REFERENCES albums(id) ON DELETE CASCADE,
+  PRIMARY KEY(kiosk_id, album_id)
+);
+
+-- Assets a kiosk's frame has displayed in its current rotation
+CREATE TABLE IF NOT EXISTS kiosk_shown (
+  kiosk_id INTEGER NOT NULL,
+  asset_id INTEGER NOT NULL,
+  shown_at INTEGER NOT NULL,
+  FOREIGN KEY(kiosk_id) REFERENCES kiosks(id) ON DELETE CASCADE,
+  FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE,
+  PRIMARY KEY(kiosk_id, asset_id)
+);
+
+CREATE TABLE IF NOT EXISTS shares (
+  id INTEGER PRIMARY KEY,
+  token_hash BLOB NOT NULL UNIQUE,
+  album_id INTEGER,
+  asset_id INTEGER,
+  password_hash TEXT,
+  expires_at INTEGER,
+  created_by INTEGER,
+  created_at INTEGER NOT NULL,
+  FOREIGN KEY(album_id) REFERENCES albums(id) ON DELETE CASCADE,
+  FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
+-- User-defined processing rules the writer applies to incoming assets; conditions and actions are JSON
+CREATE TABLE IF NOT EXISTS processing_rules (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL,
+  enabled INTEGER NOT NULL DEFAULT 1,
+  conditions_json TEXT NOT NULL,
+  actions_json TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  updated_at INTEGER NOT NULL
+);
+
+-- One row per scan of a scan path, newest kept (see writer::record_scan_run)
+CREATE TABLE IF NOT EXISTS scan_runs (
+  id INTEGER PRIMARY KEY,
+  path TEXT NOT NULL,
+  started_at INTEGER NOT NULL,
+  finished_at INTEGER NOT NULL,
+  status TEXT NOT NULL,
+  files INTEGER NOT NULL DEFAULT 0,
+  bytes INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS idx_scan_runs_path ON scan_runs(path, started_at);
     "#,
     )?;
 
-    // Backwards-compatible migration: ensure video_codec column exists
-    let mut stmt = conn.prepare("PRAGMA table_info(assets)")?;
-    let mut has_video_codec = false;
-    {
-        let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
-        for name in rows {
-            if name.unwrap_or_default() == "video_codec" {
-                has_video_codec = true;
-                break;
-            }
+    // Backwards-compatible migration: ensure video_codec, rating, favorite and stack_id columns exist
+    for (column, kind) in [
+        ("video_codec", "TEXT"),
+        ("rating", "INTEGER NOT NULL DEFAULT 0"),
+        ("favorite", "INTEGER NOT NULL DEFAULT 0"),
+        ("stack_id", "INTEGER"),
+    ] {
+        if !column_exists(conn, "assets", column)? {
+            let _ = conn.execute(&format!("ALTER TABLE assets ADD COLUMN {} {}", column, kind), []);
         }
     }
-    if !has_video_codec {
-        let _ = conn.execute("ALTER TABLE assets ADD COLUMN video_codec TEXT", []);
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_assets_rating ON assets(rating);
+         CREATE INDEX IF NOT EXISTS idx_assets_favorite ON assets(favorite);
+         CREATE INDEX IF NOT EXISTS idx_assets_stack ON assets(stack_id);",
+    )?;
+
+    // Smart albums store their filter rules as JSON (NULL for regular albums)
+    if !column_exists(conn, "albums", "rules_json")? {
+        let _ = conn.execute("ALTER TABLE albums ADD COLUMN rules_json TEXT", []);
+    }
+
+    // Scan paths remember whether the volume accepted writes when they were added
+    if !column_exists(conn, "scan_paths", "read_only")? {
+        let _ = conn.execute("ALTER TABLE scan_paths ADD COLUMN read_only INTEGER NOT NULL DEFAULT 0", []);
+    }
+
+    // Multi-user: libraries, albums and credentials belong to a user (NULL = shared with everyone)
+    for (table, column) in [
+        ("assets", "owner_id"),
+        ("scan_paths", "owner_id"),
+        ("albums", "owner_id"),
+        ("tokens", "user_id"),
+        ("sessions", "user_id"),
+    ] {
+        if !column_exists(conn, table, column)? {
+            let _ = conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} INTEGER", table, column), []);
+        }
+    }
+    // Recurring scans: a cron expression and when it last fired
+    if !column_exists(conn, "scan_paths", "schedule")? {
+        let _ = conn.execute("ALTER TABLE scan_paths ADD COLUMN schedule TEXT", []);
+    }
+    if !column_exists(conn, "scan_paths", "last_scheduled_at")? {
+        let _ = conn.execute("ALTER TABLE scan_paths ADD COLUMN last_scheduled_at INTEGER", []);
+    }
+    // Cold storage: originals that may be offline, per asset or for a whole scan path
+    for table in ["assets", "scan_paths"] {
+        if !column_exists(conn, table, "archived")? {
+            let _ = conn.execute(&format!("ALTER TABLE {} ADD COLUMN archived INTEGER NOT NULL DEFAULT 0", table), []);
+        }
+    }
+    // Locked assets refuse edits and deletes through the API
+    if !column_exists(conn, "assets", "locked")? {
+        let _ = conn.execute("ALTER TABLE assets ADD COLUMN locked INTEGER NOT NULL DEFAULT 0", []);
+    }
+    if !column_exists(conn, "scan_paths", "archive_label")? {
+        let _ = conn.execute("ALTER TABLE scan_paths ADD COLUMN archive_label TEXT", []);
+    }
+    // Comma-separated date sources, see pipeline::dates
+    if !column_exists(conn, "scan_paths", "date_priority")? {
+        let _ = conn.execute("ALTER TABLE scan_paths ADD COLUMN date_priority TEXT", []);
+    }
+    // Soft scan limits, and the JSON report of the last scan they stopped; see pipeline::quota
+    for (column, kind) in [("max_assets", "INTEGER"), ("max_bytes", "INTEGER"), ("quota_hit", "TEXT")] {
+        if !column_exists(conn, "scan_paths", column)? {
+            let _ = conn.execute(&format!("ALTER TABLE scan_paths ADD COLUMN {} {}", column, kind), []);
+        }
+    }
+    // Messaging app an asset was received through, see pipeline::messaging
+    if !column_exists(conn, "assets", "source_app")? {
+        let _ = conn.execute("ALTER TABLE assets ADD COLUMN source_app TEXT", []);
+    }
+    // Motion half of a live photo, see writer::link_live_photos; existing libraries are paired once
+    let has_live_photos = column_exists(conn, "assets", "live_photo_video_id")?;
+    if !has_live_photos {
+        let _ = conn.execute("ALTER TABLE assets ADD COLUMN live_photo_video_id INTEGER", []);
+    }
+    // When the asset was first indexed (Unix seconds), for GET /assets/new. Assets indexed before
+    // this was recorded fall back to their file's mtime, so an upgrade doesn't mark them all new.
+    if !column_exists(conn, "assets", "ingested_at")? {
+        let _ = conn.execute("ALTER TABLE assets ADD COLUMN ingested_at INTEGER", []);
+        conn.execute("UPDATE assets SET ingested_at = mtime_ns / 1000000000 WHERE ingested_at IS NULL", [])?;
+    }
+    // Pets are recognized alongside people and kept apart from them
+    for table in ["persons", "face_embeddings"] {
+        if !column_exists(conn, table, "subject_type")? {
+            let _ = conn.execute(&format!("ALTER TABLE {} ADD COLUMN subject_type TEXT NOT NULL DEFAULT 'person'", table), []);
+        }
+    }
+    // Face chosen as a person's avatar, see PUT /persons/:id/cover
+    if !column_exists(conn, "persons", "cover_face_id")? {
+        let _ = conn.execute("ALTER TABLE persons ADD COLUMN cover_face_id INTEGER", []);
+    }
+    // Include/exclude globs, one per line
+    for column in ["include_patterns", "exclude_patterns"] {
+        if !column_exists(conn, "scan_paths", column)? {
+            let _ = conn.execute(&format!("ALTER TABLE scan_paths ADD COLUMN {} TEXT", column), []);
+        }
+    }
+
+    // Root-relative paths: the scan path an asset was found under, and its path below it
+    let has_path_id = column_exists(conn, "assets", "path_id")?;
+    if !has_path_id {
+        let _ = conn.execute("ALTER TABLE assets ADD COLUMN path_id INTEGER REFERENCES scan_paths(id) ON DELETE SET NULL", []);
+        let _ = conn.execute("ALTER TABLE assets ADD COLUMN rel_path TEXT", []);
+    }
+    // Tags predicted by the classifier carry its confidence; NULL marks a tag someone added
+    if !column_exists(conn, "asset_tags", "confidence")? {
+        let _ = conn.execute("ALTER TABLE asset_tags ADD COLUMN confidence REAL", []);
+    }
+
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_assets_owner ON assets(owner_id);
+         CREATE INDEX IF NOT EXISTS idx_albums_owner ON albums(owner_id);
+         CREATE INDEX IF NOT EXISTS idx_assets_path_id ON assets(path_id);
+         CREATE INDEX IF NOT EXISTS idx_assets_ingested_at ON assets(ingested_at);",
+    )?;
+    if !has_path_id {
+        super::writer::assign_asset_roots(conn, None)?;
+    }
+    if !has_live_photos {
+        super::writer::link_live_photos(conn, None)?;
+    }
+
+    // assets.path and dirname are absolute copies kept for lookups and display; moving a
+    // scan path rebuilds them (and the search index) from rel_path. Roots without a '/' are
+    // Windows paths and get a '\' separator. Recreated on every start so fixes reach old databases.
+    conn.execute_batch(
+        r"DROP TRIGGER IF EXISTS scan_paths_relocate;
+          CREATE TRIGGER scan_paths_relocate AFTER UPDATE OF path ON scan_paths
+          WHEN OLD.path != NEW.path
+          BEGIN
+            INSERT INTO fts_assets(fts_assets, rowid, filename, dirname, path)
+              SELECT 'delete', id, filename, dirname, path FROM assets WHERE path_id = NEW.id;
+            UPDATE assets SET
+              path = rtrim(NEW.path, '/\') || CASE WHEN instr(NEW.path, '/') > 0 THEN '/' ELSE '\' END || rel_path,
+              dirname = rtrim(NEW.path, '/\') || substr(dirname, length(rtrim(OLD.path, '/\')) + 1)
+            WHERE path_id = NEW.id AND rel_path IS NOT NULL;
+            INSERT INTO fts_assets(rowid, filename, dirname, path)
+              SELECT id, filename, dirname, path FROM assets WHERE path_id = NEW.id;
+          END;",
+    )?;
+
+    // Free-text search document per asset (rowid = asset id): filename and folder tokens, tags,
+    // album and person names, the `caption` property and recognized text. Triggers keep it current.
+    let mut had_search = conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE name = 'fts_search'", [], |r| r.get::<_, i64>(0))? > 0;
+    if had_search && !column_exists(conn, "fts_search", "ocr")? {
+        // FTS5 tables can't gain columns: rebuild the index, its view and the triggers filling it
+        let triggers: Vec<String> = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'trigger' AND name LIKE 'search\\_%' ESCAPE '\\'")?
+            .query_map([], |r| r.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        for name in triggers {
+            conn.execute_batch(&format!("DROP TRIGGER IF EXISTS {}", name))?;
+        }
+        conn.execute_batch("DROP VIEW IF EXISTS search_docs; DROP TABLE IF EXISTS fts_search;")?;
+        had_search = false;
+    }
+    conn.execute_batch(
+        r"CREATE VIRTUAL TABLE IF NOT EXISTS fts_search USING fts5(filename, folders, tags, albums, people, caption, ocr);
+          CREATE VIEW IF NOT EXISTS search_docs AS
+            SELECT a.id, a.filename, a.dirname AS folders,
+              (SELECT group_concat(t.name, ' ') FROM asset_tags at JOIN tags t ON t.id = at.tag_id WHERE at.asset_id = a.id) AS tags,
+              (SELECT group_concat(al.name, ' ') FROM album_assets aa JOIN albums al ON al.id = aa.album_id WHERE aa.asset_id = a.id) AS albums,
+              (SELECT group_concat(DISTINCT p.name) FROM face_embeddings f JOIN persons p ON p.id = f.person_id WHERE f.asset_id = a.id) AS people,
+              (SELECT value FROM asset_properties ap WHERE ap.asset_id = a.id AND ap.key = 'caption') AS caption,
+              (SELECT text FROM asset_text tx WHERE tx.asset_id = a.id) AS ocr
+            FROM assets a;
+          CREATE TRIGGER IF NOT EXISTS search_asset_delete AFTER DELETE ON assets
+          BEGIN
+            DELETE FROM fts_search WHERE rowid = OLD.id;
+          END;",
+    )?;
+    // (trigger, event, assets to re-index, condition)
+    let reindex = [
+        ("search_asset_insert", "INSERT ON assets", "NEW.id", "1"),
+        ("search_asset_rename", "UPDATE OF filename, dirname ON assets", "NEW.id", "1"),
+        ("search_tag_add", "INSERT ON asset_tags", "NEW.asset_id", "1"),
+        ("search_tag_remove", "DELETE ON asset_tags", "OLD.asset_id", "1"),
+        ("search_tag_rename", "UPDATE OF name ON tags", "SELECT asset_id FROM asset_tags WHERE tag_id = NEW.id", "1"),
+        ("search_album_add", "INSERT ON album_assets", "NEW.asset_id", "1"),
+        ("search_album_remove", "DELETE ON album_assets", "OLD.asset_id", "1"),
+        ("search_album_rename", "UPDATE OF name ON albums", "SELECT asset_id FROM album_assets WHERE album_id = NEW.id", "1"),
+        ("search_face_add", "INSERT ON face_embeddings", "NEW.asset_id", "NEW.person_id IS NOT NULL"),
+        ("search_face_assign", "UPDATE OF person_id ON face_embeddings", "NEW.asset_id", "OLD.person_id IS NOT NEW.person_id"),
+        ("search_face_remove", "DELETE ON face_embeddings", "OLD.asset_id", "OLD.person_id IS NOT NULL"),
+        ("search_person_rename", "UPDATE OF name ON persons", "SELECT asset_id FROM face_embeddings WHERE person_id = NEW.id", "OLD.name IS NOT NEW.name"),
+        ("search_person_remove", "DELETE ON persons", "SELECT asset_id FROM face_embeddings WHERE person_id = OLD.id", "1"),
+        ("search_caption_set", "INSERT ON asset_properties", "NEW.asset_id", "NEW.key = 'caption'"),
+        ("search_caption_update", "UPDATE ON asset_properties", "NEW.asset_id", "NEW.key = 'caption' OR OLD.key = 'caption'"),
+        ("search_caption_remove", "DELETE ON asset_properties", "OLD.asset_id", "OLD.key = 'caption'"),
+        ("search_ocr_set", "INSERT ON asset_text", "NEW.asset_id", "1"),
+        ("search_ocr_update", "UPDATE ON asset_text", "NEW.asset_id", "1"),
+        ("search_ocr_remove", "DELETE ON asset_text", "OLD.asset_id", "1"),
+    ];
+    for (name, event, ids, when) in reindex {
+        conn.execute_batch(&format!(
+            "CREATE TRIGGER IF NOT EXISTS {name} AFTER {event} WHEN {when}
+             BEGIN
+               DELETE FROM fts_search WHERE rowid IN ({ids});
+               INSERT INTO fts_search(rowid, filename, folders, tags, albums, people, caption, ocr)
+                 SELECT id, filename, folders, tags, albums, people, caption, ocr FROM search_docs WHERE id IN ({ids});
+             END;"
+        ))?;
+    }
+    if !had_search {
+        conn.execute(
+            "INSERT INTO fts_search(rowid, filename, folders, tags, albums, people, caption, ocr)
+             SELECT id, filename, folders, tags, albums, people, caption, ocr FROM search_docs",
+            [],
+        )?;
     }
 
     Ok(())
 }
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let mut rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    let found = rows.any(|name| name.is_ok_and(|name| name == column));
+    Ok(found)
+}
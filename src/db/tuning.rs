@@ -0,0 +1,116 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const MIB: u64 = 1024 * 1024;
+/// SQLite's page cache default (2000 KiB), used when RAM can't be detected
+const DEFAULT_CACHE_BYTES: u64 = 2000 * 1024;
+const DEFAULT_MMAP_BYTES: u64 = 256 * MIB;
+/// SQLite's default compile-time mmap ceiling; larger values are silently clamped
+const MAX_MMAP_BYTES: u64 = 0x7fff_0000;
+
+/// Page cache and mmap sizes saved through PUT /settings/sqlite; unset fields are auto-tuned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SqliteOverrides {
+    /// Page cache per connection
+    pub cache_mb: Option<u64>,
+    /// Memory-mapped I/O size; 0 turns mmap off
+    pub mmap_mb: Option<u64>,
+}
+
+/// Per-connection cache_size and mmap_size, sized from RAM and the database file at startup
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SqliteTuning {
+    pub cache_kib: u64,
+    pub mmap_bytes: u64,
+    /// Physical RAM, or the cgroup limit if lower; None if it couldn't be read
+    pub memory_bytes: Option<u64>,
+    pub db_bytes: u64,
+    pub overrides: SqliteOverrides,
+}
+
+impl Default for SqliteTuning {
+    fn default() -> Self {
+        Self::auto(None, 0)
+    }
+}
+
+impl SqliteTuning {
+    pub fn auto(memory_bytes: Option<u64>, db_bytes: u64) -> Self {
+        let (cache, mmap) = match memory_bytes {
+            Some(ram) => (
+                // The pool and the writer each get one, so about 4% of RAM in total;
+                // a cache bigger than the file itself is wasted
+                (ram / 256).min(db_bytes).clamp(2 * MIB, 64 * MIB),
+                // Room for the library to grow, but never more than a quarter of RAM
+                db_bytes.saturating_mul(2).max(256 * MIB).min(ram / 4).min(MAX_MMAP_BYTES),
+            ),
+            None => (DEFAULT_CACHE_BYTES, DEFAULT_MMAP_BYTES),
+        };
+        Self { cache_kib: cache / 1024, mmap_bytes: mmap, memory_bytes, db_bytes, overrides: SqliteOverrides::default() }
+    }
+
+    /// Auto-tune for the database at `db_path` on this machine
+    pub fn detect(db_path: &Path) -> Self {
+        let db_bytes = std::fs::metadata(db_path).map(|m| m.len()).unwrap_or(0);
+        Self::auto(system_memory(), db_bytes)
+    }
+
+    pub fn with_overrides(mut self, overrides: &SqliteOverrides) -> Self {
+        if let Some(mb) = overrides.cache_mb {
+            self.cache_kib = mb * 1024;
+        }
+        if let Some(mb) = overrides.mmap_mb {
+            self.mmap_bytes = mb * MIB;
+        }
+        self.overrides = *overrides;
+        self
+    }
+
+    pub fn apply(&self, conn: &Connection) -> rusqlite::Result<()> {
+        // A negative cache_size is in KiB rather than pages
+        conn.pragma_update(None, "cache_size", -(self.cache_kib as i64))?;
+        conn.pragma_update(None, "mmap_size", self.mmap_bytes as i64)?;
+        Ok(())
+    }
+}
+
+/// Total RAM in bytes, capped by the container's memory limit
+pub fn system_memory() -> Option<u64> {
+    let mut sys = sysinfo::System::new();
+    sys.refresh_memory();
+    let total = Some(sys.total_memory()).filter(|m| *m > 0)?;
+    Some(match sys.cgroup_limits() {
+        Some(limits) if limits.total_memory > 0 => limits.total_memory.min(total),
+        _ => total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * MIB;
+
+    #[test]
+    fn test_sqlite_auto_tune() {
+        // Low-RAM NAS with a large library
+        let nas = SqliteTuning::auto(Some(GIB), 5 * GIB);
+        assert_eq!((nas.cache_kib, nas.mmap_bytes), (4 * 1024, 256 * MIB));
+        // Big box, big library: both capped
+        let big = SqliteTuning::auto(Some(32 * GIB), 5 * GIB);
+        assert_eq!((big.cache_kib, big.mmap_bytes), (64 * 1024, MAX_MMAP_BYTES));
+        // Small library: cache no bigger than the file
+        let small = SqliteTuning::auto(Some(32 * GIB), 10 * MIB);
+        assert_eq!((small.cache_kib, small.mmap_bytes), (10 * 1024, 256 * MIB));
+        assert_eq!(SqliteTuning::default().mmap_bytes, DEFAULT_MMAP_BYTES);
+
+        let tuned = small.with_overrides(&SqliteOverrides { cache_mb: Some(16), mmap_mb: Some(0) });
+        assert_eq!((tuned.cache_kib, tuned.mmap_bytes), (16 * 1024, 0));
+        let conn = Connection::open_in_memory().unwrap();
+        tuned.apply(&conn).unwrap();
+        let cache: i64 = conn.query_row("PRAGMA cache_size", [], |r| r.get(0)).unwrap();
+        assert_eq!(cache, -16 * 1024);
+    }
+}
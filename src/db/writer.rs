@@ -1,13 +1,16 @@
 use anyhow::Result;
-use rusqlite::{params, Connection, Transaction};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
 use tokio::sync::mpsc::{Receiver, Sender};
 use crate::pipeline::thumb::ThumbJob;
 use crate::pipeline::QueueGauges;
+use crate::pipeline::plugins::{Event, IngestedAsset};
 #[cfg(feature = "facial-recognition")]
 use crate::pipeline::face::{FaceJob, FaceProcessor};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use crate::stats::Stats;
+use crate::stats::{PipelineStage, Stats};
+use crate::models::album::SmartAlbumRules;
+use crate::models::rule::{self, RuleActions, RuleConditions};
 #[cfg(feature = "facial-recognition")]
 use std::path::PathBuf;
 #[cfg(feature = "facial-recognition")]
@@ -16,6 +19,12 @@ use tokio::runtime::Handle;
 
 // Type alias for FTS row tuple
 pub type FtsRow = (i64, String, String, String, Option<Vec<u8>>, String);
+// Candidate stack: optional burst key plus (asset id, filename) members
+type StackGroup = (Option<String>, Vec<(i64, String)>);
+// Stacking input: (id, filename, owner, camera, taken_at)
+type StackShot = (i64, String, Option<i64>, Option<String>, i64);
+// Owner plus camera or burst key: shots only stack within one of these
+type StackSource = (Option<i64>, String);
 
 // Configuration struct for run_writer
 pub struct WriterConfig {
@@ -74,12 +83,82 @@ pub struct DbWriteItem {
     pub video_codec: Option<String>,
     pub mime: String,
     pub flags: i64,
+    /// Asset properties found by the filename extractors; set on every write, never cleared
+    pub properties: Vec<(String, String)>,
+    /// Messaging app the file was received through; like `properties`, never cleared
+    pub source_app: Option<crate::pipeline::messaging::SourceApp>,
+}
+
+/// An indexed asset with the same content whose file is gone from its stored path,
+/// i.e. the file was moved or renamed since the last scan. Copies are not renames.
+fn find_renamed_asset(tx: &Transaction<'_>, it: &DbWriteItem) -> Result<Option<(i64, String, String, String)>> {
+    let Some(sha256) = it.sha256.as_ref().filter(|s| !s.is_empty()) else {
+        return Ok(None);
+    };
+    let known: bool = tx.query_row("SELECT EXISTS(SELECT 1 FROM assets WHERE path = ?1)", params![it.path], |r| r.get(0))?;
+    if known {
+        return Ok(None);
+    }
+    // Archived originals may just be offline, so a copy elsewhere is not a move
+    let mut stmt = tx.prepare("SELECT id, path, dirname, filename FROM assets WHERE sha256 = ?1 AND size_bytes = ?2 AND path != ?3 AND archived = 0 ORDER BY id")?;
+    let candidates = stmt
+        .query_map(params![sha256, it.size_bytes, it.path], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))?
+        .collect::<rusqlite::Result<Vec<(i64, String, String, String)>>>()?;
+    Ok(candidates.into_iter().find(|(_, path, _, _)| !std::path::Path::new(path).exists()))
+}
+
+/// Point a moved asset at its new path, keeping its id so albums, tags and faces stay attached
+fn update_renamed_item(tx: &Transaction<'_>, id: i64, old: (&str, &str, &str), it: &DbWriteItem) -> Result<()> {
+    tx.execute(
+        "UPDATE assets SET path=?1, dirname=?2, filename=?3, ext=?4, size_bytes=?5, mtime_ns=?6, ctime_ns=?7, sha256=?8, xxh64=?9, taken_at=?10, width=?11, height=?12, duration_ms=?13, camera_make=?14, camera_model=?15, lens_model=?16, iso=?17, fnumber=?18, exposure=?19, video_codec=?20, mime=?21, flags=?22 WHERE id=?23",
+        params![
+            it.path,
+            it.dirname,
+            it.filename,
+            it.ext,
+            it.size_bytes,
+            it.mtime_ns,
+            it.ctime_ns,
+            it.sha256,
+            it.xxh64,
+            it.taken_at,
+            it.width,
+            it.height,
+            it.duration_ms,
+            it.camera_make,
+            it.camera_model,
+            it.lens_model,
+            it.iso,
+            it.fnumber,
+            it.exposure,
+            it.video_codec,
+            it.mime,
+            it.flags,
+            id,
+        ],
+    )?;
+    // fts_assets is contentless, so the old terms are removed by replaying them as a delete;
+    // commit_batch indexes the new path
+    let (old_path, old_dirname, old_filename) = old;
+    let _ = tx.execute(
+        "INSERT INTO fts_assets(fts_assets, rowid, filename, dirname, path) VALUES ('delete', ?1, ?2, ?3, ?4)",
+        params![id, old_filename, old_dirname, old_path],
+    );
+    Ok(())
 }
 
 fn upsert_item(tx: &Transaction<'_>, it: &DbWriteItem) -> Result<i64> {
+    if let Some((id, old_path, old_dirname, old_filename)) = find_renamed_asset(tx, it)? {
+        tracing::info!("asset {} moved: {:?} -> {:?}", id, old_path, it.path);
+        update_renamed_item(tx, id, (&old_path, &old_dirname, &old_filename), it)?;
+        return Ok(id);
+    }
+
+    // ingested_at is only set by the insert, so rewriting a known file keeps its original value
+    let ingested_at = chrono::Utc::now().timestamp();
     // Try RETURNING first (SQLite 3.35.0+ supports RETURNING with ON CONFLICT)
-    let sql = "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, sha256, xxh64, taken_at, width, height, duration_ms, camera_make, camera_model, lens_model, iso, fnumber, exposure, video_codec, mime, flags)
-         VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16,?17,?18,?19,?20,?21,?22)
+    let sql = "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, sha256, xxh64, taken_at, width, height, duration_ms, camera_make, camera_model, lens_model, iso, fnumber, exposure, video_codec, mime, flags, ingested_at)
+         VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16,?17,?18,?19,?20,?21,?22,?23)
          ON CONFLICT(path) DO UPDATE SET dirname=excluded.dirname, filename=excluded.filename, ext=excluded.ext, size_bytes=excluded.size_bytes, mtime_ns=excluded.mtime_ns, ctime_ns=excluded.ctime_ns, sha256=excluded.sha256, xxh64=excluded.xxh64, taken_at=excluded.taken_at, width=excluded.width, height=excluded.height, duration_ms=excluded.duration_ms, camera_make=excluded.camera_make, camera_model=excluded.camera_model, lens_model=excluded.lens_model, iso=excluded.iso, fnumber=excluded.fnumber, exposure=excluded.exposure, video_codec=excluded.video_codec, mime=excluded.mime, flags=excluded.flags
          RETURNING id";
     
@@ -107,13 +186,14 @@ fn upsert_item(tx: &Transaction<'_>, it: &DbWriteItem) -> Result<i64> {
         it.video_codec,
         it.mime,
         it.flags,
+        ingested_at,
     ], |r| r.get::<_, i64>(0)) {
         Ok(id) => Ok(id),
         Err(_) => {
             // Fallback: execute then query (for older SQLite versions)
             tx.execute(
-                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, sha256, xxh64, taken_at, width, height, duration_ms, camera_make, camera_model, lens_model, iso, fnumber, exposure, video_codec, mime, flags)
-                 VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16,?17,?18,?19,?20,?21,?22)
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, sha256, xxh64, taken_at, width, height, duration_ms, camera_make, camera_model, lens_model, iso, fnumber, exposure, video_codec, mime, flags, ingested_at)
+                 VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16,?17,?18,?19,?20,?21,?22,?23)
                  ON CONFLICT(path) DO UPDATE SET dirname=excluded.dirname, filename=excluded.filename, ext=excluded.ext, size_bytes=excluded.size_bytes, mtime_ns=excluded.mtime_ns, ctime_ns=excluded.ctime_ns, sha256=excluded.sha256, xxh64=excluded.xxh64, taken_at=excluded.taken_at, width=excluded.width, height=excluded.height, duration_ms=excluded.duration_ms, camera_make=excluded.camera_make, camera_model=excluded.camera_model, lens_model=excluded.lens_model, iso=excluded.iso, fnumber=excluded.fnumber, exposure=excluded.exposure, video_codec=excluded.video_codec, mime=excluded.mime, flags=excluded.flags",
                 params![
                     it.path,
@@ -138,6 +218,7 @@ fn upsert_item(tx: &Transaction<'_>, it: &DbWriteItem) -> Result<i64> {
                     it.video_codec,
                     it.mime,
                     it.flags,
+                    ingested_at,
                 ],
             )?;
             tx.query_row("SELECT id FROM assets WHERE path = ?", params![it.path], |r| r.get(0))
@@ -192,15 +273,21 @@ fn run_writer_impl(config: WriterConfig) -> Result<()> {
                         #[cfg(feature = "facial-recognition")]
                         db_path: config.db_path.as_ref(),
                     };
+                    let started = Instant::now();
                     match commit_batch(commit_config) {
                         Ok(_) => {
                             // Track files committed to SQLite (this is where files are actually committed in this codebase)
                             if let Some(s) = &config.stats {
                                 s.inc_files_committed(n as u64);
                                 s.inc_bytes(bytes);
+                                s.observe_stage(PipelineStage::DbWrite, started.elapsed());
+                                s.observe_db_batch(n);
                             }
                         }
                         Err(e) => {
+                            if let Some(s) = &config.stats {
+                                s.inc_stage_errors(PipelineStage::DbWrite);
+                            }
                             eprintln!("ERROR committing batch: {:?}", e);
                             return Err(e);
                         }
@@ -228,15 +315,21 @@ fn run_writer_impl(config: WriterConfig) -> Result<()> {
                         #[cfg(feature = "facial-recognition")]
                         db_path: config.db_path.as_ref(),
                     };
+                    let started = Instant::now();
                     match commit_batch(commit_config) {
                         Ok(_) => {
                             // Track files committed to SQLite (this is where files are actually committed in this codebase)
                             if let Some(s) = &config.stats {
                                 s.inc_files_committed(n as u64);
                                 s.inc_bytes(bytes);
+                                s.observe_stage(PipelineStage::DbWrite, started.elapsed());
+                                s.observe_db_batch(n);
                             }
                         }
                         Err(e) => {
+                            if let Some(s) = &config.stats {
+                                s.inc_stage_errors(PipelineStage::DbWrite);
+                            }
                             eprintln!("ERROR committing timeout batch: {:?}", e);
                             return Err(e);
                         }
@@ -263,15 +356,21 @@ fn run_writer_impl(config: WriterConfig) -> Result<()> {
             #[cfg(feature = "facial-recognition")]
             db_path: config.db_path.as_ref(),
         };
+        let started = Instant::now();
         match commit_batch(commit_config) {
             Ok(_) => {
                 // Track files committed to SQLite (this is where files are actually committed in this codebase)
                 if let Some(s) = &config.stats {
                     s.inc_files_committed(n as u64);
                     s.inc_bytes(bytes);
+                    s.observe_stage(PipelineStage::DbWrite, started.elapsed());
+                    s.observe_db_batch(n);
                 }
             }
             Err(e) => {
+                if let Some(s) = &config.stats {
+                    s.inc_stage_errors(PipelineStage::DbWrite);
+                }
                 eprintln!("ERROR committing final batch: {:?}", e);
                 return Err(e);
             }
@@ -280,6 +379,15 @@ fn run_writer_impl(config: WriterConfig) -> Result<()> {
     Ok(())
 }
 
+/// Regroup the stacks around the files just written so their bursts are picked up. A failure
+/// only leaves the old stacks in place, so it doesn't stop the writer.
+fn restack(conn: &Connection, written_ids: &[i64]) {
+    match restack_assets(conn, written_ids, stack_window_secs()) {
+        Ok(n) => tracing::debug!("Regrouped {} stacks after writing {} files", n, written_ids.len()),
+        Err(e) => tracing::warn!("Could not regroup stacks: {}", e),
+    }
+}
+
 fn commit_batch(config: CommitBatchConfig<'_>) -> Result<()> {
     let CommitBatchConfig {
         conn,
@@ -300,14 +408,32 @@ fn commit_batch(config: CommitBatchConfig<'_>) -> Result<()> {
     let mut image_assets_for_face_detection: Vec<(i64, PathBuf, String)> = Vec::new();
     
     let tx = conn.unchecked_transaction()?;
+    let rules = crate::db::query::list_rules(&tx)?;
+    let now = chrono::Utc::now().timestamp();
+    let mut written_ids = Vec::with_capacity(buf.len());
+    let mut clear_journal = tx.prepare("DELETE FROM pending_jobs WHERE path = ?1")?;
     for it in buf.drain(..) {
         match upsert_item(&tx, &it) {
             Ok(id) => {
+                written_ids.push(id);
+                clear_journal.execute(params![it.path])?;
                 fts_rows.push((id, it.filename.clone(), it.dirname.clone(), it.path.clone(), it.sha256.clone(), it.mime.clone()));
+                if !it.properties.is_empty() {
+                    let properties: Vec<(String, Option<String>)> = it.properties.iter().map(|(k, v)| (k.clone(), Some(v.clone()))).collect();
+                    write_asset_properties(&tx, id, &properties)?;
+                }
+                if let Some(app) = it.source_app {
+                    tx.execute("UPDATE assets SET source_app = ?1 WHERE id = ?2", params![app.as_str(), id])?;
+                    add_tags_to_asset(&tx, id, &[app.as_str().to_string()])?;
+                }
+                let skip_faces = apply_rules(&tx, &rules, id, &it, now)?;
+                if skip_faces {
+                    continue;
+                }
                 
-                // Collect image assets for potential face detection
+                // Collect image and video assets for potential face detection
                 #[cfg(feature = "facial-recognition")]
-                if it.mime.starts_with("image/") {
+                if it.mime.starts_with("image/") || it.mime.starts_with("video/") {
                     image_assets_for_face_detection.push((id, PathBuf::from(&it.path), it.ext.clone()));
                 }
             }
@@ -317,7 +443,23 @@ fn commit_batch(config: CommitBatchConfig<'_>) -> Result<()> {
             }
         }
     }
+    drop(clear_journal);
+    assign_asset_owners(&tx, Some(&written_ids))?;
+    assign_asset_roots(&tx, Some(&written_ids))?;
+    link_live_photos(&tx, Some(&written_ids))?;
     tx.commit()?;
+    restack(conn, &written_ids);
+    let plugins = crate::pipeline::plugins::registry();
+    if !plugins.is_empty() {
+        for (id, _, _, path, sha, mime) in fts_rows.iter() {
+            plugins.notify(Event::AssetIngested(IngestedAsset {
+                asset_id: *id,
+                path: path.clone(),
+                mime: mime.clone(),
+                sha256: sha.as_deref().map(hex::encode),
+            }));
+        }
+    }
     if !fts_rows.is_empty() {
         let tx2 = conn.unchecked_transaction()?;
         {
@@ -333,6 +475,8 @@ fn commit_batch(config: CommitBatchConfig<'_>) -> Result<()> {
                                     path: chunk.3.clone(),
                                     sha256_hex: hex::encode(&sha),
                                     mime: chunk.5.clone(),
+                                    force: false,
+                                    queued_at: std::time::Instant::now(),
                                 });
                                 gauges.thumb.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                             } else {
@@ -402,8 +546,14 @@ fn commit_batch(config: CommitBatchConfig<'_>) -> Result<()> {
             // Normalize extension (lowercase, remove leading dot)
             let ext_normalized = ext.trim_start_matches('.').to_lowercase();
             
-            // Check if extension is allowed
-            if !allowed_exts.iter().any(|&allowed| allowed.to_lowercase() == ext_normalized) {
+            // Check if extension is allowed; videos need frame sampling on and their extension not excluded
+            let is_video = crate::pipeline::face::VIDEO_EXTENSIONS.contains(&ext_normalized.as_str());
+            let allowed = if is_video {
+                crate::pipeline::face::video_frame_count() > 0 && !excluded_extensions.contains(&ext_normalized)
+            } else {
+                allowed_exts.iter().any(|&allowed| allowed.to_lowercase() == ext_normalized)
+            };
+            if !allowed {
                 continue;
             }
             
@@ -419,11 +569,14 @@ fn commit_batch(config: CommitBatchConfig<'_>) -> Result<()> {
             }
             
             // Queue for face detection
-            if face_tx_ref.try_send(FaceJob { asset_id, image_path: path }).is_err() {
+            if face_tx_ref.try_send(FaceJob { asset_id, image_path: path, queued_at: std::time::Instant::now() }).is_err() {
                 // Channel is full or closed - skip this file, it will be picked up later
                 continue;
             }
             gauges.face.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            if let Err(e) = journal_face_jobs(conn, &[asset_id]) {
+                tracing::warn!("Could not journal face job for asset {}: {}", asset_id, e);
+            }
         }
     }
     
@@ -433,10 +586,16 @@ fn commit_batch(config: CommitBatchConfig<'_>) -> Result<()> {
 // Face and Person write functions
 #[cfg(feature = "facial-recognition")]
 pub fn insert_person(conn: &Connection, name: Option<String>) -> Result<i64> {
+    insert_subject(conn, name, crate::pipeline::face::Subject::Person)
+}
+
+/// A new person or pet
+#[cfg(feature = "facial-recognition")]
+pub fn insert_subject(conn: &Connection, name: Option<String>, subject: crate::pipeline::face::Subject) -> Result<i64> {
     let created_at = chrono::Utc::now().timestamp();
     conn.execute(
-        "INSERT INTO persons (name, created_at) VALUES (?1, ?2)",
-        params![name, created_at],
+        "INSERT INTO persons (name, created_at, subject_type) VALUES (?1, ?2, ?3)",
+        params![name, created_at, subject.as_str()],
     )?;
     Ok(conn.last_insert_rowid())
 }
@@ -450,6 +609,13 @@ pub fn update_person_name(conn: &Connection, person_id: i64, name: Option<String
     Ok(updated > 0)
 }
 
+/// Choose the face shown for a person, or None to go back to picking one automatically
+#[cfg(feature = "facial-recognition")]
+pub fn set_person_cover(conn: &Connection, person_id: i64, face_id: Option<i64>) -> Result<bool> {
+    let updated = conn.execute("UPDATE persons SET cover_face_id = ?1 WHERE id = ?2", params![face_id, person_id])?;
+    Ok(updated > 0)
+}
+
 #[cfg(feature = "facial-recognition")]
 pub fn delete_person(conn: &Connection, person_id: i64) -> Result<bool> {
     // First, unlink all face embeddings from this person
@@ -457,6 +623,7 @@ pub fn delete_person(conn: &Connection, person_id: i64) -> Result<bool> {
         "UPDATE face_embeddings SET person_id = NULL WHERE person_id = ?1",
         params![person_id],
     )?;
+    conn.execute("DELETE FROM face_feedback WHERE person_id = ?1", params![person_id])?;
     
     // Then delete the person
     let deleted = conn.execute("DELETE FROM persons WHERE id = ?1", params![person_id])?;
@@ -489,6 +656,7 @@ pub fn merge_persons(conn: &Connection, source_person_id: i64, target_person_id:
         "UPDATE face_embeddings SET person_id = ?1 WHERE person_id = ?2",
         params![target_person_id, source_person_id],
     )?;
+    move_face_feedback(&tx, source_person_id, target_person_id)?;
     
     // Delete the person_profiles entry for the source person
     // (must be done before deleting the person due to foreign key constraint)
@@ -523,45 +691,101 @@ pub struct PersonProfileSummary {
     pub centroid_dim: usize,
 }
 
+/// How much more a face a user confirmed counts toward its person's centroid than one that was
+/// only clustered or matched automatically
 #[cfg(feature = "facial-recognition")]
-pub fn rebuild_person_profile(conn: &Connection, person_id: i64) -> Result<Option<PersonProfileSummary>> {
-    use crate::db::query;
+const CONFIRMED_FACE_WEIGHT: f32 = 3.0;
 
-    let embeddings = query::get_person_face_embeddings(conn, person_id)?;
-    if embeddings.is_empty() {
-        conn.execute("DELETE FROM person_profiles WHERE person_id = ?1", params![person_id])?;
-        return Ok(None);
-    }
-
-    let mut centroid = vec![0f32; embeddings[0].len()];
+/// Normalized weighted mean of (embedding, confirmed) pairs and how many faces went into it;
+/// embeddings whose length differs from the first are skipped
+#[cfg(feature = "facial-recognition")]
+fn weighted_centroid(embeddings: &[(Vec<f32>, bool)]) -> Option<(Vec<f32>, usize)> {
+    let dim = embeddings.first()?.0.len();
+    let mut centroid = vec![0f32; dim];
     let mut used = 0usize;
-
-    for emb in embeddings.iter() {
-        if emb.len() != centroid.len() {
-            continue;
-        }
-        for (idx, value) in emb.iter().enumerate() {
-            centroid[idx] += value;
+    for (emb, confirmed) in embeddings.iter().filter(|(emb, _)| emb.len() == dim) {
+        let weight = if *confirmed { CONFIRMED_FACE_WEIGHT } else { 1.0 };
+        for (sum, value) in centroid.iter_mut().zip(emb) {
+            *sum += value * weight;
         }
         used += 1;
     }
-
     if used == 0 {
-        conn.execute("DELETE FROM person_profiles WHERE person_id = ?1", params![person_id])?;
-        return Ok(None);
+        return None;
     }
-
-    let inv = 1.0f32 / used as f32;
-    for value in centroid.iter_mut() {
-        *value *= inv;
-    }
-
     let norm = centroid.iter().map(|v| v * v).sum::<f32>().sqrt();
     if norm > 0.0 {
         for value in centroid.iter_mut() {
             *value /= norm;
         }
     }
+    Some((centroid, used))
+}
+
+/// Remember that a face does (`confirmed`) or doesn't show a person
+#[cfg(feature = "facial-recognition")]
+pub fn record_face_feedback(conn: &Connection, face_id: i64, person_id: i64, confirmed: bool) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO face_feedback (face_id, person_id, verdict, created_at) VALUES (?1, ?2, ?3, strftime('%s','now'))",
+        params![face_id, person_id, if confirmed { 1 } else { -1 }],
+    )?;
+    Ok(())
+}
+
+/// Confirm that a face shows a person, assigning it to them, or reject it, unassigning it if it
+/// was theirs. Every affected profile is rebuilt. Returns the face's person afterwards, or None
+/// if the face doesn't exist.
+#[cfg(feature = "facial-recognition")]
+pub fn set_face_feedback(conn: &Connection, face_id: i64, person_id: i64, confirmed: bool) -> Result<Option<Option<i64>>> {
+    let tx = conn.unchecked_transaction()?;
+    let Some(current) = tx
+        .query_row("SELECT person_id FROM face_embeddings WHERE id = ?1", params![face_id], |r| r.get::<_, Option<i64>>(0))
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    record_face_feedback(&tx, face_id, person_id, confirmed)?;
+    let after = match (confirmed, current) {
+        (true, _) => Some(person_id),
+        (false, Some(p)) if p == person_id => None,
+        (false, other) => other,
+    };
+    if after != current {
+        update_face_person(&tx, face_id, after)?;
+        if let Some(prev) = current {
+            // Moving a face away from someone says it isn't them
+            if confirmed {
+                record_face_feedback(&tx, face_id, prev, false)?;
+            }
+            rebuild_person_profile(&tx, prev)?;
+        }
+    }
+    rebuild_person_profile(&tx, person_id)?;
+    tx.commit()?;
+    Ok(Some(after))
+}
+
+/// Carry a merged-away person's feedback over to the person they were merged into, dropping
+/// rejections that now contradict the faces the target holds
+#[cfg(feature = "facial-recognition")]
+fn move_face_feedback(conn: &Connection, source_person_id: i64, target_person_id: i64) -> Result<()> {
+    conn.execute("UPDATE OR REPLACE face_feedback SET person_id = ?2 WHERE person_id = ?1", params![source_person_id, target_person_id])?;
+    conn.execute(
+        "DELETE FROM face_feedback WHERE person_id = ?1 AND verdict < 0 AND face_id IN (SELECT id FROM face_embeddings WHERE person_id = ?1)",
+        params![target_person_id],
+    )?;
+    Ok(())
+}
+
+#[cfg(feature = "facial-recognition")]
+pub fn rebuild_person_profile(conn: &Connection, person_id: i64) -> Result<Option<PersonProfileSummary>> {
+    use crate::db::query;
+
+    let embeddings = query::get_person_face_embeddings(conn, person_id)?;
+    let Some((centroid, used)) = weighted_centroid(&embeddings) else {
+        conn.execute("DELETE FROM person_profiles WHERE person_id = ?1", params![person_id])?;
+        return Ok(None);
+    };
 
     let blob = encode_embedding(&centroid);
     conn.execute(
@@ -577,10 +801,95 @@ pub fn rebuild_person_profile(conn: &Connection, person_id: i64) -> Result<Optio
     }))
 }
 
+/// Centroids of every person with faces, building the profiles that are missing first
+#[cfg(feature = "facial-recognition")]
+pub fn person_centroids(conn: &Connection) -> Result<Vec<(i64, Vec<f32>)>> {
+    let missing: Vec<i64> = conn
+        .prepare(
+            "SELECT DISTINCT person_id FROM face_embeddings
+             WHERE person_id IS NOT NULL AND person_id NOT IN (SELECT person_id FROM person_profiles)",
+        )?
+        .query_map([], |r| r.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for person_id in missing {
+        rebuild_person_profile(conn, person_id)?;
+    }
+    crate::db::query::get_person_centroids(conn)
+}
+
+#[cfg(feature = "facial-recognition")]
+pub struct FaceAssignment {
+    /// Refreshed centroid of each person that gained faces
+    pub centroids: Vec<(i64, Option<Vec<f32>>)>,
+    /// Faces whose person was deleted or merged away in the meantime; they stay unassigned
+    pub orphaned: Vec<i64>,
+}
+
+/// Assign new faces (face id, person id) to existing persons and refresh their profiles
+#[cfg(feature = "facial-recognition")]
+pub fn assign_faces_to_persons(conn: &Connection, assignments: &[(i64, i64)]) -> Result<FaceAssignment> {
+    let mut touched: Vec<i64> = Vec::new();
+    let mut orphaned = Vec::new();
+    for &(face_id, person_id) in assignments {
+        let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM persons WHERE id = ?1)", params![person_id], |r| r.get(0))?;
+        if exists && update_face_person(conn, face_id, Some(person_id))? {
+            if !touched.contains(&person_id) {
+                touched.push(person_id);
+            }
+        } else {
+            orphaned.push(face_id);
+        }
+    }
+    let mut centroids = Vec::with_capacity(touched.len());
+    for person_id in touched {
+        rebuild_person_profile(conn, person_id)?;
+        centroids.push((person_id, crate::db::query::get_person_centroid(conn, person_id)?));
+    }
+    Ok(FaceAssignment { centroids, orphaned })
+}
+
+/// Move faces to `person_id` in one transaction, then refresh the profile of that person and of
+/// every person the faces were taken from. Returns the ids of the faces that exist.
+#[cfg(feature = "facial-recognition")]
+pub fn assign_faces_to_person(conn: &Connection, person_id: i64, face_ids: &[i64]) -> Result<Vec<i64>> {
+    let tx = conn.unchecked_transaction()?;
+    let mut assigned = Vec::new();
+    let mut previous: Vec<i64> = Vec::new();
+    {
+        let mut current = tx.prepare("SELECT person_id FROM face_embeddings WHERE id = ?1")?;
+        for &face_id in face_ids {
+            if assigned.contains(&face_id) {
+                continue;
+            }
+            let Some(prev) = current.query_row(params![face_id], |r| r.get::<_, Option<i64>>(0)).optional()? else {
+                continue;
+            };
+            update_face_person(&tx, face_id, Some(person_id))?;
+            record_face_feedback(&tx, face_id, person_id, true)?;
+            assigned.push(face_id);
+            if let Some(prev) = prev.filter(|p| *p != person_id) {
+                record_face_feedback(&tx, face_id, prev, false)?;
+                if !previous.contains(&prev) {
+                    previous.push(prev);
+                }
+            }
+        }
+    }
+    if !assigned.is_empty() {
+        rebuild_person_profile(&tx, person_id)?;
+    }
+    for prev in previous {
+        rebuild_person_profile(&tx, prev)?;
+    }
+    tx.commit()?;
+    Ok(assigned)
+}
+
 #[cfg(all(test, feature = "facial-recognition"))]
 mod tests {
     use super::*;
     use crate::db::schema;
+    use crate::pipeline::face::Subject;
 
     fn insert_dummy_asset(conn: &Connection, asset_id: i64) {
         conn.execute(
@@ -607,6 +916,116 @@ mod tests {
         assert_eq!(profile.face_count, 2);
         assert_eq!(profile.centroid_dim, 3);
     }
+
+    #[test]
+    fn assigns_new_faces_to_existing_persons() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        insert_dummy_asset(&conn, 1);
+        let person_id = insert_person(&conn, None).unwrap();
+        insert_face_embedding(&conn, 1, Some(person_id), &[1.0, 0.0, 0.0], "{}", 0.9).unwrap();
+        let new_face = insert_face_embedding(&conn, 1, None, &[0.0, 1.0, 0.0], "{}", 0.9).unwrap();
+        let stray_face = insert_face_embedding(&conn, 1, None, &[0.0, 0.0, 1.0], "{}", 0.9).unwrap();
+
+        // Profiles missing for persons with faces are built on first use
+        assert_eq!(person_centroids(&conn).unwrap(), vec![(person_id, vec![1.0, 0.0, 0.0])]);
+
+        let gone = insert_person(&conn, None).unwrap();
+        delete_person(&conn, gone).unwrap();
+        let result = assign_faces_to_persons(&conn, &[(new_face, person_id), (stray_face, gone)]).unwrap();
+        assert_eq!(result.orphaned, vec![stray_face]);
+        let (id, centroid) = &result.centroids[0];
+        assert_eq!(*id, person_id);
+        let c = centroid.as_ref().unwrap();
+        assert!((c[0] - c[1]).abs() < 1e-6 && c[2] == 0.0);
+    }
+
+    #[test]
+    fn bulk_assigns_faces_to_person() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        insert_dummy_asset(&conn, 1);
+        let alice = insert_person(&conn, None).unwrap();
+        let bob = insert_person(&conn, None).unwrap();
+        let mistaken = insert_face_embedding(&conn, 1, Some(bob), &[1.0, 0.0], "{}", 0.9).unwrap();
+        let kept = insert_face_embedding(&conn, 1, Some(bob), &[0.0, 1.0], "{}", 0.9).unwrap();
+        let stray = insert_face_embedding(&conn, 1, None, &[1.0, 0.0], "{}", 0.9).unwrap();
+        rebuild_person_profile(&conn, bob).unwrap();
+
+        let assigned = assign_faces_to_person(&conn, alice, &[stray, mistaken, stray, 999]).unwrap();
+        assert_eq!(assigned, vec![stray, mistaken]);
+        let count = |person: i64| -> i64 {
+            conn.query_row("SELECT face_count FROM person_profiles WHERE person_id = ?1", params![person], |r| r.get(0)).unwrap()
+        };
+        assert_eq!((count(alice), count(bob)), (2, 1));
+        let owner: Option<i64> = conn.query_row("SELECT person_id FROM face_embeddings WHERE id = ?1", params![kept], |r| r.get(0)).unwrap();
+        assert_eq!(owner, Some(bob));
+    }
+
+    #[test]
+    fn face_feedback_guides_profiles_and_merges() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        insert_dummy_asset(&conn, 1);
+        let alice = insert_person(&conn, None).unwrap();
+        let a1 = insert_face_embedding(&conn, 1, Some(alice), &[1.0, 0.0], "{}", 0.9).unwrap();
+        insert_face_embedding(&conn, 1, Some(alice), &[0.0, 1.0], "{}", 0.9).unwrap();
+
+        // A confirmed face pulls the centroid toward itself
+        assert_eq!(set_face_feedback(&conn, a1, alice, true).unwrap(), Some(Some(alice)));
+        let c = crate::db::query::get_person_centroid(&conn, alice).unwrap().unwrap();
+        assert!((c[0] - 3.0 / 10f32.sqrt()).abs() < 1e-5);
+
+        // Rejecting a face takes it away from the person and keeps look-alikes from being matched to them
+        let lookalike = insert_face_embedding(&conn, 1, Some(alice), &[0.9, 0.1], "{}", 0.9).unwrap();
+        assert_eq!(set_face_feedback(&conn, lookalike, alice, false).unwrap(), Some(None));
+        let mut index = crate::pipeline::face::FaceIndex::new();
+        index.set_person_centroids(person_centroids(&conn).unwrap(), crate::db::query::get_person_rejections(&conn).unwrap(), Default::default());
+        assert_eq!(index.nearest_person(&[0.6, 0.4], Subject::Person, 1.0).map(|(p, _)| p), Some(alice));
+        assert_eq!(index.nearest_person(&[0.91, 0.09], Subject::Person, 1.0), None);
+        assert_eq!(set_face_feedback(&conn, 999, alice, true).unwrap(), None);
+
+        // Smart merge leaves apart persons a rejection or their names tell apart
+        let carol = insert_person(&conn, None).unwrap();
+        update_face_person(&conn, lookalike, Some(carol)).unwrap();
+        rebuild_person_profile(&conn, carol).unwrap();
+        let dave = insert_person(&conn, Some("Dave".to_string())).unwrap();
+        let erin = insert_person(&conn, Some("Erin".to_string())).unwrap();
+        insert_face_embedding(&conn, 1, Some(dave), &[0.0, 1.0], "{}", 0.9).unwrap();
+        insert_face_embedding(&conn, 1, Some(erin), &[0.0, 1.0], "{}", 0.9).unwrap();
+        let unnamed = insert_person(&conn, None).unwrap();
+        insert_face_embedding(&conn, 1, Some(unnamed), &[0.0, 1.0], "{}", 0.9).unwrap();
+        insert_face_embedding(&conn, 1, Some(unnamed), &[0.0, 1.0], "{}", 0.9).unwrap();
+        assert_eq!(smart_merge_persons(&conn, 0.5).unwrap().0, 1);
+        let remaining: Vec<i64> = crate::db::query::list_persons(&conn).unwrap().into_iter().map(|p| p.0).collect();
+        assert!(remaining.contains(&alice) && remaining.contains(&carol) && remaining.contains(&dave) && remaining.contains(&erin));
+        assert!(!remaining.contains(&unnamed), "the unnamed look-alike joins a named person");
+    }
+
+    #[test]
+    fn pets_stay_apart_from_persons() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        insert_dummy_asset(&conn, 1);
+        let alice = insert_person(&conn, None).unwrap();
+        let rex = insert_subject(&conn, None, Subject::Pet).unwrap();
+        insert_face_embedding(&conn, 1, Some(alice), &[1.0, 0.0], "{}", 0.9).unwrap();
+        let rex_face = insert_subject_embedding(&conn, 1, Some(rex), &[1.0, 0.0], "{}", 0.9, Subject::Pet).unwrap();
+
+        // Pets stay out of the person face index and are matched only to pets
+        assert!(!crate::db::query::face_embedding_ids(&conn).unwrap().contains(&rex_face));
+        let mut index = crate::pipeline::face::FaceIndex::new();
+        let pets = crate::db::query::get_pet_person_ids(&conn).unwrap();
+        index.set_person_centroids(person_centroids(&conn).unwrap(), Vec::new(), pets);
+        assert_eq!(index.nearest_person(&[1.0, 0.0], Subject::Person, 0.1).map(|(p, _)| p), Some(alice));
+        assert_eq!(index.nearest_person(&[1.0, 0.0], Subject::Pet, 0.1).map(|(p, _)| p), Some(rex));
+
+        // Identical embeddings, but a dog is never merged into a person
+        assert_eq!(smart_merge_persons(&conn, 0.5).unwrap(), (0, 0));
+        let listed = |subject| crate::db::query::list_persons_paged(&conn, 0, 10, crate::db::query::PersonSort::Faces, subject, crate::db::query::AccessScope::All).unwrap();
+        assert_eq!(listed(Subject::Person).items.iter().map(|p| p.id).collect::<Vec<_>>(), vec![alice]);
+        assert_eq!(listed(Subject::Pet).items.iter().map(|p| p.id).collect::<Vec<_>>(), vec![rex]);
+    }
 }
 
 #[cfg(feature = "facial-recognition")]
@@ -645,6 +1064,10 @@ pub fn smart_merge_persons(conn: &Connection, merge_threshold: f32) -> Result<(i
     let mut persons_merged = 0i64;
     let mut faces_merged = 0i64;
     let mut merged_person_ids = std::collections::HashSet::new();
+    // Persons a user's rejection says are different people; grows as merges absorb them
+    let mut distinct = query::get_distinct_person_pairs(&tx)?;
+    let pets = query::get_pet_person_ids(&tx)?;
+    let named = |name: &Option<String>| name.as_deref().is_some_and(|n| !n.trim().is_empty());
     
     // Helper function to get centroid, computing on-the-fly if needed
     let get_centroid = |person_id: i64| -> Option<Vec<f32>> {
@@ -654,42 +1077,8 @@ pub fn smart_merge_persons(conn: &Connection, merge_threshold: f32) -> Result<(i
             Ok(None) => {
                 // Profile doesn't exist, compute centroid from embeddings
                 match query::get_person_face_embeddings(&tx, person_id) {
-                    Ok(embeddings) if !embeddings.is_empty() => {
-                        // Compute centroid
-                        let mut centroid = vec![0f32; embeddings[0].len()];
-                        let mut used = 0usize;
-                        
-                        for emb in &embeddings {
-                            if emb.len() != centroid.len() {
-                                continue;
-                            }
-                            for (idx, value) in emb.iter().enumerate() {
-                                centroid[idx] += value;
-                            }
-                            used += 1;
-                        }
-                        
-                        if used == 0 {
-                            return None;
-                        }
-                        
-                        // Average
-                        let inv = 1.0f32 / used as f32;
-                        for value in centroid.iter_mut() {
-                            *value *= inv;
-                        }
-                        
-                        // Normalize
-                        let norm = centroid.iter().map(|v| v * v).sum::<f32>().sqrt();
-                        if norm > 0.0 {
-                            for value in centroid.iter_mut() {
-                                *value /= norm;
-                            }
-                        }
-                        
-                        Some(centroid)
-                    }
-                    _ => None
+                    Ok(embeddings) => weighted_centroid(&embeddings).map(|(centroid, _)| centroid),
+                    Err(_) => None
                 }
             }
             Err(e) => {
@@ -724,6 +1113,12 @@ pub fn smart_merge_persons(conn: &Connection, merge_threshold: f32) -> Result<(i
             if merged_person_ids.contains(&person_j_id) {
                 continue;
             }
+            // Two differently named persons are different people, whatever their faces look like
+            let renamed_apart = named(&persons[i].1) && named(&person_j.1)
+                && !persons[i].1.as_deref().unwrap_or_default().trim().eq_ignore_ascii_case(person_j.1.as_deref().unwrap_or_default().trim());
+            if renamed_apart || pets.contains(&person_i_id) != pets.contains(&person_j_id) || distinct.contains(&(person_i_id.min(person_j_id), person_i_id.max(person_j_id))) {
+                continue;
+            }
             
             // Get centroid for person j
             let centroid_j = match get_centroid(person_j_id) {
@@ -743,8 +1138,13 @@ pub fn smart_merge_persons(conn: &Connection, merge_threshold: f32) -> Result<(i
                     |row| row.get(0)
                 )?;
                 
-                // Merge smaller person into larger person, or j into i if equal
-                let (source_id, target_id) = if face_count_j > face_count_i {
+                // Keep the named person; otherwise merge smaller into larger, or j into i if equal
+                let j_wins = match (named(&persons[i].1), named(&person_j.1)) {
+                    (false, true) => true,
+                    (true, false) => false,
+                    _ => face_count_j > face_count_i,
+                };
+                let (source_id, target_id) = if j_wins {
                     (person_i_id, person_j_id)
                 } else {
                     (person_j_id, person_i_id)
@@ -755,6 +1155,12 @@ pub fn smart_merge_persons(conn: &Connection, merge_threshold: f32) -> Result<(i
                     "UPDATE face_embeddings SET person_id = ?1 WHERE person_id = ?2",
                     params![target_id, source_id],
                 )?;
+                move_face_feedback(&tx, source_id, target_id)?;
+                let inherited: Vec<(i64, i64)> = distinct.iter()
+                    .filter_map(|&(a, b)| if a == source_id { Some(b) } else if b == source_id { Some(a) } else { None })
+                    .map(|other| (other.min(target_id), other.max(target_id)))
+                    .collect();
+                distinct.extend(inherited);
                 
                 // Delete the person_profiles entry for the source person
                 // (must be done before deleting the person due to foreign key constraint)
@@ -818,6 +1224,20 @@ pub fn insert_face_embedding(
     embedding: &[f32],
     bbox_json: &str,
     confidence: f64,
+) -> Result<i64> {
+    insert_subject_embedding(conn, asset_id, person_id, embedding, bbox_json, confidence, crate::pipeline::face::Subject::Person)
+}
+
+/// Store a detected person or pet face
+#[cfg(feature = "facial-recognition")]
+pub fn insert_subject_embedding(
+    conn: &Connection,
+    asset_id: i64,
+    person_id: Option<i64>,
+    embedding: &[f32],
+    bbox_json: &str,
+    confidence: f64,
+    subject: crate::pipeline::face::Subject,
 ) -> Result<i64> {
     // Convert embedding to bytes (little-endian f32)
     let embedding_bytes: Vec<u8> = embedding.iter()
@@ -825,8 +1245,8 @@ pub fn insert_face_embedding(
         .collect();
     
     conn.execute(
-        "INSERT INTO face_embeddings (asset_id, person_id, embedding_blob, bbox_json, confidence) VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![asset_id, person_id, embedding_bytes, bbox_json, confidence],
+        "INSERT INTO face_embeddings (asset_id, person_id, embedding_blob, bbox_json, confidence, subject_type) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![asset_id, person_id, embedding_bytes, bbox_json, confidence, subject.as_str()],
     )?;
     Ok(conn.last_insert_rowid())
 }
@@ -860,6 +1280,7 @@ pub fn clear_all_facial_data(conn: &Connection) -> Result<(usize, usize)> {
     // Delete in order: person_profiles -> face_embeddings -> persons
     // (person_profiles has foreign key to persons)
     let _ = tx.execute("DELETE FROM person_profiles", []);
+    tx.execute("DELETE FROM face_feedback", [])?;
     let faces_deleted = tx.execute("DELETE FROM face_embeddings", [])?;
     let persons_deleted = tx.execute("DELETE FROM persons", [])?;
     
@@ -876,11 +1297,27 @@ pub fn clear_all_facial_data(conn: &Connection) -> Result<(usize, usize)> {
 pub fn clear_persons_and_face_assignments(conn: &Connection) -> Result<()> {
     // Unlink all face embeddings from persons
     conn.execute("UPDATE face_embeddings SET person_id = NULL", [])?;
+    // Feedback names persons that are about to go away
+    conn.execute("DELETE FROM face_feedback", [])?;
     // Delete all persons
     conn.execute("DELETE FROM persons", [])?;
     Ok(())
 }
 
+/// Runtime setting saved through the settings API
+pub fn get_setting(conn: &Connection, key: &str) -> Result<Option<String>> {
+    let value = conn.query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0)).optional()?;
+    Ok(value)
+}
+
+pub fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)",
+        params![key, value, chrono::Utc::now().timestamp()],
+    )?;
+    Ok(())
+}
+
 #[cfg(feature = "facial-recognition")]
 pub fn get_face_setting(conn: &Connection, key: &str) -> Result<Option<String>> {
     let mut stmt = conn.prepare("SELECT value FROM face_settings WHERE key = ?1")?;
@@ -943,7 +1380,14 @@ pub fn clear_all_data(conn: &Connection) -> Result<(usize, usize, usize)> {
     for asset_id in &asset_ids {
         let _ = tx.execute("DELETE FROM fts_assets WHERE rowid = ?1", params![asset_id]);
     }
-    
+
+    // Drop tag links (tags themselves are kept for reuse) and links to single assets
+    tx.execute("DELETE FROM asset_tags", [])?;
+    tx.execute("DELETE FROM asset_properties", [])?;
+    tx.execute("DELETE FROM fts_asset_properties", [])?;
+    tx.execute("DELETE FROM shares WHERE asset_id IS NOT NULL", [])?;
+    tx.execute("DELETE FROM stacks", [])?;
+
     // Delete all assets
     let assets_deleted = tx.execute("DELETE FROM assets", [])?;
     
@@ -957,6 +1401,83 @@ pub fn clear_all_data(conn: &Connection) -> Result<(usize, usize, usize)> {
     Ok((assets_deleted, faces_deleted, persons_deleted))
 }
 
+/// Record files handed to the hash or metadata stage, so a crash before they are committed
+/// doesn't lose them; the writer removes each path once its asset row is written
+pub fn journal_pending_jobs(conn: &Connection, paths: &[String]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    {
+        let now = chrono::Utc::now().timestamp();
+        let mut stmt = tx.prepare("INSERT OR REPLACE INTO pending_jobs (path, queued_at) VALUES (?1, ?2)")?;
+        for path in paths {
+            stmt.execute(params![path, now])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Empty the journal, returning the paths left over from the last run that are still
+/// under a scan path. Re-enqueued files are journaled again as they pass through discovery.
+pub fn take_pending_jobs(conn: &Connection) -> Result<Vec<String>> {
+    let tx = conn.unchecked_transaction()?;
+    let paths = {
+        let mut stmt = tx.prepare(
+            r"SELECT pj.path FROM pending_jobs pj WHERE EXISTS (SELECT 1 FROM scan_paths sp
+                WHERE substr(pj.path, 1, length(rtrim(sp.path, '/\')) + 1) IN (rtrim(sp.path, '/\') || '/', rtrim(sp.path, '/\') || '\'))
+              ORDER BY pj.queued_at, pj.path",
+        )?;
+        let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+    tx.execute("DELETE FROM pending_jobs", [])?;
+    tx.commit()?;
+    Ok(paths)
+}
+
+/// Record assets queued for face detection, so a restart can resume them
+pub fn journal_face_jobs(conn: &Connection, asset_ids: &[i64]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    {
+        let now = chrono::Utc::now().timestamp();
+        let mut stmt = tx.prepare("INSERT OR REPLACE INTO face_jobs (asset_id, queued_at, done) VALUES (?1, ?2, 0)")?;
+        for asset_id in asset_ids {
+            stmt.execute(params![asset_id, now])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Mark an asset's face detection as done, whether or not it found faces
+pub fn finish_face_job(conn: &Connection, asset_id: i64) -> Result<()> {
+    conn.execute("UPDATE face_jobs SET done = 1 WHERE asset_id = ?1", params![asset_id])?;
+    Ok(())
+}
+
+/// Start counting a new detection run: forget the jobs the last one finished
+pub fn start_face_run(conn: &Connection) -> Result<()> {
+    conn.execute("DELETE FROM face_jobs WHERE done = 1", [])?;
+    Ok(())
+}
+
+/// Drop the whole face detection journal, e.g. when detection is turned off
+pub fn clear_face_jobs(conn: &Connection) -> Result<()> {
+    conn.execute("DELETE FROM face_jobs", [])?;
+    Ok(())
+}
+
+/// Unfinished face detection jobs with their asset paths, oldest first. Jobs for assets that
+/// have been removed since are dropped.
+pub fn pending_face_jobs(conn: &Connection) -> Result<Vec<(i64, String)>> {
+    conn.execute("DELETE FROM face_jobs WHERE asset_id NOT IN (SELECT id FROM assets)", [])?;
+    let mut stmt = conn.prepare(
+        "SELECT j.asset_id, a.path FROM face_jobs j JOIN assets a ON a.id = j.asset_id
+         WHERE j.done = 0 ORDER BY j.queued_at, j.asset_id",
+    )?;
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
 /// Add a scan path
 pub fn add_scan_path(conn: &Connection, path: &str) -> Result<i64> {
     let created_at = chrono::Utc::now().timestamp();
@@ -964,40 +1485,151 @@ pub fn add_scan_path(conn: &Connection, path: &str) -> Result<i64> {
         "INSERT OR IGNORE INTO scan_paths (path, created_at) VALUES (?1, ?2)",
         params![path, created_at],
     )?;
-    Ok(conn.last_insert_rowid())
+    let id = conn.last_insert_rowid();
+    // Assets already indexed under the new path (e.g. a subfolder of another scan path) move to it
+    assign_asset_roots(conn, None)?;
+    Ok(id)
 }
 
-/// Remove a scan path
-pub fn remove_scan_path(conn: &Connection, path: &str) -> Result<bool> {
-    let deleted = conn.execute("DELETE FROM scan_paths WHERE path = ?1", params![path])?;
-    Ok(deleted > 0)
+/// Record whether a scan path's volume accepts writes
+pub fn set_scan_path_read_only(conn: &Connection, path: &str, read_only: bool) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE scan_paths SET read_only = ?1 WHERE path = ?2",
+        params![read_only as i64, path],
+    )?;
+    Ok(updated > 0)
 }
 
-/// Delete all assets that start with the given path prefix
-pub fn delete_assets_by_path_prefix(conn: &Connection, path_prefix: &str) -> Result<(usize, usize)> {
-    let tx = conn.unchecked_transaction()?;
+/// Set or clear a scan path's recurring scan; a new schedule starts fresh
+pub fn set_scan_path_schedule(conn: &Connection, path: &str, schedule: Option<&str>) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE scan_paths SET schedule = ?1, last_scheduled_at = NULL WHERE path = ?2",
+        params![schedule, path],
+    )?;
+    Ok(updated > 0)
+}
 
-    // Normalize path prefix for directory matching across platforms.
-    // Windows paths typically use `\`, but some callers may provide `/`.
-    // We generate LIKE patterns for BOTH separators to ensure deletion works
-    // regardless of how paths are stored in the DB.
-    fn escape_like(s: &str) -> String {
-        s.replace("\\", "\\\\").replace("%", "\\%").replace("_", "\\_")
-    }
+/// Replace a scan path's include/exclude globs; an empty list clears that side
+pub fn set_scan_path_filters(conn: &Connection, path: &str, include: &[String], exclude: &[String]) -> Result<bool> {
+    let join = |patterns: &[String]| if patterns.is_empty() { None } else { Some(patterns.join("\n")) };
+    let updated = conn.execute(
+        "UPDATE scan_paths SET include_patterns = ?1, exclude_patterns = ?2 WHERE path = ?3",
+        params![join(include), join(exclude), path],
+    )?;
+    Ok(updated > 0)
+}
 
-    let mut prefixes: Vec<String> = Vec::new();
-    // Exact prefix (for file paths or exact directory match)
-    prefixes.push(path_prefix.to_string());
+/// Set a scan path's date source order (comma-separated), or None to use the default
+pub fn set_scan_path_date_priority(conn: &Connection, path: &str, priority: Option<&str>) -> Result<bool> {
+    let updated = conn.execute("UPDATE scan_paths SET date_priority = ?1 WHERE path = ?2", params![priority, path])?;
+    Ok(updated > 0)
+}
 
-    let (base, had_sep) = if path_prefix.ends_with('/') {
-        (path_prefix.trim_end_matches('/').to_string(), true)
-    } else if path_prefix.ends_with('\\') {
-        (path_prefix.trim_end_matches('\\').to_string(), true)
-    } else {
-        (path_prefix.to_string(), false)
-    };
+pub fn set_scan_path_quota(conn: &Connection, path: &str, quota: &crate::pipeline::quota::ScanQuota) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE scan_paths SET max_assets = ?1, max_bytes = ?2 WHERE path = ?3",
+        params![quota.max_assets.map(|n| n as i64), quota.max_bytes.map(|n| n as i64), path],
+    )?;
+    Ok(updated > 0)
+}
 
-    if had_sep {
+/// Save how the last scan of a path ended: stopped at its quota, or None when it finished
+pub fn set_scan_path_quota_hit(conn: &Connection, path: &str, hit: Option<&crate::pipeline::quota::QuotaHit>) -> Result<()> {
+    let json = hit.map(serde_json::to_string).transpose()?;
+    conn.execute("UPDATE scan_paths SET quota_hit = ?1 WHERE path = ?2", params![json, path])?;
+    Ok(())
+}
+
+/// Scans kept per path in the history
+pub const SCAN_RUNS_KEPT: i64 = 50;
+
+/// Add a finished scan to the history, dropping the path's oldest beyond [`SCAN_RUNS_KEPT`]
+pub fn record_scan_run(conn: &Connection, run: &crate::pipeline::discover::ScanRun) -> Result<()> {
+    conn.execute(
+        "INSERT INTO scan_runs (path, started_at, finished_at, status, files, bytes) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![run.path, run.started_at, run.finished_at, run.status, run.files as i64, run.bytes as i64],
+    )?;
+    conn.execute(
+        "DELETE FROM scan_runs WHERE path = ?1 AND id NOT IN (SELECT id FROM scan_runs WHERE path = ?1 ORDER BY id DESC LIMIT ?2)",
+        params![run.path, SCAN_RUNS_KEPT],
+    )?;
+    Ok(())
+}
+
+/// Mark a scan path as archived to cold storage (or back online), flagging every asset under it.
+/// Returns the number of assets updated, or None if the path isn't a scan path.
+pub fn set_scan_path_archived(conn: &Connection, path: &str, archived: bool, label: Option<&str>) -> Result<Option<usize>> {
+    let tx = conn.unchecked_transaction()?;
+    let updated = tx.execute(
+        "UPDATE scan_paths SET archived = ?1, archive_label = ?2 WHERE path = ?3",
+        params![archived as i64, label.filter(|_| archived), path],
+    )?;
+    if updated == 0 {
+        return Ok(None);
+    }
+    let base = path.trim_end_matches(['/', '\\']);
+    let assets = tx.execute(
+        "UPDATE assets SET archived = ?1
+         WHERE substr(path, 1, length(?2) + 1) IN (?2 || '/', ?2 || '\\')",
+        params![archived as i64, base],
+    )?;
+    tx.commit()?;
+    Ok(Some(assets))
+}
+
+pub fn set_asset_archived(conn: &Connection, asset_id: i64, archived: bool) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE assets SET archived = ?1 WHERE id = ?2",
+        params![archived as i64, asset_id],
+    )?;
+    Ok(updated > 0)
+}
+
+pub fn set_asset_locked(conn: &Connection, asset_id: i64, locked: bool) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE assets SET locked = ?1 WHERE id = ?2",
+        params![locked as i64, asset_id],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Record that the scheduler started a scan, so it fires once per matching minute
+pub fn mark_scan_path_scheduled(conn: &Connection, path: &str, at: i64) -> Result<()> {
+    conn.execute("UPDATE scan_paths SET last_scheduled_at = ?1 WHERE path = ?2", params![at, path])?;
+    Ok(())
+}
+
+/// Remove a scan path
+pub fn remove_scan_path(conn: &Connection, path: &str) -> Result<bool> {
+    let deleted = conn.execute("DELETE FROM scan_paths WHERE path = ?1", params![path])?;
+    Ok(deleted > 0)
+}
+
+/// Delete all assets that start with the given path prefix
+pub fn delete_assets_by_path_prefix(conn: &Connection, path_prefix: &str) -> Result<(usize, usize)> {
+    let tx = conn.unchecked_transaction()?;
+
+    // Normalize path prefix for directory matching across platforms.
+    // Windows paths typically use `\`, but some callers may provide `/`.
+    // We generate LIKE patterns for BOTH separators to ensure deletion works
+    // regardless of how paths are stored in the DB.
+    fn escape_like(s: &str) -> String {
+        s.replace("\\", "\\\\").replace("%", "\\%").replace("_", "\\_")
+    }
+
+    let mut prefixes: Vec<String> = Vec::new();
+    // Exact prefix (for file paths or exact directory match)
+    prefixes.push(path_prefix.to_string());
+
+    let (base, had_sep) = if path_prefix.ends_with('/') {
+        (path_prefix.trim_end_matches('/').to_string(), true)
+    } else if path_prefix.ends_with('\\') {
+        (path_prefix.trim_end_matches('\\').to_string(), true)
+    } else {
+        (path_prefix.to_string(), false)
+    };
+
+    if had_sep {
         // If caller passed a trailing separator, include both normalized forms.
         prefixes.push(format!("{}/", base));
         prefixes.push(format!("{}\\", base));
@@ -1054,11 +1686,22 @@ pub fn delete_assets_by_path_prefix(conn: &Connection, path_prefix: &str) -> Res
     #[cfg(not(feature = "facial-recognition"))]
     let faces_deleted = 0;
     
-    // Delete from FTS table
+    // Delete from FTS table and tag links
     for asset_id in &asset_ids {
         let _ = tx.execute("DELETE FROM fts_assets WHERE rowid = ?1", params![asset_id]);
+        tx.execute("DELETE FROM asset_tags WHERE asset_id = ?1", params![asset_id])?;
+        delete_asset_properties(&tx, *asset_id)?;
+        tx.execute("DELETE FROM shares WHERE asset_id = ?1", params![asset_id])?;
+        dissolve_stacks_with_cover(&tx, *asset_id)?;
+        unlink_live_photo(&tx, *asset_id)?;
     }
-    
+
+    // Files still waiting in the pipeline must not come back after a restart
+    tx.execute(
+        "DELETE FROM pending_jobs WHERE (path LIKE ?1 ESCAPE '\\' OR path LIKE ?2 ESCAPE '\\' OR path = ?3)",
+        params![like_pattern1, like_pattern2, path_prefix]
+    )?;
+
     // Delete from assets table
     let assets_deleted = tx.execute(
         "DELETE FROM assets WHERE (path LIKE ?1 ESCAPE '\\' OR path LIKE ?2 ESCAPE '\\' OR path = ?3)",
@@ -1074,16 +1717,27 @@ pub fn delete_assets_by_path_prefix(conn: &Connection, path_prefix: &str) -> Res
     Ok((assets_deleted, faces_deleted))
 }
 
-/// Create a new album
-pub fn create_album(conn: &Connection, name: &str, description: Option<&str>) -> Result<i64> {
+/// Create a new album; passing rules makes it a smart album
+pub fn create_album(conn: &Connection, name: &str, description: Option<&str>, rules: Option<&SmartAlbumRules>) -> Result<i64> {
     let now = chrono::Utc::now().timestamp();
+    let rules_json = rules.map(serde_json::to_string).transpose()?;
     conn.execute(
-        "INSERT INTO albums (name, description, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
-        params![name, description, now, now],
+        "INSERT INTO albums (name, description, created_at, updated_at, rules_json) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![name, description, now, now, rules_json],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
+/// Replace a smart album's rules
+pub fn set_album_rules(conn: &Connection, id: i64, rules: &SmartAlbumRules) -> Result<bool> {
+    let now = chrono::Utc::now().timestamp();
+    let updated = conn.execute(
+        "UPDATE albums SET rules_json = ?1, updated_at = ?2 WHERE id = ?3",
+        params![serde_json::to_string(rules)?, now, id],
+    )?;
+    Ok(updated > 0)
+}
+
 /// Update an album's name and/or description
 pub fn update_album(conn: &Connection, id: i64, name: Option<&str>, description: Option<&str>) -> Result<bool> {
     let now = chrono::Utc::now().timestamp();
@@ -1118,8 +1772,9 @@ pub fn update_album(conn: &Connection, id: i64, name: Option<&str>, description:
     }
 }
 
-/// Delete an album (cascade deletes album_assets)
+/// Delete an album (cascade deletes album_assets) and its share links
 pub fn delete_album(conn: &Connection, id: i64) -> Result<bool> {
+    conn.execute("DELETE FROM shares WHERE album_id = ?1", params![id])?;
     let deleted = conn.execute("DELETE FROM albums WHERE id = ?1", params![id])?;
     Ok(deleted > 0)
 }
@@ -1191,114 +1846,1179 @@ pub fn remove_assets_from_album(conn: &Connection, album_id: i64, asset_ids: &[i
     Ok(removed)
 }
 
-#[cfg(test)]
-mod path_deletion_tests {
-    use super::*;
-    use crate::db::schema;
-
-    fn setup_test_db() -> Connection {
-        let conn = Connection::open_in_memory().unwrap();
-        schema::apply_schema(&conn).unwrap();
-        conn
+/// Add and remove a set of assets across several albums in one transaction.
+/// Returns (links added, links removed); nothing is applied if any statement fails.
+pub fn batch_update_album_assets(conn: &Connection, album_ids: &[i64], add: &[i64], remove: &[i64]) -> Result<(usize, usize)> {
+    let tx = conn.unchecked_transaction()?;
+    let now = chrono::Utc::now().timestamp();
+    let mut added = 0;
+    let mut removed = 0;
+    {
+        let mut insert = tx.prepare("INSERT OR IGNORE INTO album_assets (album_id, asset_id) VALUES (?1, ?2)")?;
+        let mut delete = tx.prepare("DELETE FROM album_assets WHERE album_id = ?1 AND asset_id = ?2")?;
+        for album_id in album_ids {
+            for asset_id in add {
+                added += insert.execute(params![album_id, asset_id])?;
+            }
+            for asset_id in remove {
+                removed += delete.execute(params![album_id, asset_id])?;
+            }
+            tx.execute("UPDATE albums SET updated_at = ?1 WHERE id = ?2", params![now, album_id])?;
+        }
     }
+    tx.commit()?;
+    Ok((added, removed))
+}
 
-    fn insert_test_asset(conn: &Connection, path: &str) {
-        conn.execute(
-            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags)
-             VALUES (?1, ?2, ?3, ?4, 1000, 0, 0, 'image/jpeg', 0)",
-            params![
-                path,
-                std::path::Path::new(path).parent().and_then(|p| p.to_str()).unwrap_or(""),
-                std::path::Path::new(path).file_name().and_then(|f| f.to_str()).unwrap_or(""),
-                std::path::Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("")
-            ],
-        ).unwrap();
+/// Extract a burst identifier from a filename, e.g. "..._BURST20231224123456_COVER.jpg"
+/// (Pixel/Android) yields "BURST20231224123456"
+pub fn burst_key(filename: &str) -> Option<String> {
+    let upper = filename.to_uppercase();
+    let start = upper.find("BURST")?;
+    let digits: String = upper[start + 5..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() {
+        None
+    } else {
+        Some(format!("BURST{}", digits))
     }
+}
 
-    #[test]
-    fn test_delete_assets_with_forward_slash_paths() {
-        let conn = setup_test_db();
+/// Longest clip still taken for the motion half of a live photo; iPhones record about three seconds
+pub const LIVE_PHOTO_MAX_MS: i64 = 5000;
+
+/// Pair still images with the short video of the same name beside them (IMG_0001.HEIC and
+/// IMG_0001.MOV from an iPhone live photo) by setting the image's `live_photo_video_id`. Only
+/// the images among `asset_ids` and those beside a clip among them are re-paired, or every image
+/// for None (links to videos that are gone are cleared on the way). Returns the number of those
+/// images now paired.
+pub fn link_live_photos(conn: &Connection, asset_ids: Option<&[i64]>) -> Result<usize> {
+    const PAIR: &str = "UPDATE assets SET live_photo_video_id = (
+            SELECT v.id FROM assets v
+            WHERE v.dirname = assets.dirname AND lower(v.ext) IN ('mov', 'mp4')
+              AND (v.duration_ms IS NULL OR v.duration_ms <= ?1)
+              AND lower(substr(v.filename, 1, length(v.filename) - length(v.ext) - 1))
+                = lower(substr(assets.filename, 1, length(assets.filename) - length(assets.ext) - 1))
+            ORDER BY v.id LIMIT 1)
+         WHERE lower(ext) IN ('jpg', 'jpeg', 'heic', 'heif')";
+    const PAIRED: &str = "SELECT COUNT(*) FROM assets WHERE live_photo_video_id IS NOT NULL";
+    match asset_ids {
+        None => {
+            conn.execute(PAIR, params![LIVE_PHOTO_MAX_MS])?;
+            conn.execute(
+                "UPDATE assets SET live_photo_video_id = NULL
+                 WHERE live_photo_video_id IS NOT NULL AND live_photo_video_id NOT IN (SELECT id FROM assets)",
+                [],
+            )?;
+            Ok(conn.query_row(PAIRED, [], |r| r.get::<_, i64>(0))? as usize)
+        }
+        Some(asset_ids) => {
+            // The asset itself if it's a still, or the stills of the same name beside it if it's a clip
+            let mut stills = conn.prepare(
+                "SELECT s.id FROM assets a JOIN assets s ON s.id = a.id OR (
+                    lower(a.ext) IN ('mov', 'mp4') AND s.dirname = a.dirname
+                    AND lower(substr(s.filename, 1, length(s.filename) - length(s.ext) - 1))
+                      = lower(substr(a.filename, 1, length(a.filename) - length(a.ext) - 1)))
+                 WHERE a.id = ?1 AND lower(s.ext) IN ('jpg', 'jpeg', 'heic', 'heif')",
+            )?;
+            let mut still_ids = std::collections::BTreeSet::new();
+            for id in asset_ids {
+                for still in stills.query_map(params![id], |r| r.get::<_, i64>(0))? {
+                    still_ids.insert(still?);
+                }
+            }
+            let mut video = conn.prepare(
+                "SELECT v.id FROM assets s JOIN assets v ON v.dirname = s.dirname
+                 WHERE s.id = ?1 AND lower(v.ext) IN ('mov', 'mp4')
+                   AND (v.duration_ms IS NULL OR v.duration_ms <= ?2)
+                   AND lower(substr(v.filename, 1, length(v.filename) - length(v.ext) - 1))
+                     = lower(substr(s.filename, 1, length(s.filename) - length(s.ext) - 1))
+                 ORDER BY v.id LIMIT 1",
+            )?;
+            // Rows already pointing at the right clip are left alone
+            let mut link = conn.prepare(
+                "UPDATE assets SET live_photo_video_id = ?2 WHERE id = ?1 AND live_photo_video_id IS NOT ?2",
+            )?;
+            let mut total = 0;
+            for still in still_ids {
+                let video_id: Option<i64> = video.query_row(params![still, LIVE_PHOTO_MAX_MS], |r| r.get(0)).optional()?;
+                link.execute(params![still, video_id])?;
+                total += video_id.is_some() as usize;
+            }
+            Ok(total)
+        }
+    }
+}
 
-        // Insert assets with forward slash paths (Unix-style)
-        insert_test_asset(&conn, "/photos/vacation/img1.jpg");
-        insert_test_asset(&conn, "/photos/vacation/img2.jpg");
-        insert_test_asset(&conn, "/photos/work/img3.jpg");
+/// Unpair the stills whose live photo clip is `video_id`, before the clip is deleted
+pub fn unlink_live_photo(conn: &Connection, video_id: i64) -> Result<()> {
+    conn.execute("UPDATE assets SET live_photo_video_id = NULL WHERE live_photo_video_id = ?1", params![video_id])?;
+    Ok(())
+}
 
-        // Verify all assets exist
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
-        assert_eq!(count, 3);
+/// Drop the stack whose cover is `asset_id` so its remaining members aren't hidden
+/// from collapsed listings (the next rebuild regroups them)
+pub fn dissolve_stacks_with_cover(conn: &Connection, asset_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE assets SET stack_id = NULL WHERE stack_id IN (SELECT id FROM stacks WHERE cover_asset_id = ?1)",
+        params![asset_id],
+    )?;
+    conn.execute("DELETE FROM stacks WHERE cover_asset_id = ?1", params![asset_id])?;
+    Ok(())
+}
 
-        // Delete assets under /photos/vacation
-        let (deleted, _) = delete_assets_by_path_prefix(&conn, "/photos/vacation").unwrap();
-        assert_eq!(deleted, 2);
+/// Max seconds between consecutive shots from the same camera for them to stack
+/// (FLASH_STACK_WINDOW_SECS, default 2)
+pub fn stack_window_secs() -> i64 {
+    std::env::var("FLASH_STACK_WINDOW_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(2)
+}
 
-        // Verify only work photo remains
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
-        assert_eq!(count, 1);
+/// Longest time a window-based stack may span, unless `window_secs` itself is longer;
+/// keeps timelapses and interval shooting from chaining into one huge stack
+const MAX_STACK_SPAN_SECS: i64 = 10;
+
+/// Load EXIF-dated images matching `condition`, ordered for the time sweep
+fn query_shots<P: rusqlite::Params>(conn: &Connection, condition: &str, params: P) -> Result<Vec<StackShot>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, filename, owner_id, COALESCE(camera_make, '') || '|' || COALESCE(camera_model, ''), taken_at
+         FROM assets
+         WHERE mime LIKE 'image/%' AND taken_at IS NOT NULL AND {}
+         ORDER BY owner_id, camera_make, camera_model, taken_at, id",
+        condition
+    ))?;
+    let mapped = stmt.query_map(params, |row| {
+        let camera: String = row.get(3)?;
+        // Both camera fields empty means no EXIF camera; taken_at is then just the mtime
+        let camera = if camera == "|" { None } else { Some(camera) };
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, camera, row.get(4)?))
+    })?;
+    Ok(mapped.collect::<std::result::Result<Vec<_>, _>>()?)
+}
 
-        let remaining: String = conn.query_row("SELECT path FROM assets", [], |r| r.get(0)).unwrap();
-        assert_eq!(remaining, "/photos/work/img3.jpg");
+/// Group shots ordered by owner, camera and time: shots sharing a burst ID, or taken by the
+/// same camera within `window_secs` of the previous shot and `MAX_STACK_SPAN_SECS` of the
+/// stack's first shot. Groups never mix owners; single shots come back as groups of one.
+fn group_shots(rows: Vec<StackShot>, window_secs: i64) -> Vec<StackGroup> {
+    let mut groups: Vec<StackGroup> = Vec::new();
+    let mut burst_groups: std::collections::HashMap<StackSource, usize> = std::collections::HashMap::new();
+    let mut current: Vec<(i64, String)> = Vec::new();
+    // Owner, camera, first and previous shot time of the stack being built
+    let mut last: Option<(Option<i64>, String, i64, i64)> = None;
+    let max_span = MAX_STACK_SPAN_SECS.max(window_secs);
+
+    for (id, filename, owner, camera, taken_at) in rows {
+        if let Some(key) = burst_key(&filename) {
+            let idx = *burst_groups.entry((owner, key.clone())).or_insert_with(|| {
+                groups.push((Some(key), Vec::new()));
+                groups.len() - 1
+            });
+            groups[idx].1.push((id, filename));
+            continue;
+        }
+        let Some(camera) = camera else { continue };
+        let first = match &last {
+            Some((o, cam, first, t)) if *o == owner && *cam == camera && taken_at - *t <= window_secs && taken_at - *first <= max_span => {
+                Some(*first)
+            }
+            _ => None,
+        };
+        if first.is_none() && !current.is_empty() {
+            groups.push((None, std::mem::take(&mut current)));
+        }
+        current.push((id, filename));
+        last = Some((owner, camera, first.unwrap_or(taken_at), taken_at));
     }
+    if !current.is_empty() {
+        groups.push((None, current));
+    }
+    groups
+}
 
-    #[test]
-    fn test_delete_assets_with_backslash_paths() {
-        let conn = setup_test_db();
-
-        // Insert assets with backslash paths (Windows-style)
-        insert_test_asset(&conn, r"C:\Users\Photos\vacation\img1.jpg");
-        insert_test_asset(&conn, r"C:\Users\Photos\vacation\img2.jpg");
-        insert_test_asset(&conn, r"C:\Users\Photos\work\img3.jpg");
-
-        // Verify all assets exist
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
-        assert_eq!(count, 3);
+/// Prefer the camera-marked cover shot, otherwise the first frame
+fn stack_cover(members: &[(i64, String)]) -> i64 {
+    members
+        .iter()
+        .find(|(_, f)| f.to_uppercase().contains("COVER"))
+        .unwrap_or(&members[0])
+        .0
+}
 
-        // Delete assets under C:\Users\Photos\vacation
-        let (deleted, _) = delete_assets_by_path_prefix(&conn, r"C:\Users\Photos\vacation").unwrap();
-        assert_eq!(deleted, 2);
+/// Regroup all images into stacks (see `group_shots`), replacing every existing stack.
+/// Returns the number of stacks created.
+pub fn rebuild_stacks(conn: &Connection, window_secs: i64) -> Result<usize> {
+    let groups = group_shots(query_shots(conn, "1", [])?, window_secs);
 
-        // Verify only work photo remains
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
-        assert_eq!(count, 1);
+    let now = chrono::Utc::now().timestamp();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("UPDATE assets SET stack_id = NULL WHERE stack_id IS NOT NULL", [])?;
+    tx.execute("DELETE FROM stacks", [])?;
+    let mut created = 0;
+    for (key, members) in groups.into_iter().filter(|(_, m)| m.len() > 1) {
+        tx.execute(
+            "INSERT INTO stacks (cover_asset_id, asset_count, burst_key, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![stack_cover(&members), members.len() as i64, key, now],
+        )?;
+        let stack_id = tx.last_insert_rowid();
+        for (id, _) in &members {
+            tx.execute("UPDATE assets SET stack_id = ?1 WHERE id = ?2", params![stack_id, id])?;
+        }
+        created += 1;
+    }
+    tx.commit()?;
+    Ok(created)
+}
 
-        let remaining: String = conn.query_row("SELECT path FROM assets", [], |r| r.get(0)).unwrap();
-        assert_eq!(remaining, r"C:\Users\Photos\work\img3.jpg");
+/// Shots from one owner's camera that chain (gaps of at most `window_secs`) into any of
+/// `seeds`, which all come from that camera. The fetched time range widens until no such
+/// run can continue past it.
+fn camera_runs(conn: &Connection, owner: Option<i64>, camera: &str, seeds: &[(i64, i64)], window_secs: i64) -> Result<Vec<StackShot>> {
+    let lo = seeds.iter().map(|(_, t)| *t).min().unwrap_or_default();
+    let hi = seeds.iter().map(|(_, t)| *t).max().unwrap_or_default();
+    let seed_ids: std::collections::HashSet<i64> = seeds.iter().map(|(id, _)| *id).collect();
+    let mut margin = MAX_STACK_SPAN_SECS.max(window_secs).saturating_mul(4).max(1);
+    loop {
+        let (from, to) = (lo.saturating_sub(margin), hi.saturating_add(margin));
+        let rows = query_shots(
+            conn,
+            "owner_id IS ?1 AND COALESCE(camera_make, '') || '|' || COALESCE(camera_model, '') = ?2 AND taken_at BETWEEN ?3 AND ?4",
+            params![owner, camera, from, to],
+        )?;
+        let mut runs: Vec<Vec<StackShot>> = Vec::new();
+        for row in rows.into_iter().filter(|r| burst_key(&r.1).is_none()) {
+            match runs.last_mut() {
+                Some(run) if row.4 - run.last().map_or(row.4, |r| r.4) <= window_secs => run.push(row),
+                _ => runs.push(vec![row]),
+            }
+        }
+        runs.retain(|run| run.iter().any(|r| seed_ids.contains(&r.0)));
+        let open_ended = runs.iter().any(|run| {
+            (run[0].4.saturating_sub(from) <= window_secs && from > i64::MIN)
+                || (to.saturating_sub(run[run.len() - 1].4) <= window_secs && to < i64::MAX)
+        });
+        if !open_ended {
+            return Ok(runs.into_iter().flatten().collect());
+        }
+        margin = margin.saturating_mul(2);
     }
+}
 
-    #[test]
-    fn test_delete_assets_with_trailing_separator() {
-        let conn = setup_test_db();
+/// Regroup only the stacks `asset_ids` can belong to: their bursts and the runs of shots
+/// from the same owner and camera around them. A stack keeps its id when it is regrouped,
+/// so clients holding it aren't left with a stale id. Returns the number of stacks in the
+/// regrouped shots.
+pub fn restack_assets(conn: &Connection, asset_ids: &[i64], window_secs: i64) -> Result<usize> {
+    use std::collections::{HashMap, HashSet};
+
+    // The shots written, plus the rest of the stacks they were in
+    let mut seeds: HashSet<i64> = asset_ids.iter().copied().collect();
+    for chunk in asset_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(",");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id FROM assets WHERE stack_id IN (SELECT stack_id FROM assets WHERE id IN ({}) AND stack_id IS NOT NULL)",
+            placeholders
+        ))?;
+        let ids = stmt.query_map(rusqlite::params_from_iter(chunk), |row| row.get::<_, i64>(0))?;
+        for id in ids {
+            seeds.insert(id?);
+        }
+    }
+    let seed_list: Vec<i64> = seeds.iter().copied().collect();
+    let mut bursts: HashSet<StackSource> = HashSet::new();
+    let mut cameras: HashMap<StackSource, Vec<(i64, i64)>> = HashMap::new();
+    for chunk in seed_list.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(",");
+        for (id, filename, owner, camera, taken_at) in query_shots(conn, &format!("id IN ({})", placeholders), rusqlite::params_from_iter(chunk))? {
+            if let Some(key) = burst_key(&filename) {
+                bursts.insert((owner, key));
+            } else if let Some(camera) = camera {
+                cameras.entry((owner, camera)).or_default().push((id, taken_at));
+            }
+        }
+    }
 
-        // Insert assets with backslash paths
-        insert_test_asset(&conn, r"D:\Media\photos\img1.jpg");
-        insert_test_asset(&conn, r"D:\Media\photos\img2.jpg");
+    let mut groups: Vec<StackGroup> = Vec::new();
+    if !bursts.is_empty() {
+        // One pass over burst-named files covers every burst touched
+        let rows = query_shots(conn, "filename LIKE '%BURST%'", [])?
+            .into_iter()
+            .filter(|r| burst_key(&r.1).is_some_and(|key| bursts.contains(&(r.2, key))))
+            .collect();
+        groups.extend(group_shots(rows, window_secs));
+    }
+    for ((owner, camera), camera_seeds) in cameras {
+        groups.extend(group_shots(camera_runs(conn, owner, &camera, &camera_seeds, window_secs)?, window_secs));
+    }
 
-        // Delete with trailing backslash
-        let (deleted, _) = delete_assets_by_path_prefix(&conn, r"D:\Media\photos\").unwrap();
-        assert_eq!(deleted, 2);
+    // Every regrouped shot, and every seed that no longer stacks, gets a fresh assignment
+    let mut assigned: HashMap<i64, Option<i64>> = seeds.iter().map(|id| (*id, None)).collect();
+    for (_, members) in &groups {
+        for (id, _) in members {
+            assigned.insert(*id, None);
+        }
+    }
+    let mut old: HashMap<i64, Option<i64>> = HashMap::new();
+    let ids: Vec<i64> = assigned.keys().copied().collect();
+    for chunk in ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(",");
+        let mut stmt = conn.prepare(&format!("SELECT id, stack_id FROM assets WHERE id IN ({})", placeholders))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(chunk), |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?)))?;
+        for row in rows {
+            let (id, stack_id) = row?;
+            old.insert(id, stack_id);
+        }
+    }
 
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
-        assert_eq!(count, 0);
+    let now = chrono::Utc::now().timestamp();
+    let tx = conn.unchecked_transaction()?;
+    let mut kept: HashSet<i64> = HashSet::new();
+    let mut stacked = 0;
+    for (key, members) in groups.into_iter().filter(|(_, m)| m.len() > 1) {
+        // Reuse the old stack most of the members were in
+        let mut votes: HashMap<i64, usize> = HashMap::new();
+        for (id, _) in &members {
+            if let Some(Some(stack_id)) = old.get(id) {
+                if !kept.contains(stack_id) {
+                    *votes.entry(*stack_id).or_default() += 1;
+                }
+            }
+        }
+        let reused = votes.into_iter().max_by_key(|(stack_id, n)| (*n, std::cmp::Reverse(*stack_id))).map(|(stack_id, _)| stack_id);
+        let stack_id = match reused {
+            Some(stack_id) => {
+                tx.execute(
+                    "UPDATE stacks SET cover_asset_id = ?1, asset_count = ?2, burst_key = ?3 WHERE id = ?4",
+                    params![stack_cover(&members), members.len() as i64, key, stack_id],
+                )?;
+                stack_id
+            }
+            None => {
+                tx.execute(
+                    "INSERT INTO stacks (cover_asset_id, asset_count, burst_key, created_at) VALUES (?1, ?2, ?3, ?4)",
+                    params![stack_cover(&members), members.len() as i64, key, now],
+                )?;
+                tx.last_insert_rowid()
+            }
+        };
+        kept.insert(stack_id);
+        for (id, _) in &members {
+            assigned.insert(*id, Some(stack_id));
+        }
+        stacked += 1;
+    }
+    for (id, stack_id) in &assigned {
+        if old.get(id).copied().flatten() != *stack_id {
+            tx.execute("UPDATE assets SET stack_id = ?1 WHERE id = ?2", params![stack_id, id])?;
+        }
     }
 
-    #[test]
-    fn test_delete_no_match() {
-        let conn = setup_test_db();
+    // Old stacks not reused may still hold shots outside the regrouped runs
+    let dropped: HashSet<i64> = old.values().flatten().copied().filter(|s| !kept.contains(s)).collect();
+    for stack_id in dropped {
+        let members: Vec<(i64, String)> = {
+            let mut stmt = tx.prepare("SELECT id, filename FROM assets WHERE stack_id = ?1 ORDER BY taken_at, id")?;
+            let rows = stmt.query_map(params![stack_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        if members.len() < 2 {
+            tx.execute("UPDATE assets SET stack_id = NULL WHERE stack_id = ?1", params![stack_id])?;
+            tx.execute("DELETE FROM stacks WHERE id = ?1", params![stack_id])?;
+        } else {
+            tx.execute(
+                "UPDATE stacks SET asset_count = ?1,
+                    cover_asset_id = CASE WHEN cover_asset_id IN (SELECT id FROM assets WHERE stack_id = ?2) THEN cover_asset_id ELSE ?3 END
+                 WHERE id = ?2",
+                params![members.len() as i64, stack_id, stack_cover(&members)],
+            )?;
+        }
+    }
+    tx.commit()?;
+    Ok(stacked)
+}
 
-        insert_test_asset(&conn, r"C:\Users\Photos\img1.jpg");
+/// Set an asset's star rating (0-5)
+pub fn set_asset_rating(conn: &Connection, asset_id: i64, rating: i64) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE assets SET rating = ?1 WHERE id = ?2",
+        params![rating.clamp(0, 5), asset_id],
+    )?;
+    Ok(updated > 0)
+}
 
-        // Try to delete from non-existent path
-        let (deleted, _) = delete_assets_by_path_prefix(&conn, r"D:\Other\path").unwrap();
-        assert_eq!(deleted, 0);
+/// Mark or unmark an asset as favorite
+pub fn set_asset_favorite(conn: &Connection, asset_id: i64, favorite: bool) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE assets SET favorite = ?1 WHERE id = ?2",
+        params![favorite as i64, asset_id],
+    )?;
+    Ok(updated > 0)
+}
 
-        // Original asset should still exist
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
-        assert_eq!(count, 1);
+/// Mark or unmark an asset as one of the user's favorites. Returns false if the asset doesn't exist.
+pub fn set_user_favorite(conn: &Connection, user_id: i64, asset_id: i64, favorite: bool) -> Result<bool> {
+    let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM assets WHERE id = ?1)", params![asset_id], |r| r.get(0))?;
+    if !exists {
+        return Ok(false);
+    }
+    if favorite {
+        conn.execute(
+            "INSERT OR IGNORE INTO user_favorites (user_id, asset_id, created_at) VALUES (?1, ?2, ?3)",
+            params![user_id, asset_id, chrono::Utc::now().timestamp()],
+        )?;
+    } else {
+        conn.execute("DELETE FROM user_favorites WHERE user_id = ?1 AND asset_id = ?2", params![user_id, asset_id])?;
     }
+    Ok(true)
+}
 
-    #[test]
-    fn test_delete_exact_path_match() {
-        let conn = setup_test_db();
+/// Attach tags to an asset, creating any tags that don't exist yet.
+/// Returns the number of new asset-tag links.
+pub fn add_tags_to_asset(conn: &Connection, asset_id: i64, tags: &[String]) -> Result<usize> {
+    if tags.is_empty() {
+        return Ok(0);
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let tx = conn.unchecked_transaction()?;
+    let mut added = 0;
+
+    for name in tags {
+        let name = name.trim();
+        if name.is_empty() {
+            continue;
+        }
+
+        let tag_id = ensure_tag(&tx, name, now)?;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO asset_tags (asset_id, tag_id, created_at) VALUES (?1, ?2, ?3)",
+            params![asset_id, tag_id, now],
+        )?;
+        if inserted == 0 {
+            // Confirming a predicted tag makes it a manual one, which re-classifying leaves alone
+            tx.execute(
+                "UPDATE asset_tags SET confidence = NULL WHERE asset_id = ?1 AND tag_id = ?2",
+                params![asset_id, tag_id],
+            )?;
+        }
+        added += inserted;
+    }
+
+    tx.commit()?;
+    Ok(added)
+}
+
+/// Id of the tag called `name`, creating it (case-insensitive unique) and indexing it for search if needed
+fn ensure_tag(conn: &Connection, name: &str, now: i64) -> Result<i64> {
+    let created = conn.execute(
+        "INSERT OR IGNORE INTO tags (name, created_at) VALUES (?1, ?2)",
+        params![name, now],
+    )?;
+    let tag_id: i64 = conn.query_row("SELECT id FROM tags WHERE name = ?1", params![name], |r| r.get(0))?;
+    if created > 0 {
+        conn.execute("INSERT INTO fts_tags(rowid, name) VALUES (?1, ?2)", params![tag_id, name])?;
+    }
+    Ok(tag_id)
+}
+
+/// Replace an asset's predicted tags with `tags` (name, confidence). Manual tags are kept
+/// as they are, and a prediction never overrides one.
+pub fn set_auto_tags(conn: &Connection, asset_id: i64, tags: &[(String, f32)]) -> Result<()> {
+    let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM assets WHERE id = ?1)", params![asset_id], |r| r.get(0))?;
+    if !exists {
+        return Ok(());
+    }
+    let now = chrono::Utc::now().timestamp();
+    let tx = conn.unchecked_transaction()?;
+    let mut keep = Vec::with_capacity(tags.len());
+    for (name, confidence) in tags {
+        let tag_id = ensure_tag(&tx, name, now)?;
+        tx.execute(
+            "INSERT INTO asset_tags (asset_id, tag_id, created_at, confidence) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(asset_id, tag_id) DO UPDATE SET confidence = excluded.confidence
+             WHERE asset_tags.confidence IS NOT NULL",
+            params![asset_id, tag_id, now, *confidence as f64],
+        )?;
+        keep.push(tag_id);
+    }
+    let stale: Vec<i64> = {
+        let mut stmt = tx.prepare("SELECT tag_id FROM asset_tags WHERE asset_id = ?1 AND confidence IS NOT NULL")?;
+        let rows = stmt.query_map(params![asset_id], |r| r.get::<_, i64>(0))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|id| !keep.contains(id))
+            .collect()
+    };
+    for tag_id in stale {
+        tx.execute("DELETE FROM asset_tags WHERE asset_id = ?1 AND tag_id = ?2", params![asset_id, tag_id])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Remove a tag (matched case-insensitively by name) from an asset
+pub fn remove_tag_from_asset(conn: &Connection, asset_id: i64, tag: &str) -> Result<bool> {
+    let removed = conn.execute(
+        "DELETE FROM asset_tags WHERE asset_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
+        params![asset_id, tag.trim()],
+    )?;
+    Ok(removed > 0)
+}
+
+/// Set or clear custom properties on an asset; a `None` value removes the key.
+/// Keys are matched case-insensitively and values are indexed for free-text search.
+pub fn set_asset_properties(conn: &Connection, asset_id: i64, properties: &[(String, Option<String>)]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    write_asset_properties(&tx, asset_id, properties)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn write_asset_properties(tx: &Connection, asset_id: i64, properties: &[(String, Option<String>)]) -> Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    for (key, value) in properties {
+        let existing: Option<i64> = tx
+            .query_row(
+                "SELECT id FROM asset_properties WHERE asset_id = ?1 AND key = ?2",
+                params![asset_id, key],
+                |r| r.get(0),
+            )
+            .optional()?;
+        match (existing, value) {
+            (Some(id), Some(value)) => {
+                tx.execute(
+                    "UPDATE asset_properties SET key = ?1, value = ?2, updated_at = ?3 WHERE id = ?4",
+                    params![key, value, now, id],
+                )?;
+                tx.execute("UPDATE fts_asset_properties SET value = ?1 WHERE rowid = ?2", params![value, id])?;
+            }
+            (None, Some(value)) => {
+                tx.execute(
+                    "INSERT INTO asset_properties (asset_id, key, value, updated_at) VALUES (?1, ?2, ?3, ?4)",
+                    params![asset_id, key, value, now],
+                )?;
+                let id = tx.last_insert_rowid();
+                tx.execute("INSERT INTO fts_asset_properties(rowid, value) VALUES (?1, ?2)", params![id, value])?;
+            }
+            (Some(id), None) => {
+                tx.execute("DELETE FROM fts_asset_properties WHERE rowid = ?1", params![id])?;
+                tx.execute("DELETE FROM asset_properties WHERE id = ?1", params![id])?;
+            }
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Drop every custom property of an asset, along with its search entries
+pub fn delete_asset_properties(conn: &Connection, asset_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM fts_asset_properties WHERE rowid IN (SELECT id FROM asset_properties WHERE asset_id = ?1)",
+        params![asset_id],
+    )?;
+    conn.execute("DELETE FROM asset_properties WHERE asset_id = ?1", params![asset_id])?;
+    Ok(())
+}
+
+fn sql_value_to_json(v: rusqlite::types::Value) -> serde_json::Value {
+    use rusqlite::types::Value;
+    match v {
+        Value::Null => serde_json::Value::Null,
+        Value::Integer(i) => serde_json::json!(i),
+        Value::Real(f) => serde_json::json!(f),
+        Value::Text(s) => serde_json::Value::String(s),
+        Value::Blob(b) => serde_json::json!({ "blob": hex::encode(b) }),
+    }
+}
+
+fn json_to_sql_value(v: &serde_json::Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match v {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or(0.0)),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        serde_json::Value::Object(o) => o
+            .get("blob")
+            .and_then(|b| b.as_str())
+            .and_then(|b| hex::decode(b).ok())
+            .map(Value::Blob)
+            .unwrap_or(Value::Null),
+        serde_json::Value::Array(_) => Value::Null,
+    }
+}
+
+/// Capture an asset row with its tags, album links and custom properties so a held delete can be undone
+pub fn snapshot_asset(conn: &Connection, asset_id: i64) -> Result<Option<serde_json::Value>> {
+    let mut stmt = conn.prepare("SELECT * FROM assets WHERE id = ?1")?;
+    let names: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt.query(params![asset_id])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    let mut asset = serde_json::Map::new();
+    for (i, name) in names.iter().enumerate() {
+        asset.insert(name.clone(), sql_value_to_json(row.get::<_, rusqlite::types::Value>(i)?));
+    }
+    let mut stmt = conn.prepare("SELECT tag_id FROM asset_tags WHERE asset_id = ?1")?;
+    let tags = stmt
+        .query_map(params![asset_id], |r| r.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut stmt = conn.prepare("SELECT album_id FROM album_assets WHERE asset_id = ?1")?;
+    let albums = stmt
+        .query_map(params![asset_id], |r| r.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let properties = crate::db::query::get_asset_properties(conn, asset_id)?;
+    // Per-user favorites cascade away with the asset; (user_id, created_at) pairs
+    let mut stmt = conn.prepare("SELECT user_id, created_at FROM user_favorites WHERE asset_id = ?1")?;
+    let favorites = stmt
+        .query_map(params![asset_id], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    // Share links of just this asset are deleted with it
+    let mut stmt = conn.prepare("SELECT token_hash, password_hash, expires_at, created_by, created_at FROM shares WHERE asset_id = ?1")?;
+    let shares = stmt
+        .query_map(params![asset_id], |r| {
+            Ok(serde_json::json!({
+                "token_hash": hex::encode(r.get::<_, Vec<u8>>(0)?),
+                "password_hash": r.get::<_, Option<String>>(1)?,
+                "expires_at": r.get::<_, Option<i64>>(2)?,
+                "created_by": r.get::<_, Option<i64>>(3)?,
+                "created_at": r.get::<_, i64>(4)?,
+            }))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(Some(serde_json::json!({
+        "asset": asset, "tags": tags, "albums": albums, "properties": properties, "favorites": favorites, "shares": shares
+    })))
+}
+
+/// Re-insert an asset captured by `snapshot_asset`, keeping its id when it is still free.
+/// Returns the restored asset id.
+pub fn restore_asset_snapshot(conn: &Connection, snapshot: &serde_json::Value) -> Result<i64> {
+    let asset = snapshot
+        .get("asset")
+        .and_then(|a| a.as_object())
+        .ok_or_else(|| anyhow::anyhow!("Snapshot has no asset row"))?;
+    let old_id = asset.get("id").and_then(|v| v.as_i64());
+    let id_taken = match old_id {
+        Some(id) => conn.query_row("SELECT 1 FROM assets WHERE id = ?1", params![id], |_| Ok(())).is_ok(),
+        None => true,
+    };
+
+    let mut cols = Vec::new();
+    let mut values = Vec::new();
+    for (name, value) in asset {
+        if name == "id" && id_taken {
+            continue;
+        }
+        cols.push(format!("\"{}\"", name.replace('"', "")));
+        values.push(json_to_sql_value(value));
+    }
+    let placeholders = (1..=cols.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ");
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        &format!("INSERT INTO assets ({}) VALUES ({})", cols.join(", "), placeholders),
+        rusqlite::params_from_iter(values),
+    )?;
+    let id = tx.last_insert_rowid();
+    tx.execute(
+        "INSERT INTO fts_assets(rowid, filename, dirname, path) SELECT id, filename, dirname, path FROM assets WHERE id = ?1",
+        params![id],
+    )?;
+    let now = chrono::Utc::now().timestamp();
+    for tag_id in snapshot.get("tags").and_then(|t| t.as_array()).into_iter().flatten().filter_map(|t| t.as_i64()) {
+        tx.execute(
+            "INSERT OR IGNORE INTO asset_tags (asset_id, tag_id, created_at) SELECT ?1, id, ?3 FROM tags WHERE id = ?2",
+            params![id, tag_id, now],
+        )?;
+    }
+    for album_id in snapshot.get("albums").and_then(|a| a.as_array()).into_iter().flatten().filter_map(|a| a.as_i64()) {
+        tx.execute(
+            "INSERT OR IGNORE INTO album_assets (album_id, asset_id) SELECT id, ?1 FROM albums WHERE id = ?2",
+            params![id, album_id],
+        )?;
+    }
+    if let Some(properties) = snapshot.get("properties").and_then(|p| p.as_array()) {
+        let properties: Vec<(String, Option<String>)> = properties
+            .iter()
+            .filter_map(|p| Some((p.get(0)?.as_str()?.to_string(), Some(p.get(1)?.as_str()?.to_string()))))
+            .collect();
+        write_asset_properties(&tx, id, &properties)?;
+    }
+    for favorite in snapshot.get("favorites").and_then(|f| f.as_array()).into_iter().flatten() {
+        let (Some(user_id), Some(created_at)) = (favorite.get(0).and_then(|v| v.as_i64()), favorite.get(1).and_then(|v| v.as_i64())) else {
+            continue;
+        };
+        // Users deleted since are skipped
+        tx.execute(
+            "INSERT OR IGNORE INTO user_favorites (user_id, asset_id, created_at) SELECT id, ?2, ?3 FROM users WHERE id = ?1",
+            params![user_id, id, created_at],
+        )?;
+    }
+    for share in snapshot.get("shares").and_then(|s| s.as_array()).into_iter().flatten() {
+        let Some(token_hash) = share.get("token_hash").and_then(|t| t.as_str()).and_then(|t| hex::decode(t).ok()) else {
+            continue;
+        };
+        tx.execute(
+            "INSERT OR IGNORE INTO shares (token_hash, asset_id, password_hash, expires_at, created_by, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                token_hash,
+                id,
+                share.get("password_hash").and_then(|v| v.as_str()),
+                share.get("expires_at").and_then(|v| v.as_i64()),
+                share.get("created_by").and_then(|v| v.as_i64()),
+                share.get("created_at").and_then(|v| v.as_i64()).unwrap_or(now),
+            ],
+        )?;
+    }
+    // A restored clip gets a new id when its old one was reused, so pair it up again
+    link_live_photos(&tx, Some(&[id]))?;
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Record an original that was moved into the delete holding area
+pub fn insert_pending_delete(
+    conn: &Connection,
+    asset_id: i64,
+    original_path: &str,
+    held_path: &str,
+    sha256: Option<&[u8]>,
+    snapshot: &serde_json::Value,
+    delete_after: i64,
+) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO pending_deletes (asset_id, original_path, held_path, sha256, snapshot_json, delete_after, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![asset_id, original_path, held_path, sha256, snapshot.to_string(), delete_after, chrono::Utc::now().timestamp()],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn remove_pending_delete(conn: &Connection, id: i64) -> Result<bool> {
+    let deleted = conn.execute("DELETE FROM pending_deletes WHERE id = ?1", params![id])?;
+    Ok(deleted > 0)
+}
+
+/// Store an API token by its hash; the plaintext is only ever shown to the caller once
+pub fn insert_api_token(conn: &Connection, name: &str, token_hash: &[u8], user_id: Option<i64>) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO tokens (name, token_hash, created_at, user_id) VALUES (?1, ?2, ?3, ?4)",
+        params![name, token_hash, chrono::Utc::now().timestamp(), user_id],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Delete an API token; `user_id` restricts deletion to that user's tokens
+pub fn delete_api_token(conn: &Connection, id: i64, user_id: Option<i64>) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM tokens WHERE id = ?1 AND (?2 IS NULL OR user_id = ?2)",
+        params![id, user_id],
+    )?;
+    Ok(deleted > 0)
+}
+
+pub fn touch_api_token(conn: &Connection, id: i64, now: i64) -> Result<()> {
+    conn.execute("UPDATE tokens SET last_used_at = ?2 WHERE id = ?1", params![id, now])?;
+    Ok(())
+}
+
+pub fn insert_session(conn: &Connection, token_hash: &[u8], user_id: Option<i64>, now: i64, expires_at: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO sessions (token_hash, created_at, expires_at, user_id) VALUES (?1, ?2, ?3, ?4)",
+        params![token_hash, now, expires_at, user_id],
+    )?;
+    // Opportunistically drop sessions that have run out
+    conn.execute("DELETE FROM sessions WHERE expires_at <= ?1", params![now])?;
+    Ok(())
+}
+
+pub fn delete_session(conn: &Connection, token_hash: &[u8]) -> Result<bool> {
+    let deleted = conn.execute("DELETE FROM sessions WHERE token_hash = ?1", params![token_hash])?;
+    Ok(deleted > 0)
+}
+
+/// Create a user; returns None if the username is taken
+pub fn create_user(conn: &Connection, username: &str, password_hash: &str, is_admin: bool) -> Result<Option<i64>> {
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO users (username, password_hash, is_admin, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![username, password_hash, is_admin as i64, chrono::Utc::now().timestamp()],
+    )?;
+    Ok((inserted > 0).then(|| conn.last_insert_rowid()))
+}
+
+/// Delete a user along with their sessions, tokens and album shares.
+/// Their scan paths, albums and assets stay owned by the deleted id, so only admins see them.
+pub fn delete_user(conn: &Connection, user_id: i64) -> Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM sessions WHERE user_id = ?1", params![user_id])?;
+    tx.execute("DELETE FROM tokens WHERE user_id = ?1", params![user_id])?;
+    tx.execute("DELETE FROM album_shares WHERE user_id = ?1", params![user_id])?;
+    tx.execute("DELETE FROM shares WHERE created_by = ?1", params![user_id])?;
+    let deleted = tx.execute("DELETE FROM users WHERE id = ?1", params![user_id])?;
+    tx.commit()?;
+    Ok(deleted > 0)
+}
+
+/// Create a public share link for an album or a single asset
+pub fn insert_share(
+    conn: &Connection,
+    token_hash: &[u8],
+    album_id: Option<i64>,
+    asset_id: Option<i64>,
+    password_hash: Option<&str>,
+    expires_at: Option<i64>,
+    created_by: Option<i64>,
+) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO shares (token_hash, album_id, asset_id, password_hash, expires_at, created_by, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![token_hash, album_id, asset_id, password_hash, expires_at, created_by, chrono::Utc::now().timestamp()],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Revoke a share link; `created_by` restricts deletion to that user's links
+pub fn delete_share(conn: &Connection, id: i64, created_by: Option<i64>) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM shares WHERE id = ?1 AND (?2 IS NULL OR created_by = ?2)",
+        params![id, created_by],
+    )?;
+    Ok(deleted > 0)
+}
+
+pub fn insert_kiosk(conn: &Connection, token_hash: &[u8], name: &str, album_ids: &[i64], created_by: Option<i64>) -> Result<i64> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO kiosks (name, token_hash, created_by, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![name, token_hash, created_by, chrono::Utc::now().timestamp()],
+    )?;
+    let id = tx.last_insert_rowid();
+    {
+        let mut stmt = tx.prepare("INSERT OR IGNORE INTO kiosk_albums (kiosk_id, album_id, position) VALUES (?1, ?2, ?3)")?;
+        for (position, album_id) in album_ids.iter().enumerate() {
+            stmt.execute(params![id, album_id, position as i64])?;
+        }
+    }
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Revoke a kiosk token; `created_by` restricts deletion to that user's tokens
+pub fn delete_kiosk(conn: &Connection, id: i64, created_by: Option<i64>) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM kiosks WHERE id = ?1 AND (?2 IS NULL OR created_by = ?2)",
+        params![id, created_by],
+    )?;
+    Ok(deleted > 0)
+}
+
+pub fn insert_rule(conn: &Connection, name: &str, enabled: bool, conditions: &RuleConditions, actions: &RuleActions) -> Result<i64> {
+    let now = chrono::Utc::now().timestamp();
+    conn.execute(
+        "INSERT INTO processing_rules (name, enabled, conditions_json, actions_json, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+        params![name, enabled, serde_json::to_string(conditions)?, serde_json::to_string(actions)?, now],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_rule(conn: &Connection, id: i64, name: &str, enabled: bool, conditions: &RuleConditions, actions: &RuleActions) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE processing_rules SET name = ?2, enabled = ?3, conditions_json = ?4, actions_json = ?5, updated_at = ?6 WHERE id = ?1",
+        params![id, name, enabled, serde_json::to_string(conditions)?, serde_json::to_string(actions)?, chrono::Utc::now().timestamp()],
+    )?;
+    Ok(updated > 0)
+}
+
+pub fn delete_rule(conn: &Connection, id: i64) -> Result<bool> {
+    Ok(conn.execute("DELETE FROM processing_rules WHERE id = ?1", params![id])? > 0)
+}
+
+/// Apply the combined actions of the rules matching a just-written asset. Returns whether
+/// face detection should skip it.
+fn apply_rules(conn: &Connection, rules: &[rule::ProcessingRule], asset_id: i64, it: &DbWriteItem, now: i64) -> Result<bool> {
+    let actions = rule::actions_for(rules, it);
+    for name in actions.tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        let tag_id = ensure_tag(conn, name, now)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO asset_tags (asset_id, tag_id, created_at) VALUES (?1, ?2, ?3)",
+            params![asset_id, tag_id, now],
+        )?;
+    }
+    Ok(actions.skip_faces)
+}
+
+/// Note that the kiosk's frame displayed an asset
+pub fn record_kiosk_shown(conn: &Connection, kiosk_id: i64, asset_id: i64, now: i64) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO kiosk_shown (kiosk_id, asset_id, shown_at) VALUES (?1, ?2, ?3)",
+        params![kiosk_id, asset_id, now],
+    )?;
+    Ok(())
+}
+
+/// Store the CLIP vector for an asset, replacing any earlier one
+pub fn upsert_clip_embedding(conn: &Connection, asset_id: i64, model: &str, embedding: &[f32], now: i64) -> Result<()> {
+    let blob: Vec<u8> = embedding.iter().flat_map(|f| f.to_le_bytes()).collect();
+    conn.execute(
+        "INSERT OR REPLACE INTO clip_embeddings (asset_id, model, embedding, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![asset_id, model, blob, now],
+    )?;
+    Ok(())
+}
+
+/// Store the text OCR found in an asset; empty text records that it had none
+pub fn set_asset_text(conn: &Connection, asset_id: i64, text: &str, engine: &str, now: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO asset_text (asset_id, text, engine, created_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(asset_id) DO UPDATE SET text = excluded.text, engine = excluded.engine, created_at = excluded.created_at",
+        params![asset_id, text, engine, now],
+    )?;
+    Ok(())
+}
+
+/// Start a new rotation for the kiosk's frame
+pub fn reset_kiosk_shown(conn: &Connection, kiosk_id: i64) -> Result<()> {
+    conn.execute("DELETE FROM kiosk_shown WHERE kiosk_id = ?1", params![kiosk_id])?;
+    Ok(())
+}
+
+pub fn touch_kiosk(conn: &Connection, id: i64, now: i64) -> Result<()> {
+    conn.execute("UPDATE kiosks SET last_used_at = ?1 WHERE id = ?2", params![now, id])?;
+    Ok(())
+}
+
+/// Give an unowned scan path an owner, then claim the assets already indexed under it
+pub fn set_scan_path_owner(conn: &Connection, path: &str, owner_id: i64) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE scan_paths SET owner_id = ?1 WHERE path = ?2 AND owner_id IS NULL",
+        params![owner_id, path],
+    )?;
+    if updated > 0 {
+        assign_asset_owners(conn, None)?;
+    }
+    Ok(updated > 0)
+}
+
+/// Owner of the deepest owned scan path containing `assets.path`
+const SCAN_PATH_OWNER_SQL: &str = r"(SELECT sp.owner_id FROM scan_paths sp
+    WHERE sp.owner_id IS NOT NULL
+      AND (assets.path = sp.path
+           OR substr(assets.path, 1, length(rtrim(sp.path, '/\')) + 1) IN (rtrim(sp.path, '/\') || '/', rtrim(sp.path, '/\') || '\'))
+    ORDER BY length(sp.path) DESC LIMIT 1)";
+
+/// Deepest scan path containing `assets.path`
+const SCAN_PATH_ID_SQL: &str = r"(SELECT sp.id FROM scan_paths sp
+    WHERE substr(assets.path, 1, length(rtrim(sp.path, '/\')) + 1) IN (rtrim(sp.path, '/\') || '/', rtrim(sp.path, '/\') || '\')
+    ORDER BY length(sp.path) DESC LIMIT 1)";
+
+/// Attach assets to the scan path they live under and store their path relative to it.
+/// `asset_ids` limits the update to freshly written or moved rows; None recomputes every asset.
+pub fn assign_asset_roots(conn: &Connection, asset_ids: Option<&[i64]>) -> Result<usize> {
+    let update = format!(
+        r"UPDATE assets SET path_id = {id}, rel_path = substr(path, length(rtrim((SELECT sp.path FROM scan_paths sp WHERE sp.id = {id}), '/\')) + 2) WHERE 1",
+        id = SCAN_PATH_ID_SQL
+    );
+    match asset_ids {
+        None => Ok(conn.execute(&update, [])?),
+        Some(ids) => {
+            let mut updated = 0;
+            for chunk in ids.chunks(500) {
+                let placeholders = vec!["?"; chunk.len()].join(",");
+                updated += conn.execute(
+                    &format!("{} AND id IN ({})", update, placeholders),
+                    rusqlite::params_from_iter(chunk),
+                )?;
+            }
+            Ok(updated)
+        }
+    }
+}
+
+/// Point a scan path, and any scan paths nested in it, at a new location. The
+/// `scan_paths_relocate` trigger rewrites the assets' absolute paths from their rel_path.
+/// Returns the number of assets moved, or None if `old` isn't a scan path.
+pub fn relocate_scan_path(conn: &Connection, old: &str, new: &str) -> Result<Option<i64>> {
+    let tx = conn.unchecked_transaction()?;
+    let Some(id) = tx.query_row("SELECT id FROM scan_paths WHERE path = ?1", params![old], |r| r.get::<_, i64>(0)).optional()? else {
+        return Ok(None);
+    };
+    let old_base = old.trim_end_matches(['/', '\\']);
+    let new_base = new.trim_end_matches(['/', '\\']);
+    tx.execute(
+        "UPDATE scan_paths SET path = ?1 || substr(path, length(?2) + 1)
+         WHERE substr(path, 1, length(?2) + 1) IN (?2 || '/', ?2 || '\\')",
+        params![new_base, old_base],
+    )?;
+    tx.execute("UPDATE scan_paths SET path = ?1 WHERE id = ?2", params![new, id])?;
+    let moved: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM assets WHERE path_id IN (SELECT id FROM scan_paths WHERE id = ?1 OR substr(path, 1, length(?2) + 1) IN (?2 || '/', ?2 || '\\'))",
+        params![id, new_base],
+        |r| r.get(0),
+    )?;
+    tx.commit()?;
+    Ok(Some(moved))
+}
+
+/// Give unowned assets the owner of the scan path they were found under.
+/// `asset_ids` limits the update to freshly written rows; None checks every unowned asset.
+pub fn assign_asset_owners(conn: &Connection, asset_ids: Option<&[i64]>) -> Result<usize> {
+    let any_owned: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM scan_paths WHERE owner_id IS NOT NULL)",
+        [],
+        |r| r.get(0),
+    )?;
+    if !any_owned {
+        return Ok(0);
+    }
+    let update = format!("UPDATE assets SET owner_id = {} WHERE owner_id IS NULL", SCAN_PATH_OWNER_SQL);
+    match asset_ids {
+        None => Ok(conn.execute(&update, [])?),
+        Some(ids) => {
+            let mut updated = 0;
+            for chunk in ids.chunks(500) {
+                let placeholders = vec!["?"; chunk.len()].join(",");
+                updated += conn.execute(
+                    &format!("{} AND id IN ({})", update, placeholders),
+                    rusqlite::params_from_iter(chunk),
+                )?;
+            }
+            Ok(updated)
+        }
+    }
+}
+
+pub fn set_album_owner(conn: &Connection, album_id: i64, owner_id: Option<i64>) -> Result<bool> {
+    let updated = conn.execute("UPDATE albums SET owner_id = ?1 WHERE id = ?2", params![owner_id, album_id])?;
+    Ok(updated > 0)
+}
+
+/// Share an album with a user; returns false if it was already shared
+pub fn share_album(conn: &Connection, album_id: i64, user_id: i64) -> Result<bool> {
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO album_shares (album_id, user_id, created_at) VALUES (?1, ?2, ?3)",
+        params![album_id, user_id, chrono::Utc::now().timestamp()],
+    )?;
+    Ok(inserted > 0)
+}
+
+pub fn unshare_album(conn: &Connection, album_id: i64, user_id: i64) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM album_shares WHERE album_id = ?1 AND user_id = ?2",
+        params![album_id, user_id],
+    )?;
+    Ok(deleted > 0)
+}
+
+#[cfg(test)]
+mod path_deletion_tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        conn
+    }
+
+    fn insert_test_asset(conn: &Connection, path: &str) {
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags)
+             VALUES (?1, ?2, ?3, ?4, 1000, 0, 0, 'image/jpeg', 0)",
+            params![
+                path,
+                std::path::Path::new(path).parent().and_then(|p| p.to_str()).unwrap_or(""),
+                std::path::Path::new(path).file_name().and_then(|f| f.to_str()).unwrap_or(""),
+                std::path::Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("")
+            ],
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_delete_assets_with_forward_slash_paths() {
+        let conn = setup_test_db();
+
+        // Insert assets with forward slash paths (Unix-style)
+        insert_test_asset(&conn, "/photos/vacation/img1.jpg");
+        insert_test_asset(&conn, "/photos/vacation/img2.jpg");
+        insert_test_asset(&conn, "/photos/work/img3.jpg");
+
+        // Verify all assets exist
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
+        assert_eq!(count, 3);
+
+        // Delete assets under /photos/vacation
+        let (deleted, _) = delete_assets_by_path_prefix(&conn, "/photos/vacation").unwrap();
+        assert_eq!(deleted, 2);
+
+        // Verify only work photo remains
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
+        assert_eq!(count, 1);
+
+        let remaining: String = conn.query_row("SELECT path FROM assets", [], |r| r.get(0)).unwrap();
+        assert_eq!(remaining, "/photos/work/img3.jpg");
+    }
+
+    #[test]
+    fn test_delete_assets_with_backslash_paths() {
+        let conn = setup_test_db();
+
+        // Insert assets with backslash paths (Windows-style)
+        insert_test_asset(&conn, r"C:\Users\Photos\vacation\img1.jpg");
+        insert_test_asset(&conn, r"C:\Users\Photos\vacation\img2.jpg");
+        insert_test_asset(&conn, r"C:\Users\Photos\work\img3.jpg");
+
+        // Verify all assets exist
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
+        assert_eq!(count, 3);
+
+        // Delete assets under C:\Users\Photos\vacation
+        let (deleted, _) = delete_assets_by_path_prefix(&conn, r"C:\Users\Photos\vacation").unwrap();
+        assert_eq!(deleted, 2);
+
+        // Verify only work photo remains
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
+        assert_eq!(count, 1);
+
+        let remaining: String = conn.query_row("SELECT path FROM assets", [], |r| r.get(0)).unwrap();
+        assert_eq!(remaining, r"C:\Users\Photos\work\img3.jpg");
+    }
+
+    #[test]
+    fn test_delete_assets_with_trailing_separator() {
+        let conn = setup_test_db();
+
+        // Insert assets with backslash paths
+        insert_test_asset(&conn, r"D:\Media\photos\img1.jpg");
+        insert_test_asset(&conn, r"D:\Media\photos\img2.jpg");
+
+        // Delete with trailing backslash
+        let (deleted, _) = delete_assets_by_path_prefix(&conn, r"D:\Media\photos\").unwrap();
+        assert_eq!(deleted, 2);
+
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_delete_no_match() {
+        let conn = setup_test_db();
+
+        insert_test_asset(&conn, r"C:\Users\Photos\img1.jpg");
+
+        // Try to delete from non-existent path
+        let (deleted, _) = delete_assets_by_path_prefix(&conn, r"D:\Other\path").unwrap();
+        assert_eq!(deleted, 0);
+
+        // Original asset should still exist
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_delete_exact_path_match() {
+        let conn = setup_test_db();
 
         // This edge case: path prefix IS a complete file path
         insert_test_asset(&conn, r"C:\single\file.jpg");
@@ -1306,4 +3026,520 @@ mod path_deletion_tests {
         let (deleted, _) = delete_assets_by_path_prefix(&conn, r"C:\single\file.jpg").unwrap();
         assert_eq!(deleted, 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_pending_job_journal() {
+        let conn = setup_test_db();
+        add_scan_path(&conn, "/photos").unwrap();
+        add_scan_path(&conn, "/videos").unwrap();
+        let paths = ["/photos/a.jpg", "/photos/b.jpg", "/videos/c.mp4", "/removed/d.jpg"].map(String::from);
+        journal_pending_jobs(&conn, &paths).unwrap();
+        // Journaling the same file twice keeps one entry
+        journal_pending_jobs(&conn, &paths[..1]).unwrap();
+
+        // Removing a scan path drops its pending files too
+        delete_assets_by_path_prefix(&conn, "/videos").unwrap();
+        // Paths outside every scan path are not brought back
+        assert_eq!(take_pending_jobs(&conn).unwrap(), vec!["/photos/a.jpg".to_string(), "/photos/b.jpg".to_string()]);
+        assert!(take_pending_jobs(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_face_job_journal() {
+        let conn = setup_test_db();
+        for path in ["/photos/a.jpg", "/photos/b.jpg", "/photos/c.jpg"] {
+            insert_test_asset(&conn, path);
+        }
+        let counts = |conn: &Connection| crate::db::query::face_job_counts(conn).unwrap();
+        journal_face_jobs(&conn, &[1, 2, 3]).unwrap();
+        journal_face_jobs(&conn, &[1]).unwrap();
+        finish_face_job(&conn, 2).unwrap();
+        assert_eq!(counts(&conn), (1, 3));
+
+        // A removed asset is no longer resumed or counted
+        conn.execute("DELETE FROM assets WHERE id = 3", []).unwrap();
+        assert_eq!(pending_face_jobs(&conn).unwrap(), vec![(1, "/photos/a.jpg".to_string())]);
+        assert_eq!(counts(&conn), (1, 2));
+
+        // A new run starts counting from what is still pending
+        start_face_run(&conn).unwrap();
+        assert_eq!(counts(&conn), (0, 1));
+        clear_face_jobs(&conn).unwrap();
+        assert!(pending_face_jobs(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_relocate_scan_path() {
+        let conn = setup_test_db();
+        add_scan_path(&conn, "/mnt/old/photos").unwrap();
+        add_scan_path(&conn, "/mnt/old/photos/phone").unwrap();
+        insert_test_asset(&conn, "/mnt/old/photos/2024/a.jpg");
+        insert_test_asset(&conn, "/mnt/old/photos/phone/b.jpg");
+        insert_test_asset(&conn, "/elsewhere/c.jpg");
+        assert_eq!(assign_asset_roots(&conn, None).unwrap(), 3);
+        conn.execute("INSERT INTO fts_assets(rowid, filename, dirname, path) SELECT id, filename, dirname, path FROM assets", []).unwrap();
+        let roots: Vec<(Option<i64>, Option<String>)> = conn
+            .prepare("SELECT path_id, rel_path FROM assets ORDER BY id").unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(roots, vec![(Some(1), Some("2024/a.jpg".into())), (Some(2), Some("b.jpg".into())), (None, None)]);
+
+        assert_eq!(relocate_scan_path(&conn, "/mnt/old/photos", "/mnt/new/photos/").unwrap(), Some(2));
+        let paths: Vec<(String, String)> = conn
+            .prepare("SELECT path, dirname FROM assets ORDER BY id").unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(paths, vec![
+            ("/mnt/new/photos/2024/a.jpg".into(), "/mnt/new/photos/2024".into()),
+            ("/mnt/new/photos/phone/b.jpg".into(), "/mnt/new/photos/phone".into()),
+            ("/elsewhere/c.jpg".into(), "/elsewhere".into()),
+        ]);
+        let nested: String = conn.query_row("SELECT path FROM scan_paths WHERE id = 2", [], |r| r.get(0)).unwrap();
+        assert_eq!(nested, "/mnt/new/photos/phone");
+        // The search index follows the move
+        let hits: i64 = conn.query_row("SELECT COUNT(*) FROM fts_assets WHERE fts_assets MATCH 'path:new'", [], |r| r.get(0)).unwrap();
+        assert_eq!(hits, 2);
+        let stale: i64 = conn.query_row("SELECT COUNT(*) FROM fts_assets WHERE fts_assets MATCH 'path:old'", [], |r| r.get(0)).unwrap();
+        assert_eq!(stale, 0);
+
+        assert_eq!(relocate_scan_path(&conn, "/not/a/scan/path", "/x").unwrap(), None);
+
+        // Windows roots keep their backslashes
+        add_scan_path(&conn, r"D:\photos").unwrap();
+        conn.execute(
+            r"INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags)
+              VALUES ('D:\photos\2024\d.jpg', 'D:\photos\2024', 'd.jpg', 'jpg', 1000, 0, 0, 'image/jpeg', 0)",
+            [],
+        ).unwrap();
+        assign_asset_roots(&conn, None).unwrap();
+        assert_eq!(relocate_scan_path(&conn, r"D:\photos", r"E:\photos\").unwrap(), Some(1));
+        let moved: (String, String) = conn
+            .query_row("SELECT path, dirname FROM assets WHERE filename = 'd.jpg'", [], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!(moved, (r"E:\photos\2024\d.jpg".into(), r"E:\photos\2024".into()));
+    }
+}
+
+#[cfg(test)]
+mod stack_tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn insert_shot(conn: &Connection, filename: &str, camera: Option<&str>, taken_at: i64) {
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, camera_model, taken_at)
+             VALUES (?1, '/dcim', ?2, 'jpg', 1000, 0, 0, 'image/jpeg', 0, ?3, ?4)",
+            params![format!("/dcim/{}", filename), filename, camera, taken_at],
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_burst_key() {
+        assert_eq!(burst_key("00000IMG_00000_BURST20231224123456_COVER.jpg").as_deref(), Some("BURST20231224123456"));
+        assert_eq!(burst_key("IMG_1234.jpg"), None);
+        assert_eq!(burst_key("burst.jpg"), None);
+    }
+
+    #[test]
+    fn test_rebuild_stacks() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+
+        // Three shots 1s apart, then a gap, then an isolated shot (same camera)
+        insert_shot(&conn, "a1.jpg", Some("X100V"), 1000);
+        insert_shot(&conn, "a2.jpg", Some("X100V"), 1001);
+        insert_shot(&conn, "a3.jpg", Some("X100V"), 1002);
+        insert_shot(&conn, "b1.jpg", Some("X100V"), 1100);
+        // Same time but different camera: not stacked with the X100V burst
+        insert_shot(&conn, "c1.jpg", Some("Pixel 8"), 1001);
+        // Burst ID from filename, regardless of camera/time
+        insert_shot(&conn, "00001IMG_00001_BURST20231224123456.jpg", None, 5000);
+        insert_shot(&conn, "00000IMG_00000_BURST20231224123456_COVER.jpg", None, 9000);
+        // Another user's shots from the same camera and burst: never stacked with the first user's
+        insert_shot(&conn, "d1.jpg", Some("X100V"), 1001);
+        insert_shot(&conn, "00002IMG_00002_BURST20231224123456.jpg", None, 5001);
+        conn.execute("INSERT INTO users (id, username, password_hash, created_at) VALUES (1, 'alice', 'x', 0), (2, 'bob', 'x', 0)", []).unwrap();
+        conn.execute("UPDATE assets SET owner_id = 1 WHERE id IN (1, 2, 3)", []).unwrap();
+        conn.execute("UPDATE assets SET owner_id = 2 WHERE id IN (8, 9)", []).unwrap();
+
+        assert_eq!(rebuild_stacks(&conn, 2).unwrap(), 2);
+
+        let stacks = crate::db::query::list_stacks(&conn, 0, 10, crate::db::query::AccessScope::All).unwrap();
+        assert_eq!(stacks.total, 2);
+        let (_, cover, count, key, ids) = &stacks.items[0];
+        assert_eq!((*cover, *count, key.as_deref()), (1, 3, None));
+        assert_eq!(ids, &vec![1, 2, 3]);
+        let (_, cover, _, key, ids) = &stacks.items[1];
+        assert_eq!((*cover, key.as_deref()), (7, Some("BURST20231224123456")));
+        assert_eq!(ids, &vec![6, 7]);
+
+        // Collapsed listing hides non-cover stack members
+        let filters = crate::db::query::ListFilters { collapse_stacks: true, ..Default::default() };
+        let page = crate::db::query::list_assets(&conn, 0, 100, "none", "asc", filters).unwrap();
+        let ids: Vec<i64> = page.items.iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec![1, 4, 5, 7, 8, 9]);
+        assert_eq!(page.total, 6);
+
+        // Other users only see the shared stack
+        let stacks = crate::db::query::list_stacks(&conn, 0, 10, crate::db::query::AccessScope::User(2)).unwrap();
+        assert_eq!(stacks.total, 1);
+        assert_eq!(stacks.items[0].4, vec![6, 7]);
+        assert_eq!(crate::db::query::list_stacks(&conn, 0, 10, crate::db::query::AccessScope::User(1)).unwrap().total, 2);
+
+        // Rebuilding is idempotent
+        assert_eq!(rebuild_stacks(&conn, 2).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_restack_assets() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        let members = |conn: &Connection| -> Vec<Vec<i64>> {
+            let mut all: Vec<Vec<i64>> = crate::db::query::list_stacks(conn, 0, 100, crate::db::query::AccessScope::All)
+                .unwrap().items.into_iter().map(|s| s.4).collect();
+            all.sort();
+            all
+        };
+
+        insert_shot(&conn, "a1.jpg", Some("X100V"), 1000);
+        insert_shot(&conn, "a2.jpg", Some("X100V"), 1001);
+        insert_shot(&conn, "b1.jpg", Some("X100V"), 1100);
+        assert_eq!(rebuild_stacks(&conn, 2).unwrap(), 1);
+        let stack_id: i64 = conn.query_row("SELECT id FROM stacks", [], |r| r.get(0)).unwrap();
+
+        // A new frame joins the existing stack, which keeps its id
+        insert_shot(&conn, "a3.jpg", Some("X100V"), 1002);
+        assert_eq!(restack_assets(&conn, &[4], 2).unwrap(), 1);
+        assert_eq!(members(&conn), vec![vec![1, 2, 4]]);
+        assert_eq!(conn.query_row("SELECT id FROM stacks", [], |r| r.get::<_, i64>(0)).unwrap(), stack_id);
+
+        // New stacks form next to it without touching it, bursts included
+        insert_shot(&conn, "b2.jpg", Some("X100V"), 1101);
+        insert_shot(&conn, "00001IMG_00001_BURST20231224123456.jpg", None, 5000);
+        insert_shot(&conn, "00000IMG_00000_BURST20231224123456_COVER.jpg", None, 9000);
+        assert_eq!(restack_assets(&conn, &[5, 6, 7], 2).unwrap(), 2);
+        assert_eq!(members(&conn), vec![vec![1, 2, 4], vec![3, 5], vec![6, 7]]);
+        assert_eq!(conn.query_row("SELECT asset_count FROM stacks WHERE id = ?1", [stack_id], |r| r.get::<_, i64>(0)).unwrap(), 3);
+
+        // A rewritten shot that moved away leaves its old stack, which shrinks
+        conn.execute("UPDATE assets SET taken_at = 2000 WHERE id = 2", []).unwrap();
+        restack_assets(&conn, &[2], 2).unwrap();
+        assert_eq!(members(&conn), vec![vec![1, 4], vec![3, 5], vec![6, 7]]);
+        assert_eq!(conn.query_row("SELECT asset_count FROM stacks WHERE id = ?1", [stack_id], |r| r.get::<_, i64>(0)).unwrap(), 2);
+
+        // Incremental regrouping agrees with a full rebuild
+        let incremental = members(&conn);
+        rebuild_stacks(&conn, 2).unwrap();
+        assert_eq!(members(&conn), incremental);
+    }
+
+    #[test]
+    fn test_rebuild_stacks_caps_span() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+
+        // A 25s timelapse at one frame per second splits into stacks of at most 10s
+        for i in 0..25 {
+            insert_shot(&conn, &format!("t{:02}.jpg", i), Some("GoPro"), 1000 + i);
+        }
+        assert_eq!(rebuild_stacks(&conn, 2).unwrap(), 3);
+        let stacks = crate::db::query::list_stacks(&conn, 0, 10, crate::db::query::AccessScope::All).unwrap();
+        let mut counts: Vec<i64> = stacks.items.iter().map(|s| s.2).collect();
+        counts.sort();
+        assert_eq!(counts, vec![3, 11, 11]);
+
+        // A window longer than the cap still stacks everything within it
+        assert_eq!(rebuild_stacks(&conn, 30).unwrap(), 1);
+
+        // Regrouping a frame at the end of a long timelapse follows the run back to its start
+        rebuild_stacks(&conn, 2).unwrap();
+        for i in 25..100 {
+            insert_shot(&conn, &format!("t{:02}.jpg", i), Some("GoPro"), 1000 + i);
+        }
+        let id = conn.last_insert_rowid();
+        restack_assets(&conn, &[id], 2).unwrap();
+        let members = |conn: &Connection| -> Vec<Vec<i64>> {
+            let mut all: Vec<Vec<i64>> = crate::db::query::list_stacks(conn, 0, 100, crate::db::query::AccessScope::All)
+                .unwrap().items.into_iter().map(|s| s.4).collect();
+            all.sort();
+            all
+        };
+        let incremental = members(&conn);
+        assert_eq!(incremental.len(), 9);
+        rebuild_stacks(&conn, 2).unwrap();
+        assert_eq!(members(&conn), incremental);
+    }
+}
+
+#[cfg(test)]
+mod album_tests {
+    use super::*;
+    use crate::db::schema;
+
+    #[test]
+    fn test_batch_update_album_assets() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        for id in 1..=4 {
+            conn.execute(
+                "INSERT INTO assets (id, path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime) VALUES (?1, ?2, '/a', 'x.jpg', 'jpg', 1, 0, 0, 'image/jpeg')",
+                params![id, format!("/a/{}.jpg", id)],
+            ).unwrap();
+        }
+        let a = create_album(&conn, "A", None, None).unwrap();
+        let b = create_album(&conn, "B", None, None).unwrap();
+        add_assets_to_album(&conn, a, &[1, 2]).unwrap();
+
+        let (added, removed) = batch_update_album_assets(&conn, &[a, b], &[3, 4], &[1]).unwrap();
+        // 3,4 into both albums; 1 only existed in A
+        assert_eq!(added, 4);
+        assert_eq!(removed, 1);
+
+        let members = |album: i64| -> Vec<i64> {
+            let mut stmt = conn.prepare("SELECT asset_id FROM album_assets WHERE album_id = ?1 ORDER BY asset_id").unwrap();
+            stmt.query_map(params![album], |r| r.get(0)).unwrap().map(|r| r.unwrap()).collect()
+        };
+        assert_eq!(members(a), vec![2, 3, 4]);
+        assert_eq!(members(b), vec![3, 4]);
+    }
+}
+
+#[cfg(test)]
+mod rename_tests {
+    use super::*;
+    use crate::db::schema;
+
+    fn item(path: &str, sha256: &[u8]) -> DbWriteItem {
+        let p = std::path::Path::new(path);
+        DbWriteItem {
+            path: path.to_string(),
+            dirname: p.parent().unwrap().to_string_lossy().into_owned(),
+            filename: p.file_name().unwrap().to_string_lossy().into_owned(),
+            ext: "jpg".into(),
+            size_bytes: 100,
+            mtime_ns: 0,
+            ctime_ns: 0,
+            sha256: Some(sha256.to_vec()),
+            xxh64: Some(1),
+            taken_at: None,
+            width: Some(10),
+            height: Some(10),
+            duration_ms: None,
+            camera_make: None,
+            camera_model: None,
+            lens_model: None,
+            iso: None,
+            fnumber: None,
+            exposure: None,
+            video_codec: None,
+            mime: "image/jpeg".into(),
+            flags: 0,
+            properties: Vec::new(),
+            source_app: None,
+        }
+    }
+
+    fn write(conn: &Connection, path: &std::path::Path, sha256: &[u8]) -> i64 {
+        let tx = conn.unchecked_transaction().unwrap();
+        let id = upsert_item(&tx, &item(&path.to_string_lossy(), sha256)).unwrap();
+        tx.commit().unwrap();
+        id
+    }
+
+    #[test]
+    fn test_moved_file_keeps_asset_id() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        let old_path = tmp.path().join("old").join("a.jpg");
+        let new_path = tmp.path().join("new").join("a.jpg");
+        let copy_path = tmp.path().join("copy").join("a.jpg");
+        for p in [&old_path, &new_path, &copy_path] {
+            std::fs::create_dir_all(p.parent().unwrap()).unwrap();
+            std::fs::write(p, b"x").unwrap();
+        }
+
+        let id = write(&conn, &old_path, b"h1");
+        add_tags_to_asset(&conn, id, &["trip".to_string()]).unwrap();
+
+        // Old file still there: the new one is a copy and gets its own row
+        let copy_id = write(&conn, &copy_path, b"h1");
+        assert_ne!(copy_id, id);
+
+        // Old file gone: same asset, new path
+        std::fs::remove_file(&old_path).unwrap();
+        assert_eq!(write(&conn, &new_path, b"h1"), id);
+        let (path, filename): (String, String) = conn.query_row("SELECT path, filename FROM assets WHERE id = ?1", params![id], |r| Ok((r.get(0)?, r.get(1)?))).unwrap();
+        assert_eq!((path, filename), (new_path.to_string_lossy().into_owned(), "a.jpg".to_string()));
+        let tags: i64 = conn.query_row("SELECT COUNT(*) FROM asset_tags WHERE asset_id = ?1", params![id], |r| r.get(0)).unwrap();
+        assert_eq!(tags, 1);
+
+        // Different content is never treated as a move
+        let other = tmp.path().join("new").join("b.jpg");
+        assert_ne!(write(&conn, &other, b"h2"), id);
+        let total: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |r| r.get(0)).unwrap();
+        assert_eq!(total, 3);
+    }
+
+    #[test]
+    fn test_rewrite_keeps_ingested_at() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        let path = std::path::Path::new("/photos/a.jpg");
+        let before = chrono::Utc::now().timestamp();
+        let id = write(&conn, path, b"h1");
+        let ingested_at = |id: i64| conn.query_row("SELECT ingested_at FROM assets WHERE id = ?1", params![id], |r| r.get::<_, i64>(0)).unwrap();
+        assert!(ingested_at(id) >= before);
+
+        conn.execute("UPDATE assets SET ingested_at = 5 WHERE id = ?1", params![id]).unwrap();
+        assert_eq!(write(&conn, path, b"h2"), id);
+        assert_eq!(ingested_at(id), 5);
+    }
+
+    #[test]
+    fn test_archived_originals_are_not_moves() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        let cold = tmp.path().join("cold");
+        let archived_path = cold.join("a.jpg");
+        let copy_path = tmp.path().join("photos").join("a.jpg");
+        std::fs::create_dir_all(copy_path.parent().unwrap()).unwrap();
+        std::fs::write(&copy_path, b"x").unwrap();
+
+        let cold_str = cold.to_string_lossy().into_owned();
+        add_scan_path(&conn, &cold_str).unwrap();
+        let id = write(&conn, &archived_path, b"h1");
+        assert_eq!(set_scan_path_archived(&conn, &cold_str, true, Some("Drive B")).unwrap(), Some(1));
+        assert_eq!(crate::db::query::get_asset_archive(&conn, id).unwrap(), Some(Some("Drive B".to_string())));
+
+        // The drive is unplugged, so the archived original is missing; a copy elsewhere must not take it over
+        assert_ne!(write(&conn, &copy_path, b"h1"), id);
+        assert!(!crate::db::query::delete_asset_by_path(&conn, &archived_path.to_string_lossy()).unwrap());
+
+        assert_eq!(set_scan_path_archived(&conn, &cold_str, false, None).unwrap(), Some(1));
+        assert_eq!(crate::db::query::get_asset_archive(&conn, id).unwrap(), None);
+        assert_eq!(set_scan_path_archived(&conn, "/not/a/scan/path", true, None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_asset_lock() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        let id = write(&conn, &tmp.path().join("scan.tif"), b"h1");
+        assert!(!crate::db::query::is_asset_locked(&conn, id).unwrap());
+
+        assert!(set_asset_locked(&conn, id, true).unwrap());
+        assert!(crate::db::query::is_asset_locked(&conn, id).unwrap());
+        assert!(crate::db::query::get_asset_by_id(&conn, id).unwrap().unwrap().locked);
+        assert!(set_asset_locked(&conn, id, false).unwrap());
+        assert!(!crate::db::query::is_asset_locked(&conn, id).unwrap());
+
+        assert!(!set_asset_locked(&conn, id + 1, true).unwrap());
+        assert!(!crate::db::query::is_asset_locked(&conn, id + 1).unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tag_tests {
+    use super::*;
+    use crate::db::schema;
+
+    #[test]
+    fn test_set_auto_tags_keeps_manual_tags() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO assets (id, path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime) VALUES (1, '/a/beach.jpg', '/a', 'beach.jpg', 'jpg', 1, 0, 0, 'image/jpeg')",
+            [],
+        ).unwrap();
+        let auto = |conn: &Connection| crate::db::query::get_asset_auto_tags(conn, 1).unwrap().into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+
+        add_tags_to_asset(&conn, 1, &["Beach".to_string()]).unwrap();
+        set_auto_tags(&conn, 1, &[("sunset".to_string(), 0.9), ("beach".to_string(), 0.7)]).unwrap();
+        assert_eq!(auto(&conn), vec!["sunset"]);
+        assert_eq!(crate::db::query::get_asset_tags(&conn, 1).unwrap(), vec!["Beach", "sunset"]);
+
+        // Re-classifying drops predictions that no longer apply, but never the manual tag
+        set_auto_tags(&conn, 1, &[("food".to_string(), 0.6)]).unwrap();
+        assert_eq!(auto(&conn), vec!["food"]);
+        assert_eq!(crate::db::query::get_asset_tags(&conn, 1).unwrap(), vec!["Beach", "food"]);
+
+        // Adding a predicted tag by hand confirms it
+        add_tags_to_asset(&conn, 1, &["food".to_string()]).unwrap();
+        set_auto_tags(&conn, 1, &[]).unwrap();
+        assert!(auto(&conn).is_empty());
+        assert_eq!(crate::db::query::get_asset_tags(&conn, 1).unwrap(), vec!["Beach", "food"]);
+    }
+}
+
+#[cfg(test)]
+mod rule_tests {
+    use super::*;
+    use crate::db::schema;
+
+    #[test]
+    fn test_processing_rules() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        let screenshots = RuleConditions { path_contains: Some("/Screenshots".to_string()), ..Default::default() };
+        let clips = RuleConditions { mime: Some("video".to_string()), max_duration_ms: Some(3000), ..Default::default() };
+        insert_rule(&conn, "Screenshots", true, &screenshots, &RuleActions { tags: vec!["screenshot".to_string()], skip_faces: true }).unwrap();
+        let clip_rule = insert_rule(&conn, "Clips", true, &clips, &RuleActions { tags: vec!["clip".to_string()], skip_faces: false }).unwrap();
+        let rules = crate::db::query::list_rules(&conn).unwrap();
+        assert_eq!(rules.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), vec!["Screenshots", "Clips"]);
+
+        let item = |path: &str, mime: &str, duration_ms: Option<i64>| DbWriteItem {
+            path: path.to_string(),
+            dirname: String::new(),
+            filename: String::new(),
+            ext: path.rsplit('.').next().unwrap().to_string(),
+            size_bytes: 100,
+            mtime_ns: 0,
+            ctime_ns: 0,
+            sha256: None,
+            xxh64: None,
+            taken_at: None,
+            width: None,
+            height: None,
+            duration_ms,
+            camera_make: None,
+            camera_model: None,
+            lens_model: None,
+            iso: None,
+            fnumber: None,
+            exposure: None,
+            video_codec: None,
+            mime: mime.to_string(),
+            flags: 0,
+            properties: Vec::new(),
+            source_app: None,
+        };
+        let shot = item("C:\\Users\\me\\Pictures\\screenshots\\a.png", "image/png", None);
+        let short = item("/phone/b.mp4", "video/mp4", Some(2500));
+        let long = item("/phone/c.mp4", "video/mp4", Some(60_000));
+        for (id, it) in [(1, &shot), (2, &short), (3, &long)] {
+            conn.execute(
+                "INSERT INTO assets (id, path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime) VALUES (?1, ?2, '', '', ?3, 1, 0, 0, ?4)",
+                params![id, it.path, it.ext, it.mime],
+            ).unwrap();
+        }
+
+        assert!(apply_rules(&conn, &rules, 1, &shot, 0).unwrap());
+        assert!(!apply_rules(&conn, &rules, 2, &short, 0).unwrap());
+        assert!(!apply_rules(&conn, &rules, 3, &long, 0).unwrap());
+        // Re-indexing a changed file applies the rules again without duplicating tags
+        assert!(apply_rules(&conn, &rules, 1, &shot, 0).unwrap());
+        let tags = |id| crate::db::query::get_asset_tags(&conn, id).unwrap();
+        assert_eq!((tags(1), tags(2), tags(3)), (vec!["screenshot".to_string()], vec!["clip".to_string()], vec![]));
+
+        // Disabled rules are kept but don't apply
+        update_rule(&conn, clip_rule, "Clips", false, &clips, &RuleActions { tags: vec!["short".to_string()], skip_faces: false }).unwrap();
+        let rules = crate::db::query::list_rules(&conn).unwrap();
+        assert!(!rules[1].enabled && rules[1].actions.tags == ["short"]);
+        apply_rules(&conn, &rules, 2, &short, 0).unwrap();
+        assert_eq!(tags(2), vec!["clip".to_string()]);
+        assert!(delete_rule(&conn, clip_rule).unwrap() && !delete_rule(&conn, clip_rule).unwrap());
+    }
+}
@@ -11,17 +11,18 @@ use parking_lot::Mutex;
 use tokio::sync::broadcast;
 use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64};
 use std::collections::HashMap;
-use r2d2::Pool;
-use r2d2_sqlite::SqliteConnectionManager;
 
-/// Type alias for the SQLite connection pool
-pub type DbPool = Pool<SqliteConnectionManager>;
+/// The SQLite read connection pool
+pub use db::DbPool;
 
 /// Cache for expensive database counts with TTL
 pub struct StatsCache {
     pub asset_count: AtomicI64,
     pub photo_count: AtomicI64,
     pub video_count: AtomicI64,
+    /// Originals waiting in the delete holding area, and their total size
+    pub trash_count: AtomicI64,
+    pub trash_bytes: AtomicI64,
     /// Unix timestamp in seconds when cache was last updated
     pub last_updated: AtomicU64,
     /// Track if processing was active in the last stats check (for detecting completion)
@@ -34,6 +35,8 @@ impl StatsCache {
             asset_count: AtomicI64::new(0),
             photo_count: AtomicI64::new(0),
             video_count: AtomicI64::new(0),
+            trash_count: AtomicI64::new(0),
+            trash_bytes: AtomicI64::new(0),
             last_updated: AtomicU64::new(0),
             was_processing_active: AtomicBool::new(false),
         }
@@ -62,6 +65,19 @@ impl StatsCache {
         self.last_updated.store(now, Relaxed);
     }
 
+    /// Update the cached holding area totals (call before `update`, which marks the cache fresh)
+    pub fn update_trash(&self, count: i64, bytes: i64) {
+        use std::sync::atomic::Ordering::Relaxed;
+        self.trash_count.store(count, Relaxed);
+        self.trash_bytes.store(bytes, Relaxed);
+    }
+
+    /// Get cached holding area totals as (count, bytes)
+    pub fn trash(&self) -> (i64, i64) {
+        use std::sync::atomic::Ordering::Relaxed;
+        (self.trash_count.load(Relaxed), self.trash_bytes.load(Relaxed))
+    }
+
     /// Get cached counts
     pub fn get(&self) -> (i64, i64, i64) {
         use std::sync::atomic::Ordering::Relaxed;
@@ -104,7 +120,47 @@ pub struct AppState {
     pub scan_running: Arc<AtomicBool>,
     pub path_scan_running: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
     pub path_watcher_paused: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+    /// Cancel flag of each path's latest scan, see `POST /paths/cancel`
+    pub path_scan_cancel: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
     pub path_watchers: Arc<Mutex<HashMap<String, tokio::task::JoinHandle<()>>>>,
+    /// Include/exclude globs per scan path, shared with that path's watcher
+    pub path_filters: Arc<Mutex<HashMap<String, pipeline::path_filter::SharedPathFilter>>>,
+    /// Status of background video transcodes, keyed by asset id
+    pub transcodes: Arc<pipeline::transcode::TranscodeTracker>,
+    /// Status of highlight video renders, keyed by album id
+    pub highlights: Arc<pipeline::transcode::TranscodeTracker>,
+    pub auth: Arc<api::auth::AuthConfig>,
+    /// Temporary images uploaded as search queries
+    pub reference_images: Arc<utils::reference::ReferenceStore>,
+    /// Ingest filters, shared with the discover forwarder and metadata workers
+    pub ingest_filter: pipeline::ingest::SharedIngestFilter,
+    /// Date source order per scan path, shared with the metadata workers
+    pub date_priorities: pipeline::dates::SharedDatePriorities,
+    /// Filename metadata extractors, shared with the metadata workers
+    pub filename_extractors: pipeline::extract::SharedExtractors,
+    /// Whether media received through messaging apps shows in the timeline and memories
+    pub received_media: pipeline::messaging::SharedReceivedMedia,
+    /// Page cache and mmap sizes the connections were opened with
+    pub sqlite_tuning: db::tuning::SqliteTuning,
+    /// Saved overrides; changes apply on the next restart
+    pub sqlite_overrides: Arc<parking_lot::RwLock<db::tuning::SqliteOverrides>>,
+    /// What startup recovery repaired after an unclean shutdown
+    pub recovery: Arc<Mutex<utils::recovery::RecoveryReport>>,
+    /// Latest checksum manifest verification, running or finished
+    pub manifest_verify: Arc<Mutex<Option<Arc<utils::manifest::VerifyJob>>>>,
+    /// Latest bulk thumbnail warm-up, running or finished
+    pub thumb_warm: Arc<Mutex<Option<Arc<pipeline::thumb::WarmJob>>>>,
+    /// Latest derived-cache collection, periodic or requested
+    pub derived_gc: Arc<Mutex<Option<utils::derived_gc::GcReport>>>,
+    /// Latest storage usage report, recomputed in the background
+    pub storage: Arc<Mutex<Option<utils::storage::StorageReport>>>,
+    /// Open queues handed to Cast receivers
+    pub cast_sessions: Arc<utils::cast::CastStore>,
+    /// Weekly memories email, loaded from the settings table
+    pub digest: Arc<parking_lot::RwLock<utils::digest::DigestSettings>>,
+    /// CLIP encoders and image vectors behind GET /search/semantic
+    #[cfg(feature = "semantic-search")]
+    pub semantic: Arc<pipeline::embed::SemanticSearch>,
     #[cfg(feature = "facial-recognition")]
     pub face_detection_enabled: Arc<AtomicBool>,
     #[cfg(feature = "facial-recognition")]
@@ -117,6 +173,9 @@ impl AppState {
     #[cfg(feature = "facial-recognition")]
     pub fn new(paths: AppPaths, pool: DbPool, queues: pipeline::Queues, gauges: Arc<pipeline::QueueGauges>, stats: Arc<stats::Stats>, face_processor: Arc<parking_lot::Mutex<pipeline::face::FaceProcessor>>, face_index: Arc<parking_lot::Mutex<pipeline::face::FaceIndex>>) -> Self {
         let (tx, _) = broadcast::channel(8);
+        let reference_images = Arc::new(utils::reference::ReferenceStore::new(paths.data.join("reference")));
+        #[cfg(feature = "semantic-search")]
+        let semantic = Arc::new(pipeline::embed::SemanticSearch::new(paths.data.join("models")));
         Self {
             started_at: std::time::Instant::now(),
             db_path: paths.db_path.clone(),
@@ -130,7 +189,28 @@ impl AppState {
             scan_running: Arc::new(AtomicBool::new(false)),
             path_scan_running: Arc::new(Mutex::new(HashMap::new())),
             path_watcher_paused: Arc::new(Mutex::new(HashMap::new())),
+            path_scan_cancel: Arc::new(Mutex::new(HashMap::new())),
             path_watchers: Arc::new(Mutex::new(HashMap::new())),
+            path_filters: Arc::new(Mutex::new(HashMap::new())),
+            transcodes: Arc::new(pipeline::transcode::TranscodeTracker::default()),
+            highlights: Arc::new(pipeline::transcode::TranscodeTracker::default()),
+            auth: Arc::new(api::auth::AuthConfig::from_env()),
+            reference_images,
+            ingest_filter: Arc::new(parking_lot::RwLock::new(pipeline::ingest::IngestFilter::default())),
+            date_priorities: Arc::new(parking_lot::RwLock::new(pipeline::dates::DatePriorities::default())),
+            filename_extractors: Arc::new(parking_lot::RwLock::new(pipeline::extract::Extractors::default())),
+            received_media: Arc::new(parking_lot::RwLock::new(pipeline::messaging::ReceivedMedia::default())),
+            sqlite_tuning: db::tuning::SqliteTuning::default(),
+            sqlite_overrides: Arc::new(parking_lot::RwLock::new(db::tuning::SqliteOverrides::default())),
+            recovery: Arc::new(Mutex::new(utils::recovery::RecoveryReport::default())),
+            manifest_verify: Arc::new(Mutex::new(None)),
+            thumb_warm: Arc::new(Mutex::new(None)),
+            derived_gc: Arc::new(Mutex::new(None)),
+            storage: Arc::new(Mutex::new(None)),
+            cast_sessions: Arc::new(utils::cast::CastStore::new()),
+            digest: Arc::new(parking_lot::RwLock::new(utils::digest::DigestSettings::default())),
+            #[cfg(feature = "semantic-search")]
+            semantic,
             face_detection_enabled: Arc::new(AtomicBool::new(false)),
             face_processor,
             face_index,
@@ -140,6 +220,9 @@ impl AppState {
     #[cfg(not(feature = "facial-recognition"))]
     pub fn new(paths: AppPaths, pool: DbPool, queues: pipeline::Queues, gauges: Arc<pipeline::QueueGauges>, stats: Arc<stats::Stats>) -> Self {
         let (tx, _) = broadcast::channel(8);
+        let reference_images = Arc::new(utils::reference::ReferenceStore::new(paths.data.join("reference")));
+        #[cfg(feature = "semantic-search")]
+        let semantic = Arc::new(pipeline::embed::SemanticSearch::new(paths.data.join("models")));
         Self {
             started_at: std::time::Instant::now(),
             db_path: paths.db_path.clone(),
@@ -153,7 +236,28 @@ impl AppState {
             scan_running: Arc::new(AtomicBool::new(false)),
             path_scan_running: Arc::new(Mutex::new(HashMap::new())),
             path_watcher_paused: Arc::new(Mutex::new(HashMap::new())),
+            path_scan_cancel: Arc::new(Mutex::new(HashMap::new())),
             path_watchers: Arc::new(Mutex::new(HashMap::new())),
+            path_filters: Arc::new(Mutex::new(HashMap::new())),
+            transcodes: Arc::new(pipeline::transcode::TranscodeTracker::default()),
+            highlights: Arc::new(pipeline::transcode::TranscodeTracker::default()),
+            auth: Arc::new(api::auth::AuthConfig::from_env()),
+            reference_images,
+            ingest_filter: Arc::new(parking_lot::RwLock::new(pipeline::ingest::IngestFilter::default())),
+            date_priorities: Arc::new(parking_lot::RwLock::new(pipeline::dates::DatePriorities::default())),
+            filename_extractors: Arc::new(parking_lot::RwLock::new(pipeline::extract::Extractors::default())),
+            received_media: Arc::new(parking_lot::RwLock::new(pipeline::messaging::ReceivedMedia::default())),
+            sqlite_tuning: db::tuning::SqliteTuning::default(),
+            sqlite_overrides: Arc::new(parking_lot::RwLock::new(db::tuning::SqliteOverrides::default())),
+            recovery: Arc::new(Mutex::new(utils::recovery::RecoveryReport::default())),
+            manifest_verify: Arc::new(Mutex::new(None)),
+            thumb_warm: Arc::new(Mutex::new(None)),
+            derived_gc: Arc::new(Mutex::new(None)),
+            storage: Arc::new(Mutex::new(None)),
+            cast_sessions: Arc::new(utils::cast::CastStore::new()),
+            digest: Arc::new(parking_lot::RwLock::new(utils::digest::DigestSettings::default())),
+            #[cfg(feature = "semantic-search")]
+            semantic,
         }
     }
 }
@@ -15,31 +15,78 @@ async fn main() -> anyhow::Result<()> {
     std::env::set_var("VIPS_WARNING", "0");
     
     logging::init();
+    let host = seen_backend::utils::host::host();
+    if host.constrained {
+        info!(
+            "{} ({}, {} cores): using lighter defaults for workers, previews and GPU probing",
+            host.model.as_deref().unwrap_or("Small ARM host"), host.arch, host.cores
+        );
+    }
     let cfg = Config::from_env();
+    if cfg.profile != seen_backend::utils::config::Profile::Default {
+        info!("Profile {}: smaller queues, read pool, SQLite cache and worker counts", cfg.profile.as_str());
+    }
     let data_dir = cfg.data.clone();
     let db_dir = data_dir.join("db");
     let derived_dir = data_dir.join("derived");
     std::fs::create_dir_all(&db_dir)?;
     std::fs::create_dir_all(&derived_dir)?;
     let db_path = db_dir.join("seen.db");
-    // Create connection pool with 10 connections (good for SQLite WAL mode)
-    let pool = db::create_pool(&db_path, 10)?;
+    // A library restored through POST /import/archive is swapped in before anything opens the database
+    if seen_backend::utils::backup::apply_pending_restore(&db_path)? {
+        info!("Restored library database from backup archive");
+    }
+    // Page cache and mmap sized from RAM and the library, unless overridden through PUT /settings/sqlite
+    let (sqlite_overrides, pool_settings) = {
+        let conn = db::open_or_create(&db_path)?;
+        // Face thresholds, HEVC mode and thread counts: copied from the environment on the first
+        // start, then kept in the database and applied over it
+        let env_import = seen_backend::utils::env_settings::import_and_apply(&conn)?;
+        if !env_import.imported.is_empty() {
+            info!("Saved {} in the database; change them through PUT /settings/env from now on", env_import.imported.join(", "));
+        }
+        for shadowed in &env_import.shadowed {
+            tracing::warn!("{}={} is ignored; the saved value {} applies (PUT /settings/env to change it)", shadowed.var, shadowed.env, shadowed.saved);
+        }
+        let default_pool = db::pool::PoolSettings { size: cfg.profile.pool_size() };
+        (seen_backend::api::settings::load_sqlite_overrides(&conn)?, seen_backend::api::settings::load_pool_settings(&conn, &default_pool)?)
+    };
+    // Again, with the saved thread counts in place
+    let cfg = Config::from_env();
+    let sqlite_tuning = cfg.profile.limit_sqlite(db::tuning::SqliteTuning::detect(&db_path)).with_overrides(&sqlite_overrides);
+    info!("SQLite cache {} KiB per connection, mmap {} MiB", sqlite_tuning.cache_kib, sqlite_tuning.mmap_bytes / (1024 * 1024));
+    // 10 connections (3 with the low-memory profile) unless resized through PUT /settings/db-pool
+    let pool = db::create_pool(&db_path, pool_settings.size.clamp(1, db::pool::MAX_POOL_SIZE), sqlite_tuning)?;
+    // Repair what an unclean shutdown may have left half-written before anything reads it
+    let booted_at = std::time::SystemTime::now();
+    let mut recovery = seen_backend::utils::recovery::RecoveryReport::default();
+    seen_backend::utils::recovery::repair_database(&*pool.get()?, &mut recovery)?;
+    // Files that were in the pipeline when it stopped; sent back once the workers are up
+    let pending_jobs = db::writer::take_pending_jobs(&*pool.get()?)?;
+    recovery.pending_jobs_requeued = pending_jobs.len();
     // Initialize libvips (warnings are suppressed via environment variables set above)
     #[cfg(not(target_env = "msvc"))]
     let _app = libvips::VipsApp::new("seen", false)?;
+    #[cfg(not(target_env = "msvc"))]
+    _app.concurrency_set(cfg.vips_concurrency as i32);
     #[cfg(target_env = "msvc")]
     let _app = (); // libvips doesn't compile on Windows MSVC
     
+    // Verify ffmpeg/ffprobe (FLASH_FFMPEG / FLASH_FFPROBE, bundled, or PATH) before probing GPU support
+    let _binaries = seen_backend::utils::ffmpeg::verify_binaries();
+
     // Initialize GPU configuration
     let _gpu_config = seen_backend::utils::ffmpeg::init_gpu_config();
 
-    let (discover_tx, discover_rx) = mpsc::channel::<discover::DiscoverItem>(100_000);
-    let (hash_tx, hash_rx) = mpsc::channel::<hash::HashJob>(4_096);
-    let (meta_tx, meta_rx) = mpsc::channel::<metadata::MetaJob>(4_096);
-    let (db_tx, db_rx) = mpsc::channel::<db::writer::DbWriteItem>(65_536);
-    let (thumb_tx, thumb_rx) = mpsc::channel::<thumb::ThumbJob>(16_384);
+    let capacity = cfg.profile.queue_capacity();
+    let (discover_tx, discover_rx) = mpsc::channel::<discover::DiscoverItem>(capacity.discover);
+    let (hash_tx, hash_rx) = mpsc::channel::<hash::HashJob>(capacity.hash);
+    let (meta_tx, meta_rx) = mpsc::channel::<metadata::MetaJob>(capacity.meta);
+    let (db_tx, db_rx) = mpsc::channel::<db::writer::DbWriteItem>(capacity.db_write);
+    let (thumb_tx, thumb_rx) = mpsc::channel::<thumb::ThumbJob>(capacity.thumb);
+    let (transcode_tx, transcode_rx) = mpsc::channel::<pipeline::transcode::TranscodeJob>(capacity.transcode);
     #[cfg(feature = "facial-recognition")]
-    let (face_tx, face_rx) = mpsc::channel::<pipeline::face::FaceJob>(4_096);
+    let (face_tx, face_rx) = mpsc::channel::<pipeline::face::FaceJob>(capacity.face);
 
     let gauges = Arc::new(pipeline::QueueGauges::default());
     
@@ -60,9 +107,26 @@ async fn main() -> anyhow::Result<()> {
         });
     }
     
-    discover::start_forwarder(discover_rx, hash_tx.clone(), Some(meta_tx.clone()), Some(db_path.clone()), gauges.clone(), Some(stats.clone()));
-    hash::start_workers(cfg.hash_threads, hash_rx, meta_tx.clone(), gauges.clone());
-    metadata::start_workers(cfg.meta_threads, meta_rx, db_tx.clone(), gauges.clone());
+    // FLASH_INGEST_* and FLASH_LOW_IMPACT* defaults, unless settings were saved through PUT /settings/*
+    let (meta_settings, received_media) = {
+        let conn = pool.get()?;
+        gauges.throttle.set(seen_backend::api::settings::load_low_impact(&conn, &cfg.low_impact)?);
+        let meta_settings = metadata::MetaSettings {
+            ingest: Arc::new(parking_lot::RwLock::new(seen_backend::api::settings::load_ingest_filter(&conn, &cfg.ingest)?)),
+            date_priorities: Arc::new(parking_lot::RwLock::new(pipeline::dates::load_priorities(&conn)?)),
+            extractors: Arc::new(parking_lot::RwLock::new(seen_backend::api::settings::load_extractors(&conn)?)),
+        };
+        (meta_settings, seen_backend::api::settings::load_received_media(&conn)?)
+    };
+
+    pipeline::plugins::register_from_env();
+    discover::start_forwarder(discover_rx, hash_tx.clone(), Some(meta_tx.clone()), Some(db_path.clone()), gauges.clone(), Some(stats.clone()), meta_settings.ingest.clone());
+    hash::start_workers(cfg.hash_threads, hash_rx, meta_tx.clone(), gauges.clone(), stats.clone(), cfg.sampled_hash_min_mb * 1024 * 1024);
+    metadata::start_workers(cfg.meta_threads, meta_rx, db_tx.clone(), gauges.clone(), meta_settings.clone(), stats.clone());
+    if !pending_jobs.is_empty() {
+        info!("re-enqueuing {} files left over from an unclean shutdown", pending_jobs.len());
+        tokio::spawn(discover::requeue_pending(pending_jobs, discover_tx.clone(), gauges.clone()));
+    }
     // Initialize face processor (only if feature enabled)
     #[cfg(feature = "facial-recognition")]
     let models_dir = data_dir.join("models");
@@ -74,6 +138,7 @@ async fn main() -> anyhow::Result<()> {
     #[cfg(feature = "facial-recognition")]
     {
         let processor = face_processor_arc.clone();
+        let (resume_pool, resume_tx, resume_gauges) = (pool.clone(), face_tx.clone(), gauges.clone());
         tokio::spawn(async move {
             // Get models_dir before holding lock, then drop lock before await
             let models_dir = {
@@ -86,25 +151,52 @@ async fn main() -> anyhow::Result<()> {
                 tracing::error!("Failed to initialize face processor: {}", e);
             } else {
                 // Update the shared processor with loaded models
-                let mut proc = processor.lock();
-                *proc = temp_processor;
+                *processor.lock() = temp_processor;
+                // A run the last shutdown interrupted carries on where it stopped
+                pipeline::face::resume_jobs(resume_pool, resume_tx, resume_gauges).await;
             }
         });
     }
     
     // Initialize face index
     #[cfg(feature = "facial-recognition")]
-    let face_index = Arc::new(parking_lot::Mutex::new(pipeline::face::FaceIndex::new()));
+    let face_index = Arc::new(parking_lot::Mutex::new(pipeline::face::load_face_index(data_dir.join("faces.hnsw")).await));
+    #[cfg(feature = "facial-recognition")]
+    pipeline::face::start_index_warmup(pool.clone(), face_index.clone());
+    #[cfg(feature = "facial-recognition")]
+    pipeline::face::start_index_saver(face_index.clone());
     
     let paths = seen_backend::AppPaths { root: cfg.root.clone(), root_host: cfg.root_host.clone(), data: cfg.data.clone(), db_path: db_path.clone(), derived: derived_dir.clone() };
     #[cfg(feature = "facial-recognition")]
-    let queues = pipeline::Queues { discover_tx: discover_tx.clone(), hash_tx: hash_tx.clone(), meta_tx: meta_tx.clone(), db_tx: db_tx.clone(), thumb_tx: thumb_tx.clone(), face_tx: face_tx.clone() };
+    let queues = pipeline::Queues { discover_tx: discover_tx.clone(), hash_tx: hash_tx.clone(), meta_tx: meta_tx.clone(), db_tx: db_tx.clone(), thumb_tx: thumb_tx.clone(), transcode_tx: transcode_tx.clone(), face_tx: face_tx.clone() };
     #[cfg(not(feature = "facial-recognition"))]
-    let queues = pipeline::Queues { discover_tx: discover_tx.clone(), hash_tx: hash_tx.clone(), meta_tx: meta_tx.clone(), db_tx: db_tx.clone(), thumb_tx: thumb_tx.clone() };
+    let queues = pipeline::Queues { discover_tx: discover_tx.clone(), hash_tx: hash_tx.clone(), meta_tx: meta_tx.clone(), db_tx: db_tx.clone(), thumb_tx: thumb_tx.clone(), transcode_tx: transcode_tx.clone() };
     #[cfg(feature = "facial-recognition")]
-    let state = Arc::new(seen_backend::AppState::new(paths, pool, queues, gauges.clone(), stats.clone(), face_processor_arc.clone(), face_index.clone()));
+    let mut app_state = seen_backend::AppState::new(paths, pool, queues, gauges.clone(), stats.clone(), face_processor_arc.clone(), face_index.clone());
     #[cfg(not(feature = "facial-recognition"))]
-    let state = Arc::new(seen_backend::AppState::new(paths, pool, queues, gauges.clone(), stats.clone()));
+    let mut app_state = seen_backend::AppState::new(paths, pool, queues, gauges.clone(), stats.clone());
+    app_state.ingest_filter = meta_settings.ingest;
+    app_state.date_priorities = meta_settings.date_priorities;
+    app_state.filename_extractors = meta_settings.extractors;
+    *app_state.received_media.write() = received_media;
+    app_state.sqlite_tuning = sqlite_tuning;
+    *app_state.sqlite_overrides.write() = sqlite_overrides;
+    *app_state.recovery.lock() = recovery;
+    let state = Arc::new(app_state);
+
+    // Partial and empty derived files from before boot; can take a while on large libraries
+    {
+        let state = state.clone();
+        let derived = derived_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            let swept = seen_backend::utils::recovery::sweep_derived(&derived, booted_at);
+            let mut report = state.recovery.lock();
+            report.temp_files_removed = swept.temp_files_removed;
+            report.empty_files_removed = swept.empty_files_removed;
+            report.bytes_freed = swept.bytes_freed;
+            report.derived_swept_at = swept.derived_swept_at;
+        });
+    }
     
     // Note: File watchers are now started dynamically when paths are added or scans are started
     // The old static watcher has been removed in favor of per-path watchers
@@ -122,6 +214,9 @@ async fn main() -> anyhow::Result<()> {
         let db_path_for_writer = db_path.clone();
         tokio::task::spawn_blocking(move || {
             if let Ok(conn2) = rusqlite::Connection::open(dbp.clone()) {
+                if let Err(e) = sqlite_tuning.apply(&conn2) {
+                    tracing::warn!("Could not tune writer connection: {}", e);
+                }
                 let handle = tokio::runtime::Handle::current();
                 #[cfg(feature = "facial-recognition")]
                 {
@@ -170,7 +265,42 @@ async fn main() -> anyhow::Result<()> {
             }
         });
     }
-    thumb::start_workers(cfg.thumb_threads, thumb_rx, derived_dir.clone(), cfg.thumb_size, cfg.preview_size, gauges.clone());
+    let thumb_options = thumb::ThumbOptions { thumb_size: cfg.thumb_size, preview_size: cfg.preview_size, video_anim_preview: cfg.video_anim_preview };
+    thumb::start_workers(cfg.thumb_threads, thumb_rx, derived_dir.clone(), thumb_options, gauges.clone(), stats.clone());
+    pipeline::transcode::start_worker(transcode_rx, state.transcodes.clone(), gauges.clone(), stats.clone());
+    seen_backend::utils::delete_hold::start_purger(db_path.clone(), derived_dir.clone());
+    seen_backend::utils::reference::start_purger(state.reference_images.clone());
+    seen_backend::utils::cast::start_purger(state.cast_sessions.clone());
+    seen_backend::utils::scheduler::start(state.clone());
+    *state.digest.write() = seen_backend::api::settings::load_digest(&*state.pool.get()?)?;
+    seen_backend::utils::digest::start(state.clone());
+    seen_backend::utils::derived_gc::start(state.clone(), seen_backend::utils::derived_gc::default_interval_hours());
+    seen_backend::utils::storage::start(state.clone(), seen_backend::utils::storage::refresh_minutes());
+
+    // Read text in screenshots and scanned documents for search when tesseract is available
+    if pipeline::ocr::enabled() {
+        pipeline::ocr::start_worker(state.clone(), std::time::Duration::from_secs(60));
+    } else {
+        info!("OCR disabled (FLASH_OCR=0 or tesseract not found)");
+    }
+
+    // Load CLIP models and embed images missing a vector (only if feature enabled)
+    #[cfg(feature = "semantic-search")]
+    {
+        #[cfg(feature = "auto-tagging")]
+        state.semantic.auto_tags.set_settings(seen_backend::api::settings::load_auto_tags(&*state.pool.get()?)?);
+        let semantic = state.semantic.clone();
+        let pool = state.pool.clone();
+        #[cfg(feature = "auto-tagging")]
+        let state_c = state.clone();
+        tokio::spawn(async move {
+            semantic.initialize(&pool).await;
+            // Classify assets embedded before auto-tagging was on, or under older labels
+            #[cfg(feature = "auto-tagging")]
+            pipeline::autotag::start_retag(state_c);
+        });
+        pipeline::embed::start_embed_worker(state.clone(), std::time::Duration::from_secs(60));
+    }
     
     // Start face workers (only if feature enabled)
     #[cfg(feature = "facial-recognition")]
@@ -179,12 +309,13 @@ async fn main() -> anyhow::Result<()> {
         let dbp = db_path.clone();
         let g = gauges.clone();
         let idx = face_index.clone();
+        let face_stats = stats.clone();
         let n_workers = std::env::var("FLASH_FACE_THREADS")
             .ok()
             .and_then(|v| v.parse().ok())
             .unwrap_or(1);
         tokio::spawn(async move {
-            pipeline::face::start_face_workers(n_workers, face_rx, processor, dbp, g, idx).await;
+            pipeline::face::start_face_workers(n_workers, face_rx, processor, dbp, g, idx, face_stats).await;
         });
     }
     let app = seen_backend::api::routes::router(state.clone());
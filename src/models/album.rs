@@ -0,0 +1,58 @@
+use serde::{Serialize, Deserialize};
+
+/// Filter rules stored (as JSON) on a smart album. All set fields must match.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SmartAlbumRules {
+    /// Inclusive lower bound on taken_at (unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<i64>,
+    /// Inclusive upper bound on taken_at (unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_make: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub person_id: Option<i64>,
+    /// Either a full MIME type ("image/jpeg") or a top-level type ("video")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
+}
+
+impl SmartAlbumRules {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Aggregates shown in an album header
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct AlbumStats {
+    pub asset_count: i64,
+    pub total_bytes: i64,
+    /// Earliest and latest taken_at among the album's assets (unix seconds)
+    pub date_from: Option<i64>,
+    pub date_to: Option<i64>,
+    pub top_people: Vec<AlbumPersonCount>,
+    pub places: Vec<AlbumPlaceCount>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct AlbumPersonCount {
+    pub person_id: i64,
+    pub name: Option<String>,
+    pub asset_count: i64,
+    /// Most confident detection of the person within the album, for a face thumbnail
+    pub face_id: i64,
+}
+
+/// Assets carry no GPS data, so places are the folders the album's assets live in
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct AlbumPlaceCount {
+    pub name: String,
+    pub dirname: String,
+    pub asset_count: i64,
+}
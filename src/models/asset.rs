@@ -1,5 +1,9 @@
 use serde::{Serialize, Deserialize};
 
+/// `Asset::flags` bit: `sha256` and `xxh64` cover only the start and end of the file and its size,
+/// see `FLASH_SAMPLED_HASH_MIN_MB`
+pub const FLAG_SAMPLED_HASH: i64 = 1;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Asset {
     pub id: i64,
@@ -24,7 +28,23 @@ pub struct Asset {
     pub exposure: Option<f64>,
     pub video_codec: Option<String>,
     pub mime: String,
+    /// `FLAG_*` bits
     pub flags: i64,
+    pub rating: i64,
+    pub favorite: bool,
+    pub stack_id: Option<i64>,
+    /// The original lives on cold storage and may be offline
+    #[serde(default)]
+    pub archived: bool,
+    /// Protected from rotation, metadata edits and deletion until unlocked
+    #[serde(default)]
+    pub locked: bool,
+    /// The video recorded with this still when it is a live photo, see `GET /assets/:id/live`
+    #[serde(default)]
+    pub live_photo_video_id: Option<i64>,
+    /// When the asset was first indexed, Unix seconds
+    #[serde(default)]
+    pub ingested_at: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -46,4 +66,149 @@ pub struct SearchResult {
     pub items: Vec<Asset>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub match_counts: Option<SearchMatchCounts>,
+    /// Relevance and a highlighted excerpt for returned assets matched by the search index, in `items` order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hits: Option<Vec<SearchHit>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchHit {
+    pub asset_id: i64,
+    /// Higher is more relevant
+    pub score: f64,
+    /// Matched text with HTML escaped and matches wrapped in `<mark>`
+    pub snippet: String,
+}
+
+/// Number of assets taken on one day, for the activity heatmap
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CalendarDay {
+    /// YYYY-MM-DD
+    pub date: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CalendarStats {
+    pub year: i32,
+    pub total: i64,
+    /// Highest single-day count, to scale the heatmap colors
+    pub max_count: i64,
+    /// Days with at least one asset, in date order
+    pub days: Vec<CalendarDay>,
+    /// Every year that has dated assets, newest first
+    pub years: Vec<i32>,
+}
+
+/// One attribute of the compared assets, values in the same order as `AssetComparison::assets`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub field: String,
+    pub values: Vec<serde_json::Value>,
+    /// Every asset has the same value
+    pub same: bool,
+    /// Ids holding the preferable value, for fields where more is better (resolution, size)
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub best: Vec<i64>,
+}
+
+/// Side-by-side view of candidate duplicates
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssetComparison {
+    pub assets: Vec<Asset>,
+    pub fields: Vec<FieldDiff>,
+    /// All assets have the same content hash
+    pub identical: bool,
+    /// Copy to keep by default: most pixels, then largest file, then highest rating
+    pub suggested_keep: Option<i64>,
+}
+
+impl AssetComparison {
+    pub fn new(assets: Vec<Asset>) -> Self {
+        let camera = |a: &Asset| {
+            let parts: Vec<&str> = [&a.camera_make, &a.camera_model].into_iter().flatten().map(|s| s.as_str()).collect();
+            (!parts.is_empty()).then(|| parts.join(" "))
+        };
+
+        let fields = vec![
+            diff(&assets, "resolution", |a| match (a.width, a.height) {
+                (Some(w), Some(h)) => serde_json::json!(format!("{}x{}", w, h)),
+                _ => serde_json::Value::Null,
+            }, Some(pixels)),
+            diff(&assets, "size_bytes", |a| serde_json::json!(a.size_bytes), Some(size_bytes)),
+            diff(&assets, "taken_at", |a| serde_json::json!(a.taken_at), None),
+            diff(&assets, "mtime_ns", |a| serde_json::json!(a.mtime_ns), None),
+            diff(&assets, "camera", |a| serde_json::json!(camera(a)), None),
+            diff(&assets, "lens_model", |a| serde_json::json!(a.lens_model), None),
+            diff(&assets, "mime", |a| serde_json::json!(a.mime), None),
+            diff(&assets, "duration_ms", |a| serde_json::json!(a.duration_ms), None),
+            diff(&assets, "sha256", |a| serde_json::json!(a.sha256), None),
+            diff(&assets, "xxh64", |a| serde_json::json!(a.xxh64), None),
+            diff(&assets, "path", |a| serde_json::json!(a.path), None),
+        ];
+        let identical = assets.first().and_then(|a| a.sha256.as_ref())
+            .is_some_and(|first| assets.iter().all(|a| a.sha256.as_ref() == Some(first)));
+        let suggested_keep = assets.iter()
+            .max_by(|a, b| {
+                (pixels(a), a.size_bytes, a.rating).cmp(&(pixels(b), b.size_bytes, b.rating))
+                    .then(b.id.cmp(&a.id))
+            })
+            .map(|a| a.id);
+
+        Self { assets, fields, identical, suggested_keep }
+    }
+}
+
+fn pixels(a: &Asset) -> i64 {
+    a.width.unwrap_or(0) * a.height.unwrap_or(0)
+}
+
+fn size_bytes(a: &Asset) -> i64 {
+    a.size_bytes
+}
+
+fn diff(assets: &[Asset], field: &str, value: impl Fn(&Asset) -> serde_json::Value, rank: Option<fn(&Asset) -> i64>) -> FieldDiff {
+    let values: Vec<serde_json::Value> = assets.iter().map(value).collect();
+    let same = values.windows(2).all(|w| w[0] == w[1]);
+    let best = match rank {
+        Some(rank) if !same => {
+            let top = assets.iter().map(rank).max().unwrap_or(0);
+            assets.iter().filter(|a| top > 0 && rank(a) == top).map(|a| a.id).collect()
+        }
+        _ => Vec::new(),
+    };
+    FieldDiff { field: field.to_string(), values, same, best }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(id: i64, width: i64, size_bytes: i64, sha256: &str) -> Asset {
+        Asset {
+            id, path: format!("/p/{}.jpg", id), dirname: "/p".into(), filename: format!("{}.jpg", id), ext: "jpg".into(),
+            size_bytes, mtime_ns: 0, ctime_ns: 0, sha256: Some(sha256.into()), xxh64: None, taken_at: Some(100),
+            width: Some(width), height: Some(width / 2), duration_ms: None, camera_make: Some("Canon".into()),
+            camera_model: Some("EOS R5".into()), lens_model: None, iso: None, fnumber: None, exposure: None,
+            video_codec: None, mime: "image/jpeg".into(), flags: 0, rating: 0, favorite: false, stack_id: None, archived: false, locked: false, live_photo_video_id: None, ingested_at: None,
+        }
+    }
+
+    #[test]
+    fn test_asset_comparison() {
+        let cmp = AssetComparison::new(vec![asset(1, 2000, 500, "aa"), asset(2, 4000, 400, "bb"), asset(3, 4000, 900, "cc")]);
+        let field = |name: &str| cmp.fields.iter().find(|f| f.field == name).unwrap();
+        assert_eq!(field("resolution").values[0], serde_json::json!("2000x1000"));
+        assert_eq!(field("resolution").best, vec![2, 3]);
+        assert_eq!(field("size_bytes").best, vec![3]);
+        assert!(field("camera").same && field("camera").values[0] == serde_json::json!("Canon EOS R5"));
+        assert!(field("taken_at").best.is_empty());
+        assert!(!field("sha256").same && !cmp.identical);
+        assert_eq!(cmp.suggested_keep, Some(3));
+
+        let same = AssetComparison::new(vec![asset(4, 100, 10, "aa"), asset(5, 100, 10, "aa")]);
+        assert!(same.identical);
+        assert!(same.fields.iter().find(|f| f.field == "size_bytes").unwrap().best.is_empty());
+        assert_eq!(same.suggested_keep, Some(4));
+    }
 }
@@ -1 +1,5 @@
 pub mod asset;
+pub mod album;
+pub mod person;
+pub mod rule;
+pub mod share;
@@ -0,0 +1,25 @@
+use serde::Serialize;
+
+/// One year or month of a person's photos
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PersonTimelineBucket {
+    pub year: i32,
+    /// 1-12, or None when bucketing by year
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub month: Option<u32>,
+    pub count: i64,
+    /// Assets with the most confident detections of the person, for thumbnails
+    pub sample_asset_ids: Vec<i64>,
+}
+
+/// A row of the paged person listing
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PersonSummary {
+    pub id: i64,
+    pub name: Option<String>,
+    pub created_at: i64,
+    pub face_count: i64,
+    pub asset_count: i64,
+    /// Most confident face of the person, for the avatar
+    pub cover_face_id: Option<i64>,
+}
@@ -0,0 +1,100 @@
+use serde::{Serialize, Deserialize};
+use crate::db::writer::DbWriteItem;
+
+/// What an incoming asset must look like for a rule to apply. All set fields must match.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RuleConditions {
+    /// Case-insensitive part of the full path, with `/` as the separator on every platform
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_contains: Option<String>,
+    /// Extensions without the dot; the asset's must be one of them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ext: Option<Vec<String>>,
+    /// Either a full MIME type ("image/png") or a top-level type ("video")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
+    /// Inclusive bounds on the video length; assets without one never match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_duration_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration_ms: Option<i64>,
+    /// Inclusive bounds on the file size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size_bytes: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size_bytes: Option<i64>,
+    /// Case-insensitive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_make: Option<String>,
+}
+
+impl RuleConditions {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn matches(&self, item: &DbWriteItem) -> bool {
+        let within = |value: Option<i64>, min: Option<i64>, max: Option<i64>| {
+            (min.is_none() && max.is_none())
+                || value.is_some_and(|v| min.is_none_or(|m| v >= m) && max.is_none_or(|m| v <= m))
+        };
+        self.path_contains.as_deref().is_none_or(|part| {
+            item.path.replace('\\', "/").to_lowercase().contains(&part.replace('\\', "/").to_lowercase())
+        }) && self.ext.as_ref().is_none_or(|exts| {
+            exts.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(item.ext.trim_start_matches('.')))
+        }) && self.mime.as_deref().is_none_or(|mime| {
+            if mime.contains('/') {
+                item.mime.eq_ignore_ascii_case(mime)
+            } else {
+                item.mime.split('/').next().is_some_and(|top| top.eq_ignore_ascii_case(mime))
+            }
+        }) && within(item.duration_ms, self.min_duration_ms, self.max_duration_ms)
+            && within(Some(item.size_bytes), self.min_size_bytes, self.max_size_bytes)
+            && self.camera_make.as_deref().is_none_or(|make| {
+                item.camera_make.as_deref().is_some_and(|m| m.trim().eq_ignore_ascii_case(make.trim()))
+            })
+    }
+}
+
+/// What happens to a matching asset
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RuleActions {
+    /// Added like manual tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Leave the asset out of automatic face detection
+    #[serde(default)]
+    pub skip_faces: bool,
+}
+
+impl RuleActions {
+    pub fn is_empty(&self) -> bool {
+        self.tags.iter().all(|t| t.trim().is_empty()) && !self.skip_faces
+    }
+}
+
+/// A user-defined step the writer applies to every asset it stores, new or changed
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ProcessingRule {
+    pub id: i64,
+    pub name: String,
+    pub enabled: bool,
+    pub conditions: RuleConditions,
+    pub actions: RuleActions,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// The combined actions of the enabled rules matching `item`
+pub fn actions_for(rules: &[ProcessingRule], item: &DbWriteItem) -> RuleActions {
+    let mut actions = RuleActions::default();
+    for rule in rules.iter().filter(|r| r.enabled && r.conditions.matches(item)) {
+        for tag in &rule.actions.tags {
+            if !actions.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                actions.tags.push(tag.clone());
+            }
+        }
+        actions.skip_faces |= rule.actions.skip_faces;
+    }
+    actions
+}
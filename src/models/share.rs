@@ -0,0 +1,40 @@
+use serde::Serialize;
+
+/// A public link to an album or a single asset
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ShareLink {
+    pub id: i64,
+    pub album_id: Option<i64>,
+    pub asset_id: Option<i64>,
+    pub has_password: bool,
+    pub expires_at: Option<i64>,
+    /// User who created the link; None for the admin password or when auth is disabled
+    pub created_by: Option<i64>,
+    pub created_at: i64,
+}
+
+/// A pre-authorized token for a photo frame or TV browser, limited to a set of albums
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Kiosk {
+    pub id: i64,
+    pub name: String,
+    /// In display order
+    pub album_ids: Vec<i64>,
+    /// User who created the token; None for the admin password or when auth is disabled
+    pub created_by: Option<i64>,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+}
+
+/// What visitors of a share link see of an asset: no paths or library details
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SharedAsset {
+    pub id: i64,
+    pub filename: String,
+    pub mime: String,
+    pub size_bytes: i64,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub taken_at: Option<i64>,
+}
@@ -0,0 +1,143 @@
+//! Five-point face alignment for ArcFace.
+//!
+//! SCRFD's keypoints (eyes, nose tip, mouth corners) are mapped onto the template ArcFace was
+//! trained with by the least-squares similarity transform (rotation, uniform scale, translation;
+//! Umeyama 1991), and the 112×112 input is sampled through it. Compared with an axis-aligned crop
+//! this removes head roll and keeps the eyes at the same place in every crop, which is what the
+//! embedding expects.
+
+use image::{DynamicImage, Rgb, RgbImage};
+
+/// ArcFace input size
+pub const ALIGNED_SIZE: u32 = 112;
+
+/// Keypoint positions in the 112×112 crop: left eye, right eye, nose tip, left and right mouth corner
+pub const ARCFACE_TEMPLATE: [[f32; 2]; 5] = [
+    [38.2946, 51.6963],
+    [73.5318, 51.5014],
+    [56.0252, 71.7366],
+    [41.5493, 92.3655],
+    [70.7299, 92.2041],
+];
+
+/// `dst = [a -b; b a] · src + [tx ty]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Similarity {
+    pub a: f32,
+    pub b: f32,
+    pub tx: f32,
+    pub ty: f32,
+}
+
+impl Similarity {
+    /// Least-squares fit mapping `src` onto `dst`; None when the source points coincide
+    pub fn estimate(src: &[[f32; 2]; 5], dst: &[[f32; 2]; 5]) -> Option<Self> {
+        let mean = |p: &[[f32; 2]; 5]| {
+            let (x, y) = p.iter().fold((0.0, 0.0), |(x, y), q| (x + q[0], y + q[1]));
+            (x / 5.0, y / 5.0)
+        };
+        let (sx, sy) = mean(src);
+        let (dx, dy) = mean(dst);
+        let (mut dot, mut cross, mut norm) = (0.0f32, 0.0f32, 0.0f32);
+        for (s, d) in src.iter().zip(dst) {
+            let (x, y) = (s[0] - sx, s[1] - sy);
+            let (u, v) = (d[0] - dx, d[1] - dy);
+            dot += x * u + y * v;
+            cross += x * v - y * u;
+            norm += x * x + y * y;
+        }
+        if norm < 1e-6 {
+            return None;
+        }
+        let (a, b) = (dot / norm, cross / norm);
+        Some(Self { a, b, tx: dx - (a * sx - b * sy), ty: dy - (b * sx + a * sy) })
+    }
+
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.a * x - self.b * y + self.tx, self.b * x + self.a * y + self.ty)
+    }
+
+    /// None for a degenerate (zero-scale) transform
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.a * self.a + self.b * self.b;
+        if det < 1e-12 {
+            return None;
+        }
+        let (a, b) = (self.a / det, -self.b / det);
+        Some(Self { a, b, tx: -(a * self.tx - b * self.ty), ty: -(b * self.tx + a * self.ty) })
+    }
+}
+
+/// The 112×112 ArcFace crop for a face with the given keypoints (image pixels), or None when they
+/// don't define a transform
+pub fn align_face(image: &DynamicImage, landmarks: &[[f32; 2]; 5]) -> Option<DynamicImage> {
+    let to_image = Similarity::estimate(landmarks, &ARCFACE_TEMPLATE)?.inverse()?;
+    let rgb = image.to_rgb8();
+    let mut out = RgbImage::new(ALIGNED_SIZE, ALIGNED_SIZE);
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        // Pixel centres, so a pure translation samples the source without blurring it
+        let (sx, sy) = to_image.apply(x as f32 + 0.5, y as f32 + 0.5);
+        *pixel = bilinear(&rgb, sx - 0.5, sy - 0.5);
+    }
+    Some(DynamicImage::ImageRgb8(out))
+}
+
+/// Black outside the image, like the border insightface's warpAffine leaves
+fn bilinear(img: &RgbImage, x: f32, y: f32) -> Rgb<u8> {
+    let (w, h) = (img.width() as i64, img.height() as i64);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let at = |px: i64, py: i64| -> [f32; 3] {
+        if px < 0 || py < 0 || px >= w || py >= h {
+            return [0.0; 3];
+        }
+        let p = img.get_pixel(px as u32, py as u32);
+        [p[0] as f32, p[1] as f32, p[2] as f32]
+    };
+    let (p00, p10, p01, p11) = (at(x0, y0), at(x0 + 1, y0), at(x0, y0 + 1), at(x0 + 1, y0 + 1));
+    let mut out = [0u8; 3];
+    for c in 0..3 {
+        let top = p00[c] + (p10[c] - p00[c]) * fx;
+        let bottom = p01[c] + (p11[c] - p01[c]) * fx;
+        out[c] = (top + (bottom - top) * fy).round().clamp(0.0, 255.0) as u8;
+    }
+    Rgb(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similarity_recovers_transform() {
+        // Template rotated by 30°, scaled by 2 and shifted, as keypoints in a photo would be
+        let (cos, sin) = (30f32.to_radians().cos(), 30f32.to_radians().sin());
+        let known = Similarity { a: 2.0 * cos, b: 2.0 * sin, tx: 140.0, ty: -25.0 };
+        let landmarks = ARCFACE_TEMPLATE.map(|[x, y]| {
+            let (u, v) = known.apply(x, y);
+            [u, v]
+        });
+        let fit = Similarity::estimate(&landmarks, &ARCFACE_TEMPLATE).unwrap();
+        for (l, t) in landmarks.iter().zip(&ARCFACE_TEMPLATE) {
+            let (x, y) = fit.apply(l[0], l[1]);
+            assert!((x - t[0]).abs() < 1e-3 && (y - t[1]).abs() < 1e-3);
+        }
+        let back = fit.inverse().unwrap();
+        let (x, y) = back.apply(ARCFACE_TEMPLATE[2][0], ARCFACE_TEMPLATE[2][1]);
+        assert!((x - landmarks[2][0]).abs() < 1e-2 && (y - landmarks[2][1]).abs() < 1e-2);
+
+        assert!(Similarity::estimate(&[[5.0, 5.0]; 5], &ARCFACE_TEMPLATE).is_none());
+    }
+
+    #[test]
+    fn test_align_face_translation() {
+        // Keypoints already at the template, 10px right and 20px down: a plain crop
+        let img = RgbImage::from_fn(200, 200, |x, y| Rgb([x as u8, y as u8, 7]));
+        let landmarks = ARCFACE_TEMPLATE.map(|[x, y]| [x + 10.0, y + 20.0]);
+        let aligned = align_face(&DynamicImage::ImageRgb8(img), &landmarks).unwrap().to_rgb8();
+        assert_eq!(aligned.dimensions(), (ALIGNED_SIZE, ALIGNED_SIZE));
+        assert_eq!(aligned.get_pixel(0, 0), &Rgb([10, 20, 7]));
+        assert_eq!(aligned.get_pixel(100, 50), &Rgb([110, 70, 7]));
+    }
+}
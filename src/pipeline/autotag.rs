@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "auto-tagging")]
+use anyhow::Result;
+#[cfg(feature = "auto-tagging")]
+use parking_lot::{Mutex, RwLock};
+#[cfg(feature = "auto-tagging")]
+use std::sync::Arc;
+#[cfg(feature = "auto-tagging")]
+use crate::pipeline::embed::ClipProcessor;
+
+/// CLIP's logit scale: cosine similarities are multiplied by this before the softmax
+const LOGIT_SCALE: f32 = 100.0;
+/// Competes with the labels so unremarkable photos don't get forced into one
+pub const BACKGROUND_PROMPT: &str = "a photo";
+
+/// A label the classifier may attach as a tag
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoTagLabel {
+    /// Tag name written to the asset
+    pub name: String,
+    /// Text the image is compared against; defaults to "a photo of a <name>"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// Minimum confidence (0–1) for the tag to be attached
+    pub threshold: f32,
+}
+
+impl AutoTagLabel {
+    fn new(name: &str, prompt: &str, threshold: f32) -> Self {
+        Self { name: name.to_string(), prompt: Some(prompt.to_string()), threshold }
+    }
+
+    pub fn prompt(&self) -> String {
+        self.prompt.clone().unwrap_or_else(|| format!("a photo of a {}", self.name))
+    }
+}
+
+/// Which labels are predicted, saved under the `auto_tags` settings key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoTagSettings {
+    pub enabled: bool,
+    pub labels: Vec<AutoTagLabel>,
+}
+
+impl Default for AutoTagSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            labels: vec![
+                AutoTagLabel::new("sunset", "a photo of a sunset", 0.5),
+                AutoTagLabel::new("document", "a photo of a printed document or receipt", 0.5),
+                AutoTagLabel::new("screenshot", "a screenshot of a phone or computer screen", 0.5),
+                AutoTagLabel::new("food", "a photo of food", 0.5),
+                AutoTagLabel::new("beach", "a photo of a beach", 0.5),
+                AutoTagLabel::new("snow", "a photo of snow", 0.5),
+                AutoTagLabel::new("mountains", "a photo of mountains", 0.5),
+                AutoTagLabel::new("dog", "a photo of a dog", 0.5),
+                AutoTagLabel::new("cat", "a photo of a cat", 0.5),
+                AutoTagLabel::new("night", "a photo taken at night", 0.5),
+            ],
+        }
+    }
+}
+
+impl AutoTagSettings {
+    /// Why the settings can't be used, if they can't
+    pub fn validate(&self) -> Result<(), String> {
+        let mut names = std::collections::HashSet::new();
+        for label in &self.labels {
+            let name = label.name.trim();
+            if name.is_empty() {
+                return Err("Label names can't be empty".to_string());
+            }
+            if !(0.0..=1.0).contains(&label.threshold) {
+                return Err(format!("Threshold for '{}' must be between 0 and 1", name));
+            }
+            if !names.insert(name.to_lowercase()) {
+                return Err(format!("Label '{}' is listed twice", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Labels whose confidence clears their threshold, best first.
+/// `label_vectors` are the encoded prompts in `labels` order followed by the background prompt;
+/// confidence is the softmax over all of them.
+pub fn classify(image: &[f32], labels: &[AutoTagLabel], label_vectors: &[Vec<f32>]) -> Vec<(String, f32)> {
+    let logits: Vec<f32> = label_vectors
+        .iter()
+        .map(|v| LOGIT_SCALE * v.iter().zip(image).map(|(a, b)| a * b).sum::<f32>())
+        .collect();
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exps: Vec<f32> = logits.iter().map(|l| (l - max).exp()).collect();
+    let total: f32 = exps.iter().sum();
+    let mut out: Vec<(String, f32)> = labels
+        .iter()
+        .zip(&exps)
+        .map(|(label, e)| (label, e / total))
+        .filter(|(label, p)| *p >= label.threshold)
+        .map(|(label, p)| (label.name.trim().to_string(), p))
+        .collect();
+    out.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    out
+}
+
+/// Settings key recording which settings the stored predictions were made with
+#[cfg(feature = "auto-tagging")]
+const APPLIED_KEY: &str = "auto_tags_applied";
+/// Assets classified per read of the vector index
+#[cfg(feature = "auto-tagging")]
+const RETAG_BATCH: usize = 256;
+
+/// Prompts and their encoded vectors, in label order with the background prompt last
+#[cfg(feature = "auto-tagging")]
+type PromptVectors = (Vec<String>, Arc<Vec<Vec<f32>>>);
+
+/// The live settings plus the encoded prompts they need
+#[cfg(feature = "auto-tagging")]
+#[derive(Default)]
+pub struct AutoTagger {
+    settings: RwLock<AutoTagSettings>,
+    /// Prompt vectors keyed by the prompts they encode, so edited labels are re-encoded on next use
+    vectors: Mutex<Option<PromptVectors>>,
+    /// (pass running, another pass requested)
+    retag: Mutex<(bool, bool)>,
+}
+
+#[cfg(feature = "auto-tagging")]
+impl AutoTagger {
+    pub fn settings(&self) -> AutoTagSettings {
+        self.settings.read().clone()
+    }
+
+    pub fn set_settings(&self, settings: AutoTagSettings) {
+        *self.settings.write() = settings;
+    }
+
+    fn label_vectors(&self, processor: &ClipProcessor, labels: &[AutoTagLabel]) -> Result<Arc<Vec<Vec<f32>>>> {
+        let mut prompts: Vec<String> = labels.iter().map(|l| l.prompt()).collect();
+        prompts.push(BACKGROUND_PROMPT.to_string());
+        let mut cached = self.vectors.lock();
+        if let Some((key, vectors)) = cached.as_ref() {
+            if *key == prompts {
+                return Ok(vectors.clone());
+            }
+        }
+        let vectors = Arc::new(prompts.iter().map(|p| processor.embed_text(p)).collect::<Result<Vec<_>>>()?);
+        *cached = Some((prompts, vectors.clone()));
+        Ok(vectors)
+    }
+
+    /// Tags for an image vector; None while auto-tagging is off
+    pub fn predict(&self, processor: &ClipProcessor, image: &[f32]) -> Result<Option<Vec<(String, f32)>>> {
+        let settings = self.settings();
+        if !settings.enabled {
+            return Ok(None);
+        }
+        let vectors = self.label_vectors(processor, &settings.labels)?;
+        Ok(Some(classify(image, &settings.labels, &vectors)))
+    }
+}
+
+/// Re-classify every embedded asset unless its predictions already match the current settings.
+/// Runs in the background; a call during a pass queues one more pass.
+#[cfg(feature = "auto-tagging")]
+pub fn start_retag(state: Arc<crate::AppState>) {
+    {
+        let mut retag = state.semantic.auto_tags.retag.lock();
+        if retag.0 {
+            retag.1 = true;
+            return;
+        }
+        retag.0 = true;
+    }
+    tokio::spawn(async move {
+        loop {
+            let state_c = state.clone();
+            match tokio::task::spawn_blocking(move || retag_all(&state_c)).await {
+                Ok(Ok(Some(n))) => tracing::info!("Auto-tags refreshed for {} assets", n),
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => tracing::warn!("Auto-tagging pass failed: {}", e),
+                Err(e) => tracing::warn!("Auto-tagging task panicked: {}", e),
+            }
+            let mut retag = state.semantic.auto_tags.retag.lock();
+            if retag.1 {
+                retag.1 = false;
+            } else {
+                retag.0 = false;
+                break;
+            }
+        }
+    });
+}
+
+/// Assets re-classified, or None if there was nothing to do
+#[cfg(feature = "auto-tagging")]
+fn retag_all(state: &crate::AppState) -> Result<Option<usize>> {
+    let Some(processor) = state.semantic.processor() else {
+        return Ok(None);
+    };
+    let conn = state.pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+    let tagger = &state.semantic.auto_tags;
+    let settings = tagger.settings();
+    let applied = serde_json::to_string(&settings)?;
+    if crate::db::writer::get_setting(&conn, APPLIED_KEY)?.as_deref() == Some(applied.as_str()) {
+        return Ok(None);
+    }
+    // Turning auto-tagging off keeps the predictions already made
+    let mut done = 0;
+    if settings.enabled {
+        let vectors = tagger.label_vectors(&processor, &settings.labels)?;
+        let ids = state.semantic.index.read().ids().to_vec();
+        for chunk in ids.chunks(RETAG_BATCH) {
+            let predictions: Vec<(i64, Vec<(String, f32)>)> = {
+                let index = state.semantic.index.read();
+                chunk
+                    .iter()
+                    .filter_map(|&id| index.get(id).map(|v| (id, classify(v, &settings.labels, &vectors))))
+                    .collect()
+            };
+            for (asset_id, tags) in predictions {
+                crate::db::writer::set_auto_tags(&conn, asset_id, &tags)?;
+                done += 1;
+            }
+        }
+    }
+    crate::db::writer::set_setting(&conn, APPLIED_KEY, &applied)?;
+    Ok(Some(done))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let labels = vec![
+            AutoTagLabel { name: "sunset".to_string(), prompt: None, threshold: 0.5 },
+            AutoTagLabel { name: "food".to_string(), prompt: None, threshold: 0.01 },
+        ];
+        assert_eq!(labels[0].prompt(), "a photo of a sunset");
+        // sunset, food, background
+        let vectors = vec![vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0], vec![0.0, 0.0, 1.0]];
+
+        let tags = classify(&[0.8, 0.6, 0.0], &labels, &vectors);
+        assert_eq!(tags.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>(), vec!["sunset"]);
+        assert!(tags[0].1 > 0.99);
+
+        let tags = classify(&[0.0, 0.6, 0.8], &labels, &vectors);
+        assert!(tags.is_empty());
+        let tags = classify(&[0.0, 0.77, 0.8], &labels, &vectors);
+        assert_eq!(tags[0].0, "food");
+
+        let mut settings = AutoTagSettings::default();
+        assert!(settings.validate().is_ok());
+        settings.labels.push(AutoTagLabel { name: "Sunset".to_string(), prompt: None, threshold: 0.5 });
+        assert!(settings.validate().is_err());
+        settings.labels.pop();
+        settings.labels[0].threshold = 1.5;
+        assert!(settings.validate().is_err());
+    }
+}
@@ -0,0 +1,418 @@
+//! Where an asset's capture date (`taken_at`) comes from.
+//!
+//! Each scan path can rank the sources; the first one that yields a date wins. Dates in EXIF,
+//! QuickTime's local creation date and filenames carry no time zone, so their wall-clock time is
+//! stored as if it were UTC, the same way the calendar and memories read it back.
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Enough of a file to reach the EXIF block of JPEGs and TIFF-based raw formats
+const EXIF_READ_LIMIT: u64 = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateSource {
+    /// EXIF DateTimeOriginal, then DateTimeDigitized and DateTime
+    Exif,
+    /// A video's creation date from its QuickTime/MP4 metadata
+    Quicktime,
+    /// A date from the filename extractors, else one in the file name such as `IMG_20230514_153012.jpg`
+    Filename,
+    /// The file's modification time
+    Mtime,
+}
+
+impl DateSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DateSource::Exif => "exif",
+            DateSource::Quicktime => "quicktime",
+            DateSource::Filename => "filename",
+            DateSource::Mtime => "mtime",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "exif" => Some(DateSource::Exif),
+            "quicktime" => Some(DateSource::Quicktime),
+            "filename" => Some(DateSource::Filename),
+            "mtime" => Some(DateSource::Mtime),
+            _ => None,
+        }
+    }
+}
+
+pub const DEFAULT_PRIORITY: [DateSource; 4] = [DateSource::Exif, DateSource::Quicktime, DateSource::Filename, DateSource::Mtime];
+
+/// Parse source names in order, e.g. `["filename", "exif"]`; duplicates are dropped
+pub fn parse_priority<S: AsRef<str>>(names: &[S]) -> Result<Vec<DateSource>, String> {
+    let mut priority = Vec::with_capacity(names.len());
+    for name in names {
+        let source = DateSource::parse(name.as_ref())
+            .ok_or_else(|| format!("Unknown date source '{}' (expected exif, quicktime, filename or mtime)", name.as_ref()))?;
+        if !priority.contains(&source) {
+            priority.push(source);
+        }
+    }
+    if priority.is_empty() {
+        return Err("At least one date source is required".to_string());
+    }
+    Ok(priority)
+}
+
+pub fn format_priority(priority: &[DateSource]) -> String {
+    priority.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(",")
+}
+
+/// Priority from FLASH_DATE_PRIORITY (comma-separated), or [`DEFAULT_PRIORITY`]
+pub fn priority_from_env() -> Vec<DateSource> {
+    let Ok(value) = std::env::var("FLASH_DATE_PRIORITY") else {
+        return DEFAULT_PRIORITY.to_vec();
+    };
+    let names: Vec<&str> = value.split(',').filter(|s| !s.trim().is_empty()).collect();
+    parse_priority(&names).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring FLASH_DATE_PRIORITY: {}", e);
+        DEFAULT_PRIORITY.to_vec()
+    })
+}
+
+/// Date source order for every scan path, shared with the metadata workers and the paths API
+pub type SharedDatePriorities = Arc<RwLock<DatePriorities>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatePriorities {
+    /// For scan paths without their own
+    pub default: Vec<DateSource>,
+    /// Scan path and its order
+    pub paths: Vec<(String, Vec<DateSource>)>,
+}
+
+impl Default for DatePriorities {
+    fn default() -> Self {
+        Self { default: DEFAULT_PRIORITY.to_vec(), paths: Vec::new() }
+    }
+}
+
+impl DatePriorities {
+    /// Order for a file: the deepest scan path holding it that has one, else the default
+    pub fn for_path(&self, path: &str) -> &[DateSource] {
+        self.paths
+            .iter()
+            .filter(|(root, _)| Path::new(path).starts_with(root))
+            .max_by_key(|(root, _)| root.len())
+            .map(|(_, priority)| priority.as_slice())
+            .unwrap_or(&self.default)
+    }
+}
+
+/// The default from [`priority_from_env`] plus every scan path's saved order; unreadable
+/// entries fall back to the default
+pub fn load_priorities(conn: &rusqlite::Connection) -> anyhow::Result<DatePriorities> {
+    let paths = crate::db::query::get_scan_path_date_priorities(conn)?
+        .into_iter()
+        .filter_map(|(path, saved)| {
+            let names: Vec<&str> = saved.split(',').collect();
+            match parse_priority(&names) {
+                Ok(priority) => Some((path, priority)),
+                Err(e) => {
+                    tracing::warn!("Ignoring date priority of {}: {}", path, e);
+                    None
+                }
+            }
+        })
+        .collect();
+    Ok(DatePriorities { default: priority_from_env(), paths })
+}
+
+/// Dates a file offers; each source is only looked up when the ones before it came up empty
+pub struct DateCandidates<'a> {
+    pub path: &'a Path,
+    pub filename: &'a str,
+    pub mime: &'a str,
+    pub mtime_ns: i64,
+    /// From ffprobe, for videos
+    pub quicktime: Option<i64>,
+    /// From the user's filename extractors, tried before the built-in filename patterns
+    pub extracted: Option<i64>,
+}
+
+/// `taken_at` and the source it came from, following `priority`
+pub fn resolve(priority: &[DateSource], candidates: &DateCandidates) -> Option<(i64, DateSource)> {
+    priority.iter().find_map(|&source| {
+        let date = match source {
+            DateSource::Exif if candidates.mime.starts_with("image/") => exif_date(candidates.path),
+            DateSource::Exif => None,
+            DateSource::Quicktime => candidates.quicktime,
+            DateSource::Filename => candidates.extracted.or_else(|| filename_date(candidates.filename)),
+            DateSource::Mtime => Some(candidates.mtime_ns / 1_000_000_000),
+        };
+        date.map(|d| (d, source))
+    })
+}
+
+fn naive_timestamp(date: NaiveDateTime) -> i64 {
+    date.and_utc().timestamp()
+}
+
+/// A video's creation date from ffprobe's format tags. Apple's local-time tag is preferred so
+/// iPhone videos line up with photos from the same moment; `creation_time` is UTC.
+pub fn quicktime_date(tags: &serde_json::Value) -> Option<i64> {
+    let tag = |name: &str| tags.get(name).and_then(|v| v.as_str());
+    if let Some(local) = tag("com.apple.quicktime.creationdate") {
+        if let Ok(date) = chrono::DateTime::parse_from_str(local, "%Y-%m-%dT%H:%M:%S%z") {
+            return Some(naive_timestamp(date.naive_local()));
+        }
+    }
+    let created = chrono::DateTime::parse_from_rfc3339(tag("creation_time")?).ok()?;
+    // Cameras without a clock write the QuickTime epoch or 1970
+    (created.timestamp() > 0).then_some(created.timestamp())
+}
+
+/// Look for a date, optionally followed by a time, in a file name:
+/// `IMG_20230514_153012`, `PXL_20230514_153012345`, `VID-20230514-WA0003`,
+/// `Screenshot_2023-05-14-15-30-12`, `2023-05-14 15.30.12`, `signal-2023-05-14-153012`.
+/// Dates without a time are taken at midnight.
+pub fn filename_date(filename: &str) -> Option<i64> {
+    let bytes = filename.as_bytes();
+    (0..bytes.len())
+        .filter(|&i| bytes[i].is_ascii_digit() && (i == 0 || !bytes[i - 1].is_ascii_digit()))
+        .find_map(|i| date_at(bytes, i))
+}
+
+fn digits(bytes: &[u8], pos: usize, n: usize) -> Option<u32> {
+    let slice = bytes.get(pos..pos + n)?;
+    slice.iter().all(u8::is_ascii_digit).then(|| slice.iter().fold(0, |acc, b| acc * 10 + (b - b'0') as u32))
+}
+
+/// Skip one separator from `allowed` at `pos`, if there is one
+fn separator(bytes: &[u8], pos: usize, allowed: &[u8]) -> usize {
+    if bytes.get(pos).is_some_and(|b| allowed.contains(b)) { pos + 1 } else { pos }
+}
+
+fn date_at(bytes: &[u8], start: usize) -> Option<i64> {
+    let year = digits(bytes, start, 4)?;
+    if !(1900..=2099).contains(&year) {
+        return None;
+    }
+    let mut pos = start + 4;
+    let sep = bytes.get(pos).copied().filter(|b| b"-_.".contains(b));
+    pos += sep.is_some() as usize;
+    let month = digits(bytes, pos, 2)?;
+    pos += 2;
+    if let Some(sep) = sep {
+        // "2023-05-14", not "2023-0514"
+        if bytes.get(pos) != Some(&sep) {
+            return None;
+        }
+        pos += 1;
+    }
+    let day = digits(bytes, pos, 2)?;
+    pos += 2;
+    let date = NaiveDate::from_ymd_opt(year as i32, month, day)?;
+
+    let time_start = separator(bytes, pos, b"-_. T");
+    let time = (|| {
+        let hour = digits(bytes, time_start, 2)?;
+        let p = separator(bytes, time_start + 2, b"-_.:");
+        let minute = digits(bytes, p, 2)?;
+        let p = separator(bytes, p + 2, b"-_.:");
+        let second = digits(bytes, p, 2)?;
+        NaiveTime::from_hms_opt(hour, minute, second)
+    })();
+    match time {
+        Some(time) => Some(naive_timestamp(date.and_time(time))),
+        // A longer run of digits is some other number, not a date
+        None if bytes.get(pos).is_some_and(u8::is_ascii_digit) => None,
+        None => Some(naive_timestamp(date.and_time(NaiveTime::MIN))),
+    }
+}
+
+/// The capture date from a file's EXIF block: JPEG APP1, TIFF-based raw files, and an
+/// `Exif\0\0` block near the start of other formats (HEIC, WebP)
+pub fn exif_date(path: &Path) -> Option<i64> {
+    let mut head = Vec::new();
+    std::fs::File::open(path).ok()?.take(EXIF_READ_LIMIT).read_to_end(&mut head).ok()?;
+    tiff_date(find_tiff(&head)?)
+}
+
+fn find_tiff(data: &[u8]) -> Option<&[u8]> {
+    if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        return Some(data);
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        let mut pos = 2;
+        while pos + 4 <= data.len() && data[pos] == 0xFF {
+            let marker = data[pos + 1];
+            // Start of scan: image data follows, no more metadata
+            if marker == 0xDA || marker == 0xD9 {
+                return None;
+            }
+            let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+            let segment = data.get(pos + 4..pos + 2 + len)?;
+            if marker == 0xE1 && segment.starts_with(b"Exif\0\0") {
+                return Some(&segment[6..]);
+            }
+            pos += 2 + len;
+        }
+        return None;
+    }
+    let at = data.windows(6).position(|w| w == b"Exif\0\0")?;
+    Some(&data[at + 6..])
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl Tiff<'_> {
+    fn u16(&self, pos: usize) -> Option<u16> {
+        let b: [u8; 2] = self.data.get(pos..pos + 2)?.try_into().ok()?;
+        Some(if self.little_endian { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+    }
+
+    fn u32(&self, pos: usize) -> Option<u32> {
+        let b: [u8; 4] = self.data.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+    }
+
+    /// Position of `tag`'s 12-byte entry in the IFD at `ifd`
+    fn entry(&self, ifd: usize, tag: u16) -> Option<usize> {
+        let count = self.u16(ifd)? as usize;
+        (0..count).map(|i| ifd + 2 + i * 12).find(|&e| self.u16(e) == Some(tag))
+    }
+
+    fn ascii(&self, ifd: usize, tag: u16) -> Option<&str> {
+        let e = self.entry(ifd, tag)?;
+        // Type 2 is ASCII
+        if self.u16(e + 2)? != 2 {
+            return None;
+        }
+        let len = self.u32(e + 4)? as usize;
+        let start = if len <= 4 { e + 8 } else { self.u32(e + 8)? as usize };
+        let raw = self.data.get(start..start.checked_add(len)?)?;
+        std::str::from_utf8(raw).ok().map(|s| s.trim_end_matches('\0').trim())
+    }
+}
+
+fn exif_timestamp(value: &str) -> Option<i64> {
+    NaiveDateTime::parse_from_str(value.get(..19)?, "%Y:%m:%d %H:%M:%S").ok().map(naive_timestamp)
+}
+
+fn tiff_date(data: &[u8]) -> Option<i64> {
+    let little_endian = match data.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let tiff = Tiff { data, little_endian };
+    if tiff.u16(2)? != 42 {
+        return None;
+    }
+    let ifd0 = tiff.u32(4)? as usize;
+    let exif_ifd = tiff.entry(ifd0, 0x8769).and_then(|e| tiff.u32(e + 8)).map(|o| o as usize);
+    let original = exif_ifd.and_then(|ifd| {
+        // DateTimeOriginal, then DateTimeDigitized
+        [0x9003, 0x9004].into_iter().find_map(|tag| tiff.ascii(ifd, tag).and_then(exif_timestamp))
+    });
+    // DateTime in IFD0 changes when software edits the file, so it comes last
+    original.or_else(|| tiff.ascii(ifd0, 0x0132).and_then(exif_timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> i64 {
+        naive_timestamp(NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap())
+    }
+
+    /// A little-endian TIFF block with DateTime in IFD0 and DateTimeOriginal in the EXIF IFD
+    fn tiff_block(original: &str) -> Vec<u8> {
+        let mut t = b"II*\0".to_vec();
+        t.extend_from_slice(&8u32.to_le_bytes());
+        // IFD0 at 8: two entries, then the next-IFD offset
+        t.extend_from_slice(&2u16.to_le_bytes());
+        let (datetime_at, exif_at, original_at) = (8 + 2 + 24 + 4, 8 + 2 + 24 + 4 + 20, 8 + 2 + 24 + 4 + 20 + 18);
+        for (tag, kind, count, value) in [(0x0132u16, 2u16, 20u32, datetime_at as u32), (0x8769, 4, 1, exif_at as u32)] {
+            t.extend_from_slice(&tag.to_le_bytes());
+            t.extend_from_slice(&kind.to_le_bytes());
+            t.extend_from_slice(&count.to_le_bytes());
+            t.extend_from_slice(&value.to_le_bytes());
+        }
+        t.extend_from_slice(&0u32.to_le_bytes());
+        t.extend_from_slice(b"2024:01:02 03:04:05\0");
+        // EXIF IFD: one entry
+        t.extend_from_slice(&1u16.to_le_bytes());
+        t.extend_from_slice(&0x9003u16.to_le_bytes());
+        t.extend_from_slice(&2u16.to_le_bytes());
+        t.extend_from_slice(&20u32.to_le_bytes());
+        t.extend_from_slice(&(original_at as u32).to_le_bytes());
+        t.extend_from_slice(&0u32.to_le_bytes());
+        t.extend_from_slice(original.as_bytes());
+        t.push(0);
+        t
+    }
+
+    #[test]
+    fn test_filename_dates() {
+        assert_eq!(filename_date("IMG_20230514_153012.jpg"), Some(at("2023-05-14 15:30:12")));
+        assert_eq!(filename_date("PXL_20230514_153012345.jpg"), Some(at("2023-05-14 15:30:12")));
+        assert_eq!(filename_date("VID-20230514-WA0003.mp4"), Some(at("2023-05-14 00:00:00")));
+        assert_eq!(filename_date("Screenshot_2023-05-14-15-30-12.png"), Some(at("2023-05-14 15:30:12")));
+        assert_eq!(filename_date("2023-05-14 15.30.12.jpg"), Some(at("2023-05-14 15:30:12")));
+        assert_eq!(filename_date("signal-2023-05-14-153012.jpg"), Some(at("2023-05-14 15:30:12")));
+        assert_eq!(filename_date("holiday 2019.05.01.jpg"), Some(at("2019-05-01 00:00:00")));
+        for name in ["DSC_1234.jpg", "IMG_20231399_000000.jpg", "1684067696000.jpg", "2023-0514.jpg", "scan 1850-01-01.tif"] {
+            assert_eq!(filename_date(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_exif_and_priority() {
+        let block = tiff_block("2021:07:08 09:10:11");
+        assert_eq!(tiff_date(&block), Some(at("2021-07-08 09:10:11")));
+        // A blank DateTimeOriginal falls back to DateTime
+        assert_eq!(tiff_date(&tiff_block("0000:00:00 00:00:00")), Some(at("2024-01-02 03:04:05")));
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 4, 0, 0, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&((block.len() + 8) as u16).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(&block);
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0, 2]);
+        let dir = tempfile::TempDir::new().unwrap();
+        let photo = dir.path().join("IMG_20200101_000000.jpg");
+        std::fs::write(&photo, &jpeg).unwrap();
+        assert_eq!(exif_date(&photo), Some(at("2021-07-08 09:10:11")));
+
+        let candidates = DateCandidates { path: &photo, filename: "IMG_20200101_000000.jpg", mime: "image/jpeg", mtime_ns: 5_000_000_000, quicktime: None, extracted: None };
+        assert_eq!(resolve(&DEFAULT_PRIORITY, &candidates), Some((at("2021-07-08 09:10:11"), DateSource::Exif)));
+        let priority = parse_priority(&["filename", "EXIF", "filename"]).unwrap();
+        assert_eq!(priority, vec![DateSource::Filename, DateSource::Exif]);
+        assert_eq!(resolve(&priority, &candidates), Some((at("2020-01-01 00:00:00"), DateSource::Filename)));
+        assert_eq!(resolve(&[DateSource::Quicktime, DateSource::Mtime], &candidates), Some((5, DateSource::Mtime)));
+        assert_eq!(resolve(&[DateSource::Quicktime], &candidates), None);
+        let renamed = DateCandidates { extracted: Some(7), ..candidates };
+        assert_eq!(resolve(&priority, &renamed), Some((7, DateSource::Filename)));
+        assert!(parse_priority(&["gps"]).is_err() && parse_priority::<&str>(&[]).is_err());
+
+        let tags = serde_json::json!({ "creation_time": "2023-05-14T13:30:12.000000Z", "com.apple.quicktime.creationdate": "2023-05-14T15:30:12+0200" });
+        assert_eq!(quicktime_date(&tags), Some(at("2023-05-14 15:30:12")));
+        assert_eq!(quicktime_date(&serde_json::json!({ "creation_time": "2023-05-14T13:30:12.000000Z" })), Some(at("2023-05-14 13:30:12")));
+        assert_eq!(quicktime_date(&serde_json::json!({ "creation_time": "1970-01-01T00:00:00.000000Z" })), None);
+
+        let priorities = DatePriorities {
+            default: DEFAULT_PRIORITY.to_vec(),
+            paths: vec![("/photos".to_string(), vec![DateSource::Mtime]), ("/photos/scans".to_string(), vec![DateSource::Filename])],
+        };
+        assert_eq!(priorities.for_path("/photos/scans/a.tif"), &[DateSource::Filename]);
+        assert_eq!(priorities.for_path("/photos/a.jpg"), &[DateSource::Mtime]);
+        assert_eq!(priorities.for_path("/photos2/a.jpg"), &DEFAULT_PRIORITY);
+    }
+}
@@ -8,6 +8,9 @@ use std::fs;
 use crate::pipeline::hash::HashJob;
 use crate::pipeline::metadata::MetaJob;
 use crate::pipeline::QueueGauges;
+use crate::pipeline::path_filter::{PathFilter, SharedPathFilter};
+use crate::pipeline::quota::{QuotaHit, ScanQuota};
+use crate::pipeline::ingest::SharedIngestFilter;
 use std::sync::Arc;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
@@ -24,6 +27,42 @@ pub struct DiscoverItem {
     pub filename: String,
     pub ext: String,
     pub mime: String,
+    /// Re-hash and re-extract metadata even when path, size and mtime match the database
+    pub force: bool,
+    /// The cancel flag of the scan that found the file; once set, the file is dropped before hashing
+    pub canceled: Option<Arc<std::sync::atomic::AtomicBool>>,
+}
+
+/// How a scan walks its path
+#[derive(Clone, Debug, Default)]
+pub struct ScanOptions {
+    /// Re-hash and re-extract every file, see [`DiscoverItem::force`]
+    pub force: bool,
+    pub filter: PathFilter,
+    pub quota: ScanQuota,
+    /// Set through `POST /paths/cancel`: the walk stops and the files it queued are dropped
+    pub cancel: Option<Arc<std::sync::atomic::AtomicBool>>,
+}
+
+/// What a scan sent on to the pipeline
+#[derive(Clone, Debug, Default)]
+pub struct ScanSummary {
+    pub files: u64,
+    pub bytes: u64,
+    pub quota_hit: Option<QuotaHit>,
+}
+
+/// One scan of a path, as kept in the scan history
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct ScanRun {
+    pub path: String,
+    pub started_at: i64,
+    pub finished_at: i64,
+    /// "completed", "quota" (stopped at the path's quota), "paused", "canceled" or "failed"
+    pub status: String,
+    /// Files sent on for indexing, and their size
+    pub files: u64,
+    pub bytes: u64,
 }
 
 pub(crate) fn is_hidden(p: &Path) -> bool {
@@ -60,6 +99,8 @@ pub(crate) fn discover_item_from_metadata(path: &Path, md: &fs::Metadata) -> Opt
     if !md.is_file() {
         return None;
     }
+    // Stored without a `\\?\` prefix, however the walk reached the file
+    let path = &crate::utils::path::plain_path(path);
     let size_bytes = md.len() as i64;
     let mtime_ns = md
         .modified()
@@ -102,14 +143,34 @@ pub(crate) fn discover_item_from_metadata(path: &Path, md: &fs::Metadata) -> Opt
         filename,
         ext,
         mime,
+        force: false,
+        canceled: None,
     })
 }
 
 pub(crate) fn to_discover_item(path: &Path) -> Option<DiscoverItem> {
-    let md = fs::metadata(path).ok()?;
+    let md = fs::metadata(crate::utils::path::long_path(path)).ok()?;
     discover_item_from_metadata(path, &md)
 }
 
+/// Send files left in the pending job journal by an unclean shutdown back through discovery.
+/// Files that are gone are dropped; unchanged ones are skipped by the forwarder as usual.
+pub async fn requeue_pending(paths: Vec<String>, tx: Sender<DiscoverItem>, gauges: Arc<QueueGauges>) -> usize {
+    let mut sent = 0;
+    for path in paths {
+        let Some(item) = to_discover_item(Path::new(&path)) else {
+            debug!("pending file no longer exists: {:?}", path);
+            continue;
+        };
+        if tx.send(item).await.is_err() {
+            break;
+        }
+        gauges.discover.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        sent += 1;
+    }
+    sent
+}
+
 #[derive(Clone, Debug)]
 struct RemovedFile {
     old_path: String,
@@ -120,17 +181,33 @@ struct RemovedFile {
     removed_at: Instant,
 }
 
-pub fn start_forwarder(mut rx: Receiver<DiscoverItem>, hash_tx: Sender<HashJob>, meta_tx: Option<Sender<MetaJob>>, db_path: Option<PathBuf>, gauges: Arc<QueueGauges>, _stats: Option<Arc<crate::stats::Stats>>) {
+pub fn start_forwarder(mut rx: Receiver<DiscoverItem>, hash_tx: Sender<HashJob>, meta_tx: Option<Sender<MetaJob>>, db_path: Option<PathBuf>, gauges: Arc<QueueGauges>, stats: Option<Arc<crate::stats::Stats>>, ingest: SharedIngestFilter) {
     use tracing::debug;
     tokio::spawn(async move {
-        // Open read-only database connection for skip checks if provided
-        let db_conn = if let Some(ref dbp) = db_path {
-            rusqlite::Connection::open_with_flags(dbp, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY).ok()
-        } else {
-            None
-        };
-        
-        while let Some(it) = rx.recv().await {
+        // Database connection for skip checks and the pending job journal, if provided
+        let db_conn = db_path.as_ref().and_then(|dbp| rusqlite::Connection::open(dbp).ok());
+        if let Some(conn) = db_conn.as_ref() {
+            let _ = conn.busy_timeout(Duration::from_secs(5));
+        }
+        // Paths forwarded since the last journal write; flushed in batches and whenever the queue drains
+        let mut journal: Vec<String> = Vec::new();
+
+        loop {
+            if !journal.is_empty() && (journal.len() >= 256 || rx.is_empty()) {
+                if let Some(conn) = db_conn.as_ref() {
+                    if let Err(e) = crate::db::writer::journal_pending_jobs(conn, &journal) {
+                        tracing::warn!("could not journal {} pending files: {}", journal.len(), e);
+                    }
+                }
+                journal.clear();
+            }
+            let Some(it) = rx.recv().await else { break };
+            // Left by a canceled scan: dropped even while the pipeline is paused
+            if it.canceled.as_ref().is_some_and(|c| c.load(std::sync::atomic::Ordering::Relaxed)) {
+                gauges.discover.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+                continue;
+            }
+            gauges.wait_while_paused().await;
             gauges.discover.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
             
             // Skip files that are not images or videos
@@ -138,13 +215,23 @@ pub fn start_forwarder(mut rx: Receiver<DiscoverItem>, hash_tx: Sender<HashJob>,
                 debug!("skipping non-image/non-video file: {:?} (mime: {})", it.path, it.mime);
                 continue;
             }
+
+            // Too small or an unwanted format: never hashed, never indexed
+            if !ingest.read().allows_file(it.size_bytes, &it.ext) {
+                debug!("skipping file rejected by ingest filters: {:?}", it.path);
+                if let Some(ref s) = stats {
+                    s.inc_files_filtered(1);
+                }
+                continue;
+            }
             
             // Fast-path: Check if file is unchanged (skip hashing if so)
             // BUT: Always re-hash if SHA256 is missing (needed for thumbnails, especially for videos)
+            // A forced rescan bypasses this and reprocesses every file
             let mut skip_hash = false;
-            if let Some(ref conn) = db_conn {
+            if let Some(conn) = db_conn.as_ref().filter(|_| !it.force) {
                 let path_str = it.path.to_string_lossy();
-                if let Ok(Some((id, xxh64, sha256))) = crate::db::query::check_file_unchanged(conn, &path_str, it.mtime_ns, it.size_bytes) {
+                if let Ok(Some((id, xxh64, sha256, flags))) = crate::db::query::check_file_unchanged(conn, &path_str, it.mtime_ns, it.size_bytes) {
                     // File unchanged - skip hashing only if SHA256 is already present
                     // If SHA256 is None, we need to re-hash (especially for video files)
                     if sha256.is_some() {
@@ -153,6 +240,9 @@ pub fn start_forwarder(mut rx: Receiver<DiscoverItem>, hash_tx: Sender<HashJob>,
                             // File is completely unchanged and fully indexed - skip everything
                             debug!("skipping unchanged file entirely: {:?}", it.path);
                             // Don't increment files_committed - file is already in database and counted
+                            if let Some(ref s) = stats {
+                                s.inc_files_unchanged(1);
+                            }
                             continue; // Skip metadata extraction and DB write
                         } else {
                             // Metadata incomplete - still extract metadata
@@ -171,8 +261,10 @@ pub fn start_forwarder(mut rx: Receiver<DiscoverItem>, hash_tx: Sender<HashJob>,
                                     job: hash_job,
                                     xxh64,
                                     sha256,
+                                    sampled_hash: flags & crate::models::asset::FLAG_SAMPLED_HASH != 0,
                                 };
                                 debug!("skipping hash for unchanged file (metadata incomplete): {:?}", it.path);
+                                journal.push(path_str.to_string());
                                 let _ = meta_tx.send(meta_job).await;
                                 gauges.metadata.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                                 skip_hash = true;
@@ -188,6 +280,7 @@ pub fn start_forwarder(mut rx: Receiver<DiscoverItem>, hash_tx: Sender<HashJob>,
             if !skip_hash {
                 let job = HashJob { path: it.path, size_bytes: it.size_bytes, mtime_ns: it.mtime_ns, ctime_ns: it.ctime_ns, dirname: it.dirname, filename: it.filename, ext: it.ext, mime: it.mime };
                 debug!("forwarding to hash: {:?}", job.path);
+                journal.push(job.path.to_string_lossy().into_owned());
                 let _ = hash_tx.send(job).await;
                 gauges.hash.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             }
@@ -202,8 +295,9 @@ pub async fn scan_bfs(
     gauges: Arc<QueueGauges>,
     scan_running: Arc<std::sync::atomic::AtomicBool>,
     stats: Option<Arc<crate::stats::Stats>>,
-) -> Result<()> {
-    crate::pipeline::discover_linux::scan_bfs_getdents(root, tx, gauges, scan_running, stats).await
+    options: ScanOptions,
+) -> Result<ScanSummary> {
+    crate::pipeline::discover_linux::scan_bfs_getdents(root, tx, gauges, scan_running, stats, options).await
 }
 
   #[cfg(not(target_os = "linux"))]
@@ -213,25 +307,29 @@ pub async fn scan_bfs(
       gauges: Arc<QueueGauges>,
       scan_running: Arc<std::sync::atomic::AtomicBool>,
       stats: Option<Arc<crate::stats::Stats>>,
-  ) -> Result<()> {
+      options: ScanOptions,
+  ) -> Result<ScanSummary> {
       use tracing::{info, warn, debug};
+      let ScanOptions { force, filter, quota, cancel } = options;
+      let mut counter = crate::pipeline::quota::QuotaCounter::new(quota);
       let patterns = read_ignore(&root);
     info!("scanning root: {:?}", root);
     let mut file_count = 0;
     let mut dir_count = 0;
     let mut check_counter = 0;
-    for entry in WalkDir::new(&root).follow_links(false).into_iter() {
+    // Deep trees and network shares past MAX_PATH are only readable in `\\?\` form on Windows
+    for entry in WalkDir::new(crate::utils::path::long_path(&root)).follow_links(false).into_iter() {
         // Check scan_running flag periodically (every 100 entries)
         check_counter += 1;
         if check_counter % 100 == 0 {
             if !scan_running.load(std::sync::atomic::Ordering::Relaxed) {
                 info!("scan_stopped_during_walk: {:?}", root);
-                return Ok(());
+                break;
             }
         }
         match entry {
               Ok(e) => {
-                  let p = e.path();
+                  let p = crate::utils::path::plain_path(&e.path());
                   if e.file_type().is_dir() {
                       dir_count += 1;
                       if is_hidden(&p) { continue; }
@@ -240,11 +338,18 @@ pub async fn scan_bfs(
                   }
                   if is_hidden(&p) { continue; }
                   if ignored(&p, &patterns) { continue; }
+                  if !filter.allows_file(&p) { continue; }
                   // Fast-path: skip non-image/non-video extensions before metadata/stat calls
                   if !has_image_video_extension(&p) { continue; }
-                  if let Some(item) = to_discover_item(&p) {
+                  if let Some(mut item) = to_discover_item(&p) {
+                      item.force = force;
+                      item.canceled = cancel.clone();
                       // Only process image and video files
                       if item.mime.starts_with("image/") || item.mime.starts_with("video/") {
+                          if !counter.admit(item.size_bytes.max(0) as u64) {
+                              warn!("scan of {:?} stopped at its quota after {} files", root, file_count);
+                              break;
+                          }
                           file_count += 1;
                           // Increment discovery counter immediately when file is discovered
                           // This gives accurate discovery rate in the frontend
@@ -267,10 +372,11 @@ pub async fn scan_bfs(
         }
     }
     info!("scan complete, found {} files in {} directories", file_count, dir_count);
-    Ok(())
+    let (files, bytes) = counter.counted();
+    Ok(ScanSummary { files, bytes, quota_hit: counter.hit() })
 }
 
-pub async fn watch(root: PathBuf, tx: Sender<DiscoverItem>, db_path: Option<PathBuf>, gauges: Arc<QueueGauges>, stats: Option<Arc<crate::stats::Stats>>, watcher_paused: Option<Arc<std::sync::atomic::AtomicBool>>) -> Result<()> {
+pub async fn watch(root: PathBuf, tx: Sender<DiscoverItem>, db_path: Option<PathBuf>, gauges: Arc<QueueGauges>, stats: Option<Arc<crate::stats::Stats>>, watcher_paused: Option<Arc<std::sync::atomic::AtomicBool>>, filter: Option<SharedPathFilter>) -> Result<()> {
     let (evt_tx, mut evt_rx) = tokio::sync::mpsc::channel::<notify::Result<notify::Event>>(1024);
     tokio::task::spawn_blocking(move || {
         let mut watcher: RecommendedWatcher = notify::recommended_watcher(move |res| { let _ = evt_tx.blocking_send(res); }).unwrap();
@@ -360,6 +466,10 @@ pub async fn watch(root: PathBuf, tx: Sender<DiscoverItem>, db_path: Option<Path
                     
                     let old_path = &ev.paths[0];
                     let new_path = &ev.paths[1];
+                    if filter.as_ref().is_some_and(|f| !f.read().allows_file(new_path)) {
+                        debug!("skipping rename into filtered path: {:?}", new_path);
+                        continue;
+                    }
                     
                     // Validate that the new path is an image/video file
                     if let Some(item) = to_discover_item(new_path) {
@@ -403,6 +513,10 @@ pub async fn watch(root: PathBuf, tx: Sender<DiscoverItem>, db_path: Option<Path
                 }
                 EventKind::Create(_) | EventKind::Modify(_) => {
                     for p in ev.paths {
+                        // Honor the scan path's include/exclude globs
+                        if filter.as_ref().is_some_and(|f| !f.read().allows_file(&p)) {
+                            continue;
+                        }
                         if let Some(item) = to_discover_item(&p) {
                             // Only process image and video files
                             if item.mime.starts_with("image/") || item.mime.starts_with("video/") {
@@ -13,7 +13,9 @@ use libc::{dirent64, DT_DIR, DT_REG, DT_LNK, O_RDONLY, O_DIRECTORY};
 #[cfg(target_os = "linux")]
 use crate::pipeline::QueueGauges;
 #[cfg(target_os = "linux")]
-use crate::pipeline::discover::{DiscoverItem, is_hidden, read_ignore, ignored};
+use crate::pipeline::discover::{DiscoverItem, ScanOptions, ScanSummary, is_hidden, read_ignore, ignored};
+use crate::pipeline::path_filter::PathFilter;
+use crate::pipeline::quota::QuotaCounter;
 #[cfg(target_os = "linux")]
 use std::sync::Arc;
 #[cfg(target_os = "linux")]
@@ -112,6 +114,8 @@ fn to_discover_item_fast(path: &PathBuf) -> Option<DiscoverItem> {
         filename,
         ext,
         mime,
+        force: false,
+        canceled: None,
     })
 }
 
@@ -222,11 +226,12 @@ fn read_directory_entries(fd: RawFd, path: &Path) -> Result<Vec<(OsString, u8)>>
 fn process_directory(
     dir: &Path,
     patterns: &[String],
+    filter: &PathFilter,
 ) -> Result<(Vec<FileInfo>, Vec<PathBuf>)> {
     if is_hidden(dir) {
         return Ok((Vec::new(), Vec::new()));
     }
-    if ignored(dir, patterns) {
+    if ignored(dir, patterns) || filter.skips_dir(dir) {
         return Ok((Vec::new(), Vec::new()));
     }
 
@@ -272,6 +277,7 @@ fn process_directory(
 fn enumerate_files_fast(
     root: &Path,
     patterns: &[String],
+    filter: &PathFilter,
     scan_running: Arc<std::sync::atomic::AtomicBool>,
 ) -> Result<Vec<FileInfo>> {
     let patterns = Arc::new(patterns.to_vec());
@@ -297,7 +303,7 @@ fn enumerate_files_fast(
         let patterns_clone = patterns.clone();
         let results: Vec<Result<(Vec<FileInfo>, Vec<PathBuf>)>> = batch
             .par_iter()
-            .map(|dir| process_directory(dir, &patterns_clone))
+            .map(|dir| process_directory(dir, &patterns_clone, filter))
             .collect();
 
         // Collect files and subdirectories from parallel processing
@@ -324,11 +330,13 @@ pub async fn scan_bfs_getdents(
     gauges: Arc<QueueGauges>,
     scan_running: Arc<std::sync::atomic::AtomicBool>,
     stats: Option<Arc<crate::stats::Stats>>,
-) -> Result<()> {
+    options: ScanOptions,
+) -> Result<ScanSummary> {
+    let ScanOptions { force, filter, quota, cancel } = options;
     let patterns = read_ignore(&root);
     info!("scanning root with Linux getdents64 enumeration: {:?}", root);
 
-    let files = match enumerate_files_fast(&root, &patterns, scan_running.clone()) {
+    let files = match enumerate_files_fast(&root, &patterns, &filter, scan_running.clone()) {
         Ok(files) => files,
         Err(e) => {
             error!("Failed during fast file enumeration: {}", e);
@@ -353,7 +361,7 @@ pub async fn scan_bfs_getdents(
             if is_hidden(&file_info.path) {
                 return None;
             }
-            if ignored(&file_info.path, patterns_ref) {
+            if ignored(&file_info.path, patterns_ref) || !filter.allows_file(&file_info.path) {
                 return None;
             }
             // Filter by extension BEFORE doing stat call - this avoids expensive stat() on non-image/video files
@@ -362,7 +370,9 @@ pub async fn scan_bfs_getdents(
             }
 
             match to_discover_item_fast(&file_info.path) {
-                Some(item) => {
+                Some(mut item) => {
+                    item.force = force;
+                    item.canceled = cancel.clone();
                     if item.mime.starts_with("image/") || item.mime.starts_with("video/") {
                         // Increment discovery counter immediately when file is discovered (not when sent to channel)
                         // This gives accurate discovery rate in the frontend
@@ -385,7 +395,18 @@ pub async fn scan_bfs_getdents(
 
     if !scan_running.load(std::sync::atomic::Ordering::Relaxed) {
         info!("scan stopped during linux processing: {:?}", root);
-        return Ok(());
+        return Ok(ScanSummary::default());
+    }
+
+    // Only as much as the path's quota allows goes on, in enumeration order
+    let mut counter = QuotaCounter::new(quota);
+    let found = discovered_items.len();
+    let discovered_items: Vec<DiscoverItem> = discovered_items
+        .into_iter()
+        .take_while(|item| counter.admit(item.size_bytes.max(0) as u64))
+        .collect();
+    if discovered_items.len() < found {
+        warn!("scan of {:?} stopped at its quota: indexing {} of {} files", root, discovered_items.len(), found);
     }
 
     let item_count = discovered_items.len();
@@ -396,9 +417,16 @@ pub async fn scan_bfs_getdents(
     // This dramatically improves throughput by reducing await overhead
     const BATCH_SIZE: usize = 1000;
     let mut sent_count = 0;
+    let mut sent_bytes = 0u64;
     let mut batch = Vec::with_capacity(BATCH_SIZE.min(item_count));
     
     for item in discovered_items {
+        // Paused or canceled while waiting on a full queue
+        if !scan_running.load(std::sync::atomic::Ordering::Relaxed) {
+            info!("scan stopped while queueing: {:?}", root);
+            batch.clear();
+            break;
+        }
         batch.push(item);
         
         // Send batch when full or if this is the last item
@@ -409,9 +437,11 @@ pub async fn scan_bfs_getdents(
             let mut batch_iter = batch.drain(..);
             
             for item in batch_iter.by_ref() {
+                let size = item.size_bytes.max(0) as u64;
                 match tx.try_send(item) {
                     Ok(()) => {
                         sent_count += 1;
+                        sent_bytes += size;
                         gauges.discover.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                     }
                     Err(tokio::sync::mpsc::error::TrySendError::Full(item)) => {
@@ -421,12 +451,17 @@ pub async fn scan_bfs_getdents(
                         
                         // Send remaining items one-by-one with await (handles backpressure)
                         for item in remaining_items {
+                            if !scan_running.load(std::sync::atomic::Ordering::Relaxed) {
+                                break;
+                            }
+                            let size = item.size_bytes.max(0) as u64;
                             if tx.send(item).await.is_err() {
                                 warn!("Failed to send discovered item to channel, receiver dropped.");
                                 failed = true;
                                 break;
                             }
                             sent_count += 1;
+                            sent_bytes += size;
                             gauges.discover.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                         }
                         break;
@@ -447,17 +482,22 @@ pub async fn scan_bfs_getdents(
     // Send remaining items in final batch
     if !batch.is_empty() {
         for item in batch {
+            if !scan_running.load(std::sync::atomic::Ordering::Relaxed) {
+                break;
+            }
+            let size = item.size_bytes.max(0) as u64;
             if tx.send(item).await.is_err() {
                 warn!("Failed to send discovered item to channel, receiver dropped.");
                 break;
             }
             sent_count += 1;
+            sent_bytes += size;
             gauges.discover.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         }
     }
 
     info!("Phase 3 complete: sent {} files to processing pipeline. Processing (hashing, metadata extraction) will now begin.", sent_count);
     info!("Linux getdents64 scan complete: discovery finished, processing pipeline active");
-    Ok(())
+    Ok(ScanSummary { files: sent_count as u64, bytes: sent_bytes, quota_hit: counter.hit() })
 }
 
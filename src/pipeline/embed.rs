@@ -0,0 +1,488 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+#[cfg(feature = "semantic-search")]
+use image::DynamicImage;
+#[cfg(feature = "semantic-search")]
+use ort::session::Session;
+#[cfg(feature = "semantic-search")]
+use ort::value::Value;
+#[cfg(feature = "semantic-search")]
+use parking_lot::{Mutex, RwLock};
+#[cfg(feature = "semantic-search")]
+use std::path::PathBuf;
+#[cfg(feature = "semantic-search")]
+use std::sync::Arc;
+#[cfg(feature = "semantic-search")]
+use tracing::{info, warn};
+
+/// Stored with each vector, so switching models never mixes embedding spaces
+pub const CLIP_MODEL: &str = "clip-vit-base-patch32";
+/// Tokens the text encoder takes, including the start and end markers
+pub const CONTEXT_LEN: usize = 77;
+const START_OF_TEXT: i64 = 49406;
+const END_OF_TEXT: i64 = 49407;
+
+#[cfg(feature = "semantic-search")]
+const CLIP_BASE_URL: &str = "https://huggingface.co/Xenova/clip-vit-base-patch32/resolve/main";
+/// (file under the models directory, path under CLIP_BASE_URL)
+#[cfg(feature = "semantic-search")]
+const CLIP_FILES: [(&str, &str); 4] = [
+    ("clip_vision.onnx", "onnx/vision_model.onnx"),
+    ("clip_text.onnx", "onnx/text_model.onnx"),
+    ("clip_vocab.json", "vocab.json"),
+    ("clip_merges.txt", "merges.txt"),
+];
+#[cfg(feature = "semantic-search")]
+const IMAGE_SIZE: u32 = 224;
+#[cfg(feature = "semantic-search")]
+const IMAGE_MEAN: [f32; 3] = [0.481_454_66, 0.457_827_5, 0.408_210_73];
+#[cfg(feature = "semantic-search")]
+const IMAGE_STD: [f32; 3] = [0.268_629_54, 0.261_302_6, 0.275_777_1];
+/// Assets embedded per database round trip
+#[cfg(feature = "semantic-search")]
+const EMBED_BATCH: usize = 64;
+
+/// Printable stand-ins for every byte, so merges never see whitespace or control characters
+fn byte_chars() -> [char; 256] {
+    let mut chars = ['\0'; 256];
+    let mut extra = 0;
+    for (b, c) in chars.iter_mut().enumerate() {
+        let printable = matches!(b, 0x21..=0x7e | 0xa1..=0xac | 0xae..=0xff);
+        let code = if printable {
+            b as u32
+        } else {
+            extra += 1;
+            255 + extra
+        };
+        *c = char::from_u32(code).unwrap_or('\u{fffd}');
+    }
+    chars
+}
+
+fn contraction(rest: &str) -> Option<&str> {
+    ["'s", "'t", "'re", "'ve", "'m", "'ll", "'d"].into_iter().find(|c| rest.starts_with(c))
+}
+
+/// Split lowercased text into words, single digits, punctuation runs and contractions
+fn pretokenize(text: &str) -> Vec<String> {
+    let text = text.to_lowercase();
+    let mut words = Vec::new();
+    let mut rest = text.as_str();
+    while let Some(c) = rest.chars().next() {
+        let len = if let Some(suffix) = contraction(rest) {
+            suffix.len()
+        } else if c.is_alphabetic() {
+            rest.find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len())
+        } else if c.is_numeric() || c.is_whitespace() {
+            c.len_utf8()
+        } else {
+            rest.find(|c: char| c.is_alphanumeric() || c.is_whitespace()).unwrap_or(rest.len())
+        };
+        if !c.is_whitespace() {
+            words.push(rest[..len].to_string());
+        }
+        rest = &rest[len..];
+    }
+    words
+}
+
+/// CLIP's byte-level BPE tokenizer
+pub struct ClipTokenizer {
+    encoder: HashMap<String, i64>,
+    ranks: HashMap<(String, String), usize>,
+    byte_chars: [char; 256],
+}
+
+impl ClipTokenizer {
+    /// `merges` is the contents of merges.txt: one pair per line, best first
+    pub fn new(encoder: HashMap<String, i64>, merges: &str) -> Self {
+        let ranks = merges
+            .lines()
+            .filter(|l| !l.starts_with('#'))
+            .filter_map(|l| l.split_once(' '))
+            .enumerate()
+            .map(|(rank, (a, b))| ((a.to_string(), b.to_string()), rank))
+            .collect();
+        Self { encoder, ranks, byte_chars: byte_chars() }
+    }
+
+    pub fn load(vocab: &Path, merges: &Path) -> Result<Self> {
+        let encoder: HashMap<String, i64> = serde_json::from_slice(&std::fs::read(vocab).context("Failed to read CLIP vocabulary")?)
+            .context("Failed to parse CLIP vocabulary")?;
+        let merges = std::fs::read_to_string(merges).context("Failed to read CLIP merges")?;
+        Ok(Self::new(encoder, &merges))
+    }
+
+    fn bpe(&self, word: &str) -> Vec<String> {
+        let mut parts: Vec<String> = word.bytes().map(|b| self.byte_chars[b as usize].to_string()).collect();
+        if let Some(last) = parts.last_mut() {
+            last.push_str("</w>");
+        }
+        loop {
+            let best = parts
+                .windows(2)
+                .enumerate()
+                .filter_map(|(i, pair)| self.ranks.get(&(pair[0].clone(), pair[1].clone())).map(|rank| (*rank, i)))
+                .min();
+            let Some((_, i)) = best else { break };
+            let (a, b) = (parts[i].clone(), parts[i + 1].clone());
+            let mut merged = Vec::with_capacity(parts.len());
+            let mut j = 0;
+            while j < parts.len() {
+                if j + 1 < parts.len() && parts[j] == a && parts[j + 1] == b {
+                    merged.push(format!("{}{}", a, b));
+                    j += 2;
+                } else {
+                    merged.push(parts[j].clone());
+                    j += 1;
+                }
+            }
+            parts = merged;
+        }
+        parts
+    }
+
+    /// Token ids for `text`, wrapped in start/end markers and zero-padded to CONTEXT_LEN
+    pub fn encode(&self, text: &str) -> Vec<i64> {
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut ids = vec![START_OF_TEXT];
+        for word in pretokenize(&collapsed) {
+            ids.extend(self.bpe(&word).iter().filter_map(|p| self.encoder.get(p)));
+        }
+        ids.truncate(CONTEXT_LEN - 1);
+        ids.push(END_OF_TEXT);
+        ids.resize(CONTEXT_LEN, 0);
+        ids
+    }
+}
+
+/// Scale `v` to unit length, so a dot product is cosine similarity
+pub fn normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Image embeddings held in memory for brute-force nearest-neighbor search
+#[derive(Default)]
+pub struct ClipIndex {
+    positions: HashMap<i64, usize>,
+    ids: Vec<i64>,
+    vectors: Vec<Vec<f32>>,
+}
+
+impl ClipIndex {
+    /// Add or replace the vector for `asset_id`
+    pub fn insert(&mut self, asset_id: i64, mut vector: Vec<f32>) {
+        normalize(&mut vector);
+        match self.positions.get(&asset_id) {
+            Some(&pos) => self.vectors[pos] = vector,
+            None => {
+                self.positions.insert(asset_id, self.ids.len());
+                self.ids.push(asset_id);
+                self.vectors.push(vector);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Indexed assets, in insertion order
+    pub fn ids(&self) -> &[i64] {
+        &self.ids
+    }
+
+    /// The normalized vector stored for `asset_id`
+    pub fn get(&self, asset_id: i64) -> Option<&[f32]> {
+        self.positions.get(&asset_id).map(|&pos| self.vectors[pos].as_slice())
+    }
+
+    /// Every indexed asset by cosine similarity to `query`, best first
+    pub fn search(&self, query: &[f32]) -> Vec<(i64, f32)> {
+        let mut query = query.to_vec();
+        normalize(&mut query);
+        let mut scored: Vec<(i64, f32)> = self
+            .ids
+            .iter()
+            .zip(&self.vectors)
+            .filter(|(_, v)| v.len() == query.len())
+            .map(|(id, v)| (*id, v.iter().zip(&query).map(|(a, b)| a * b).sum()))
+            .collect();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored
+    }
+}
+
+/// CLIP image and text encoders
+#[cfg(feature = "semantic-search")]
+pub struct ClipProcessor {
+    vision: Mutex<Session>,
+    text: Mutex<Session>,
+    tokenizer: ClipTokenizer,
+}
+
+#[cfg(feature = "semantic-search")]
+impl ClipProcessor {
+    /// Fetch missing model files (unless SEEN_CLIP_AUTO_DOWNLOAD=0) and load them
+    pub async fn load(models_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(models_dir).context("Failed to create models directory")?;
+        let auto_dl = std::env::var("SEEN_CLIP_AUTO_DOWNLOAD")
+            .map(|v| !matches!(v.as_str(), "0" | "false" | "FALSE"))
+            .unwrap_or(true);
+        if auto_dl {
+            if let Err(e) = download_models(models_dir).await {
+                warn!("CLIP model auto-download failed: {}", e);
+            }
+        }
+        for (file, _) in CLIP_FILES {
+            if !models_dir.join(file).exists() {
+                anyhow::bail!("CLIP model file missing: {:?}", models_dir.join(file));
+            }
+        }
+        let vision = Session::builder()?
+            .commit_from_file(models_dir.join(CLIP_FILES[0].0))
+            .context("Failed to create CLIP vision session")?;
+        let text = Session::builder()?
+            .commit_from_file(models_dir.join(CLIP_FILES[1].0))
+            .context("Failed to create CLIP text session")?;
+        let tokenizer = ClipTokenizer::load(&models_dir.join(CLIP_FILES[2].0), &models_dir.join(CLIP_FILES[3].0))?;
+        info!("CLIP models loaded from {:?}", models_dir);
+        Ok(Self { vision: Mutex::new(vision), text: Mutex::new(text), tokenizer })
+    }
+
+    /// Resize the short side to 224, center crop, normalize (NCHW)
+    fn preprocess(image: &DynamicImage) -> ([i64; 4], Vec<f32>) {
+        let (w, h) = (image.width().max(1), image.height().max(1));
+        let scale = IMAGE_SIZE as f32 / w.min(h) as f32;
+        let (rw, rh) = (((w as f32 * scale).round() as u32).max(IMAGE_SIZE), ((h as f32 * scale).round() as u32).max(IMAGE_SIZE));
+        let resized = image
+            .resize_exact(rw, rh, image::imageops::FilterType::CatmullRom)
+            .crop_imm((rw - IMAGE_SIZE) / 2, (rh - IMAGE_SIZE) / 2, IMAGE_SIZE, IMAGE_SIZE)
+            .to_rgb8();
+        let plane = (IMAGE_SIZE * IMAGE_SIZE) as usize;
+        let mut data = vec![0f32; 3 * plane];
+        for (i, pixel) in resized.pixels().enumerate() {
+            for c in 0..3 {
+                data[c * plane + i] = (pixel[c] as f32 / 255.0 - IMAGE_MEAN[c]) / IMAGE_STD[c];
+            }
+        }
+        ([1, 3, IMAGE_SIZE as i64, IMAGE_SIZE as i64], data)
+    }
+
+    pub fn embed_image(&self, image_path: &Path) -> Result<Vec<f32>> {
+        let img = image::open(image_path).context(format!("Failed to open image: {:?}", image_path))?;
+        let (shape, data) = Self::preprocess(&img);
+        let mut session = self.vision.lock();
+        let input_name = session.inputs[0].name.clone();
+        let input = Value::from_array((shape.to_vec(), data)).context("Failed to create CLIP image tensor")?;
+        let outputs = session.run(ort::inputs![input_name => input]).context("CLIP image inference failed")?;
+        let key = outputs.keys().find(|k| *k == "image_embeds").or_else(|| outputs.keys().next()).context("CLIP image model has no outputs")?;
+        let (_, slice) = outputs[key].try_extract_tensor::<f32>().context("CLIP image output is not a float tensor")?;
+        let mut v = slice.to_vec();
+        normalize(&mut v);
+        Ok(v)
+    }
+
+    pub fn embed_text(&self, text: &str) -> Result<Vec<f32>> {
+        let ids = self.tokenizer.encode(text);
+        let len = ids.iter().position(|id| *id == END_OF_TEXT).map_or(CONTEXT_LEN, |p| p + 1);
+        let mask: Vec<i64> = (0..CONTEXT_LEN).map(|i| i64::from(i < len)).collect();
+        let mut session = self.text.lock();
+        let names: Vec<String> = session.inputs.iter().map(|i| i.name.clone()).collect();
+        let shape = vec![1i64, CONTEXT_LEN as i64];
+        let input_ids = Value::from_array((shape.clone(), ids)).context("Failed to create CLIP token tensor")?;
+        let outputs = if names.iter().any(|n| n == "attention_mask") {
+            let attention = Value::from_array((shape, mask)).context("Failed to create CLIP mask tensor")?;
+            session.run(ort::inputs!["input_ids" => input_ids, "attention_mask" => attention])
+        } else {
+            session.run(ort::inputs![names[0].clone() => input_ids])
+        }
+        .context("CLIP text inference failed")?;
+        let key = outputs.keys().find(|k| *k == "text_embeds").or_else(|| outputs.keys().next()).context("CLIP text model has no outputs")?;
+        let (_, slice) = outputs[key].try_extract_tensor::<f32>().context("CLIP text output is not a float tensor")?;
+        let mut v = slice.to_vec();
+        normalize(&mut v);
+        Ok(v)
+    }
+}
+
+#[cfg(feature = "semantic-search")]
+async fn download_models(models_dir: &Path) -> Result<()> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Ok(token) = std::env::var("HF_TOKEN") {
+        if !token.is_empty() {
+            headers.insert(reqwest::header::AUTHORIZATION, reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))?);
+        }
+    }
+    let client = reqwest::Client::builder().default_headers(headers).build().context("Failed to create HTTP client")?;
+    for (file, remote) in CLIP_FILES {
+        let path = models_dir.join(file);
+        if path.exists() {
+            continue;
+        }
+        let url = format!("{}/{}", CLIP_BASE_URL, remote);
+        info!("Downloading CLIP model file {}...", remote);
+        let response = client.get(&url).send().await.context(format!("Failed to download {}", url))?;
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to download {}: HTTP {}", url, response.status());
+        }
+        let bytes = response.bytes().await.context("Failed to read response body")?;
+        // Write aside and rename, so an interrupted download is never loaded
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, &bytes).context(format!("Failed to write file: {:?}", partial))?;
+        std::fs::rename(&partial, &path)?;
+        info!("Downloaded {:?} ({} bytes)", path, bytes.len());
+    }
+    Ok(())
+}
+
+/// Loaded encoders plus the in-memory index the search endpoint reads
+#[cfg(feature = "semantic-search")]
+pub struct SemanticSearch {
+    pub models_dir: PathBuf,
+    processor: RwLock<Option<Arc<ClipProcessor>>>,
+    pub index: RwLock<ClipIndex>,
+    /// Zero-shot labels attached as tags from the same image vectors
+    #[cfg(feature = "auto-tagging")]
+    pub auto_tags: crate::pipeline::autotag::AutoTagger,
+}
+
+#[cfg(feature = "semantic-search")]
+impl SemanticSearch {
+    pub fn new(models_dir: PathBuf) -> Self {
+        Self {
+            models_dir,
+            processor: RwLock::new(None),
+            index: RwLock::new(ClipIndex::default()),
+            #[cfg(feature = "auto-tagging")]
+            auto_tags: crate::pipeline::autotag::AutoTagger::default(),
+        }
+    }
+
+    /// None until the models have loaded
+    pub fn processor(&self) -> Option<Arc<ClipProcessor>> {
+        self.processor.read().clone()
+    }
+
+    /// Load the stored vectors and the models; search stays unavailable if the models can't load
+    pub async fn initialize(&self, pool: &crate::db::DbPool) {
+        match pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e)).and_then(|conn| crate::db::query::clip_embeddings(&conn, CLIP_MODEL)) {
+            Ok(rows) => {
+                let mut index = self.index.write();
+                for (asset_id, vector) in rows {
+                    index.insert(asset_id, vector);
+                }
+                info!("Semantic index loaded: {} assets", index.len());
+            }
+            Err(e) => warn!("Failed to load stored CLIP embeddings: {}", e),
+        }
+        match ClipProcessor::load(&self.models_dir).await {
+            Ok(processor) => *self.processor.write() = Some(Arc::new(processor)),
+            Err(e) => warn!("CLIP models not loaded: {}", e),
+        }
+    }
+}
+
+/// Embed images missing a vector, one pass over the library every `idle` when caught up
+#[cfg(feature = "semantic-search")]
+pub fn start_embed_worker(state: Arc<crate::AppState>, idle: std::time::Duration) {
+    tokio::spawn(async move {
+        let mut after = 0i64;
+        loop {
+            state.gauges.wait_while_paused().await;
+            let Some(processor) = state.semantic.processor() else {
+                tokio::time::sleep(idle).await;
+                continue;
+            };
+            let state_c = state.clone();
+            let batch = tokio::task::spawn_blocking(move || -> Result<Option<i64>> {
+                let conn = state_c.pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+                let pending = crate::db::query::assets_missing_clip_embedding(&conn, CLIP_MODEL, after, EMBED_BATCH)?;
+                let Some(&(last, _)) = pending.last() else {
+                    return Ok(None);
+                };
+                for (asset_id, sha) in pending {
+                    // The 1600px preview is plenty for a 224px encoder; assets without one are retried next pass
+                    let preview = crate::pipeline::thumb::thumb_path(&state_c.paths.derived, &sha, 1600);
+                    if !preview.exists() {
+                        continue;
+                    }
+                    match processor.embed_image(&preview) {
+                        Ok(vector) => {
+                            crate::db::writer::upsert_clip_embedding(&conn, asset_id, CLIP_MODEL, &vector, chrono::Utc::now().timestamp())?;
+                            #[cfg(feature = "auto-tagging")]
+                            match state_c.semantic.auto_tags.predict(&processor, &vector) {
+                                Ok(Some(tags)) => crate::db::writer::set_auto_tags(&conn, asset_id, &tags)?,
+                                Ok(None) => {}
+                                Err(e) => warn!("Auto-tagging failed for asset {}: {}", asset_id, e),
+                            }
+                            state_c.semantic.index.write().insert(asset_id, vector);
+                        }
+                        Err(e) => warn!("CLIP embedding failed for asset {}: {}", asset_id, e),
+                    }
+                }
+                Ok(Some(last))
+            })
+            .await;
+            match batch {
+                Ok(Ok(Some(last))) => after = last,
+                Ok(Ok(None)) => {
+                    after = 0;
+                    tokio::time::sleep(idle).await;
+                }
+                Ok(Err(e)) => {
+                    warn!("Semantic embedding pass failed: {}", e);
+                    tokio::time::sleep(idle).await;
+                }
+                Err(e) => {
+                    warn!("Semantic embedding task panicked: {}", e);
+                    tokio::time::sleep(idle).await;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_tokenizer() {
+        assert_eq!(byte_chars()[b' ' as usize], '\u{120}');
+        assert_eq!(byte_chars()[b'a' as usize], 'a');
+        assert_eq!(pretokenize("Dog's  beach, 2019!"), vec!["dog", "'s", "beach", ",", "2", "0", "1", "9", "!"]);
+
+        let vocab = [("d", 0), ("o", 1), ("do", 2), ("dog</w>", 3), ("'s</w>", 4), ("1</w>", 5), ("!</w>", 6), ("g</w>", 7)];
+        let encoder = vocab.iter().map(|(t, id)| (t.to_string(), *id)).collect();
+        let tokenizer = ClipTokenizer::new(encoder, "#version: 0.2\nd o\ndo g</w>\n' s</w>\n");
+        let ids = tokenizer.encode("Dog's 1!");
+        assert_eq!(ids.len(), CONTEXT_LEN);
+        assert_eq!(&ids[..6], &[START_OF_TEXT, 3, 4, 5, 6, END_OF_TEXT]);
+        assert!(ids[6..].iter().all(|id| *id == 0));
+        assert_eq!(tokenizer.bpe("god"), vec!["g", "o", "d</w>"]);
+    }
+
+    #[test]
+    fn test_clip_index_search() {
+        let mut index = ClipIndex::default();
+        index.insert(1, vec![1.0, 0.0, 0.0]);
+        index.insert(2, vec![0.0, 2.0, 0.0]);
+        index.insert(3, vec![1.0, 1.0, 0.0]);
+        let ranked = index.search(&[0.0, 1.0, 0.1]);
+        assert_eq!(ranked.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![2, 3, 1]);
+        assert!((ranked[0].1 - 0.995).abs() < 0.01);
+        // Re-embedding replaces the vector rather than adding a row
+        index.insert(1, vec![0.0, 1.0, 0.0]);
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.search(&[0.0, 1.0, 0.0])[0].1, 1.0);
+    }
+}
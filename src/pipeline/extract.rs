@@ -0,0 +1,179 @@
+//! User-defined regexes that pull metadata out of file and folder names, for files whose
+//! embedded metadata is missing, such as WhatsApp exports and scanner output.
+//!
+//! Named capture groups say what a match means: `year`, `month`, `day` and optionally `hour`,
+//! `minute`, `second` make a date; `camera` fills the camera model; any other group is stored as
+//! an asset property under its own name, e.g. `(?P<event>[^/]+)` sets the `event` property.
+
+use chrono::{NaiveDate, NaiveTime};
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Extractors shared by the metadata workers and the settings API
+pub type SharedExtractors = Arc<RwLock<Extractors>>;
+
+/// Which part of the path a pattern is matched against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchOn {
+    /// The file name with its extension
+    #[default]
+    Filename,
+    /// The name of the folder holding the file
+    Folder,
+    /// The full path, with `/` separators on every platform
+    Path,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Extractor {
+    /// Shown in the settings UI
+    #[serde(default)]
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub match_on: MatchOn,
+}
+
+const DATE_GROUPS: [&str; 6] = ["year", "month", "day", "hour", "minute", "second"];
+
+/// Extractors with their patterns compiled, in the order they are tried
+#[derive(Debug, Clone, Default)]
+pub struct Extractors {
+    extractors: Vec<(Extractor, Regex)>,
+}
+
+impl Extractors {
+    /// Compile every pattern; the error names the first one that doesn't compile or captures nothing usable
+    pub fn new(extractors: Vec<Extractor>) -> Result<Self, String> {
+        let compiled = extractors
+            .into_iter()
+            .map(|extractor| {
+                let label = if extractor.name.is_empty() { extractor.pattern.clone() } else { extractor.name.clone() };
+                let regex = Regex::new(&extractor.pattern).map_err(|e| format!("Invalid pattern for '{}': {}", label, e))?;
+                let names: Vec<&str> = regex.capture_names().flatten().collect();
+                if names.is_empty() {
+                    return Err(format!("Pattern for '{}' has no named groups, e.g. (?P<year>\\d{{4}})", label));
+                }
+                let date_parts = names.iter().filter(|n| DATE_GROUPS.contains(n)).count();
+                if date_parts > 0 && !["year", "month", "day"].iter().all(|g| names.contains(g)) {
+                    return Err(format!("Pattern for '{}' needs year, month and day groups to make a date", label));
+                }
+                Ok((extractor, regex))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { extractors: compiled })
+    }
+
+    /// Saved extractors whose patterns no longer compile are dropped with a warning
+    pub fn load(extractors: Vec<Extractor>) -> Self {
+        let valid = extractors
+            .into_iter()
+            .filter(|extractor| match Self::new(vec![extractor.clone()]) {
+                Ok(_) => true,
+                Err(e) => {
+                    tracing::warn!("Ignoring filename extractor: {}", e);
+                    false
+                }
+            })
+            .collect();
+        Self::new(valid).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extractors.is_empty()
+    }
+
+    pub fn extractors(&self) -> Vec<Extractor> {
+        self.extractors.iter().map(|(extractor, _)| extractor.clone()).collect()
+    }
+
+    /// Run every extractor over a file's path; for each value the first extractor that yields it wins
+    pub fn extract(&self, path: &str, dirname: &str, filename: &str) -> Extracted {
+        let mut out = Extracted::default();
+        let path = path.replace('\\', "/");
+        for (extractor, regex) in &self.extractors {
+            let subject = match extractor.match_on {
+                MatchOn::Filename => filename,
+                MatchOn::Folder => dirname.rsplit(['/', '\\']).next().unwrap_or(dirname),
+                MatchOn::Path => path.as_str(),
+            };
+            let Some(caps) = regex.captures(subject) else {
+                continue;
+            };
+            if out.date.is_none() {
+                out.date = capture_date(&caps);
+            }
+            for name in regex.capture_names().flatten().filter(|n| !DATE_GROUPS.contains(n)) {
+                let Some(value) = caps.name(name).map(|m| m.as_str().trim()).filter(|v| !v.is_empty()) else {
+                    continue;
+                };
+                if name == "camera" {
+                    out.camera.get_or_insert_with(|| value.to_string());
+                } else if !out.properties.iter().any(|(key, _)| key.eq_ignore_ascii_case(name)) {
+                    out.properties.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// What the extractors found for one file
+#[derive(Debug, Default, PartialEq)]
+pub struct Extracted {
+    /// Wall-clock time stored as UTC, like EXIF dates
+    pub date: Option<i64>,
+    pub camera: Option<String>,
+    pub properties: Vec<(String, String)>,
+}
+
+fn capture_date(caps: &regex::Captures) -> Option<i64> {
+    let part = |name: &str| caps.name(name).and_then(|m| m.as_str().parse::<u32>().ok());
+    let mut year = part("year")? as i32;
+    // Two-digit years, as in `DD-MM-YY` scanner output
+    if year < 100 {
+        year += if year < 70 { 2000 } else { 1900 };
+    }
+    let date = NaiveDate::from_ymd_opt(year, part("month")?, part("day")?)?;
+    let time = NaiveTime::from_hms_opt(part("hour").unwrap_or(0), part("minute").unwrap_or(0), part("second").unwrap_or(0))?;
+    Some(date.and_time(time).and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extractor(pattern: &str, match_on: MatchOn) -> Extractor {
+        Extractor { name: String::new(), pattern: pattern.to_string(), match_on }
+    }
+
+    #[test]
+    fn test_extractors() {
+        let extractors = Extractors::new(vec![
+            extractor(r"^(?P<year>\d{4})-(?P<month>\d\d)-(?P<day>\d\d) (?P<event>.+)$", MatchOn::Folder),
+            extractor(r"^scan_(?P<camera>[A-Za-z0-9]+)_(?P<day>\d\d)(?P<month>\d\d)(?P<year>\d\d)", MatchOn::Filename),
+            extractor(r"/albums/(?P<event>[^/]+)/", MatchOn::Path),
+        ])
+        .unwrap();
+
+        let found = extractors.extract("C:\\photos\\2019-06-01 Wedding\\scan_EPSON_150798.jpg", "C:\\photos\\2019-06-01 Wedding", "scan_EPSON_150798.jpg");
+        assert_eq!(found.date, Some(NaiveDate::from_ymd_opt(2019, 6, 1).unwrap().and_time(NaiveTime::MIN).and_utc().timestamp()));
+        assert_eq!(found.camera.as_deref(), Some("EPSON"));
+        assert_eq!(found.properties, vec![("event".to_string(), "Wedding".to_string())]);
+
+        let found = extractors.extract("/albums/Trip/scan_HP_310212.tif", "/albums/Trip", "scan_HP_310212.tif");
+        assert_eq!(found.date, None, "31/02 is not a date");
+        assert_eq!(found.camera.as_deref(), Some("HP"));
+        assert_eq!(found.properties, vec![("event".to_string(), "Trip".to_string())]);
+        assert_eq!(extractors.extract("/x/IMG_1.jpg", "/x", "IMG_1.jpg"), Extracted::default());
+
+        assert!(Extractors::new(vec![extractor("(unclosed", MatchOn::Filename)]).is_err());
+        assert!(Extractors::new(vec![extractor(r"\d+", MatchOn::Filename)]).is_err());
+        assert!(Extractors::new(vec![extractor(r"(?P<year>\d{4})", MatchOn::Filename)]).is_err());
+        let loaded = Extractors::load(vec![extractor("(bad", MatchOn::Path), extractor(r"(?P<event>.+)", MatchOn::Folder)]);
+        assert_eq!(loaded.extractors().len(), 1);
+    }
+}
@@ -3,11 +3,11 @@ use anyhow::{Context, Result};
 #[cfg(feature = "facial-recognition")]
 use image::DynamicImage;
 #[cfg(feature = "facial-recognition")]
+use ort::execution_providers::{CUDAExecutionProvider, CoreMLExecutionProvider, DirectMLExecutionProvider, ExecutionProvider, TensorRTExecutionProvider};
+#[cfg(feature = "facial-recognition")]
 use ort::session::Session;
 #[cfg(feature = "facial-recognition")]
 use ort::value::Value;
-// Note: ExecutionProvider API may vary by ort version
-// If CUDA is not available, we'll skip GPU acceleration
 use parking_lot::Mutex;
 #[cfg(feature = "facial-recognition")]
 use serde::{Deserialize, Serialize};
@@ -42,18 +42,234 @@ const SCRFD_MODEL_URL_GH: &str = "https://github.com/deepinsight/insightface/rel
 #[cfg(feature = "facial-recognition")]
 const ARCFACE_MODEL_URL_PRIMARY: &str = "https://huggingface.co/maze/faceX/resolve/e010b5098c3685fd00b22dd2aec6f37320e3d850/w600k_r50.onnx";
 
+// Optional pet models, looked for in the models directory; SEEN_PET_DETECT_MODEL_URL and
+// SEEN_PET_EMBED_MODEL_URL download them when missing. Pets are recognized once both load.
+//
+// The detector takes a 640x640 RGB image scaled to [0, 1] (padded at the right and bottom, like
+// SCRFD) and returns rows of (x1, y1, x2, y2, score, class) in input pixels, as end-to-end YOLO
+// exports do. The embedder takes a 224x224 RGB crop normalized with the ImageNet mean and std.
+#[cfg(feature = "facial-recognition")]
+const PET_DETECT_MODEL: &str = "pet_detect.onnx";
+#[cfg(feature = "facial-recognition")]
+const PET_EMBED_MODEL: &str = "pet_embed.onnx";
+
+/// What a detected face belongs to; persons and pets are clustered and listed apart
+#[cfg(feature = "facial-recognition")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Subject {
+    #[default]
+    Person,
+    Pet,
+}
+
+#[cfg(feature = "facial-recognition")]
+impl Subject {
+    pub const ALL: [Subject; 2] = [Subject::Person, Subject::Pet];
+
+    /// Stored in `persons.subject_type` and `face_embeddings.subject_type`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Subject::Person => "person",
+            Subject::Pet => "pet",
+        }
+    }
+}
+
+/// Frames sampled from each video (SEEN_FACE_VIDEO_FRAMES, default 8; 0 leaves videos out)
+#[cfg(feature = "facial-recognition")]
+pub fn video_frame_count() -> usize {
+    std::env::var("SEEN_FACE_VIDEO_FRAMES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(8)
+        .min(64)
+}
+
+/// Video formats faces are looked for in, unless excluded like image extensions
+#[cfg(feature = "facial-recognition")]
+pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "m4v", "mkv", "avi", "webm", "3gp", "mts", "m2ts", "wmv"];
+
+/// Faces in different frames of one video closer than this are the same person, and stored once
+#[cfg(feature = "facial-recognition")]
+const VIDEO_FACE_DEDUP_DISTANCE: f32 = 0.45;
+
+#[cfg(feature = "facial-recognition")]
+pub fn is_video_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// The keyframe at or after `frame_ms` into the video, full size. Face boxes found in it are
+/// stored with `frame_ms`, so thumbnails crop the same frame.
+#[cfg(feature = "facial-recognition")]
+pub fn video_frame(path: &Path, frame_ms: i64) -> Result<DynamicImage> {
+    let args: Vec<String> = vec![
+        "-v".into(), "error".into(),
+        "-skip_frame".into(), "nokey".into(),
+        "-ss".into(), format!("{:.3}", frame_ms as f64 / 1000.0),
+        "-i".into(), path.to_string_lossy().to_string(),
+        "-frames:v".into(), "1".into(),
+        "-f".into(), "image2pipe".into(),
+        "-vcodec".into(), "mjpeg".into(),
+        "-q:v".into(), "2".into(),
+        "pipe:1".into(),
+    ];
+    let output = crate::utils::ffmpeg::run_ffmpeg_with_timeout(args, std::time::Duration::from_secs(30))?;
+    if !output.status.success() || output.stdout.is_empty() {
+        anyhow::bail!("ffmpeg found no frame at {} ms in {:?}: {}", frame_ms, path, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    image::load_from_memory(&output.stdout).context(format!("Failed to decode frame of {:?}", path))
+}
+
+/// Keep one face per person seen in a video: the most confident detection among close matches
+#[cfg(feature = "facial-recognition")]
+pub fn dedupe_video_faces(mut faces: Vec<FaceEmbedding>, max_distance: f32) -> Vec<FaceEmbedding> {
+    faces.sort_by(|a, b| b.bbox.confidence.total_cmp(&a.bbox.confidence));
+    let embeddings: Vec<Vec<f32>> = faces.iter().map(|f| f.embedding.clone()).collect();
+    let mut keep: Vec<usize> = group_similar_faces(&embeddings, max_distance).into_iter().map(|members| members[0]).collect();
+    keep.sort_unstable();
+    faces.into_iter().enumerate().filter(|(i, _)| keep.binary_search(i).is_ok()).map(|(_, f)| f).collect()
+}
+
 #[cfg(feature = "facial-recognition")]
 pub struct FaceJob {
     pub asset_id: i64,
     pub image_path: PathBuf,
+    /// Jobs queued before a flush are dropped, see [`crate::pipeline::QueueGauges::flush`]
+    pub queued_at: std::time::Instant,
+}
+
+/// Hardware the face models run on, through ONNX Runtime execution providers
+#[cfg(feature = "facial-recognition")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Accelerator {
+    TensorRT,
+    Cuda,
+    DirectML,
+    CoreML,
+    Cpu,
+}
+
+#[cfg(feature = "facial-recognition")]
+impl Accelerator {
+    /// Tried by `SEEN_FACE_USE_GPU=1`; TensorRT spends minutes building engines on first use, so it is only used when listed
+    const GPU_DEFAULT: [Accelerator; 3] = [Accelerator::Cuda, Accelerator::DirectML, Accelerator::CoreML];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Accelerator::TensorRT => "tensorrt",
+            Accelerator::Cuda => "cuda",
+            Accelerator::DirectML => "directml",
+            Accelerator::CoreML => "coreml",
+            Accelerator::Cpu => "cpu",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        [Accelerator::TensorRT, Accelerator::Cuda, Accelerator::DirectML, Accelerator::CoreML, Accelerator::Cpu]
+            .into_iter()
+            .find(|a| a.as_str().eq_ignore_ascii_case(s.trim()))
+    }
+
+    /// TensorRT keeps its built engines next to the models so later starts are quick
+    fn provider(self, models_dir: &Path) -> Option<Box<dyn ExecutionProvider>> {
+        match self {
+            Accelerator::TensorRT => {
+                let cache = models_dir.join("tensorrt");
+                Some(Box::new(
+                    TensorRTExecutionProvider::default()
+                        .with_engine_cache(true)
+                        .with_engine_cache_path(cache.display())
+                        .with_timing_cache(true)
+                        .with_timing_cache_path(cache.display()),
+                ))
+            }
+            Accelerator::Cuda => Some(Box::new(CUDAExecutionProvider::default())),
+            Accelerator::DirectML => Some(Box::new(DirectMLExecutionProvider::default())),
+            Accelerator::CoreML => Some(Box::new(CoreMLExecutionProvider::default())),
+            Accelerator::Cpu => None,
+        }
+    }
+}
+
+/// Execution providers to try, best first: `SEEN_FACE_EXECUTION_PROVIDERS` (e.g. `tensorrt,cuda`) when
+/// set, otherwise the usual GPU ones with `SEEN_FACE_USE_GPU=1`. Empty runs on the CPU.
+#[cfg(feature = "facial-recognition")]
+fn preferred_accelerators() -> Vec<Accelerator> {
+    if let Ok(list) = std::env::var("SEEN_FACE_EXECUTION_PROVIDERS") {
+        return list
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .filter_map(|s| {
+                let accelerator = Accelerator::parse(s);
+                if accelerator.is_none() {
+                    warn!("Ignoring unknown execution provider {:?} in SEEN_FACE_EXECUTION_PROVIDERS", s.trim());
+                }
+                accelerator
+            })
+            .take_while(|a| *a != Accelerator::Cpu)
+            .collect();
+    }
+    let use_gpu = std::env::var("SEEN_FACE_USE_GPU")
+        .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE"))
+        .unwrap_or(false);
+    if use_gpu { Accelerator::GPU_DEFAULT.to_vec() } else { Vec::new() }
+}
+
+/// Open a model on every preferred execution provider that registers, or on the CPU when none does
+/// or the session can't be created on them. Returns the provider the session runs on first.
+#[cfg(feature = "facial-recognition")]
+fn commit_session(path: &Path, preferred: &[Accelerator], models_dir: &Path) -> Result<(Session, Accelerator)> {
+    if !preferred.is_empty() {
+        match commit_accelerated(path, preferred, models_dir) {
+            Ok(Some(found)) => return Ok(found),
+            Ok(None) => warn!("None of the execution providers {:?} is available for {:?}; using CPU", preferred, path),
+            Err(e) => warn!("Could not open {:?} on {:?}, using CPU: {}", path, preferred, e),
+        }
+    }
+    let session = Session::builder()?
+        .commit_from_file(path)
+        .context(format!("Failed to create session for {:?}", path))?;
+    Ok((session, Accelerator::Cpu))
+}
+
+#[cfg(feature = "facial-recognition")]
+fn commit_accelerated(path: &Path, preferred: &[Accelerator], models_dir: &Path) -> Result<Option<(Session, Accelerator)>> {
+    let mut builder = Session::builder()?;
+    let mut registered = Vec::new();
+    for &accelerator in preferred {
+        let Some(provider) = accelerator.provider(models_dir).filter(|p| p.supported_by_platform()) else {
+            continue;
+        };
+        match provider.register(&mut builder) {
+            Ok(()) => registered.push(accelerator),
+            Err(e) => info!("{} execution provider unavailable: {}", accelerator.as_str(), e),
+        }
+    }
+    let Some(&first) = registered.first() else {
+        return Ok(None);
+    };
+    // DirectML can't run with memory patterns or parallel execution
+    if registered.contains(&Accelerator::DirectML) {
+        builder = builder.with_memory_pattern(false)?.with_parallel_execution(false)?;
+    }
+    Ok(Some((builder.commit_from_file(path)?, first)))
 }
 
 #[cfg(feature = "facial-recognition")]
 pub struct FaceProcessor {
     pub models_dir: PathBuf,
-    use_gpu: bool,
+    /// Execution providers to try, best first; empty means CPU only
+    accelerators: Vec<Accelerator>,
+    /// What the face models ended up running on
+    accelerator: Accelerator,
     scrfd_session: Option<Mutex<Session>>,
     arcface_session: Option<Mutex<Session>>,
+    pet_detect_session: Option<Mutex<Session>>,
+    pet_embed_session: Option<Mutex<Session>>,
 }
 
 #[cfg(feature = "facial-recognition")]
@@ -67,6 +283,12 @@ pub struct FaceBbox {
     pub x2: f32,
     pub y2: f32,
     pub confidence: f32,
+    /// Position in a video of the frame the face was found in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_ms: Option<i64>,
+    /// Eyes, nose tip and mouth corners in image pixels, when the detector gives them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub landmarks: Option<[[f32; 2]; 5]>,
 }
 
 #[cfg(feature = "facial-recognition")]
@@ -75,6 +297,7 @@ pub struct FaceEmbedding {
     pub embedding: Vec<f32>,
     pub bbox: FaceBbox,
     pub asset_id: i64,
+    pub subject: Subject,
 }
 
 #[cfg(feature = "facial-recognition")]
@@ -82,17 +305,20 @@ impl FaceProcessor {
     pub fn new(models_dir: PathBuf) -> Self {
         Self {
             models_dir,
-            use_gpu: std::env::var("SEEN_FACE_USE_GPU")
-                .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE"))
-                .unwrap_or(false),
+            accelerators: preferred_accelerators(),
+            accelerator: Accelerator::Cpu,
             scrfd_session: None,
             arcface_session: None,
+            pet_detect_session: None,
+            pet_embed_session: None,
         }
     }
 
     // Lightweight accessors for model load state
     pub fn scrfd_loaded(&self) -> bool { self.scrfd_session.is_some() }
     pub fn arcface_loaded(&self) -> bool { self.arcface_session.is_some() }
+    pub fn pets_loaded(&self) -> bool { self.pet_detect_session.is_some() && self.pet_embed_session.is_some() }
+    pub fn accelerator(&self) -> Accelerator { self.accelerator }
 
     pub async fn initialize(&mut self) -> Result<()> {
         std::fs::create_dir_all(&self.models_dir)
@@ -112,6 +338,12 @@ impl FaceProcessor {
         if let Err(e) = self.load_models().await {
             warn!("Face models not loaded: {}", e);
         }
+        if auto_dl {
+            if let Err(e) = self.download_pet_models().await {
+                warn!("Pet model download failed: {}", e);
+            }
+        }
+        self.load_pet_models();
         Ok(())
     }
 
@@ -136,6 +368,27 @@ impl FaceProcessor {
         Ok(())
     }
 
+    /// Fetch the pet models whose URL is configured and that aren't in the models directory yet
+    async fn download_pet_models(&self) -> Result<()> {
+        let wanted: Vec<(String, PathBuf)> = [("SEEN_PET_DETECT_MODEL_URL", PET_DETECT_MODEL), ("SEEN_PET_EMBED_MODEL_URL", PET_EMBED_MODEL)]
+            .into_iter()
+            .filter_map(|(var, file)| {
+                let url = std::env::var(var).ok().filter(|u| !u.trim().is_empty())?;
+                let path = self.models_dir.join(file);
+                (!path.exists()).then_some((url, path))
+            })
+            .collect();
+        if wanted.is_empty() {
+            return Ok(());
+        }
+        let client = self.create_http_client()?;
+        for (url, path) in wanted {
+            info!("Downloading pet model to {:?}...", path);
+            self.download_file(&client, &url, &path).await?;
+        }
+        Ok(())
+    }
+
     fn create_http_client(&self) -> Result<reqwest::Client> {
         let mut headers = reqwest::header::HeaderMap::new();
         if let Ok(token) = std::env::var("HF_TOKEN") {
@@ -198,33 +451,47 @@ impl FaceProcessor {
             );
         }
 
-        let scrfd_builder = Session::builder()?;
-        let arc_builder = Session::builder()?;
-
-        if self.use_gpu {
-            info!("Attempting to use GPU for face models.");
-            // TODO: Fix GPU execution provider configuration
-            // The ExecutionProvider API in ort 2.0.0-rc.10 may require a different approach
-            // For now, GPU configuration is disabled to allow compilation
-            // See ort crate documentation for the correct way to configure CUDA execution providers
-            warn!("GPU support temporarily disabled - using CPU. ExecutionProvider API needs to be updated for ort 2.0.0-rc.10");
+        if self.accelerators.is_empty() {
+            info!("Using CPU for face models (set SEEN_FACE_USE_GPU=1 to try the GPU).");
         } else {
-            info!("Using CPU for face models (GPU disabled by config).");
+            info!("Trying execution providers {:?} for face models.", self.accelerators);
         }
 
-        let scrfd = scrfd_builder
-            .commit_from_file(&scrfd_path)
-            .context("Failed to create SCRFD session")?;
-        let arc = arc_builder
-            .commit_from_file(&arcface_path)
-            .context("Failed to create ArcFace session")?;
+        let (scrfd, scrfd_on) = commit_session(&scrfd_path, &self.accelerators, &self.models_dir).context("Failed to create SCRFD session")?;
+        let (arc, arc_on) = commit_session(&arcface_path, &self.accelerators, &self.models_dir).context("Failed to create ArcFace session")?;
 
         self.scrfd_session = Some(Mutex::new(scrfd));
         self.arcface_session = Some(Mutex::new(arc));
-        info!("Face models loaded: SCRFD={:?} ArcFace={:?}", scrfd_path, arcface_path);
+        // Detection does most of the work, so its provider is the one reported
+        self.accelerator = scrfd_on;
+        info!(
+            "Face models loaded: SCRFD={:?} on {} ArcFace={:?} on {}",
+            scrfd_path, scrfd_on.as_str(), arcface_path, arc_on.as_str()
+        );
         Ok(())
     }
 
+    /// Load the pet detector and embedder when both are present; pets are skipped otherwise
+    fn load_pet_models(&mut self) {
+        let detect_path = self.models_dir.join(PET_DETECT_MODEL);
+        let embed_path = self.models_dir.join(PET_EMBED_MODEL);
+        if !detect_path.exists() || !embed_path.exists() {
+            info!("Pet models not found in {:?}; pets won't be recognized", self.models_dir);
+            return;
+        }
+        let load = |path: &Path| -> Result<Session> {
+            commit_session(path, &self.accelerators, &self.models_dir).map(|(session, _)| session)
+        };
+        match (load(&detect_path), load(&embed_path)) {
+            (Ok(detect), Ok(embed)) => {
+                self.pet_detect_session = Some(Mutex::new(detect));
+                self.pet_embed_session = Some(Mutex::new(embed));
+                info!("Pet models loaded: detector={:?} embedder={:?}", detect_path, embed_path);
+            }
+            (Err(e), _) | (_, Err(e)) => warn!("Pet models not loaded: {}", e),
+        }
+    }
+
     fn preprocess_scrfd(&self, image: &DynamicImage) -> Result<ScrfdPreprocessResult> {
         // Resize with padding to 640x640 (NCHW), normalize to [-1, 1]
         let (ow, oh) = (image.width() as f32, image.height() as f32);
@@ -260,8 +527,12 @@ impl FaceProcessor {
     }
 
     fn preprocess_arcface(&self, face_crop: &DynamicImage) -> Result<([i64; 4], Vec<f32>)> {
-        let resized = face_crop.resize_exact(112, 112, image::imageops::FilterType::Triangle);
-        let rgb = resized.to_rgb8();
+        // Aligned crops are already 112×112
+        let rgb = if face_crop.width() == 112 && face_crop.height() == 112 {
+            face_crop.to_rgb8()
+        } else {
+            face_crop.resize_exact(112, 112, image::imageops::FilterType::Triangle).to_rgb8()
+        };
         let mut data = Vec::with_capacity(3 * 112 * 112);
         for c in 0..3 {
             for y in 0..112u32 {
@@ -420,6 +691,12 @@ impl FaceProcessor {
                 // which makes it hard to separate.
                 // Let's try to get the tensors again properly.
                 
+                // Keypoints (bnkps models): five (dx, dy) pairs per anchor, in strides from the anchor
+                let kps = outputs
+                    .get(format!("kps_{}", stride_str).as_str())
+                    .and_then(|v| v.try_extract_tensor::<f32>().ok())
+                    .map(|(_, data)| data);
+
                 let (scores, boxes, height, width) = if let (Some(sv), Some(bv)) = (score_tensor_opt, bbox_tensor_opt) {
                     if let (Ok((s_shape, s_data)), Ok((b_shape, b_data))) = (sv.try_extract_tensor::<f32>(), bv.try_extract_tensor::<f32>()) {
                         // Shape is typically [1, H*W, 1] or [H*W, 1] for scores
@@ -487,7 +764,11 @@ impl FaceProcessor {
                         let h_px = y2 - y1;
                         if w_px < 8.0 || h_px < 8.0 { continue; }
                         
-                        raw.push(FaceBbox { x1, y1, x2, y2, confidence: conf });
+                        let landmarks = kps.filter(|k| k.len() >= (idx + 1) * 10).map(|k| {
+                            let k = &k[idx * 10..(idx + 1) * 10];
+                            std::array::from_fn(|p| [(cx + k[2 * p] * stride) / scale, (cy + k[2 * p + 1] * stride) / scale])
+                        });
+                        raw.push(FaceBbox { x1, y1, x2, y2, confidence: conf, frame_ms: None, landmarks });
                     }
                 }
             }
@@ -540,7 +821,7 @@ impl FaceProcessor {
                              let y2 = ((cy + bb) / scale).max(0.0).min(img_h);
                              
                              if x2 > x1 && y2 > y1 && (x2-x1) >= 8.0 && (y2-y1) >= 8.0 {
-                                 raw.push(FaceBbox { x1, y1, x2, y2, confidence: conf });
+                                 raw.push(FaceBbox { x1, y1, x2, y2, confidence: conf, frame_ms: None, landmarks: None });
                              }
                          } else {
                              warn!("SCRFD fallback: bounds check failed for anchor {} (b={})", i, b);
@@ -565,6 +846,99 @@ impl FaceProcessor {
         Ok(vec![])
     }
 
+    /// Pet faces in the image, in image pixels; see PET_DETECT_MODEL for what the model returns
+    pub fn detect_pets(&self, image: &DynamicImage) -> Result<Vec<FaceBbox>> {
+        let mut session_guard = self
+            .pet_detect_session
+            .as_ref()
+            .context("Pet detection model not loaded")?
+            .lock();
+        let (ow, oh) = (image.width() as f32, image.height() as f32);
+        let scale = 640.0 / ow.max(oh);
+        let resized = image.resize_exact((ow * scale) as u32, (oh * scale) as u32, image::imageops::FilterType::Triangle);
+        let mut padded = image::DynamicImage::new_rgb8(640, 640);
+        image::imageops::overlay(&mut padded, &resized, 0, 0);
+        let rgb = padded.to_rgb8();
+        let mut data = Vec::with_capacity(3 * 640 * 640);
+        for c in 0..3 {
+            for p in rgb.pixels() {
+                data.push(p[c] as f32 / 255.0);
+            }
+        }
+
+        let input_name = session_guard.inputs[0].name.clone();
+        let input = Value::from_array((vec![1i64, 3, 640, 640], data))
+            .context("Failed to create pet detector input tensor")?;
+        let outputs = session_guard
+            .run(ort::inputs![input_name => input])
+            .context("Pet detection inference failed")?;
+        let Some(value) = outputs.keys().next().and_then(|k| outputs.get(k)) else {
+            return Ok(vec![]);
+        };
+        let (_, rows) = value.try_extract_tensor::<f32>().context("Pet detector output is not a float tensor")?;
+
+        let threshold: f32 = std::env::var("SEEN_PET_CONFIDENCE_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0.5);
+        let raw: Vec<FaceBbox> = rows
+            .chunks_exact(6)
+            .filter(|row| row[4] >= threshold)
+            .map(|row| FaceBbox {
+                x1: (row[0] / scale).clamp(0.0, ow),
+                y1: (row[1] / scale).clamp(0.0, oh),
+                x2: (row[2] / scale).clamp(0.0, ow),
+                y2: (row[3] / scale).clamp(0.0, oh),
+                confidence: row[4],
+                frame_ms: None,
+                landmarks: None,
+            })
+            .filter(|b| b.x2 - b.x1 >= 16.0 && b.y2 - b.y1 >= 16.0)
+            .collect();
+        let out: Vec<FaceBbox> = nms_wrapper(&raw, 0.45).into_iter().map(|i| raw[i].clone()).collect();
+        info!("Pet detection: {} faces", out.len());
+        Ok(out)
+    }
+
+    /// L2-normalized embedding of a pet face crop; empty when the model gives nothing usable
+    pub fn recognize_pet(&self, crop: &DynamicImage) -> Result<Vec<f32>> {
+        const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+        const STD: [f32; 3] = [0.229, 0.224, 0.225];
+        let mut session_guard = self
+            .pet_embed_session
+            .as_ref()
+            .context("Pet recognition model not loaded")?
+            .lock();
+        let rgb = crop.resize_exact(224, 224, image::imageops::FilterType::Triangle).to_rgb8();
+        let mut data = Vec::with_capacity(3 * 224 * 224);
+        for c in 0..3 {
+            for p in rgb.pixels() {
+                data.push((p[c] as f32 / 255.0 - MEAN[c]) / STD[c]);
+            }
+        }
+
+        let input_name = session_guard.inputs[0].name.clone();
+        let input = Value::from_array((vec![1i64, 3, 224, 224], data))
+            .context("Failed to create pet embedder input tensor")?;
+        let outputs = session_guard
+            .run(ort::inputs![input_name => input])
+            .context("Pet recognition inference failed")?;
+        let Some(value) = outputs.keys().next().and_then(|k| outputs.get(k)) else {
+            return Ok(vec![]);
+        };
+        let (_, slice) = value.try_extract_tensor::<f32>().context("Pet embedder output is not a float tensor")?;
+        let mut v = slice.to_vec();
+        let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm == 0.0 {
+            warn!("Pet embedding has zero norm (all zeros)");
+            return Ok(vec![]);
+        }
+        for x in &mut v {
+            *x /= norm;
+        }
+        Ok(v)
+    }
+
     pub fn recognize_face(&self, face_crop: &DynamicImage) -> Result<Vec<f32>> {
         let mut session_guard = self
             .arcface_session
@@ -647,7 +1021,49 @@ impl FaceProcessor {
         }
         
         let img = image::open(image_path).context(format!("Failed to open image: {:?}", image_path))?;
-        let bboxes = self.detect_faces(&img)?;
+        self.faces_in(asset_id, &img)
+    }
+
+    /// Faces in frames spread evenly over the video, each person once
+    pub fn process_video(&self, asset_id: i64, video_path: &Path) -> Result<Vec<FaceEmbedding>> {
+        let frames = video_frame_count();
+        if frames == 0 {
+            return Ok(vec![]);
+        }
+        let duration_ms = crate::pipeline::thumb::video_duration_secs(&video_path.to_string_lossy())
+            .map(|d| (d * 1000.0) as i64)
+            .unwrap_or(0);
+        let mut faces = Vec::new();
+        // Centered in equal slices, so a short clip isn't sampled at its black first frame
+        let mut offsets: Vec<i64> = (0..frames as i64).map(|i| duration_ms * (2 * i + 1) / (2 * frames as i64)).collect();
+        offsets.dedup();
+        for frame_ms in offsets {
+            let img = match video_frame(video_path, frame_ms) {
+                Ok(img) => img,
+                Err(e) => {
+                    warn!("Skipping frame at {} ms of asset {}: {}", frame_ms, asset_id, e);
+                    continue;
+                }
+            };
+            for mut face in self.faces_in(asset_id, &img)? {
+                face.bbox.frame_ms = Some(frame_ms);
+                faces.push(face);
+            }
+        }
+        Ok(dedupe_video_faces(faces, VIDEO_FACE_DEDUP_DISTANCE))
+    }
+
+    /// Image or video, by extension
+    pub fn process_asset(&self, asset_id: i64, path: &Path) -> Result<Vec<FaceEmbedding>> {
+        if is_video_path(path) {
+            self.process_video(asset_id, path)
+        } else {
+            self.process_image(asset_id, path)
+        }
+    }
+
+    fn faces_in(&self, asset_id: i64, img: &DynamicImage) -> Result<Vec<FaceEmbedding>> {
+        let bboxes = self.detect_faces(img)?;
         if bboxes.is_empty() {
             return Ok(vec![]);
         }
@@ -658,30 +1074,64 @@ impl FaceProcessor {
             let x2 = bbox.x2.min(img.width() as f32) as u32;
             let y2 = bbox.y2.min(img.height() as f32) as u32;
             if x2 > x1 && y2 > y1 {
-                let face_crop = img.crop_imm(x1, y1, x2 - x1, y2 - y1);
+                // Aligned to the ArcFace template when the detector gave keypoints, else the box
+                let face_crop = bbox
+                    .landmarks
+                    .and_then(|l| crate::pipeline::align::align_face(img, &l))
+                    .unwrap_or_else(|| img.crop_imm(x1, y1, x2 - x1, y2 - y1));
                 match self.recognize_face(&face_crop) {
                     Ok(embedding) => {
                         embeddings.push(FaceEmbedding {
                             embedding,
                             bbox: bbox.clone(),
                             asset_id,
+                            subject: Subject::Person,
                         });
                     }
                     Err(e) => warn!("Failed to generate embedding for face: {}", e),
                 }
             }
         }
+        if self.pets_loaded() {
+            embeddings.extend(self.pets_in(asset_id, img)?);
+        }
         Ok(embeddings)
     }
+
+    fn pets_in(&self, asset_id: i64, img: &DynamicImage) -> Result<Vec<FaceEmbedding>> {
+        let mut pets = Vec::new();
+        for bbox in self.detect_pets(img)? {
+            let (x1, y1) = (bbox.x1 as u32, bbox.y1 as u32);
+            let (x2, y2) = (bbox.x2 as u32, bbox.y2 as u32);
+            if x2 <= x1 || y2 <= y1 {
+                continue;
+            }
+            match self.recognize_pet(&img.crop_imm(x1, y1, x2 - x1, y2 - y1)) {
+                Ok(embedding) if !embedding.is_empty() => pets.push(FaceEmbedding { embedding, bbox, asset_id, subject: Subject::Pet }),
+                Ok(_) => {}
+                Err(e) => warn!("Failed to generate embedding for pet: {}", e),
+            }
+        }
+        Ok(pets)
+    }
 }
 
-// HDBSCAN-style clustering wrapper
-// --------------------------------
+// Face clustering
+// ---------------
 //
-// We expose a single entrypoint, `cluster_faces_hdbscan`, that can later be
-// backed by a real HDBSCAN implementation. For now, we keep a conservative
-// DBSCAN-like fallback using the existing cosine_distance logic so behavior is
-// predictable and easy to tune.
+// `cluster_faces_hdbscan` is the single entrypoint. It runs HDBSCAN by default;
+// SEEN_FACE_CLUSTER_ALGORITHM=dbscan switches back to the fixed-radius DBSCAN
+// for comparison.
+
+/// Clustering algorithms selectable with SEEN_FACE_CLUSTER_ALGORITHM
+pub const CLUSTER_ALGORITHMS: [&str; 2] = ["hdbscan", "dbscan"];
+
+pub fn cluster_algorithm() -> &'static str {
+    match std::env::var("SEEN_FACE_CLUSTER_ALGORITHM").ok().as_deref().map(str::trim) {
+        Some(v) if v.eq_ignore_ascii_case("dbscan") => "dbscan",
+        _ => "hdbscan",
+    }
+}
 
 #[cfg(feature = "facial-recognition")]
 pub fn cluster_faces_hdbscan(
@@ -689,24 +1139,68 @@ pub fn cluster_faces_hdbscan(
     min_cluster_size: usize,
     min_samples: usize,
 ) -> Vec<Vec<usize>> {
-    // Fallback: simple DBSCAN-style clustering using cosine distance.
-    // This preserves current behavior while giving us a place to plug a
-    // true HDBSCAN implementation without touching callers.
-
     if embeddings.is_empty() {
         return vec![];
     }
 
-    // Interpret `min_cluster_size` as the minimum cluster size, and
-    // `min_samples` as neighborhood density; for the fallback we just use
-    // `min_samples` for both.
     // Epsilon (distance threshold): Lower = stricter (more fragmentation), Higher = looser (more merging)
     // Default 0.55 allows for some variation in lighting/pose while keeping different people separate.
+    // HDBSCAN only uses it when a batch holds a single group of faces.
     let epsilon: f32 = std::env::var("SEEN_FACE_CLUSTER_EPSILON")
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(0.55);
 
+    let algorithm = cluster_algorithm();
+    let started = std::time::Instant::now();
+    let clusters = if algorithm == "dbscan" {
+        cluster_faces_dbscan(embeddings, min_cluster_size, min_samples, epsilon)
+    } else {
+        crate::pipeline::hdbscan::hdbscan(embeddings.len(), min_cluster_size, min_samples, epsilon, |a, b| {
+            cosine_distance(&embeddings[a].embedding, &embeddings[b].embedding)
+        })
+    };
+    let clustered: usize = clusters.iter().map(Vec::len).sum();
+    info!(
+        "{} clustered {} faces into {} groups ({} left as noise) in {:?}",
+        algorithm,
+        embeddings.len(),
+        clusters.len(),
+        embeddings.len() - clustered,
+        started.elapsed()
+    );
+    clusters
+}
+
+/// Cluster persons and pets apart: each cluster comes back with its subject, holding indices into `embeddings`
+#[cfg(feature = "facial-recognition")]
+pub fn cluster_by_subject(
+    embeddings: &[FaceEmbedding],
+    min_cluster_size: usize,
+    min_samples: usize,
+) -> Vec<(Subject, Vec<usize>)> {
+    let mut out = Vec::new();
+    for subject in Subject::ALL {
+        let members: Vec<usize> = (0..embeddings.len()).filter(|&i| embeddings[i].subject == subject).collect();
+        if members.is_empty() {
+            continue;
+        }
+        let subset: Vec<FaceEmbedding> = members.iter().map(|&i| embeddings[i].clone()).collect();
+        for cluster in cluster_faces_hdbscan(&subset, min_cluster_size, min_samples) {
+            out.push((subject, cluster.into_iter().map(|i| members[i]).collect()));
+        }
+    }
+    out
+}
+
+/// Fixed-radius DBSCAN: faces within `epsilon` of a face with `min_samples` such neighbours join its cluster
+#[cfg(feature = "facial-recognition")]
+fn cluster_faces_dbscan(
+    embeddings: &[FaceEmbedding],
+    min_cluster_size: usize,
+    min_samples: usize,
+    epsilon: f32,
+) -> Vec<Vec<usize>> {
     let mut visited = vec![false; embeddings.len()];
     let mut clusters = Vec::new();
 
@@ -775,6 +1269,41 @@ pub fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
     1.0 - (dot_product / (norm_a * norm_b))
 }
 
+/// Greedy grouping for the unassigned-faces review queue. Each face joins the group whose
+/// seed (first face) is closest, if within `max_distance`, or else seeds a new group. Comparing
+/// against seeds rather than every member keeps groups from chaining across different people.
+/// Groups come back largest first; pass the clearest faces first so they become seeds.
+#[cfg(feature = "facial-recognition")]
+pub fn group_similar_faces(embeddings: &[Vec<f32>], max_distance: f32) -> Vec<Vec<usize>> {
+    let normalized: Vec<Vec<f32>> = embeddings
+        .iter()
+        .map(|e| {
+            let norm = e.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if norm == 0.0 { e.clone() } else { e.iter().map(|x| x / norm).collect() }
+        })
+        .collect();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, embedding) in normalized.iter().enumerate() {
+        let closest = groups
+            .iter()
+            .enumerate()
+            .filter(|(_, members)| normalized[members[0]].len() == embedding.len())
+            .map(|(g, members)| {
+                let dot: f32 = normalized[members[0]].iter().zip(embedding).map(|(x, y)| x * y).sum();
+                (g, 1.0 - dot)
+            })
+            .filter(|(_, distance)| *distance <= max_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match closest {
+            Some((g, _)) => groups[g].push(i),
+            None => groups.push(vec![i]),
+        }
+    }
+    // Stable sort: equally sized groups keep the order of their seeds
+    groups.sort_by_key(|members| std::cmp::Reverse(members.len()));
+    groups
+}
+
 // NMS helpers
 #[cfg(feature = "facial-recognition")]
 fn nms_wrapper(boxes: &[FaceBbox], iou_threshold: f32) -> Vec<usize> {
@@ -830,18 +1359,65 @@ fn calculate_iou(a: &FaceBbox, b: &FaceBbox) -> f32 {
 // Face embedding index
 // --------------------
 //
-// We keep the public API surface small so we can swap the underlying
-// implementation (brute-force vs FAISS) without touching the rest of the code.
-//
-// For now, we provide a brute-force implementation that can be replaced with a
-// FAISS-backed index in a follow-up step. The API is intentionally similar to
-// what a FAISS wrapper would expose (add + k‑NN search).
+// Every stored person face embedding goes into an HNSW graph (see `hnsw`) saved under
+// `data/faces.hnsw`. At startup the saved graph is loaded and brought in line
+// with `face_embeddings`, so only faces added or deleted since the last save
+// cost anything; the graph is saved again in the background when it changes.
+
+/// Neighbours per node and candidates considered when linking a new face
+#[cfg(feature = "facial-recognition")]
+const HNSW_M: usize = 16;
+#[cfg(feature = "facial-recognition")]
+const HNSW_EF_CONSTRUCTION: usize = 100;
+/// Candidates explored per search; more finds the true neighbours more often
+#[cfg(feature = "facial-recognition")]
+const HNSW_EF_SEARCH: usize = 64;
+#[cfg(feature = "facial-recognition")]
+const INDEX_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
 
 #[cfg(feature = "facial-recognition")]
 pub struct FaceIndex {
-    id_to_index: HashMap<i64, usize>,
-    index_to_id: Vec<i64>,
-    embeddings: Vec<Vec<f32>>,
+    graph: crate::pipeline::hnsw::Hnsw,
+    /// Where the graph is saved; None keeps it in memory only
+    path: Option<PathBuf>,
+    /// Changed since it was last saved
+    dirty: bool,
+    /// Profile centroid of each known person, for assigning new faces as they arrive
+    person_centroids: HashMap<i64, Vec<f32>>,
+    /// Faces users said are not the person, so look-alikes of them aren't assigned to that person
+    person_rejections: HashMap<i64, Vec<Vec<f32>>>,
+    /// Which of the known persons are pets; faces only join persons of their own subject
+    pet_ids: std::collections::HashSet<i64>,
+    /// When `person_centroids` was last read from the database
+    persons_loaded_at: Option<std::time::Instant>,
+    warmup: WarmupProgress,
+}
+
+/// The startup pass bringing the saved index in line with `face_embeddings`
+#[cfg(feature = "facial-recognition")]
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct WarmupProgress {
+    pub done: bool,
+    /// Faces stored since the index was last saved, and how many of them are in by now
+    pub to_add: usize,
+    pub added: usize,
+    /// Faces deleted since the index was last saved
+    pub removed: usize,
+}
+
+/// How long the face workers trust their copy of the person centroids; persons renamed,
+/// merged or created through the API are picked up after at most this long
+#[cfg(feature = "facial-recognition")]
+const PERSON_CENTROIDS_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Largest cosine distance between a new face and a person's centroid for the face to join
+/// that person right away (SEEN_FACE_ASSIGN_THRESHOLD, default 0.45; 0 sends every face to clustering)
+#[cfg(feature = "facial-recognition")]
+pub fn assign_threshold() -> f32 {
+    std::env::var("SEEN_FACE_ASSIGN_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0.45)
 }
 
 #[cfg(feature = "facial-recognition")]
@@ -852,52 +1428,376 @@ impl Default for FaceIndex {
 }
 
 impl FaceIndex {
+    /// An empty index kept in memory only
     pub fn new() -> Self {
         Self {
-            id_to_index: HashMap::new(),
-            index_to_id: Vec::new(),
-            embeddings: Vec::new(),
+            graph: crate::pipeline::hnsw::Hnsw::new(HNSW_M, HNSW_EF_CONSTRUCTION),
+            path: None,
+            dirty: false,
+            person_centroids: HashMap::new(),
+            person_rejections: HashMap::new(),
+            pet_ids: std::collections::HashSet::new(),
+            persons_loaded_at: None,
+            warmup: WarmupProgress::default(),
+        }
+    }
+
+    /// The index saved at `path`, or an empty one that will be saved there when the file is
+    /// missing or unreadable. [`start_index_warmup`] then brings it in line with the database.
+    pub fn open(path: &Path) -> Self {
+        let mut index = Self::new();
+        index.path = Some(path.to_path_buf());
+        match std::fs::read(path) {
+            Ok(bytes) => match crate::pipeline::hnsw::Hnsw::from_bytes(&bytes) {
+                Ok(graph) => index.graph = graph,
+                Err(e) => warn!("Rebuilding face index, {} is unusable: {}", path.display(), e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Rebuilding face index, cannot read {}: {}", path.display(), e),
         }
+        info!("Face index opened: {} faces from {}", index.graph.len(), path.display());
+        index
     }
 
-    /// Add a new face embedding to the index.
-    ///
-    /// In a FAISS-backed implementation this would also insert into the FAISS
-    /// index. Here we just append to an in-memory Vec for brute-force search.
+    /// How far the startup pass of [`start_index_warmup`] has got
+    pub fn warmup(&self) -> WarmupProgress {
+        self.warmup
+    }
+
+    pub fn len(&self) -> usize {
+        self.graph.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graph.is_empty()
+    }
+
+    /// Add a stored face, replacing its embedding if it is already indexed
     pub fn add_embedding(&mut self, face_id: i64, embedding: &[f32]) {
-        let index_pos = self.index_to_id.len();
-        self.id_to_index.insert(face_id, index_pos);
-        self.index_to_id.push(face_id);
-        self.embeddings.push(embedding.to_vec());
+        match self.graph.insert(face_id, embedding) {
+            Ok(()) => self.dirty = true,
+            Err(e) => warn!("Face {} left out of the index: {}", face_id, e),
+        }
     }
 
-    /// k‑NN search using cosine distance.
-    ///
-    /// This mirrors what a FAISS `IndexFlatIP` or `IndexFlatL2` would do for
-    /// small datasets, but implemented in pure Rust for now. Once FAISS is
-    /// wired in, this method can delegate to a FAISS index instead.
+    /// Drop a deleted face; returns whether it was indexed
+    pub fn remove_embedding(&mut self, face_id: i64) -> bool {
+        let removed = self.graph.remove(face_id);
+        self.dirty |= removed;
+        removed
+    }
+
+    /// Up to `k` indexed faces within cosine distance `threshold` of `embedding`, closest first.
+    /// Approximate: a true neighbour is occasionally missed.
     pub fn find_similar(&self, embedding: &[f32], k: usize, threshold: f32) -> Vec<(i64, f32)> {
-        let mut results: Vec<(i64, f32)> = self
-            .embeddings
+        self.graph
+            .search(embedding, k, HNSW_EF_SEARCH)
+            .into_iter()
+            .filter(|(_, dist)| *dist <= threshold)
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.graph = crate::pipeline::hnsw::Hnsw::new(HNSW_M, HNSW_EF_CONSTRUCTION);
+        self.dirty = true;
+        self.person_centroids.clear();
+        self.person_rejections.clear();
+        self.pet_ids.clear();
+        self.persons_loaded_at = None;
+    }
+
+    /// The serialized graph and where to write it, when it changed since the last save
+    fn take_snapshot(&mut self) -> Option<(PathBuf, Vec<u8>)> {
+        let path = self.path.clone()?;
+        if !std::mem::take(&mut self.dirty) {
+            return None;
+        }
+        Some((path, self.graph.to_bytes()))
+    }
+
+    /// Whether the person centroids should be read again from the database
+    pub fn persons_stale(&self) -> bool {
+        self.persons_loaded_at.is_none_or(|at| at.elapsed() > PERSON_CENTROIDS_TTL)
+    }
+
+    pub fn set_person_centroids(
+        &mut self,
+        centroids: Vec<(i64, Vec<f32>)>,
+        rejections: Vec<(i64, Vec<f32>)>,
+        pet_ids: std::collections::HashSet<i64>,
+    ) {
+        self.person_centroids = centroids.into_iter().collect();
+        self.pet_ids = pet_ids;
+        self.person_rejections.clear();
+        for (person_id, embedding) in rejections {
+            self.person_rejections.entry(person_id).or_default().push(embedding);
+        }
+        self.persons_loaded_at = Some(std::time::Instant::now());
+    }
+
+    /// Replace one person's centroid; None forgets the person
+    pub fn set_person_centroid(&mut self, person_id: i64, centroid: Option<Vec<f32>>) {
+        match centroid {
+            Some(c) => self.person_centroids.insert(person_id, c),
+            None => self.person_centroids.remove(&person_id),
+        };
+    }
+
+    /// Force the next lookup to re-read the centroids, e.g. after persons were merged or deleted
+    pub fn invalidate_persons(&mut self) {
+        self.persons_loaded_at = None;
+    }
+
+    /// The person of the given subject whose centroid is closest to `embedding`, if within `threshold`.
+    /// A person is passed over when a face rejected for them is closer to `embedding` than their centroid.
+    pub fn nearest_person(&self, embedding: &[f32], subject: Subject, threshold: f32) -> Option<(i64, f32)> {
+        self.person_centroids
             .iter()
-            .enumerate()
-            .map(|(idx, emb)| {
-                let dist = cosine_distance(embedding, emb);
-                let face_id = self.index_to_id.get(idx).copied().unwrap_or(-1);
-                (face_id, dist)
-            })
+            .filter(|(person_id, _)| self.pet_ids.contains(person_id) == (subject == Subject::Pet))
+            .map(|(person_id, centroid)| (*person_id, cosine_distance(embedding, centroid)))
             .filter(|(_, dist)| *dist <= threshold)
-            .collect();
+            .filter(|(person_id, dist)| {
+                self.person_rejections
+                    .get(person_id)
+                    .is_none_or(|rejected| rejected.iter().all(|r| cosine_distance(embedding, r) >= *dist))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+    }
+}
+
+/// Open the saved face index (see `FaceIndex::open`) off the async runtime
+#[cfg(feature = "facial-recognition")]
+pub async fn load_face_index(path: PathBuf) -> FaceIndex {
+    let result = tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || FaceIndex::open(&path)
+    })
+    .await;
+    result.unwrap_or_else(|e| {
+        error!("Failed to open face index, starting empty: {}", e);
+        let mut index = FaceIndex::new();
+        index.path = Some(path);
+        index
+    })
+}
+
+/// Faces read from the database and added to the index per lock, so searches wait at most for one batch
+#[cfg(feature = "facial-recognition")]
+const WARMUP_BATCH: usize = 500;
+
+/// Bring the opened index in line with `face_embeddings` in the background, so the server answers
+/// while a large library is still being indexed: faces deleted since the last save are dropped
+/// and faces stored since are streamed in, with progress logged and shown in `GET /faces/progress`
+#[cfg(feature = "facial-recognition")]
+pub fn start_index_warmup(pool: crate::db::DbPool, face_index: Arc<Mutex<FaceIndex>>) {
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = warm_index(&pool, &face_index) {
+            error!("Face index warm-up stopped, similar faces may be missed until the next restart: {}", e);
+        }
+        face_index.lock().warmup.done = true;
+    });
+}
 
-        results.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-        results.truncate(k);
-        results
+#[cfg(feature = "facial-recognition")]
+fn warm_index(pool: &crate::db::DbPool, face_index: &Mutex<FaceIndex>) -> anyhow::Result<()> {
+    let started = std::time::Instant::now();
+    let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+    let stored: std::collections::HashSet<i64> = crate::db::query::face_embedding_ids(&conn)?.into_iter().collect();
+    let mut missing = {
+        let mut index = face_index.lock();
+        let deleted: Vec<i64> = index.graph.ids().filter(|id| !stored.contains(id)).collect();
+        for id in &deleted {
+            index.remove_embedding(*id);
+        }
+        let missing: Vec<i64> = stored.iter().copied().filter(|id| !index.graph.contains(*id)).collect();
+        index.warmup.removed = deleted.len();
+        index.warmup.to_add = missing.len();
+        missing
+    };
+    missing.sort_unstable();
+    if !missing.is_empty() {
+        info!("Face index warm-up: adding {} faces stored since the last save", missing.len());
     }
 
-    pub fn clear(&mut self) {
-        self.id_to_index.clear();
-        self.index_to_id.clear();
-        self.embeddings.clear();
+    // Logged about every tenth of the way
+    let log_every = (missing.len() / 10).max(WARMUP_BATCH);
+    let mut next_log = log_every;
+    for chunk in missing.chunks(WARMUP_BATCH) {
+        let embeddings = crate::db::query::get_face_embeddings_by_id(&conn, chunk)?;
+        let mut index = face_index.lock();
+        for (face_id, embedding) in embeddings {
+            index.add_embedding(face_id, &embedding);
+        }
+        index.warmup.added += chunk.len();
+        if index.warmup.added >= next_log && index.warmup.added < missing.len() {
+            info!("Face index warm-up: {} of {} faces added", index.warmup.added, missing.len());
+            next_log += log_every;
+        }
+    }
+
+    // A face deleted during the pass may have been read just before
+    let stored: std::collections::HashSet<i64> = crate::db::query::face_embedding_ids(&conn)?.into_iter().collect();
+    let mut index = face_index.lock();
+    let stale: Vec<i64> = index.graph.ids().filter(|id| !stored.contains(id)).collect();
+    for id in stale {
+        index.remove_embedding(id);
+    }
+    info!(
+        "Face index ready: {} faces ({} added, {} removed) in {:.1}s",
+        index.len(), index.warmup.added, index.warmup.removed, started.elapsed().as_secs_f32()
+    );
+    Ok(())
+}
+
+/// Save the face index in the background whenever it changed
+#[cfg(feature = "facial-recognition")]
+pub fn start_index_saver(face_index: Arc<parking_lot::Mutex<FaceIndex>>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(INDEX_SAVE_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let Some((path, bytes)) = face_index.lock().take_snapshot() else {
+                continue;
+            };
+            let result = tokio::task::spawn_blocking(move || -> std::io::Result<()> {
+                let partial = path.with_extension("hnsw.partial");
+                std::fs::write(&partial, &bytes)?;
+                std::fs::rename(&partial, &path)
+            })
+            .await;
+            let error = match result {
+                Ok(Ok(())) => continue,
+                Ok(Err(e)) => e.to_string(),
+                Err(e) => e.to_string(),
+            };
+            warn!("Failed to save face index: {}", error);
+            face_index.lock().dirty = true;
+        }
+    });
+}
+
+/// Assign each stored face to the closest known person when it is near enough, and return
+/// the rest for batch clustering
+#[cfg(feature = "facial-recognition")]
+async fn assign_to_known_persons(
+    face_index: &Arc<parking_lot::Mutex<FaceIndex>>,
+    db_path: &Path,
+    stored: Vec<(i64, FaceEmbedding)>,
+) -> Vec<(i64, FaceEmbedding)> {
+    let threshold = assign_threshold();
+    if threshold <= 0.0 || stored.is_empty() {
+        return stored;
+    }
+    if face_index.lock().persons_stale() {
+        let dbp = db_path.to_path_buf();
+        let loaded = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+            let conn = rusqlite::Connection::open(dbp)?;
+            Ok((
+                crate::db::writer::person_centroids(&conn)?,
+                crate::db::query::get_person_rejections(&conn)?,
+                crate::db::query::get_pet_person_ids(&conn)?,
+            ))
+        })
+        .await;
+        match loaded {
+            Ok(Ok((centroids, rejections, pet_ids))) => face_index.lock().set_person_centroids(centroids, rejections, pet_ids),
+            Ok(Err(e)) => warn!("Failed to load person centroids: {}", e),
+            Err(e) => warn!("Person centroid task failed: {}", e),
+        }
+    }
+
+    let mut assignments = Vec::new();
+    let mut leftovers = Vec::new();
+    {
+        let index = face_index.lock();
+        for (face_id, embed) in stored {
+            match index.nearest_person(&embed.embedding, embed.subject, threshold) {
+                Some((person_id, _)) => assignments.push((face_id, person_id, embed)),
+                None => leftovers.push((face_id, embed)),
+            }
+        }
+    }
+    if assignments.is_empty() {
+        return leftovers;
+    }
+
+    let dbp = db_path.to_path_buf();
+    let pairs: Vec<(i64, i64)> = assignments.iter().map(|(face_id, person_id, _)| (*face_id, *person_id)).collect();
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(dbp)?;
+        crate::db::writer::assign_faces_to_persons(&conn, &pairs)
+    })
+    .await;
+    match result {
+        Ok(Ok(crate::db::writer::FaceAssignment { centroids, orphaned })) => {
+            let assigned = assignments.len() - orphaned.len();
+            let mut index = face_index.lock();
+            for (person_id, centroid) in centroids {
+                index.set_person_centroid(person_id, centroid);
+            }
+            if !orphaned.is_empty() {
+                index.invalidate_persons();
+            }
+            if assigned > 0 {
+                info!("Assigned {} new faces to existing persons", assigned);
+            }
+            leftovers.extend(assignments.into_iter().filter(|(face_id, _, _)| orphaned.contains(face_id)).map(|(face_id, _, embed)| (face_id, embed)));
+        }
+        Ok(Err(e)) => {
+            error!("Failed to assign faces to existing persons: {}", e);
+            leftovers.extend(assignments.into_iter().map(|(face_id, _, embed)| (face_id, embed)));
+        }
+        Err(e) => {
+            error!("Face assignment task panicked: {}", e);
+            leftovers.extend(assignments.into_iter().map(|(face_id, _, embed)| (face_id, embed)));
+        }
+    }
+    leftovers
+}
+
+#[cfg(feature = "facial-recognition")]
+async fn finish_job(db_path: &Path, asset_id: i64) {
+    let db_path = db_path.to_path_buf();
+    let done = tokio::task::spawn_blocking(move || {
+        let conn = rusqlite::Connection::open(db_path)?;
+        crate::db::writer::finish_face_job(&conn, asset_id)
+    })
+    .await;
+    if let Ok(Err(e)) = done {
+        warn!("Could not mark face job for asset {} done: {}", asset_id, e);
+    }
+}
+
+/// Queue the jobs a previous run left unfinished; called once the models are loaded, when
+/// face detection is on
+#[cfg(feature = "facial-recognition")]
+pub async fn resume_jobs(pool: crate::db::DbPool, face_tx: mpsc::Sender<FaceJob>, gauges: Arc<crate::pipeline::QueueGauges>) {
+    let pending = tokio::task::spawn_blocking(move || {
+        let conn = pool.get()?;
+        if !crate::db::writer::get_face_detection_enabled(&conn)? {
+            return Ok(Vec::new());
+        }
+        crate::db::writer::pending_face_jobs(&conn)
+    })
+    .await;
+    let pending = match pending {
+        Ok(Ok(pending)) => pending,
+        Ok(Err(e)) => {
+            error!("Could not read unfinished face jobs: {}", e);
+            return;
+        }
+        Err(_) => return,
+    };
+    if pending.is_empty() {
+        return;
+    }
+    info!("Resuming face detection for {} assets", pending.len());
+    for (asset_id, path) in pending {
+        if face_tx.send(FaceJob { asset_id, image_path: PathBuf::from(path), queued_at: std::time::Instant::now() }).await.is_err() {
+            break;
+        }
+        gauges.face.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
     }
 }
 
@@ -909,6 +1809,7 @@ pub async fn start_face_workers(
     db_path: PathBuf,
     gauges: Arc<crate::pipeline::QueueGauges>,
     face_index: Arc<parking_lot::Mutex<FaceIndex>>,
+    stats: Arc<crate::stats::Stats>,
 ) {
     // Distribute jobs to workers using round-robin
     let mut worker_txs = Vec::new();
@@ -940,31 +1841,43 @@ pub async fn start_face_workers(
         let db_path_c = db_path.clone();
         let gauges_c = gauges.clone();
         let face_index_c = face_index.clone();
+        let stats_c = stats.clone();
         // Accumulate (face_id, embedding) pairs so we can map clusters back to DB rows
         let mut accumulated_with_ids: Vec<(i64, FaceEmbedding)> = Vec::new();
 
         tokio::spawn(async move {
             while let Some(job) = worker_rx.recv().await {
+                let run = gauges_c.wait_to_run(crate::pipeline::FlushQueue::Face, job.queued_at).await;
                 gauges_c
                     .face
                     .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+                if !run {
+                    continue;
+                }
+                let started = std::time::Instant::now();
                 let embeddings = {
                     let processor_clone = processor_c.clone();
                     let asset_id_clone = job.asset_id;
                     let image_path_clone = job.image_path.clone();
                     match tokio::task::spawn_blocking(move || {
                         let processor_guard = processor_clone.lock();
-                        processor_guard.process_image(asset_id_clone, &image_path_clone)
+                        processor_guard.process_asset(asset_id_clone, &image_path_clone)
                     })
                     .await
                     {
                         Ok(result) => result,
                         Err(e) => {
                             error!("Face processing task panicked for asset {}: {}", job.asset_id, e);
+                            stats_c.inc_stage_errors(crate::stats::PipelineStage::Face);
+                            finish_job(&db_path_c, job.asset_id).await;
                             continue; // Skip this job and continue processing others
                         }
                     }
                 };
+                // Failures count as done too, or every restart would retry them
+                finish_job(&db_path_c, job.asset_id).await;
+                stats_c.observe_stage(crate::stats::PipelineStage::Face, started.elapsed());
+                stats_c.record_asset_cost(crate::stats::PipelineStage::Face, &job.image_path.to_string_lossy(), started.elapsed());
                 match embeddings {
                     Ok(embeddings) => {
                         if embeddings.is_empty() {
@@ -977,13 +1890,14 @@ pub async fn start_face_workers(
                             let mut stored = Vec::new();
                             for embed in embeds {
                                 let bbox_json = serde_json::to_string(&embed.bbox).ok()?;
-                                match crate::db::writer::insert_face_embedding(
+                                match crate::db::writer::insert_subject_embedding(
                                     &conn,
                                     embed.asset_id,
                                     None,
                                     &embed.embedding,
                                     &bbox_json,
                                     embed.bbox.confidence as f64,
+                                    embed.subject,
                                 ) {
                                     Ok(face_id) => stored.push((face_id, embed)),
                                     Err(e) => {
@@ -1002,18 +1916,17 @@ pub async fn start_face_workers(
                             }
                         };
                         if let Some(stored) = stored_ids {
-                            // Update in-memory search index
+                            // Update in-memory search index; pets are matched through their centroids only
                             {
                                 let mut index = face_index_c.lock();
-                                for (face_id, embed) in &stored {
+                                for (face_id, embed) in stored.iter().filter(|(_, e)| e.subject == Subject::Person) {
                                     index.add_embedding(*face_id, &embed.embedding);
                                 }
                             }
 
-                            // Accumulate for clustering with face IDs
-                            for (fid, embed) in stored {
-                                accumulated_with_ids.push((fid, embed));
-                            }
+                            // Faces of people already known join them now; only the rest wait for clustering
+                            let leftovers = assign_to_known_persons(&face_index_c, &db_path_c, stored).await;
+                            accumulated_with_ids.extend(leftovers);
 
                             info!("Processed {} faces in asset {}", embeddings.len(), job.asset_id);
 
@@ -1021,6 +1934,7 @@ pub async fn start_face_workers(
                             if accumulated_with_ids.len() >= get_cluster_batch_size() {
                                 let items = std::mem::take(&mut accumulated_with_ids);
                                 let dbp = db_path_c.clone();
+                                let index = face_index_c.clone();
                                 let item_count = items.len();
                                 info!("Starting clustering for {} accumulated faces", item_count);
                                 tokio::spawn(async move {
@@ -1058,19 +1972,19 @@ pub async fn start_face_workers(
                                         }
                                     }
                                     
-                                    let clusters = cluster_faces_hdbscan(&embeds_only, min_cluster_size, min_samples);
+                                    let clusters = cluster_by_subject(&embeds_only, min_cluster_size, min_samples);
                                     info!("Clustering produced {} clusters from {} faces", clusters.len(), embeds_only.len());
 
                                     // Persist: create a person per cluster and assign faces
                                     let result = tokio::task::spawn_blocking(move || {
                                         let conn = rusqlite::Connection::open(dbp).ok()?;
-                                        let mut persons_created = 0;
+                                        let mut person_ids = Vec::new();
                                         let mut faces_assigned = 0;
-                                        for cluster in clusters {
+                                        for (subject, cluster) in clusters {
                                             if cluster.is_empty() { continue; }
-                                            let person_id = match crate::db::writer::insert_person(&conn, None) {
+                                            let person_id = match crate::db::writer::insert_subject(&conn, None, subject) {
                                                 Ok(pid) => {
-                                                    persons_created += 1;
+                                                    person_ids.push(pid);
                                                     pid
                                                 },
                                                 Err(e) => {
@@ -1088,13 +2002,17 @@ pub async fn start_face_workers(
                                                 }
                                             }
                                         }
+                                        let persons_created = person_ids.len();
                                         info!("Clustering complete: {} persons created, {} faces assigned", persons_created, faces_assigned);
+                                        crate::pipeline::plugins::faces_clustered("pipeline", person_ids, faces_assigned);
                                         Some((persons_created, faces_assigned))
                                     }).await;
                                     
                                     match result {
                                         Ok(Some((persons, faces))) => {
                                             info!("Clustering persisted: {} persons, {} faces", persons, faces);
+                                            // New persons can take new faces straight away
+                                            index.lock().invalidate_persons();
                                         }
                                         Ok(None) => {
                                             error!("Clustering task returned no result for asset {} (database connection failed)", job.asset_id);
@@ -1109,6 +2027,7 @@ pub async fn start_face_workers(
                     }
                     Err(e) => {
                         error!("Failed to process faces for asset {}: {}", job.asset_id, e);
+                        stats_c.inc_stage_errors(crate::stats::PipelineStage::Face);
                         // Continue processing other jobs - don't crash the worker
                         // The error is logged for visibility in CI logs
                     },
@@ -1122,3 +2041,43 @@ pub async fn start_face_workers(
         let _ = distributor.await;
     });
 }
+
+#[cfg(all(test, feature = "facial-recognition"))]
+mod tests {
+    use super::*;
+
+    fn at_degrees(degrees: f32) -> Vec<f32> {
+        let radians = degrees.to_radians();
+        vec![radians.cos(), radians.sin()]
+    }
+
+    #[test]
+    fn test_group_similar_faces() {
+        // Two people, one of them seen three times, and a face unlike either; vectors needn't be unit length
+        let faces = vec![vec![2.0, 0.0], vec![0.9, 0.1], vec![0.0, 1.0], vec![0.1, 0.9], vec![0.0, 3.0], vec![-1.0, 0.0]];
+        let groups = group_similar_faces(&faces, 0.1);
+        assert_eq!(groups, vec![vec![2, 3, 4], vec![0, 1], vec![5]]);
+        let mut seen: Vec<usize> = groups.into_iter().flatten().collect();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..faces.len()).collect::<Vec<_>>());
+
+        // The threshold is inclusive; equally sized groups keep the order of their seeds
+        let orthogonal = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        assert_eq!(group_similar_faces(&orthogonal, 1.0), vec![vec![0, 1]]);
+        assert_eq!(group_similar_faces(&orthogonal, 0.999), vec![vec![0], vec![1]]);
+
+        // Faces are compared with seeds only, so groups don't chain from 0° through 50° to 100°
+        let max_distance = 1.0 - 60f32.to_radians().cos();
+        let chain = vec![at_degrees(0.0), at_degrees(50.0), at_degrees(100.0)];
+        assert_eq!(group_similar_faces(&chain, max_distance), vec![vec![0, 1], vec![2]]);
+
+        // A face within range of two seeds joins the closer one
+        let between = vec![at_degrees(0.0), at_degrees(90.0), at_degrees(40.0), at_degrees(50.0)];
+        assert_eq!(group_similar_faces(&between, 0.6), vec![vec![0, 2], vec![1, 3]]);
+
+        // Embeddings of another size never join
+        let mixed = vec![vec![1.0, 0.0], vec![1.0, 0.0, 0.0], vec![1.0, 0.0]];
+        assert_eq!(group_similar_faces(&mixed, 0.5), vec![vec![0, 2], vec![1]]);
+        assert!(group_similar_faces(&[], 0.5).is_empty());
+    }
+}
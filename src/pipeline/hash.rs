@@ -1,12 +1,14 @@
 use anyhow::Result;
 use sha2::{Sha256, Digest};
 use std::fs::File;
-use std::io::{Read, BufReader};
+use std::io::{Read, BufReader, Seek, SeekFrom};
 use tokio::sync::mpsc::{Receiver, Sender};
 use xxhash_rust::xxh3::Xxh3;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use crate::pipeline::metadata::MetaJob;
 use crate::pipeline::QueueGauges;
+use crate::pipeline::throttle::Stage;
+use crate::stats::{IoStage, PipelineStage, Stats};
 use std::sync::Arc;
 use memmap2::MmapOptions;
 
@@ -29,7 +31,7 @@ pub struct HashResult {
     pub sha256: Option<Vec<u8>>,
 }
 
-fn hash_file(path: &PathBuf, size_bytes: i64, mime: &str) -> Result<(i64, Option<Vec<u8>>)> {
+fn hash_file(path: &Path, size_bytes: i64, mime: &str) -> Result<(i64, Option<Vec<u8>>)> {
     // Always calculate SHA256 for video files (needed for thumbnails)
     // For other files, only calculate SHA256 if under 64MB (to save time on very large files)
     let is_video = mime.starts_with("video/");
@@ -42,7 +44,7 @@ fn hash_file(path: &PathBuf, size_bytes: i64, mime: &str) -> Result<(i64, Option
     if size_bytes >= MMAP_THRESHOLD {
         // Memory-mapped approach for large files (faster than buffered reads)
         // Safety: We've already checked the file exists and size_bytes matches actual file size
-        let file = File::open(path)?;
+        let file = File::open(crate::utils::path::long_path(path))?;
         let mmap = unsafe { 
             // Safe: File is opened successfully, size_bytes was obtained from metadata
             MmapOptions::new().map(&file)? 
@@ -69,7 +71,7 @@ fn hash_file(path: &PathBuf, size_bytes: i64, mime: &str) -> Result<(i64, Option
         Ok((x, sha))
     } else {
         // Buffered read approach for smaller files (better for small files)
-        let f = File::open(path)?;
+        let f = File::open(crate::utils::path::long_path(path))?;
         // Larger buffer for better I/O performance
         let mut reader = BufReader::with_capacity(4 * 1024 * 1024, f); // 4MB buffer
         let mut xx = Xxh3::new();
@@ -91,7 +93,49 @@ fn hash_file(path: &PathBuf, size_bytes: i64, mime: &str) -> Result<(i64, Option
     }
 }
 
-pub fn start_workers(n: usize, mut rx: Receiver<HashJob>, tx: Sender<MetaJob>, gauges: Arc<QueueGauges>) {
+/// Bytes read from each end of a file whose hash is sampled
+pub const SAMPLE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Hash the first and last `SAMPLE_BYTES` of a file and its size instead of all of it, for files
+/// too big to read in full during a scan. Copies still match, but an edit in the middle goes unnoticed.
+fn hash_sampled(path: &Path, size_bytes: i64) -> Result<(i64, Vec<u8>)> {
+    let mut f = File::open(crate::utils::path::long_path(path))?;
+    let size = size_bytes.max(0) as u64;
+    let mut xx = Xxh3::new();
+    let mut sh = Sha256::new();
+    let mut buf = vec![0u8; SAMPLE_BYTES as usize];
+    let head = size.min(SAMPLE_BYTES);
+    let tail_start = size.saturating_sub(SAMPLE_BYTES).max(head);
+    for (start, len) in [(0, head), (tail_start, size - tail_start)] {
+        f.seek(SeekFrom::Start(start))?;
+        let chunk = &mut buf[..len as usize];
+        f.read_exact(chunk)?;
+        xx.update(chunk);
+        sh.update(&*chunk);
+    }
+    xx.update(&size.to_le_bytes());
+    sh.update(size.to_le_bytes());
+    Ok((xx.digest() as i64, sh.finalize().to_vec()))
+}
+
+/// Hash an in-memory buffer the same way `hash_file` hashes files on disk
+pub fn hash_bytes(data: &[u8]) -> (i64, Vec<u8>) {
+    let mut xx = Xxh3::new();
+    xx.update(data);
+    let sha = Sha256::digest(data).to_vec();
+    (xx.digest() as i64, sha)
+}
+
+/// SHA-256 of a file of any size, streamed
+pub fn sha256_file(path: &std::path::Path) -> std::io::Result<Vec<u8>> {
+    let mut reader = BufReader::with_capacity(4 * 1024 * 1024, File::open(crate::utils::path::long_path(path))?);
+    let mut sha = Sha256::new();
+    std::io::copy(&mut reader, &mut sha)?;
+    Ok(sha.finalize().to_vec())
+}
+
+/// Files larger than `sample_above` bytes get a sampled hash; 0 hashes every file in full
+pub fn start_workers(n: usize, mut rx: Receiver<HashJob>, tx: Sender<MetaJob>, gauges: Arc<QueueGauges>, stats: Arc<Stats>, sample_above: u64) {
     // Distribute jobs to workers using a work-stealing pattern
     // Each worker gets its own channel, distributor round-robins jobs
     let mut worker_txs = Vec::new();
@@ -122,28 +166,47 @@ pub fn start_workers(n: usize, mut rx: Receiver<HashJob>, tx: Sender<MetaJob>, g
     for mut worker_rx in worker_rxs.into_iter() {
         let txc = tx.clone();
         let gaugesc = gauges.clone();
+        let statsc = stats.clone();
         tokio::spawn(async move {
             while let Some(job) = worker_rx.recv().await {
+                gaugesc.wait_while_paused().await;
                 gaugesc.hash.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
                 let mut xxh64 = 0i64;
                 let mut sha256 = None;
+                let permit = gaugesc.throttle.acquire(Stage::Hash, job.size_bytes.max(0) as u64).await;
                 // Move blocking I/O to spawn_blocking
                 let path = job.path.clone();
                 let size_bytes = job.size_bytes;
                 let mime = job.mime.clone();
-                match tokio::task::spawn_blocking(move || hash_file(&path, size_bytes, &mime)).await {
+                let sampled_hash = sample_above > 0 && size_bytes.max(0) as u64 > sample_above;
+                let started = std::time::Instant::now();
+                match tokio::task::spawn_blocking(move || {
+                    if sampled_hash {
+                        hash_sampled(&path, size_bytes).map(|(x, s)| (x, Some(s)))
+                    } else {
+                        hash_file(&path, size_bytes, &mime)
+                    }
+                }).await {
                     Ok(Ok((x, s))) => { 
                         xxh64 = x; 
                         sha256 = s; 
+                        // Hashing reads the whole file, or both ends of it when sampled
+                        let read = if sampled_hash { (2 * SAMPLE_BYTES).min(size_bytes.max(0) as u64) } else { size_bytes.max(0) as u64 };
+                        statsc.record_read(IoStage::Hash, read);
+                        statsc.observe_stage(PipelineStage::Hash, started.elapsed());
                     }
                     Ok(Err(e)) => { 
                         tracing::debug!("hash error for {:?}: {:?}", job.path, e); 
+                        statsc.inc_stage_errors(PipelineStage::Hash);
                     }
                     Err(e) => {
                         tracing::debug!("hash task error for {:?}: {:?}", job.path, e);
+                        statsc.inc_stage_errors(PipelineStage::Hash);
                     }
                 }
-                let out = MetaJob { job, xxh64: Some(xxh64), sha256 };
+                statsc.record_asset_cost(PipelineStage::Hash, &job.path.to_string_lossy(), started.elapsed());
+                permit.finish().await;
+                let out = MetaJob { job, xxh64: Some(xxh64), sha256, sampled_hash };
                 let _ = txc.send(out).await;
                 gaugesc.metadata.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             }
@@ -221,4 +284,32 @@ mod tests {
         assert_eq!(xxh64_1, xxh64_2);
         assert_eq!(sha256_1, sha256_2);
     }
+
+    #[test]
+    fn test_hash_sampled() {
+        use std::io::Write;
+        let tmp = TempDir::new().unwrap();
+        let test_file = tmp.path().join("big.mov");
+        let size = 3 * SAMPLE_BYTES;
+        File::create(&test_file).unwrap().set_len(size).unwrap();
+        let write_at = |offset: u64| {
+            let mut f = std::fs::OpenOptions::new().write(true).open(&test_file).unwrap();
+            f.seek(SeekFrom::Start(offset)).unwrap();
+            f.write_all(b"x").unwrap();
+        };
+
+        let original = hash_sampled(&test_file, size as i64).unwrap();
+        // The middle isn't read
+        write_at(SAMPLE_BYTES + 10);
+        assert_eq!(hash_sampled(&test_file, size as i64).unwrap(), original);
+        write_at(size - 1);
+        assert_ne!(hash_sampled(&test_file, size as i64).unwrap(), original);
+
+        // Files smaller than the two samples are read once, with the size still mixed in
+        let small = tmp.path().join("small.mov");
+        std::fs::write(&small, b"fake video content").unwrap();
+        let (xxh64, sha256) = hash_sampled(&small, 18).unwrap();
+        assert_ne!(Some(sha256), hash_file(&small, 18, "video/mp4").unwrap().1);
+        assert!(xxh64 != 0);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,243 @@
+//! HDBSCAN (Campello, Moulavi & Sander 2013) over any distance.
+//!
+//! Points are linked by mutual-reachability distance, `max(core(a), core(b), d(a, b))`, where a
+//! point's core distance is the distance to its `min_samples`-th nearest neighbour (itself
+//! included). The minimum spanning tree of that graph is the single-linkage hierarchy; it is
+//! condensed so a split only counts when both sides keep `min_cluster_size` points, and the
+//! clusters that persist longest (most stable) are kept. Unlike DBSCAN there is no single
+//! radius, so a person photographed in many conditions stays one cluster while tight groups of
+//! look-alikes still separate.
+//!
+//! Time is O(n²) distance evaluations and memory O(n), so batches of a few thousand faces are fine.
+
+/// A merge in the single-linkage tree; children below `n` are points
+struct Merge {
+    left: usize,
+    right: usize,
+    distance: f32,
+    size: usize,
+}
+
+/// A cluster in the condensed tree; index 0 is the root
+struct Cluster {
+    parent: usize,
+    /// Lambda (1 / distance) the cluster split off its parent at
+    birth: f64,
+    stability: f64,
+    children: Vec<usize>,
+}
+
+fn lambda(distance: f32) -> f64 {
+    1.0 / (distance as f64).max(1e-9)
+}
+
+fn core_distances(n: usize, min_samples: usize, distance: &impl Fn(usize, usize) -> f32) -> Vec<f32> {
+    // min_samples counts the point itself
+    let k = min_samples.saturating_sub(1).min(n - 1);
+    if k == 0 {
+        return vec![0.0; n];
+    }
+    let mut row = Vec::with_capacity(n - 1);
+    (0..n)
+        .map(|i| {
+            row.clear();
+            row.extend((0..n).filter(|&j| j != i).map(|j| distance(i, j)));
+            *row.select_nth_unstable_by(k - 1, |a, b| a.total_cmp(b)).1
+        })
+        .collect()
+}
+
+/// Prim's algorithm on the complete mutual-reachability graph, edges sorted by weight
+fn spanning_tree(n: usize, core: &[f32], distance: &impl Fn(usize, usize) -> f32) -> Vec<(usize, usize, f32)> {
+    let mut in_tree = vec![false; n];
+    let mut best = vec![(f32::INFINITY, 0usize); n];
+    let mut edges = Vec::with_capacity(n - 1);
+    let mut current = 0;
+    in_tree[0] = true;
+    for _ in 1..n {
+        let mut next = usize::MAX;
+        for j in 0..n {
+            if in_tree[j] {
+                continue;
+            }
+            let d = distance(current, j).max(core[current]).max(core[j]);
+            if d < best[j].0 {
+                best[j] = (d, current);
+            }
+            if next == usize::MAX || best[j].0 < best[next].0 {
+                next = j;
+            }
+        }
+        edges.push((best[next].1, next, best[next].0));
+        in_tree[next] = true;
+        current = next;
+    }
+    edges.sort_by(|a, b| a.2.total_cmp(&b.2));
+    edges
+}
+
+fn find(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+/// Single-linkage tree: merge `i` is node `n + i`, the last one is the root
+fn linkage(n: usize, edges: &[(usize, usize, f32)]) -> Vec<Merge> {
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut node: Vec<usize> = (0..n).collect();
+    let mut merges: Vec<Merge> = Vec::with_capacity(n - 1);
+    for &(a, b, distance) in edges {
+        let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+        let size = |id: usize, merges: &[Merge]| if id < n { 1 } else { merges[id - n].size };
+        let (left, right) = (node[ra], node[rb]);
+        let size = size(left, &merges) + size(right, &merges);
+        merges.push(Merge { left, right, distance, size });
+        parent[rb] = ra;
+        node[ra] = n + merges.len() - 1;
+    }
+    merges
+}
+
+fn leaves(n: usize, merges: &[Merge], root: usize) -> Vec<usize> {
+    let mut out = Vec::new();
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        if id < n {
+            out.push(id);
+        } else {
+            stack.push(merges[id - n].left);
+            stack.push(merges[id - n].right);
+        }
+    }
+    out
+}
+
+/// Cluster the points `0..n`. Returns clusters of point indices; points in none are noise.
+///
+/// When the hierarchy never splits into two clusters of `min_cluster_size`, the points within
+/// `single_cluster_distance` of the rest form one cluster, so a batch holding a single person
+/// isn't all noise.
+pub fn hdbscan(
+    n: usize,
+    min_cluster_size: usize,
+    min_samples: usize,
+    single_cluster_distance: f32,
+    distance: impl Fn(usize, usize) -> f32,
+) -> Vec<Vec<usize>> {
+    let min_cluster_size = min_cluster_size.max(2);
+    if n < min_cluster_size {
+        return Vec::new();
+    }
+    let core = core_distances(n, min_samples.max(1), &distance);
+    let merges = linkage(n, &spanning_tree(n, &core, &distance));
+
+    // Condense: walk down from the root, following splits where both sides are big enough
+    let mut clusters = vec![Cluster { parent: 0, birth: 0.0, stability: 0.0, children: Vec::new() }];
+    let mut point_cluster = vec![0usize; n];
+    let mut point_lambda = vec![0f64; n];
+    let mut label = vec![usize::MAX; merges.len()];
+    let root = n + merges.len() - 1;
+    label[root - n] = 0;
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        let merge = &merges[id - n];
+        let c = label[id - n];
+        let lam = lambda(merge.distance);
+        let size = |child: usize| if child < n { 1 } else { merges[child - n].size };
+        let sides = [merge.left, merge.right];
+        let big: Vec<bool> = sides.iter().map(|&s| size(s) >= min_cluster_size).collect();
+        for (&side, &is_big) in sides.iter().zip(&big) {
+            if !is_big {
+                for p in leaves(n, &merges, side) {
+                    point_cluster[p] = c;
+                    point_lambda[p] = lam;
+                    clusters[c].stability += lam - clusters[c].birth;
+                }
+            } else if big[0] && big[1] {
+                let k = clusters.len();
+                clusters[c].stability += (lam - clusters[c].birth) * size(side) as f64;
+                clusters[c].children.push(k);
+                clusters.push(Cluster { parent: c, birth: lam, stability: 0.0, children: Vec::new() });
+                label[side - n] = k;
+                stack.push(side);
+            } else {
+                // The cluster just lost some points; it carries on under the same label
+                label[side - n] = c;
+                stack.push(side);
+            }
+        }
+    }
+
+    // Excess of mass: keep a cluster unless its descendants together are more stable.
+    // Children always come after their parent, so walking backwards sees them first.
+    let mut value: Vec<f64> = clusters.iter().map(|c| c.stability).collect();
+    let mut keep = vec![false; clusters.len()];
+    for k in (1..clusters.len()).rev() {
+        let below: f64 = clusters[k].children.iter().map(|&c| value[c]).sum();
+        keep[k] = clusters[k].stability >= below;
+        value[k] = value[k].max(below);
+    }
+    let mut chosen = vec![false; clusters.len()];
+    let mut covered = vec![false; clusters.len()];
+    for k in 1..clusters.len() {
+        let p = clusters[k].parent;
+        covered[k] = covered[p] || chosen[p];
+        chosen[k] = keep[k] && !covered[k];
+    }
+
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); clusters.len()];
+    for (p, &start) in point_cluster.iter().enumerate() {
+        let mut c = start;
+        while c != 0 && !chosen[c] {
+            c = clusters[c].parent;
+        }
+        if c != 0 {
+            members[c].push(p);
+        }
+    }
+    if clusters.len() == 1 {
+        members[0] = (0..n).filter(|&p| point_lambda[p] >= lambda(single_cluster_distance)).collect();
+        if members[0].len() < min_cluster_size {
+            members[0].clear();
+        }
+    }
+    members.retain(|m| !m.is_empty());
+    members.sort_by_key(|m| m[0]);
+    members
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn euclidean(points: &[(f32, f32)]) -> impl Fn(usize, usize) -> f32 + '_ {
+        |a, b| ((points[a].0 - points[b].0).powi(2) + (points[a].1 - points[b].1).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn test_hdbscan() {
+        // A tight group, a spread-out group no single DBSCAN radius fits alongside it, and an outlier
+        let mut points = Vec::new();
+        for i in 0..6 {
+            points.push((i as f32 * 0.01, 0.0));
+        }
+        for i in 0..6 {
+            points.push((10.0 + i as f32 * 0.8, 5.0 + (i % 2) as f32 * 0.8));
+        }
+        points.push((-30.0, 40.0));
+        let clusters = hdbscan(points.len(), 3, 2, 0.5, euclidean(&points));
+        assert_eq!(clusters, vec![(0..6).collect::<Vec<_>>(), (6..12).collect()]);
+
+        // One group only: kept as a single cluster, the far point stays noise
+        let single: Vec<(f32, f32)> = vec![(0.0, 0.0), (0.1, 0.0), (0.0, 0.1), (0.1, 0.1), (9.0, 9.0)];
+        assert_eq!(hdbscan(single.len(), 3, 2, 0.5, euclidean(&single)), vec![vec![0, 1, 2, 3]]);
+        assert!(hdbscan(single.len(), 3, 2, 0.01, euclidean(&single)).is_empty());
+
+        assert!(hdbscan(2, 3, 2, 0.5, |_, _| 0.0).is_empty());
+        // Duplicates collapse without dividing by zero
+        assert_eq!(hdbscan(4, 2, 2, 0.5, |_, _| 0.0), vec![vec![0, 1, 2, 3]]);
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::utils::ffmpeg;
+
+pub const DEFAULT_MAX_SLIDES: usize = 20;
+pub const DEFAULT_SLIDE_SECS: f32 = 3.0;
+const FADE_SECS: f32 = 0.75;
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+const FPS: u32 = 30;
+
+/// An asset that may appear in a highlight video
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighlightCandidate {
+    pub id: i64,
+    pub taken_at: Option<i64>,
+    pub rating: i64,
+    pub favorite: bool,
+    pub face_count: i64,
+}
+
+impl HighlightCandidate {
+    /// Favorites and high ratings dominate; photos with people beat scenery on ties
+    pub fn score(&self) -> i64 {
+        self.rating * 2 + if self.favorite { 3 } else { 0 } + self.face_count.min(3)
+    }
+}
+
+/// Best `max` candidates, returned in the order they were taken
+pub fn pick_highlights(candidates: &[HighlightCandidate], max: usize) -> Vec<i64> {
+    let mut ranked: Vec<&HighlightCandidate> = candidates.iter().collect();
+    ranked.sort_by(|a, b| b.score().cmp(&a.score()).then(a.taken_at.cmp(&b.taken_at)).then(a.id.cmp(&b.id)));
+    ranked.truncate(max);
+    ranked.sort_by(|a, b| a.taken_at.cmp(&b.taken_at).then(a.id.cmp(&b.id)));
+    ranked.into_iter().map(|c| c.id).collect()
+}
+
+/// Next asset for a photo frame: the best-scored one not yet `shown` this cycle, ties broken by `seed`.
+/// None once every candidate has been shown.
+pub fn pick_next(candidates: &[HighlightCandidate], shown: &std::collections::HashSet<i64>, seed: u64) -> Option<i64> {
+    let fresh: Vec<&HighlightCandidate> = candidates.iter().filter(|c| !shown.contains(&c.id)).collect();
+    let best = fresh.iter().map(|c| c.score()).max()?;
+    let tier: Vec<i64> = fresh.iter().filter(|c| c.score() == best).map(|c| c.id).collect();
+    Some(tier[(seed % tier.len() as u64) as usize])
+}
+
+/// Where an album's highlight video is cached
+pub fn highlight_path(derived: &Path, album_id: i64) -> PathBuf {
+    crate::utils::path::long_path(&derived.join("highlights").join(format!("album-{}.mp4", album_id)))
+}
+
+/// ffmpeg arguments for a slideshow of still images joined with crossfades
+pub fn slideshow_args(images: &[PathBuf], dst: &Path, slide_secs: f32) -> Vec<String> {
+    let fade = FADE_SECS.min(slide_secs / 2.0);
+    let mut args: Vec<String> = vec!["-y".into(), "-hide_banner".into(), "-loglevel".into(), "error".into()];
+    for image in images {
+        args.extend(["-loop".into(), "1".into(), "-t".into(), format!("{:.2}", slide_secs), "-i".into(), image.to_string_lossy().into_owned()]);
+    }
+
+    // Letterbox every slide to the same frame, then chain xfades; each fade starts `fade` before the previous clip ends
+    let mut filter = String::new();
+    for i in 0..images.len() {
+        filter.push_str(&format!(
+            "[{i}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p[v{i}];",
+            i = i, w = WIDTH, h = HEIGHT, fps = FPS
+        ));
+    }
+    let mut last = "v0".to_string();
+    for i in 1..images.len() {
+        let offset = i as f32 * (slide_secs - fade);
+        let out = format!("x{}", i);
+        filter.push_str(&format!("[{}][v{}]xfade=transition=fade:duration={:.2}:offset={:.2}[{}];", last, i, fade, offset, out));
+        last = out;
+    }
+    filter.pop();
+
+    args.extend([
+        "-filter_complex".into(), filter,
+        "-map".into(), format!("[{}]", last),
+        "-c:v".into(), "libx264".into(),
+        "-preset".into(), "veryfast".into(),
+        "-crf".into(), "23".into(),
+        "-pix_fmt".into(), "yuv420p".into(),
+        "-movflags".into(), "+faststart".into(),
+        "-an".into(),
+        dst.to_string_lossy().into_owned(),
+    ]);
+    args
+}
+
+/// Render the slideshow to `dst`, writing a temporary file first so a failed run leaves no partial video
+pub fn render_slideshow(images: &[PathBuf], dst: &Path, slide_secs: f32) -> Result<()> {
+    if images.is_empty() {
+        anyhow::bail!("no images to render");
+    }
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let partial = dst.with_extension("partial.mp4");
+    let timeout = Duration::from_secs(60 + images.len() as u64 * 15);
+    let output = ffmpeg::run_ffmpeg_with_timeout(slideshow_args(images, &partial, slide_secs), timeout)?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&partial);
+        anyhow::bail!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    std::fs::rename(&partial, dst)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: i64, taken_at: i64, rating: i64, favorite: bool, face_count: i64) -> HighlightCandidate {
+        HighlightCandidate { id, taken_at: Some(taken_at), rating, favorite, face_count }
+    }
+
+    #[test]
+    fn test_pick_highlights() {
+        let candidates = vec![
+            candidate(1, 500, 0, false, 0),
+            candidate(2, 100, 5, false, 0),
+            candidate(3, 300, 0, true, 2),
+            candidate(4, 200, 0, false, 1),
+            candidate(5, 400, 3, false, 0),
+        ];
+        // Scores: 0, 10, 5, 1, 6 -> top three are 2, 5, 3, then back in time order
+        assert_eq!(pick_highlights(&candidates, 3), vec![2, 3, 5]);
+        assert_eq!(pick_highlights(&candidates, 10).len(), 5);
+
+        let mut shown = std::collections::HashSet::new();
+        let order: Vec<i64> = std::iter::from_fn(|| {
+            let next = pick_next(&candidates, &shown, 7)?;
+            shown.insert(next);
+            Some(next)
+        }).collect();
+        assert_eq!(order, vec![2, 5, 3, 4, 1]);
+        let ties = vec![candidate(1, 0, 0, false, 0), candidate(2, 0, 0, false, 0)];
+        assert_ne!(pick_next(&ties, &Default::default(), 0), pick_next(&ties, &Default::default(), 1));
+    }
+
+    #[test]
+    fn test_slideshow_args_chain_crossfades() {
+        let images: Vec<PathBuf> = ["/d/a.webp", "/d/b.webp", "/d/c.webp"].iter().map(PathBuf::from).collect();
+        let args = slideshow_args(&images, Path::new("/d/out.mp4"), 3.0);
+        assert_eq!(args.iter().filter(|a| *a == "-loop").count(), 3);
+        let filter = &args[args.iter().position(|a| a == "-filter_complex").unwrap() + 1];
+        assert!(filter.contains("[v0][v1]xfade=transition=fade:duration=0.75:offset=2.25[x1]"));
+        assert!(filter.contains("[x1][v2]xfade=transition=fade:duration=0.75:offset=4.50[x2]"));
+        assert!(args.contains(&"[x2]".to_string()));
+        assert_eq!(args.last().unwrap(), "/d/out.mp4");
+
+        let single = slideshow_args(&images[..1], Path::new("/d/out.mp4"), 3.0);
+        assert!(single.contains(&"[v0]".to_string()));
+    }
+}
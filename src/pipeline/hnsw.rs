@@ -0,0 +1,457 @@
+//! Approximate nearest neighbours by cosine distance with a hierarchical navigable small world
+//! graph (Malkov & Yashunin 2016).
+//!
+//! Every vector is a node on layer 0 and, with geometrically falling odds, on the layers above.
+//! A search walks greedily down from the single node on the top layer and then explores layer 0
+//! breadth-first, keeping the `ef` closest nodes seen, so it touches O(log n) nodes instead of all
+//! of them. Vectors are stored at unit length, which makes cosine distance `1 - dot`.
+//!
+//! Removal leaves the node in place as a waypoint and hides it from results; once a quarter of
+//! the nodes are removed the graph is rebuilt from the rest. [`Hnsw::to_bytes`] and
+//! [`Hnsw::from_bytes`] save and restore the whole graph, so it isn't rebuilt on every start.
+
+use anyhow::{ensure, Context, Result};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+const MAGIC: &[u8; 8] = b"SEENHNSW";
+const FORMAT_VERSION: u32 = 1;
+/// Layers above this are never drawn; with m = 16 the odds of reaching it are 16^-16
+const MAX_LEVEL: usize = 16;
+/// Removed nodes tolerated before a rebuild, below which rebuilding isn't worth it
+const MIN_REBUILD: usize = 64;
+
+struct Node {
+    id: i64,
+    vector: Vec<f32>,
+    /// Neighbours on each layer the node is on, layer 0 first
+    links: Vec<Vec<u32>>,
+    removed: bool,
+}
+
+/// A node and its distance to the query, ordered by distance
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate(f32, u32);
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>()
+}
+
+pub struct Hnsw {
+    /// Neighbours kept per node on the upper layers; layer 0 keeps twice as many
+    m: usize,
+    /// Candidates considered when linking a new node
+    ef_construction: usize,
+    dim: usize,
+    nodes: Vec<Node>,
+    /// Node of every id not removed
+    slots: HashMap<i64, u32>,
+    entry: Option<u32>,
+    removed: usize,
+    /// xorshift state for drawing levels; fixed seed so builds are reproducible
+    rng: u64,
+}
+
+impl Hnsw {
+    pub fn new(m: usize, ef_construction: usize) -> Self {
+        Self {
+            m: m.max(2),
+            ef_construction: ef_construction.max(1),
+            dim: 0,
+            nodes: Vec::new(),
+            slots: HashMap::new(),
+            entry: None,
+            removed: 0,
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn contains(&self, id: i64) -> bool {
+        self.slots.contains_key(&id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = i64> + '_ {
+        self.slots.keys().copied()
+    }
+
+    fn random_level(&mut self) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        let uniform = ((self.rng >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        let level = -uniform.ln() / (self.m as f64).ln();
+        (level as usize).min(MAX_LEVEL)
+    }
+
+    fn top_layer(&self) -> usize {
+        self.entry.map_or(0, |e| self.nodes[e as usize].links.len() - 1)
+    }
+
+    fn dist(&self, query: &[f32], node: u32) -> f32 {
+        distance(query, &self.nodes[node as usize].vector)
+    }
+
+    /// The `ef` nodes closest to `query` reachable on `layer` from `entries`, closest first
+    fn search_layer(&self, query: &[f32], entries: &[u32], ef: usize, layer: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = entries.iter().copied().collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+        let mut results: BinaryHeap<Candidate> = BinaryHeap::new();
+        for &e in entries {
+            let c = Candidate(self.dist(query, e), e);
+            candidates.push(Reverse(c));
+            results.push(c);
+        }
+        while let Some(Reverse(Candidate(d, node))) = candidates.pop() {
+            if results.len() >= ef && results.peek().is_some_and(|worst| d > worst.0) {
+                break;
+            }
+            for &next in &self.nodes[node as usize].links[layer] {
+                if !visited.insert(next) {
+                    continue;
+                }
+                let c = Candidate(self.dist(query, next), next);
+                if results.len() < ef || results.peek().is_some_and(|worst| c < *worst) {
+                    candidates.push(Reverse(c));
+                    results.push(c);
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+        results.into_sorted_vec()
+    }
+
+    /// Pick up to `m` of `candidates` (closest first), skipping ones closer to an already picked
+    /// neighbour than to the node, so links spread out instead of all pointing into one clump.
+    /// Skipped candidates fill any places left.
+    fn select_neighbours(&self, candidates: &[Candidate], m: usize) -> Vec<u32> {
+        let mut selected: Vec<u32> = Vec::with_capacity(m);
+        let mut skipped = Vec::new();
+        for &Candidate(d, c) in candidates {
+            if selected.len() >= m {
+                break;
+            }
+            let vector = &self.nodes[c as usize].vector;
+            if selected.iter().all(|&s| self.dist(vector, s) > d) {
+                selected.push(c);
+            } else {
+                skipped.push(c);
+            }
+        }
+        let room = m.saturating_sub(selected.len());
+        selected.extend(skipped.into_iter().take(room));
+        selected
+    }
+
+    /// Add `vector` under `id`, replacing any vector already stored for it
+    pub fn insert(&mut self, id: i64, vector: &[f32]) -> Result<()> {
+        ensure!(!vector.is_empty(), "empty vector");
+        if self.nodes.is_empty() {
+            self.dim = vector.len();
+        }
+        ensure!(vector.len() == self.dim, "vector has {} dimensions, the index {}", vector.len(), self.dim);
+        self.remove(id);
+
+        let vector = normalized(vector);
+        let level = self.random_level();
+        let slot = self.nodes.len() as u32;
+        self.nodes.push(Node { id, vector, links: vec![Vec::new(); level + 1], removed: false });
+        self.slots.insert(id, slot);
+        let Some(entry) = self.entry else {
+            self.entry = Some(slot);
+            return Ok(());
+        };
+
+        let query = self.nodes[slot as usize].vector.clone();
+        let top = self.top_layer();
+        let mut entries = vec![entry];
+        for layer in (level + 1..=top).rev() {
+            entries = vec![self.search_layer(&query, &entries, 1, layer)[0].1];
+        }
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &entries, self.ef_construction, layer);
+            let max_links = if layer == 0 { 2 * self.m } else { self.m };
+            let neighbours = self.select_neighbours(&found, self.m);
+            for &n in &neighbours {
+                let links = &mut self.nodes[n as usize].links[layer];
+                links.push(slot);
+                if links.len() > max_links {
+                    let vector = &self.nodes[n as usize].vector;
+                    let mut candidates: Vec<Candidate> = self.nodes[n as usize].links[layer]
+                        .iter()
+                        .map(|&l| Candidate(self.dist(vector, l), l))
+                        .collect();
+                    candidates.sort();
+                    self.nodes[n as usize].links[layer] = self.select_neighbours(&candidates, max_links);
+                }
+            }
+            self.nodes[slot as usize].links[layer] = neighbours;
+            entries = found.iter().map(|c| c.1).collect();
+        }
+        if level > top {
+            self.entry = Some(slot);
+        }
+        Ok(())
+    }
+
+    /// Hide `id` from searches; returns whether it was in the index
+    pub fn remove(&mut self, id: i64) -> bool {
+        let Some(slot) = self.slots.remove(&id) else {
+            return false;
+        };
+        self.nodes[slot as usize].removed = true;
+        self.removed += 1;
+        if self.removed >= MIN_REBUILD && self.removed * 4 >= self.nodes.len() {
+            self.rebuild();
+        }
+        true
+    }
+
+    /// Build the graph again from the nodes not removed
+    fn rebuild(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        self.slots.clear();
+        self.entry = None;
+        self.removed = 0;
+        for node in nodes.into_iter().filter(|n| !n.removed) {
+            // Same dimension as before, so this can't fail
+            let _ = self.insert(node.id, &node.vector);
+        }
+    }
+
+    /// The `k` closest ids to `query` with their cosine distances, closest first. `ef` trades
+    /// speed for recall; it is raised to `k` when smaller.
+    pub fn search(&self, query: &[f32], k: usize, ef: usize) -> Vec<(i64, f32)> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        if query.len() != self.dim || k == 0 {
+            return Vec::new();
+        }
+        let query = normalized(query);
+        let mut entries = vec![entry];
+        for layer in (1..=self.top_layer()).rev() {
+            entries = vec![self.search_layer(&query, &entries, 1, layer)[0].1];
+        }
+        // Removed nodes still take up places in the candidate list
+        let ef = ef.max(k) + self.removed.min(ef.max(k));
+        self.search_layer(&query, &entries, ef, 0)
+            .into_iter()
+            .filter(|c| !self.nodes[c.1 as usize].removed)
+            .take(k)
+            .map(|c| (self.nodes[c.1 as usize].id, c.0))
+            .collect()
+    }
+
+    /// Serialize the graph, little-endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64 + self.nodes.len() * (self.dim * 4 + self.m * 12 + 16));
+        out.extend_from_slice(MAGIC);
+        for value in [FORMAT_VERSION, self.m as u32, self.ef_construction as u32, self.dim as u32] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&self.rng.to_le_bytes());
+        out.extend_from_slice(&self.entry.unwrap_or(u32::MAX).to_le_bytes());
+        out.extend_from_slice(&(self.nodes.len() as u32).to_le_bytes());
+        for node in &self.nodes {
+            out.extend_from_slice(&node.id.to_le_bytes());
+            out.push(node.removed as u8);
+            out.push(node.links.len() as u8);
+            for x in &node.vector {
+                out.extend_from_slice(&x.to_le_bytes());
+            }
+            for links in &node.links {
+                out.extend_from_slice(&(links.len() as u32).to_le_bytes());
+                for l in links {
+                    out.extend_from_slice(&l.to_le_bytes());
+                }
+            }
+        }
+        out
+    }
+
+    /// Restore a graph saved with [`Hnsw::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes, pos: 0 };
+        ensure!(reader.take(MAGIC.len())? == MAGIC, "not a face index file");
+        let version = reader.u32()?;
+        ensure!(version == FORMAT_VERSION, "unsupported face index version {}", version);
+        let (m, ef_construction, dim) = (reader.u32()? as usize, reader.u32()? as usize, reader.u32()? as usize);
+        let rng = reader.u64()?;
+        let entry = reader.u32()?;
+        let count = reader.u32()? as usize;
+
+        let mut index = Self::new(m, ef_construction);
+        index.dim = dim;
+        index.rng = rng;
+        index.nodes.reserve(count);
+        for slot in 0..count {
+            let id = reader.u64()? as i64;
+            let removed = reader.take(1)?[0] != 0;
+            let layers = reader.take(1)?[0] as usize;
+            ensure!((1..=MAX_LEVEL + 1).contains(&layers), "node {} has {} layers", id, layers);
+            let vector = (0..dim).map(|_| reader.u32().map(f32::from_bits)).collect::<Result<Vec<_>>>()?;
+            let mut links = Vec::with_capacity(layers);
+            for _ in 0..layers {
+                let n = reader.u32()? as usize;
+                let layer = (0..n).map(|_| reader.u32()).collect::<Result<Vec<_>>>()?;
+                ensure!(layer.iter().all(|&l| (l as usize) < count), "node {} links outside the index", id);
+                links.push(layer);
+            }
+            if removed {
+                index.removed += 1;
+            } else {
+                index.slots.insert(id, slot as u32);
+            }
+            index.nodes.push(Node { id, vector, links, removed });
+        }
+        ensure!(reader.pos == bytes.len(), "trailing data after the face index");
+        index.entry = match entry {
+            u32::MAX => None,
+            e => {
+                ensure!((e as usize) < count, "entry point outside the index");
+                Some(e)
+            }
+        };
+        // Every neighbour must be on the layer it is linked from
+        for node in &index.nodes {
+            for (layer, links) in node.links.iter().enumerate() {
+                ensure!(links.iter().all(|&l| index.nodes[l as usize].links.len() > layer), "node {} links below its layer", node.id);
+            }
+        }
+        Ok(index)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len()).context("face index file is truncated")?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random unit vectors
+    fn vectors(n: usize, dim: usize) -> Vec<Vec<f32>> {
+        let mut state = 42u64;
+        (0..n)
+            .map(|_| {
+                (0..dim)
+                    .map(|_| {
+                        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn exact(points: &[Vec<f32>], query: &[f32], k: usize, skip: &HashSet<i64>) -> Vec<i64> {
+        let q = normalized(query);
+        let mut all: Vec<(f32, i64)> = points
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !skip.contains(&(*i as i64)))
+            .map(|(i, p)| (distance(&q, &normalized(p)), i as i64))
+            .collect();
+        all.sort_by(|a, b| a.0.total_cmp(&b.0));
+        all.into_iter().take(k).map(|(_, id)| id).collect()
+    }
+
+    fn recall(index: &Hnsw, points: &[Vec<f32>], queries: &[Vec<f32>], skip: &HashSet<i64>) -> f32 {
+        let mut hits = 0;
+        for q in queries {
+            let truth: HashSet<i64> = exact(points, q, 10, skip).into_iter().collect();
+            hits += index.search(q, 10, 64).iter().filter(|(id, _)| truth.contains(id)).count();
+        }
+        hits as f32 / (queries.len() * 10) as f32
+    }
+
+    #[test]
+    fn test_hnsw() {
+        let points = vectors(1000, 32);
+        let queries = vectors(1050, 32).split_off(1000);
+        let mut index = Hnsw::new(16, 64);
+        for (i, p) in points.iter().enumerate() {
+            index.insert(i as i64, p).unwrap();
+        }
+        assert_eq!(index.len(), 1000);
+        assert!(recall(&index, &points, &queries, &HashSet::new()) > 0.9);
+        // A stored vector finds itself first, at distance ~0
+        let (id, d) = index.search(&points[77], 1, 16)[0];
+        assert_eq!(id, 77);
+        assert!(d.abs() < 1e-5);
+        assert!(index.insert(5000, &[1.0, 2.0]).is_err());
+
+        // Removed ids never come back, before and after the rebuild a third of them trigger
+        let removed: HashSet<i64> = (0..334).map(|i| i * 3).collect();
+        for &id in &removed {
+            assert!(index.remove(id));
+            assert!(index.search(&points[id as usize], 5, 32).iter().all(|(found, _)| *found != id));
+        }
+        assert!(!index.remove(0));
+        assert_eq!(index.len(), 666);
+        assert!(index.nodes.len() < 1000, "rebuild should have dropped removed nodes");
+        assert!(recall(&index, &points, &queries, &removed) > 0.9);
+
+        // Saved and restored, the graph answers the same
+        let restored = Hnsw::from_bytes(&index.to_bytes()).unwrap();
+        assert_eq!(restored.len(), index.len());
+        for q in &queries {
+            assert_eq!(restored.search(q, 10, 64), index.search(q, 10, 64));
+        }
+        let bytes = index.to_bytes();
+        assert!(Hnsw::from_bytes(&bytes[..bytes.len() - 3]).is_err());
+        assert!(Hnsw::from_bytes(b"not an index").is_err());
+        assert!(Hnsw::from_bytes(&Hnsw::new(16, 100).to_bytes()).unwrap().search(&points[0], 3, 10).is_empty());
+    }
+}
@@ -0,0 +1,79 @@
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Ingest rules shared by the discover forwarder, metadata workers and the settings API
+pub type SharedIngestFilter = Arc<RwLock<IngestFilter>>;
+
+/// Keeps junk such as backup thumbnails, icons and unwanted formats out of the assets table.
+/// Zero or empty fields don't filter.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngestFilter {
+    /// Files smaller than this are skipped before hashing
+    pub min_bytes: u64,
+    /// Images narrower than this are dropped once their dimensions are read
+    pub min_width: u32,
+    /// Images shorter than this are dropped once their dimensions are read
+    pub min_height: u32,
+    /// Lowercase extensions without the dot; empty allows every supported format
+    pub extensions: Vec<String>,
+}
+
+impl IngestFilter {
+    /// Lowercase extensions, strip leading dots, drop blanks and duplicates
+    pub fn normalized(mut self) -> Self {
+        let mut extensions: Vec<String> = self.extensions.iter()
+            .map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect();
+        extensions.sort();
+        extensions.dedup();
+        self.extensions = extensions;
+        self
+    }
+
+    /// Parse a comma-separated extension list such as `jpg,.HEIC, png`
+    pub fn parse_extensions(list: &str) -> Vec<String> {
+        list.split(',').map(str::to_string).collect()
+    }
+
+    /// Checked at discovery, before the file is hashed
+    pub fn allows_file(&self, size_bytes: i64, ext: &str) -> bool {
+        if size_bytes < self.min_bytes as i64 {
+            return false;
+        }
+        self.extensions.is_empty() || self.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext))
+    }
+
+    /// Checked once an image's dimensions are known; unknown dimensions pass
+    pub fn allows_dimensions(&self, width: Option<i64>, height: Option<i64>) -> bool {
+        width.is_none_or(|w| w >= self.min_width as i64) && height.is_none_or(|h| h >= self.min_height as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ingest_filter() {
+        let open = IngestFilter::default();
+        assert!(open.allows_file(0, "jpg") && open.allows_dimensions(Some(1), Some(1)));
+
+        let filter = IngestFilter {
+            min_bytes: 10_000,
+            min_width: 200,
+            min_height: 200,
+            extensions: IngestFilter::parse_extensions("JPG, .heic,,jpg"),
+        }.normalized();
+        assert_eq!(filter.extensions, vec!["heic", "jpg"]);
+        assert!(filter.allows_file(10_000, "JPG"));
+        assert!(!filter.allows_file(2_048, "jpg"));
+        assert!(!filter.allows_file(50_000, "png"));
+        assert!(filter.allows_dimensions(Some(4000), Some(3000)));
+        assert!(!filter.allows_dimensions(Some(32), Some(32)));
+        assert!(!filter.allows_dimensions(Some(1920), Some(100)));
+        assert!(filter.allows_dimensions(None, None));
+    }
+}
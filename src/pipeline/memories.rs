@@ -0,0 +1,179 @@
+use chrono::{DateTime, Datelike, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+
+/// A quiet spell this long ends an event
+pub const EVENT_GAP_SECS: i64 = 12 * 3600;
+/// Fewer photos than this is an ordinary day, not an event
+pub const MIN_EVENT_ASSETS: usize = 15;
+
+/// An asset with a capture date
+#[derive(Debug, Clone)]
+pub struct DatedAsset {
+    pub id: i64,
+    pub taken_at: i64,
+    pub dirname: String,
+}
+
+/// A burst of photos close together in time: a trip, a party, a wedding
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub start: i64,
+    pub end: i64,
+    pub asset_count: usize,
+    /// The middle photo, a fair pick for a link back into the library
+    pub cover_asset_id: i64,
+    /// Name of the folder most of the event's photos are in, unless it's only a date or number
+    pub title: Option<String>,
+}
+
+/// A calendar entry: an event's anniversary or photos taken on this day in earlier years
+#[derive(Debug, Clone, PartialEq)]
+pub struct Memory {
+    /// Stable identifier for calendar clients
+    pub key: String,
+    pub date: NaiveDate,
+    pub summary: String,
+    pub description: String,
+    pub asset_id: i64,
+}
+
+fn date_of(ts: i64) -> Option<NaiveDate> {
+    DateTime::from_timestamp(ts, 0).map(|d| d.date_naive())
+}
+
+fn folder_title(dirname: &str) -> Option<String> {
+    let name = dirname.trim_end_matches(['/', '\\']).rsplit(['/', '\\']).next()?.trim();
+    name.chars().any(|c| c.is_alphabetic()).then(|| name.to_string())
+}
+
+/// Split `assets` (sorted by `taken_at`) into events
+pub fn detect_events(assets: &[DatedAsset]) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut start = 0;
+    for i in 1..=assets.len() {
+        if i < assets.len() && assets[i].taken_at - assets[i - 1].taken_at <= EVENT_GAP_SECS {
+            continue;
+        }
+        let run = &assets[start..i];
+        start = i;
+        if run.len() < MIN_EVENT_ASSETS {
+            continue;
+        }
+        let mut folders: HashMap<&str, usize> = HashMap::new();
+        for asset in run {
+            *folders.entry(asset.dirname.as_str()).or_default() += 1;
+        }
+        let folder = folders.into_iter().max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0))).map(|(f, _)| f);
+        events.push(Event {
+            start: run[0].taken_at,
+            end: run[run.len() - 1].taken_at,
+            asset_count: run.len(),
+            cover_asset_id: run[run.len() / 2].id,
+            title: folder.and_then(folder_title),
+        });
+    }
+    events
+}
+
+fn years_since(years: i32) -> String {
+    if years == 1 { "1 year".to_string() } else { format!("{} years", years) }
+}
+
+/// Anniversaries of `events` falling between `from` and `to` (inclusive)
+pub fn anniversaries(events: &[Event], from: NaiveDate, to: NaiveDate) -> Vec<Memory> {
+    let mut out = Vec::new();
+    for event in events {
+        let (Some(start), Some(end)) = (date_of(event.start), date_of(event.end)) else {
+            continue;
+        };
+        for year in from.year()..=to.year() {
+            let years = year - start.year();
+            // Feb 29 events have no anniversary in other years
+            let Some(date) = start.with_year(year).filter(|d| years > 0 && *d >= from && *d <= to) else {
+                continue;
+            };
+            let what = event.title.clone().unwrap_or_else(|| format!("{} photos", event.asset_count));
+            let span = if end == start {
+                start.format("%-d %b %Y").to_string()
+            } else {
+                format!("{} – {}", start.format("%-d %b %Y"), end.format("%-d %b %Y"))
+            };
+            out.push(Memory {
+                key: format!("event-{}-{}", event.start, years),
+                date,
+                summary: format!("{} since {}", years_since(years), what),
+                description: format!("{} photos, {}", event.asset_count, span),
+                asset_id: event.cover_asset_id,
+            });
+        }
+    }
+    out.sort_by(|a, b| a.date.cmp(&b.date).then(a.key.cmp(&b.key)));
+    out
+}
+
+/// Days between `from` and `to` with photos from the same day in earlier years
+pub fn on_this_day(assets: &[DatedAsset], from: NaiveDate, to: NaiveDate) -> Vec<Memory> {
+    // (month, day) -> year -> (count, latest asset)
+    let mut days: HashMap<(u32, u32), BTreeMap<i32, (usize, i64)>> = HashMap::new();
+    for asset in assets {
+        let Some(date) = date_of(asset.taken_at) else { continue };
+        let entry = days.entry((date.month(), date.day())).or_default().entry(date.year()).or_insert((0, asset.id));
+        entry.0 += 1;
+        entry.1 = asset.id;
+    }
+    let mut out = Vec::new();
+    for date in from.iter_days().take_while(|d| *d <= to) {
+        let Some(years) = days.get(&(date.month(), date.day())) else { continue };
+        let earlier: Vec<(i32, usize, i64)> = years.range(..date.year()).map(|(&y, &(n, id))| (y, n, id)).collect();
+        let Some(&(_, _, asset_id)) = earlier.last() else { continue };
+        let total: usize = earlier.iter().map(|(_, n, _)| n).sum();
+        let list: Vec<String> = earlier.iter().map(|(y, _, _)| y.to_string()).collect();
+        out.push(Memory {
+            key: format!("day-{}", date.format("%Y%m%d")),
+            date,
+            summary: format!("On this day: {} photo{}", total, if total == 1 { "" } else { "s" }),
+            description: format!("Taken on {} in {}", date.format("%-d %B"), list.join(", ")),
+            asset_id,
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(id: i64, ts: i64, dir: &str) -> DatedAsset {
+        DatedAsset { id, taken_at: ts, dirname: dir.to_string() }
+    }
+
+    #[test]
+    fn test_events_and_memories() {
+        // 2019-06-12 00:00 UTC; a three-day trip with a photo every two hours, then a lone photo a week later
+        let trip_start = 1560297600;
+        let mut assets: Vec<DatedAsset> = (0..36).map(|i| at(i + 1, trip_start + i * 7200, "/photos/2019/Iceland")).collect();
+        assets[0].dirname = "/photos/2019/06".to_string();
+        assets.push(at(100, trip_start + 8 * 86400, "/photos/2019/06"));
+
+        let events = detect_events(&assets);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].asset_count, events[0].cover_asset_id), (36, 19));
+        assert_eq!(events[0].title.as_deref(), Some("Iceland"));
+        assert_eq!(folder_title("/photos/2019/06"), None);
+
+        let from = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2025, 5, 31).unwrap();
+        let memories = anniversaries(&events, from, to);
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].date, NaiveDate::from_ymd_opt(2024, 6, 12).unwrap());
+        assert_eq!(memories[0].summary, "5 years since Iceland");
+        assert_eq!(memories[0].description, "36 photos, 12 Jun 2019 – 14 Jun 2019");
+
+        let days = on_this_day(&assets, NaiveDate::from_ymd_opt(2024, 6, 12).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 30).unwrap());
+        assert_eq!(days.iter().map(|m| m.date.day()).collect::<Vec<_>>(), vec![12, 13, 14, 20]);
+        assert_eq!(days[0].summary, "On this day: 12 photos");
+        assert_eq!(days[3].asset_id, 100);
+        // The day itself is not a memory yet
+        assert!(on_this_day(&assets, NaiveDate::from_ymd_opt(2019, 6, 12).unwrap(), NaiveDate::from_ymd_opt(2019, 6, 12).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! Media received through messaging apps. WhatsApp and Telegram save what others send into
+//! their own folders and names, strip EXIF and downscale to fixed sizes; those copies are
+//! tagged with the app and, by default, kept out of the timeline and memories.
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Where received media shows up, shared by the API and the memories digest
+pub type SharedReceivedMedia = Arc<RwLock<ReceivedMedia>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceApp {
+    WhatsApp,
+    Telegram,
+}
+
+impl SourceApp {
+    /// Stored in `assets.source_app` and used as the tag name
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SourceApp::WhatsApp => "whatsapp",
+            SourceApp::Telegram => "telegram",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceivedMedia {
+    /// Leave received media out of `GET /assets` unless asked for
+    #[serde(default = "yes")]
+    pub hide_in_timeline: bool,
+    /// Leave received media out of "on this day" memories, anniversaries and the digest
+    #[serde(default = "yes")]
+    pub hide_in_memories: bool,
+}
+
+fn yes() -> bool {
+    true
+}
+
+impl Default for ReceivedMedia {
+    fn default() -> Self {
+        Self { hide_in_timeline: true, hide_in_memories: true }
+    }
+}
+
+/// `IMG-20230514-WA0012.jpg` as saved by the phone apps, `WhatsApp Image 2023-05-14 at 15.30.12.jpeg` by WhatsApp Web
+static WHATSAPP_NAME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?:(?:IMG|VID|AUD|PTT|STK|DOC)-\d{8}-WA\d{4,}|WhatsApp (?:Image|Video) \d{4}-\d{2}-\d{2} at )").unwrap()
+});
+/// `photo_2023-05-14_15-30-12.jpg` as saved by Telegram Desktop; common enough elsewhere that it needs more evidence
+static TELEGRAM_NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^(?:photo|video)_\d{4}-\d{2}-\d{2}_\d{2}-\d{2}-\d{2}").unwrap());
+
+/// Longest side of the images Telegram sends, standard and high quality
+const TELEGRAM_LONG_EDGES: [i64; 2] = [1280, 2560];
+
+/// The messaging app a file was received through, if it looks like one. Folder names and the
+/// apps' own file names are enough; a name that only resembles one also needs the app's image
+/// size and no EXIF, which `exif_stripped` is asked for only then.
+pub fn detect(path: &str, filename: &str, dims: Option<(i64, i64)>, exif_stripped: impl FnOnce() -> bool) -> Option<SourceApp> {
+    let path = path.replace('\\', "/").to_lowercase();
+    let mut folders = path.split('/').rev().skip(1);
+    if let Some(app) = folders.find_map(|folder| {
+        let app_folder = |name: &str| folder == name || folder.strip_prefix(name).is_some_and(|rest| rest.starts_with(' '));
+        if app_folder("whatsapp") {
+            Some(SourceApp::WhatsApp)
+        } else if app_folder("telegram") {
+            Some(SourceApp::Telegram)
+        } else {
+            None
+        }
+    }) {
+        return Some(app);
+    }
+    if WHATSAPP_NAME.is_match(filename) {
+        return Some(SourceApp::WhatsApp);
+    }
+    let telegram_sized = dims.is_some_and(|(w, h)| TELEGRAM_LONG_EDGES.contains(&w.max(h)));
+    if TELEGRAM_NAME.is_match(filename) && telegram_sized && exif_stripped() {
+        return Some(SourceApp::Telegram);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let detect = |path: &str, dims, stripped| {
+            let filename = path.rsplit(['/', '\\']).next().unwrap();
+            detect(path, filename, dims, || stripped)
+        };
+        assert_eq!(detect("/sdcard/WhatsApp/Media/WhatsApp Images/Sent/x.jpg", None, false), Some(SourceApp::WhatsApp));
+        assert_eq!(detect("C:\\Users\\me\\Pictures\\Telegram Desktop\\cat.png", None, false), Some(SourceApp::Telegram));
+        assert_eq!(detect("/photos/phone/IMG-20230514-WA0012.jpg", None, false), Some(SourceApp::WhatsApp));
+        assert_eq!(detect("/photos/WhatsApp Image 2023-05-14 at 15.30.12.jpeg", None, false), Some(SourceApp::WhatsApp));
+
+        // A Telegram-like name alone isn't enough
+        assert_eq!(detect("/photos/photo_2023-05-14_15-30-12.jpg", Some((1280, 960)), true), Some(SourceApp::Telegram));
+        assert_eq!(detect("/photos/photo_2023-05-14_15-30-12.jpg", Some((4032, 3024)), true), None);
+        assert_eq!(detect("/photos/photo_2023-05-14_15-30-12.jpg", Some((1280, 960)), false), None);
+
+        // Folders that only start with the app's name, and the file name itself, don't count
+        assert_eq!(detect("/photos/whatsappish/IMG_1234.jpg", None, true), None);
+        assert_eq!(detect("/photos/trip/whatsapp", None, true), None);
+        assert_eq!(detect("/photos/IMG_20230514_153012.jpg", Some((1600, 1200)), true), None);
+    }
+}
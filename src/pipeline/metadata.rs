@@ -3,6 +3,11 @@ use tokio::sync::mpsc::{Receiver, Sender};
 use crate::db::writer::DbWriteItem;
 use crate::pipeline::hash::HashJob;
 use crate::pipeline::QueueGauges;
+use crate::pipeline::dates;
+use crate::pipeline::extract::{Extracted, SharedExtractors};
+use crate::pipeline::messaging;
+use crate::pipeline::plugins;
+use crate::stats::PipelineStage;
 use std::sync::Arc;
 
 #[derive(Clone, Debug)]
@@ -10,6 +15,8 @@ pub struct MetaJob {
     pub job: HashJob,
     pub xxh64: Option<i64>,
     pub sha256: Option<Vec<u8>>,
+    /// The hashes cover only part of the file, see `hash::hash_sampled`
+    pub sampled_hash: bool,
 }
 
 fn parse_duration_ms(v: &Value) -> Option<i64> {
@@ -20,33 +27,50 @@ fn parse_duration_ms(v: &Value) -> Option<i64> {
     None
 }
 
-async fn probe_video(path: &str) -> (Option<i64>, Option<i64>, Option<i64>, Option<String>) {
+#[derive(Default)]
+struct VideoProbe {
+    width: Option<i64>,
+    height: Option<i64>,
+    duration_ms: Option<i64>,
+    codec: Option<String>,
+    /// From the container's creation date tags
+    created_at: Option<i64>,
+}
+
+async fn probe_video(path: &str) -> VideoProbe {
     let args = ["-v", "quiet", "-print_format", "json", "-show_streams", "-show_format", path];
-    let (code, stdout, _) = crate::utils::exec::exec_capture("ffprobe", &args).await.unwrap_or((1, Vec::new(), Vec::new()));
-    if code != 0 { return (None, None, None, None); }
+    let (code, stdout, _) = crate::utils::exec::exec_capture(crate::utils::ffmpeg::ffprobe_bin(), &args).await.unwrap_or((1, Vec::new(), Vec::new()));
+    if code != 0 { return VideoProbe::default(); }
     if let Ok(v) = serde_json::from_slice::<Value>(&stdout) {
-        let mut w = None;
-        let mut h = None;
-        let mut codec = None;
+        let mut probe = VideoProbe::default();
         if let Some(streams) = v.get("streams").and_then(|x| x.as_array()) {
             for s in streams {
                 if let Some(c) = s.get("codec_type").and_then(|x| x.as_str()) {
                     if c == "video" {
-                        w = s.get("width").and_then(|x| x.as_i64());
-                        h = s.get("height").and_then(|x| x.as_i64());
-                        codec = s.get("codec_name").and_then(|x| x.as_str()).map(|s| s.to_string());
+                        probe.width = s.get("width").and_then(|x| x.as_i64());
+                        probe.height = s.get("height").and_then(|x| x.as_i64());
+                        probe.codec = s.get("codec_name").and_then(|x| x.as_str()).map(|s| s.to_string());
                         break;
                     }
                 }
             }
         }
-        let dur = v.get("format").and_then(|f| f.get("duration")).and_then(parse_duration_ms);
-        return (w, h, dur, codec);
+        probe.duration_ms = v.get("format").and_then(|f| f.get("duration")).and_then(parse_duration_ms);
+        probe.created_at = v.get("format").and_then(|f| f.get("tags")).and_then(dates::quicktime_date);
+        return probe;
     }
-    (None, None, None, None)
+    VideoProbe::default()
+}
+
+/// Settings the metadata workers read for every file; the settings and paths APIs change them at runtime
+#[derive(Clone)]
+pub struct MetaSettings {
+    pub ingest: crate::pipeline::ingest::SharedIngestFilter,
+    pub date_priorities: dates::SharedDatePriorities,
+    pub extractors: SharedExtractors,
 }
 
-pub fn start_workers(n: usize, mut rx: Receiver<MetaJob>, tx: Sender<DbWriteItem>, gauges: Arc<QueueGauges>) {
+pub fn start_workers(n: usize, mut rx: Receiver<MetaJob>, tx: Sender<DbWriteItem>, gauges: Arc<QueueGauges>, settings: MetaSettings, stats: Arc<crate::stats::Stats>) {
     // Distribute jobs to workers using round-robin
     let mut worker_txs = Vec::new();
     let mut worker_rxs = Vec::new();
@@ -75,13 +99,18 @@ pub fn start_workers(n: usize, mut rx: Receiver<MetaJob>, tx: Sender<DbWriteItem
     for mut worker_rx in worker_rxs.into_iter() {
         let txc = tx.clone();
         let gaugesc = gauges.clone();
+        let settings = settings.clone();
+        let statsc = stats.clone();
         tokio::spawn(async move {
             while let Some(job) = worker_rx.recv().await {
+                gaugesc.wait_while_paused().await;
                 gaugesc.metadata.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
                 let mut width = None;
                 let mut height = None;
                 let mut duration_ms = None;
                 let mut video_codec = None;
+                let mut quicktime = None;
+                let started = std::time::Instant::now();
 
                 if job.job.mime.starts_with("image/") {
                     // Move blocking libvips calls to a blocking thread to avoid stalling the async runtime.
@@ -96,6 +125,8 @@ pub fn start_workers(n: usize, mut rx: Receiver<MetaJob>, tx: Sender<DbWriteItem
                         {
                             width = Some(w);
                             height = Some(h);
+                        } else {
+                            statsc.inc_stage_errors(PipelineStage::Metadata);
                         }
                     }
                     #[cfg(target_env = "msvc")]
@@ -103,15 +134,49 @@ pub fn start_workers(n: usize, mut rx: Receiver<MetaJob>, tx: Sender<DbWriteItem
                         // libvips not available on Windows MSVC - skip image dimension extraction
                     }
                 } else if job.job.mime.starts_with("video/") {
-                    let (w, h, d, codec) = probe_video(&job.job.path.to_string_lossy()).await;
-                    width = w;
-                    height = h;
-                    duration_ms = d;
-                    video_codec = codec;
+                    let probe = probe_video(&job.job.path.to_string_lossy()).await;
+                    width = probe.width;
+                    height = probe.height;
+                    duration_ms = probe.duration_ms;
+                    video_codec = probe.codec;
+                    quicktime = probe.created_at;
+                    if width.is_none() && duration_ms.is_none() {
+                        statsc.inc_stage_errors(PipelineStage::Metadata);
+                    }
+                }
+
+                // Only headers are read here, and how much of the file that takes isn't visible, so no bytes are counted
+                statsc.record_read(crate::stats::IoStage::Metadata, 0);
+                statsc.observe_stage(PipelineStage::Metadata, started.elapsed());
+                statsc.record_asset_cost(PipelineStage::Metadata, &job.job.path.to_string_lossy(), started.elapsed());
+
+                // Icon-sized images are only recognizable once decoded
+                if job.job.mime.starts_with("image/") && !settings.ingest.read().allows_dimensions(width, height) {
+                    tracing::debug!("skipping image below minimum dimensions: {:?} ({:?}x{:?})", job.job.path, width, height);
+                    continue;
                 }
 
-                let item = DbWriteItem {
-                    path: job.job.path.to_string_lossy().to_string(),
+                let path_str = job.job.path.to_string_lossy().to_string();
+                let extracted = {
+                    let extractors = settings.extractors.read();
+                    if extractors.is_empty() { Extracted::default() } else { extractors.extract(&path_str, &job.job.dirname, &job.job.filename) }
+                };
+
+                // EXIF is read from disk, so resolve off the async runtime
+                let priority = settings.date_priorities.read().for_path(&path_str).to_vec();
+                let (path, filename, mime, mtime_ns, from_name) = (job.job.path.clone(), job.job.filename.clone(), job.job.mime.clone(), job.job.mtime_ns, extracted.date);
+                let dims = width.zip(height);
+                let (taken_at, source_app) = tokio::task::spawn_blocking(move || {
+                    let candidates = dates::DateCandidates { path: &path, filename: &filename, mime: &mime, mtime_ns, quicktime, extracted: from_name };
+                    let taken_at = dates::resolve(&priority, &candidates).map(|(date, _)| date);
+                    let source_app = messaging::detect(&path.to_string_lossy(), &filename, dims, || dates::exif_date(&path).is_none());
+                    (taken_at, source_app)
+                })
+                .await
+                .unwrap_or((Some(mtime_ns / 1_000_000_000), None));
+
+                let mut item = DbWriteItem {
+                    path: path_str,
                     dirname: job.job.dirname,
                     filename: job.job.filename,
                     ext: job.job.ext,
@@ -120,20 +185,31 @@ pub fn start_workers(n: usize, mut rx: Receiver<MetaJob>, tx: Sender<DbWriteItem
                     ctime_ns: job.job.ctime_ns,
                     sha256: job.sha256,
                     xxh64: job.xxh64,
-                    taken_at: Some(job.job.mtime_ns / 1_000_000_000),
+                    taken_at,
                     width,
                     height,
                     duration_ms,
                     camera_make: None,
-                    camera_model: None,
+                    camera_model: extracted.camera,
                     lens_model: None,
                     iso: None,
                     fnumber: None,
                     exposure: None,
                     video_codec,
                     mime: job.job.mime,
-                    flags: 0,
+                    flags: if job.sampled_hash { crate::models::asset::FLAG_SAMPLED_HASH } else { 0 },
+                    properties: extracted.properties,
+                    source_app,
                 };
+                if !plugins::registry().is_empty() {
+                    item = match tokio::task::spawn_blocking(move || {
+                        plugins::registry().metadata_extracted(&mut item);
+                        item
+                    }).await {
+                        Ok(item) => item,
+                        Err(_) => continue,
+                    };
+                }
                 let _ = txc.send(item).await;
                 gaugesc.db_write.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             }
@@ -1,14 +1,32 @@
+pub mod align;
+pub mod autotag;
+pub mod dates;
 pub mod discover;
 #[cfg(target_os = "linux")]
 pub mod discover_linux;
+pub mod embed;
+pub mod extract;
 pub mod hash;
+pub mod hdbscan;
+pub mod highlight;
+pub mod hnsw;
+pub mod ingest;
+pub mod memories;
+pub mod messaging;
 pub mod metadata;
+pub mod ocr;
+pub mod path_filter;
+pub mod quota;
+pub mod plugins;
 pub mod thumb;
+pub mod throttle;
+pub mod transcode;
 #[cfg(feature = "facial-recognition")]
 pub mod face;
 
 use tokio::sync::mpsc::Sender;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+use std::time::Instant;
 
 #[derive(Clone)]
 pub struct Queues {
@@ -17,6 +35,7 @@ pub struct Queues {
     pub meta_tx: Sender<metadata::MetaJob>,
     pub db_tx: Sender<crate::db::writer::DbWriteItem>,
     pub thumb_tx: Sender<thumb::ThumbJob>,
+    pub transcode_tx: Sender<transcode::TranscodeJob>,
     #[cfg(feature = "facial-recognition")]
     pub face_tx: Sender<face::FaceJob>,
 }
@@ -27,6 +46,7 @@ pub struct QueueDepths {
     pub metadata: usize,
     pub db_write: usize,
     pub thumb: usize,
+    pub transcode: usize,
     #[cfg(feature = "facial-recognition")]
     pub face: usize,
 }
@@ -38,8 +58,46 @@ pub struct QueueGauges {
     pub metadata: AtomicUsize,
     pub db_write: AtomicUsize,
     pub thumb: AtomicUsize,
+    pub transcode: AtomicUsize,
     #[cfg(feature = "facial-recognition")]
     pub face: AtomicUsize,
+    /// Set by POST /pipeline/pause; workers hold their next job until it clears
+    paused: AtomicBool,
+    /// Unix time the pipeline was paused, 0 while running
+    paused_at: AtomicI64,
+    /// Low-impact limits on the hash and thumbnail workers
+    pub throttle: throttle::Throttle,
+    /// Jobs queued before these times were discarded, see [`QueueGauges::flush`]
+    thumb_flushed_at: parking_lot::Mutex<Option<Instant>>,
+    #[cfg(feature = "facial-recognition")]
+    face_flushed_at: parking_lot::Mutex<Option<Instant>>,
+}
+
+/// Queues POST /admin/queues/flush can empty
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlushQueue {
+    Thumb,
+    #[cfg(feature = "facial-recognition")]
+    Face,
+}
+
+impl FlushQueue {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "thumb" => Some(Self::Thumb),
+            #[cfg(feature = "facial-recognition")]
+            "face" => Some(Self::Face),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Thumb => "thumb",
+            #[cfg(feature = "facial-recognition")]
+            Self::Face => "face",
+        }
+    }
 }
 
 impl QueueGauges {
@@ -50,8 +108,100 @@ impl QueueGauges {
             metadata: self.metadata.load(Ordering::Relaxed),
             db_write: self.db_write.load(Ordering::Relaxed),
             thumb: self.thumb.load(Ordering::Relaxed),
+            transcode: self.transcode.load(Ordering::Relaxed),
             #[cfg(feature = "facial-recognition")]
             face: self.face.load(Ordering::Relaxed),
         }
     }
+
+    /// Returns false if the pipeline was already paused
+    pub fn pause(&self) -> bool {
+        if self.paused.swap(true, Ordering::Relaxed) {
+            return false;
+        }
+        self.paused_at.store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+        true
+    }
+
+    /// Returns false if the pipeline was not paused
+    pub fn resume(&self) -> bool {
+        self.paused_at.store(0, Ordering::Relaxed);
+        self.paused.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn paused_since(&self) -> Option<i64> {
+        Some(self.paused_at.load(Ordering::Relaxed)).filter(|t| *t > 0)
+    }
+
+    fn flushable(&self, queue: FlushQueue) -> (&AtomicUsize, &parking_lot::Mutex<Option<Instant>>) {
+        match queue {
+            FlushQueue::Thumb => (&self.thumb, &self.thumb_flushed_at),
+            #[cfg(feature = "facial-recognition")]
+            FlushQueue::Face => (&self.face, &self.face_flushed_at),
+        }
+    }
+
+    /// Discard every job waiting in `queue`. The channels can't be emptied from outside, so
+    /// workers drop jobs queued before now as they reach them, taking each off the gauge like a
+    /// finished one; that is quick even while paused. Returns how many jobs the gauge counted.
+    pub fn flush(&self, queue: FlushQueue) -> usize {
+        let (gauge, flushed_at) = self.flushable(queue);
+        *flushed_at.lock() = Some(Instant::now());
+        gauge.load(Ordering::Relaxed)
+    }
+
+    /// Whether a job queued at `queued_at` was discarded by a flush
+    pub fn is_flushed(&self, queue: FlushQueue, queued_at: Instant) -> bool {
+        self.flushable(queue).1.lock().is_some_and(|at| queued_at <= at)
+    }
+
+    /// Called by worker loops before taking a job; jobs stay queued while paused
+    pub async fn wait_while_paused(&self) {
+        while self.is_paused() {
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Like [`Self::wait_while_paused`] for a job of `queue`, but returns false instead of waiting
+    /// once the job is flushed, so a paused pipeline can still drop it
+    pub async fn wait_to_run(&self, queue: FlushQueue, queued_at: Instant) -> bool {
+        loop {
+            if self.is_flushed(queue, queued_at) {
+                return false;
+            }
+            if !self.is_paused() {
+                return true;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flush_queue() {
+        let gauges = QueueGauges::default();
+        let before = Instant::now();
+        gauges.thumb.store(3, Ordering::Relaxed);
+        assert!(!gauges.is_flushed(FlushQueue::Thumb, before));
+        // The gauge is left to the workers, which take each dropped job off it
+        assert_eq!(gauges.flush(FlushQueue::Thumb), 3);
+        assert_eq!(gauges.thumb.load(Ordering::Relaxed), 3);
+        assert!(gauges.is_flushed(FlushQueue::Thumb, before));
+        assert!(!gauges.is_flushed(FlushQueue::Thumb, Instant::now()));
+
+        // Flushed jobs aren't held by a pause
+        gauges.pause();
+        let rt = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        assert!(!rt.block_on(gauges.wait_to_run(FlushQueue::Thumb, before)));
+        assert_eq!(FlushQueue::parse("thumb"), Some(FlushQueue::Thumb));
+        assert_eq!(FlushQueue::parse("hash"), None);
+    }
 }
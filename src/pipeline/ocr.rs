@@ -0,0 +1,226 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use std::io::Cursor;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+/// Recorded with each result, so a different engine can redo the library later
+pub const OCR_ENGINE: &str = "tesseract";
+/// Words tesseract is less sure of (0–100) are dropped; photos otherwise fill up with noise
+const MIN_CONFIDENCE: f32 = 60.0;
+/// Results with fewer letters and digits than this count as no text
+const MIN_TEXT_CHARS: usize = 4;
+/// Long edge the image is scaled down to before recognition
+const MAX_EDGE: u32 = 2500;
+const OCR_BATCH: usize = 32;
+const OCR_TIMEOUT: Duration = Duration::from_secs(60);
+
+static TESSERACT_BIN: Lazy<String> = Lazy::new(|| crate::utils::ffmpeg::resolve_binary("FLASH_TESSERACT", "tesseract"));
+
+/// Path (or bare command name) used to invoke tesseract
+pub fn tesseract_bin() -> &'static str {
+    TESSERACT_BIN.as_str()
+}
+
+/// Tesseract language(s), e.g. "eng+deu" (FLASH_OCR_LANG, default "eng")
+fn languages() -> String {
+    std::env::var("FLASH_OCR_LANG")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty() && v.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '_'))
+        .unwrap_or_else(|| "eng".to_string())
+}
+
+/// OCR runs unless FLASH_OCR=0 and tesseract can be started
+pub fn enabled() -> bool {
+    let wanted = std::env::var("FLASH_OCR")
+        .map(|v| !matches!(v.as_str(), "0" | "false" | "FALSE"))
+        .unwrap_or(true);
+    wanted
+        && std::process::Command::new(tesseract_bin())
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+}
+
+/// Confident words from tesseract's TSV output, one line of text per recognized line
+pub fn text_from_tsv(tsv: &str) -> String {
+    let mut lines: Vec<(String, Vec<&str>)> = Vec::new();
+    for row in tsv.lines().skip(1) {
+        let cols: Vec<&str> = row.splitn(12, '\t').collect();
+        // level 5 rows are words: level, page, block, paragraph, line, word, box (4), confidence, text
+        if cols.len() < 12 || cols[0] != "5" {
+            continue;
+        }
+        let conf: f32 = cols[10].parse().unwrap_or(-1.0);
+        let word = cols[11].trim();
+        if conf < MIN_CONFIDENCE || !word.chars().any(|c| c.is_alphanumeric()) {
+            continue;
+        }
+        let key = cols[1..5].join(".");
+        match lines.last_mut() {
+            Some((k, words)) if *k == key => words.push(word),
+            _ => lines.push((key, vec![word])),
+        }
+    }
+    let text = lines.iter().map(|(_, words)| words.join(" ")).collect::<Vec<_>>().join("\n");
+    if text.chars().filter(|c| c.is_alphanumeric()).count() < MIN_TEXT_CHARS {
+        String::new()
+    } else {
+        text
+    }
+}
+
+/// Grayscale PNG of the image for tesseract, scaled to at most MAX_EDGE
+fn prepare_image(path: &Path) -> Result<Vec<u8>> {
+    let img = image::open(path).context(format!("Failed to open image: {:?}", path))?;
+    let img = if img.width().max(img.height()) > MAX_EDGE {
+        img.resize(MAX_EDGE, MAX_EDGE, image::imageops::FilterType::Triangle)
+    } else {
+        img
+    };
+    let mut png = Vec::new();
+    image::DynamicImage::ImageLuma8(img.to_luma8())
+        .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .context("Failed to encode image for OCR")?;
+    Ok(png)
+}
+
+/// Recognize the text in a PNG
+async fn recognize(png: Vec<u8>) -> Result<String> {
+    let mut child = tokio::process::Command::new(tesseract_bin())
+        .args(["stdin", "stdout", "-l", &languages(), "tsv"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start tesseract")?;
+    let mut stdin = child.stdin.take().context("tesseract has no stdin")?;
+    let feed = tokio::spawn(async move {
+        let _ = stdin.write_all(&png).await;
+    });
+    let output = tokio::time::timeout(OCR_TIMEOUT, child.wait_with_output())
+        .await
+        .context("tesseract timed out")??;
+    let _ = feed.await;
+    if !output.status.success() {
+        anyhow::bail!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(text_from_tsv(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Image formats read directly; everything else (RAW, HEIC, ...) is read from its 1600px preview
+fn reads_original(mime: &str) -> bool {
+    matches!(mime, "image/jpeg" | "image/png" | "image/webp" | "image/bmp" | "image/tiff" | "image/gif")
+}
+
+/// Recognize text in images that haven't been read yet, one pass over the library every `idle` when caught up
+pub fn start_worker(state: Arc<crate::AppState>, idle: Duration) {
+    tokio::spawn(async move {
+        info!("OCR enabled ({}, languages {})", tesseract_bin(), languages());
+        let mut after = 0i64;
+        loop {
+            state.gauges.wait_while_paused().await;
+            let pending = tokio::task::spawn_blocking({
+                let pool = state.pool.clone();
+                move || -> Result<Vec<(i64, String, String, String)>> {
+                    let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+                    crate::db::query::assets_missing_text(&conn, after, OCR_BATCH)
+                }
+            })
+            .await;
+            let pending = match pending {
+                Ok(Ok(pending)) => pending,
+                Ok(Err(e)) => {
+                    warn!("OCR pass failed: {}", e);
+                    tokio::time::sleep(idle).await;
+                    continue;
+                }
+                Err(e) => {
+                    warn!("OCR task panicked: {}", e);
+                    tokio::time::sleep(idle).await;
+                    continue;
+                }
+            };
+            let Some((last, ..)) = pending.last() else {
+                after = 0;
+                tokio::time::sleep(idle).await;
+                continue;
+            };
+            after = *last;
+
+            for (asset_id, path, mime, sha) in pending {
+                state.gauges.wait_while_paused().await;
+                let source = if reads_original(&mime) {
+                    std::path::PathBuf::from(&path)
+                } else {
+                    crate::pipeline::thumb::thumb_path(&state.paths.derived, &sha, 1600)
+                };
+                // Originals that are offline and previews not made yet are retried next pass
+                if !source.exists() {
+                    continue;
+                }
+                let png = match tokio::task::spawn_blocking(move || prepare_image(&source)).await {
+                    Ok(Ok(png)) => png,
+                    Ok(Err(e)) => {
+                        warn!("OCR skipped asset {}: {}", asset_id, e);
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("OCR task panicked: {}", e);
+                        continue;
+                    }
+                };
+                let text = match recognize(png).await {
+                    Ok(text) => text,
+                    Err(e) => {
+                        warn!("OCR failed for asset {}: {}", asset_id, e);
+                        continue;
+                    }
+                };
+                let pool = state.pool.clone();
+                let saved = tokio::task::spawn_blocking(move || -> Result<()> {
+                    let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+                    crate::db::writer::set_asset_text(&conn, asset_id, &text, OCR_ENGINE, chrono::Utc::now().timestamp())
+                })
+                .await;
+                match saved {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => warn!("Failed to save OCR text for asset {}: {}", asset_id, e),
+                    Err(e) => warn!("OCR task panicked: {}", e),
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_from_tsv() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                   1\t1\t0\t0\t0\t0\t0\t0\t800\t600\t-1\t\n\
+                   5\t1\t1\t1\t1\t1\t10\t10\t80\t20\t96.5\tINVOICE\n\
+                   5\t1\t1\t1\t1\t2\t95\t10\t50\t20\t91.0\t2023\n\
+                   5\t1\t1\t1\t2\t1\t10\t40\t30\t20\t35.2\tl1|\n\
+                   5\t1\t1\t1\t2\t2\t45\t40\t60\t20\t88.0\tTotal:\n\
+                   5\t1\t1\t1\t2\t3\t110\t40\t60\t20\t90.0\t$42.00\n\
+                   5\t1\t2\t1\t1\t1\t10\t90\t10\t20\t95.0\t—\n";
+        assert_eq!(text_from_tsv(tsv), "INVOICE 2023\nTotal: $42.00");
+
+        // A few stray letters on a photo are not text
+        let noise = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                     5\t1\t1\t1\t1\t1\t10\t10\t10\t20\t70.0\tA\n\
+                     5\t1\t2\t1\t1\t1\t90\t10\t10\t20\t75.0\tii\n";
+        assert_eq!(text_from_tsv(noise), "");
+    }
+}
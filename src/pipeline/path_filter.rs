@@ -0,0 +1,179 @@
+use parking_lot::RwLock;
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// A scan path's filter, shared with its watcher so edits apply without a restart
+pub type SharedPathFilter = Arc<RwLock<PathFilter>>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Pattern {
+    /// No slash: tested against every component, e.g. `@eaDir` or `*.tmp`
+    Name(String),
+    /// Has a slash (or starts with one): tested against the path relative to the scan root,
+    /// where `**` spans any number of directories
+    Path(Vec<String>),
+}
+
+impl Pattern {
+    fn parse(raw: &str) -> Result<Self, String> {
+        let normalized = raw.trim().replace('\\', "/");
+        let anchored = normalized.trim_end_matches('/');
+        let body = anchored.trim_start_matches('/');
+        if body.is_empty() {
+            return Err(format!("Pattern '{}' is empty", raw));
+        }
+        let segments: Vec<String> = body.split('/').filter(|s| !s.is_empty()).map(String::from).collect();
+        if segments.iter().any(|s| s == "." || s == "..") {
+            return Err(format!("Pattern '{}' may not contain . or .. segments", raw));
+        }
+        if anchored.contains('/') {
+            Ok(Pattern::Path(segments))
+        } else {
+            Ok(Pattern::Name(body.to_string()))
+        }
+    }
+
+    /// A path matches when the pattern matches it or one of its parent directories
+    fn matches(&self, components: &[Cow<'_, str>]) -> bool {
+        match self {
+            Pattern::Name(pattern) => components.iter().any(|c| wildcard(pattern, c)),
+            Pattern::Path(segments) => (1..=components.len()).any(|n| segments_match(segments, &components[..n])),
+        }
+    }
+}
+
+/// `*` matches any run of characters and `?` a single one, never crossing a `/`
+fn wildcard(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and retry
+                Some((star, consumed)) => {
+                    p = star + 1;
+                    t = consumed + 1;
+                    backtrack = Some((star, consumed + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn segments_match(pattern: &[String], path: &[Cow<'_, str>]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((first, rest)) => match path.split_first() {
+            Some((component, tail)) => wildcard(first, component) && segments_match(rest, tail),
+            None => false,
+        },
+    }
+}
+
+/// Include/exclude globs for one scan root.
+/// Excluded directories are not descended into; when include patterns are set, a file must match one of them.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    root: PathBuf,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    pub fn new(root: &Path, include: &[String], exclude: &[String]) -> Result<Self, String> {
+        let parse = |patterns: &[String]| patterns.iter().map(|p| Pattern::parse(p)).collect::<Result<Vec<_>, _>>();
+        Ok(Self { root: root.to_path_buf(), include: parse(include)?, exclude: parse(exclude)? })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    fn components<'a>(&self, path: &'a Path) -> Vec<Cow<'a, str>> {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn excluded(&self, components: &[Cow<'_, str>]) -> bool {
+        self.exclude.iter().any(|p| p.matches(components))
+    }
+
+    /// True if the scan should not descend into `dir`
+    pub fn skips_dir(&self, dir: &Path) -> bool {
+        !self.exclude.is_empty() && self.excluded(&self.components(dir))
+    }
+
+    /// True if `path` should be indexed
+    pub fn allows_file(&self, path: &Path) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let components = self.components(path);
+        if self.excluded(&components) {
+            return false;
+        }
+        self.include.is_empty() || self.include.iter().any(|p| p.matches(&components))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_path_filter_globs() {
+        let root = Path::new("/photos");
+        let filter = PathFilter::new(root, &[], &patterns(&["@eaDir", "node_modules", "*.tmp", "/2019/raw", "**/cache/*.jpg"])).unwrap();
+        assert!(filter.allows_file(&root.join("2024/IMG_1.jpg")));
+        assert!(!filter.allows_file(&root.join("2024/@eaDir/IMG_1.jpg/SYNOPHOTO_THUMB_XL.jpg")));
+        assert!(filter.skips_dir(&root.join("app/node_modules")));
+        assert!(!filter.allows_file(&root.join("2024/upload.jpg.tmp")));
+        assert!(filter.allows_file(&root.join("tmp/a.jpg")));
+        // Anchored patterns only match from the root
+        assert!(filter.skips_dir(&root.join("2019/raw")));
+        assert!(!filter.allows_file(&root.join("2019/raw/a.cr2")));
+        assert!(filter.allows_file(&root.join("2020/2019/raw/a.cr2")));
+        assert!(!filter.allows_file(&root.join("a/b/cache/x.jpg")));
+        assert!(filter.allows_file(&root.join("a/b/cache/x.png")));
+        // The root's own name never counts
+        assert!(PathFilter::new(Path::new("/tmp/x.tmp"), &[], &patterns(&["*.tmp"])).unwrap().allows_file(Path::new("/tmp/x.tmp/a.jpg")));
+
+        let include = PathFilter::new(root, &patterns(&["*.jpg", "DCIM"]), &patterns(&["Trash"])).unwrap();
+        assert!(include.allows_file(&root.join("a/b.JPG.jpg")));
+        assert!(include.allows_file(&root.join("phone/DCIM/clip.mp4")));
+        assert!(!include.allows_file(&root.join("phone/clip.mp4")));
+        assert!(!include.allows_file(&root.join("Trash/b.jpg")));
+        assert!(!include.skips_dir(&root.join("phone")));
+
+        assert!(wildcard("IMG_????.*", "IMG_0001.heic"));
+        assert!(!wildcard("IMG_????.*", "IMG_01.heic"));
+        assert!(wildcard("*a*b*", "xxaxxbxx"));
+        for bad in ["", "  ", "/", "../x", "a/./b"] {
+            assert!(PathFilter::new(root, &patterns(&[bad]), &[]).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+}
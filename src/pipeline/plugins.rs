@@ -0,0 +1,281 @@
+//! Extension points in the indexing pipeline.
+//!
+//! A plugin implements [`Plugin`] and is registered once at startup with [`register`]; every
+//! hook has a no-op default, so a plugin only overrides the ones it needs. `metadata_extracted`
+//! runs on the metadata worker before the asset is written and may change what gets stored.
+//! The other hooks are notifications, delivered in order on a dedicated thread so a slow
+//! plugin never holds up indexing. Errors and panics are logged and don't reach the pipeline.
+//!
+//! Without writing Rust, `FLASH_HOOK_COMMAND` registers a [`CommandPlugin`] that runs a
+//! program for each notification with the event as JSON on stdin.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::io::Write;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::db::writer::DbWriteItem;
+
+/// Most notifications waiting for delivery; more are dropped with a warning
+const MAX_PENDING: usize = 10_000;
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An asset written to the library, new or re-indexed after it changed
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestedAsset {
+    pub asset_id: i64,
+    pub path: String,
+    pub mime: String,
+    pub sha256: Option<String>,
+}
+
+/// Faces grouped into new persons
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusteredFaces {
+    /// "pipeline" for batches from the face workers, "recluster" or "unassigned" for API runs
+    pub source: &'static str,
+    pub person_ids: Vec<i64>,
+    pub faces_assigned: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    AssetIngested(IngestedAsset),
+    FacesClustered(ClusteredFaces),
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::AssetIngested(_) => "asset_ingested",
+            Event::FacesClustered(_) => "faces_clustered",
+        }
+    }
+}
+
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Fill in or correct metadata before the asset is stored. Runs on the metadata worker, so keep it quick.
+    fn metadata_extracted(&self, _item: &mut DbWriteItem) -> Result<()> {
+        Ok(())
+    }
+
+    fn asset_ingested(&self, _asset: &IngestedAsset) -> Result<()> {
+        Ok(())
+    }
+
+    fn faces_clustered(&self, _faces: &ClusteredFaces) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn guarded(plugin: &dyn Plugin, hook: &str, f: impl FnOnce() -> Result<()>) {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => tracing::warn!("plugin {} failed in {}: {:#}", plugin.name(), hook, e),
+        Err(_) => tracing::error!("plugin {} panicked in {}", plugin.name(), hook),
+    }
+}
+
+fn deliver(plugins: &[Arc<dyn Plugin>], event: &Event) {
+    for plugin in plugins {
+        guarded(plugin.as_ref(), event.name(), || match event {
+            Event::AssetIngested(asset) => plugin.asset_ingested(asset),
+            Event::FacesClustered(faces) => plugin.faces_clustered(faces),
+        });
+    }
+}
+
+#[derive(Default)]
+pub struct Registry {
+    plugins: Arc<RwLock<Vec<Arc<dyn Plugin>>>>,
+    /// Feeds the delivery thread, started with the first plugin
+    sender: Mutex<Option<mpsc::SyncSender<Event>>>,
+}
+
+impl Registry {
+    pub fn register(&self, plugin: Arc<dyn Plugin>) {
+        tracing::info!("plugin registered: {}", plugin.name());
+        self.plugins.write().push(plugin);
+        let mut sender = self.sender.lock();
+        if sender.is_none() {
+            let (tx, rx) = mpsc::sync_channel::<Event>(MAX_PENDING);
+            let plugins = self.plugins.clone();
+            *sender = Some(tx);
+            std::thread::Builder::new()
+                .name("seen-plugins".into())
+                .spawn(move || {
+                    while let Ok(event) = rx.recv() {
+                        let current = plugins.read().clone();
+                        deliver(&current, &event);
+                    }
+                })
+                .expect("failed to start plugin thread");
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.plugins.read().iter().map(|p| p.name().to_string()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.read().is_empty()
+    }
+
+    pub fn metadata_extracted(&self, item: &mut DbWriteItem) {
+        for plugin in self.plugins.read().iter() {
+            guarded(plugin.as_ref(), "metadata_extracted", || plugin.metadata_extracted(item));
+        }
+    }
+
+    /// Queue a notification for the plugins; free when none are registered
+    pub fn notify(&self, event: Event) {
+        let Some(sender) = self.sender.lock().clone() else {
+            return;
+        };
+        if let Err(mpsc::TrySendError::Full(event)) = sender.try_send(event) {
+            tracing::warn!("plugins are behind, dropping {} event", event.name());
+        }
+    }
+}
+
+static REGISTRY: Lazy<Registry> = Lazy::new(Registry::default);
+
+/// The plugins the pipeline calls
+pub fn registry() -> &'static Registry {
+    &REGISTRY
+}
+
+/// Add a plugin to the pipeline; call before the workers start so no event is missed
+pub fn register(plugin: Arc<dyn Plugin>) {
+    REGISTRY.register(plugin);
+}
+
+/// Announce persons created by a clustering run; runs that created none aren't announced
+pub fn faces_clustered(source: &'static str, person_ids: Vec<i64>, faces_assigned: usize) {
+    if !person_ids.is_empty() {
+        REGISTRY.notify(Event::FacesClustered(ClusteredFaces { source, person_ids, faces_assigned }));
+    }
+}
+
+/// Runs a program for every notification, with the event as JSON on stdin and its name in `SEEN_EVENT`
+pub struct CommandPlugin {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandPlugin {
+    /// Split `command` on whitespace into the program and its arguments
+    pub fn new(command: &str) -> Option<Self> {
+        let mut parts = command.split_whitespace().map(String::from);
+        let program = parts.next()?;
+        Some(Self { program, args: parts.collect() })
+    }
+
+    fn run(&self, event: &Event) -> Result<()> {
+        let json = serde_json::to_vec(event)?;
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .env("SEEN_EVENT", event.name())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .context(format!("Failed to start {}", self.program))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook that exits without reading its input is fine
+            let _ = stdin.write_all(&json);
+        }
+        let started = std::time::Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                anyhow::ensure!(status.success(), "{} exited with {}", self.program, status);
+                return Ok(());
+            }
+            if started.elapsed() > COMMAND_TIMEOUT {
+                let _ = child.kill();
+                let _ = child.wait();
+                anyhow::bail!("{} timed out after {:?}", self.program, COMMAND_TIMEOUT);
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+impl Plugin for CommandPlugin {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn asset_ingested(&self, asset: &IngestedAsset) -> Result<()> {
+        self.run(&Event::AssetIngested(asset.clone()))
+    }
+
+    fn faces_clustered(&self, faces: &ClusteredFaces) -> Result<()> {
+        self.run(&Event::FacesClustered(faces.clone()))
+    }
+}
+
+/// Register the hook command from FLASH_HOOK_COMMAND, if set
+pub fn register_from_env() {
+    if let Some(plugin) = std::env::var("FLASH_HOOK_COMMAND").ok().as_deref().and_then(CommandPlugin::new) {
+        register(Arc::new(plugin));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recorder {
+        seen: Mutex<Vec<String>>,
+    }
+
+    impl Plugin for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn metadata_extracted(&self, item: &mut DbWriteItem) -> Result<()> {
+            item.camera_make = Some("Sidecar".to_string());
+            Ok(())
+        }
+
+        fn asset_ingested(&self, asset: &IngestedAsset) -> Result<()> {
+            self.seen.lock().push(asset.path.clone());
+            anyhow::ensure!(!asset.path.ends_with(".bad"), "cannot read {}", asset.path);
+            Ok(())
+        }
+
+        fn faces_clustered(&self, _faces: &ClusteredFaces) -> Result<()> {
+            panic!("plugin bug");
+        }
+    }
+
+    #[test]
+    fn test_plugin_hooks() {
+        let recorder = Arc::new(Recorder { seen: Mutex::new(Vec::new()) });
+        let plugins: Vec<Arc<dyn Plugin>> = vec![recorder.clone()];
+        let asset = |path: &str| Event::AssetIngested(IngestedAsset { asset_id: 1, path: path.to_string(), mime: "image/jpeg".to_string(), sha256: None });
+
+        // Errors and panics stay inside the plugin
+        deliver(&plugins, &asset("/a.bad"));
+        deliver(&plugins, &Event::FacesClustered(ClusteredFaces { source: "pipeline", person_ids: vec![4], faces_assigned: 3 }));
+        deliver(&plugins, &asset("/b.jpg"));
+        assert_eq!(*recorder.seen.lock(), vec!["/a.bad".to_string(), "/b.jpg".to_string()]);
+
+        let json = serde_json::to_value(Event::FacesClustered(ClusteredFaces { source: "recluster", person_ids: vec![4, 5], faces_assigned: 9 })).unwrap();
+        assert_eq!(json, serde_json::json!({ "event": "faces_clustered", "source": "recluster", "person_ids": [4, 5], "faces_assigned": 9 }));
+
+        assert!(CommandPlugin::new("  ").is_none());
+        let command = CommandPlugin::new("/usr/local/bin/on-event --verbose").unwrap();
+        assert_eq!((command.program.as_str(), command.args.as_slice()), ("/usr/local/bin/on-event", &["--verbose".to_string()][..]));
+    }
+}
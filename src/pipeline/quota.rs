@@ -0,0 +1,111 @@
+//! Soft limits on how much one scan path indexes.
+//!
+//! A scan counts the media files it would index against the path's asset and byte limits and
+//! stops at the first file that doesn't fit, so a path pointed at an enormous mount by mistake
+//! indexes a bounded amount instead of filling a small device with hashes and thumbnails. Files
+//! indexed before the limit stay; the watcher still picks up new files one at a time.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanQuota {
+    pub max_assets: Option<u64>,
+    pub max_bytes: Option<u64>,
+}
+
+impl ScanQuota {
+    /// Limits as given to the API, where None means no limit and zero is refused
+    pub fn new(max_assets: Option<u64>, max_bytes: Option<u64>) -> Result<Self, String> {
+        if max_assets == Some(0) || max_bytes == Some(0) {
+            return Err("Limits must be at least 1; use null for no limit".to_string());
+        }
+        Ok(Self { max_assets, max_bytes })
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_assets.is_none() && self.max_bytes.is_none()
+    }
+}
+
+/// Why and where a scan stopped early
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaHit {
+    /// Unix seconds
+    pub at: i64,
+    /// "assets" or "bytes"
+    pub limit: String,
+    /// What the scan sent on before it stopped
+    pub indexed_assets: u64,
+    pub indexed_bytes: u64,
+}
+
+/// A scan's running totals against its quota
+#[derive(Debug, Default)]
+pub struct QuotaCounter {
+    quota: ScanQuota,
+    assets: u64,
+    bytes: u64,
+    hit: Option<&'static str>,
+}
+
+impl QuotaCounter {
+    pub fn new(quota: ScanQuota) -> Self {
+        Self { quota, ..Default::default() }
+    }
+
+    /// Count a file of `size` bytes if it fits. Once one file doesn't, every later one is refused
+    /// too, so the scan can stop there.
+    pub fn admit(&mut self, size: u64) -> bool {
+        if self.hit.is_some() {
+            return false;
+        }
+        if self.quota.max_assets.is_some_and(|max| self.assets + 1 > max) {
+            self.hit = Some("assets");
+        } else if self.quota.max_bytes.is_some_and(|max| self.bytes + size > max) {
+            self.hit = Some("bytes");
+        } else {
+            self.assets += 1;
+            self.bytes += size;
+        }
+        self.hit.is_none()
+    }
+
+    /// Files admitted so far, and their total size
+    pub fn counted(&self) -> (u64, u64) {
+        (self.assets, self.bytes)
+    }
+
+    pub fn hit(&self) -> Option<QuotaHit> {
+        self.hit.map(|limit| QuotaHit {
+            at: chrono::Utc::now().timestamp(),
+            limit: limit.to_string(),
+            indexed_assets: self.assets,
+            indexed_bytes: self.bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_counter() {
+        let mut unlimited = QuotaCounter::new(ScanQuota::default());
+        assert!((0..1000).all(|_| unlimited.admit(u32::MAX as u64)));
+        assert!(unlimited.hit().is_none());
+
+        let mut counter = QuotaCounter::new(ScanQuota { max_assets: Some(3), max_bytes: None });
+        assert!(counter.admit(10) && counter.admit(10) && counter.admit(10));
+        assert!(!counter.admit(1));
+        let hit = counter.hit().unwrap();
+        assert_eq!((hit.limit.as_str(), hit.indexed_assets, hit.indexed_bytes), ("assets", 3, 30));
+
+        // A file that would cross the byte budget stops the scan, even if smaller ones follow
+        let mut counter = QuotaCounter::new(ScanQuota { max_assets: Some(10), max_bytes: Some(100) });
+        assert!(counter.admit(60) && counter.admit(40));
+        assert!(!counter.admit(1) && !counter.admit(0));
+        let hit = counter.hit().unwrap();
+        assert_eq!((hit.limit.as_str(), hit.indexed_assets, hit.indexed_bytes), ("bytes", 2, 100));
+    }
+}
@@ -0,0 +1,139 @@
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Low-impact mode, for indexing a share that is also serving something else (e.g. a media server).
+/// Off by default; the limits only apply while `enabled` is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LowImpact {
+    pub enabled: bool,
+    /// Hash workers, and separately thumbnail workers, allowed to work at the same time
+    pub max_workers: usize,
+    /// Pause after each hashed or thumbnailed file
+    pub file_delay_ms: u64,
+    /// Combined read rate of the hash and thumbnail workers; 0 means uncapped
+    pub max_read_mb_per_sec: u64,
+}
+
+impl Default for LowImpact {
+    fn default() -> Self {
+        Self { enabled: false, max_workers: 1, file_delay_ms: 50, max_read_mb_per_sec: 20 }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Stage {
+    Hash,
+    Thumb,
+}
+
+/// Applies the low-impact limits; shared by every hash and thumbnail worker
+#[derive(Default)]
+pub struct Throttle {
+    settings: RwLock<LowImpact>,
+    hash_active: AtomicUsize,
+    thumb_active: AtomicUsize,
+    /// When the read budget handed out so far is used up
+    read_free_at: Mutex<Option<Instant>>,
+}
+
+/// A worker slot; released on drop
+pub struct Permit<'a> {
+    throttle: &'a Throttle,
+    active: &'a AtomicUsize,
+}
+
+impl Permit<'_> {
+    /// Release the slot after the configured per-file pause
+    pub async fn finish(self) {
+        let delay = self.throttle.settings.read().clone();
+        if delay.enabled && delay.file_delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(delay.file_delay_ms)).await;
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Throttle {
+    pub fn settings(&self) -> LowImpact {
+        self.settings.read().clone()
+    }
+
+    pub fn set(&self, settings: LowImpact) {
+        *self.settings.write() = settings;
+    }
+
+    fn active(&self, stage: Stage) -> &AtomicUsize {
+        match stage {
+            Stage::Hash => &self.hash_active,
+            Stage::Thumb => &self.thumb_active,
+        }
+    }
+
+    /// Wait for a worker slot, then for enough read budget to cover `bytes`
+    pub async fn acquire(&self, stage: Stage, bytes: u64) -> Permit<'_> {
+        let active = self.active(stage);
+        loop {
+            let settings = self.settings();
+            let limit = if settings.enabled { settings.max_workers.max(1) } else { usize::MAX };
+            let current = active.load(Ordering::Relaxed);
+            if current < limit && active.compare_exchange(current, current + 1, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let wait = self.reserve_read(bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        Permit { throttle: self, active }
+    }
+
+    /// How long to wait before reading `bytes` under the bandwidth cap
+    fn reserve_read(&self, bytes: u64) -> Duration {
+        let settings = self.settings();
+        if !settings.enabled || settings.max_read_mb_per_sec == 0 || bytes == 0 {
+            return Duration::ZERO;
+        }
+        let now = Instant::now();
+        let mut free_at = self.read_free_at.lock();
+        let start = free_at.filter(|t| *t > now).unwrap_or(now);
+        *free_at = Some(start + Duration::from_secs_f64(bytes as f64 / (settings.max_read_mb_per_sec as f64 * 1_000_000.0)));
+        start - now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_low_impact_limits() {
+        let throttle = Throttle::default();
+        // Disabled: no slot limit and no read budget
+        let a = throttle.acquire(Stage::Hash, 1 << 30).await;
+        let b = throttle.acquire(Stage::Hash, 1 << 30).await;
+        assert_eq!(throttle.hash_active.load(Ordering::Relaxed), 2);
+        drop((a, b));
+        assert_eq!(throttle.hash_active.load(Ordering::Relaxed), 0);
+
+        throttle.set(LowImpact { enabled: true, max_workers: 1, file_delay_ms: 0, max_read_mb_per_sec: 10 });
+        assert!(throttle.reserve_read(5_000_000).is_zero());
+        let queued = throttle.reserve_read(1_000_000);
+        assert!(queued > Duration::from_millis(450) && queued <= Duration::from_millis(500));
+
+        let hash = throttle.acquire(Stage::Hash, 0).await;
+        let thumb = tokio::time::timeout(Duration::from_millis(50), throttle.acquire(Stage::Thumb, 0)).await;
+        assert!(thumb.is_ok(), "stages have separate slots");
+        assert!(tokio::time::timeout(Duration::from_millis(250), throttle.acquire(Stage::Hash, 0)).await.is_err());
+        hash.finish().await;
+        assert!(tokio::time::timeout(Duration::from_millis(250), throttle.acquire(Stage::Hash, 0)).await.is_ok());
+    }
+}
@@ -1,8 +1,12 @@
 use anyhow::Result;
 use std::path::{Path, PathBuf};
-use tokio::sync::mpsc::Receiver;
-use crate::pipeline::QueueGauges;
+use serde::Serialize;
+use tokio::sync::mpsc::{Receiver, Sender};
+use crate::pipeline::{FlushQueue, QueueGauges};
+use crate::pipeline::throttle::Stage;
+use crate::stats::{IoStage, PipelineStage, Stats};
 use crate::utils::ffmpeg;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, warn};
@@ -13,75 +17,239 @@ pub struct ThumbJob {
     pub path: String,
     pub sha256_hex: String,
     pub mime: String,
+    /// Render again even if the files exist, e.g. after a size change or corruption
+    pub force: bool,
+    /// Jobs queued before a flush are dropped, see [`QueueGauges::flush`]
+    pub queued_at: std::time::Instant,
 }
 
 fn ensure_dir(p: &Path) -> std::io::Result<()> { std::fs::create_dir_all(p) }
 
-fn thumb_path(derived: &Path, sha_hex: &str, size: i32) -> PathBuf {
+pub fn thumb_path(derived: &Path, sha_hex: &str, size: i32) -> PathBuf {
     let sub = &sha_hex[0..2];
-    derived.join(sub).join(format!("{}-{}.webp", sha_hex, size))
+    crate::utils::path::long_path(&derived.join(sub).join(format!("{}-{}.webp", sha_hex, size)))
+}
+
+/// Animated hover preview for videos, stored next to the static thumbs
+pub fn anim_path(derived: &Path, sha_hex: &str) -> PathBuf {
+    let sub = &sha_hex[0..2];
+    crate::utils::path::long_path(&derived.join(sub).join(format!("{}-anim.webp", sha_hex)))
+}
+
+/// Frames sampled across the clip for the hover preview
+const ANIM_FRAMES: u32 = 5;
+
+/// Sizes up to this are saved with the fastest WebP effort: they're rendered in bulk, and at that size
+/// the slower effort levels only save a few hundred bytes. Small ARM boards use it for every size.
+const FAST_ENCODE_MAX: i32 = 512;
+
+/// Largest size first, so each smaller one can be resized from the one before
+fn sizes_descending(targets: &[(PathBuf, i32)]) -> Vec<(&Path, i32)> {
+    let mut sizes: Vec<(&Path, i32)> = targets.iter().map(|(dst, size)| (dst.as_path(), *size)).collect();
+    sizes.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    sizes
+}
+
+/// Remove what a failed write left behind
+fn discard_partial(dst: &Path) {
+    if dst.exists() {
+        if let Err(rm_err) = std::fs::remove_file(dst) {
+            warn!("Failed to clean up partial thumbnail file {:?} after write error: {}", dst, rm_err);
+        }
+    }
 }
 
 #[cfg(not(target_env = "msvc"))]
-fn image_make_thumb(src: &str, dst: &Path, size: i32) -> Result<()> {
-    let img = libvips::VipsImage::new_from_file(src)
-        .map_err(|e| anyhow::anyhow!("Failed to load image {}: {}", src, e))?;
-    let out = libvips::ops::thumbnail_image(&img, size)
-        .map_err(|e| anyhow::anyhow!("Failed to create thumbnail for {}: {}", src, e))?;
-    let write_result = out.image_write_to_file(dst.to_string_lossy().as_ref())
-        .map_err(|e| anyhow::anyhow!("Failed to write thumbnail file for {}: {}", src, e));
-    
-    // Clean up partial file on failure
-    if let Err(e) = write_result {
-        if dst.exists() {
-            if let Err(rm_err) = std::fs::remove_file(dst) {
-                warn!("Failed to clean up partial thumbnail file {:?} after write error: {}", dst, rm_err);
-            }
+fn vips_save_webp(img: &libvips::VipsImage, src: &str, dst: &Path, size: i32) -> Result<()> {
+    let path = dst.to_string_lossy();
+    // libvips before 8.12 doesn't know `effort`; those get the default encoder settings
+    let fast = size <= FAST_ENCODE_MAX || crate::utils::host::host().constrained;
+    if fast && img.image_write_to_file(&format!("{}[effort=0]", path)).is_ok() {
+        return Ok(());
+    }
+    img.image_write_to_file(path.as_ref()).map_err(|e| {
+        discard_partial(dst);
+        anyhow::anyhow!("Failed to write thumbnail file for {}: {}", src, e)
+    })
+}
+
+/// WebP bytes for an image composed in memory, e.g. a face collage
+#[cfg(not(target_env = "msvc"))]
+pub fn encode_webp(img: &image::DynamicImage) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+    let vips = libvips::VipsImage::new_from_buffer(&png, "").map_err(|e| anyhow::anyhow!("Failed to load image into libvips: {}", e))?;
+    vips.image_write_to_buffer(".webp[Q=85]").map_err(|e| anyhow::anyhow!("Failed to encode WebP: {}", e))
+}
+
+#[cfg(target_env = "msvc")]
+pub fn encode_webp(img: &image::DynamicImage) -> Result<Vec<u8>> {
+    let rgb8 = img.to_rgb8();
+    Ok(webp::Encoder::from_rgb(&rgb8, rgb8.width(), rgb8.height()).encode(85.0).to_vec())
+}
+
+/// Write every size from one decoded image: the largest comes from `largest`, which is kept in memory,
+/// and smaller ones are resized from it instead of decoding the original again. Failed writes are
+/// logged here; an error means nothing could be decoded.
+#[cfg(not(target_env = "msvc"))]
+fn vips_write_sizes(largest: libvips::VipsImage, src: &str, sizes: &[(&Path, i32)]) -> Result<()> {
+    let base = libvips::VipsImage::image_copy_memory(largest)
+        .map_err(|e| anyhow::anyhow!("Failed to decode {}: {}", src, e))?;
+    for (i, (dst, size)) in sizes.iter().enumerate() {
+        let written = if i == 0 {
+            vips_save_webp(&base, src, dst, *size)
+        } else {
+            libvips::ops::thumbnail_image(&base, *size)
+                .map_err(|e| anyhow::anyhow!("Failed to create thumbnail for {}: {}", src, e))
+                .and_then(|out| vips_save_webp(&out, src, dst, *size))
+        };
+        match written {
+            Ok(()) => debug!("Created {}px derivative for {}: {:?}", size, src, dst),
+            Err(e) => warn!("{}", e),
         }
-        return Err(e);
     }
-    
     Ok(())
 }
 
+/// Decoding the original is the slow part, so it happens once, shrinking on load where the format allows
+#[cfg(not(target_env = "msvc"))]
+fn image_make_sizes(src: &str, sizes: &[(&Path, i32)]) -> Result<()> {
+    let largest = libvips::ops::thumbnail(src, sizes[0].1)
+        .map_err(|e| anyhow::anyhow!("Failed to load image {}: {}", src, e))?;
+    vips_write_sizes(largest, src, sizes)
+}
+
 #[cfg(target_env = "msvc")]
-fn image_make_thumb(src: &str, dst: &Path, size: i32) -> Result<()> {
-    use image::DynamicImage;
-    
-    // Load image using image crate
-    let img = image::open(src)
-        .map_err(|e| anyhow::anyhow!("Failed to decode image {}: {}", src, e))?;
-    
-    // Resize maintaining aspect ratio
-    let resized = img.thumbnail(size as u32, size as u32);
-    
-    // Convert to RGB8 if needed
-    let rgb8 = match resized {
-        DynamicImage::ImageRgb8(img) => img,
-        img => img.to_rgb8(),
-    };
-    
-    // Encode as WebP
-    let encoder = webp::Encoder::from_rgb(&rgb8, rgb8.width(), rgb8.height());
-    let webp_data = encoder.encode(85.0); // Quality 85 (0-100)
-    
-    // Write to file - WebPMemory implements AsRef<[u8]>
-    let write_result = std::fs::write(dst, webp_data.as_ref())
-        .map_err(|e| anyhow::anyhow!("Failed to write WebP file for {}: {}", src, e));
-    
-    // Clean up partial file on failure
-    if let Err(e) = write_result {
-        if dst.exists() {
-            if let Err(rm_err) = std::fs::remove_file(dst) {
-                warn!("Failed to clean up partial thumbnail file {:?} after write error: {}", dst, rm_err);
-            }
+fn save_webp(img: &image::DynamicImage, src: &str, dst: &Path) -> Result<()> {
+    let rgb8 = img.to_rgb8();
+    let webp_data = webp::Encoder::from_rgb(&rgb8, rgb8.width(), rgb8.height()).encode(85.0); // Quality 85 (0-100)
+    std::fs::write(dst, webp_data.as_ref()).map_err(|e| {
+        discard_partial(dst);
+        anyhow::anyhow!("Failed to write WebP file for {}: {}", src, e)
+    })
+}
+
+/// Like the libvips version: one decode, each smaller size resized from the one before
+#[cfg(target_env = "msvc")]
+fn write_sizes(img: image::DynamicImage, src: &str, sizes: &[(&Path, i32)]) {
+    let mut current = img;
+    for (dst, size) in sizes {
+        current = current.thumbnail(*size as u32, *size as u32);
+        match save_webp(&current, src, dst) {
+            Ok(()) => debug!("Created {}px derivative for {}: {:?}", size, src, dst),
+            Err(e) => warn!("{}", e),
         }
-        return Err(e);
     }
-    
+}
+
+#[cfg(target_env = "msvc")]
+fn image_make_sizes(src: &str, sizes: &[(&Path, i32)]) -> Result<()> {
+    let img = image::open(src).map_err(|e| anyhow::anyhow!("Failed to decode image {}: {}", src, e))?;
+    write_sizes(img, src, sizes);
     Ok(())
 }
 
+/// LibRaw's dcraw_emu (FLASH_DCRAW_EMU overrides the path)
+static DCRAW_EMU_BIN: once_cell::sync::Lazy<String> =
+    once_cell::sync::Lazy::new(|| crate::utils::ffmpeg::resolve_binary("FLASH_DCRAW_EMU", "dcraw_emu"));
+
+/// Camera RAW and HEIF containers that libvips often can't open on its own
+fn needs_decode_fallback(src: &str) -> bool {
+    let ext = Path::new(src)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    is_heif_ext(&ext) || is_raw_ext(&ext)
+}
+
+fn is_heif_ext(ext: &str) -> bool {
+    matches!(ext, "heic" | "heif" | "hif" | "avif")
+}
+
+fn is_raw_ext(ext: &str) -> bool {
+    matches!(
+        ext,
+        "dng" | "cr2" | "cr3" | "crw" | "nef" | "nrw" | "arw" | "srf" | "sr2" | "orf" | "rw2" | "raf" | "pef" | "srw"
+            | "3fr" | "x3f" | "mrw" | "mef" | "mos" | "erf" | "dcr" | "kdc" | "fff" | "iiq" | "rwl" | "raw"
+    )
+}
+
+/// Decode RAW/HEIF to an intermediate image (PPM or PNG) using external tools.
+/// RAW goes through dcraw_emu (half-size, camera white balance); anything left goes through ffmpeg.
+fn decode_with_external_tools(src: &str) -> Result<Vec<u8>> {
+    let ext = Path::new(src).extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).unwrap_or_default();
+    let mut errors = Vec::new();
+
+    if is_raw_ext(&ext) {
+        match std::process::Command::new(DCRAW_EMU_BIN.as_str())
+            .args(["-w", "-h", "-Z", "-", src])
+            .stdin(std::process::Stdio::null())
+            .output()
+        {
+            Ok(out) if out.status.success() && !out.stdout.is_empty() => return Ok(out.stdout),
+            Ok(out) => errors.push(format!("dcraw_emu: {}", String::from_utf8_lossy(&out.stderr).trim())),
+            Err(e) => errors.push(format!("dcraw_emu: {}", e)),
+        }
+    }
+
+    let args: Vec<String> = vec![
+        "-v".into(), "error".into(),
+        "-i".into(), src.into(),
+        "-frames:v".into(), "1".into(),
+        "-f".into(), "image2pipe".into(),
+        "-vcodec".into(), "png".into(),
+        "-".into(),
+    ];
+    match ffmpeg::run_ffmpeg_with_timeout(args, Duration::from_secs(60)) {
+        Ok(out) if out.status.success() && !out.stdout.is_empty() => return Ok(out.stdout),
+        Ok(out) => errors.push(format!("ffmpeg: {}", String::from_utf8_lossy(&out.stderr).trim())),
+        Err(e) => errors.push(format!("ffmpeg: {}", e)),
+    }
+
+    anyhow::bail!("No decoder could open {}: {}", src, errors.join("; "))
+}
+
+#[cfg(not(target_env = "msvc"))]
+fn buffer_make_sizes(data: &[u8], src: &str, sizes: &[(&Path, i32)]) -> Result<()> {
+    let img = libvips::VipsImage::new_from_buffer(data, "")
+        .map_err(|e| anyhow::anyhow!("Failed to load decoded image {}: {}", src, e))?;
+    let largest = libvips::ops::thumbnail_image(&img, sizes[0].1)
+        .map_err(|e| anyhow::anyhow!("Failed to create thumbnail for {}: {}", src, e))?;
+    vips_write_sizes(largest, src, sizes)
+}
+
+#[cfg(target_env = "msvc")]
+fn buffer_make_sizes(data: &[u8], src: &str, sizes: &[(&Path, i32)]) -> Result<()> {
+    let img = image::load_from_memory(data).map_err(|e| anyhow::anyhow!("Failed to decode image {}: {}", src, e))?;
+    write_sizes(img, src, sizes);
+    Ok(())
+}
+
+/// Build every missing derivative for an image from a single decode. RAW/HEIF files that the
+/// native decoder rejects are decoded once through external tools and resized from that.
+fn image_make_thumbs(src: &str, targets: &[(PathBuf, i32)]) {
+    if targets.is_empty() {
+        return;
+    }
+    let sizes = sizes_descending(targets);
+    let pending: Vec<(&Path, i32)> = match image_make_sizes(src, &sizes) {
+        Ok(()) => return,
+        Err(e) if needs_decode_fallback(src) => {
+            debug!("Native decode failed for {} ({}), trying external decoders", src, e);
+            sizes
+        }
+        Err(e) => {
+            warn!("Failed to create derivatives for {}: {}", src, e);
+            return;
+        }
+    };
+    match decode_with_external_tools(src).and_then(|data| buffer_make_sizes(&data, src, &pending)) {
+        Ok(()) => debug!("Decoded {} via external decoder", src),
+        Err(e) => warn!("Failed to decode {}: {}", src, e),
+    }
+}
+
 fn video_make_thumb(src: &str, dst: &Path, size: i32) -> Result<()> {
     // Extract a frame from video at 1 second (or start if video is shorter)
     // Try GPU-accelerated path first, fallback to CPU
@@ -367,7 +535,167 @@ fn video_make_thumb(src: &str, dst: &Path, size: i32) -> Result<()> {
     }
 }
 
-pub fn start_workers(n: usize, mut rx: Receiver<ThumbJob>, derived: PathBuf, thumb_size: i32, preview_size: i32, gauges: Arc<QueueGauges>) {
+pub(crate) fn video_duration_secs(src: &str) -> Option<f64> {
+    let out = std::process::Command::new(ffmpeg::ffprobe_bin())
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1", src])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&out.stdout).trim().parse::<f64>().ok().filter(|d| *d > 0.0)
+}
+
+/// Render a short looping animated WebP by sampling frames evenly across the clip
+fn video_make_anim(src: &str, dst: &Path, size: i32) -> Result<()> {
+    let duration = video_duration_secs(src).unwrap_or(ANIM_FRAMES as f64);
+    // Sample ANIM_FRAMES frames over the clip, then show each for half a second
+    let filter = format!(
+        "fps={:.6},scale={}:{}:force_original_aspect_ratio=decrease,setpts=N/2/TB",
+        ANIM_FRAMES as f64 / duration,
+        size,
+        size
+    );
+    let args: Vec<String> = vec![
+        "-v".into(), "error".into(),
+        "-i".into(), src.into(),
+        "-vf".into(), filter,
+        "-frames:v".into(), ANIM_FRAMES.to_string(),
+        "-an".into(),
+        "-r".into(), "2".into(),
+        "-c:v".into(), "libwebp_anim".into(),
+        "-loop".into(), "0".into(),
+        "-quality".into(), "70".into(),
+        "-f".into(), "webp".into(),
+        "-y".into(), dst.to_string_lossy().to_string(),
+    ];
+    let result = ffmpeg::run_ffmpeg_with_timeout(args, Duration::from_secs(60));
+    let ok = matches!(&result, Ok(out) if out.status.success()) && dst.metadata().map(|m| m.len() > 0).unwrap_or(false);
+    if ok {
+        return Ok(());
+    }
+    if dst.exists() {
+        let _ = std::fs::remove_file(dst);
+    }
+    match result {
+        Ok(out) => anyhow::bail!("ffmpeg failed to render animated preview for {}: {}", src, String::from_utf8_lossy(&out.stderr).trim()),
+        Err(e) => anyhow::bail!("ffmpeg failed to render animated preview for {}: {}", src, e),
+    }
+}
+
+/// Render the static thumbnail and preview for one asset right away instead of waiting in the queue.
+/// Returns false when both already existed.
+pub fn render_now(job: &ThumbJob, derived: &Path, thumb_size: i32, preview_size: i32) -> Result<bool> {
+    if job.sha256_hex.len() < 2 {
+        anyhow::bail!("asset {} has not been hashed yet", job.id);
+    }
+    let targets: Vec<(PathBuf, i32)> = [thumb_size, preview_size]
+        .into_iter()
+        .map(|size| (thumb_path(derived, &job.sha256_hex, size), size))
+        .filter(|(p, _)| !p.exists())
+        .collect();
+    if targets.is_empty() {
+        return Ok(false);
+    }
+    ensure_dir(targets[0].0.parent().unwrap())?;
+    if job.mime.starts_with("image/") {
+        image_make_thumbs(&job.path, &targets);
+    } else if job.mime.starts_with("video/") {
+        for (dst, size) in &targets {
+            video_make_thumb(&job.path, dst, *size)?;
+        }
+    } else {
+        anyhow::bail!("asset {} is not an image or video", job.id);
+    }
+    if targets.iter().any(|(p, _)| !p.exists()) {
+        anyhow::bail!("could not render thumbnails for {}", job.path);
+    }
+    Ok(true)
+}
+
+/// A warm run only tops up the thumbnail queue while it is shorter than this, so scans keep priority
+const WARM_QUEUE_LOW: usize = 32;
+
+/// Progress of a bulk thumbnail warm-up or regeneration
+#[derive(Default)]
+pub struct WarmJob {
+    /// Assets matching the filter
+    pub matched: u64,
+    /// Matching assets to render: those missing a thumbnail or preview, or every hashed one when regenerating
+    pub missing: u64,
+    /// Existing renders are replaced rather than kept
+    pub regenerate: bool,
+    pub started_at: i64,
+    queued: AtomicU64,
+    running: AtomicBool,
+    finished_at: parking_lot::Mutex<Option<i64>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WarmReport {
+    pub running: bool,
+    pub regenerate: bool,
+    pub matched: u64,
+    pub missing: u64,
+    /// Jobs handed to the thumbnail workers so far
+    pub queued: u64,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+impl WarmJob {
+    pub fn new(matched: u64, missing: u64, started_at: i64) -> Self {
+        Self { matched, missing, started_at, running: AtomicBool::new(true), ..Default::default() }
+    }
+
+    /// A run that replaces existing renders
+    pub fn regenerating(matched: u64, missing: u64, started_at: i64) -> Self {
+        Self { regenerate: true, ..Self::new(matched, missing, started_at) }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self) -> WarmReport {
+        WarmReport {
+            running: self.is_running(),
+            regenerate: self.regenerate,
+            matched: self.matched,
+            missing: self.missing,
+            queued: self.queued.load(Ordering::Relaxed),
+            started_at: self.started_at,
+            finished_at: *self.finished_at.lock(),
+        }
+    }
+
+    /// Feed `jobs` to the thumbnail workers, waiting whenever the queue is busy with newly indexed files
+    pub async fn run(&self, jobs: Vec<ThumbJob>, tx: Sender<ThumbJob>, gauges: Arc<QueueGauges>) {
+        for job in jobs {
+            while gauges.thumb.load(Ordering::Relaxed) >= WARM_QUEUE_LOW {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+            gauges.thumb.fetch_add(1, Ordering::Relaxed);
+            if tx.send(job).await.is_err() {
+                gauges.thumb.fetch_sub(1, Ordering::Relaxed);
+                break;
+            }
+            self.queued.fetch_add(1, Ordering::Relaxed);
+        }
+        *self.finished_at.lock() = Some(chrono::Utc::now().timestamp());
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Rendered sizes for the thumbnail workers
+#[derive(Clone, Copy, Debug)]
+pub struct ThumbOptions {
+    pub thumb_size: i32,
+    pub preview_size: i32,
+    /// Also render the animated hover preview for videos
+    pub video_anim_preview: bool,
+}
+
+pub fn start_workers(n: usize, mut rx: Receiver<ThumbJob>, derived: PathBuf, options: ThumbOptions, gauges: Arc<QueueGauges>, stats: Arc<Stats>) {
+    let ThumbOptions { thumb_size, preview_size, video_anim_preview } = options;
     // Distribute jobs to workers using round-robin
     let mut worker_txs = Vec::new();
     let mut worker_rxs = Vec::new();
@@ -396,9 +724,14 @@ pub fn start_workers(n: usize, mut rx: Receiver<ThumbJob>, derived: PathBuf, thu
     for mut worker_rx in worker_rxs.into_iter() {
         let derivedc = derived.clone();
         let gaugesc = gauges.clone();
+        let statsc = stats.clone();
         tokio::spawn(async move {
             while let Some(job) = worker_rx.recv().await {
+                let run = gaugesc.wait_to_run(FlushQueue::Thumb, job.queued_at).await;
                 gaugesc.thumb.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+                if !run {
+                    continue;
+                }
                 if job.sha256_hex.len() < 2 {
                     continue;
                 }
@@ -408,40 +741,34 @@ pub fn start_workers(n: usize, mut rx: Receiver<ThumbJob>, derived: PathBuf, thu
                     continue;
                 }
 
-                let src = job.path.clone();
+                let src = crate::utils::path::long_path(Path::new(&job.path)).to_string_lossy().into_owned();
                 let sha_hex = job.sha256_hex.clone();
 
                 let p1 = thumb_path(&derivedc, &sha_hex, thumb_size);
                 let p2 = thumb_path(&derivedc, &sha_hex, preview_size);
                 let _ = ensure_dir(p1.parent().unwrap());
-                let p1_exists = p1.exists();
-                let p2_exists = p2.exists();
-                if !p1_exists || !p2_exists {
+                let p1_exists = !job.force && p1.exists();
+                let p2_exists = !job.force && p2.exists();
+                let anim = anim_path(&derivedc, &sha_hex);
+                let anim_missing = is_video && video_anim_preview && (job.force || !anim.exists());
+                if !p1_exists || !p2_exists || anim_missing {
+                    // Video renders only decode a few frames, so just images count as reads and against the read cap
+                    let bytes = if is_image { std::fs::metadata(&src).map(|m| m.len()).unwrap_or(0) } else { 0 };
+                    let permit = gaugesc.throttle.acquire(Stage::Thumb, bytes).await;
                     let src_clone = src.clone();
                     let p1_clone = p1.clone();
                     let p2_clone = p2.clone();
+                    let started = std::time::Instant::now();
                     if is_image {
                         let _ = tokio::task::spawn_blocking(move || {
+                            let mut targets = Vec::with_capacity(2);
                             if !p1_exists {
-                                match image_make_thumb(&src_clone, &p1_clone, thumb_size) {
-                                    Ok(()) => {
-                                        debug!("Successfully created thumbnail for {}: {:?}", src_clone, p1_clone);
-                                    }
-                                    Err(e) => {
-                                        warn!("Failed to create thumbnail for {}: {}", src_clone, e);
-                                    }
-                                }
+                                targets.push((p1_clone, thumb_size));
                             }
                             if !p2_exists {
-                                match image_make_thumb(&src_clone, &p2_clone, preview_size) {
-                                    Ok(()) => {
-                                        debug!("Successfully created preview for {}: {:?}", src_clone, p2_clone);
-                                    }
-                                    Err(e) => {
-                                        warn!("Failed to create preview for {}: {}", src_clone, e);
-                                    }
-                                }
+                                targets.push((p2_clone, preview_size));
                             }
+                            image_make_thumbs(&src_clone, &targets);
                         })
                         .await;
                     } else if is_video {
@@ -481,9 +808,23 @@ pub fn start_workers(n: usize, mut rx: Receiver<ThumbJob>, derived: PathBuf, thu
                                     }
                                 }
                             }
+                            if anim_missing {
+                                match video_make_anim(&src_clone_for_preview, &anim, thumb_size) {
+                                    Ok(()) => debug!("Created animated preview for {}: {:?}", src_clone_for_preview, anim),
+                                    Err(e) => warn!("Failed to create animated preview for {}: {}", src_clone_for_preview, e),
+                                }
+                            }
                         })
                         .await;
                     }
+                    statsc.record_read(IoStage::Thumb, bytes);
+                    statsc.observe_stage(PipelineStage::Thumb, started.elapsed());
+                    statsc.record_asset_cost(PipelineStage::Thumb, &job.path, started.elapsed());
+                    // The render helpers log their own failures; a missing output is what counts
+                    if !p1.exists() || !p2.exists() {
+                        statsc.inc_stage_errors(PipelineStage::Thumb);
+                    }
+                    permit.finish().await;
                 }
             }
         });
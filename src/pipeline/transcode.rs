@@ -0,0 +1,451 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::Receiver;
+use super::QueueGauges;
+use crate::stats::Stats;
+
+#[derive(Debug, Clone)]
+pub struct TranscodeJob {
+    pub asset_id: i64,
+    pub src: PathBuf,
+    pub dst: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TranscodeState {
+    Queued,
+    Running,
+    Failed { error: String },
+}
+
+/// In-flight and failed transcodes by asset id. Finished jobs are dropped; the cached file is the record.
+#[derive(Default)]
+pub struct TranscodeTracker {
+    states: Mutex<HashMap<i64, TranscodeState>>,
+}
+
+impl TranscodeTracker {
+    pub fn get(&self, asset_id: i64) -> Option<TranscodeState> {
+        self.states.lock().get(&asset_id).cloned()
+    }
+
+    /// Mark an asset queued unless it is already queued or running. Returns false if it was.
+    pub fn try_queue(&self, asset_id: i64) -> bool {
+        let mut states = self.states.lock();
+        match states.get(&asset_id) {
+            Some(TranscodeState::Queued) | Some(TranscodeState::Running) => false,
+            _ => {
+                states.insert(asset_id, TranscodeState::Queued);
+                true
+            }
+        }
+    }
+
+    pub fn set(&self, asset_id: i64, state: TranscodeState) {
+        self.states.lock().insert(asset_id, state);
+    }
+
+    pub fn clear(&self, asset_id: i64) {
+        self.states.lock().remove(&asset_id);
+    }
+}
+
+pub fn is_browser_compatible_video(mime: &str) -> bool {
+    // Browser-compatible formats that don't need transcoding
+    matches!(
+        mime,
+        "video/mp4" | "video/webm" | "video/ogg" | "video/ogv"
+    )
+}
+
+/// Whether a video has to be transcoded before browsers can play it
+pub fn needs_transcode(mime: &str, video_codec: Option<&str>) -> bool {
+    let mut browser_compatible = is_browser_compatible_video(mime);
+
+    if mime == "video/mp4" {
+        // HEVC transcode behavior can be controlled via env:
+        // SEEN_HEVC_TRANSCODE = "auto" (default) | "never" | "always"
+        let hevc_mode = std::env::var("SEEN_HEVC_TRANSCODE")
+            .unwrap_or_else(|_| "auto".to_string())
+            .to_lowercase();
+
+        match hevc_mode.as_str() {
+            "never" | "off" | "false" => {
+                // Always treat MP4 as browser-compatible, even if HEVC.
+                browser_compatible = true;
+            }
+            "always" | "force" => {
+                // Always transcode MP4, regardless of codec.
+                browser_compatible = false;
+            }
+            _ => {
+                // auto mode: only force transcode for HEVC/H.265 when codec is known.
+                if let Some(codec) = video_codec {
+                    let codec_lower = codec.to_lowercase();
+                    if codec_lower.contains("hevc") || codec_lower.contains("h265") {
+                        browser_compatible = false;
+                    }
+                }
+            }
+        }
+    }
+
+    !browser_compatible
+}
+
+/// Where ffmpeg writes before the output is renamed to `dst`, e.g. `abc.partial.mp4`;
+/// one left behind by a crash is removed at the next startup
+pub fn partial_path(dst: &Path) -> PathBuf {
+    let ext = dst.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+    dst.with_extension(format!("partial.{}", ext))
+}
+
+/// Cached transcode output for a video, if one exists (MP4, or WebM from the CPU fallback)
+pub fn cached_transcode(dst: &Path) -> Option<(PathBuf, &'static str)> {
+    let non_empty = |p: &Path| std::fs::metadata(p).map(|m| m.is_file() && m.len() > 0).unwrap_or(false);
+    let webm = dst.with_extension("webm");
+    if non_empty(dst) {
+        Some((dst.to_path_buf(), "video/mp4"))
+    } else if non_empty(&webm) {
+        Some((webm, "video/webm"))
+    } else {
+        None
+    }
+}
+
+/// Transcode with the GPU-aware encoder, falling back to the CPU encoder chain
+pub async fn transcode_with_fallback(src: &Path, dst: &Path) -> anyhow::Result<(PathBuf, &'static str)> {
+    if let Err(e) = transcode_video_to_mp4(src, dst).await {
+        tracing::error!("Failed to transcode video {}: {}", src.display(), e);
+        tracing::info!("Attempting CPU fallback transcoding for {}", src.display());
+        transcode_video_to_mp4_cpu(src, dst).await?;
+    }
+    cached_transcode(dst).ok_or_else(|| anyhow::anyhow!("Transcoding completed but no output file found for {}", src.display()))
+}
+
+/// Run queued transcodes one at a time so a burst of requests can't saturate the encoder
+pub fn start_worker(mut rx: Receiver<TranscodeJob>, tracker: Arc<TranscodeTracker>, gauges: Arc<QueueGauges>, stats: Arc<Stats>) {
+    tokio::spawn(async move {
+        while let Some(job) = rx.recv().await {
+            gauges.wait_while_paused().await;
+            gauges.transcode.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+            if cached_transcode(&job.dst).is_some() {
+                tracker.clear(job.asset_id);
+                continue;
+            }
+            tracker.set(job.asset_id, TranscodeState::Running);
+            tracing::info!("Transcoding video {} ({})", job.asset_id, job.src.display());
+            let started = std::time::Instant::now();
+            match transcode_with_fallback(&job.src, &job.dst).await {
+                Ok((path, _)) => {
+                    tracing::info!("Transcoded video {} -> {}", job.asset_id, path.display());
+                    stats.observe_transcode(started.elapsed());
+                    tracker.clear(job.asset_id);
+                }
+                Err(e) => {
+                    tracing::error!("Transcoding video {} failed: {}", job.asset_id, e);
+                    stats.inc_transcode_failures();
+                    tracker.set(job.asset_id, TranscodeState::Failed { error: e.to_string() });
+                }
+            }
+        }
+    });
+}
+
+pub fn get_transcoded_video_path(derived_dir: &Path, sha256: &str) -> PathBuf {
+    let path = if sha256.len() >= 2 {
+        let sub = &sha256[0..2];
+        derived_dir.join(sub).join(format!("{}-transcoded.mp4", sha256))
+    } else {
+        derived_dir.join(format!("{}-transcoded.mp4", sha256))
+    };
+    crate::utils::path::long_path(&path)
+}
+
+async fn transcode_video_to_mp4(src_path: &Path, dst_path: &Path) -> Result<(), anyhow::Error> {
+    use std::time::Duration;
+
+    // Ensure parent directory exists
+    if let Some(parent) = dst_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    // Get GPU config for potential acceleration
+    let gpu_config = crate::utils::ffmpeg::get_gpu_config();
+    // Respect auto-disabled GPU flag: if disabled, fall back to CPU.
+    let accel = if gpu_config.enabled {
+        gpu_config.accel.clone()
+    } else {
+        crate::utils::ffmpeg::GpuAccel::Cpu
+    };
+
+    // Build FFmpeg args based on GPU availability
+    let mut args = Vec::new();
+
+    // Overwrite output file if it exists
+    args.push("-y".to_string());
+
+    // Add GPU acceleration settings (must come before input file)
+    match accel {
+        crate::utils::ffmpeg::GpuAccel::Cuda => {
+            // Use CUDA for hardware-accelerated decoding
+            args.push("-hwaccel".to_string());
+            args.push("cuda".to_string());
+        }
+        crate::utils::ffmpeg::GpuAccel::Qsv => {
+            args.push("-hwaccel".to_string());
+            args.push("qsv".to_string());
+        }
+        crate::utils::ffmpeg::GpuAccel::D3d11va => {
+            args.push("-hwaccel".to_string());
+            args.push("d3d11va".to_string());
+        }
+        crate::utils::ffmpeg::GpuAccel::VideoToolbox => {
+            args.push("-hwaccel".to_string());
+            args.push("videotoolbox".to_string());
+        }
+        crate::utils::ffmpeg::GpuAccel::Cpu => {
+            // No hardware acceleration
+        }
+    }
+
+    // Add input file
+    args.push("-i".to_string());
+    args.push(src_path.to_string_lossy().to_string());
+
+    // Add encoding settings (after input file)
+    match accel {
+        crate::utils::ffmpeg::GpuAccel::Cuda => {
+            args.push("-c:v".to_string());
+            args.push("h264_nvenc".to_string());
+            args.push("-preset".to_string());
+            args.push("p4".to_string()); // NVENC preset (p4 = medium quality, good speed)
+            args.push("-cq".to_string());
+            args.push("23".to_string()); // Constant quality (similar to CRF)
+        }
+        crate::utils::ffmpeg::GpuAccel::Qsv => {
+            args.push("-c:v".to_string());
+            args.push("h264_qsv".to_string());
+            args.push("-preset".to_string());
+            args.push("medium".to_string());
+            args.push("-global_quality".to_string());
+            args.push("23".to_string());
+        }
+        crate::utils::ffmpeg::GpuAccel::D3d11va => {
+            // D3D11VA for decode, try NVENC for encode (fallback to CPU handled by FFmpeg)
+            args.push("-c:v".to_string());
+            args.push("h264_nvenc".to_string());
+            args.push("-preset".to_string());
+            args.push("p4".to_string());
+            args.push("-cq".to_string());
+            args.push("23".to_string());
+        }
+        crate::utils::ffmpeg::GpuAccel::VideoToolbox => {
+            args.push("-c:v".to_string());
+            args.push("h264_videotoolbox".to_string());
+            args.push("-b:v".to_string());
+            args.push("5M".to_string()); // Bitrate for VideoToolbox
+        }
+        crate::utils::ffmpeg::GpuAccel::Cpu => {
+            // Pure CPU encoding using libx264 (widely supported in browsers)
+            args.push("-c:v".to_string());
+            args.push("libx264".to_string());
+            args.push("-preset".to_string());
+            args.push("medium".to_string());
+            args.push("-crf".to_string());
+            args.push("23".to_string());
+        }
+    }
+
+    // Audio encoding
+    args.push("-c:a".to_string());
+    args.push("aac".to_string());
+    args.push("-b:a".to_string());
+    args.push("192k".to_string());
+
+    // Output format settings
+    args.push("-movflags".to_string());
+    args.push("+faststart".to_string()); // Enable streaming
+    args.push("-f".to_string());
+    args.push("mp4".to_string());
+
+    // Output file, renamed into place once complete
+    let partial = partial_path(dst_path);
+    args.push(partial.to_string_lossy().to_string());
+
+    tracing::info!("Transcoding video: {} -> {}", src_path.display(), dst_path.display());
+
+    // Run FFmpeg with timeout (10 minutes for long videos) in a blocking task
+    let src_path_str = src_path.to_path_buf();
+    let dst_path_str = dst_path.to_path_buf();
+    let output = tokio::task::spawn_blocking(move || {
+        crate::utils::ffmpeg::run_ffmpeg_with_timeout(args, Duration::from_secs(600))
+    }).await??;
+
+    let used_gpu = !matches!(accel, crate::utils::ffmpeg::GpuAccel::Cpu);
+
+    if !output.status.success() {
+        if used_gpu {
+            crate::utils::ffmpeg::record_gpu_failure();
+        } else {
+            crate::utils::ffmpeg::increment_cpu_job();
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // Clean up partial file if it exists
+        let _ = std::fs::remove_file(&partial);
+        return Err(anyhow::anyhow!("FFmpeg transcoding failed: {}", stderr));
+    }
+
+    // Successful transcode; update GPU/CPU stats
+    if used_gpu {
+        crate::utils::ffmpeg::increment_gpu_job();
+    } else {
+        crate::utils::ffmpeg::increment_cpu_job();
+    }
+
+    // Verify the output file exists and has content
+    // Small delay to ensure file is fully written to disk
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    match std::fs::metadata(&partial) {
+        Ok(meta) if meta.is_file() && meta.len() > 0 => {
+            std::fs::rename(&partial, &dst_path_str)?;
+            tracing::info!("Video transcoding completed successfully: {} -> {} ({} bytes)",
+                src_path_str.display(), dst_path_str.display(), meta.len());
+            Ok(())
+        }
+        Ok(meta) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(anyhow::anyhow!("Transcoded file is invalid: is_file={}, size={}", meta.is_file(), meta.len()))
+        }
+        Err(e) => {
+            Err(anyhow::anyhow!("Transcoded file not found after transcoding: {}", e))
+        }
+    }
+}
+
+async fn transcode_video_to_mp4_cpu(src_path: &Path, dst_path: &Path) -> Result<(), anyhow::Error> {
+    use std::time::Duration;
+
+    // Ensure parent directory exists
+    if let Some(parent) = dst_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    // Try encoders in order of preference
+    // Note: mpeg4 (MPEG-4 Part 2) is not well-supported by browsers in MP4 containers.
+    // Browsers expect H.264 (MPEG-4 Part 10/AVC), so we prioritize libx264 and WebM codecs.
+    let encoder_configs = vec![
+        ("libx264", ("mp4", vec!["-preset", "medium", "-crf", "23"])),
+        ("h264_v4l2m2m", ("mp4", vec!["-qmin", "18", "-qmax", "28", "-b:v", "2M"])), // V4L2 mem2mem H.264 (hardware if available)
+        ("libx265", ("mp4", vec!["-preset", "medium", "-x265-params", "crf=23"])), // Use x265-params for libx265
+        ("h264_qsv", ("mp4", vec!["-preset", "medium", "-global_quality", "23"])),
+        ("libvpx-vp9", ("webm", vec!["-quality", "good", "-speed", "1", "-b:v", "2M"])), // WebM with VP9 - use quality/speed instead of crf
+        ("libvpx", ("webm", vec!["-quality", "good", "-speed", "1", "-b:v", "2M"])), // WebM with VP8 - use quality/speed instead of crf
+        ("mpeg4", ("mp4", vec!["-qscale:v", "3", "-pix_fmt", "yuv420p"])), // Last resort
+    ];
+
+    let mut last_error = None;
+
+    for (encoder, (container_format, encoder_args)) in encoder_configs {
+        // Build FFmpeg args for CPU-only encoding
+        let mut args = vec!["-y".to_string()];
+
+        // Add input file
+        args.push("-i".to_string());
+        args.push(src_path.to_string_lossy().to_string());
+
+        // Add color space conversion for MJPEG inputs (yuvj422p -> yuv420p)
+        // This fixes the "deprecated pixel format" warning and ensures proper color range
+        // MJPEG uses full-range JPEG colorspace (yuvj422p), need to convert to standard yuv420p
+        args.push("-vf".to_string());
+        args.push("format=yuv420p".to_string()); // Convert to standard yuv420p with proper color range
+
+        // Try this encoder
+        args.push("-c:v".to_string());
+        args.push(encoder.to_string());
+        for arg in encoder_args {
+            args.push(arg.to_string());
+        }
+
+        // Audio encoding - use opus for WebM, aac for MP4
+        args.push("-c:a".to_string());
+        if container_format == "webm" {
+            args.push("libopus".to_string());
+            args.push("-b:a".to_string());
+            args.push("128k".to_string());
+        } else {
+            args.push("aac".to_string());
+            args.push("-b:a".to_string());
+            args.push("192k".to_string());
+        }
+
+        // Output format settings
+        if container_format == "mp4" {
+            args.push("-movflags".to_string());
+            args.push("+faststart".to_string());
+        }
+        args.push("-f".to_string());
+        args.push(container_format.to_string());
+
+        // Output file - adjust extension based on container format
+        let output_path = if container_format == "webm" {
+            dst_path.with_extension("webm")
+        } else {
+            dst_path.to_path_buf()
+        };
+        let partial = partial_path(&output_path);
+        args.push(partial.to_string_lossy().to_string());
+
+        tracing::info!("Trying CPU transcoding with encoder '{}' ({}): {} -> {}", encoder, container_format, src_path.display(), output_path.display());
+
+        // Run FFmpeg with timeout
+        let src_path_str = src_path.to_path_buf();
+        let output_path_str = output_path.clone();
+        let output = tokio::task::spawn_blocking(move || {
+            crate::utils::ffmpeg::run_ffmpeg_with_timeout(args, Duration::from_secs(600))
+        }).await??;
+
+        if output.status.success() {
+            // Verify the output file exists and has content
+            std::thread::sleep(std::time::Duration::from_millis(100));
+
+            match std::fs::metadata(&partial) {
+                Ok(meta) if meta.is_file() && meta.len() > 0 => {
+                    std::fs::rename(&partial, &output_path_str)?;
+                    tracing::info!("CPU video transcoding succeeded with encoder '{}' ({}): {} -> {} ({} bytes)",
+                        encoder, container_format, src_path_str.display(), output_path_str.display(), meta.len());
+                    // Increment CPU job counter for periodic GPU retry mechanism
+                    crate::utils::ffmpeg::increment_cpu_job();
+                    // Keep the file in its native format (WebM or MP4)
+                    // The serving logic will handle the MIME type based on file extension
+                    return Ok(());
+                }
+                Ok(meta) => {
+                    let _ = std::fs::remove_file(&partial);
+                    last_error = Some(format!("Transcoded file is invalid: is_file={}, size={}", meta.is_file(), meta.len()));
+                    continue; // Try next encoder
+                }
+                Err(e) => {
+                    last_error = Some(format!("Transcoded file not found: {}", e));
+                    continue; // Try next encoder
+                }
+            }
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // Clean up partial file if it exists
+            let _ = std::fs::remove_file(&partial);
+            last_error = Some(format!("Encoder '{}' failed: {}", encoder, stderr));
+            tracing::warn!("Encoder '{}' failed, trying next: {}", encoder, stderr);
+            continue; // Try next encoder
+        }
+    }
+
+    // All encoders failed
+    Err(anyhow::anyhow!("All video encoders failed. Last error: {}",
+        last_error.unwrap_or_else(|| "Unknown error".to_string())))
+}
@@ -1,10 +1,188 @@
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+/// Pipeline stages that read originals
+#[derive(Clone, Copy, Debug)]
+pub enum IoStage {
+    Hash,
+    Metadata,
+    Thumb,
+}
+
+/// Pipeline stages with a latency histogram and an error counter
+#[derive(Clone, Copy, Debug)]
+pub enum PipelineStage {
+    Hash,
+    Metadata,
+    Thumb,
+    Face,
+    /// One committed batch
+    DbWrite,
+}
+
+impl PipelineStage {
+    const ALL: [PipelineStage; 5] = [PipelineStage::Hash, PipelineStage::Metadata, PipelineStage::Thumb, PipelineStage::Face, PipelineStage::DbWrite];
+
+    fn label(self) -> &'static str {
+        match self {
+            PipelineStage::Hash => "hash",
+            PipelineStage::Metadata => "metadata",
+            PipelineStage::Thumb => "thumb",
+            PipelineStage::Face => "face",
+            PipelineStage::DbWrite => "db_write",
+        }
+    }
+}
+
+/// Most files whose processing time is kept; past this the cheaper half is forgotten
+const ASSET_COST_CAPACITY: usize = 10_000;
+
+/// Time one file has taken in the pipeline so far
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct AssetCost {
+    pub path: String,
+    pub total_ms: u64,
+    /// Milliseconds per stage that has seen the file
+    pub stages: std::collections::BTreeMap<&'static str, u64>,
+}
+
+/// Upper bounds, in seconds, for per-file stage latency
+const STAGE_SECONDS_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+/// Upper bounds, in seconds, for video transcodes
+const TRANSCODE_SECONDS_BUCKETS: &[f64] = &[1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0];
+/// Upper bounds, in rows, for DB writer batches
+const BATCH_SIZE_BUCKETS: &[f64] = &[1.0, 10.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0];
+
+#[derive(Default)]
+struct HistogramData {
+    /// Observations per bucket, not cumulative; the last one is +Inf
+    counts: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+/// A Prometheus histogram with fixed bucket bounds
+pub struct Histogram {
+    bounds: &'static [f64],
+    data: parking_lot::Mutex<HistogramData>,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self { bounds, data: parking_lot::Mutex::new(HistogramData { counts: vec![0; bounds.len() + 1], ..Default::default() }) }
+    }
+
+    pub fn observe(&self, value: f64) {
+        let bucket = self.bounds.iter().position(|b| value <= *b).unwrap_or(self.bounds.len());
+        let mut d = self.data.lock();
+        d.counts[bucket] += 1;
+        d.count += 1;
+        d.sum += value;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.data.lock().count
+    }
+
+    /// Average observation, 0 before the first
+    pub fn mean(&self) -> f64 {
+        let d = self.data.lock();
+        if d.count == 0 { 0.0 } else { d.sum / d.count as f64 }
+    }
+
+    /// `labels` is empty or a `key="value"` list without braces
+    fn write(&self, out: &mut String, name: &str, labels: &str) {
+        let d = self.data.lock();
+        let sep = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (i, n) in d.counts.iter().enumerate() {
+            cumulative += n;
+            let le = self.bounds.get(i).map_or_else(|| "+Inf".to_string(), |b| b.to_string());
+            out.push_str(&format!("{}_bucket{{{}{}le=\"{}\"}} {}\n", name, labels, sep, le, cumulative));
+        }
+        let braces = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+        out.push_str(&format!("{}_sum{} {}\n", name, braces, d.sum));
+        out.push_str(&format!("{}_count{} {}\n", name, braces, d.count));
+    }
+}
+
+/// `# HELP` and `# TYPE` lines opening a metric family
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+}
+
+/// Rates cover the current window plus the one before it; a stage idle for two windows reports 0
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+struct RateWindow {
+    started: Option<Instant>,
+    files: u64,
+    bytes: u64,
+    prev_files: u64,
+    prev_bytes: u64,
+    prev_secs: f64,
+}
+
+/// Files and bytes one stage has read, with its recent throughput
+#[derive(Default)]
+pub struct StageThroughput {
+    files_total: AtomicU64,
+    bytes_total: AtomicU64,
+    window: parking_lot::Mutex<RateWindow>,
+}
+
+impl StageThroughput {
+    fn record(&self, bytes: u64) {
+        self.files_total.fetch_add(1, Ordering::Relaxed);
+        self.bytes_total.fetch_add(bytes, Ordering::Relaxed);
+        let now = Instant::now();
+        let mut w = self.window.lock();
+        let elapsed = now.duration_since(*w.started.get_or_insert(now));
+        if elapsed >= RATE_WINDOW {
+            let idle = elapsed >= RATE_WINDOW * 2;
+            w.prev_files = if idle { 0 } else { w.files };
+            w.prev_bytes = if idle { 0 } else { w.bytes };
+            w.prev_secs = if idle { 0.0 } else { elapsed.as_secs_f64() };
+            w.started = Some(now);
+            w.files = 0;
+            w.bytes = 0;
+        }
+        w.files += 1;
+        w.bytes += bytes;
+    }
+
+    pub fn files_total(&self) -> u64 { self.files_total.load(Ordering::Relaxed) }
+    pub fn bytes_total(&self) -> u64 { self.bytes_total.load(Ordering::Relaxed) }
+
+    /// (files/sec, bytes/sec) over roughly the last 5-10 seconds
+    pub fn rates(&self) -> (f64, f64) {
+        let w = self.window.lock();
+        let Some(started) = w.started else {
+            return (0.0, 0.0);
+        };
+        let elapsed = started.elapsed();
+        if elapsed >= RATE_WINDOW * 2 {
+            return (0.0, 0.0);
+        }
+        // At least a second, so the first file of a burst doesn't read as a huge rate
+        let secs = (elapsed.as_secs_f64() + w.prev_secs).max(1.0);
+        ((w.files + w.prev_files) as f64 / secs, (w.bytes + w.prev_bytes) as f64 / secs)
+    }
+
+    fn reset(&self) {
+        self.files_total.store(0, Ordering::Relaxed);
+        self.bytes_total.store(0, Ordering::Relaxed);
+        *self.window.lock() = RateWindow::default();
+    }
+}
 
 pub struct Stats {
     files_total: AtomicU64,
     bytes_total: AtomicU64,
     files_committed: AtomicU64,
+    files_unchanged: AtomicU64,
+    files_filtered: AtomicU64,
     started: Instant,
     last_scan_start: parking_lot::Mutex<Option<Instant>>,
     last_processing_start: parking_lot::Mutex<Option<Instant>>,
@@ -17,6 +195,18 @@ pub struct Stats {
     last_completed_processing_rate: parking_lot::Mutex<Option<f64>>,
     last_completed_processing_mb_per_sec: parking_lot::Mutex<Option<f64>>,
     last_completed_processing_elapsed: parking_lot::Mutex<Option<f64>>,
+    hash_io: StageThroughput,
+    metadata_io: StageThroughput,
+    thumb_io: StageThroughput,
+    stage_seconds: [Histogram; 5],
+    stage_errors: [AtomicU64; 5],
+    db_batch_rows: Histogram,
+    thumb_cache_hits: AtomicU64,
+    thumb_cache_misses: AtomicU64,
+    transcode_seconds: Histogram,
+    transcode_failures: AtomicU64,
+    /// Seconds spent per stage on each file, by path
+    asset_costs: parking_lot::Mutex<std::collections::HashMap<String, [f64; 5]>>,
 }
 
 impl Default for Stats {
@@ -31,6 +221,8 @@ impl Stats {
             files_total: AtomicU64::new(0),
             bytes_total: AtomicU64::new(0),
             files_committed: AtomicU64::new(0),
+            files_unchanged: AtomicU64::new(0),
+            files_filtered: AtomicU64::new(0),
             started: Instant::now(),
             last_scan_start: parking_lot::Mutex::new(None),
             last_processing_start: parking_lot::Mutex::new(None),
@@ -43,6 +235,17 @@ impl Stats {
             last_completed_processing_rate: parking_lot::Mutex::new(None),
             last_completed_processing_mb_per_sec: parking_lot::Mutex::new(None),
             last_completed_processing_elapsed: parking_lot::Mutex::new(None),
+            hash_io: StageThroughput::default(),
+            metadata_io: StageThroughput::default(),
+            thumb_io: StageThroughput::default(),
+            stage_seconds: PipelineStage::ALL.map(|_| Histogram::new(STAGE_SECONDS_BUCKETS)),
+            stage_errors: Default::default(),
+            db_batch_rows: Histogram::new(BATCH_SIZE_BUCKETS),
+            thumb_cache_hits: AtomicU64::new(0),
+            thumb_cache_misses: AtomicU64::new(0),
+            transcode_seconds: Histogram::new(TRANSCODE_SECONDS_BUCKETS),
+            transcode_failures: AtomicU64::new(0),
+            asset_costs: parking_lot::Mutex::new(std::collections::HashMap::new()),
         }
     }
     pub fn inc_files(&self, n: u64) { self.files_total.fetch_add(n, Ordering::Relaxed); }
@@ -74,7 +277,66 @@ impl Stats {
             self.last_scan_committed_files.store(new_value, Ordering::Relaxed);
         }
     }
+    /// Files a rescan skipped because path, size and mtime matched the database
+    pub fn inc_files_unchanged(&self, n: u64) { self.files_unchanged.fetch_add(n, Ordering::Relaxed); }
+    pub fn files_unchanged(&self) -> u64 { self.files_unchanged.load(Ordering::Relaxed) }
+    /// Files discovery dropped because of the ingest filters
+    pub fn inc_files_filtered(&self, n: u64) { self.files_filtered.fetch_add(n, Ordering::Relaxed); }
+    pub fn files_filtered(&self) -> u64 { self.files_filtered.load(Ordering::Relaxed) }
     pub fn files_committed(&self) -> u64 { self.files_committed.load(Ordering::Relaxed) }
+    pub fn stage(&self, stage: IoStage) -> &StageThroughput {
+        match stage {
+            IoStage::Hash => &self.hash_io,
+            IoStage::Metadata => &self.metadata_io,
+            IoStage::Thumb => &self.thumb_io,
+        }
+    }
+    /// Record one file a stage finished and the bytes it read from the original
+    pub fn record_read(&self, stage: IoStage, bytes: u64) { self.stage(stage).record(bytes); }
+    pub fn stage_latency(&self, stage: PipelineStage) -> &Histogram { &self.stage_seconds[stage as usize] }
+    pub fn observe_stage(&self, stage: PipelineStage, elapsed: Duration) { self.stage_latency(stage).observe(elapsed.as_secs_f64()); }
+    /// Add time spent on one file, failed attempts included. Only the costliest files are remembered.
+    pub fn record_asset_cost(&self, stage: PipelineStage, path: &str, elapsed: Duration) {
+        let mut costs = self.asset_costs.lock();
+        if costs.len() >= ASSET_COST_CAPACITY && !costs.contains_key(path) {
+            let mut totals: Vec<f64> = costs.values().map(|s| s.iter().sum()).collect();
+            let mid = totals.len() / 2;
+            let (_, median, _) = totals.select_nth_unstable_by(mid, f64::total_cmp);
+            let median = *median;
+            costs.retain(|_, s| s.iter().sum::<f64>() > median);
+        }
+        costs.entry(path.to_string()).or_default()[stage as usize] += elapsed.as_secs_f64();
+    }
+    /// The `n` files that took longest over all stages, slowest first
+    pub fn slowest_assets(&self, n: usize) -> Vec<AssetCost> {
+        let costs = self.asset_costs.lock();
+        let mut slowest: Vec<(&String, f64)> = costs.iter().map(|(path, s)| (path, s.iter().sum())).collect();
+        slowest.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        slowest
+            .into_iter()
+            .take(n)
+            .map(|(path, total)| AssetCost {
+                path: path.clone(),
+                total_ms: (total * 1000.0).round() as u64,
+                stages: PipelineStage::ALL
+                    .iter()
+                    .filter(|stage| costs[path][**stage as usize] > 0.0)
+                    .map(|stage| (stage.label(), (costs[path][*stage as usize] * 1000.0).round() as u64))
+                    .collect(),
+            })
+            .collect()
+    }
+    pub fn inc_stage_errors(&self, stage: PipelineStage) { self.stage_errors[stage as usize].fetch_add(1, Ordering::Relaxed); }
+    pub fn stage_errors(&self, stage: PipelineStage) -> u64 { self.stage_errors[stage as usize].load(Ordering::Relaxed) }
+    pub fn observe_db_batch(&self, rows: usize) { self.db_batch_rows.observe(rows as f64); }
+    pub fn db_batch_rows(&self) -> &Histogram { &self.db_batch_rows }
+    /// A thumbnail or preview request for a hashed asset, found in the cache (hit) or not rendered yet (miss)
+    pub fn inc_thumb_cache(&self, hit: bool) {
+        let counter = if hit { &self.thumb_cache_hits } else { &self.thumb_cache_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn observe_transcode(&self, elapsed: Duration) { self.transcode_seconds.observe(elapsed.as_secs_f64()); }
+    pub fn inc_transcode_failures(&self) { self.transcode_failures.fetch_add(1, Ordering::Relaxed); }
     pub fn files_total(&self) -> u64 { self.files_total.load(Ordering::Relaxed) }
     pub fn bytes_total(&self) -> u64 { self.bytes_total.load(Ordering::Relaxed) }
     pub fn uptime_secs(&self) -> u64 { self.started.elapsed().as_secs() }
@@ -244,11 +506,51 @@ impl Stats {
     }
     pub fn metrics_text(&self) -> String {
         let mut s = String::new();
+        family(&mut s, "seen_uptime_seconds", "gauge", "Seconds since the server started");
         s.push_str(&format!("seen_uptime_seconds {}\n", self.uptime_secs()));
+        family(&mut s, "seen_processed_files_total", "counter", "Files discovered for processing");
         s.push_str(&format!("seen_processed_files_total {}\n", self.files_total()));
+        family(&mut s, "seen_processed_bytes_total", "counter", "Bytes of the files discovered for processing");
         s.push_str(&format!("seen_processed_bytes_total {}\n", self.bytes_total()));
+        family(&mut s, "seen_processed_files_per_second", "gauge", "Files processed per second since startup");
         s.push_str(&format!("seen_processed_files_per_second {}\n", self.files_per_sec()));
+        family(&mut s, "seen_processed_bytes_per_second", "gauge", "Bytes processed per second since startup");
         s.push_str(&format!("seen_processed_bytes_per_second {}\n", self.bytes_per_sec()));
+
+        let io_stages = [("hash", IoStage::Hash), ("metadata", IoStage::Metadata), ("thumb", IoStage::Thumb)];
+        let io_families = [
+            ("seen_stage_files_total", "counter", "Files read by each pipeline stage"),
+            ("seen_stage_read_bytes_total", "counter", "Bytes read by each pipeline stage"),
+            ("seen_stage_files_per_second", "gauge", "Recent files per second of each pipeline stage"),
+            ("seen_stage_read_bytes_per_second", "gauge", "Recent read bytes per second of each pipeline stage"),
+        ];
+        for (i, (name, kind, help)) in io_families.into_iter().enumerate() {
+            family(&mut s, name, kind, help);
+            for (label, stage) in io_stages {
+                let io = self.stage(stage);
+                let (files_per_sec, bytes_per_sec) = io.rates();
+                let value = [io.files_total() as f64, io.bytes_total() as f64, files_per_sec, bytes_per_sec][i];
+                s.push_str(&format!("{}{{stage=\"{}\"}} {}\n", name, label, value));
+            }
+        }
+
+        family(&mut s, "seen_stage_duration_seconds", "histogram", "Time to process one item in each pipeline stage (one batch for db_write)");
+        for stage in PipelineStage::ALL {
+            self.stage_latency(stage).write(&mut s, "seen_stage_duration_seconds", &format!("stage=\"{}\"", stage.label()));
+        }
+        family(&mut s, "seen_stage_errors_total", "counter", "Items that failed in each pipeline stage");
+        for stage in PipelineStage::ALL {
+            s.push_str(&format!("seen_stage_errors_total{{stage=\"{}\"}} {}\n", stage.label(), self.stage_errors(stage)));
+        }
+        family(&mut s, "seen_db_write_batch_size", "histogram", "Rows committed per DB writer batch");
+        self.db_batch_rows.write(&mut s, "seen_db_write_batch_size", "");
+        family(&mut s, "seen_thumb_cache_requests_total", "counter", "Thumbnail and preview requests found in the cache (hit) or not rendered yet (miss)");
+        s.push_str(&format!("seen_thumb_cache_requests_total{{result=\"hit\"}} {}\n", self.thumb_cache_hits.load(Ordering::Relaxed)));
+        s.push_str(&format!("seen_thumb_cache_requests_total{{result=\"miss\"}} {}\n", self.thumb_cache_misses.load(Ordering::Relaxed)));
+        family(&mut s, "seen_transcode_duration_seconds", "histogram", "Time to transcode a video for browser playback");
+        self.transcode_seconds.write(&mut s, "seen_transcode_duration_seconds", "");
+        family(&mut s, "seen_transcode_failures_total", "counter", "Video transcodes that failed");
+        s.push_str(&format!("seen_transcode_failures_total {}\n", self.transcode_failures.load(Ordering::Relaxed)));
         s
     }
     
@@ -257,6 +559,8 @@ impl Stats {
         self.files_total.store(0, Ordering::Relaxed);
         self.bytes_total.store(0, Ordering::Relaxed);
         self.files_committed.store(0, Ordering::Relaxed);
+        self.files_unchanged.store(0, Ordering::Relaxed);
+        self.files_filtered.store(0, Ordering::Relaxed);
         // Note: started is not reset as it's used for uptime calculation
         // Instead, we reset last_scan_files to 0 so new scans start from 0
         self.last_scan_files.store(0, Ordering::Relaxed);
@@ -268,5 +572,84 @@ impl Stats {
         *self.last_completed_processing_rate.lock() = None;
         *self.last_completed_processing_mb_per_sec.lock() = None;
         *self.last_completed_processing_elapsed.lock() = None;
+        self.hash_io.reset();
+        self.metadata_io.reset();
+        self.thumb_io.reset();
+        self.asset_costs.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_throughput() {
+        let stats = Stats::new();
+        assert_eq!(stats.stage(IoStage::Hash).rates(), (0.0, 0.0));
+        stats.record_read(IoStage::Hash, 4_000_000);
+        stats.record_read(IoStage::Hash, 2_000_000);
+        stats.record_read(IoStage::Thumb, 1_000_000);
+
+        let hash = stats.stage(IoStage::Hash);
+        assert_eq!((hash.files_total(), hash.bytes_total()), (2, 6_000_000));
+        // Within the first second the rate is spread over one second
+        assert_eq!(hash.rates(), (2.0, 6_000_000.0));
+        assert_eq!(stats.stage(IoStage::Metadata).files_total(), 0);
+        assert!(stats.metrics_text().contains("seen_stage_read_bytes_total{stage=\"thumb\"} 1000000"));
+
+        stats.reset_stats();
+        assert_eq!(stats.stage(IoStage::Hash).bytes_total(), 0);
+        assert_eq!(stats.stage(IoStage::Hash).rates(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_slowest_assets() {
+        let stats = Stats::new();
+        stats.record_asset_cost(PipelineStage::Hash, "/a.jpg", Duration::from_millis(5));
+        stats.record_asset_cost(PipelineStage::Hash, "/huge.tif", Duration::from_millis(400));
+        stats.record_asset_cost(PipelineStage::Thumb, "/huge.tif", Duration::from_millis(2600));
+        stats.record_asset_cost(PipelineStage::Metadata, "/b.mp4", Duration::from_millis(900));
+
+        let slowest = stats.slowest_assets(2);
+        assert_eq!(slowest.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(), vec!["/huge.tif", "/b.mp4"]);
+        assert_eq!(slowest[0].total_ms, 3000);
+        assert_eq!(slowest[0].stages.iter().map(|(s, ms)| (*s, *ms)).collect::<Vec<_>>(), vec![("hash", 400), ("thumb", 2600)]);
+
+        // Past capacity the cheaper files make room
+        for i in 0..ASSET_COST_CAPACITY {
+            stats.record_asset_cost(PipelineStage::Hash, &format!("/{}.jpg", i), Duration::from_millis(1));
+        }
+        assert!(stats.asset_costs.lock().len() <= ASSET_COST_CAPACITY);
+        assert_eq!(stats.slowest_assets(1)[0].path, "/huge.tif");
+
+        stats.reset_stats();
+        assert!(stats.slowest_assets(10).is_empty());
+    }
+
+    #[test]
+    fn test_histogram_exposition() {
+        let stats = Stats::new();
+        stats.observe_stage(PipelineStage::Hash, Duration::from_millis(3));
+        stats.observe_stage(PipelineStage::Hash, Duration::from_millis(200));
+        stats.observe_stage(PipelineStage::Hash, Duration::from_secs(120));
+        stats.inc_stage_errors(PipelineStage::Thumb);
+        stats.observe_db_batch(100);
+        stats.inc_thumb_cache(true);
+
+        let text = stats.metrics_text();
+        assert!(text.contains("# TYPE seen_stage_duration_seconds histogram\n"));
+        assert!(text.contains("seen_stage_duration_seconds_bucket{stage=\"hash\",le=\"0.001\"} 0\n"));
+        assert!(text.contains("seen_stage_duration_seconds_bucket{stage=\"hash\",le=\"0.005\"} 1\n"));
+        assert!(text.contains("seen_stage_duration_seconds_bucket{stage=\"hash\",le=\"60\"} 2\n"));
+        assert!(text.contains("seen_stage_duration_seconds_bucket{stage=\"hash\",le=\"+Inf\"} 3\n"));
+        assert!(text.contains("seen_stage_duration_seconds_count{stage=\"hash\"} 3\n"));
+        assert!(text.contains("seen_stage_duration_seconds_count{stage=\"face\"} 0\n"));
+        assert!(text.contains("seen_stage_errors_total{stage=\"thumb\"} 1\n"));
+        assert!(text.contains("seen_db_write_batch_size_bucket{le=\"50\"} 0\nseen_db_write_batch_size_bucket{le=\"100\"} 1\n"));
+        assert!(text.contains("seen_db_write_batch_size_sum 100\n"));
+        assert!(text.contains("seen_thumb_cache_requests_total{result=\"hit\"} 1\n"));
+        // Every family is announced once
+        assert_eq!(text.matches("# TYPE seen_stage_files_total ").count(), 1);
     }
 }
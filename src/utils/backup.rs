@@ -0,0 +1,298 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+pub const BACKUP_FORMAT: &str = "seen-backup";
+pub const BACKUP_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DATABASE_ENTRY: &str = "seen.db";
+const DERIVED_PREFIX: &str = "derived";
+
+/// First entry of every backup archive
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupManifest {
+    pub format: String,
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: i64,
+    pub assets: i64,
+    pub albums: i64,
+    pub tags: i64,
+    pub persons: i64,
+    pub faces: i64,
+}
+
+/// Copy the live database into a standalone file, consistent even while the pipeline writes
+pub fn snapshot_database(conn: &rusqlite::Connection, dest: &Path) -> Result<BackupManifest> {
+    if dest.exists() {
+        std::fs::remove_file(dest)?;
+    }
+    conn.execute("VACUUM INTO ?1", rusqlite::params![dest.to_string_lossy()])?;
+    let snapshot = rusqlite::Connection::open(dest)?;
+    let count = |table: &str| -> Result<i64> {
+        Ok(snapshot.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?)
+    };
+    Ok(BackupManifest {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().timestamp(),
+        assets: count("assets")?,
+        albums: count("albums")?,
+        tags: count("tags")?,
+        persons: count("persons")?,
+        faces: count("face_embeddings")?,
+    })
+}
+
+/// Write the manifest, the database snapshot and every derived file (thumbnails, previews) as a tar.
+/// Originals are not included.
+pub fn write_archive<W: Write>(out: W, manifest: &BackupManifest, db_snapshot: &Path, derived_dir: &Path) -> Result<W> {
+    let mut builder = tar::Builder::new(out);
+    builder.mode(tar::HeaderMode::Deterministic);
+
+    let manifest_json = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created_at.max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_ENTRY, manifest_json.as_slice())?;
+
+    builder.append_path_with_name(db_snapshot, DATABASE_ENTRY)?;
+
+    if derived_dir.is_dir() {
+        for entry in walkdir::WalkDir::new(derived_dir).sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(derived_dir)?;
+            builder.append_path_with_name(entry.path(), Path::new(DERIVED_PREFIX).join(relative))?;
+        }
+    }
+
+    Ok(builder.into_inner()?)
+}
+
+/// Where a restored database waits until the next start
+pub fn pending_restore_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".restore");
+    db_path.with_file_name(name)
+}
+
+/// Only plain relative paths below `derived/` are unpacked
+fn derived_entry_path(entry_path: &Path) -> Option<PathBuf> {
+    let relative = entry_path.strip_prefix(DERIVED_PREFIX).ok()?;
+    let safe = relative.components().all(|c| matches!(c, Component::Normal(_)));
+    (safe && relative.components().next().is_some()).then(|| relative.to_path_buf())
+}
+
+/// Unpack a backup archive: derived files go straight into `derived_dir` (they are named by
+/// content hash, so merging is harmless) and the database is staged next to `db_path` to be
+/// swapped in by [`apply_pending_restore`] on the next start, while no connection holds it open.
+/// The inner error describes why the archive was rejected.
+pub fn restore_archive<R: Read>(input: R, db_path: &Path, derived_dir: &Path) -> Result<std::result::Result<BackupManifest, String>> {
+    let mut archive = tar::Archive::new(input);
+    let staged_db = pending_restore_path(db_path);
+    let partial_db = staged_db.with_extension("restore.partial");
+    let mut manifest: Option<BackupManifest> = None;
+    let mut has_db = false;
+
+    let entries = match archive.entries() {
+        Ok(entries) => entries,
+        Err(e) => return Ok(Err(format!("Not a tar archive: {}", e))),
+    };
+    for entry in entries {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => return Ok(Err(format!("Corrupt archive: {}", e))),
+        };
+        let entry_path = entry.path()?.into_owned();
+        if manifest.is_none() {
+            if entry_path != Path::new(MANIFEST_ENTRY) {
+                return Ok(Err("Archive does not start with a manifest; not a seen backup".to_string()));
+            }
+            let parsed: BackupManifest = match serde_json::from_reader(&mut entry) {
+                Ok(parsed) => parsed,
+                Err(e) => return Ok(Err(format!("Invalid manifest: {}", e))),
+            };
+            if parsed.format != BACKUP_FORMAT {
+                return Ok(Err(format!("Unknown archive format '{}'", parsed.format)));
+            }
+            if parsed.version > BACKUP_VERSION {
+                return Ok(Err(format!("Backup version {} is newer than this server supports ({})", parsed.version, BACKUP_VERSION)));
+            }
+            manifest = Some(parsed);
+            continue;
+        }
+
+        if entry_path == Path::new(DATABASE_ENTRY) {
+            let mut file = std::fs::File::create(&partial_db)?;
+            io::copy(&mut entry, &mut file)?;
+            file.sync_all()?;
+            has_db = true;
+        } else if let Some(relative) = derived_entry_path(&entry_path) {
+            if entry.header().entry_type().is_file() {
+                let target = derived_dir.join(relative);
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut file = std::fs::File::create(&target)?;
+                io::copy(&mut entry, &mut file)?;
+            }
+        } else {
+            tracing::warn!("Skipping unexpected backup entry {}", entry_path.display());
+        }
+    }
+
+    let Some(manifest) = manifest else {
+        return Ok(Err("Archive is empty".to_string()));
+    };
+    if !has_db {
+        let _ = std::fs::remove_file(&partial_db);
+        return Ok(Err("Archive does not contain a database".to_string()));
+    }
+    // Make sure the database opens before staging it
+    let check = rusqlite::Connection::open(&partial_db).and_then(|conn| {
+        conn.query_row("PRAGMA quick_check", [], |row| row.get::<_, String>(0))
+    });
+    match check {
+        Ok(result) if result == "ok" => {}
+        Ok(result) => {
+            let _ = std::fs::remove_file(&partial_db);
+            return Ok(Err(format!("Database in archive failed its integrity check: {}", result)));
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial_db);
+            return Ok(Err(format!("Database in archive cannot be opened: {}", e)));
+        }
+    }
+    std::fs::rename(&partial_db, &staged_db)?;
+    Ok(Ok(manifest))
+}
+
+/// Swap in a database staged by [`restore_archive`]; call before opening the pool.
+/// The replaced database is kept as `<db>.before-restore`.
+pub fn apply_pending_restore(db_path: &Path) -> Result<bool> {
+    let staged = pending_restore_path(db_path);
+    if !staged.exists() {
+        return Ok(false);
+    }
+    let mut previous = db_path.file_name().unwrap_or_default().to_os_string();
+    previous.push(".before-restore");
+    let previous = db_path.with_file_name(previous);
+    let had_db = db_path.exists();
+    if had_db {
+        std::fs::rename(db_path, &previous)?;
+    }
+    // Recent commits may still sit in the WAL, so it moves with the database it belongs to
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = |db: &Path| {
+            let mut name = db.file_name().unwrap_or_default().to_os_string();
+            name.push(suffix);
+            db.with_file_name(name)
+        };
+        let result = if had_db {
+            std::fs::rename(sidecar(db_path), sidecar(&previous))
+        } else {
+            std::fs::remove_file(sidecar(db_path))
+        };
+        match result {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    std::fs::rename(&staged, db_path)?;
+    Ok(true)
+}
+
+/// A fresh file under `dir` to spool an uploaded archive to, so concurrent uploads don't share one
+pub fn create_upload_spool(dir: &Path) -> Result<(PathBuf, std::fs::File)> {
+    loop {
+        let mut id = [0u8; 8];
+        getrandom::fill(&mut id).map_err(|e| anyhow::anyhow!("Failed to name upload file: {}", e))?;
+        let path = dir.join(format!("restore-upload-{}.tar", hex::encode(id)));
+        match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Blocking writer that hands each chunk to an async response body
+pub struct ChannelWriter {
+    tx: tokio::sync::mpsc::Sender<io::Result<bytes::Bytes>>,
+}
+
+impl ChannelWriter {
+    pub fn new(tx: tokio::sync::mpsc::Sender<io::Result<bytes::Bytes>>) -> Self {
+        Self { tx }
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx
+            .blocking_send(Ok(bytes::Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_backup_round_trip() {
+        let source = TempDir::new().unwrap();
+        let conn = crate::db::open_or_create(source.path().join("seen.db")).unwrap();
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags)
+             VALUES ('/p/a.jpg', '/p', 'a.jpg', 'jpg', 1, 0, 0, 'image/jpeg', 0)",
+            [],
+        ).unwrap();
+        let asset_id = conn.last_insert_rowid();
+        let album = crate::db::writer::create_album(&conn, "Trip", None, None).unwrap();
+        crate::db::writer::add_assets_to_album(&conn, album, &[asset_id]).unwrap();
+        let derived = source.path().join("derived");
+        std::fs::create_dir_all(derived.join("ab")).unwrap();
+        std::fs::write(derived.join("ab").join("abcd-256.webp"), b"thumb").unwrap();
+
+        let snapshot = source.path().join("snapshot.db");
+        let manifest = snapshot_database(&conn, &snapshot).unwrap();
+        assert_eq!((manifest.assets, manifest.albums), (1, 1));
+        let archive = write_archive(Vec::new(), &manifest, &snapshot, &derived).unwrap();
+
+        let target = TempDir::new().unwrap();
+        let db_path = target.path().join("seen.db");
+        crate::db::open_or_create(&db_path).unwrap();
+        let target_derived = target.path().join("derived");
+        let restored = restore_archive(archive.as_slice(), &db_path, &target_derived).unwrap().unwrap();
+        assert_eq!(restored, manifest);
+        assert_eq!(std::fs::read(target_derived.join("ab").join("abcd-256.webp")).unwrap(), b"thumb");
+
+        // As left by a crash: commits not yet checkpointed into the database
+        std::fs::write(target.path().join("seen.db-wal"), b"wal").unwrap();
+        assert!(apply_pending_restore(&db_path).unwrap());
+        assert!(!apply_pending_restore(&db_path).unwrap());
+        assert_eq!(std::fs::read(target.path().join("seen.db.before-restore-wal")).unwrap(), b"wal");
+        let conn = crate::db::open_or_create(&db_path).unwrap();
+        assert_eq!(crate::db::query::count_assets(&conn).unwrap(), 1);
+        assert!(target.path().join("seen.db.before-restore").exists());
+
+        // Anything that isn't a backup is rejected without staging a database
+        assert!(restore_archive(&b"not a tar"[..], &db_path, &target_derived).unwrap().is_err());
+        assert!(!pending_restore_path(&db_path).exists());
+    }
+}
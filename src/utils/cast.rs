@@ -0,0 +1,144 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a cast session's media URLs keep working unless FLASH_CAST_TTL_SECS says otherwise
+pub const DEFAULT_TTL_SECS: i64 = 4 * 3600;
+/// Oldest sessions are dropped once this many are open
+const MAX_SESSIONS: usize = 32;
+/// Assets one session may queue
+pub const MAX_QUEUE: usize = 500;
+
+/// A queue of assets handed to a Cast receiver. Receivers fetch media without
+/// credentials, so the session token in the URL is what grants access.
+#[derive(Clone, Debug)]
+pub struct CastSession {
+    pub token: String,
+    /// Queue order; only these assets are served under the token
+    pub asset_ids: Vec<i64>,
+    /// How long the sender shows each photo before advancing
+    pub slide_secs: u32,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+impl CastSession {
+    pub fn covers_asset(&self, asset_id: i64) -> bool {
+        self.asset_ids.contains(&asset_id)
+    }
+}
+
+/// Open cast sessions, kept in memory; a restart ends them
+pub struct CastStore {
+    ttl_secs: i64,
+    sessions: Mutex<HashMap<String, CastSession>>,
+}
+
+impl Default for CastStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CastStore {
+    pub fn new() -> Self {
+        let ttl_secs = std::env::var("FLASH_CAST_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v: &i64| *v > 0)
+            .unwrap_or(DEFAULT_TTL_SECS);
+        Self { ttl_secs, sessions: Mutex::new(HashMap::new()) }
+    }
+
+    /// Open a session under a fresh random token
+    pub fn insert(&self, asset_ids: Vec<i64>, slide_secs: u32, now: i64) -> Result<CastSession> {
+        let mut raw = [0u8; 32];
+        getrandom::fill(&mut raw).map_err(|e| anyhow::anyhow!("Failed to generate token: {}", e))?;
+        let token = hex::encode(raw);
+        let session = CastSession {
+            token: token.clone(),
+            asset_ids,
+            slide_secs,
+            created_at: now,
+            expires_at: now + self.ttl_secs,
+        };
+        let mut sessions = self.sessions.lock();
+        while sessions.len() >= MAX_SESSIONS {
+            let Some(oldest) = sessions.values().min_by_key(|s| s.created_at).map(|s| s.token.clone()) else { break };
+            sessions.remove(&oldest);
+        }
+        sessions.insert(token, session.clone());
+        Ok(session)
+    }
+
+    /// Look up a session; expired ones are invisible
+    pub fn get(&self, token: &str, now: i64) -> Option<CastSession> {
+        self.sessions.lock().get(token).filter(|s| s.expires_at > now).cloned()
+    }
+
+    pub fn remove(&self, token: &str) -> bool {
+        self.sessions.lock().remove(token).is_some()
+    }
+
+    /// Drop expired sessions; returns how many were removed
+    pub fn purge_expired(&self, now: i64) -> usize {
+        let mut sessions = self.sessions.lock();
+        let before = sessions.len();
+        sessions.retain(|_, s| s.expires_at > now);
+        before - sessions.len()
+    }
+}
+
+/// Cast metadata types (`metadataType` in the Cast media protocol)
+pub fn metadata_type(mime: &str) -> u8 {
+    if mime.starts_with("image/") {
+        4 // PHOTO
+    } else {
+        0 // GENERIC
+    }
+}
+
+pub fn start_purger(store: Arc<CastStore>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let n = store.purge_expired(chrono::Utc::now().timestamp());
+            if n > 0 {
+                tracing::debug!("Purged {} expired cast sessions", n);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cast_store_expiry_and_eviction() {
+        let store = CastStore::new();
+        let session = store.insert(vec![3, 1, 2], 10, 1000).unwrap();
+        assert_eq!(session.token.len(), 64);
+        assert_eq!(session.expires_at, 1000 + store.ttl_secs);
+        assert!(store.get(&session.token, 1001).unwrap().covers_asset(1));
+        assert!(!session.covers_asset(4));
+        assert!(store.get(&session.token, session.expires_at).is_none());
+        assert!(store.get("unknown", 1001).is_none());
+
+        assert_eq!(store.purge_expired(session.expires_at - 1), 0);
+        assert_eq!(store.purge_expired(session.expires_at), 1);
+
+        // The oldest session makes room for a new one
+        let first = store.insert(vec![1], 10, 2000).unwrap();
+        for i in 1..MAX_SESSIONS as i64 {
+            store.insert(vec![1], 10, 2000 + i).unwrap();
+        }
+        assert!(store.get(&first.token, 2100).is_some());
+        store.insert(vec![1], 10, 3000).unwrap();
+        assert!(store.get(&first.token, 2100).is_none());
+        assert!(!store.remove(&first.token));
+    }
+}
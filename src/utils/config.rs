@@ -1,5 +1,86 @@
 use std::env;
 use std::path::PathBuf;
+use crate::pipeline::ingest::IngestFilter;
+use crate::pipeline::throttle::LowImpact;
+
+/// Sizing preset chosen with SEEN_PROFILE; explicit settings still take precedence
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Profile {
+    #[default]
+    Default,
+    /// For 512MB–1GB containers: short queues, a small read pool and cache, one worker per stage
+    LowMemory,
+}
+
+/// Bounded channel sizes between the pipeline stages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueCapacity {
+    pub discover: usize,
+    pub hash: usize,
+    pub meta: usize,
+    pub db_write: usize,
+    pub thumb: usize,
+    pub transcode: usize,
+    pub face: usize,
+}
+
+impl Profile {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "" | "default" => Some(Self::Default),
+            "low-memory" | "lowmem" => Some(Self::LowMemory),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::LowMemory => "low-memory",
+        }
+    }
+
+    pub fn queue_capacity(self) -> QueueCapacity {
+        match self {
+            Self::Default => QueueCapacity {
+                discover: 100_000,
+                hash: 4_096,
+                meta: 4_096,
+                db_write: 65_536,
+                thumb: 16_384,
+                transcode: 1_024,
+                face: 4_096,
+            },
+            // Enough to keep each stage busy; a full queue just makes the scanner wait
+            Self::LowMemory => QueueCapacity {
+                discover: 2_048,
+                hash: 256,
+                meta: 256,
+                db_write: 1_024,
+                thumb: 512,
+                transcode: 64,
+                face: 256,
+            },
+        }
+    }
+
+    /// Read pool size unless one was saved through PUT /settings/db-pool
+    pub fn pool_size(self) -> u32 {
+        match self {
+            Self::Default => 10,
+            Self::LowMemory => 3,
+        }
+    }
+
+    /// Caps the auto-tuned SQLite cache and mmap; saved overrides are applied afterwards
+    pub fn limit_sqlite(self, mut tuning: crate::db::tuning::SqliteTuning) -> crate::db::tuning::SqliteTuning {
+        if self == Self::LowMemory {
+            tuning.cache_kib = tuning.cache_kib.min(2 * 1024);
+            tuning.mmap_bytes = tuning.mmap_bytes.min(64 * 1024 * 1024);
+        }
+        tuning
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -8,10 +89,21 @@ pub struct Config {
     pub data: PathBuf,
     pub port: u16,
     pub hash_threads: usize,
+    /// Files bigger than this many MB are hashed from their start, end and size only; 0 hashes everything in full
+    pub sampled_hash_min_mb: u64,
     pub meta_threads: usize,
     pub thumb_threads: usize,
+    /// libvips threads per image; defaults to the cores shared out between the thumbnail workers
+    pub vips_concurrency: usize,
     pub thumb_size: i32,
     pub preview_size: i32,
+    /// Also render a short looping animated WebP for video hover previews
+    pub video_anim_preview: bool,
+    /// Default ingest filters; values saved through the settings API take precedence
+    pub ingest: IngestFilter,
+    /// Default low-impact mode; values saved through the settings API take precedence
+    pub low_impact: LowImpact,
+    pub profile: Profile,
 }
 
 impl Config {
@@ -20,25 +112,77 @@ impl Config {
         let root_host = env::var("FLASH_ROOT_HOST").ok();
         let data = env::var("FLASH_DATA").unwrap_or_else(|_| "/flash-data".to_string());
         let port = env::var("FLASH_PORT").ok().and_then(|v| v.parse().ok()).unwrap_or(9161);
-        let hash_threads = env::var("FLASH_HASH_THREADS").ok().and_then(|v| v.parse().ok()).unwrap_or(2);
-        let meta_threads = env::var("FLASH_META_THREADS").ok().and_then(|v| v.parse().ok()).unwrap_or(2);
-        let thumb_threads = env::var("FLASH_THUMB_THREADS").ok().and_then(|v| v.parse().ok()).unwrap_or(1);
+        let profile = env::var("SEEN_PROFILE").ok().and_then(|v| Profile::parse(&v)).unwrap_or_default();
+        // Small ARM boards and the low-memory profile share the lighter worker defaults
+        let lean = profile == Profile::LowMemory || crate::utils::host::host().constrained;
+        let hash_threads = env::var("FLASH_HASH_THREADS").ok().and_then(|v| v.parse().ok()).unwrap_or_else(|| default_io_threads(lean));
+        let sampled_hash_min_mb = env::var("FLASH_SAMPLED_HASH_MIN_MB").ok().and_then(|v| v.parse().ok()).unwrap_or(16 * 1024);
+        let meta_threads = env::var("FLASH_META_THREADS").ok().and_then(|v| v.parse().ok()).unwrap_or_else(|| default_io_threads(lean));
+        let thumb_threads = env::var("FLASH_THUMB_THREADS").ok().and_then(|v| v.parse().ok()).unwrap_or_else(|| default_thumb_threads(lean));
+        let vips_concurrency = env::var("FLASH_VIPS_CONCURRENCY").ok().and_then(|v| v.parse().ok()).filter(|n| *n > 0)
+            .unwrap_or_else(|| default_vips_concurrency(lean, thumb_threads));
         let thumb_size = env::var("FLASH_THUMB_SIZE").ok().and_then(|v| v.parse().ok()).unwrap_or(256);
-        let preview_size = env::var("FLASH_PREVIEW_SIZE").ok().and_then(|v| v.parse().ok()).unwrap_or(1600);
+        let preview_size = env::var("FLASH_PREVIEW_SIZE").ok().and_then(|v| v.parse().ok()).unwrap_or_else(|| default_preview_size(lean));
+        let video_anim_preview = env::var("FLASH_VIDEO_ANIM_PREVIEW").map(|v| v == "1" || v.eq_ignore_ascii_case("true")).unwrap_or(false);
+        let ingest = IngestFilter {
+            min_bytes: env::var("FLASH_INGEST_MIN_BYTES").ok().and_then(|v| v.parse().ok()).unwrap_or(0),
+            min_width: env::var("FLASH_INGEST_MIN_WIDTH").ok().and_then(|v| v.parse().ok()).unwrap_or(0),
+            min_height: env::var("FLASH_INGEST_MIN_HEIGHT").ok().and_then(|v| v.parse().ok()).unwrap_or(0),
+            extensions: env::var("FLASH_INGEST_EXTENSIONS").map(|v| IngestFilter::parse_extensions(&v)).unwrap_or_default(),
+        }.normalized();
+        let low_impact_defaults = LowImpact::default();
+        let low_impact = LowImpact {
+            enabled: env::var("FLASH_LOW_IMPACT").map(|v| v == "1" || v.eq_ignore_ascii_case("true")).unwrap_or(false),
+            max_workers: env::var("FLASH_LOW_IMPACT_WORKERS").ok().and_then(|v| v.parse().ok()).unwrap_or(low_impact_defaults.max_workers),
+            file_delay_ms: env::var("FLASH_LOW_IMPACT_DELAY_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(low_impact_defaults.file_delay_ms),
+            max_read_mb_per_sec: env::var("FLASH_LOW_IMPACT_MAX_MBPS").ok().and_then(|v| v.parse().ok()).unwrap_or(low_impact_defaults.max_read_mb_per_sec),
+        };
         Self {
             root: PathBuf::from(root),
             root_host,
             data: PathBuf::from(data),
             port,
             hash_threads,
+            sampled_hash_min_mb,
             meta_threads,
             thumb_threads,
+            vips_concurrency,
             thumb_size,
             preview_size,
+            video_anim_preview,
+            ingest,
+            low_impact,
+            profile,
         }
     }
 }
 
+fn cores() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+/// Hash and metadata workers; one each on lean hosts, where two saturate the disk and CPU
+fn default_io_threads(lean: bool) -> usize {
+    if lean { 1 } else { 2 }
+}
+
+/// Decoding is mostly single-threaded inside libvips, so several images at once make better use of
+/// many cores than one image with many threads. Half the cores leaves room for hashing and metadata.
+fn default_thumb_threads(lean: bool) -> usize {
+    if lean { 1 } else { (cores() / 2).clamp(1, 8) }
+}
+
+/// The cores shared out between the thumbnail workers, or half of them on lean hosts
+fn default_vips_concurrency(lean: bool, thumb_threads: usize) -> usize {
+    let cores = if lean { cores() / 2 } else { cores() };
+    (cores / thumb_threads.max(1)).max(1)
+}
+
+/// Smaller previews take about a third less time and memory to decode, resize and encode
+fn default_preview_size(lean: bool) -> i32 {
+    if lean { 1280 } else { 1600 }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,21 +215,40 @@ mod tests {
             "FLASH_DATA",
             "FLASH_PORT",
             "FLASH_HASH_THREADS",
+            "FLASH_SAMPLED_HASH_MIN_MB",
             "FLASH_META_THREADS",
             "FLASH_THUMB_THREADS",
+            "FLASH_VIPS_CONCURRENCY",
             "FLASH_THUMB_SIZE",
             "FLASH_PREVIEW_SIZE",
+            "FLASH_VIDEO_ANIM_PREVIEW",
+            "FLASH_INGEST_MIN_BYTES",
+            "FLASH_INGEST_MIN_WIDTH",
+            "FLASH_INGEST_MIN_HEIGHT",
+            "FLASH_INGEST_EXTENSIONS",
+            "FLASH_LOW_IMPACT",
+            "FLASH_LOW_IMPACT_WORKERS",
+            "FLASH_LOW_IMPACT_DELAY_MS",
+            "FLASH_LOW_IMPACT_MAX_MBPS",
+            "SEEN_PROFILE",
         ]);
 
         let config = Config::from_env();
+        let lean = crate::utils::host::host().constrained;
+        assert_eq!(config.profile, Profile::Default);
         assert_eq!(config.root, PathBuf::from("/photos"));
         assert_eq!(config.data, PathBuf::from("/flash-data"));
         assert_eq!(config.port, 9161);
-        assert_eq!(config.hash_threads, 2);
-        assert_eq!(config.meta_threads, 2);
-        assert_eq!(config.thumb_threads, 1);
+        assert_eq!(config.hash_threads, default_io_threads(lean));
+        assert_eq!(config.sampled_hash_min_mb, 16384);
+        assert_eq!(config.meta_threads, default_io_threads(lean));
+        assert_eq!(config.thumb_threads, default_thumb_threads(lean));
+        assert_eq!(config.vips_concurrency, default_vips_concurrency(lean, config.thumb_threads));
         assert_eq!(config.thumb_size, 256);
-        assert_eq!(config.preview_size, 1600);
+        assert_eq!(config.preview_size, default_preview_size(lean));
+        assert!(!config.video_anim_preview);
+        assert_eq!(config.ingest, IngestFilter::default());
+        assert_eq!(config.low_impact, LowImpact::default());
 
         restore_vars(saved);
     }
@@ -98,34 +261,72 @@ mod tests {
             "FLASH_DATA",
             "FLASH_PORT",
             "FLASH_HASH_THREADS",
+            "FLASH_SAMPLED_HASH_MIN_MB",
             "FLASH_META_THREADS",
             "FLASH_THUMB_THREADS",
+            "FLASH_VIPS_CONCURRENCY",
             "FLASH_THUMB_SIZE",
             "FLASH_PREVIEW_SIZE",
+            "FLASH_INGEST_MIN_BYTES",
+            "FLASH_INGEST_EXTENSIONS",
+            "FLASH_LOW_IMPACT",
+            "FLASH_LOW_IMPACT_WORKERS",
+            "FLASH_LOW_IMPACT_MAX_MBPS",
+            "SEEN_PROFILE",
         ]);
 
         env::set_var("FLASH_ROOT", "/custom/photos");
         env::set_var("FLASH_DATA", "/custom/data");
         env::set_var("FLASH_PORT", "8080");
         env::set_var("FLASH_HASH_THREADS", "4");
+        env::set_var("FLASH_SAMPLED_HASH_MIN_MB", "0");
         env::set_var("FLASH_META_THREADS", "3");
         env::set_var("FLASH_THUMB_THREADS", "2");
         env::set_var("FLASH_THUMB_SIZE", "512");
         env::set_var("FLASH_PREVIEW_SIZE", "2048");
+        env::set_var("FLASH_INGEST_MIN_BYTES", "20000");
+        env::set_var("FLASH_INGEST_EXTENSIONS", "JPG,heic");
+        env::set_var("FLASH_LOW_IMPACT", "true");
+        env::set_var("FLASH_LOW_IMPACT_MAX_MBPS", "5");
         
         let config = Config::from_env();
         assert_eq!(config.root, PathBuf::from("/custom/photos"));
         assert_eq!(config.data, PathBuf::from("/custom/data"));
         assert_eq!(config.port, 8080);
         assert_eq!(config.hash_threads, 4);
+        assert_eq!(config.sampled_hash_min_mb, 0);
         assert_eq!(config.meta_threads, 3);
         assert_eq!(config.thumb_threads, 2);
+        assert_eq!(config.vips_concurrency, default_vips_concurrency(crate::utils::host::host().constrained, 2));
         assert_eq!(config.thumb_size, 512);
         assert_eq!(config.preview_size, 2048);
+        assert_eq!(config.ingest.min_bytes, 20000);
+        assert_eq!(config.ingest.extensions, vec!["heic", "jpg"]);
+        assert!(config.low_impact.enabled);
+        assert_eq!(config.low_impact.max_read_mb_per_sec, 5);
+        assert_eq!(config.low_impact.max_workers, 1);
 
         restore_vars(saved);
     }
 
+    #[test]
+    fn test_profile() {
+        assert_eq!(Profile::parse("low-memory"), Some(Profile::LowMemory));
+        assert_eq!(Profile::parse(" LOW_MEMORY "), Some(Profile::LowMemory));
+        assert_eq!(Profile::parse(""), Some(Profile::Default));
+        assert_eq!(Profile::parse("tiny"), None);
+
+        let (full, low) = (Profile::Default.queue_capacity(), Profile::LowMemory.queue_capacity());
+        assert_eq!(full.discover, 100_000);
+        assert!(low.discover < full.discover && low.db_write < full.db_write && low.thumb < full.thumb);
+        assert_eq!((Profile::Default.pool_size(), Profile::LowMemory.pool_size()), (10, 3));
+
+        let tuning = crate::db::tuning::SqliteTuning::auto(Some(8 << 30), 4 << 30);
+        assert_eq!(Profile::Default.limit_sqlite(tuning), tuning);
+        let limited = Profile::LowMemory.limit_sqlite(tuning);
+        assert_eq!((limited.cache_kib, limited.mmap_bytes), (2 * 1024, 64 * 1024 * 1024));
+    }
+
     #[test]
     fn test_config_root_host() {
         let saved = clear_vars(&["FLASH_ROOT_HOST"]);
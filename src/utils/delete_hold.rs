@@ -0,0 +1,171 @@
+use anyhow::Result;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long permanently deleted originals stay in the holding area (FLASH_DELETE_HOLD_SECS).
+/// 0 disables the holding area and deletes immediately.
+pub fn default_hold_secs() -> i64 {
+    std::env::var("FLASH_DELETE_HOLD_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &i64| *v >= 0)
+        .unwrap_or(0)
+}
+
+pub fn hold_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("pending-delete")
+}
+
+/// Rename, falling back to copy + remove when source and target are on different volumes
+fn move_file(src: &Path, dst: &Path) -> io::Result<()> {
+    match std::fs::rename(src, dst) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound || e.kind() == io::ErrorKind::PermissionDenied => Err(e),
+        Err(_) => {
+            std::fs::copy(src, dst)?;
+            if let Err(e) = std::fs::remove_file(src) {
+                let _ = std::fs::remove_file(dst);
+                return Err(e);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Move an original into the holding area; returns the held path
+pub fn move_into_hold(src: &Path, hold_dir: &Path, asset_id: i64) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(hold_dir)?;
+    let filename = src.file_name().and_then(|s| s.to_str()).unwrap_or("asset");
+    let held = hold_dir.join(format!("{}-{}-{}", asset_id, chrono::Utc::now().timestamp(), filename));
+    move_file(src, &held)?;
+    Ok(held)
+}
+
+/// Move a held original back to where it came from. Refuses to overwrite.
+pub fn move_out_of_hold(held: &Path, original: &Path) -> io::Result<()> {
+    if original.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", original.display())));
+    }
+    if let Some(parent) = original.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    move_file(held, original)
+}
+
+/// Remove the thumbnail and preview generated for an asset
+pub fn remove_derived_files(sha256: Option<&[u8]>, derived_dir: &Path) {
+    if let Some(sha) = sha256 {
+        if sha.is_empty() {
+            return;
+        }
+        let sha_hex = hex::encode(sha);
+        if sha_hex.len() < 2 {
+            return;
+        }
+        let sub = &sha_hex[0..2];
+        let thumb_path = derived_dir.join(sub).join(format!("{}-256.webp", sha_hex));
+        let preview_path = derived_dir.join(sub).join(format!("{}-1600.webp", sha_hex));
+        let anim_path = derived_dir.join(sub).join(format!("{}-anim.webp", sha_hex));
+        let _ = std::fs::remove_file(thumb_path);
+        let _ = std::fs::remove_file(preview_path);
+        let _ = std::fs::remove_file(anim_path);
+    }
+}
+
+/// Delete every held original whose undo window has passed
+pub fn purge_expired(conn: &rusqlite::Connection, derived_dir: &Path, now: i64) -> Result<usize> {
+    let due = crate::db::query::due_pending_deletes(conn, now)?;
+    let mut purged = 0;
+    for (id, held_path, sha256) in due {
+        match std::fs::remove_file(&held_path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                tracing::warn!("Failed to purge held file {}: {}", held_path, e);
+                continue;
+            }
+        }
+        remove_derived_files(sha256.as_deref(), derived_dir);
+        crate::db::writer::remove_pending_delete(conn, id)?;
+        purged += 1;
+    }
+    Ok(purged)
+}
+
+/// Periodically purge expired held deletes
+pub fn start_purger(db_path: PathBuf, derived_dir: PathBuf) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let dbp = db_path.clone();
+            let derived = derived_dir.clone();
+            let result = tokio::task::spawn_blocking(move || -> Result<usize> {
+                let conn = rusqlite::Connection::open(&dbp)?;
+                purge_expired(&conn, &derived, chrono::Utc::now().timestamp())
+            }).await;
+            match result {
+                Ok(Ok(n)) if n > 0 => tracing::info!("Purged {} held deletes", n),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("Held delete purge failed: {}", e),
+                Err(e) => tracing::warn!("Held delete purge task failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_hold_undo_and_purge() {
+        let tmp = TempDir::new().unwrap();
+        let conn = crate::db::open_or_create(tmp.path().join("test.db")).unwrap();
+        let original = tmp.path().join("photos").join("a.jpg");
+        std::fs::create_dir_all(original.parent().unwrap()).unwrap();
+        std::fs::write(&original, b"jpeg").unwrap();
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, rating)
+             VALUES (?1, ?2, 'a.jpg', 'jpg', 4, 0, 0, 'image/jpeg', 0, 4)",
+            rusqlite::params![original.to_string_lossy(), original.parent().unwrap().to_string_lossy()],
+        ).unwrap();
+        let asset_id = conn.last_insert_rowid();
+        let album = crate::db::writer::create_album(&conn, "Trip", None, None).unwrap();
+        crate::db::writer::add_assets_to_album(&conn, album, &[asset_id]).unwrap();
+        let alice = crate::db::writer::create_user(&conn, "alice", "x", false).unwrap().unwrap();
+        crate::db::writer::set_user_favorite(&conn, alice, asset_id, true).unwrap();
+        crate::db::writer::insert_share(&conn, b"token", None, Some(asset_id), None, None, Some(alice)).unwrap();
+
+        let snapshot = crate::db::writer::snapshot_asset(&conn, asset_id).unwrap().unwrap();
+        let held = move_into_hold(&original, &hold_dir(tmp.path()), asset_id).unwrap();
+        assert!(!original.exists() && held.exists());
+        crate::db::query::delete_asset_by_id(&conn, asset_id).unwrap();
+
+        // Undo restores the file and the row under the same id
+        move_out_of_hold(&held, &original).unwrap();
+        let restored = crate::db::writer::restore_asset_snapshot(&conn, &snapshot).unwrap();
+        assert_eq!(restored, asset_id);
+        let asset = crate::db::query::get_asset_by_id(&conn, asset_id).unwrap().unwrap();
+        assert_eq!(asset.rating, 4);
+        let mut assets = vec![asset];
+        crate::db::query::mark_user_favorites(&conn, Some(alice), &mut assets).unwrap();
+        assert!(assets[0].favorite);
+        let shared: Vec<u8> = conn.query_row("SELECT token_hash FROM shares WHERE asset_id = ?1", [asset_id], |r| r.get(0)).unwrap();
+        assert_eq!(shared, b"token");
+        assert_eq!(crate::db::query::get_albums_for_asset(&conn, asset_id, crate::db::query::AccessScope::All).unwrap(), vec![album]);
+        assert!(original.exists());
+
+        // Expired holds are removed from disk and from the table
+        let held = move_into_hold(&original, &hold_dir(tmp.path()), asset_id).unwrap();
+        let id = crate::db::writer::insert_pending_delete(
+            &conn, asset_id, &original.to_string_lossy(), &held.to_string_lossy(), None, &snapshot, 100,
+        ).unwrap();
+        assert_eq!(purge_expired(&conn, tmp.path(), 99).unwrap(), 0);
+        assert_eq!(purge_expired(&conn, tmp.path(), 100).unwrap(), 1);
+        assert!(!held.exists());
+        assert!(crate::db::query::get_pending_delete(&conn, id, crate::db::query::AccessScope::All).unwrap().is_none());
+    }
+}
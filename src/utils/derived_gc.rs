@@ -0,0 +1,205 @@
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use crate::AppState;
+
+/// How often the collector runs on its own (FLASH_DERIVED_GC_HOURS); 0 turns the periodic run off
+pub fn default_interval_hours() -> u64 {
+    std::env::var("FLASH_DERIVED_GC_HOURS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(24)
+}
+
+/// Set while a collection runs, so the periodic run and POST /maintenance/gc never overlap
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// What one collection removed from the derived directory
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GcReport {
+    pub files_scanned: u64,
+    /// Thumbnails, previews and transcodes of assets that are gone
+    pub files_removed: u64,
+    /// Highlight videos of deleted albums
+    pub highlights_removed: u64,
+    pub bytes_freed: u64,
+    pub started_at: i64,
+    pub finished_at: i64,
+}
+
+/// Content hashes and albums that derived files may still belong to
+pub struct LiveSet {
+    hashes: HashSet<String>,
+    albums: HashSet<i64>,
+}
+
+impl LiveSet {
+    /// Assets plus held deletes, whose derived files come back on undo
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let mut stmt = conn.prepare(
+            "SELECT sha256 FROM assets WHERE sha256 IS NOT NULL
+             UNION SELECT sha256 FROM pending_deletes WHERE sha256 IS NOT NULL",
+        )?;
+        let hashes = stmt
+            .query_map([], |row| row.get::<_, Vec<u8>>(0))?
+            .map(|sha| sha.map(hex::encode))
+            .collect::<rusqlite::Result<HashSet<_>>>()?;
+        let mut stmt = conn.prepare("SELECT id FROM albums")?;
+        let albums = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<HashSet<i64>>>()?;
+        Ok(Self { hashes, albums })
+    }
+
+    /// Whether a file with this name is still needed; names this collector doesn't recognise are kept
+    fn keeps(&self, name: &str, in_highlights: bool) -> bool {
+        if in_highlights {
+            return name
+                .strip_prefix("album-")
+                .and_then(|rest| rest.strip_suffix(".mp4"))
+                .and_then(|id| id.parse::<i64>().ok())
+                .is_none_or(|id| self.albums.contains(&id));
+        }
+        match name.split_once('-') {
+            Some((sha, _)) if sha.len() == 64 && sha.bytes().all(|b| b.is_ascii_hexdigit()) => self.hashes.contains(&sha.to_ascii_lowercase()),
+            _ => true,
+        }
+    }
+}
+
+/// Remove derived files nothing refers to any more. Files written after `cutoff` are left alone,
+/// since they may belong to an asset committed after `live` was loaded; so are `*.partial.*` files,
+/// which startup recovery handles.
+pub fn sweep(derived: &Path, live: &LiveSet, cutoff: SystemTime) -> GcReport {
+    let mut report = GcReport { started_at: chrono::Utc::now().timestamp(), ..Default::default() };
+    let root = super::path::long_path(derived);
+    let highlights = root.join("highlights");
+    for entry in walkdir::WalkDir::new(&root).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        report.files_scanned += 1;
+        let name = entry.file_name().to_string_lossy();
+        let in_highlights = entry.path().parent() == Some(highlights.as_path());
+        if name.contains(".partial.") || live.keeps(&name, in_highlights) {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.modified().map_or(true, |m| m >= cutoff) {
+            continue;
+        }
+        match std::fs::remove_file(entry.path()) {
+            Ok(()) => {
+                if in_highlights {
+                    report.highlights_removed += 1;
+                } else {
+                    report.files_removed += 1;
+                }
+                report.bytes_freed += meta.len();
+            }
+            Err(e) => tracing::warn!("derived gc: could not remove {:?}: {}", entry.path(), e),
+        }
+    }
+    report.finished_at = chrono::Utc::now().timestamp();
+    report
+}
+
+/// Run one collection; None if another one is already running
+pub fn run(state: &AppState) -> Result<Option<GcReport>> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Ok(None);
+    }
+    let result = (|| {
+        let cutoff = SystemTime::now();
+        let live = {
+            let conn = state.pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            LiveSet::load(&conn)?
+        };
+        let report = sweep(&state.paths.derived, &live, cutoff);
+        tracing::info!(
+            "derived gc: removed {} files and {} highlight videos of {} scanned ({} bytes)",
+            report.files_removed, report.highlights_removed, report.files_scanned, report.bytes_freed
+        );
+        *state.derived_gc.lock() = Some(report.clone());
+        Ok(Some(report))
+    })();
+    RUNNING.store(false, Ordering::SeqCst);
+    result
+}
+
+/// Collect every `hours` hours, starting one interval after boot
+pub fn start(state: Arc<AppState>, hours: u64) {
+    if hours == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let period = Duration::from_secs(hours * 3600);
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            let state = state.clone();
+            match tokio::task::spawn_blocking(move || run(&state)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("derived gc failed: {}", e),
+                Err(e) => tracing::warn!("derived gc task failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_derived_gc() {
+        let tmp = TempDir::new().unwrap();
+        let conn = crate::db::open_or_create(tmp.path().join("test.db")).unwrap();
+        let live_sha = [0xabu8; 32];
+        let held_sha = [0xacu8; 32];
+        conn.execute(
+            "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, sha256)
+             VALUES ('/p/a.jpg', '/p', 'a.jpg', 'jpg', 1, 0, 0, 'image/jpeg', 0, ?1)",
+            [&live_sha[..]],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO pending_deletes (asset_id, original_path, held_path, sha256, snapshot_json, delete_after, created_at)
+             VALUES (2, '/p/b.jpg', '/hold/b.jpg', ?1, '{}', 0, 0)",
+            [&held_sha[..]],
+        ).unwrap();
+        conn.execute("INSERT INTO albums (id, name, created_at, updated_at) VALUES (1, 'Trip', 0, 0)", []).unwrap();
+
+        let derived = tmp.path().join("derived");
+        let (live, held, gone) = (hex::encode(live_sha), hex::encode(held_sha), hex::encode([0xadu8; 32]));
+        std::fs::create_dir_all(derived.join("ab")).unwrap();
+        std::fs::create_dir_all(derived.join("ac")).unwrap();
+        std::fs::create_dir_all(derived.join("ad")).unwrap();
+        std::fs::create_dir_all(derived.join("highlights")).unwrap();
+        std::fs::write(derived.join("ab").join(format!("{}-256.webp", live)), b"keep").unwrap();
+        std::fs::write(derived.join("ac").join(format!("{}-256.webp", held)), b"keep").unwrap();
+        std::fs::write(derived.join("ad").join(format!("{}-256.webp", gone)), b"gone").unwrap();
+        std::fs::write(derived.join("ad").join(format!("{}-transcoded.mp4", gone)), b"gone!").unwrap();
+        std::fs::write(derived.join("ad").join(format!("{}.partial.mp4", gone)), b"busy").unwrap();
+        std::fs::write(derived.join("ad").join("notes.txt"), b"mine").unwrap();
+        std::fs::write(derived.join("highlights").join("album-1.mp4"), b"keep").unwrap();
+        std::fs::write(derived.join("highlights").join("album-2.mp4"), b"gone").unwrap();
+
+        let set = LiveSet::load(&conn).unwrap();
+        // Anything written since the cutoff may belong to an asset committed meanwhile
+        let early = sweep(&derived, &set, SystemTime::UNIX_EPOCH);
+        assert_eq!((early.files_scanned, early.files_removed + early.highlights_removed), (8, 0));
+        let report = sweep(&derived, &set, SystemTime::now() + Duration::from_secs(5));
+        assert_eq!((report.files_removed, report.highlights_removed, report.bytes_freed), (2, 1, 13));
+        assert!(derived.join("ab").join(format!("{}-256.webp", live)).exists());
+        assert!(derived.join("ac").join(format!("{}-256.webp", held)).exists());
+        assert!(derived.join("ad").join("notes.txt").exists());
+        assert!(derived.join("highlights").join("album-1.mp4").exists());
+        assert!(!derived.join("ad").join(format!("{}-transcoded.mp4", gone)).exists());
+    }
+}
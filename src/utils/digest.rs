@@ -0,0 +1,258 @@
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate, Timelike};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::{AppState, db};
+use crate::db::query::AccessScope;
+use crate::pipeline::memories::{self, Memory};
+use crate::utils::scheduler::Schedule;
+use crate::utils::smtp::{self, Email, InlineImage, SmtpSettings};
+
+/// Most memories a digest may show
+pub const MAX_ITEMS: usize = 20;
+/// Days ahead of the send date a digest covers
+const DIGEST_DAYS: u64 = 7;
+/// Long edge of the thumbnails attached to the mail
+const IMAGE_EDGE: u32 = 256;
+
+/// Weekly email of upcoming memories
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestSettings {
+    pub enabled: bool,
+    /// Cron expression in server local time (default: Mondays at 08:00)
+    pub schedule: String,
+    pub recipients: Vec<String>,
+    /// Address the app is reached at, for the links in the mail
+    pub base_url: String,
+    pub max_items: usize,
+    pub smtp: SmtpSettings,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            schedule: "0 8 * * 1".to_string(),
+            recipients: Vec::new(),
+            base_url: String::new(),
+            max_items: 6,
+            smtp: SmtpSettings::default(),
+        }
+    }
+}
+
+impl DigestSettings {
+    /// Settings good enough to send with; turned off they only need to be well formed
+    pub fn validate(&self) -> Result<(), String> {
+        Schedule::parse(&self.schedule).map_err(|e| format!("Invalid schedule: {}", e))?;
+        if !(1..=MAX_ITEMS).contains(&self.max_items) {
+            return Err(format!("max_items must be between 1 and {}", MAX_ITEMS));
+        }
+        if let Some(bad) = self.recipients.iter().find(|r| !smtp::valid_address(r)) {
+            return Err(format!("Invalid recipient '{}'", bad));
+        }
+        if !self.smtp.from.is_empty() && !smtp::valid_address(&self.smtp.from) {
+            return Err(format!("Invalid sender '{}'", self.smtp.from));
+        }
+        if !self.base_url.is_empty() && !self.base_url.starts_with("http://") && !self.base_url.starts_with("https://") {
+            return Err("base_url must start with http:// or https://".to_string());
+        }
+        if self.enabled {
+            if self.smtp.host.trim().is_empty() {
+                return Err("smtp.host is required".to_string());
+            }
+            if self.smtp.from.is_empty() {
+                return Err("smtp.from is required".to_string());
+            }
+            if self.recipients.is_empty() {
+                return Err("At least one recipient is required".to_string());
+            }
+            if self.base_url.is_empty() {
+                return Err("base_url is required".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Subject, HTML and plain text of a digest; memory `i` shows the inline image `memory-{i}@seen`
+pub fn render(entries: &[Memory], base_url: &str) -> (String, String, String) {
+    let base_url = base_url.trim_end_matches('/');
+    let subject = match entries.len() {
+        1 => "1 memory this week".to_string(),
+        n => format!("{} memories this week", n),
+    };
+    let mut html = String::from(
+        "<!DOCTYPE html><html><body style=\"font-family:sans-serif;color:#222;max-width:600px;margin:auto\">\n",
+    );
+    html.push_str(&format!("<h2>{}</h2>\n", escape_html(&subject)));
+    let mut text = format!("{}\n\n", subject);
+    for (i, m) in entries.iter().enumerate() {
+        let link = format!("{}/asset/{}", base_url, m.asset_id);
+        let date = m.date.format("%A, %B %-d").to_string();
+        html.push_str(&format!(
+            "<p><a href=\"{link}\"><img src=\"cid:memory-{i}@seen\" alt=\"\" style=\"max-width:256px;border-radius:6px\"></a><br>\
+             <b>{summary}</b><br><small>{date}</small><br>{description}<br><a href=\"{link}\">Open</a></p>\n",
+            link = escape_html(&link),
+            i = i,
+            summary = escape_html(&m.summary),
+            date = escape_html(&date),
+            description = escape_html(&m.description),
+        ));
+        text.push_str(&format!("{} ({})\n{}\n{}\n\n", m.summary, date, m.description, link));
+    }
+    html.push_str("</body></html>\n");
+    (subject, html, text)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Memories for the `DIGEST_DAYS` from `today`, one per asset, soonest first
+fn upcoming(conn: &rusqlite::Connection, today: NaiveDate, max_items: usize, hide_received: bool) -> Result<Vec<Memory>> {
+    let assets = db::query::dated_assets(conn, AccessScope::All, hide_received)?;
+    let to = today + chrono::Days::new(DIGEST_DAYS - 1);
+    let mut entries = memories::anniversaries(&memories::detect_events(&assets), today, to);
+    entries.extend(memories::on_this_day(&assets, today, to));
+    entries.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.key.cmp(&b.key)));
+    let mut seen = std::collections::HashSet::new();
+    entries.retain(|m| seen.insert(m.asset_id));
+    entries.truncate(max_items);
+    Ok(entries)
+}
+
+/// JPEG of the asset's small thumbnail; mail clients can't be relied on to show WebP
+fn thumbnail_jpeg(conn: &rusqlite::Connection, derived: &std::path::Path, asset_id: i64) -> Option<Vec<u8>> {
+    let (sha, _) = db::query::get_thumb_info(conn, asset_id).ok()?;
+    let img = image::open(crate::pipeline::thumb::thumb_path(derived, &sha?, 256)).ok()?;
+    let img = img.thumbnail(IMAGE_EDGE, IMAGE_EDGE).to_rgb8();
+    let mut jpeg = Vec::new();
+    image::DynamicImage::ImageRgb8(img)
+        .write_to(&mut Cursor::new(&mut jpeg), image::ImageOutputFormat::Jpeg(80))
+        .ok()?;
+    Some(jpeg)
+}
+
+/// Build and send the digest for the week starting today. Returns the number of memories mailed;
+/// nothing is sent when there are none.
+pub fn send_digest(state: &AppState, settings: &DigestSettings) -> Result<usize> {
+    let conn = state.pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+    let hide_received = state.received_media.read().hide_in_memories;
+    let entries = upcoming(&conn, Local::now().date_naive(), settings.max_items, hide_received)?;
+    if entries.is_empty() {
+        return Ok(0);
+    }
+    let (subject, html, text) = render(&entries, &settings.base_url);
+    let images: Vec<InlineImage> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, m)| {
+            thumbnail_jpeg(&conn, &state.paths.derived, m.asset_id)
+                .map(|data| InlineImage { content_id: format!("memory-{}@seen", i), mime: "image/jpeg", data })
+        })
+        .collect();
+    drop(conn);
+
+    let mut raw = [0u8; 12];
+    getrandom::fill(&mut raw).map_err(|e| anyhow::anyhow!("Failed to generate message id: {}", e))?;
+    let email = Email {
+        from: &settings.smtp.from,
+        to: &settings.recipients,
+        subject: &subject,
+        text: &text,
+        html: &html,
+        images: &images,
+    };
+    let message = smtp::build_message(&email, chrono::Utc::now(), &hex::encode(raw));
+    smtp::send(&settings.smtp, &settings.recipients, &message).context("Failed to send digest")?;
+    Ok(entries.len())
+}
+
+/// Check the digest schedule twice a minute and mail the digest when it's due.
+/// Digests missed while the server was down are not made up.
+pub fn start(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(30));
+        let mut last_minute = 0i64;
+        loop {
+            interval.tick().await;
+            let settings = state.digest.read().clone();
+            if !settings.enabled {
+                continue;
+            }
+            let now = Local::now();
+            let minute_start = now.timestamp() - now.second() as i64;
+            let due = Schedule::parse(&settings.schedule).is_ok_and(|s| s.matches(now.naive_local()));
+            if !due || last_minute == minute_start {
+                continue;
+            }
+            last_minute = minute_start;
+
+            let result = tokio::task::spawn_blocking({
+                let state = state.clone();
+                move || send_digest(&state, &settings)
+            }).await;
+            match result {
+                Ok(Ok(0)) => tracing::info!("memories digest skipped, no memories this week"),
+                Ok(Ok(n)) => tracing::info!("memories digest sent with {} memories", n),
+                Ok(Err(e)) => tracing::warn!("memories digest failed: {:#}", e),
+                Err(e) => tracing::warn!("memories digest task failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest_render_and_validate() {
+        let entries = vec![
+            Memory {
+                key: "day-1".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 6, 17).unwrap(),
+                summary: "Iceland <2019>".to_string(),
+                description: "5 years ago".to_string(),
+                asset_id: 42,
+            },
+            Memory {
+                key: "day-2".to_string(),
+                date: NaiveDate::from_ymd_opt(2024, 6, 19).unwrap(),
+                summary: "On this day".to_string(),
+                description: "2 photos".to_string(),
+                asset_id: 7,
+            },
+        ];
+        let (subject, html, text) = render(&entries, "https://photos.example.com/");
+        assert_eq!(subject, "2 memories this week");
+        assert!(html.contains("<a href=\"https://photos.example.com/asset/42\"><img src=\"cid:memory-0@seen\""));
+        assert!(html.contains("cid:memory-1@seen"));
+        assert!(html.contains("Iceland &lt;2019&gt;"));
+        assert!(html.contains("Monday, June 17"));
+        assert!(text.contains("Iceland <2019> (Monday, June 17)\n5 years ago\nhttps://photos.example.com/asset/42\n"));
+
+        let mut settings = DigestSettings::default();
+        assert!(settings.validate().is_ok());
+        settings.enabled = true;
+        assert_eq!(settings.validate(), Err("smtp.host is required".to_string()));
+        settings.smtp.host = "smtp.example.com".to_string();
+        settings.smtp.from = "photos@example.com".to_string();
+        settings.recipients = vec!["me@example.com".to_string()];
+        settings.base_url = "https://photos.example.com".to_string();
+        assert!(settings.validate().is_ok());
+        settings.schedule = "every monday".to_string();
+        assert!(settings.validate().is_err());
+        settings.schedule = "0 8 * * 1".to_string();
+        settings.recipients.push("me@example.com\r\nRCPT TO:<x@y.z>".to_string());
+        assert!(settings.validate().is_err());
+    }
+}
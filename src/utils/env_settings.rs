@@ -0,0 +1,196 @@
+//! Environment variables whose values now live in the database.
+//!
+//! Face thresholds, HEVC handling and worker thread counts used to be set only through the
+//! environment. The first time the server starts with a settings table, the values set then are
+//! copied into it. From then on the saved values are applied over the environment at startup and
+//! changed through `PUT /settings/env` (or `POST /faces/settings`). A variable still set in the
+//! environment to something else is reported as shadowed, since editing it no longer does anything.
+
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Settings key holding the saved values as JSON
+pub const SETTINGS_KEY: &str = "env";
+
+pub const PERSISTED_VARS: [&str; 12] = [
+    "SEEN_FACE_CONFIDENCE_THRESHOLD",
+    "SEEN_FACE_NMS_IOU_THRESHOLD",
+    "SEEN_FACE_CLUSTER_EPSILON",
+    "SEEN_FACE_HDBSCAN_MIN_CLUSTER_SIZE",
+    "SEEN_FACE_HDBSCAN_MIN_SAMPLES",
+    "SEEN_FACE_CLUSTER_ALGORITHM",
+    "SEEN_FACE_ASSIGN_THRESHOLD",
+    "SEEN_HEVC_TRANSCODE",
+    "FLASH_HASH_THREADS",
+    "FLASH_META_THREADS",
+    "FLASH_THUMB_THREADS",
+    "FLASH_VIPS_CONCURRENCY",
+];
+
+/// Read once at startup, so a change applies on the next restart; the rest are read as they're used
+pub const RESTART_VARS: [&str; 4] = ["FLASH_HASH_THREADS", "FLASH_META_THREADS", "FLASH_THUMB_THREADS", "FLASH_VIPS_CONCURRENCY"];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedEnv {
+    /// When the environment was first copied in
+    pub imported_at: i64,
+    pub values: BTreeMap<String, String>,
+}
+
+/// A variable set in the environment that the saved value overrides
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Shadowed {
+    pub var: String,
+    pub env: String,
+    pub saved: String,
+}
+
+/// What startup did with the environment
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EnvImport {
+    /// Copied into the database on this start, which only happens once
+    pub imported: Vec<String>,
+    pub shadowed: Vec<Shadowed>,
+}
+
+static LAST_IMPORT: OnceCell<EnvImport> = OnceCell::new();
+
+/// Whether `value` is usable for `var`, with the reason when it isn't
+pub fn validate(var: &str, value: &str) -> Result<(), String> {
+    let value = value.trim();
+    let ok = match var {
+        "SEEN_FACE_CONFIDENCE_THRESHOLD" | "SEEN_FACE_NMS_IOU_THRESHOLD" | "SEEN_FACE_CLUSTER_EPSILON" | "SEEN_FACE_ASSIGN_THRESHOLD" => {
+            value.parse::<f32>().is_ok_and(|v| v.is_finite() && v >= 0.0)
+        }
+        "SEEN_FACE_CLUSTER_ALGORITHM" => ["hdbscan", "dbscan"].contains(&value),
+        "SEEN_HEVC_TRANSCODE" => ["auto", "never", "off", "false", "always", "force"].contains(&value.to_lowercase().as_str()),
+        _ if PERSISTED_VARS.contains(&var) => value.parse::<usize>().is_ok_and(|v| v > 0),
+        _ => return Err(format!("{} is not kept in the database", var)),
+    };
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("Invalid value '{}' for {}", value, var))
+    }
+}
+
+pub fn load(conn: &rusqlite::Connection) -> Result<Option<SavedEnv>> {
+    Ok(match crate::db::writer::get_setting(conn, SETTINGS_KEY)? {
+        Some(json) => Some(serde_json::from_str(&json)?),
+        None => None,
+    })
+}
+
+/// Copy the persisted variables `env` has into the database unless that was done before, and
+/// compare the saved values with it. Unusable values are left behind with a warning.
+pub fn import(conn: &rusqlite::Connection, env: impl Fn(&str) -> Option<String>) -> Result<(SavedEnv, EnvImport)> {
+    let mut report = EnvImport::default();
+    let saved = match load(conn)? {
+        Some(saved) => saved,
+        None => {
+            let mut values = BTreeMap::new();
+            for var in PERSISTED_VARS {
+                let Some(value) = env(var) else { continue };
+                match validate(var, &value) {
+                    Ok(()) => {
+                        values.insert(var.to_string(), value.trim().to_string());
+                    }
+                    Err(e) => tracing::warn!("Not importing {}: {}", var, e),
+                }
+            }
+            let saved = SavedEnv { imported_at: chrono::Utc::now().timestamp(), values };
+            crate::db::writer::set_setting(conn, SETTINGS_KEY, &serde_json::to_string(&saved)?)?;
+            report.imported = saved.values.keys().cloned().collect();
+            saved
+        }
+    };
+    for (var, value) in &saved.values {
+        if let Some(current) = env(var).filter(|current| current.trim() != value) {
+            report.shadowed.push(Shadowed { var: var.clone(), env: current, saved: value.clone() });
+        }
+    }
+    Ok((saved, report))
+}
+
+/// At startup, before the configuration is read: import the environment the first time, then
+/// apply the saved values over it
+pub fn import_and_apply(conn: &rusqlite::Connection) -> Result<EnvImport> {
+    let (saved, report) = import(conn, |var| std::env::var(var).ok())?;
+    apply(saved.values.iter().map(|(var, value)| (var.as_str(), Some(value.as_str()))));
+    let _ = LAST_IMPORT.set(report.clone());
+    Ok(report)
+}
+
+/// The report from startup
+pub fn last_import() -> Option<&'static EnvImport> {
+    LAST_IMPORT.get()
+}
+
+/// Set (or, for None, unset) variables in this process
+pub fn apply<'a>(changes: impl IntoIterator<Item = (&'a str, Option<&'a str>)>) {
+    for (var, value) in changes {
+        match value {
+            Some(value) => std::env::set_var(var, value),
+            None => std::env::remove_var(var),
+        }
+    }
+}
+
+/// Save changed values, None removing one so the environment or built-in default applies again
+pub fn persist(conn: &rusqlite::Connection, changes: &[(String, Option<String>)]) -> Result<SavedEnv> {
+    let mut saved = load(conn)?.unwrap_or_else(|| SavedEnv { imported_at: chrono::Utc::now().timestamp(), ..Default::default() });
+    for (var, value) in changes {
+        match value {
+            Some(value) => saved.values.insert(var.clone(), value.clone()),
+            None => saved.values.remove(var),
+        };
+    }
+    crate::db::writer::set_setting(conn, SETTINGS_KEY, &serde_json::to_string(&saved)?)?;
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_of(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: Vec<(String, String)> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |var| vars.iter().find(|(k, _)| k == var).map(|(_, v)| v.clone())
+    }
+
+    #[test]
+    fn test_import_once_then_shadow() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::schema::apply_schema(&conn).unwrap();
+
+        let first = env_of(&[("FLASH_THUMB_THREADS", "4"), ("SEEN_HEVC_TRANSCODE", "never"), ("SEEN_FACE_NMS_IOU_THRESHOLD", "lots"), ("FLASH_PORT", "80")]);
+        let (saved, report) = import(&conn, &first).unwrap();
+        assert_eq!(report.imported, vec!["FLASH_THUMB_THREADS", "SEEN_HEVC_TRANSCODE"]);
+        assert!(report.shadowed.is_empty());
+        assert_eq!(saved.values.get("FLASH_THUMB_THREADS").map(String::as_str), Some("4"));
+
+        // Later starts only compare; a changed environment no longer wins
+        let later = env_of(&[("FLASH_THUMB_THREADS", "8"), ("SEEN_HEVC_TRANSCODE", "never"), ("FLASH_META_THREADS", "2")]);
+        let (again, report) = import(&conn, &later).unwrap();
+        assert_eq!(again, saved);
+        assert!(report.imported.is_empty());
+        assert_eq!(report.shadowed, vec![Shadowed { var: "FLASH_THUMB_THREADS".into(), env: "8".into(), saved: "4".into() }]);
+
+        let updated = persist(&conn, &[("FLASH_THUMB_THREADS".into(), None), ("SEEN_FACE_CLUSTER_ALGORITHM".into(), Some("dbscan".into()))]).unwrap();
+        assert_eq!(updated.values.keys().collect::<Vec<_>>(), vec!["SEEN_FACE_CLUSTER_ALGORITHM", "SEEN_HEVC_TRANSCODE"]);
+        assert_eq!(load(&conn).unwrap().unwrap(), updated);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("SEEN_FACE_CONFIDENCE_THRESHOLD", "0.35").is_ok());
+        assert!(validate("SEEN_FACE_CONFIDENCE_THRESHOLD", "-1").is_err());
+        assert!(validate("SEEN_HEVC_TRANSCODE", "Always").is_ok());
+        assert!(validate("SEEN_HEVC_TRANSCODE", "sometimes").is_err());
+        assert!(validate("FLASH_HASH_THREADS", "0").is_err());
+        assert!(validate("SEEN_FACE_HDBSCAN_MIN_SAMPLES", "3").is_ok());
+        assert!(validate("FLASH_ROOT", "/photos").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+
+/// A newly indexed asset, as listed in the feeds
+#[derive(Debug, Clone)]
+pub struct RecentAsset {
+    pub id: i64,
+    pub filename: String,
+    pub dirname: String,
+    pub mime: String,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub taken_at: Option<i64>,
+    /// File modification time, the closest record of when it was added
+    pub modified_at: i64,
+}
+
+impl RecentAsset {
+    fn summary(&self) -> String {
+        let mut parts = vec![self.dirname.clone()];
+        if let (Some(w), Some(h)) = (self.width, self.height) {
+            parts.push(format!("{}×{}", w, h));
+        }
+        if let Some(taken) = self.taken_at.and_then(|t| DateTime::from_timestamp(t, 0)) {
+            parts.push(format!("taken {}", taken.format("%-d %b %Y")));
+        }
+        parts.join(" · ")
+    }
+
+    fn published(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.modified_at, 0).unwrap_or_default()
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn content_html(asset: &RecentAsset, origin: &str) -> String {
+    format!(
+        "<p><a href=\"{origin}/asset/{id}\"><img src=\"{origin}/api/thumb/{id}\" alt=\"{name}\"></a></p><p>{summary}</p>",
+        origin = origin,
+        id = asset.id,
+        name = escape_xml(&asset.filename),
+        summary = escape_xml(&asset.summary()),
+    )
+}
+
+/// RSS 2.0 document listing `assets`, newest first, with Media RSS thumbnails
+pub fn rss(title: &str, origin: &str, feed_url: &str, assets: &[RecentAsset], now: DateTime<Utc>) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<rss version=\"2.0\" xmlns:media=\"http://search.yahoo.com/mrss/\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n");
+    out.push_str(&format!("<title>{}</title>\n<link>{}/</link>\n", escape_xml(title), escape_xml(origin)));
+    out.push_str(&format!("<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n", escape_xml(feed_url)));
+    out.push_str("<description>Newly added photos and videos</description>\n");
+    out.push_str(&format!("<lastBuildDate>{}</lastBuildDate>\n", now.to_rfc2822()));
+    for asset in assets {
+        let link = format!("{}/asset/{}", origin, asset.id);
+        out.push_str("<item>\n");
+        out.push_str(&format!("<title>{}</title>\n", escape_xml(&asset.filename)));
+        out.push_str(&format!("<link>{}</link>\n<guid isPermaLink=\"true\">{}</guid>\n", escape_xml(&link), escape_xml(&link)));
+        out.push_str(&format!("<pubDate>{}</pubDate>\n", asset.published().to_rfc2822()));
+        out.push_str(&format!("<description>{}</description>\n", escape_xml(&content_html(asset, origin))));
+        out.push_str(&format!("<media:thumbnail url=\"{}/api/thumb/{}\"/>\n", escape_xml(origin), asset.id));
+        out.push_str("</item>\n");
+    }
+    out.push_str("</channel>\n</rss>\n");
+    out
+}
+
+/// JSON Feed 1.1 document listing `assets`, newest first; `_seen` carries the asset details for automation
+pub fn json_feed(title: &str, origin: &str, feed_url: &str, assets: &[RecentAsset]) -> serde_json::Value {
+    let items: Vec<serde_json::Value> = assets
+        .iter()
+        .map(|asset| {
+            serde_json::json!({
+                "id": asset.id.to_string(),
+                "url": format!("{}/asset/{}", origin, asset.id),
+                "title": asset.filename,
+                "content_html": content_html(asset, origin),
+                "summary": asset.summary(),
+                "image": format!("{}/api/thumb/{}", origin, asset.id),
+                "date_published": asset.published().to_rfc3339(),
+                "_seen": {
+                    "asset_id": asset.id,
+                    "dirname": asset.dirname,
+                    "mime": asset.mime,
+                    "width": asset.width,
+                    "height": asset.height,
+                    "taken_at": asset.taken_at
+                }
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": title,
+        "home_page_url": format!("{}/", origin),
+        "feed_url": feed_url,
+        "items": items
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feeds() {
+        let assets = vec![RecentAsset {
+            id: 42,
+            filename: "Tom & Jerry <1>.jpg".to_string(),
+            dirname: "/photos/2024".to_string(),
+            mime: "image/jpeg".to_string(),
+            width: Some(4032),
+            height: Some(3024),
+            taken_at: Some(1718150400),
+            modified_at: 1718236800,
+        }];
+        let origin = "https://photos.example.com";
+        let xml = rss("New photos", origin, "https://photos.example.com/api/feeds/recent.xml", &assets, DateTime::from_timestamp(1718240000, 0).unwrap());
+        assert!(xml.contains("<title>Tom &amp; Jerry &lt;1&gt;.jpg</title>"));
+        assert!(xml.contains("<guid isPermaLink=\"true\">https://photos.example.com/asset/42</guid>"));
+        assert!(xml.contains("<pubDate>Thu, 13 Jun 2024 00:00:00 +0000</pubDate>"));
+        assert!(xml.contains("<media:thumbnail url=\"https://photos.example.com/api/thumb/42\"/>"));
+        // The HTML body is escaped once for the XML, on top of its own escaping
+        assert!(xml.contains("alt=&quot;Tom &amp;amp; Jerry"));
+        assert_eq!(xml.matches("<item>").count(), 1);
+
+        let json = json_feed("New photos", origin, "https://photos.example.com/api/feeds/recent.json", &assets);
+        let item = &json["items"][0];
+        assert_eq!(item["id"], "42");
+        assert_eq!(item["date_published"], "2024-06-13T00:00:00+00:00");
+        assert_eq!(item["summary"], "/photos/2024 · 4032×3024 · taken 12 Jun 2024");
+        assert_eq!(item["_seen"]["mime"], "image/jpeg");
+    }
+}
@@ -41,6 +41,94 @@ struct GpuFailureTracker {
     cpu_jobs_since_last_retry: u32,
 }
 
+static FFMPEG_BIN: Lazy<String> = Lazy::new(|| resolve_binary("FLASH_FFMPEG", "ffmpeg"));
+static FFPROBE_BIN: Lazy<String> = Lazy::new(|| resolve_binary("FLASH_FFPROBE", "ffprobe"));
+static BINARY_STATUS: Lazy<Mutex<Option<Vec<BinaryStatus>>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct BinaryStatus {
+    pub name: String,
+    pub path: String,
+    pub available: bool,
+    pub version: Option<String>,
+    pub warning: Option<String>,
+}
+
+/// Resolve a tool binary: explicit env override, then a copy bundled next to
+/// our own executable (Tauri sidecar layout), then whatever is on PATH.
+pub(crate) fn resolve_binary(env_key: &str, name: &str) -> String {
+    if let Ok(p) = std::env::var(env_key) {
+        if !p.trim().is_empty() {
+            return p.trim().to_string();
+        }
+    }
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(dir) = exe.parent() {
+            let bundled = dir.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+            if bundled.is_file() {
+                return bundled.to_string_lossy().to_string();
+            }
+        }
+    }
+    name.to_string()
+}
+
+/// Path (or bare command name) used to invoke ffmpeg
+pub fn ffmpeg_bin() -> &'static str {
+    FFMPEG_BIN.as_str()
+}
+
+/// Path (or bare command name) used to invoke ffprobe
+pub fn ffprobe_bin() -> &'static str {
+    FFPROBE_BIN.as_str()
+}
+
+fn probe_binary(name: &str, path: &str) -> BinaryStatus {
+    match Command::new(path).arg("-version").output() {
+        Ok(o) if o.status.success() => {
+            let version = String::from_utf8_lossy(&o.stdout).lines().next().map(|l| l.to_string());
+            BinaryStatus { name: name.to_string(), path: path.to_string(), available: true, version, warning: None }
+        }
+        Ok(o) => BinaryStatus {
+            name: name.to_string(),
+            path: path.to_string(),
+            available: false,
+            version: None,
+            warning: Some(format!("{} at '{}' exited with status {}", name, path, o.status)),
+        },
+        Err(e) => BinaryStatus {
+            name: name.to_string(),
+            path: path.to_string(),
+            available: false,
+            version: None,
+            warning: Some(format!("{} not found at '{}': {} (video thumbnails and metadata will be unavailable)", name, path, e)),
+        },
+    }
+}
+
+/// Run `-version` against ffmpeg/ffprobe once and log a warning for anything missing.
+/// Results are cached for the health endpoint.
+pub fn verify_binaries() -> Vec<BinaryStatus> {
+    let statuses = vec![
+        probe_binary("ffmpeg", ffmpeg_bin()),
+        probe_binary("ffprobe", ffprobe_bin()),
+    ];
+    for s in &statuses {
+        match &s.warning {
+            Some(w) => warn!("{}", w),
+            None => tracing::info!("{}: {} ({})", s.name, s.path, s.version.as_deref().unwrap_or("unknown version")),
+        }
+    }
+    *BINARY_STATUS.lock().unwrap() = Some(statuses.clone());
+    statuses
+}
+
+/// Cached result of `verify_binaries` (verifies on first call if startup didn't)
+pub fn binary_status() -> Vec<BinaryStatus> {
+    let cached = BINARY_STATUS.lock().unwrap().clone();
+    cached.unwrap_or_else(verify_binaries)
+}
+
 pub fn init_gpu_config() -> FfmpegConfig {
     let mut config = FFMPEG_CONFIG.lock().unwrap();
     if let Some(ref cfg) = *config {
@@ -145,8 +233,14 @@ fn detect_gpu_accel() -> GpuAccel {
         }
     }
 
+    // Small ARM boards have no accelerator ffmpeg can use, and probing costs several ffmpeg runs
+    if crate::utils::host::host().constrained {
+        debug!("GPU: skipping probe on a constrained ARM host");
+        return GpuAccel::Cpu;
+    }
+
     // Probe ffmpeg for available hardware accelerators
-    let output = Command::new("ffmpeg")
+    let output = Command::new(ffmpeg_bin())
         .args(["-hide_banner", "-hwaccels"])
         .output();
     
@@ -345,7 +439,7 @@ pub fn check_opencl_devices() -> u32 {
 }
 
 fn check_filter(filter_name: &str) -> bool {
-    let output = Command::new("ffmpeg")
+    let output = Command::new(ffmpeg_bin())
         .args(["-hide_banner", "-filters"])
         .output();
     
@@ -466,7 +560,7 @@ pub fn run_ffmpeg_with_timeout(args: Vec<String>, timeout: Duration) -> Result<s
     use std::sync::{Arc, Mutex};
     use std::thread;
     
-    let mut cmd = Command::new("ffmpeg");
+    let mut cmd = Command::new(ffmpeg_bin());
     cmd.args(&args);
     cmd.stderr(std::process::Stdio::piped());
     cmd.stdout(std::process::Stdio::piped());
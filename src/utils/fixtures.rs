@@ -0,0 +1,353 @@
+//! Synthetic photo libraries for benchmarks and tests.
+//!
+//! `generate` writes the same files, byte for byte and with the same modification times, for the
+//! same spec and seed, so discover/hash/writer timings taken on two builds can be compared. The
+//! library has JPEGs from several cameras with EXIF make, model, capture date and orientation;
+//! PNGs and JPEGs without EXIF; phone-style filenames that carry the date; exact duplicates in
+//! another folder; drawn faces; and short videos when ffmpeg is available. The faces are schematic
+//! (an oval with eyes and a mouth): they exercise the face queue and its bookkeeping, not
+//! recognition quality.
+//!
+//! Build the command-line front end with `cargo run --features fixtures --bin seen-fixtures`.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Camera make and model written to EXIF; `None` leaves the EXIF block out
+const CAMERAS: [Option<(&str, &str)>; 6] = [
+    Some(("Apple", "iPhone 13")),
+    Some(("Google", "Pixel 7")),
+    Some(("Canon", "Canon EOS R6")),
+    Some(("SONY", "ILCE-7M3")),
+    Some(("samsung", "SM-S911B")),
+    None,
+];
+const SIZES: [(u32, u32); 5] = [(640, 480), (480, 640), (1024, 768), (1600, 1200), (320, 240)];
+/// Mostly upright, some rotated as phones record them
+const ORIENTATIONS: [u16; 6] = [1, 1, 1, 6, 3, 8];
+/// 2015-01-01T00:00:00Z; capture dates fall within the ten years after
+const FIRST_DATE: i64 = 1_420_070_400;
+const DATE_SPAN: u64 = 10 * 365 * 86_400;
+
+#[derive(Clone, Debug)]
+pub struct FixtureSpec {
+    pub images: usize,
+    /// Every n-th image is copied into `duplicates/`; 0 for none
+    pub duplicate_every: usize,
+    /// Images with one to three drawn faces, under `people/`
+    pub faces: usize,
+    /// Short test-pattern clips, under `videos/`; needs ffmpeg
+    pub videos: usize,
+    /// Images are scaled down to fit, to keep small test libraries fast to write
+    pub max_dimension: u32,
+    pub seed: u64,
+}
+
+impl Default for FixtureSpec {
+    fn default() -> Self {
+        Self { images: 1000, duplicate_every: 10, faces: 0, videos: 0, max_dimension: 1600, seed: 1 }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FixtureReport {
+    pub images: usize,
+    pub duplicates: usize,
+    pub faces: usize,
+    pub videos: usize,
+    /// Why videos were left out, e.g. ffmpeg missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub videos_skipped: Option<String>,
+    pub bytes: u64,
+}
+
+/// SplitMix64: small, fast and the same on every platform
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
+
+/// Write the library described by `spec` under `root`, which is created if needed
+pub fn generate(root: &Path, spec: &FixtureSpec) -> Result<FixtureReport> {
+    let mut rng = Rng(spec.seed);
+    let mut report = FixtureReport::default();
+    let mut written: Vec<PathBuf> = Vec::new();
+
+    for i in 0..spec.images {
+        let camera = rng.pick(&CAMERAS);
+        let taken = FIRST_DATE + rng.below(DATE_SPAN) as i64;
+        let orientation = rng.pick(&ORIENTATIONS);
+        let (width, height) = fit(rng.pick(&SIZES), spec.max_dimension);
+        let img = pattern(width, height, &mut rng);
+        let date = chrono::DateTime::from_timestamp(taken, 0).context("date out of range")?.naive_utc();
+        // PNGs carry no EXIF, so their date only comes from the file
+        let png = i % 9 == 8;
+        let name = match camera {
+            _ if png => format!("Screenshot_{}.png", date.format("%Y-%m-%d-%H-%M-%S")),
+            Some(("Google", _)) => format!("PXL_{}{:03}.jpg", date.format("%Y%m%d_%H%M%S"), i % 1000),
+            Some(("samsung", _)) => format!("{}.jpg", date.format("%Y%m%d_%H%M%S")),
+            _ => format!("IMG_{:05}.jpg", i),
+        };
+        let path = root.join(date.format("%Y/%m").to_string()).join(name);
+        let bytes = if png {
+            encode(&img, image::ImageFormat::Png)?
+        } else {
+            let jpeg = encode(&img, image::ImageFormat::Jpeg)?;
+            match camera {
+                Some((make, model)) => with_exif(&jpeg, make, model, &date.format("%Y:%m:%d %H:%M:%S").to_string(), orientation),
+                None => jpeg,
+            }
+        };
+        write(&path, &bytes, taken, &mut report)?;
+        written.push(path);
+        report.images += 1;
+
+        if spec.duplicate_every > 0 && (i + 1) % spec.duplicate_every == 0 {
+            let source = &written[rng.below(written.len() as u64) as usize];
+            let copy = root.join("duplicates").join(format!("copy_{:05}_{}", i, source.file_name().unwrap().to_string_lossy()));
+            write(&copy, &std::fs::read(source)?, taken, &mut report)?;
+            report.duplicates += 1;
+        }
+    }
+
+    for i in 0..spec.faces {
+        let taken = FIRST_DATE + rng.below(DATE_SPAN) as i64;
+        let (width, height) = fit((1024, 768), spec.max_dimension);
+        let mut img = pattern(width, height, &mut rng);
+        for _ in 0..1 + rng.below(3) {
+            draw_face(&mut img, &mut rng);
+        }
+        let date = chrono::DateTime::from_timestamp(taken, 0).context("date out of range")?.naive_utc();
+        let jpeg = encode(&img, image::ImageFormat::Jpeg)?;
+        let bytes = with_exif(&jpeg, "Apple", "iPhone 13", &date.format("%Y:%m:%d %H:%M:%S").to_string(), 1);
+        write(&root.join("people").join(format!("group_{:04}.jpg", i)), &bytes, taken, &mut report)?;
+        report.faces += 1;
+    }
+
+    for i in 0..spec.videos {
+        let (width, height) = fit(rng.pick(&SIZES), spec.max_dimension.min(640));
+        let seconds = 1 + rng.below(4);
+        let taken = FIRST_DATE + rng.below(DATE_SPAN) as i64;
+        let path = root.join("videos").join(format!("VID_{:04}.mp4", i));
+        if let Err(e) = render_video(&path, width & !1, height & !1, seconds) {
+            report.videos_skipped = Some(e.to_string());
+            break;
+        }
+        set_mtime(&path, taken)?;
+        report.bytes += std::fs::metadata(&path)?.len();
+        report.videos += 1;
+    }
+    Ok(report)
+}
+
+fn fit((width, height): (u32, u32), max: u32) -> (u32, u32) {
+    let scale = (max as f64 / width.max(height) as f64).min(1.0);
+    (((width as f64 * scale) as u32).max(8), ((height as f64 * scale) as u32).max(8))
+}
+
+/// Smooth colour bands with some texture, different for every image
+fn pattern(width: u32, height: u32, rng: &mut Rng) -> image::RgbImage {
+    let (a, b, c) = (1 + rng.below(7) as u32, 1 + rng.below(7) as u32, rng.next() as u32);
+    image::RgbImage::from_fn(width, height, |x, y| {
+        let r = (x * 255 / width) as u8;
+        let g = (y * 255 / height) as u8;
+        let noise = ((x.wrapping_mul(a) ^ y.wrapping_mul(b)).wrapping_add(c) >> 2) as u8;
+        image::Rgb([r.wrapping_add(noise / 8), g, noise / 2 + (c as u8) / 2])
+    })
+}
+
+fn draw_face(img: &mut image::RgbImage, rng: &mut Rng) {
+    let (w, h) = img.dimensions();
+    let radius = (w.min(h) as u64 / 8 + rng.below(w.min(h) as u64 / 8)) as i64;
+    let cx = radius + rng.below((w as i64 - 2 * radius).max(1) as u64) as i64;
+    let cy = radius + rng.below((h as i64 - 2 * radius).max(1) as u64) as i64;
+    let skin = rng.pick(&[[241, 194, 125], [198, 134, 66], [141, 85, 36], [255, 219, 172]]);
+    let eye = radius / 8;
+    let features = [
+        (cx - radius / 3, cy - radius / 4, eye),
+        (cx + radius / 3, cy - radius / 4, eye),
+    ];
+    for y in (cy - radius).max(0)..(cy + radius).min(h as i64) {
+        for x in (cx - radius).max(0)..(cx + radius).min(w as i64) {
+            // Taller than wide, like a face
+            let (dx, dy) = ((x - cx) as f64 / (radius as f64 * 0.78), (y - cy) as f64 / radius as f64);
+            if dx * dx + dy * dy > 1.0 {
+                continue;
+            }
+            let on_eye = features.iter().any(|(ex, ey, r)| (x - ex).pow(2) + (y - ey).pow(2) <= r * r);
+            let on_mouth = (y - (cy + radius / 2)).abs() <= (radius / 20).max(1) && (x - cx).abs() <= radius / 3;
+            let colour = if on_eye || on_mouth { [40, 25, 20] } else { skin };
+            img.put_pixel(x as u32, y as u32, image::Rgb(colour));
+        }
+    }
+}
+
+fn encode(img: &image::RgbImage, format: image::ImageFormat) -> Result<Vec<u8>> {
+    let mut out = std::io::Cursor::new(Vec::new());
+    match format {
+        image::ImageFormat::Jpeg => image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, 85).encode_image(img)?,
+        _ => img.write_to(&mut out, format)?,
+    }
+    Ok(out.into_inner())
+}
+
+enum Field<'a> {
+    Ascii(&'a str),
+    Short(u16),
+    Long(u32),
+}
+
+/// A little-endian TIFF IFD starting at `start`, followed by the values that don't fit in an entry
+fn ifd(entries: &[(u16, Field)], start: usize) -> Vec<u8> {
+    let data_start = start + 2 + entries.len() * 12 + 4;
+    let (mut dir, mut data) = (Vec::new(), Vec::new());
+    dir.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for (tag, field) in entries {
+        let (kind, count, mut bytes) = match field {
+            Field::Ascii(s) => (2u16, s.len() + 1, [s.as_bytes(), b"\0"].concat()),
+            Field::Short(v) => (3, 1, v.to_le_bytes().to_vec()),
+            Field::Long(v) => (4, 1, v.to_le_bytes().to_vec()),
+        };
+        dir.extend_from_slice(&tag.to_le_bytes());
+        dir.extend_from_slice(&kind.to_le_bytes());
+        dir.extend_from_slice(&(count as u32).to_le_bytes());
+        if bytes.len() <= 4 {
+            bytes.resize(4, 0);
+            dir.extend_from_slice(&bytes);
+        } else {
+            dir.extend_from_slice(&((data_start + data.len()) as u32).to_le_bytes());
+            data.extend_from_slice(&bytes);
+            // Values start on a word boundary
+            if data.len() % 2 == 1 {
+                data.push(0);
+            }
+        }
+    }
+    dir.extend_from_slice(&0u32.to_le_bytes());
+    dir.extend_from_slice(&data);
+    dir
+}
+
+/// The JPEG with an EXIF APP1 segment carrying make, model, orientation and capture date
+fn with_exif(jpeg: &[u8], make: &str, model: &str, date: &str, orientation: u16) -> Vec<u8> {
+    let ifd0 = |exif_at: u32| {
+        ifd(
+            &[
+                (0x010f, Field::Ascii(make)),
+                (0x0110, Field::Ascii(model)),
+                (0x0112, Field::Short(orientation)),
+                (0x0132, Field::Ascii(date)),
+                (0x8769, Field::Long(exif_at)),
+            ],
+            8,
+        )
+    };
+    let exif_at = 8 + ifd0(0).len();
+    let mut tiff = b"II*\0".to_vec();
+    tiff.extend_from_slice(&8u32.to_le_bytes());
+    tiff.extend_from_slice(&ifd0(exif_at as u32));
+    tiff.extend_from_slice(&ifd(&[(0x9003, Field::Ascii(date))], exif_at));
+
+    let mut out = Vec::with_capacity(jpeg.len() + tiff.len() + 10);
+    out.extend_from_slice(&jpeg[..2]);
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    out.extend_from_slice(b"Exif\0\0");
+    out.extend_from_slice(&tiff);
+    out.extend_from_slice(&jpeg[2..]);
+    out
+}
+
+/// A bit-exact test pattern clip, so repeated runs write identical files
+fn render_video(path: &Path, width: u32, height: u32, seconds: u64) -> Result<()> {
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    let source = format!("testsrc2=size={}x{}:rate=15:duration={}", width, height, seconds);
+    let output = std::process::Command::new(crate::utils::ffmpeg::ffmpeg_bin())
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-f", "lavfi", "-i", &source])
+        .args(["-c:v", "mpeg4", "-q:v", "5", "-threads", "1", "-pix_fmt", "yuv420p"])
+        .args(["-fflags", "+bitexact", "-flags:v", "+bitexact", "-map_metadata", "-1"])
+        .arg(path)
+        .output()
+        .context("ffmpeg not available")?;
+    if !output.status.success() {
+        anyhow::bail!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn write(path: &Path, bytes: &[u8], mtime: i64, report: &mut FixtureReport) -> Result<()> {
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(path, bytes).with_context(|| format!("writing {}", path.display()))?;
+    set_mtime(path, mtime)?;
+    report.bytes += bytes.len() as u64;
+    Ok(())
+}
+
+fn set_mtime(path: &Path, secs: i64) -> Result<()> {
+    let time = UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64);
+    std::fs::File::options().write(true).open(path)?.set_modified(time)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn mtime(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    fn listing(root: &Path) -> Vec<(String, Vec<u8>, Option<SystemTime>)> {
+        let mut files: Vec<_> = walkdir::WalkDir::new(root)
+            .into_iter()
+            .flatten()
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                let rel = e.path().strip_prefix(root).unwrap().to_string_lossy().into_owned();
+                (rel, crate::pipeline::hash::sha256_file(e.path()).unwrap(), mtime(e.path()))
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_generate_is_deterministic() {
+        let spec = FixtureSpec { images: 18, duplicate_every: 5, faces: 2, videos: 0, max_dimension: 96, seed: 7 };
+        let (a, b, c) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let report = generate(a.path(), &spec).unwrap();
+        assert_eq!((report.images, report.duplicates, report.faces), (18, 3, 2));
+        generate(b.path(), &spec).unwrap();
+        let files = listing(a.path());
+        assert_eq!(files.len(), 23);
+        assert_eq!(files, listing(b.path()));
+        generate(c.path(), &FixtureSpec { seed: 8, ..spec }).unwrap();
+        assert_ne!(files, listing(c.path()));
+
+        // Duplicates are byte-identical to an image elsewhere in the library
+        let (dupes, originals): (Vec<_>, Vec<_>) = files.iter().partition(|(p, _, _)| p.starts_with("duplicates"));
+        assert!(dupes.iter().all(|(_, sha, _)| originals.iter().any(|(_, s, _)| s == sha)));
+        // The capture date in EXIF matches the modification time
+        let (path, _, modified) = files.iter().find(|(p, _, _)| p.contains("IMG_") || p.contains("PXL_")).unwrap();
+        let taken = crate::pipeline::dates::exif_date(&a.path().join(path)).unwrap();
+        assert_eq!(*modified, Some(UNIX_EPOCH + Duration::from_secs(taken as u64)));
+    }
+}
@@ -0,0 +1,61 @@
+//! The machine the backend runs on. Raspberry Pis and ARM NAS boxes have a few slow cores, little
+//! memory and no GPU that ffmpeg can use, so the defaults that suit a desktop overwhelm them.
+//! Those hosts are recognized at startup and get lighter defaults; explicit settings still win.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+/// ARM hosts with at most this many cores or this much memory get the light defaults
+const SMALL_CORES: usize = 4;
+const SMALL_MEMORY: u64 = 4 * 1024 * 1024 * 1024;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Host {
+    pub arch: &'static str,
+    pub cores: usize,
+    pub memory_bytes: Option<u64>,
+    /// Board name from the device tree, e.g. "Raspberry Pi 4 Model B Rev 1.4"
+    pub model: Option<String>,
+    /// Lighter worker, thumbnail and GPU defaults are in effect
+    pub constrained: bool,
+}
+
+static HOST: Lazy<Host> = Lazy::new(|| {
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let memory_bytes = crate::db::tuning::system_memory();
+    let model = std::fs::read_to_string("/proc/device-tree/model")
+        .ok()
+        .map(|m| m.trim_end_matches('\0').trim().to_string())
+        .filter(|m| !m.is_empty());
+    let forced = std::env::var("FLASH_CONSTRAINED_HOST").ok().map(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+    let arm = cfg!(all(target_os = "linux", any(target_arch = "aarch64", target_arch = "arm")));
+    let constrained = forced.unwrap_or_else(|| is_constrained(arm, cores, memory_bytes, model.as_deref()));
+    Host { arch: std::env::consts::ARCH, cores, memory_bytes, model, constrained }
+});
+
+/// Detected once per process
+pub fn host() -> &'static Host {
+    &HOST
+}
+
+/// Only ARM Linux boards count; an ARM server or a Mac has the headroom of any desktop
+fn is_constrained(arm: bool, cores: usize, memory_bytes: Option<u64>, model: Option<&str>) -> bool {
+    arm && (cores <= SMALL_CORES
+        || memory_bytes.is_some_and(|m| m <= SMALL_MEMORY)
+        || model.is_some_and(|m| m.starts_with("Raspberry Pi")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_constrained() {
+        const GB: u64 = 1024 * 1024 * 1024;
+        assert!(is_constrained(true, 4, Some(8 * GB), None));
+        assert!(is_constrained(true, 8, Some(2 * GB), None));
+        assert!(is_constrained(true, 8, None, Some("Raspberry Pi 5 Model B Rev 1.0")));
+        assert!(!is_constrained(true, 64, Some(128 * GB), None));
+        assert!(!is_constrained(false, 2, Some(GB), None));
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// An all-day calendar entry
+#[derive(Debug, Clone)]
+pub struct IcsEvent {
+    /// Stable across feed refreshes, so calendars update entries instead of duplicating them
+    pub uid: String,
+    pub date: NaiveDate,
+    pub summary: String,
+    pub description: String,
+    pub url: Option<String>,
+}
+
+/// Escape TEXT values (RFC 5545 3.3.11)
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line at 75 octets without splitting a UTF-8 sequence (RFC 5545 3.1)
+fn fold(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// A VCALENDAR document holding `events`
+pub fn calendar(name: &str, events: &[IcsEvent], stamp: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    let stamp = stamp.format("%Y%m%dT%H%M%SZ").to_string();
+    for line in ["BEGIN:VCALENDAR", "VERSION:2.0", "PRODID:-//seen//memories//EN", "CALSCALE:GREGORIAN", "METHOD:PUBLISH"] {
+        fold(line, &mut out);
+    }
+    fold(&format!("X-WR-CALNAME:{}", escape(name)), &mut out);
+    for event in events {
+        let end = event.date.succ_opt().unwrap_or(event.date);
+        fold("BEGIN:VEVENT", &mut out);
+        fold(&format!("UID:{}", event.uid), &mut out);
+        fold(&format!("DTSTAMP:{}", stamp), &mut out);
+        fold(&format!("DTSTART;VALUE=DATE:{}", event.date.format("%Y%m%d")), &mut out);
+        fold(&format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")), &mut out);
+        fold(&format!("SUMMARY:{}", escape(&event.summary)), &mut out);
+        fold(&format!("DESCRIPTION:{}", escape(&event.description)), &mut out);
+        if let Some(url) = &event.url {
+            fold(&format!("URL:{}", url), &mut out);
+        }
+        fold("TRANSP:TRANSPARENT", &mut out);
+        fold("END:VEVENT", &mut out);
+    }
+    fold("END:VCALENDAR", &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calendar_escapes_and_folds() {
+        let event = IcsEvent {
+            uid: "event-1-5@seen".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 6, 12).unwrap(),
+            summary: "5 years since Iceland; Reykjavík, Vík".to_string(),
+            description: format!("{}\nsecond line", "é".repeat(60)),
+            url: Some("http://localhost:9161/asset/42".to_string()),
+        };
+        let ics = calendar("Memories", &[event], DateTime::from_timestamp(0, 0).unwrap());
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240612\r\nDTEND;VALUE=DATE:20240613\r\n"));
+        assert!(ics.contains("SUMMARY:5 years since Iceland\\; Reykjavík\\, Vík\r\n"));
+        assert!(ics.contains("second line"));
+        // Every physical line fits in 75 octets
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+        let unfolded = ics.replace("\r\n ", "");
+        assert!(unfolded.contains(&format!("DESCRIPTION:{}\\nsecond line\r\n", "é".repeat(60))));
+    }
+}
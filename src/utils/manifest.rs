@@ -0,0 +1,198 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Missing and modified paths kept per verification; the counters keep counting past this
+const MAX_LISTED: usize = 1000;
+
+/// One line of a checksum manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    /// Lowercase hex SHA-256
+    pub sha256: String,
+    pub path: String,
+}
+
+/// Format a line the way `sha256sum` does, so `sha256sum -c` accepts the manifest.
+/// Paths containing a backslash or newline are escaped and the line is prefixed with `\`.
+pub fn format_line(sha256_hex: &str, path: &str) -> String {
+    if path.contains('\\') || path.contains('\n') {
+        format!("\\{}  {}\n", sha256_hex, path.replace('\\', "\\\\").replace('\n', "\\n"))
+    } else {
+        format!("{}  {}\n", sha256_hex, path)
+    }
+}
+
+fn unescape(path: &str) -> Option<String> {
+    let mut out = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next()? {
+                '\\' => out.push('\\'),
+                'n' => out.push('\n'),
+                _ => return None,
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    Some(out)
+}
+
+/// Parse `sha256sum` output (text or binary mode). Blank lines are skipped; the error names the bad line.
+pub fn parse(text: &str) -> Result<Vec<ManifestEntry>, String> {
+    let mut entries = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = || format!("Line {} is not a SHA-256 manifest entry", index + 1);
+        let (escaped, line) = match line.strip_prefix('\\') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        if line.len() < 66 || !line.is_char_boundary(64) {
+            return Err(invalid());
+        }
+        let (hash, rest) = line.split_at(64);
+        if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let path = rest.strip_prefix("  ").or_else(|| rest.strip_prefix(" *")).ok_or_else(invalid)?;
+        let path = if escaped { unescape(path).ok_or_else(invalid)? } else { path.to_string() };
+        if path.is_empty() {
+            return Err(invalid());
+        }
+        entries.push(ManifestEntry { sha256: hash.to_ascii_lowercase(), path });
+    }
+    Ok(entries)
+}
+
+/// Progress and findings of a verification run
+#[derive(Default)]
+pub struct VerifyJob {
+    pub root: Option<String>,
+    pub total: u64,
+    pub started_at: i64,
+    checked: AtomicU64,
+    ok: AtomicU64,
+    missing_count: AtomicU64,
+    modified_count: AtomicU64,
+    running: AtomicBool,
+    finished_at: Mutex<Option<i64>>,
+    missing: Mutex<Vec<String>>,
+    modified: Mutex<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyReport {
+    pub running: bool,
+    pub root: Option<String>,
+    pub total: u64,
+    pub checked: u64,
+    pub ok: u64,
+    pub missing_count: u64,
+    pub modified_count: u64,
+    /// First paths that are gone from the copy
+    pub missing: Vec<String>,
+    /// First paths whose content no longer matches
+    pub modified: Vec<String>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+impl VerifyJob {
+    pub fn new(root: Option<String>, total: u64, started_at: i64) -> Self {
+        Self { root, total, started_at, running: AtomicBool::new(true), ..Default::default() }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self) -> VerifyReport {
+        VerifyReport {
+            running: self.is_running(),
+            root: self.root.clone(),
+            total: self.total,
+            checked: self.checked.load(Ordering::Relaxed),
+            ok: self.ok.load(Ordering::Relaxed),
+            missing_count: self.missing_count.load(Ordering::Relaxed),
+            modified_count: self.modified_count.load(Ordering::Relaxed),
+            missing: self.missing.lock().clone(),
+            modified: self.modified.lock().clone(),
+            started_at: self.started_at,
+            finished_at: *self.finished_at.lock(),
+        }
+    }
+
+    fn record(&self, counter: &AtomicU64, list: &Mutex<Vec<String>>, path: &str) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        let mut list = list.lock();
+        if list.len() < MAX_LISTED {
+            list.push(path.to_string());
+        }
+    }
+
+    /// Hash every entry's file, relative entries resolved against `root`. Blocking.
+    pub fn run(&self, entries: &[ManifestEntry], root: Option<&Path>) {
+        for entry in entries {
+            let target = match root {
+                Some(root) if !Path::new(&entry.path).is_absolute() => root.join(&entry.path),
+                _ => Path::new(&entry.path).to_path_buf(),
+            };
+            match crate::pipeline::hash::sha256_file(&target) {
+                Ok(digest) if hex::encode(&digest) == entry.sha256 => {
+                    self.ok.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(_) => self.record(&self.modified_count, &self.modified, &entry.path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => self.record(&self.missing_count, &self.missing, &entry.path),
+                Err(e) => {
+                    tracing::warn!("manifest verify: cannot read {:?}: {}", target, e);
+                    self.record(&self.modified_count, &self.modified, &entry.path);
+                }
+            }
+            self.checked.fetch_add(1, Ordering::Relaxed);
+        }
+        *self.finished_at.lock() = Some(chrono::Utc::now().timestamp());
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_round_trip_and_verify() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::create_dir_all(tmp.path().join("2024")).unwrap();
+        std::fs::write(tmp.path().join("2024").join("a.jpg"), b"aaa").unwrap();
+        std::fs::write(tmp.path().join("b.jpg"), b"bbb").unwrap();
+        let sha = |data: &[u8]| hex::encode(crate::pipeline::hash::hash_bytes(data).1);
+
+        let mut text = String::new();
+        text.push_str(&format_line(&sha(b"aaa"), "2024/a.jpg"));
+        text.push_str(&format_line(&sha(b"old"), "b.jpg"));
+        text.push_str(&format_line(&sha(b"ccc"), "c.jpg"));
+        text.push_str(&format_line(&sha(b"ddd"), "odd\\name.jpg"));
+        assert!(text.contains("\\") && text.lines().nth(3).unwrap().starts_with('\\'));
+
+        let entries = parse(&text).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[3].path, "odd\\name.jpg");
+        assert_eq!(parse(&format!("{} *x.jpg\n\n", sha(b"x"))).unwrap()[0].path, "x.jpg");
+        assert!(parse("not a manifest").unwrap_err().contains("Line 1"));
+
+        let job = VerifyJob::new(None, entries.len() as u64, 0);
+        job.run(&entries, Some(tmp.path()));
+        let report = job.report();
+        assert!(!report.running && report.finished_at.is_some());
+        assert_eq!((report.checked, report.ok, report.missing_count, report.modified_count), (4, 1, 2, 1));
+        assert_eq!(report.modified, vec!["b.jpg"]);
+        assert_eq!(report.missing, vec!["c.jpg", "odd\\name.jpg"]);
+    }
+}
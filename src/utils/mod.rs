@@ -2,4 +2,24 @@ pub mod config;
 pub mod logging;
 pub mod exec;
 pub mod ffmpeg;
+pub mod host;
 pub mod path;
+pub mod selfcheck;
+pub mod delete_hold;
+pub mod backup;
+pub mod reference;
+pub mod scheduler;
+pub mod manifest;
+pub mod recovery;
+pub mod derived_gc;
+pub mod storage;
+pub mod ics;
+pub mod feed;
+pub mod cast;
+pub mod site_export;
+pub mod smtp;
+pub mod digest;
+pub mod report;
+pub mod fixtures;
+pub mod env_settings;
+pub mod xmp;
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::path::{Path, PathBuf};
 
 use crate::AppPaths;
@@ -11,26 +12,108 @@ use crate::AppPaths;
 /// the bind-mounted directory (e.g. `/photos`). If `AppPaths::root_host` is
 /// configured, we strip that prefix and substitute `AppPaths::root` instead.
 pub fn resolve_asset_path(raw: &str, paths: &AppPaths) -> PathBuf {
-    let raw_path = PathBuf::from(raw);
+    let raw = strip_verbatim(raw);
+    let raw_path = long_path(Path::new(raw.as_ref()));
     if raw_path.exists() {
         return raw_path;
     }
 
     if let Some(host_root) = &paths.root_host {
-        if let Some(mapped) = map_host_to_container(raw, host_root, &paths.root) {
-            if mapped.exists() {
-                return mapped;
-            }
+        if let Some(mapped) = map_host_to_container(&raw, host_root, &paths.root) {
             // Even if it does not exist yet, return the mapped path so the
             // caller can provide a meaningful error that references the
             // container path.
-            return mapped;
+            return long_path(&mapped);
         }
     }
 
     raw_path
 }
 
+/// Paths this long or longer need the `\\?\` form on Windows. MAX_PATH is 260, but
+/// directories are limited to 248 so a file name still fits.
+const WINDOWS_PATH_LIMIT: usize = 248;
+
+/// The plain spelling of a Windows path: `\\?\C:\x` becomes `C:\x` and `\\?\UNC\server\share`
+/// becomes `\\server\share`. Paths are stored without the prefix so a file has one row however
+/// it was reached. Anything else is returned unchanged.
+pub fn strip_verbatim(path: &str) -> Cow<'_, str> {
+    if let Some(unc) = path.strip_prefix(r"\\?\UNC\") {
+        return Cow::Owned(format!(r"\\{}", unc));
+    }
+    match path.strip_prefix(r"\\?\") {
+        // Only drive paths; other verbatim forms (volume GUIDs) have no plain spelling
+        Some(rest) if rest.as_bytes().get(1) == Some(&b':') => Cow::Borrowed(rest),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// `strip_verbatim` for a `Path`
+pub fn plain_path(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(s) => PathBuf::from(strip_verbatim(s).as_ref()),
+        None => path.to_path_buf(),
+    }
+}
+
+/// `\\?\` form of an absolute drive or UNC path, which Windows opens regardless of length.
+/// None for relative paths, paths with `.` or `..` (the prefix turns off their resolution)
+/// and paths that already have it.
+pub fn to_verbatim(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") {
+        return None;
+    }
+    let path = path.replace('/', "\\");
+    if path.split('\\').any(|c| c == "." || c == "..") {
+        return None;
+    }
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        // \\server\share\...
+        let mut parts = unc.splitn(3, '\\');
+        let (server, share) = (parts.next()?, parts.next()?);
+        if server.is_empty() || share.is_empty() {
+            return None;
+        }
+        return Some(format!(r"\\?\UNC\{}", unc));
+    }
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\' {
+        return Some(format!(r"\\?\{}", path));
+    }
+    None
+}
+
+/// A path Windows can open even past MAX_PATH, for deep folder trees and network shares.
+/// Elsewhere, and for paths short enough already, the path is returned as is.
+pub fn long_path(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        if let Some(verbatim) = path.to_str().filter(|p| p.len() >= WINDOWS_PATH_LIMIT).and_then(to_verbatim) {
+            return PathBuf::from(verbatim);
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Probe whether new files can be created in `dir` (write + remove a hidden
+/// marker file). Missing directories report as writable since we can't tell yet.
+pub fn is_dir_writable(dir: &Path) -> bool {
+    if !dir.is_dir() {
+        return true;
+    }
+    let probe = dir.join(format!(".seen-write-probe-{}", std::process::id()));
+    match std::fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            true
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            let _ = std::fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
 fn map_host_to_container(raw: &str, host_root: &str, container_root: &Path) -> Option<PathBuf> {
     let raw_path = Path::new(raw);
     let host_root_path = Path::new(host_root);
@@ -88,6 +171,29 @@ mod tests {
         assert_eq!(resolved, container_file);
     }
 
+    #[test]
+    fn test_windows_verbatim_paths() {
+        assert_eq!(strip_verbatim(r"\\?\C:\Photos\a.jpg"), r"C:\Photos\a.jpg");
+        assert_eq!(strip_verbatim(r"\\?\UNC\nas\photos\a.jpg"), r"\\nas\photos\a.jpg");
+        assert_eq!(strip_verbatim(r"\\?\Volume{1234}\a.jpg"), r"\\?\Volume{1234}\a.jpg");
+        assert_eq!(strip_verbatim("/photos/a.jpg"), "/photos/a.jpg");
+
+        assert_eq!(to_verbatim(r"C:\Photos\a.jpg").as_deref(), Some(r"\\?\C:\Photos\a.jpg"));
+        assert_eq!(to_verbatim("C:/Photos/a.jpg").as_deref(), Some(r"\\?\C:\Photos\a.jpg"));
+        assert_eq!(to_verbatim(r"\\nas\photos\2024\a.jpg").as_deref(), Some(r"\\?\UNC\nas\photos\2024\a.jpg"));
+        assert_eq!(to_verbatim(r"\\nas"), None);
+        assert_eq!(to_verbatim(r"C:\Photos\..\a.jpg"), None);
+        assert_eq!(to_verbatim(r"Photos\a.jpg"), None);
+        assert_eq!(to_verbatim(r"\\?\C:\a.jpg"), None);
+        assert_eq!(to_verbatim("/photos/a.jpg"), None);
+        // Round trip
+        let deep = format!(r"\\nas\photos\{}\a.jpg", "sub\\".repeat(60));
+        assert_eq!(strip_verbatim(&to_verbatim(&deep).unwrap()), deep);
+
+        // Short paths and non-Windows hosts keep the path as is
+        assert_eq!(long_path(Path::new("/photos/a.jpg")), PathBuf::from("/photos/a.jpg"));
+    }
+
     #[test]
     fn test_resolve_asset_path_nonexistent() {
         let tmp = TempDir::new().unwrap();
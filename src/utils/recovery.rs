@@ -0,0 +1,136 @@
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// What startup recovery repaired after an unclean shutdown
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecoveryReport {
+    /// Assets committed without their search index row
+    pub fts_rows_restored: usize,
+    /// Face embeddings whose asset no longer exists
+    pub orphaned_faces_removed: usize,
+    /// Files discovered but never committed, sent through the pipeline again
+    pub pending_jobs_requeued: usize,
+    /// `*.partial.*` files from interrupted transcodes and highlight renders
+    pub temp_files_removed: usize,
+    /// Zero-byte thumbnails and previews from interrupted writes
+    pub empty_files_removed: usize,
+    pub bytes_freed: u64,
+    /// When the derived directory sweep finished (Unix seconds); it runs in the background
+    pub derived_swept_at: Option<i64>,
+}
+
+impl RecoveryReport {
+    pub fn repaired(&self) -> usize {
+        self.fts_rows_restored + self.orphaned_faces_removed + self.pending_jobs_requeued + self.temp_files_removed + self.empty_files_removed
+    }
+}
+
+/// Database repairs; cheap enough to run before the server starts
+pub fn repair_database(conn: &Connection, report: &mut RecoveryReport) -> Result<()> {
+    // fts_assets is contentless, but its rowids can still be listed
+    report.fts_rows_restored = conn.execute(
+        "INSERT INTO fts_assets(rowid, filename, dirname, path)
+         SELECT id, filename, dirname, path FROM assets WHERE id NOT IN (SELECT rowid FROM fts_assets)",
+        [],
+    )?;
+    report.orphaned_faces_removed = conn.execute(
+        "DELETE FROM face_embeddings WHERE asset_id NOT IN (SELECT id FROM assets)",
+        [],
+    )?;
+    if report.fts_rows_restored > 0 {
+        tracing::warn!("recovery: restored search index rows for {} assets", report.fts_rows_restored);
+    }
+    if report.orphaned_faces_removed > 0 {
+        tracing::warn!("recovery: removed {} face embeddings of deleted assets", report.orphaned_faces_removed);
+    }
+    Ok(())
+}
+
+/// Remove leftovers of derived-file writes cut short by a crash.
+/// Only files last written before `booted_at` are touched, so the sweep can run alongside the workers.
+/// Returns what it removed; the caller merges it into the shared report.
+pub fn sweep_derived(derived: &Path, booted_at: SystemTime) -> RecoveryReport {
+    let mut report = RecoveryReport::default();
+    for entry in walkdir::WalkDir::new(super::path::long_path(derived)).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let name = entry.file_name().to_string_lossy();
+        let partial = name.contains(".partial.");
+        let len = meta.len();
+        if (!partial && len > 0) || meta.modified().map_or(true, |m| m >= booted_at) {
+            continue;
+        }
+        match std::fs::remove_file(entry.path()) {
+            Ok(()) => {
+                if partial {
+                    report.temp_files_removed += 1;
+                } else {
+                    report.empty_files_removed += 1;
+                }
+                report.bytes_freed += len;
+            }
+            Err(e) => tracing::warn!("recovery: could not remove {:?}: {}", entry.path(), e),
+        }
+    }
+    report.derived_swept_at = Some(chrono::Utc::now().timestamp());
+    if report.temp_files_removed + report.empty_files_removed > 0 {
+        tracing::warn!(
+            "recovery: removed {} partial and {} empty derived files ({} bytes)",
+            report.temp_files_removed, report.empty_files_removed, report.bytes_freed
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_startup_recovery() {
+        let tmp = TempDir::new().unwrap();
+        let conn = crate::db::open_or_create(tmp.path().join("test.db")).unwrap();
+        // Committed rows whose FTS insert never happened
+        for name in ["a.jpg", "b.jpg"] {
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags)
+                 VALUES (?1, '/p', ?2, 'jpg', 1, 0, 0, 'image/jpeg', 0)",
+                rusqlite::params![format!("/p/{}", name), name],
+            ).unwrap();
+        }
+        conn.execute("INSERT INTO fts_assets(rowid, filename, dirname, path) VALUES (1, 'a.jpg', '/p', '/p/a.jpg')", []).unwrap();
+        conn.pragma_update(None, "foreign_keys", false).unwrap();
+        conn.execute("INSERT INTO face_embeddings (asset_id, embedding_blob, bbox_json, confidence) VALUES (99, x'00', '{}', 0.9)", []).unwrap();
+        conn.execute("INSERT INTO face_embeddings (asset_id, embedding_blob, bbox_json, confidence) VALUES (1, x'00', '{}', 0.9)", []).unwrap();
+
+        let mut report = RecoveryReport::default();
+        repair_database(&conn, &mut report).unwrap();
+        assert_eq!((report.fts_rows_restored, report.orphaned_faces_removed), (1, 1));
+        let hits: i64 = conn.query_row("SELECT COUNT(*) FROM fts_assets WHERE fts_assets MATCH 'b'", [], |r| r.get(0)).unwrap();
+        assert_eq!(hits, 1);
+        let mut again = RecoveryReport::default();
+        repair_database(&conn, &mut again).unwrap();
+        assert_eq!(again.repaired(), 0);
+
+        let derived = tmp.path().join("derived");
+        std::fs::create_dir_all(derived.join("ab")).unwrap();
+        std::fs::write(derived.join("ab").join("abc.partial.mp4"), b"half").unwrap();
+        std::fs::write(derived.join("ab").join("abc-256.webp"), b"").unwrap();
+        std::fs::write(derived.join("ab").join("abd-256.webp"), b"webp").unwrap();
+        // Files written since boot may belong to a running worker
+        let early = sweep_derived(&derived, SystemTime::UNIX_EPOCH);
+        assert_eq!(early.temp_files_removed + early.empty_files_removed, 0);
+        let swept = sweep_derived(&derived, SystemTime::now() + std::time::Duration::from_secs(5));
+        assert_eq!((swept.temp_files_removed, swept.empty_files_removed, swept.bytes_freed), (1, 1, 4));
+        assert!(derived.join("ab").join("abd-256.webp").exists());
+        assert!(swept.derived_swept_at.is_some());
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::db::query::AccessScope;
+
+/// How long an uploaded reference image stays searchable unless FLASH_REFERENCE_TTL_SECS says otherwise
+pub const DEFAULT_TTL_SECS: i64 = 30 * 60;
+/// Oldest sessions are dropped once this many are open
+const MAX_IMAGES: usize = 64;
+
+/// A temporary image used as a search query. It is never ingested into the library.
+#[derive(Clone, Debug)]
+pub struct ReferenceImage {
+    pub id: String,
+    pub path: PathBuf,
+    pub scope: AccessScope,
+    pub sha256: Vec<u8>,
+    pub xxh64: i64,
+    pub size_bytes: i64,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+/// Reference images live as files under `<data>/reference`, indexed in memory.
+/// Files left over from a previous run are unreachable and removed on startup.
+pub struct ReferenceStore {
+    dir: PathBuf,
+    ttl_secs: i64,
+    images: Mutex<HashMap<String, ReferenceImage>>,
+}
+
+impl ReferenceStore {
+    pub fn new(dir: PathBuf) -> Self {
+        let ttl_secs = std::env::var("FLASH_REFERENCE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v: &i64| *v > 0)
+            .unwrap_or(DEFAULT_TTL_SECS);
+        let _ = std::fs::remove_dir_all(&dir);
+        Self { dir, ttl_secs, images: Mutex::new(HashMap::new()) }
+    }
+
+    /// Store an uploaded image under a fresh random id
+    pub fn insert(&self, scope: AccessScope, ext: &str, bytes: &[u8], now: i64) -> Result<ReferenceImage> {
+        let mut raw = [0u8; 16];
+        getrandom::fill(&mut raw).map_err(|e| anyhow::anyhow!("Failed to generate id: {}", e))?;
+        let id = hex::encode(raw);
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("{}.{}", id, ext));
+        std::fs::write(&path, bytes)?;
+
+        let (xxh64, sha256) = crate::pipeline::hash::hash_bytes(bytes);
+        let image = ReferenceImage {
+            id: id.clone(),
+            path,
+            scope,
+            sha256,
+            xxh64,
+            size_bytes: bytes.len() as i64,
+            created_at: now,
+            expires_at: now + self.ttl_secs,
+        };
+
+        let evicted = {
+            let mut images = self.images.lock();
+            let mut evicted = Vec::new();
+            while images.len() >= MAX_IMAGES {
+                let Some(oldest) = images.values().min_by_key(|i| i.created_at).map(|i| i.id.clone()) else { break };
+                evicted.extend(images.remove(&oldest));
+            }
+            images.insert(id, image.clone());
+            evicted
+        };
+        for old in evicted {
+            let _ = std::fs::remove_file(&old.path);
+        }
+        Ok(image)
+    }
+
+    /// Look up an image; other users' uploads and expired ones are invisible
+    pub fn get(&self, id: &str, scope: AccessScope, now: i64) -> Option<ReferenceImage> {
+        self.images.lock()
+            .get(id)
+            .filter(|i| i.scope == scope && i.expires_at > now)
+            .cloned()
+    }
+
+    pub fn remove(&self, id: &str, scope: AccessScope) -> bool {
+        let removed = {
+            let mut images = self.images.lock();
+            match images.get(id) {
+                Some(image) if image.scope == scope => images.remove(id),
+                _ => None,
+            }
+        };
+        match removed {
+            Some(image) => {
+                let _ = std::fs::remove_file(&image.path);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop expired images and their files; returns how many were removed
+    pub fn purge_expired(&self, now: i64) -> usize {
+        let expired: Vec<ReferenceImage> = {
+            let mut images = self.images.lock();
+            let ids: Vec<String> = images.values().filter(|i| i.expires_at <= now).map(|i| i.id.clone()).collect();
+            ids.iter().filter_map(|id| images.remove(id)).collect()
+        };
+        for image in &expired {
+            let _ = std::fs::remove_file(&image.path);
+        }
+        expired.len()
+    }
+}
+
+pub fn start_purger(store: Arc<ReferenceStore>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let n = store.purge_expired(chrono::Utc::now().timestamp());
+            if n > 0 {
+                tracing::debug!("Purged {} expired reference images", n);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reference_store_scope_and_expiry() {
+        let tmp = TempDir::new().unwrap();
+        let store = ReferenceStore::new(tmp.path().join("reference"));
+        let image = store.insert(AccessScope::User(1), "jpg", b"jpeg bytes", 1000).unwrap();
+        assert!(image.path.exists());
+        assert_eq!(image.expires_at, 1000 + store.ttl_secs);
+
+        assert!(store.get(&image.id, AccessScope::User(1), 1001).is_some());
+        assert!(store.get(&image.id, AccessScope::User(2), 1001).is_none());
+        assert!(store.get(&image.id, AccessScope::User(1), image.expires_at).is_none());
+        assert!(!store.remove(&image.id, AccessScope::User(2)));
+
+        assert_eq!(store.purge_expired(image.expires_at - 1), 0);
+        assert_eq!(store.purge_expired(image.expires_at), 1);
+        assert!(!image.path.exists());
+
+        let other = store.insert(AccessScope::All, "png", b"png bytes", 2000).unwrap();
+        assert!(store.remove(&other.id, AccessScope::All));
+        assert!(!other.path.exists());
+    }
+}
@@ -0,0 +1,466 @@
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+use crate::utils::site_export::escape_html;
+use crate::utils::storage::{self, PathUsage, StorageReport};
+
+/// Rows in the camera, format and biggest-file lists
+const TOP: usize = 10;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Totals {
+    pub assets: i64,
+    pub images: i64,
+    pub videos: i64,
+    pub bytes: i64,
+    pub albums: i64,
+    pub tags: i64,
+    pub persons: i64,
+    pub faces: i64,
+}
+
+/// Assets taken (or, without a capture date, last modified) in one year
+#[derive(Debug, Clone, Serialize)]
+pub struct YearGrowth {
+    pub year: i32,
+    pub assets: i64,
+    pub bytes: i64,
+    /// Assets from this year and all before it
+    pub cumulative_assets: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CameraCount {
+    pub make: Option<String>,
+    pub model: String,
+    pub assets: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FormatCount {
+    pub mime: String,
+    pub assets: i64,
+    pub bytes: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BigFile {
+    pub id: i64,
+    pub path: String,
+    pub mime: String,
+    pub size_bytes: i64,
+}
+
+/// Files with the same content and size indexed more than once
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DuplicateEstimate {
+    /// Distinct contents stored more than once
+    pub groups: i64,
+    /// Copies beyond the first of each
+    pub extra_files: i64,
+    /// Space the extra copies take
+    pub extra_bytes: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryReport {
+    pub generated_at: i64,
+    pub totals: Totals,
+    pub growth: Vec<YearGrowth>,
+    pub cameras: Vec<CameraCount>,
+    pub formats: Vec<FormatCount>,
+    pub biggest_files: Vec<BigFile>,
+    pub duplicates: DuplicateEstimate,
+    pub scan_paths: Vec<PathUsage>,
+    /// The storage sizer's latest report, when it has run
+    pub storage: Option<StorageReport>,
+}
+
+fn count(conn: &Connection, sql: &str) -> Result<i64> {
+    Ok(conn.query_row(sql, [], |r| r.get(0))?)
+}
+
+/// Gather the report; `storage` is attached as is
+pub fn build(conn: &Connection, storage: Option<StorageReport>) -> Result<LibraryReport> {
+    let (assets, images, videos, bytes) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(mime LIKE 'image/%'), 0), COALESCE(SUM(mime LIKE 'video/%'), 0), COALESCE(SUM(size_bytes), 0) FROM assets",
+        [],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+    )?;
+    let totals = Totals {
+        assets,
+        images,
+        videos,
+        bytes,
+        albums: count(conn, "SELECT COUNT(*) FROM albums")?,
+        tags: count(conn, "SELECT COUNT(*) FROM tags")?,
+        persons: count(conn, "SELECT COUNT(*) FROM persons")?,
+        faces: count(conn, "SELECT COUNT(*) FROM face_embeddings")?,
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT CAST(strftime('%Y', COALESCE(taken_at, mtime_ns / 1000000000), 'unixepoch') AS INTEGER) AS year,
+                COUNT(*), COALESCE(SUM(size_bytes), 0)
+         FROM assets GROUP BY year ORDER BY year",
+    )?;
+    let mut cumulative = 0;
+    let growth = stmt
+        .query_map([], |r| Ok((r.get::<_, Option<i32>>(0)?, r.get::<_, i64>(1)?, r.get::<_, i64>(2)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|(year, assets, bytes)| {
+            cumulative += assets;
+            year.map(|year| YearGrowth { year, assets, bytes, cumulative_assets: cumulative })
+        })
+        .collect();
+
+    let mut stmt = conn.prepare(
+        "SELECT MAX(TRIM(camera_make)), TRIM(camera_model) AS model, COUNT(*) AS n FROM assets
+         WHERE camera_model IS NOT NULL AND TRIM(camera_model) != ''
+         GROUP BY model COLLATE NOCASE ORDER BY n DESC, model LIMIT ?1",
+    )?;
+    let cameras = stmt
+        .query_map([TOP as i64], |r| Ok(CameraCount { make: r.get(0)?, model: r.get(1)?, assets: r.get(2)? }))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT mime, COUNT(*) AS n, COALESCE(SUM(size_bytes), 0) FROM assets GROUP BY mime ORDER BY n DESC, mime LIMIT ?1",
+    )?;
+    let formats = stmt
+        .query_map([TOP as i64], |r| Ok(FormatCount { mime: r.get(0)?, assets: r.get(1)?, bytes: r.get(2)? }))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare("SELECT id, path, mime, size_bytes FROM assets ORDER BY size_bytes DESC, id LIMIT ?1")?;
+    let biggest_files = stmt
+        .query_map([TOP as i64], |r| Ok(BigFile { id: r.get(0)?, path: r.get(1)?, mime: r.get(2)?, size_bytes: r.get(3)? }))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let duplicates = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(n - 1), 0), COALESCE(SUM((n - 1) * size_bytes), 0) FROM (
+           SELECT COUNT(*) AS n, size_bytes FROM assets
+           WHERE sha256 IS NOT NULL AND length(sha256) > 0
+           GROUP BY sha256, size_bytes HAVING n > 1
+         )",
+        [],
+        |r| Ok(DuplicateEstimate { groups: r.get(0)?, extra_files: r.get(1)?, extra_bytes: r.get(2)? }),
+    )?;
+
+    Ok(LibraryReport {
+        generated_at: chrono::Utc::now().timestamp(),
+        totals,
+        growth,
+        cameras,
+        formats,
+        biggest_files,
+        duplicates,
+        scan_paths: storage::measure_originals(conn)?,
+        storage,
+    })
+}
+
+pub fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// A titled table, the shape both renderers draw
+struct Section {
+    title: &'static str,
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+fn sections(report: &LibraryReport) -> Vec<Section> {
+    let t = &report.totals;
+    let mut overview = vec![
+        vec!["Assets".to_string(), t.assets.to_string()],
+        vec!["Photos".to_string(), t.images.to_string()],
+        vec!["Videos".to_string(), t.videos.to_string()],
+        vec!["Originals".to_string(), format_bytes(t.bytes)],
+        vec!["Albums".to_string(), t.albums.to_string()],
+        vec!["Tags".to_string(), t.tags.to_string()],
+        vec!["People".to_string(), format!("{} ({} faces)", t.persons, t.faces)],
+    ];
+    if let Some(storage) = &report.storage {
+        overview.push(vec!["Database".to_string(), format_bytes((storage.database.bytes + storage.database.wal_bytes) as i64)]);
+        overview.push(vec!["Thumbnails and previews".to_string(), format_bytes(storage.derived.total_bytes as i64)]);
+    }
+    let d = &report.duplicates;
+    vec![
+        Section { title: "Overview", columns: vec!["", ""], rows: overview },
+        Section {
+            title: "Growth by year",
+            columns: vec!["Year", "Assets", "Size", "Total assets"],
+            rows: report
+                .growth
+                .iter()
+                .map(|g| vec![g.year.to_string(), g.assets.to_string(), format_bytes(g.bytes), g.cumulative_assets.to_string()])
+                .collect(),
+        },
+        Section {
+            title: "Top cameras",
+            columns: vec!["Camera", "Assets"],
+            rows: report
+                .cameras
+                .iter()
+                .map(|c| {
+                    let name = match &c.make {
+                        // Models often repeat the make ("Canon EOS R6")
+                        Some(make) if !c.model.to_lowercase().starts_with(&make.to_lowercase()) => format!("{} {}", make, c.model),
+                        _ => c.model.clone(),
+                    };
+                    vec![name, c.assets.to_string()]
+                })
+                .collect(),
+        },
+        Section {
+            title: "Formats",
+            columns: vec!["Type", "Assets", "Size"],
+            rows: report.formats.iter().map(|f| vec![f.mime.clone(), f.assets.to_string(), format_bytes(f.bytes)]).collect(),
+        },
+        Section {
+            title: "Biggest files",
+            columns: vec!["Path", "Size"],
+            rows: report.biggest_files.iter().map(|f| vec![f.path.clone(), format_bytes(f.size_bytes)]).collect(),
+        },
+        Section {
+            title: "Duplicates",
+            columns: vec!["", ""],
+            rows: vec![
+                vec!["Files stored more than once".to_string(), d.groups.to_string()],
+                vec!["Extra copies".to_string(), d.extra_files.to_string()],
+                vec!["Space they take".to_string(), format_bytes(d.extra_bytes)],
+            ],
+        },
+        Section {
+            title: "Scan paths",
+            columns: vec!["Path", "Assets", "Size"],
+            rows: report.scan_paths.iter().map(|p| vec![p.path.clone(), p.assets.to_string(), format_bytes(p.bytes)]).collect(),
+        },
+    ]
+}
+
+fn generated_label(report: &LibraryReport) -> String {
+    chrono::DateTime::from_timestamp(report.generated_at, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+/// A self-contained HTML page
+pub fn render_html(report: &LibraryReport) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Library report</title>\n<style>\
+         body{font-family:sans-serif;color:#222;max-width:900px;margin:2em auto;padding:0 1em}\
+         table{border-collapse:collapse;width:100%;margin-bottom:2em}\
+         th,td{text-align:left;padding:4px 8px;border-bottom:1px solid #ddd}\
+         td:not(:first-child),th:not(:first-child){text-align:right;white-space:nowrap}\
+         td:first-child{word-break:break-all}\
+         </style></head><body>\n",
+    );
+    html.push_str(&format!("<h1>Library report</h1>\n<p>Generated {}</p>\n", escape_html(&generated_label(report))));
+    for section in sections(report) {
+        html.push_str(&format!("<h2>{}</h2>\n<table>\n", escape_html(section.title)));
+        if section.columns.iter().any(|c| !c.is_empty()) {
+            html.push_str("<tr>");
+            for column in &section.columns {
+                html.push_str(&format!("<th>{}</th>", escape_html(column)));
+            }
+            html.push_str("</tr>\n");
+        }
+        if section.rows.is_empty() {
+            html.push_str(&format!("<tr><td colspan=\"{}\">None</td></tr>\n", section.columns.len()));
+        }
+        for row in &section.rows {
+            html.push_str("<tr>");
+            for cell in row {
+                html.push_str(&format!("<td>{}</td>", escape_html(cell)));
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</body></html>\n");
+    html
+}
+
+/// Text in a PDF string literal: WinAnsi bytes, anything outside Latin-1 as '?'
+fn pdf_text(text: &str, out: &mut Vec<u8>) {
+    out.push(b'(');
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push(b'\\');
+                out.push(c as u8);
+            }
+            c if (c as u32) < 0x20 => out.push(b' '),
+            c if (c as u32) <= 0xff => out.push(c as u32 as u8),
+            _ => out.push(b'?'),
+        }
+    }
+    out.push(b')');
+}
+
+/// Keep the end of a long path, which is the part that tells files apart
+fn clip(text: &str, max: usize) -> String {
+    let count = text.chars().count();
+    if count <= max {
+        text.to_string()
+    } else {
+        format!("...{}", text.chars().skip(count - max + 3).collect::<String>())
+    }
+}
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+
+/// A text run: font resource, size, x and the text
+type Run = (&'static str, f32, f32, String);
+
+/// Draw one line `height` below the previous, starting a new page when it wouldn't fit
+fn line(page: &mut Vec<u8>, y: &mut f32, pages: &mut Vec<Vec<u8>>, height: f32, runs: &[Run]) {
+    if *y - height < MARGIN {
+        pages.push(std::mem::take(page));
+        *y = PAGE_HEIGHT - MARGIN;
+    }
+    *y -= height;
+    for (font, size, x, text) in runs {
+        page.extend_from_slice(format!("BT /{} {} Tf {} {} Td ", font, size, x, y).as_bytes());
+        pdf_text(text, page);
+        page.extend_from_slice(b" Tj ET\n");
+    }
+}
+
+/// A4 pages of text in the built-in Helvetica fonts, so no font has to be embedded
+pub fn render_pdf(report: &LibraryReport) -> Vec<u8> {
+    // Content streams of the filled pages, then the one being drawn
+    let mut pages: Vec<Vec<u8>> = Vec::new();
+    let mut page = Vec::new();
+    let mut y = PAGE_HEIGHT - MARGIN;
+
+    line(&mut page, &mut y, &mut pages, 22.0, &[("F2", 18.0, MARGIN, "Library report".to_string())]);
+    line(&mut page, &mut y, &mut pages, 16.0, &[("F1", 9.0, MARGIN, format!("Generated {}", generated_label(report)))]);
+    for section in sections(report) {
+        line(&mut page, &mut y, &mut pages, 28.0, &[("F2", 13.0, MARGIN, section.title.to_string())]);
+        // The first column takes what the others leave
+        let others = section.columns.len().saturating_sub(1) as f32;
+        let first_width = PAGE_WIDTH - 2.0 * MARGIN - others * 80.0;
+        let x = |i: usize| if i == 0 { MARGIN } else { MARGIN + first_width + (i - 1) as f32 * 80.0 };
+        let first_chars = (first_width / 5.0) as usize;
+        if section.columns.iter().any(|c| !c.is_empty()) {
+            let runs: Vec<Run> = section.columns.iter().enumerate().map(|(i, c)| ("F2", 9.0, x(i), c.to_string())).collect();
+            line(&mut page, &mut y, &mut pages, 14.0, &runs);
+        }
+        if section.rows.is_empty() {
+            line(&mut page, &mut y, &mut pages, 13.0, &[("F1", 9.0, MARGIN, "None".to_string())]);
+        }
+        for row in &section.rows {
+            let runs: Vec<Run> = row
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| ("F1", 9.0, x(i), if i == 0 { clip(cell, first_chars) } else { cell.clone() }))
+                .collect();
+            line(&mut page, &mut y, &mut pages, 13.0, &runs);
+        }
+    }
+    pages.push(page);
+
+    // Objects: 1 catalog, 2 page tree, 3-4 fonts, then a page and its content stream per page
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        Vec::new(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+    ];
+    let mut kids = Vec::new();
+    for content in pages {
+        let page_id = objects.len() + 1;
+        kids.push(format!("{} 0 R", page_id));
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page_id + 1
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(&content);
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+    }
+    objects[1] = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), kids.len()).into_bytes();
+
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).as_bytes());
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    #[test]
+    fn test_library_report() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::apply_schema(&conn).unwrap();
+        let assets = [
+            ("/p/a.jpg", "image/jpeg", 3_000_000, Some(1_600_000_000), Some("Canon"), Some("Canon EOS R6"), "aa"),
+            ("/p/copy/a.jpg", "image/jpeg", 3_000_000, Some(1_600_000_000), Some("Canon"), Some("Canon EOS R6"), "aa"),
+            ("/p/b (1).mp4", "video/mp4", 90_000_000, Some(1_700_000_000), Some("Apple"), Some("iPhone 13"), "bb"),
+            ("/p/c.png", "image/png", 1_000, None, None, None, "cc"),
+        ];
+        for (path, mime, size, taken_at, make, model, sha) in assets {
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, taken_at, camera_make, camera_model, sha256)
+                 VALUES (?1, '/p', ?1, 'x', ?2, 1500000000000000000, 0, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![path, size, mime, taken_at, make, model, sha.as_bytes()],
+            ).unwrap();
+        }
+
+        let report = build(&conn, None).unwrap();
+        assert_eq!((report.totals.assets, report.totals.images, report.totals.videos), (4, 3, 1));
+        assert_eq!(report.growth.iter().map(|g| (g.year, g.assets, g.cumulative_assets)).collect::<Vec<_>>(), vec![(2017, 1, 1), (2020, 2, 3), (2023, 1, 4)]);
+        assert_eq!((report.cameras[0].model.as_str(), report.cameras[0].assets), ("Canon EOS R6", 2));
+        assert_eq!(report.biggest_files[0].path, "/p/b (1).mp4");
+        assert_eq!((report.duplicates.groups, report.duplicates.extra_files, report.duplicates.extra_bytes), (1, 1, 3_000_000));
+
+        let html = render_html(&report);
+        assert!(html.contains("<td>Canon EOS R6</td><td>2</td>"));
+        assert!(html.contains("<td>iPhone 13</td>") || html.contains("<td>Apple iPhone 13</td>"));
+        assert!(html.contains("<td>Space they take</td><td>2.9 MB</td>"));
+
+        let pdf = render_pdf(&report);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with(b"%PDF-1.4") && text.ends_with("%%EOF\n"));
+        assert!(text.contains("(/p/b \\(1\\).mp4) Tj"));
+        // startxref points at the cross-reference table
+        let start: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        assert!(pdf[start..].starts_with(b"xref\n"));
+        assert_eq!(format_bytes(512), "512 B");
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDateTime, TimeZone, Timelike};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::{AppState, db};
+
+/// A five-field cron expression (`minute hour day-of-month month day-of-week`), evaluated in local time.
+/// Fields accept `*`, numbers, ranges (`1-5`), steps (`*/15`, `8-18/2`) and comma lists.
+/// `@hourly`, `@daily`, `@weekly` and `@monthly` are accepted as shorthands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("Expected 5 fields (minute hour day month weekday), got {}", fields.len()));
+        }
+        // Sunday may be written as 0 or 7
+        let weekdays = parse_field(fields[4], 0, 7, "weekday")?;
+        let weekdays = (weekdays | (weekdays >> 7)) & 0x7f;
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59, "minute")?,
+            hours: parse_field(fields[1], 0, 23, "hour")?,
+            days: parse_field(fields[2], 1, 31, "day")?,
+            months: parse_field(fields[3], 1, 12, "month")?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    fn day_matches(&self, date: chrono::NaiveDate) -> bool {
+        if !bit(self.months, date.month()) {
+            return false;
+        }
+        let day = bit(self.days, date.day());
+        let weekday = bit(self.weekdays, date.weekday().num_days_from_sunday());
+        // Like cron: when both are restricted, either one matching is enough
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day,
+            (true, false) => weekday,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// True if the schedule fires during the minute containing `at`
+    pub fn matches(&self, at: NaiveDateTime) -> bool {
+        self.day_matches(at.date()) && bit(self.hours, at.hour()) && bit(self.minutes, at.minute())
+    }
+
+    /// First firing strictly after `after`, if any within the next few years
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        // Four years covers schedules that only fire on February 29th
+        for offset in 0..(4 * 366 + 1) {
+            let date = start.date() + ChronoDuration::days(offset);
+            if !self.day_matches(date) {
+                continue;
+            }
+            for hour in (0..24).filter(|h| bit(self.hours, *h)) {
+                for minute in (0..60).filter(|m| bit(self.minutes, *m)) {
+                    let candidate = date.and_hms_opt(hour, minute, 0)?;
+                    if candidate >= start {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+fn bit(mask: u64, n: u32) -> bool {
+    mask & (1u64 << n) != 0
+}
+
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid {} field '{}'", name, field);
+    let number = |s: &str| -> Result<u32, String> {
+        s.parse::<u32>().ok().filter(|n| (min..=max).contains(n)).ok_or_else(|| format!("{} '{}' is outside {}-{}", name, s, min, max))
+    };
+    let mut mask = 0u64;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0).ok_or_else(invalid)?),
+            None => (item, 1),
+        };
+        let (lo, hi) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((lo, hi)) => (number(lo)?, number(hi)?),
+                // `5/15` means every 15 starting at 5
+                None if step > 1 => (number(range)?, max),
+                None => {
+                    let n = number(range)?;
+                    (n, n)
+                }
+            },
+        };
+        if lo > hi {
+            return Err(invalid());
+        }
+        for n in (lo..=hi).step_by(step as usize) {
+            mask |= 1u64 << n;
+        }
+    }
+    Ok(mask)
+}
+
+/// Next firing of `schedule` after `now`, as a unix timestamp
+pub fn next_run_at(schedule: &Schedule, now: chrono::DateTime<Local>) -> Option<i64> {
+    let next = schedule.next_after(now.naive_local())?;
+    Local.from_local_datetime(&next).earliest().map(|t| t.timestamp())
+}
+
+/// Check every scan path's schedule twice a minute and start the scans that are due.
+/// Runs missed while the server was down are not made up.
+pub fn start(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+            let now = Local::now();
+            let minute_start = now.timestamp() - now.second() as i64;
+
+            let due = tokio::task::spawn_blocking({
+                let pool = state.pool.clone();
+                move || -> anyhow::Result<Vec<String>> {
+                    let conn = pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+                    let mut due = Vec::new();
+                    for (path, expr, last_run) in db::query::get_scan_path_schedules(&conn)? {
+                        let schedule = match Schedule::parse(&expr) {
+                            Ok(schedule) => schedule,
+                            Err(e) => {
+                                tracing::warn!("Ignoring invalid schedule '{}' for {}: {}", expr, path, e);
+                                continue;
+                            }
+                        };
+                        if schedule.matches(now.naive_local()) && last_run.is_none_or(|t| t < minute_start) {
+                            db::writer::mark_scan_path_scheduled(&conn, &path, now.timestamp())?;
+                            due.push(path);
+                        }
+                    }
+                    Ok(due)
+                }
+            }).await;
+
+            match due {
+                Ok(Ok(paths)) => {
+                    for path in paths {
+                        if crate::api::handlers::start_path_scan(&state, path.clone(), false) {
+                            tracing::info!("scheduled scan started for {:?}", path);
+                        } else {
+                            tracing::debug!("scheduled scan skipped, {:?} is already being scanned", path);
+                        }
+                    }
+                }
+                Ok(Err(e)) => tracing::warn!("Failed to check scan schedules: {}", e),
+                Err(e) => tracing::warn!("Scan scheduler task failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_schedule_parse_and_match() {
+        let nightly = Schedule::parse("0 2 * * *").unwrap();
+        assert!(nightly.matches(at("2024-03-05 02:00")));
+        assert!(!nightly.matches(at("2024-03-05 02:01")));
+        assert_eq!(nightly.next_after(at("2024-03-05 02:00")), Some(at("2024-03-06 02:00")));
+        assert_eq!(nightly.next_after(at("2024-03-05 01:59")), Some(at("2024-03-05 02:00")));
+
+        // Weekdays every 15 minutes during office hours; 2024-03-09 is a Saturday
+        let office = Schedule::parse("*/15 9-17 * * 1-5").unwrap();
+        assert!(office.matches(at("2024-03-08 17:45")));
+        assert!(!office.matches(at("2024-03-09 10:00")));
+        assert_eq!(office.next_after(at("2024-03-08 17:45")), Some(at("2024-03-11 09:00")));
+
+        // Sunday as 7, and day-of-month OR weekday when both are restricted
+        let sunday = Schedule::parse("30 3 * * 7").unwrap();
+        assert!(sunday.matches(at("2024-03-10 03:30")));
+        let either = Schedule::parse("0 0 1 * 1").unwrap();
+        assert!(either.matches(at("2024-03-01 00:00")) && either.matches(at("2024-03-04 00:00")));
+
+        assert_eq!(Schedule::parse("@daily").unwrap(), Schedule::parse("0 0 * * *").unwrap());
+        assert_eq!(Schedule::parse("0 0 29 2 *").unwrap().next_after(at("2024-03-01 00:00")), Some(at("2028-02-29 00:00")));
+
+        for bad in ["", "0 2 * *", "60 * * * *", "* 24 * * *", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+            assert!(Schedule::parse(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+}
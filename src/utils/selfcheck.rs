@@ -0,0 +1,358 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use crate::db::tuning::SqliteTuning;
+use crate::utils::recovery::RecoveryReport;
+
+/// Minimum free space on the data volume before we warn
+const MIN_FREE_BYTES: u64 = 1024 * 1024 * 1024;
+/// Below this many inotify watches, large libraries will silently stop being watched
+const MIN_INOTIFY_WATCHES: u64 = 65_536;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, status: CheckStatus, message: impl Into<String>) -> Self {
+        Self { name, status, message: message.into(), details: None }
+    }
+
+    fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SelfCheckReport {
+    /// False if any check failed (warnings don't count)
+    pub ok: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+impl SelfCheckReport {
+    pub fn new(checks: Vec<CheckResult>) -> Self {
+        let ok = !checks.iter().any(|c| c.status == CheckStatus::Fail);
+        Self { ok, checks }
+    }
+}
+
+/// Overall state reported by GET /health
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Ok,
+    /// Serving, but something such as video thumbnails or the file watcher is broken
+    Degraded,
+    /// The library can't be read or written
+    Unhealthy,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HealthReport {
+    pub status: Health,
+    pub checks: Vec<CheckResult>,
+}
+
+impl HealthReport {
+    /// A failed `critical` check makes the server unhealthy; any other failure or warning only degrades it
+    pub fn new(critical: Vec<CheckResult>, optional: Vec<CheckResult>) -> Self {
+        let status = if critical.iter().any(|c| c.status == CheckStatus::Fail) {
+            Health::Unhealthy
+        } else if critical.iter().chain(&optional).any(|c| matches!(c.status, CheckStatus::Warn | CheckStatus::Fail)) {
+            Health::Degraded
+        } else {
+            Health::Ok
+        };
+        Self { status, checks: critical.into_iter().chain(optional).collect() }
+    }
+}
+
+/// Verify each directory exists (creating it if needed) and accepts writes
+pub fn check_writable_dirs(dirs: &[(&str, &Path)]) -> CheckResult {
+    let mut failures = Vec::new();
+    let mut details = serde_json::Map::new();
+    for (label, dir) in dirs {
+        let result = std::fs::create_dir_all(dir).and_then(|_| {
+            let probe = dir.join(format!(".seen-selfcheck-{}", std::process::id()));
+            std::fs::write(&probe, b"ok")?;
+            std::fs::remove_file(&probe)
+        });
+        match result {
+            Ok(()) => {
+                details.insert(label.to_string(), serde_json::json!({ "path": dir, "writable": true }));
+            }
+            Err(e) => {
+                failures.push(format!("{} ({}): {}", label, dir.display(), e));
+                details.insert(label.to_string(), serde_json::json!({ "path": dir, "writable": false, "error": e.to_string() }));
+            }
+        }
+    }
+    let res = if failures.is_empty() {
+        CheckResult::new("data_dirs", CheckStatus::Pass, "All data directories are writable")
+    } else {
+        CheckResult::new("data_dirs", CheckStatus::Fail, format!("Not writable: {}", failures.join("; ")))
+    };
+    res.with_details(serde_json::Value::Object(details))
+}
+
+/// Decode and thumbnail a tiny in-memory PNG through libvips
+pub fn check_libvips() -> CheckResult {
+    #[cfg(not(target_env = "msvc"))]
+    {
+        let mut png = Vec::new();
+        let img = image::DynamicImage::new_rgb8(4, 4);
+        if let Err(e) = img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png) {
+            return CheckResult::new("libvips", CheckStatus::Fail, format!("Failed to build probe image: {}", e));
+        }
+        match libvips::VipsImage::new_from_buffer(&png, "").and_then(|im| libvips::ops::thumbnail_image(&im, 2)) {
+            Ok(_) => CheckResult::new("libvips", CheckStatus::Pass, "libvips decoded and resized a test image"),
+            Err(e) => CheckResult::new("libvips", CheckStatus::Fail, format!("libvips failed to process a test image: {}", e)),
+        }
+    }
+    #[cfg(target_env = "msvc")]
+    {
+        CheckResult::new("libvips", CheckStatus::Skip, "libvips is not used on Windows MSVC builds (image crate fallback)")
+    }
+}
+
+/// ffmpeg/ffprobe presence plus hardware acceleration summary
+pub fn check_ffmpeg() -> Vec<CheckResult> {
+    let mut results = Vec::new();
+    for b in crate::utils::ffmpeg::binary_status() {
+        let name = if b.name == "ffprobe" { "ffprobe" } else { "ffmpeg" };
+        let status = if b.available { CheckStatus::Pass } else { CheckStatus::Fail };
+        let message = match (&b.warning, &b.version) {
+            (Some(w), _) => w.clone(),
+            (None, Some(v)) => v.clone(),
+            (None, None) => format!("{} available", b.name),
+        };
+        results.push(CheckResult::new(name, status, message).with_details(serde_json::json!({ "path": b.path })));
+    }
+
+    let ffmpeg_ok = results.first().map(|r| r.status == CheckStatus::Pass).unwrap_or(false);
+    if ffmpeg_ok {
+        let cfg = crate::utils::ffmpeg::get_gpu_config();
+        let accel = format!("{:?}", cfg.accel);
+        let message = if cfg.enabled {
+            format!("Hardware decode available: {}", accel)
+        } else {
+            "No hardware acceleration detected, using CPU decoding".to_string()
+        };
+        results.push(
+            CheckResult::new("ffmpeg_features", CheckStatus::Pass, message)
+                .with_details(serde_json::json!({ "accel": accel, "enabled": cfg.enabled })),
+        );
+    } else {
+        results.push(CheckResult::new("ffmpeg_features", CheckStatus::Skip, "ffmpeg unavailable"));
+    }
+    results
+}
+
+/// Free space on the volume holding `path`
+pub fn check_disk_space(path: &Path) -> CheckResult {
+    use sysinfo::Disks;
+
+    let target = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let disks = Disks::new_with_refreshed_list();
+    // Longest mount point that prefixes the data dir wins
+    let disk = disks
+        .list()
+        .iter()
+        .filter(|d| target.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len());
+
+    match disk {
+        Some(d) => {
+            let available = d.available_space();
+            let details = serde_json::json!({
+                "mount_point": d.mount_point(),
+                "available_bytes": available,
+                "total_bytes": d.total_space(),
+            });
+            let gb = available as f64 / (1024.0 * 1024.0 * 1024.0);
+            let res = if available < MIN_FREE_BYTES {
+                CheckResult::new("disk_space", CheckStatus::Warn, format!("Only {:.2} GB free on data volume", gb))
+            } else {
+                CheckResult::new("disk_space", CheckStatus::Pass, format!("{:.1} GB free on data volume", gb))
+            };
+            res.with_details(details)
+        }
+        None => CheckResult::new("disk_space", CheckStatus::Skip, format!("Could not determine volume for {}", path.display())),
+    }
+}
+
+/// inotify watch limit (Linux only; the watcher needs one watch per directory)
+pub fn check_inotify() -> CheckResult {
+    if !cfg!(target_os = "linux") {
+        return CheckResult::new("inotify", CheckStatus::Skip, "Not applicable on this platform");
+    }
+    let read = |p: &str| std::fs::read_to_string(p).ok().and_then(|s| s.trim().parse::<u64>().ok());
+    let watches = read("/proc/sys/fs/inotify/max_user_watches");
+    let instances = read("/proc/sys/fs/inotify/max_user_instances");
+    match watches {
+        Some(w) => {
+            let details = serde_json::json!({ "max_user_watches": w, "max_user_instances": instances });
+            let res = if w < MIN_INOTIFY_WATCHES {
+                CheckResult::new(
+                    "inotify",
+                    CheckStatus::Warn,
+                    format!("fs.inotify.max_user_watches is {} (recommend >= {}); large libraries may not be fully watched", w, MIN_INOTIFY_WATCHES),
+                )
+            } else {
+                CheckResult::new("inotify", CheckStatus::Pass, format!("fs.inotify.max_user_watches = {}", w))
+            };
+            res.with_details(details)
+        }
+        None => CheckResult::new("inotify", CheckStatus::Warn, "Could not read fs.inotify.max_user_watches"),
+    }
+}
+
+/// The library database answers queries and isn't opened read-only
+pub fn check_database(pool: &crate::DbPool) -> CheckResult {
+    let conn = match pool.get() {
+        Ok(conn) => conn,
+        Err(e) => return CheckResult::new("database", CheckStatus::Fail, format!("No database connection: {}", e)),
+    };
+    let assets = conn.query_row("SELECT COUNT(*) FROM assets", [], |row| row.get::<_, i64>(0));
+    let read_only = conn.is_readonly(rusqlite::DatabaseName::Main).unwrap_or(true);
+    match assets {
+        Err(e) => CheckResult::new("database", CheckStatus::Fail, format!("Database query failed: {}", e)),
+        Ok(_) if read_only => CheckResult::new("database", CheckStatus::Fail, "Database is open read-only"),
+        Ok(n) => CheckResult::new("database", CheckStatus::Pass, "Database is readable and writable")
+            .with_details(serde_json::json!({ "assets": n, "pool": pool.status() })),
+    }
+}
+
+/// Per-path file watchers; `watchers` pairs each watched path with whether its task is still running
+pub fn check_watchers(watchers: &[(String, bool)]) -> CheckResult {
+    if watchers.is_empty() {
+        return CheckResult::new("watchers", CheckStatus::Skip, "No paths are being watched");
+    }
+    let dead: Vec<&str> = watchers.iter().filter(|(_, alive)| !alive).map(|(p, _)| p.as_str()).collect();
+    let details = serde_json::json!({ "watching": watchers.len(), "stopped": dead });
+    let res = if dead.is_empty() {
+        CheckResult::new("watchers", CheckStatus::Pass, format!("{} path watcher(s) running", watchers.len()))
+    } else {
+        CheckResult::new("watchers", CheckStatus::Warn, format!("{} of {} path watchers stopped; changes there aren't picked up", dead.len(), watchers.len()))
+    };
+    res.with_details(details)
+}
+
+/// Page cache and mmap size a pooled connection actually runs with, and what they were derived from
+pub fn check_sqlite_tuning(pool: &crate::DbPool, tuning: &SqliteTuning) -> CheckResult {
+    let effective = pool.get().map_err(|e| e.to_string()).and_then(|conn| {
+        let read = |pragma: &str| conn.query_row(&format!("PRAGMA {}", pragma), [], |row| row.get::<_, i64>(0)).map_err(|e| e.to_string());
+        Ok((read("cache_size")?, read("mmap_size")?))
+    });
+    let (cache_size, mmap_size) = match effective {
+        Ok(values) => values,
+        Err(e) => return CheckResult::new("sqlite_tuning", CheckStatus::Fail, format!("Could not read SQLite pragmas: {}", e)),
+    };
+    // Negative cache_size is in KiB, positive in pages
+    let cache_kib = if cache_size < 0 { -cache_size } else { cache_size * 4 };
+    let details = serde_json::json!({
+        "cache_kib": cache_kib,
+        "mmap_bytes": mmap_size,
+        "memory_bytes": tuning.memory_bytes,
+        "db_bytes": tuning.db_bytes,
+        "cache_source": if tuning.overrides.cache_mb.is_some() { "override" } else { "auto" },
+        "mmap_source": if tuning.overrides.mmap_mb.is_some() { "override" } else { "auto" },
+    });
+    let summary = format!("cache {} KiB per connection, mmap {} MiB", cache_kib, mmap_size / (1024 * 1024));
+    let res = if (mmap_size as u64) < tuning.mmap_bytes {
+        CheckResult::new(
+            "sqlite_tuning",
+            CheckStatus::Warn,
+            format!("{} (SQLite capped the requested {} MiB)", summary, tuning.mmap_bytes / (1024 * 1024)),
+        )
+    } else {
+        CheckResult::new("sqlite_tuning", CheckStatus::Pass, summary)
+    };
+    res.with_details(details)
+}
+
+/// What startup recovery repaired; informational, the repairs are already done
+pub fn check_recovery(report: &RecoveryReport) -> CheckResult {
+    let message = match report.repaired() {
+        0 if report.derived_swept_at.is_none() => "Nothing repaired so far; derived files are still being swept".to_string(),
+        0 => "Nothing to repair".to_string(),
+        n => format!("Repaired {} item(s) left by an unclean shutdown", n),
+    };
+    let res = CheckResult::new("startup_recovery", CheckStatus::Pass, message);
+    match serde_json::to_value(report) {
+        Ok(details) => res.with_details(details),
+        Err(_) => res,
+    }
+}
+
+/// ONNX runtime / face model state
+#[cfg(feature = "facial-recognition")]
+pub fn check_onnx(models_dir: PathBuf, scrfd_loaded: bool, arcface_loaded: bool) -> CheckResult {
+    let details = serde_json::json!({
+        "models_dir": models_dir,
+        "scrfd_loaded": scrfd_loaded,
+        "arcface_loaded": arcface_loaded,
+    });
+    let res = if scrfd_loaded && arcface_loaded {
+        CheckResult::new("onnx_runtime", CheckStatus::Pass, "ONNX runtime loaded face detection and recognition models")
+    } else if !models_dir.exists() {
+        CheckResult::new("onnx_runtime", CheckStatus::Warn, format!("Models directory {} does not exist yet; models download on first use", models_dir.display()))
+    } else {
+        CheckResult::new("onnx_runtime", CheckStatus::Warn, "Face models are not loaded; facial recognition is unavailable")
+    };
+    res.with_details(details)
+}
+
+#[cfg(not(feature = "facial-recognition"))]
+pub fn check_onnx(_models_dir: PathBuf) -> CheckResult {
+    CheckResult::new("onnx_runtime", CheckStatus::Skip, "Built without facial-recognition feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_writable_dirs_and_report() {
+        let tmp = TempDir::new().unwrap();
+        let derived = tmp.path().join("derived");
+        let ok = check_writable_dirs(&[("data", tmp.path()), ("derived", &derived)]);
+        assert_eq!(ok.status, CheckStatus::Pass);
+        assert!(derived.exists());
+
+        // A regular file can't be used as a directory
+        let file = tmp.path().join("not-a-dir");
+        std::fs::write(&file, b"x").unwrap();
+        let bad = check_writable_dirs(&[("db", &file)]);
+        assert_eq!(bad.status, CheckStatus::Fail);
+
+        let report = SelfCheckReport::new(vec![ok, CheckResult::new("inotify", CheckStatus::Warn, "low")]);
+        assert!(report.ok);
+        let report = SelfCheckReport::new(vec![bad.clone()]);
+        assert!(!report.ok);
+
+        let watchers = check_watchers(&[("/photos".to_string(), true), ("/phone".to_string(), false)]);
+        assert_eq!(watchers.status, CheckStatus::Warn);
+        let pass = || CheckResult::new("database", CheckStatus::Pass, "ok");
+        assert_eq!(HealthReport::new(vec![pass()], vec![]).status, Health::Ok);
+        assert_eq!(HealthReport::new(vec![pass()], vec![watchers]).status, Health::Degraded);
+        assert_eq!(HealthReport::new(vec![bad], vec![]).status, Health::Unhealthy);
+        assert_eq!(check_watchers(&[]).status, CheckStatus::Skip);
+    }
+}
@@ -0,0 +1,404 @@
+use anyhow::{Context, Result};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// An album asset, as placed in an exported gallery
+#[derive(Debug, Clone)]
+pub struct SiteItem {
+    pub id: i64,
+    pub filename: String,
+    pub path: String,
+    pub mime: String,
+    pub sha256: Option<String>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub taken_at: Option<i64>,
+}
+
+/// What an export wrote
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct SiteSummary {
+    /// Assets in the gallery
+    pub items: usize,
+    /// Assets left out because their thumbnail or preview hasn't been generated
+    pub skipped: usize,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Where the gallery's files go
+pub trait SiteSink {
+    fn add_bytes(&mut self, name: &str, data: &[u8]) -> Result<()>;
+    fn add_file(&mut self, name: &str, source: &Path) -> Result<()>;
+}
+
+/// Writes the gallery into a directory
+pub struct DirSink {
+    root: PathBuf,
+}
+
+impl DirSink {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn target(&self, name: &str) -> Result<PathBuf> {
+        let path = self.root.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(path)
+    }
+}
+
+impl SiteSink for DirSink {
+    fn add_bytes(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        std::fs::write(self.target(name)?, data)?;
+        Ok(())
+    }
+
+    fn add_file(&mut self, name: &str, source: &Path) -> Result<()> {
+        std::fs::copy(source, self.target(name)?).context(format!("Failed to copy {:?}", source))?;
+        Ok(())
+    }
+}
+
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes the gallery as a ZIP. Entries are stored uncompressed: the images are already compressed,
+/// and it lets the archive stream out without seeking. No ZIP64, so the archive must stay under 4 GiB.
+pub struct ZipSink<W: Write> {
+    out: W,
+    written: u64,
+    entries: Vec<ZipEntry>,
+    dos_time: u16,
+    dos_date: u16,
+}
+
+impl<W: Write> ZipSink<W> {
+    pub fn new(out: W, now: chrono::NaiveDateTime) -> Self {
+        use chrono::{Datelike, Timelike};
+        let dos_time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+        let dos_date = (((now.year() - 1980).max(0) as u32) << 9 | (now.month() << 5) | now.day()) as u16;
+        Self { out, written: 0, entries: Vec::new(), dos_time, dos_date }
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn offset(&self) -> Result<u32> {
+        u32::try_from(self.written).map_err(|_| anyhow::anyhow!("Export is larger than 4 GiB; export to a directory instead"))
+    }
+
+    fn begin(&mut self, name: &str, crc: u32, size: u64) -> Result<()> {
+        if self.entries.len() >= u16::MAX as usize {
+            anyhow::bail!("Too many files for a ZIP; export to a directory instead");
+        }
+        let size = u32::try_from(size).map_err(|_| anyhow::anyhow!("{} is larger than 4 GiB", name))?;
+        let offset = self.offset()?;
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        header.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        header.extend_from_slice(&0x0800u16.to_le_bytes()); // UTF-8 names
+        header.extend_from_slice(&0u16.to_le_bytes()); // stored
+        header.extend_from_slice(&self.dos_time.to_le_bytes());
+        header.extend_from_slice(&self.dos_date.to_le_bytes());
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+        self.put(&header)?;
+        self.entries.push(ZipEntry { name: name.to_string(), crc, size, offset });
+        Ok(())
+    }
+
+    /// Write the central directory; returns the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        let start = self.offset()?;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            let mut record = Vec::with_capacity(46 + entry.name.len());
+            record.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            record.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            record.extend_from_slice(&20u16.to_le_bytes()); // version needed
+            record.extend_from_slice(&0x0800u16.to_le_bytes());
+            record.extend_from_slice(&0u16.to_le_bytes());
+            record.extend_from_slice(&self.dos_time.to_le_bytes());
+            record.extend_from_slice(&self.dos_date.to_le_bytes());
+            record.extend_from_slice(&entry.crc.to_le_bytes());
+            record.extend_from_slice(&entry.size.to_le_bytes());
+            record.extend_from_slice(&entry.size.to_le_bytes());
+            record.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            record.extend_from_slice(&[0u8; 12]); // extra and comment lengths, disk, attributes
+            record.extend_from_slice(&entry.offset.to_le_bytes());
+            record.extend_from_slice(entry.name.as_bytes());
+            self.put(&record)?;
+        }
+        let size = self.offset()? - start;
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        end.extend_from_slice(&[0u8; 4]); // disk numbers
+        end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        end.extend_from_slice(&size.to_le_bytes());
+        end.extend_from_slice(&start.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        self.put(&end)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: Write> SiteSink for ZipSink<W> {
+    fn add_bytes(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.begin(name, crc32fast::hash(data), data.len() as u64)?;
+        self.put(data)?;
+        Ok(())
+    }
+
+    fn add_file(&mut self, name: &str, source: &Path) -> Result<()> {
+        // Stored entries need the checksum up front, so originals are read twice
+        let mut hasher = crc32fast::Hasher::new();
+        let mut size = 0u64;
+        let mut file = std::fs::File::open(source).context(format!("Failed to open {:?}", source))?;
+        let mut buf = vec![0u8; 256 * 1024];
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            size += n as u64;
+        }
+        self.begin(name, hasher.finalize(), size)?;
+        let copied = io::copy(&mut std::fs::File::open(source)?.take(size), &mut self.out)?;
+        self.written += copied;
+        if copied != size {
+            anyhow::bail!("{:?} changed while it was exported", source);
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Name an original is stored under: its id keeps names unique, the rest is kept readable
+fn original_name(item: &SiteItem) -> String {
+    let clean: String = item
+        .filename
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    format!("originals/{}-{}", item.id, clean)
+}
+
+/// The gallery page: a thumbnail grid that opens previews in a lightbox, with no external assets
+pub fn index_html(title: &str, description: Option<&str>, items: &[SiteItem], with_originals: bool) -> String {
+    let mut grid = String::new();
+    let mut slides = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        let is_video = item.mime.starts_with("video/");
+        let preview = format!("previews/{}.webp", item.id);
+        let caption = match item.taken_at.and_then(|t| chrono::DateTime::from_timestamp(t, 0)) {
+            Some(taken) => format!("{} · {}", item.filename, taken.format("%-d %b %Y")),
+            None => item.filename.clone(),
+        };
+        let original = with_originals.then(|| original_name(item));
+        grid.push_str(&format!(
+            "<a href=\"{href}\" data-i=\"{i}\"{video}><img src=\"thumbs/{id}.webp\" alt=\"{alt}\" loading=\"lazy\"></a>\n",
+            href = escape_html(original.as_deref().filter(|_| is_video).unwrap_or(&preview)),
+            i = i,
+            video = if is_video { " class=\"video\"" } else { "" },
+            id = item.id,
+            alt = escape_html(&item.filename),
+        ));
+        slides.push(serde_json::json!({
+            "preview": preview,
+            "caption": caption,
+            "original": original,
+            "video": is_video,
+        }));
+    }
+    // Escape "</" so the data can't close its script element
+    let data = serde_json::Value::from(slides).to_string().replace("</", "<\\/");
+    let description = description
+        .filter(|d| !d.trim().is_empty())
+        .map(|d| format!("<p>{}</p>\n", escape_html(d)))
+        .unwrap_or_default();
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body{{margin:0;font-family:system-ui,sans-serif;background:#111;color:#eee}}
+header{{padding:24px 16px 8px}}h1{{margin:0 0 8px;font-weight:600}}header p{{margin:0;color:#aaa}}
+main{{display:grid;grid-template-columns:repeat(auto-fill,minmax(160px,1fr));gap:4px;padding:12px}}
+main a{{position:relative;display:block;aspect-ratio:1;background:#222}}
+main img{{width:100%;height:100%;object-fit:cover;display:block}}
+main a.video::after{{content:"▶";position:absolute;right:8px;bottom:6px;font-size:18px;text-shadow:0 0 4px #000}}
+#box{{position:fixed;inset:0;background:rgba(0,0,0,.94);display:none;flex-direction:column;align-items:center;justify-content:center}}
+#box.open{{display:flex}}#box img,#box video{{max-width:96vw;max-height:86vh}}
+#box p{{margin:12px;color:#ccc}}#box p a{{color:#9cf;margin-left:12px}}
+#box button{{position:absolute;background:none;border:0;color:#fff;font-size:36px;padding:16px;cursor:pointer}}
+#prev{{left:0;top:45%}}#next{{right:0;top:45%}}#close{{right:0;top:0}}
+</style>
+</head>
+<body>
+<header><h1>{title}</h1>
+{description}</header>
+<main>
+{grid}</main>
+<div id="box"><button id="close" aria-label="Close">×</button><button id="prev" aria-label="Previous">‹</button><div id="media"></div><p id="caption"></p><button id="next" aria-label="Next">›</button></div>
+<script>
+const slides={data};let at=-1;const box=document.getElementById("box");
+function show(i){{at=(i+slides.length)%slides.length;const s=slides[at];const m=document.getElementById("media");m.textContent="";
+let el;if(s.video&&s.original){{el=document.createElement("video");el.src=s.original;el.poster=s.preview;el.controls=true;el.autoplay=true}}else{{el=document.createElement("img");el.src=s.preview;el.alt=s.caption}}
+m.appendChild(el);const c=document.getElementById("caption");c.textContent=s.caption;
+if(s.original){{const a=document.createElement("a");a.href=s.original;a.textContent="Original";a.download="";c.appendChild(a)}}box.classList.add("open")}}
+function hide(){{box.classList.remove("open");document.getElementById("media").textContent="";at=-1}}
+document.querySelectorAll("main a").forEach(a=>a.addEventListener("click",e=>{{e.preventDefault();show(+a.dataset.i)}}));
+document.getElementById("prev").onclick=()=>show(at-1);document.getElementById("next").onclick=()=>show(at+1);document.getElementById("close").onclick=hide;
+document.addEventListener("keydown",e=>{{if(at<0)return;if(e.key==="Escape")hide();if(e.key==="ArrowLeft")show(at-1);if(e.key==="ArrowRight")show(at+1)}});
+</script>
+</body>
+</html>
+"#,
+        title = escape_html(title),
+        description = description,
+        grid = grid,
+        data = data,
+    )
+}
+
+/// Write the gallery: index.html, a 256px thumbnail and a 1600px preview per asset, and the originals if asked
+pub fn write_site<S: SiteSink>(
+    sink: &mut S,
+    title: &str,
+    description: Option<&str>,
+    items: &[SiteItem],
+    derived: &Path,
+    with_originals: bool,
+) -> Result<SiteSummary> {
+    let mut summary = SiteSummary::default();
+    let mut included = Vec::with_capacity(items.len());
+    for item in items {
+        let Some(sha) = item.sha256.as_deref().filter(|s| s.len() >= 2) else {
+            summary.skipped += 1;
+            continue;
+        };
+        let thumb = crate::pipeline::thumb::thumb_path(derived, sha, 256);
+        let preview = crate::pipeline::thumb::thumb_path(derived, sha, 1600);
+        if !thumb.is_file() || !preview.is_file() {
+            summary.skipped += 1;
+            continue;
+        }
+        let mut files = vec![(format!("thumbs/{}.webp", item.id), thumb), (format!("previews/{}.webp", item.id), preview)];
+        if with_originals {
+            files.push((original_name(item), PathBuf::from(&item.path)));
+        }
+        for (name, source) in files {
+            sink.add_file(&name, &source)?;
+            summary.files += 1;
+            summary.bytes += std::fs::metadata(&source).map(|m| m.len()).unwrap_or(0);
+        }
+        included.push(item.clone());
+    }
+    let html = index_html(title, description, &included, with_originals);
+    sink.add_bytes("index.html", html.as_bytes())?;
+    summary.items = included.len();
+    summary.files += 1;
+    summary.bytes += html.len() as u64;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(b: &[u8], i: usize) -> u16 {
+        u16::from_le_bytes([b[i], b[i + 1]])
+    }
+
+    fn u32_at(b: &[u8], i: usize) -> u32 {
+        u32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]])
+    }
+
+    #[test]
+    fn test_site_zip() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let derived = tmp.path().join("derived");
+        let sha = "ab".repeat(32);
+        for size in [256, 1600] {
+            let path = crate::pipeline::thumb::thumb_path(&derived, &sha, size);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, format!("webp-{}", size)).unwrap();
+        }
+        let item = |id: i64, sha: Option<&str>| SiteItem {
+            id,
+            filename: "Beach <day>.jpg".to_string(),
+            path: format!("/photos/{}.jpg", id),
+            mime: "image/jpeg".to_string(),
+            sha256: sha.map(str::to_string),
+            width: Some(4000),
+            height: Some(3000),
+            taken_at: Some(1718150400),
+        };
+        let items = vec![item(7, Some(&sha)), item(8, None)];
+
+        let now = chrono::DateTime::from_timestamp(1718236800, 0).unwrap().naive_utc();
+        let mut zip = ZipSink::new(Vec::new(), now);
+        let summary = write_site(&mut zip, "Trip </script>", Some("Summer"), &items, &derived, false).unwrap();
+        assert_eq!((summary.items, summary.skipped, summary.files), (1, 1, 3));
+        let bytes = zip.finish().unwrap();
+
+        // End of central directory: 3 entries, and the directory sits where it says
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, end), 0x0605_4b50);
+        assert_eq!(u16_at(&bytes, end + 10), 3);
+        let mut at = u32_at(&bytes, end + 16) as usize;
+        let mut names = Vec::new();
+        for _ in 0..3 {
+            assert_eq!(u32_at(&bytes, at), 0x0201_4b50);
+            let (crc, size) = (u32_at(&bytes, at + 16), u32_at(&bytes, at + 20) as usize);
+            let name_len = u16_at(&bytes, at + 28) as usize;
+            let local = u32_at(&bytes, at + 42) as usize;
+            let name = String::from_utf8(bytes[at + 46..at + 46 + name_len].to_vec()).unwrap();
+            assert_eq!(u32_at(&bytes, local), 0x0403_4b50);
+            let data_at = local + 30 + u16_at(&bytes, local + 26) as usize;
+            let data = &bytes[data_at..data_at + size];
+            assert_eq!(crc32fast::hash(data), crc);
+            if name == "thumbs/7.webp" {
+                assert_eq!(data, b"webp-256");
+            }
+            if name == "index.html" {
+                let html = String::from_utf8(data.to_vec()).unwrap();
+                assert!(html.contains("<title>Trip &lt;/script&gt;</title>"));
+                assert!(html.contains("<img src=\"thumbs/7.webp\" alt=\"Beach &lt;day&gt;.jpg\""));
+                assert!(html.contains("\"caption\":\"Beach <day>.jpg · 12 Jun 2024\""));
+                assert!(!html.contains("8.webp"));
+            }
+            names.push(name);
+            at += 46 + name_len;
+        }
+        assert_eq!(names, vec!["thumbs/7.webp", "previews/7.webp", "index.html"]);
+    }
+}
@@ -0,0 +1,248 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How the connection to the mail server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS (usually port 587)
+    #[default]
+    Starttls,
+    /// TLS from the start (usually port 465)
+    Tls,
+    /// No encryption, for a relay on the local network
+    None,
+}
+
+/// Mail server to send through
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmtpSettings {
+    pub host: String,
+    /// 0 picks the usual port for `security`
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sender address
+    pub from: String,
+}
+
+impl SmtpSettings {
+    pub fn port(&self) -> u16 {
+        match (self.port, self.security) {
+            (0, SmtpSecurity::Starttls) => 587,
+            (0, SmtpSecurity::Tls) => 465,
+            (0, SmtpSecurity::None) => 25,
+            (port, _) => port,
+        }
+    }
+}
+
+/// Rough check that `addr` is a bare mail address that can't inject headers or commands
+pub fn valid_address(addr: &str) -> bool {
+    let Some((local, domain)) = addr.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !addr.chars().any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | ',' | ';' | '"'))
+        && !domain.contains('@')
+}
+
+/// An image shown in the HTML body through `cid:<content_id>`
+pub struct InlineImage {
+    pub content_id: String,
+    pub mime: &'static str,
+    pub data: Vec<u8>,
+}
+
+pub struct Email<'a> {
+    pub from: &'a str,
+    pub to: &'a [String],
+    pub subject: &'a str,
+    pub text: &'a str,
+    pub html: &'a str,
+    pub images: &'a [InlineImage],
+}
+
+fn base64_lines(data: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+    let mut out = String::with_capacity(encoded.len() + encoded.len() / 76 * 2 + 2);
+    for chunk in encoded.as_bytes().chunks(76) {
+        out.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// RFC 2047 encoded word for header text that isn't plain ASCII
+fn header_text(text: &str) -> String {
+    let clean: String = text.chars().filter(|c| !c.is_control()).collect();
+    if clean.is_ascii() {
+        clean
+    } else {
+        format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(clean.as_bytes()))
+    }
+}
+
+/// The complete message, CRLF line endings: text and HTML alternatives, with the images attached inline
+pub fn build_message(email: &Email, date: chrono::DateTime<chrono::Utc>, message_id: &str) -> String {
+    let related = format!("related-{}", message_id);
+    let alternative = format!("alternative-{}", message_id);
+    let mut m = String::new();
+    m.push_str(&format!("From: {}\r\n", email.from));
+    m.push_str(&format!("To: {}\r\n", email.to.join(", ")));
+    m.push_str(&format!("Subject: {}\r\n", header_text(email.subject)));
+    m.push_str(&format!("Date: {}\r\n", date.to_rfc2822()));
+    m.push_str(&format!("Message-ID: <{}@seen>\r\n", message_id));
+    m.push_str("MIME-Version: 1.0\r\n");
+    m.push_str(&format!("Content-Type: multipart/related; boundary=\"{}\"\r\n\r\n", related));
+    m.push_str(&format!("--{}\r\nContent-Type: multipart/alternative; boundary=\"{}\"\r\n\r\n", related, alternative));
+    for (mime, body) in [("text/plain", email.text), ("text/html", email.html)] {
+        m.push_str(&format!("--{}\r\nContent-Type: {}; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n", alternative, mime));
+        m.push_str(&base64_lines(body.as_bytes()));
+    }
+    m.push_str(&format!("--{}--\r\n", alternative));
+    for image in email.images {
+        m.push_str(&format!(
+            "--{}\r\nContent-Type: {}\r\nContent-Transfer-Encoding: base64\r\nContent-ID: <{}>\r\nContent-Disposition: inline\r\n\r\n",
+            related, image.mime, image.content_id
+        ));
+        m.push_str(&base64_lines(&image.data));
+    }
+    m.push_str(&format!("--{}--\r\n", related));
+    m
+}
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+struct Session {
+    conn: BufReader<Box<dyn Stream>>,
+}
+
+impl Session {
+    /// Read a (possibly multi-line) reply and check its code is in `expect`
+    fn reply(&mut self, expect: std::ops::RangeInclusive<u16>) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if self.conn.read_line(&mut line)? == 0 {
+                anyhow::bail!("Mail server closed the connection");
+            }
+            text.push_str(&line);
+            // The last line of a reply has a space after the code, the others a dash
+            if line.len() < 4 || line.as_bytes()[3] != b'-' {
+                break;
+            }
+        }
+        let code: u16 = text.get(..3).and_then(|c| c.parse().ok()).context("Malformed reply from mail server")?;
+        if !expect.contains(&code) {
+            anyhow::bail!("Mail server replied: {}", text.trim());
+        }
+        Ok(text)
+    }
+
+    fn command(&mut self, line: &str, expect: std::ops::RangeInclusive<u16>) -> Result<String> {
+        let stream = self.conn.get_mut();
+        stream.write_all(line.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+        self.reply(expect)
+    }
+}
+
+fn connect(settings: &SmtpSettings) -> Result<TcpStream> {
+    let addr = (settings.host.as_str(), settings.port())
+        .to_socket_addrs()
+        .context(format!("Cannot resolve {}", settings.host))?
+        .next()
+        .context(format!("Cannot resolve {}", settings.host))?;
+    let tcp = TcpStream::connect_timeout(&addr, IO_TIMEOUT).context(format!("Cannot connect to {}:{}", settings.host, settings.port()))?;
+    tcp.set_read_timeout(Some(IO_TIMEOUT))?;
+    tcp.set_write_timeout(Some(IO_TIMEOUT))?;
+    Ok(tcp)
+}
+
+/// Deliver `message` (from `build_message`) to `to`
+pub fn send(settings: &SmtpSettings, to: &[String], message: &str) -> Result<()> {
+    let tls = || native_tls::TlsConnector::new().context("Cannot set up TLS");
+    let tcp = connect(settings)?;
+    // The plain socket is kept for a STARTTLS upgrade
+    let (mut session, plain) = match settings.security {
+        SmtpSecurity::Tls => {
+            let stream = tls()?.connect(&settings.host, tcp).map_err(|e| anyhow::anyhow!("TLS handshake failed: {}", e))?;
+            (Session { conn: BufReader::new(Box::new(stream)) }, None)
+        }
+        _ => (Session { conn: BufReader::new(Box::new(tcp.try_clone()?)) }, Some(tcp)),
+    };
+    session.reply(220..=220)?;
+    session.command("EHLO seen", 250..=250)?;
+    if let (SmtpSecurity::Starttls, Some(tcp)) = (settings.security, plain) {
+        session.command("STARTTLS", 220..=220)?;
+        let stream = tls()?.connect(&settings.host, tcp).map_err(|e| anyhow::anyhow!("TLS handshake failed: {}", e))?;
+        session = Session { conn: BufReader::new(Box::new(stream)) };
+        session.command("EHLO seen", 250..=250)?;
+    }
+    if let Some(user) = settings.username.as_deref().filter(|u| !u.is_empty()) {
+        let password = settings.password.as_deref().unwrap_or_default();
+        let token = base64::engine::general_purpose::STANDARD.encode(format!("\0{}\0{}", user, password));
+        session.command(&format!("AUTH PLAIN {}", token), 235..=235)?;
+    }
+    session.command(&format!("MAIL FROM:<{}>", settings.from), 250..=250)?;
+    for rcpt in to {
+        session.command(&format!("RCPT TO:<{}>", rcpt), 250..=251)?;
+    }
+    session.command("DATA", 354..=354)?;
+    // Dot-stuffing: a line starting with '.' gets a second one
+    let mut data = String::with_capacity(message.len() + 16);
+    for line in message.split_inclusive("\r\n") {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+    }
+    if !data.ends_with("\r\n") {
+        data.push_str("\r\n");
+    }
+    data.push('.');
+    session.command(&data, 250..=250)?;
+    let _ = session.command("QUIT", 221..=221);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_message() {
+        assert!(valid_address("me@example.com"));
+        for bad in ["me", "me@localhost", "a b@example.com", "me@example.com\r\nBcc: x@y.z", "<me@example.com>", "@example.com"] {
+            assert!(!valid_address(bad), "{:?}", bad);
+        }
+
+        let to = vec!["a@example.com".to_string(), "b@example.com".to_string()];
+        let images = [InlineImage { content_id: "m0@seen".to_string(), mime: "image/jpeg", data: vec![0xff; 100] }];
+        let email = Email { from: "photos@example.com", to: &to, subject: "Memories · this week", text: "Hi", html: "<p>Hi</p>", images: &images };
+        let date = chrono::DateTime::from_timestamp(1718236800, 0).unwrap();
+        let message = build_message(&email, date, "abc");
+        assert!(message.contains("To: a@example.com, b@example.com\r\n"));
+        assert!(message.contains("Subject: =?UTF-8?B?"));
+        assert!(message.contains("Date: Thu, 13 Jun 2024 00:00:00 +0000\r\n"));
+        assert!(message.contains("Content-ID: <m0@seen>\r\n"));
+        assert!(message.contains("Content-Type: text/html; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\nPHA+SGk8L3A+\r\n"));
+        assert!(message.ends_with("--related-abc--\r\n"));
+        assert!(message.split("\r\n").all(|line| line.len() <= 998 && !line.contains('\n')));
+        assert_eq!(SmtpSettings { security: SmtpSecurity::Tls, ..Default::default() }.port(), 465);
+    }
+}
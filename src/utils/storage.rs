@@ -0,0 +1,262 @@
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::AppState;
+
+/// How often the storage report is recomputed (FLASH_STORAGE_REFRESH_MINUTES); 0 only computes it at boot and on request
+pub fn refresh_minutes() -> u64 {
+    std::env::var("FLASH_STORAGE_REFRESH_MINUTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(60)
+}
+
+/// Set while the sizer walks the disk, so refreshes never overlap
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+impl Usage {
+    fn add(&mut self, bytes: u64) {
+        self.files += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// The derived directory, split by what the files are
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DerivedUsage {
+    pub thumbnails: Usage,
+    pub animated_thumbnails: Usage,
+    pub previews: Usage,
+    pub transcodes: Usage,
+    pub highlights: Usage,
+    /// In-progress writes and anything else
+    pub other: Usage,
+    pub total_bytes: u64,
+}
+
+/// SQLite files; the WAL grows between checkpoints
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DatabaseUsage {
+    pub bytes: u64,
+    pub wal_bytes: u64,
+}
+
+/// Indexed originals under one scan path, from the sizes recorded at ingest
+#[derive(Debug, Clone, Serialize)]
+pub struct PathUsage {
+    pub path: String,
+    pub assets: i64,
+    pub bytes: i64,
+}
+
+/// A mounted volume holding the database, the derived cache or a scan path
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumeUsage {
+    pub mount_point: PathBuf,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    /// `database`, `derived`, or scan paths
+    pub holds: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageReport {
+    pub database: DatabaseUsage,
+    pub derived: DerivedUsage,
+    pub originals: Vec<PathUsage>,
+    pub volumes: Vec<VolumeUsage>,
+    /// How long the walk took
+    pub duration_ms: u64,
+    pub computed_at: i64,
+}
+
+/// Size up the derived directory by file name
+pub fn measure_derived(derived: &Path) -> DerivedUsage {
+    let mut usage = DerivedUsage::default();
+    let root = super::path::long_path(derived);
+    let highlights = root.join("highlights");
+    for entry in walkdir::WalkDir::new(&root).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let name = entry.file_name().to_string_lossy();
+        let bucket = if name.contains(".partial.") {
+            &mut usage.other
+        } else if entry.path().parent() == Some(highlights.as_path()) {
+            &mut usage.highlights
+        } else if name.ends_with("-transcoded.mp4") {
+            &mut usage.transcodes
+        } else if name.ends_with("-anim.webp") {
+            &mut usage.animated_thumbnails
+        } else if name.ends_with("-1600.webp") {
+            &mut usage.previews
+        } else if name.ends_with(".webp") {
+            &mut usage.thumbnails
+        } else {
+            &mut usage.other
+        };
+        bucket.add(meta.len());
+        usage.total_bytes += meta.len();
+    }
+    usage
+}
+
+/// Recorded size of the originals under each scan path
+pub fn measure_originals(conn: &Connection) -> Result<Vec<PathUsage>> {
+    let mut stmt = conn.prepare(
+        "SELECT sp.path, COUNT(a.id), COALESCE(SUM(a.size_bytes), 0)
+         FROM scan_paths sp LEFT JOIN assets a ON a.path_id = sp.id
+         GROUP BY sp.id ORDER BY sp.created_at, sp.id",
+    )?;
+    let rows = stmt.query_map([], |row| Ok(PathUsage { path: row.get(0)?, assets: row.get(1)?, bytes: row.get(2)? }))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Free space on the volumes holding `targets`, one entry per volume
+pub fn measure_volumes(targets: &[(String, PathBuf)]) -> Vec<VolumeUsage> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let mut volumes: Vec<VolumeUsage> = Vec::new();
+    for (label, path) in targets {
+        let target = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        // Longest mount point that prefixes the path wins
+        let Some(disk) = disks
+            .list()
+            .iter()
+            .filter(|d| target.starts_with(d.mount_point()))
+            .max_by_key(|d| d.mount_point().as_os_str().len())
+        else {
+            continue;
+        };
+        match volumes.iter_mut().find(|v| v.mount_point == disk.mount_point()) {
+            Some(volume) => volume.holds.push(label.clone()),
+            None => volumes.push(VolumeUsage {
+                mount_point: disk.mount_point().to_path_buf(),
+                total_bytes: disk.total_space(),
+                available_bytes: disk.available_space(),
+                holds: vec![label.clone()],
+            }),
+        }
+    }
+    volumes
+}
+
+fn file_len(path: &Path) -> u64 {
+    std::fs::metadata(path).map_or(0, |m| m.len())
+}
+
+/// Recompute the report and cache it; None if a refresh is already running
+pub fn refresh(state: &AppState) -> Result<Option<StorageReport>> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Ok(None);
+    }
+    let result = (|| {
+        let started = std::time::Instant::now();
+        let originals = {
+            let conn = state.pool.get().map_err(|e| anyhow::anyhow!("Pool error: {}", e))?;
+            measure_originals(&conn)?
+        };
+        let mut wal = state.db_path.clone().into_os_string();
+        wal.push("-wal");
+        let database = DatabaseUsage { bytes: file_len(&state.db_path), wal_bytes: file_len(Path::new(&wal)) };
+        let derived = measure_derived(&state.paths.derived);
+
+        let mut targets = vec![
+            ("database".to_string(), state.db_path.clone()),
+            ("derived".to_string(), state.paths.derived.clone()),
+        ];
+        targets.extend(originals.iter().map(|p| (p.path.clone(), PathBuf::from(&p.path))));
+        let report = StorageReport {
+            database,
+            derived,
+            volumes: measure_volumes(&targets),
+            originals,
+            duration_ms: started.elapsed().as_millis() as u64,
+            computed_at: chrono::Utc::now().timestamp(),
+        };
+        *state.storage.lock() = Some(report.clone());
+        Ok(Some(report))
+    })();
+    RUNNING.store(false, Ordering::SeqCst);
+    result
+}
+
+/// Refresh in the background; does nothing if a refresh is already running
+pub fn spawn_refresh(state: Arc<AppState>) {
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = refresh(&state) {
+            tracing::warn!("storage sizer failed: {}", e);
+        }
+    });
+}
+
+/// Compute the report at boot, then every `minutes` minutes
+pub fn start(state: Arc<AppState>, minutes: u64) {
+    tokio::spawn(async move {
+        if minutes == 0 {
+            spawn_refresh(state);
+            return;
+        }
+        let mut interval = tokio::time::interval(Duration::from_secs(minutes * 60));
+        loop {
+            interval.tick().await;
+            spawn_refresh(state.clone());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_measure_storage() {
+        let tmp = TempDir::new().unwrap();
+        let derived = tmp.path().join("derived");
+        std::fs::create_dir_all(derived.join("ab")).unwrap();
+        std::fs::create_dir_all(derived.join("highlights")).unwrap();
+        std::fs::write(derived.join("ab").join("abcd-256.webp"), b"thumb").unwrap();
+        std::fs::write(derived.join("ab").join("abce-256.webp"), b"thumb").unwrap();
+        std::fs::write(derived.join("ab").join("abcd-1600.webp"), b"preview").unwrap();
+        std::fs::write(derived.join("ab").join("abcd-anim.webp"), b"anim").unwrap();
+        std::fs::write(derived.join("ab").join("abcf-transcoded.mp4"), b"video").unwrap();
+        std::fs::write(derived.join("ab").join("abcg-transcoded.partial.mp4"), b"half").unwrap();
+        std::fs::write(derived.join("highlights").join("album-1.mp4"), b"reel").unwrap();
+
+        let usage = measure_derived(&derived);
+        assert_eq!(usage.thumbnails, Usage { files: 2, bytes: 10 });
+        assert_eq!(usage.previews, Usage { files: 1, bytes: 7 });
+        assert_eq!(usage.animated_thumbnails.files, 1);
+        assert_eq!(usage.transcodes, Usage { files: 1, bytes: 5 });
+        assert_eq!(usage.highlights.files, 1);
+        assert_eq!(usage.other, Usage { files: 1, bytes: 4 });
+        assert_eq!(usage.total_bytes, 34);
+
+        let conn = crate::db::open_or_create(tmp.path().join("test.db")).unwrap();
+        crate::db::writer::add_scan_path(&conn, "/photos").unwrap();
+        crate::db::writer::add_scan_path(&conn, "/empty").unwrap();
+        for (name, size) in [("a.jpg", 100), ("b.jpg", 50)] {
+            conn.execute(
+                "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags, path_id)
+                 VALUES (?1, '/photos', ?2, 'jpg', ?3, 0, 0, 'image/jpeg', 0, (SELECT id FROM scan_paths WHERE path = '/photos'))",
+                rusqlite::params![format!("/photos/{}", name), name, size],
+            ).unwrap();
+        }
+        let originals = measure_originals(&conn).unwrap();
+        let summary: Vec<(&str, i64, i64)> = originals.iter().map(|p| (p.path.as_str(), p.assets, p.bytes)).collect();
+        assert_eq!(summary, vec![("/photos", 2, 150), ("/empty", 0, 0)]);
+    }
+}
@@ -0,0 +1,286 @@
+//! Face regions as XMP, in the Metadata Working Group region schema digiKam, Lightroom and
+//! exiftool read.
+//!
+//! Each named face becomes a region with the person's name and its box, given as a centre and size
+//! relative to the image as stored. The packet goes into a `<file>.xmp` sidecar next to the photo,
+//! or into the JPEG itself. Packets written here carry `xmp:CreatorTool="seen"`, so a later export
+//! replaces them while XMP from other tools is left alone unless overwriting is asked for.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const CREATOR_TOOL: &str = "xmp:CreatorTool=\"seen\"";
+/// APP1 payloads starting with this are XMP; the rest of the APP1 segments are EXIF
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// A face as a box in image pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaceRegion {
+    pub name: String,
+    /// MWG region type: "Face" or "Pet"
+    pub kind: &'static str,
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+}
+
+/// Where the regions are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    /// A `<file>.xmp` sidecar; the photo is not touched
+    #[default]
+    Sidecar,
+    /// Into JPEGs, which changes their hash; other formats get a sidecar
+    Embed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Sidecar,
+    Embedded,
+    Skipped(&'static str),
+}
+
+/// What an export did, with skipped assets counted by reason
+#[derive(Debug, Default, Serialize)]
+pub struct ExportSummary {
+    pub assets: usize,
+    pub sidecars: usize,
+    pub embedded: usize,
+    pub skipped: BTreeMap<&'static str, usize>,
+    pub failed: usize,
+}
+
+impl ExportSummary {
+    pub fn record(&mut self, outcome: Outcome) {
+        self.assets += 1;
+        match outcome {
+            Outcome::Sidecar => self.sidecars += 1,
+            Outcome::Embedded => self.embedded += 1,
+            Outcome::Skipped(reason) => *self.skipped.entry(reason).or_default() += 1,
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The XMP packet for an image of `width`×`height` pixels with these faces
+pub fn regions_packet(width: u32, height: u32, regions: &[FaceRegion]) -> String {
+    let (w, h) = (width.max(1) as f32, height.max(1) as f32);
+    let mut list = String::new();
+    for region in regions {
+        let x1 = (region.x1 / w).clamp(0.0, 1.0);
+        let y1 = (region.y1 / h).clamp(0.0, 1.0);
+        let x2 = (region.x2 / w).clamp(0.0, 1.0);
+        let y2 = (region.y2 / h).clamp(0.0, 1.0);
+        list.push_str(&format!(
+            "     <rdf:li>\n      <rdf:Description mwg-rs:Name=\"{}\" mwg-rs:Type=\"{}\">\n       <mwg-rs:Area stArea:x=\"{:.6}\" stArea:y=\"{:.6}\" stArea:w=\"{:.6}\" stArea:h=\"{:.6}\" stArea:unit=\"normalized\"/>\n      </rdf:Description>\n     </rdf:li>\n",
+            escape(&region.name),
+            region.kind,
+            (x1 + x2) / 2.0,
+            (y1 + y2) / 2.0,
+            (x2 - x1).max(0.0),
+            (y2 - y1).max(0.0),
+        ));
+    }
+    format!(
+        r#"<?xpacket begin="{}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:mwg-rs="http://www.metadataworkinggroup.com/schemas/regions/"
+    xmlns:stDim="http://ns.adobe.com/xap/1.0/sType/Dimensions#"
+    xmlns:stArea="http://ns.adobe.com/xmp/sType/Area#"
+    {}>
+   <mwg-rs:Regions rdf:parseType="Resource">
+    <mwg-rs:AppliedToDimensions stDim:w="{}" stDim:h="{}" stDim:unit="pixel"/>
+    <mwg-rs:RegionList>
+     <rdf:Bag>
+{}     </rdf:Bag>
+    </mwg-rs:RegionList>
+   </mwg-rs:Regions>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        '\u{feff}', CREATOR_TOOL, width, height, list
+    )
+}
+
+/// Whether an XMP packet was written by this exporter
+pub fn is_ours(packet: &str) -> bool {
+    packet.contains(CREATOR_TOOL)
+}
+
+/// `photo.jpg.xmp` for `photo.jpg`, the name digiKam and darktable use, which keeps a RAW and JPEG
+/// of the same shot apart
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".xmp");
+    PathBuf::from(name)
+}
+
+/// The segments before the image data as (marker, start, end), or None when `data` isn't a JPEG
+fn jpeg_segments(data: &[u8]) -> Option<Vec<(u8, usize, usize)>> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut segments = Vec::new();
+    let mut i = 2;
+    while i + 4 <= data.len() {
+        if data[i] != 0xFF {
+            return None;
+        }
+        let marker = data[i + 1];
+        if marker == 0xFF {
+            i += 1;
+            continue;
+        }
+        if marker == 0xDA || marker == 0xD9 {
+            return Some(segments);
+        }
+        let len = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+        if len < 2 || i + 2 + len > data.len() {
+            return None;
+        }
+        segments.push((marker, i, i + 2 + len));
+        i += 2 + len;
+    }
+    None
+}
+
+fn is_xmp_segment(data: &[u8], &(marker, start, end): &(u8, usize, usize)) -> bool {
+    marker == 0xE1 && data[start + 4..end].starts_with(JPEG_XMP_HEADER)
+}
+
+/// The XMP packet embedded in a JPEG
+pub fn jpeg_xmp(data: &[u8]) -> Option<&str> {
+    let segments = jpeg_segments(data)?;
+    let &(_, start, end) = segments.iter().find(|s| is_xmp_segment(data, s))?;
+    std::str::from_utf8(&data[start + 4 + JPEG_XMP_HEADER.len()..end]).ok()
+}
+
+/// `data` with `packet` as its XMP, replacing any there was. A new segment goes after the JFIF
+/// and EXIF headers, where readers look for it.
+pub fn embed_in_jpeg(data: &[u8], packet: &str) -> Result<Vec<u8>> {
+    let segments = jpeg_segments(data).context("Not a JPEG")?;
+    let len = 2 + JPEG_XMP_HEADER.len() + packet.len();
+    if len > u16::MAX as usize {
+        bail!("XMP packet of {} bytes doesn't fit in a JPEG segment", packet.len());
+    }
+    let mut segment = Vec::with_capacity(len + 2);
+    segment.extend_from_slice(&[0xFF, 0xE1]);
+    segment.extend_from_slice(&(len as u16).to_be_bytes());
+    segment.extend_from_slice(JPEG_XMP_HEADER);
+    segment.extend_from_slice(packet.as_bytes());
+
+    let (start, end) = match segments.iter().find(|s| is_xmp_segment(data, s)) {
+        Some(&(_, start, end)) => (start, end),
+        None => {
+            let at = segments.iter().take_while(|s| s.0 == 0xE0 || s.0 == 0xE1).last().map_or(2, |s| s.2);
+            (at, at)
+        }
+    };
+    let mut out = Vec::with_capacity(data.len() + segment.len());
+    out.extend_from_slice(&data[..start]);
+    out.extend_from_slice(&segment);
+    out.extend_from_slice(&data[end..]);
+    Ok(out)
+}
+
+/// Write `packet` for the photo at `path`. XMP another tool wrote is kept unless `overwrite`.
+pub fn write_regions(path: &Path, packet: &str, target: Target, overwrite: bool) -> Result<Outcome> {
+    let is_jpeg = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg"));
+    if target == Target::Embed && is_jpeg {
+        let data = std::fs::read(path)?;
+        if jpeg_xmp(&data).is_some_and(|existing| !overwrite && !is_ours(existing)) {
+            return Ok(Outcome::Skipped("existing XMP"));
+        }
+        let updated = embed_in_jpeg(&data, packet)?;
+        // Replaced in one step, so a crash can't leave a half-written photo
+        let tmp = path.with_file_name(format!(".{}.seen-xmp", path.file_name().and_then(|n| n.to_str()).unwrap_or("photo")));
+        std::fs::write(&tmp, updated)?;
+        if let Err(e) = std::fs::rename(&tmp, path) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        return Ok(Outcome::Embedded);
+    }
+
+    let sidecar = sidecar_path(path);
+    if !overwrite {
+        if let Ok(existing) = std::fs::read_to_string(&sidecar) {
+            if !is_ours(&existing) {
+                return Ok(Outcome::Skipped("existing XMP"));
+            }
+        }
+    }
+    std::fs::write(&sidecar, packet)?;
+    Ok(Outcome::Sidecar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(name: &str) -> FaceRegion {
+        FaceRegion { name: name.to_string(), kind: "Face", x1: 100.0, y1: 50.0, x2: 300.0, y2: 250.0 }
+    }
+
+    #[test]
+    fn test_regions_packet() {
+        let packet = regions_packet(400, 500, &[region("Ana & \"Bo\""), FaceRegion { kind: "Pet", ..region("Rex") }]);
+        assert!(packet.contains(r#"stDim:w="400" stDim:h="500""#));
+        assert!(packet.contains(r#"mwg-rs:Name="Ana &amp; &quot;Bo&quot;" mwg-rs:Type="Face""#));
+        assert!(packet.contains(r#"mwg-rs:Name="Rex" mwg-rs:Type="Pet""#));
+        assert!(packet.contains(r#"stArea:x="0.500000" stArea:y="0.300000" stArea:w="0.500000" stArea:h="0.400000""#));
+        assert!(is_ours(&packet));
+    }
+
+    #[test]
+    fn test_embed_in_jpeg() {
+        // SOI, JFIF APP0, EXIF APP1, SOS and a byte of image data
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x4A, 0x46, 0xFF, 0xE1, 0x00, 0x08];
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0x11]);
+        assert!(jpeg_xmp(&jpeg).is_none());
+
+        let first = embed_in_jpeg(&jpeg, "<first/>").unwrap();
+        assert_eq!(jpeg_xmp(&first), Some("<first/>"));
+        let segments = jpeg_segments(&first).unwrap();
+        assert_eq!(segments.iter().map(|s| s.0).collect::<Vec<_>>(), vec![0xE0, 0xE1, 0xE1]);
+        assert!(first.ends_with(&[0xFF, 0xDA, 0x00, 0x02, 0x11]));
+
+        // Embedding again replaces the packet instead of adding a second one
+        let second = embed_in_jpeg(&first, "<second/>").unwrap();
+        assert_eq!(jpeg_xmp(&second), Some("<second/>"));
+        assert_eq!(second.len(), first.len() + 1);
+        assert!(embed_in_jpeg(b"not a jpeg", "<x/>").is_err());
+    }
+
+    #[test]
+    fn test_write_regions_keeps_foreign_xmp() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("a.png");
+        std::fs::write(&photo, b"png").unwrap();
+        let packet = regions_packet(400, 500, &[region("Ana")]);
+
+        assert_eq!(write_regions(&photo, &packet, Target::Embed, false).unwrap(), Outcome::Sidecar);
+        assert_eq!(std::fs::read_to_string(dir.path().join("a.png.xmp")).unwrap(), packet);
+        // Rewriting our own sidecar is fine; someone else's needs overwrite
+        assert_eq!(write_regions(&photo, &packet, Target::Sidecar, false).unwrap(), Outcome::Sidecar);
+        std::fs::write(sidecar_path(&photo), "<x:xmpmeta/>").unwrap();
+        assert_eq!(write_regions(&photo, &packet, Target::Sidecar, false).unwrap(), Outcome::Skipped("existing XMP"));
+        assert_eq!(write_regions(&photo, &packet, Target::Sidecar, true).unwrap(), Outcome::Sidecar);
+    }
+}
@@ -1,17 +1,43 @@
-use std::path::PathBuf;
+//! Shared helpers for integration tests.
+//!
+//! `TestServer::start` boots the full router on an ephemeral port over a temporary library, with
+//! the same discover → hash → metadata → writer → thumbnail workers `main` wires up, so a test can
+//! drop files into `root`, scan them through the API and fetch what the pipeline produced:
+//!
+//! ```ignore
+//! let server = TestServer::start().await;
+//! fixtures::create_jpeg(&server.root.join("a.jpg"), 64, 48).unwrap();
+//! server.scan().await;
+//! assert!(server.wait_for_assets(1).await);
+//! ```
+//!
+//! Face, OCR, semantic search and the background schedulers are not started. The server runs on
+//! its own runtime, which is shut down without waiting when the `TestServer` is dropped; the path
+//! watchers park blocking threads that would otherwise keep a runtime from ever stopping.
+
+#![allow(dead_code)]
+
+pub mod fixtures;
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
+use seen_backend::db;
+use seen_backend::pipeline;
+use seen_backend::{AppPaths, AppState};
 use tempfile::TempDir;
-use rusqlite::Connection;
-use nazr_backend_sqlite::db;
-use nazr_backend_sqlite::{AppPaths, AppState, pipeline};
 use tokio::sync::mpsc;
 
+/// How long `wait_for_*` poll before giving up
+pub const WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Create a temporary SQLite database for testing
-pub fn setup_test_db() -> (TempDir, PathBuf, Connection) {
+pub fn setup_test_db() -> (TempDir, PathBuf, rusqlite::Connection) {
     let tmp = TempDir::new().unwrap();
     let db_dir = tmp.path().join("db");
     std::fs::create_dir_all(&db_dir).unwrap();
-    let db_path = db_dir.join("nazr.db");
+    let db_path = db_dir.join("seen.db");
     let conn = db::open_or_create(&db_path).unwrap();
     (tmp, db_path, conn)
 }
@@ -24,106 +50,220 @@ pub fn setup_test_fs() -> (TempDir, PathBuf) {
     (tmp, root)
 }
 
-/// Create a minimal JPEG image (1x1 pixel)
-pub fn create_test_image(path: &PathBuf) -> std::io::Result<()> {
-    // Minimal valid JPEG (1x1 pixel)
-    use base64::{Engine as _, engine::general_purpose};
-    let img_bytes = general_purpose::STANDARD.decode("/9j/4AAQSkZJRgABAQAAAQABAAD/2wBDAP//////////////////////////////////////////////////////////////////////////////////////2wBDAf//////////////////////////////////////////////////////////////////////////////////////wAARCABkAGQDAREAAhEBAxEB/8QAFQABAQAAAAAAAAAAAAAAAAAAAAb/xAAUEAEAAAAAAAAAAAAAAAAAAAAA/8QAFQEBAQAAAAAAAAAAAAAAAAAAAgP/xAAUEQEAAAAAAAAAAAAAAAAAAAAA/9oADAMBAAIRAxEAPwB3AAAAAP/Z").unwrap();
-    std::fs::write(path, &img_bytes)
+/// libvips can only be started once per process, and shutting it down is final
+fn init_vips() {
+    #[cfg(not(target_env = "msvc"))]
+    {
+        static VIPS: std::sync::Once = std::sync::Once::new();
+        VIPS.call_once(|| std::mem::forget(libvips::VipsApp::new("seen-test", false).expect("libvips")));
+    }
 }
 
-/// Create a test PNG image
-pub fn create_test_png(path: &PathBuf) -> std::io::Result<()> {
-    // Minimal valid PNG (1x1 pixel, red)
-    let png_bytes = vec![
-        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
-        0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
-        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 dimensions
-        0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, 0xDE, // bit depth, color type, etc.
-        0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, 0x54, // IDAT chunk
-        0x08, 0x99, 0x01, 0x01, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x02, // pixel data
-        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82, // IEND
-    ];
-    std::fs::write(path, &png_bytes)
+/// The server with its pipeline, over a library and data directory that are removed on drop
+pub struct TestServer {
+    runtime: Option<tokio::runtime::Runtime>,
+    pub state: Arc<AppState>,
+    pub client: TestClient,
+    pub addr: SocketAddr,
+    /// Library root; the default scan path
+    pub root: PathBuf,
+    pub data: PathBuf,
+    _dirs: TempDir,
+    #[cfg(feature = "facial-recognition")]
+    _face_rx: mpsc::Receiver<pipeline::face::FaceJob>,
 }
 
-/// Create a test text file (for testing non-image files)
-pub fn create_test_text_file(path: &PathBuf, content: &str) -> std::io::Result<()> {
-    std::fs::write(path, content)
+impl TestServer {
+    pub async fn start() -> Self {
+        init_vips();
+        let dirs = TempDir::new().unwrap();
+        let root = dirs.path().join("photos");
+        let data = dirs.path().join("data");
+        let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(2).enable_all().build().unwrap();
+        #[cfg(feature = "facial-recognition")]
+        let (face_tx, face_rx) = mpsc::channel::<pipeline::face::FaceJob>(256);
+        #[cfg(feature = "facial-recognition")]
+        let server = serve(root.clone(), data.clone(), face_tx);
+        #[cfg(not(feature = "facial-recognition"))]
+        let server = serve(root.clone(), data.clone());
+        let (state, addr) = runtime.spawn(server).await.unwrap();
+        Self {
+            runtime: Some(runtime),
+            state,
+            client: TestClient::new(addr.port()),
+            addr,
+            root,
+            data,
+            _dirs: dirs,
+            #[cfg(feature = "facial-recognition")]
+            _face_rx: face_rx,
+        }
+    }
+
+    pub fn db(&self) -> r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager> {
+        self.state.pool.get().unwrap()
+    }
+
+    /// Scan the library root through `POST /paths/scan`; returns the response status
+    pub async fn scan(&self) -> reqwest::StatusCode {
+        let body = serde_json::json!({ "path": self.root.to_string_lossy() });
+        self.client.post("/paths/scan", &body).await.unwrap().status()
+    }
+
+    /// Until `GET /paths/status` no longer reports the library root as scanning; false on timeout
+    pub async fn wait_for_scan_idle(&self) -> bool {
+        let path = format!("/paths/status?path={}", self.root.to_string_lossy());
+        let deadline = tokio::time::Instant::now() + WAIT_TIMEOUT;
+        while tokio::time::Instant::now() < deadline {
+            if self.client.get_json(&path).await["scanning"] == false {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
+    /// Until `n` assets are committed; false on timeout
+    pub async fn wait_for_assets(&self, n: i64) -> bool {
+        wait_for_condition(|| db::query::count_assets(&self.db()).unwrap_or(0) >= n, WAIT_TIMEOUT).await
+    }
+
+    /// Until `GET path` answers 200, returning the body
+    pub async fn wait_for_ok(&self, path: &str) -> Option<Vec<u8>> {
+        let deadline = tokio::time::Instant::now() + WAIT_TIMEOUT;
+        while tokio::time::Instant::now() < deadline {
+            if let Ok(res) = self.client.get(path).await {
+                if res.status().is_success() {
+                    return res.bytes().await.ok().map(|b| b.to_vec());
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        None
+    }
+
+    /// Absolute path of a file in the library, creating its directory
+    pub fn library_path(&self, relative: impl AsRef<Path>) -> PathBuf {
+        let path = self.root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        path
+    }
 }
 
-/// Create AppState for testing
-pub fn create_test_app_state(
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// Wires up the pipeline and router as `main` does and starts listening on an ephemeral port
+async fn serve(
     root: PathBuf,
     data: PathBuf,
-    db_path: PathBuf,
-    conn: Connection,
-) -> Arc<AppState> {
-    let db_dir = data.join("db");
-    let derived_dir = data.join("derived");
-    std::fs::create_dir_all(&db_dir).unwrap();
-    std::fs::create_dir_all(&derived_dir).unwrap();
+    #[cfg(feature = "facial-recognition")] face_tx: mpsc::Sender<pipeline::face::FaceJob>,
+) -> (Arc<AppState>, SocketAddr) {
+    let derived = data.join("derived");
+    let db_path = data.join("db").join("seen.db");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+    std::fs::create_dir_all(&derived).unwrap();
+    drop(db::open_or_create(&db_path).unwrap());
+    let pool = db::create_pool(&db_path, 4, db::tuning::SqliteTuning::default()).unwrap();
 
-    let (discover_tx, _discover_rx) = mpsc::channel::<pipeline::discover::DiscoverItem>(100);
-    let (hash_tx, _hash_rx) = mpsc::channel::<pipeline::hash::HashJob>(100);
-    let (meta_tx, _meta_rx) = mpsc::channel::<pipeline::metadata::MetaJob>(100);
-    let (db_tx, _db_rx) = mpsc::channel::<db::writer::DbWriteItem>(100);
-    let (thumb_tx, _thumb_rx) = mpsc::channel::<pipeline::thumb::ThumbJob>(100);
-    #[cfg(feature = "facial-recognition")]
-    let (face_tx, _face_rx) = mpsc::channel::<pipeline::face::FaceJob>(100);
+    let (discover_tx, discover_rx) = mpsc::channel::<pipeline::discover::DiscoverItem>(1_024);
+    let (hash_tx, hash_rx) = mpsc::channel::<pipeline::hash::HashJob>(256);
+    let (meta_tx, meta_rx) = mpsc::channel::<pipeline::metadata::MetaJob>(256);
+    let (db_tx, db_rx) = mpsc::channel::<db::writer::DbWriteItem>(1_024);
+    let (thumb_tx, thumb_rx) = mpsc::channel::<pipeline::thumb::ThumbJob>(256);
+    let (transcode_tx, transcode_rx) = mpsc::channel::<pipeline::transcode::TranscodeJob>(64);
 
     let gauges = Arc::new(pipeline::QueueGauges::default());
-    let paths = AppPaths {
-        root,
-        root_host: None,
-        data,
-        db_path: db_path.clone(),
-        derived: derived_dir,
-    };
+    let stats = Arc::new(seen_backend::stats::Stats::new());
+    let paths = AppPaths { root: root.clone(), root_host: None, data: data.clone(), db_path: db_path.clone(), derived: derived.clone() };
     #[cfg(feature = "facial-recognition")]
-    let queues = pipeline::Queues {
-        discover_tx,
-        hash_tx,
-        meta_tx,
-        db_tx,
-        thumb_tx,
-        face_tx,
-    };
+    let queues = pipeline::Queues { discover_tx, hash_tx: hash_tx.clone(), meta_tx: meta_tx.clone(), db_tx: db_tx.clone(), thumb_tx: thumb_tx.clone(), transcode_tx, face_tx };
     #[cfg(not(feature = "facial-recognition"))]
-    let queues = pipeline::Queues {
-        discover_tx,
-        hash_tx,
-        meta_tx,
-        db_tx,
-        thumb_tx,
-    };
-
-    let stats = Arc::new(nazr_backend_sqlite::stats::Stats::new());
+    let queues = pipeline::Queues { discover_tx, hash_tx: hash_tx.clone(), meta_tx: meta_tx.clone(), db_tx: db_tx.clone(), thumb_tx: thumb_tx.clone(), transcode_tx };
     #[cfg(feature = "facial-recognition")]
-    {
-        let models_dir = paths.data.join("models");
-        let face_processor = Arc::new(parking_lot::Mutex::new(pipeline::face::FaceProcessor::new(models_dir)));
+    let state = {
+        let face_processor = Arc::new(parking_lot::Mutex::new(pipeline::face::FaceProcessor::new(data.join("models"))));
         let face_index = Arc::new(parking_lot::Mutex::new(pipeline::face::FaceIndex::new()));
-        Arc::new(AppState::new(paths, conn, queues, gauges, stats, face_processor, face_index))
-    }
+        AppState::new(paths, pool, queues, gauges.clone(), stats.clone(), face_processor, face_index)
+    };
     #[cfg(not(feature = "facial-recognition"))]
-    {
-        Arc::new(AppState::new(paths, conn, queues, gauges, stats))
-    }
+    let state = AppState::new(paths, pool, queues, gauges.clone(), stats.clone());
+    let state = Arc::new(state);
+
+    let meta_settings = pipeline::metadata::MetaSettings {
+        ingest: state.ingest_filter.clone(),
+        date_priorities: state.date_priorities.clone(),
+        extractors: state.filename_extractors.clone(),
+    };
+    pipeline::discover::start_forwarder(discover_rx, hash_tx, Some(meta_tx.clone()), Some(db_path.clone()), gauges.clone(), Some(stats.clone()), meta_settings.ingest.clone());
+    pipeline::hash::start_workers(1, hash_rx, meta_tx, gauges.clone(), stats.clone(), 0);
+    pipeline::metadata::start_workers(1, meta_rx, db_tx, gauges.clone(), meta_settings, stats.clone());
+    start_writer(&db_path, db_rx, thumb_tx, gauges.clone(), stats.clone());
+    let thumb_options = pipeline::thumb::ThumbOptions { thumb_size: 256, preview_size: 1600, video_anim_preview: false };
+    pipeline::thumb::start_workers(1, thumb_rx, derived, thumb_options, gauges.clone(), stats.clone());
+    pipeline::transcode::start_worker(transcode_rx, state.transcodes.clone(), gauges, stats);
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = seen_backend::api::routes::router(state.clone());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (state, addr)
 }
 
-/// Wait for a condition to become true
-pub async fn wait_for_condition<F>(mut condition: F, max_attempts: usize, delay_ms: u64)
+/// The DB writer runs on its own thread, as in `main`. A plain thread rather than spawn_blocking,
+/// which would keep the test's runtime from shutting down while the writer waits for work.
+fn start_writer(
+    db_path: &Path,
+    rx: mpsc::Receiver<db::writer::DbWriteItem>,
+    thumb_tx: mpsc::Sender<pipeline::thumb::ThumbJob>,
+    gauges: Arc<pipeline::QueueGauges>,
+    stats: Arc<seen_backend::stats::Stats>,
+) {
+    let conn = rusqlite::Connection::open(db_path).unwrap();
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let config = db::writer::WriterConfig {
+            handle,
+            rx,
+            conn,
+            fts_batch_size: 64,
+            thumb_tx,
+            gauges,
+            stats: Some(stats),
+            #[cfg(feature = "facial-recognition")]
+            face_tx: None,
+            #[cfg(feature = "facial-recognition")]
+            face_processor: None,
+            #[cfg(feature = "facial-recognition")]
+            db_path: None,
+        };
+        if let Err(e) = db::writer::run_writer(config) {
+            eprintln!("DB writer exited: {:?}", e);
+        }
+    });
+}
+
+/// Poll `condition` every 100 ms until it holds; false on timeout
+pub async fn wait_for_condition<F>(mut condition: F, timeout: Duration) -> bool
 where
     F: FnMut() -> bool,
 {
-    use tokio::time::{sleep, Duration};
-    for _ in 0..max_attempts {
+    let deadline = tokio::time::Instant::now() + timeout;
+    while tokio::time::Instant::now() < deadline {
         if condition() {
-            return;
+            return true;
         }
-        sleep(Duration::from_millis(delay_ms)).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
     }
+    condition()
 }
 
 /// Helper to make HTTP requests to test server
@@ -133,35 +273,39 @@ pub struct TestClient {
 }
 
 impl TestClient {
+    /// Paths passed to the methods are relative to `/api`
     pub fn new(port: u16) -> Self {
         Self {
-            base_url: format!("http://127.0.0.1:{}", port),
+            base_url: format!("http://127.0.0.1:{}/api", port),
             client: reqwest::Client::new(),
         }
     }
 
     pub async fn get(&self, path: &str) -> reqwest::Result<reqwest::Response> {
-        self.client.get(&format!("{}{}", self.base_url, path)).send().await
+        self.client.get(format!("{}{}", self.base_url, path)).send().await
+    }
+
+    pub async fn get_json(&self, path: &str) -> serde_json::Value {
+        self.get(path).await.unwrap().json().await.unwrap()
     }
 
     pub async fn post(&self, path: &str, json: &serde_json::Value) -> reqwest::Result<reqwest::Response> {
         self.client
-            .post(&format!("{}{}", self.base_url, path))
+            .post(format!("{}{}", self.base_url, path))
             .json(json)
             .send()
             .await
     }
 
     pub async fn delete(&self, path: &str) -> reqwest::Result<reqwest::Response> {
-        self.client.delete(&format!("{}{}", self.base_url, path)).send().await
+        self.client.delete(format!("{}{}", self.base_url, path)).send().await
     }
 
     pub async fn put(&self, path: &str, json: &serde_json::Value) -> reqwest::Result<reqwest::Response> {
         self.client
-            .put(&format!("{}{}", self.base_url, path))
+            .put(format!("{}{}", self.base_url, path))
             .json(json)
             .send()
             .await
     }
 }
-
@@ -33,7 +33,7 @@ fn test_list_assets_integration() {
     }
     
     // Test listing with different sorts
-    let result = query::list_assets(&conn, 0, 5, "mtime_ns", "desc").unwrap();
+    let result = query::list_assets(&conn, 0, 5, "mtime_ns", "desc", query::ListFilters::default()).unwrap();
     assert_eq!(result.total, 10);
     assert_eq!(result.items.len(), 5);
     
@@ -136,14 +136,14 @@ fn test_album_queries() {
     // Test get_album
     let album = query::get_album(&conn, album_id).unwrap();
     assert!(album.is_some());
-    let (id, name, description, _, _, asset_ids) = album.unwrap();
+    let (id, name, description, _, _, asset_ids, _) = album.unwrap();
     assert_eq!(id, album_id);
     assert_eq!(name, "Test Album");
     assert_eq!(asset_ids.len(), 1);
     assert_eq!(asset_ids[0], asset_id);
     
     // Test get_albums_for_asset
-    let albums = query::get_albums_for_asset(&conn, asset_id).unwrap();
+    let albums = query::get_albums_for_asset(&conn, asset_id, query::AccessScope::All).unwrap();
     assert_eq!(albums.len(), 1);
     assert_eq!(albums[0], album_id);
 }
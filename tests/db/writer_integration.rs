@@ -46,7 +46,7 @@ fn test_remove_scan_path() {
 fn test_create_album() {
     let (_tmp, conn) = setup_test_db();
     
-    let id = writer::create_album(&conn, "Test Album", Some("Test Description")).unwrap();
+    let id = writer::create_album(&conn, "Test Album", Some("Test Description"), None).unwrap();
     assert!(id > 0);
     
     // Verify album was created
@@ -63,7 +63,7 @@ fn test_create_album() {
 fn test_update_album() {
     let (_tmp, conn) = setup_test_db();
     
-    let id = writer::create_album(&conn, "Test Album", None).unwrap();
+    let id = writer::create_album(&conn, "Test Album", None, None).unwrap();
     let updated = writer::update_album(&conn, id, Some("Updated Album"), Some("Updated Description")).unwrap();
     assert!(updated);
     
@@ -81,7 +81,7 @@ fn test_update_album() {
 fn test_delete_album() {
     let (_tmp, conn) = setup_test_db();
     
-    let id = writer::create_album(&conn, "Test Album", None).unwrap();
+    let id = writer::create_album(&conn, "Test Album", None, None).unwrap();
     let deleted = writer::delete_album(&conn, id).unwrap();
     assert!(deleted);
     
@@ -99,7 +99,7 @@ fn test_add_assets_to_album() {
     let (_tmp, conn) = setup_test_db();
     
     // Create album
-    let album_id = writer::create_album(&conn, "Test Album", None).unwrap();
+    let album_id = writer::create_album(&conn, "Test Album", None, None).unwrap();
     
     // Insert assets
     conn.execute(
@@ -134,7 +134,7 @@ fn test_remove_assets_from_album() {
     let (_tmp, conn) = setup_test_db();
     
     // Create album and add assets
-    let album_id = writer::create_album(&conn, "Test Album", None).unwrap();
+    let album_id = writer::create_album(&conn, "Test Album", None, None).unwrap();
     conn.execute(
         "INSERT INTO assets (path, dirname, filename, ext, size_bytes, mtime_ns, ctime_ns, mime, flags) VALUES 
          (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
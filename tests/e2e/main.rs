@@ -0,0 +1,7 @@
+//! End-to-end tests against a running server; see `common::TestServer`
+
+#[path = "../common/mod.rs"]
+mod common;
+
+mod full_scan;
+mod quota;
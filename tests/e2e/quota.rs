@@ -0,0 +1,36 @@
+use crate::common::{fixtures, TestServer};
+
+#[tokio::test]
+async fn test_scan_stops_at_path_quota() {
+    let server = TestServer::start().await;
+    for i in 0..4 {
+        fixtures::create_jpeg(&server.library_path(format!("mount/{}.jpg", i)), 64, 48).unwrap();
+    }
+    let mount = server.library_path("mount");
+
+    let body = serde_json::json!({ "path": mount.to_string_lossy(), "max_assets": 2 });
+    assert!(server.client.post("/paths", &body).await.unwrap().status().is_success());
+    assert!(server.wait_for_assets(2).await);
+
+    // The report is saved once the scan has stopped
+    let mut report = serde_json::Value::Null;
+    for _ in 0..100 {
+        let paths = server.client.get_json("/paths").await;
+        report = paths.as_array().unwrap().iter().find(|p| p["path"] == mount.to_string_lossy().as_ref()).unwrap()["quota_hit"].clone();
+        if !report.is_null() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(report["limit"], "assets");
+    assert_eq!(report["indexed_assets"], 2);
+
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    assert_eq!(seen_backend::db::query::count_assets(&server.db()).unwrap(), 2);
+    let history = server.client.get_json(&format!("/paths/history?path={}", urlencoding::encode(&mount.to_string_lossy()))).await;
+    assert_eq!(history["runs"][0]["status"], "quota");
+    assert_eq!(history["runs"][0]["files"], 2);
+
+    let zero = serde_json::json!({ "path": mount.to_string_lossy(), "max_bytes": 0 });
+    assert_eq!(server.client.put("/paths/quota", &zero).await.unwrap().status(), reqwest::StatusCode::BAD_REQUEST);
+}
@@ -48,7 +48,7 @@ fn test_list_assets_invalid_sort() {
     let (_tmp, conn) = setup_test_db();
     
     // Invalid sort should default to mtime_ns
-    let result = query::list_assets(&conn, 0, 10, "invalid_sort", "desc").unwrap();
+    let result = query::list_assets(&conn, 0, 10, "invalid_sort", "desc", query::ListFilters::default()).unwrap();
     assert_eq!(result.total, 0);
     assert_eq!(result.items.len(), 0);
 }
@@ -58,7 +58,7 @@ fn test_list_assets_negative_offset() {
     let (_tmp, conn) = setup_test_db();
     
     // Negative offset should be handled gracefully (treated as 0)
-    let result = query::list_assets(&conn, -10, 10, "none", "desc").unwrap();
+    let result = query::list_assets(&conn, -10, 10, "none", "desc", query::ListFilters::default()).unwrap();
     assert_eq!(result.total, 0);
     assert_eq!(result.items.len(), 0);
 }